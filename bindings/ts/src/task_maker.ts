@@ -68,6 +68,19 @@ export type UIMessage =
         message: string;
       };
     }
+  | {
+      IOISeedRun: {
+        subtask: SubtaskId;
+        testcase: TestcaseId;
+        solution: string;
+        run: number;
+        num_runs: number;
+        seed: number;
+        score: number;
+        message: string;
+        selected: boolean;
+      };
+    }
   | {
       IOISubtaskScore: {
         subtask: SubtaskId;
@@ -130,6 +143,7 @@ export type WorkerCurrentJobStatus<T> = {
   job: string;
   client: ClientInfo;
   duration: T;
+  stderr: string | null;
 };
 // Information about a client of the scheduler.
 export type ClientInfo = { uuid: ClientUuid; name: string };
@@ -156,6 +170,8 @@ export type TerryTask = {
   name: string;
   description: string;
   max_score: number;
+  public_files: string[];
+  copy_public_files: boolean;
 };
 // The output of the checker for a solution.
 export type SolutionOutcome = {
@@ -175,7 +191,8 @@ export type ExecutionResult = {
 // The type of the task. This changes the behavior of the solutions.
 export type TaskType =
   | { Batch: BatchTypeData }
-  | { Communication: CommunicationTypeData };
+  | { Communication: CommunicationTypeData }
+  | { TwoSteps: TwoStepsTypeData };
 // A subtask of a IOI task.
 export type SubtaskInfo = {
   id: SubtaskId;
@@ -217,6 +234,7 @@ export type Booklet = {
   config: BookletConfig;
   statements: Statement[];
   dest: string;
+  latex_cache_dir: string | null;
 };
 // Configuration of a `Booklet`, including the setting from the contest configuration.
 export type BookletConfig = {
@@ -229,6 +247,7 @@ export type BookletConfig = {
   location: string | null;
   date: string | null;
   logo: string | null;
+  build_stamp: string | null;
 };
 // A statement is a `.tex` file with all the other assets included in its directory.
 export type Statement = {
@@ -297,6 +316,8 @@ export type CommunicationTypeData = {
   manager: SourceFile;
   num_processes: number;
 };
+// The internal data of a task of type `TwoSteps`.
+export type TwoStepsTypeData = { checker: Checker };
 // Which tool to use to compute the score on a testcase given the input file, the _correct_ output
 // file and the output file to evaluate.
 export type Checker = "WhiteDiff" | { Custom: SourceFile };
@@ -319,13 +340,17 @@ export type InputGenerator =
   | { Custom: [SourceFile, string[]] };
 // An input file validator is responsible for checking that the input file follows the format and
 // constraints defined by the task.
-export type InputValidator = "AssumeValid" | { Custom: [SourceFile, string[]] };
+export type InputValidator =
+  | "AssumeValid"
+  | { Custom: [SourceFile, string[]] }
+  | { CustomWithVariables: [SourceFile, string[]] };
 // The source of the output files. It can either be a statically provided output file or a custom
 // command that will generate an output file.
 export type OutputGenerator =
   | "NotAvailable"
   | { StaticFile: string }
-  | { Custom: [SourceFile, string[]] };
+  | { Custom: [SourceFile, string[]] }
+  | { TwoSteps: SourceFile };
 // Information about a parsed task, returned with the `--task-info` option.
 export type TaskInfo = { IOI: IOITaskInfo } | { Terry: TerryTaskInfo };
 // Task information structure.
@@ -365,4 +390,12 @@ export type TerryTaskInfo = {
   name: string;
   description: string;
   max_score: number;
+  public_files: TaskInfoPublicFile[];
+};
+// A file published together with the statement of the task.
+export type TaskInfoPublicFile = {
+  name: string;
+  content_type: string;
+  path: string;
+  hash: string;
 };
@@ -0,0 +1,179 @@
+//! Batch mode: evaluate more tasks one after the other, sharing the same executor.
+//!
+//! The local executor (or the connection to the server) and the file store are set up only once,
+//! and at the end a summary of the scores of all the tasks is printed, or emitted as a JSON
+//! message with `--ui json`. A task that cannot be
+//! evaluated doesn't stop the others, unless the connection to the executor is lost.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Error};
+
+use task_maker_format::find_task;
+use task_maker_format::ui::{BatchTaskOutcome, JsonUIBatchSummary, UIType};
+
+use crate::context::{evaluation_stopped, ExecutorConnection};
+use crate::error::print_error;
+use crate::history::record_history;
+use crate::local::{check_denied_warnings, check_exit_policy, evaluation_builder};
+use crate::opt::Opt;
+
+/// The outcome of the evaluation of a task of the batch: the scores of its solutions (by path
+/// relative to the task directory), or the error that prevented its evaluation.
+type TaskOutcome = Result<BTreeMap<PathBuf, Option<f64>>, String>;
+
+/// Evaluate all the tasks asked with --task-dir and --task-list, then print the summary of their
+/// scores.
+pub fn evaluate_batch(opt: Opt) -> Result<(), Error> {
+    if opt.watch
+        || opt.clean
+        || opt.check_dag
+        || opt.estimate
+        || opt.print_repro.is_some()
+        || opt.time_limit.suggest_time_limit
+    {
+        bail!(
+            "More tasks cannot be evaluated with --watch, --clean, --check-dag, --estimate, \
+             --print-repro or --suggest-time-limit"
+        );
+    }
+    if opt.exclusive {
+        bail!("This option is not implemented yet");
+    }
+    opt.ui.check_json_schema_version()?;
+    let task_dirs = opt.find_task.task_dirs()?;
+    if task_dirs.is_empty() {
+        bail!("No task to evaluate");
+    }
+
+    let sandbox_runner = opt
+        .execution
+        .sandbox
+        .sandbox_runner(opt.execution.run_in_process())?;
+    let mut connection = Some(ExecutorConnection::connect(
+        &opt.execution,
+        &opt.storage,
+        sandbox_runner,
+    )?);
+    let mut outcomes: Vec<(PathBuf, TaskOutcome)> = vec![];
+    for task_dir in task_dirs {
+        let outcome = match evaluate_task(&opt, &task_dir, &mut connection) {
+            Ok(scores) => Ok(scores),
+            Err(e) => {
+                let message = format!("{:#}", e);
+                print_error(e.context(format!("Evaluation of {} failed", task_dir.display())));
+                Err(message)
+            }
+        };
+        outcomes.push((task_dir, outcome));
+        if evaluation_stopped() {
+            warn!("The evaluation has been stopped, skipping the remaining tasks");
+            break;
+        }
+        if connection.is_none() {
+            warn!("The connection to the executor has been lost, skipping the remaining tasks");
+            break;
+        }
+    }
+    if let Some(connection) = connection {
+        connection.close()?;
+    }
+
+    match opt.ui.ui {
+        UIType::Json => print_json_summary(outcomes)?,
+        _ => print_summary(&outcomes),
+    }
+    Ok(())
+}
+
+/// Evaluate a single task of the batch using the shared connection, returning the scores of its
+/// solutions.
+fn evaluate_task(
+    opt: &Opt,
+    task_dir: &Path,
+    connection: &mut Option<ExecutorConnection>,
+) -> Result<BTreeMap<PathBuf, Option<f64>>, Error> {
+    let eval_config = opt.to_config();
+    let task = find_task(Some(task_dir.into()), opt.find_task.max_depth, &eval_config)
+        .context("Invalid task directory")?;
+    let task_path = task.path().to_owned();
+    let state = evaluation_builder(opt, task, eval_config)?
+        .run_on(connection, |ui, mex| ui.on_message(mex))?;
+    if opt.history {
+        record_history(&opt.storage, &state)?;
+    }
+    check_denied_warnings(opt, &state)?;
+    check_exit_policy(opt, &state)?;
+    let scores = state
+        .solution_scores()
+        .into_iter()
+        .map(|(path, score)| match path.strip_prefix(&task_path) {
+            Ok(name) => (name.to_owned(), score),
+            Err(_) => (path, score),
+        })
+        .collect();
+    Ok(scores)
+}
+
+/// Print the scores of the solutions of all the tasks of the batch as a JSON message, after the
+/// messages of the `JsonUI` of each task.
+fn print_json_summary(outcomes: Vec<(PathBuf, TaskOutcome)>) -> Result<(), Error> {
+    let tasks = outcomes
+        .into_iter()
+        .map(|(task, outcome)| match outcome {
+            Ok(scores) => BatchTaskOutcome {
+                task,
+                scores,
+                error: None,
+            },
+            Err(error) => BatchTaskOutcome {
+                task,
+                scores: Default::default(),
+                error: Some(error),
+            },
+        })
+        .collect();
+    let summary = serde_json::to_string(&JsonUIBatchSummary::BatchSummary { tasks })
+        .context("Failed to serialize the summary")?;
+    println!("{}", summary);
+    Ok(())
+}
+
+/// Print the scores of the solutions of all the tasks of the batch.
+fn print_summary(outcomes: &[(PathBuf, TaskOutcome)]) {
+    let mut rows = vec![];
+    for (task_dir, outcome) in outcomes {
+        let task = task_dir.display().to_string();
+        match outcome {
+            Ok(scores) if scores.is_empty() => {
+                rows.push((task, String::new(), "no solutions".to_string()))
+            }
+            Ok(scores) => {
+                for (name, score) in scores {
+                    let score = match score {
+                        Some(score) => format!("{}", score),
+                        None => "-".to_string(),
+                    };
+                    rows.push((task.clone(), name.display().to_string(), score));
+                }
+            }
+            Err(error) => rows.push((task, String::new(), format!("failed: {}", error))),
+        }
+    }
+
+    let task_width = rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+    let solution_width = rows.iter().map(|row| row.1.len()).max().unwrap_or(0);
+    println!();
+    println!("Summary of {} tasks:", outcomes.len());
+    for (task, solution, score) in rows {
+        println!(
+            "  {:task_width$}  {:solution_width$}  {}",
+            task,
+            solution,
+            score,
+            task_width = task_width,
+            solution_width = solution_width
+        );
+    }
+}
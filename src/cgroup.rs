@@ -0,0 +1,168 @@
+//! Resource accounting of the sandboxes using cgroup v2.
+//!
+//! Each sandbox is run inside a fresh cgroup created under a _root_ cgroup, which should be
+//! delegated to the user running task-maker and have the `memory` and `pids` controllers enabled.
+//! After the sandbox exits, the peak memory usage and the cpu time of the whole cgroup are used
+//! instead of the ones measured by the sandbox, which only see the main process of the execution.
+//!
+//! Only a process forked for running the sandbox joins the cgroup, so the memory already used by
+//! task-maker is not accounted, and the page cache of the files written by the execution is not
+//! counted as memory used by it.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Error};
+
+/// Maximum number of processes (and threads) inside a sandbox cgroup, for preventing fork bombs.
+const MAX_PIDS: u64 = 1024;
+/// The controllers that are required in the root cgroup.
+const REQUIRED_CONTROLLERS: &[&str] = &["memory", "pids"];
+
+/// The resources used by all the processes of a cgroup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CgroupUsage {
+    /// The peak memory usage, in bytes, without the page cache (but with the files in tmpfs).
+    /// `None` if the kernel doesn't support `memory.peak`.
+    pub memory_peak: Option<u64>,
+    /// The user cpu time, in seconds.
+    pub user_time: f64,
+    /// The system cpu time, in seconds.
+    pub system_time: f64,
+}
+
+/// A cgroup created for a single sandbox. The cgroup is removed when this value is dropped, all the
+/// processes inside it must have exited by then.
+#[derive(Debug)]
+pub struct Cgroup {
+    /// The path of the cgroup directory.
+    path: PathBuf,
+}
+
+impl Cgroup {
+    /// Check that `root` is a cgroup that can be used for creating the sandbox cgroups, enabling
+    /// the required controllers for its children if needed.
+    pub fn check_root(root: &Path) -> Result<(), Error> {
+        let controllers = std::fs::read_to_string(root.join("cgroup.controllers"))
+            .with_context(|| format!("{} is not a cgroup v2 directory", root.display()))?;
+        let controllers: Vec<_> = controllers.split_whitespace().collect();
+        for controller in REQUIRED_CONTROLLERS {
+            if !controllers.contains(controller) {
+                bail!(
+                    "The {} controller is not available in the cgroup at {}",
+                    controller,
+                    root.display()
+                );
+            }
+        }
+        let enable = REQUIRED_CONTROLLERS
+            .iter()
+            .map(|c| format!("+{}", c))
+            .collect::<Vec<_>>()
+            .join(" ");
+        std::fs::write(root.join("cgroup.subtree_control"), enable).with_context(|| {
+            format!(
+                "Cannot enable the controllers in {} (is the cgroup delegated to this user?)",
+                root.display()
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Create a new cgroup for a sandbox under `root`.
+    pub fn new_in(root: &Path) -> Result<Cgroup, Error> {
+        let path = root.join(format!("sandbox-{:016x}", fastrand::u64(..)));
+        std::fs::create_dir(&path)
+            .with_context(|| format!("Failed to create cgroup at {}", path.display()))?;
+        let cgroup = Cgroup { path };
+        std::fs::write(cgroup.path.join("pids.max"), MAX_PIDS.to_string())
+            .context("Failed to set pids.max")?;
+        Ok(cgroup)
+    }
+
+    /// The path of the cgroup directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Move the current process inside the cgroup at `path`. All the processes spawned after this
+    /// call will be inside that cgroup as well. This should be called only by a process forked
+    /// for this purpose, since everything it does from now on is accounted to the cgroup.
+    pub fn join(path: &Path) -> Result<(), Error> {
+        std::fs::write(path.join("cgroup.procs"), std::process::id().to_string())
+            .with_context(|| format!("Failed to join the cgroup at {}", path.display()))
+    }
+
+    /// Read the resources used by the processes of this cgroup.
+    pub fn usage(&self) -> Result<CgroupUsage, Error> {
+        let memory_peak = match std::fs::read_to_string(self.path.join("memory.peak")) {
+            Ok(peak) => {
+                let peak: u64 = peak.trim().parse().context("Invalid memory.peak")?;
+                // the peak includes the page cache of the files written by the execution, that
+                // is still charged to the cgroup. The `anon` and `shmem` counters would not include
+                // it, but they are the current usage and not the peak one, so the page cache is
+                // subtracted from the peak instead. The tmpfs files are kept in memory by the
+                // execution, so they are not subtracted.
+                let memory_stat = std::fs::read_to_string(self.path.join("memory.stat"))
+                    .context("Failed to read memory.stat")?;
+                Some(peak.saturating_sub(parse_memory_stat_file(&memory_stat)?))
+            }
+            Err(_) => None,
+        };
+        let cpu_stat = std::fs::read_to_string(self.path.join("cpu.stat"))
+            .context("Failed to read cpu.stat")?;
+        let (user_time, system_time) = parse_cpu_stat(&cpu_stat)?;
+        Ok(CgroupUsage {
+            memory_peak,
+            user_time,
+            system_time,
+        })
+    }
+}
+
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir(&self.path) {
+            warn!(
+                "Failed to remove cgroup at {}: {:?}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Extract the size of the page cache, in bytes, from the content of a `memory.stat` file. The
+/// shared memory (i.e. the files in tmpfs) is part of the `file` counter, but it's not included.
+fn parse_memory_stat_file(content: &str) -> Result<u64, Error> {
+    let get = |key: &str| -> Result<u64, Error> {
+        let value = content
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .find(|(k, _)| *k == key)
+            .ok_or_else(|| anyhow!("Missing {} in memory.stat", key))?
+            .1;
+        value
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid {} in memory.stat", key))
+    };
+    Ok(get("file")?.saturating_sub(get("shmem")?))
+}
+
+/// Extract the user and system time, in seconds, from the content of a `cpu.stat` file.
+fn parse_cpu_stat(content: &str) -> Result<(f64, f64), Error> {
+    let get = |key: &str| -> Result<f64, Error> {
+        let value = content
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .find(|(k, _)| *k == key)
+            .ok_or_else(|| anyhow!("Missing {} in cpu.stat", key))?
+            .1;
+        let usec: u64 = value
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid {} in cpu.stat", key))?;
+        Ok(usec as f64 / 1_000_000.0)
+    };
+    Ok((get("user_usec")?, get("system_usec")?))
+}
@@ -0,0 +1,315 @@
+//! Running the executions inside OCI container images, using [podman](https://podman.io).
+//!
+//! A task can require a toolchain that is not installed on the workers (e.g. an exotic compiler):
+//! its executions are then run inside a container image that provides it. The tag of the image is
+//! resolved by the client to the digest it points to when the evaluation starts (see
+//! [`resolve_image`]), so that the cached results are not reused after the tag moves to a
+//! different image. The images are pulled by the workers the first time they are used, and podman
+//! keeps them for the following evaluations.
+//!
+//! Each execution is run in a new container, as the user of the worker, with the box directory of
+//! the sandbox mounted at the same path, no network and the same limits of the sandbox. The
+//! resources are measured with cgroup v2, like the cgroup backend does: the container is created
+//! inside a fresh cgroup under the cgroup root.
+
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Error};
+use tabox::configuration::SandboxConfiguration;
+use tabox::result::{ExitStatus, ResourceUsage, SandboxExecutionResult};
+
+use task_maker_exec::RawSandboxResult;
+
+use crate::cgroup::Cgroup;
+
+/// How often the container is checked for the wall time limit.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Where the cgroup v2 hierarchy is mounted.
+const CGROUP_MOUNT: &str = "/sys/fs/cgroup";
+/// Maximum number of processes (and threads) inside a multiprocess container.
+const MAX_PIDS: u32 = 1024;
+
+/// A runner of the executions inside container images, spawning podman.
+#[derive(Debug)]
+pub struct Container {
+    /// Path to the podman executable.
+    path: PathBuf,
+    /// The cgroup where to create the cgroups of the containers.
+    cgroup_root: PathBuf,
+    /// The images that are known to be available locally.
+    images: Mutex<HashSet<String>>,
+}
+
+impl Container {
+    /// Make a new runner of containers using podman at `path`, creating the cgroups of the
+    /// containers under `cgroup_root`. Fails if podman cannot be executed or the cgroup cannot be
+    /// used.
+    pub fn new(path: PathBuf, cgroup_root: PathBuf) -> Result<Container, Error> {
+        let output = Command::new(&path)
+            .arg("--version")
+            .output()
+            .with_context(|| format!("Cannot execute podman at {}", path.display()))?;
+        if !output.status.success() {
+            bail!(
+                "podman --version failed: {}\n{}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        Cgroup::check_root(&cgroup_root)?;
+        Ok(Container {
+            path,
+            cgroup_root,
+            images: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Run an execution inside a new container of `image`, storing the pid of podman inside
+    /// `pid`.
+    pub fn run(
+        &self,
+        image: &str,
+        config: SandboxConfiguration,
+        pid: Arc<AtomicU32>,
+    ) -> Result<RawSandboxResult, Error> {
+        let config = config.build();
+        self.pull(image)?;
+        let cgroup = Cgroup::new_in(&self.cgroup_root)?;
+        let name = format!("task-maker-{:016x}", fastrand::u64(..));
+
+        let mut cmd = Command::new(&self.path);
+        cmd.arg("--cgroup-manager=cgroupfs").arg("run");
+        cmd.args(run_args(&name, &config, cgroup.path()));
+        cmd.arg(image).arg(&config.executable).args(&config.args);
+        cmd.stdin(match &config.stdin {
+            Some(path) => File::open(path)
+                .with_context(|| format!("Failed to open stdin {}", path.display()))?
+                .into(),
+            None => Stdio::null(),
+        });
+        for (path, is_stdout) in [(&config.stdout, true), (&config.stderr, false)] {
+            let stdio = match path {
+                Some(path) => OpenOptions::new()
+                    .write(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open output {}", path.display()))?
+                    .into(),
+                None => Stdio::null(),
+            };
+            if is_stdout {
+                cmd.stdout(stdio);
+            } else {
+                cmd.stderr(stdio);
+            }
+        }
+
+        trace!("Running container {} of {}: {:?}", name, image, cmd);
+        let start = Instant::now();
+        let mut child = cmd.spawn().context("Cannot spawn podman")?;
+        pid.store(child.id(), Ordering::SeqCst);
+        let wall_time_limit = config.wall_time_limit.map(Duration::from_secs);
+        let mut killed = false;
+        let status = loop {
+            if let Some(status) = child.try_wait().context("Failed to wait for podman")? {
+                break status;
+            }
+            if !killed && wall_time_limit.map_or(false, |limit| start.elapsed() > limit) {
+                // killing podman would leave the container running
+                let _ = Command::new(&self.path)
+                    .args(["kill", "--signal", "KILL", &name])
+                    .output();
+                killed = true;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        };
+        let wall_time_usage = start.elapsed().as_secs_f64();
+
+        // podman exits with 125 if it fails, 126 and 127 if the command cannot be executed, and
+        // with 128+n if the command is killed by the signal n
+        let status = match status.code() {
+            _ if killed => ExitStatus::Killed,
+            Some(125) => bail!("podman failed to run the container of {}", image),
+            Some(code) if code > 128 => ExitStatus::Signal(code - 128),
+            Some(code) => ExitStatus::ExitCode(code),
+            None => bail!("podman was killed: {}", status),
+        };
+        let usage = cgroup.usage()?;
+        Ok(RawSandboxResult::Success(SandboxExecutionResult {
+            status,
+            resource_usage: ResourceUsage {
+                memory_usage: usage.memory_peak.unwrap_or_default(),
+                user_cpu_time: usage.user_time,
+                system_cpu_time: usage.system_time,
+                wall_time_usage,
+            },
+        }))
+    }
+
+    /// Make sure that `image` is available locally, pulling it if needed. The pulls are done one
+    /// at a time, so that the same image is not pulled twice.
+    fn pull(&self, image: &str) -> Result<(), Error> {
+        let mut images = self.images.lock().unwrap();
+        if images.contains(image) {
+            return Ok(());
+        }
+        let exists = Command::new(&self.path)
+            .args(["image", "exists", image])
+            .status()
+            .context("Cannot spawn podman")?;
+        if !exists.success() {
+            info!("Pulling container image {}", image);
+            let output = Command::new(&self.path)
+                .args(["pull", "--quiet", image])
+                .output()
+                .context("Cannot spawn podman")?;
+            if !output.status.success() {
+                bail!(
+                    "Failed to pull the container image {}: {}",
+                    image,
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+        }
+        images.insert(image.to_string());
+        Ok(())
+    }
+}
+
+/// Resolve the tag of `image` to the digest of the image it currently points to, pulling it with
+/// podman at `podman`, or the one in `PATH` if not specified. The returned reference is pinned to
+/// the digest, the images that are already pinned are returned as they are.
+pub fn resolve_image(podman: Option<&Path>, image: &str) -> Result<String, Error> {
+    if image.contains('@') {
+        return Ok(image.to_string());
+    }
+    let podman = match podman {
+        Some(podman) => podman.to_owned(),
+        None => which::which("podman").with_context(|| {
+            format!(
+                "podman is needed for resolving the container image {} to its digest, pin the \
+                image to a digest instead (e.g. {}@sha256:...)",
+                image,
+                image_repository(image)
+            )
+        })?,
+    };
+    info!("Resolving the digest of container image {}", image);
+    let output = Command::new(&podman)
+        .args(["pull", "--quiet", image])
+        .output()
+        .context("Cannot spawn podman")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to pull the container image {}: {}",
+            image,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let output = Command::new(&podman)
+        .args(["image", "inspect", "--format", "{{.Digest}}", image])
+        .output()
+        .context("Cannot spawn podman")?;
+    let digest = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || !digest.starts_with("sha256:") {
+        bail!(
+            "Failed to find the digest of the container image {}: {}",
+            image,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(format!("{}@{}", image_repository(image), digest))
+}
+
+/// The reference to `image` without its tag, if any.
+fn image_repository(image: &str) -> &str {
+    match image.rsplit_once(':') {
+        // the colon of a registry with a port is followed by the path of the image
+        Some((repository, tag)) if !tag.contains('/') => repository,
+        _ => image,
+    }
+}
+
+/// The arguments of `podman run` for running the execution described by `config` in a container
+/// named `name`, inside the cgroup at `cgroup`.
+fn run_args(name: &str, config: &SandboxConfiguration, cgroup: &Path) -> Vec<String> {
+    let cgroup_parent = match cgroup.strip_prefix(CGROUP_MOUNT) {
+        Ok(path) => Path::new("/").join(path),
+        Err(_) => cgroup.to_owned(),
+    };
+    // the box directory is owned by the user of the worker
+    // SAFETY: getuid and getgid have no preconditions and they cannot fail.
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    let mut args = vec![
+        "--rm".to_string(),
+        "--interactive".to_string(),
+        "--pull=never".to_string(),
+        "--network=none".to_string(),
+        "--read-only".to_string(),
+        "--cap-drop=all".to_string(),
+        "--security-opt=no-new-privileges".to_string(),
+        format!("--user={}:{}", uid, gid),
+        format!("--name={}", name),
+        format!("--cgroup-parent={}", cgroup_parent.display()),
+        format!("--workdir={}", config.working_directory.display()),
+    ];
+    for mount in &config.mount_paths {
+        let mode = if mount.writable { "rw" } else { "ro" };
+        args.push(format!(
+            "--volume={}:{}:{}",
+            mount.source.display(),
+            mount.target.display(),
+            mode
+        ));
+    }
+    if config.mount_tmpfs {
+        args.push("--tmpfs=/tmp".to_string());
+    }
+    for (key, value) in &config.env {
+        args.push(format!("--env={}={}", key, value));
+    }
+    if let Some(time) = config.time_limit {
+        // the process gets SIGXCPU when reaching the soft limit, and SIGKILL after one more second
+        args.push(format!("--ulimit=cpu={}:{}", time, time + 1));
+    }
+    if let Some(memory) = config.memory_limit {
+        args.push(format!("--memory={}", memory));
+        args.push(format!("--memory-swap={}", memory));
+    }
+    if let Some(stack) = config.stack_limit {
+        args.push(format!("--ulimit=stack={}:{}", stack, stack));
+    }
+    // the syscall filter of tabox forbids fork only if the execution is not multiprocess
+    let multiprocess = config
+        .syscall_filter
+        .as_ref()
+        .map(|filter| !filter.rules.iter().any(|(name, _)| name == "fork"))
+        .unwrap_or(true);
+    let max_pids = if multiprocess { MAX_PIDS } else { 1 };
+    args.push(format!("--pids-limit={}", max_pids));
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_repository() {
+        assert_eq!(image_repository("haskell:9.4"), "haskell");
+        assert_eq!(image_repository("haskell"), "haskell");
+        assert_eq!(
+            image_repository("localhost:5000/tools/ghc:9"),
+            "localhost:5000/tools/ghc"
+        );
+        assert_eq!(
+            image_repository("localhost:5000/tools/ghc"),
+            "localhost:5000/tools/ghc"
+        );
+    }
+}
@@ -2,26 +2,51 @@
 //!
 //! The structs here follow a multi-step builder pattern, moving from a struct to the next adding
 //! more and more context.
-use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Once};
 use std::thread::JoinHandle;
 
 use anyhow::{anyhow, bail, Context, Error};
 
 use task_maker_cache::Cache;
-use task_maker_dag::CacheMode;
+use task_maker_dag::{CacheMode, CacheTTL, ExecutionDAG, ToolchainVersions};
 use task_maker_exec::ductile::{new_local_channel, ChannelReceiver, ChannelSender};
-use task_maker_exec::executors::{LocalExecutor, RemoteEntityMessage, RemoteEntityMessageResponse};
+use task_maker_exec::executors::{InProcessLink, LocalExecutor};
 use task_maker_exec::proto::{ExecutorClientMessage, ExecutorServerMessage};
-use task_maker_exec::ExecutorClient;
+use task_maker_exec::{
+    check_dag, find_orphan_files, probe_toolchains, ExecutionDAGWatchSet, ExecutorClient,
+};
 use task_maker_format::ui::{UIChannelReceiver, UIMessage, UIType, UI};
 use task_maker_format::{EvaluationData, TaskFormat, UISender, VALID_TAGS};
+use task_maker_lang::LanguageManager;
 use task_maker_store::FileStore;
 
-use crate::remote::connect_to_remote_server;
+use crate::container::resolve_image;
+use crate::remote::{connect_to_remote_server, send_welcome, Welcome};
 use crate::{render_dag, ExecutionOpt, StorageOpt, ToolsSandboxRunner};
 
-/// Version of task-maker.
-const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The senders of the clients of the running evaluation, told to stop on ^C.
+type ClientSenders = Arc<Mutex<Vec<ChannelSender<ExecutorClientMessage>>>>;
+
+/// A UI shown across more evaluations (e.g. with `--watch`), built by the first one. It's never
+/// finished by the evaluations, the following ones restart it instead.
+pub type SharedUI = Arc<Mutex<Option<Box<dyn UI>>>>;
+
+lazy_static! {
+    /// The senders of the clients of the evaluation currently running, if any. The ^C handler can be
+    /// set only once, but there may be more than one evaluation in the same process (e.g. with
+    /// `--watch`).
+    static ref CURRENT_CLIENT_SENDERS: Mutex<Option<ClientSenders>> = Mutex::new(None);
+}
+
+/// Guard for setting the ^C handler only once.
+static SET_CTRLC_HANDLER: Once = Once::new();
+
+/// Whether the evaluation currently running has already been asked to stop. A second ^C while
+/// stopping exits immediately.
+static STOPPING: AtomicBool = AtomicBool::new(false);
 
 /// First step of the execution: take a task and build the Execution DAG. This needs setting the
 /// first configurations of the environment.
@@ -32,6 +57,23 @@ pub struct RuntimeContext {
     pub sandbox_runner: ToolsSandboxRunner,
 }
 
+/// The channels for communicating with a remote executor.
+pub type RemoteServer = (
+    ChannelSender<ExecutorClientMessage>,
+    ChannelReceiver<ExecutorServerMessage>,
+);
+
+/// A connection to an executor (either local or remote) that is kept open for evaluating more DAGs
+/// one after the other, sharing the local executor and the file store.
+pub struct ExecutorConnection {
+    pub file_store: Arc<FileStore>,
+    pub tx: ChannelSender<ExecutorClientMessage>,
+    pub rx: ChannelReceiver<ExecutorServerMessage>,
+    pub local_executor: Option<JoinHandle<Result<(), Error>>>,
+    /// The link with the local executor, for handing the DAGs over without serializing them.
+    pub in_process: Option<InProcessLink>,
+}
+
 /// Second step: connect to an executor (either local or remote). This opens the local store and
 /// setups the local executor if necessary.
 pub struct ConnectedExecutor {
@@ -44,7 +86,11 @@ pub struct ConnectedExecutor {
     pub file_store: Arc<FileStore>,
     pub tx: ChannelSender<ExecutorClientMessage>,
     pub rx: ChannelReceiver<ExecutorServerMessage>,
+    /// The connections to the other servers, when evaluating on more than one. The DAG is split
+    /// among `tx`/`rx` and these.
+    pub extra_servers: Vec<RemoteServer>,
     pub local_executor: Option<JoinHandle<Result<(), Error>>>,
+    pub in_process: Option<InProcessLink>,
 }
 
 /// Third step: start the UI thread.
@@ -55,11 +101,13 @@ pub struct ConnectedExecutorWithUI {
     pub file_store: Arc<FileStore>,
     pub tx: ChannelSender<ExecutorClientMessage>,
     pub rx: ChannelReceiver<ExecutorServerMessage>,
+    pub extra_servers: Vec<RemoteServer>,
     pub local_executor: Option<JoinHandle<Result<(), Error>>>,
+    pub in_process: Option<InProcessLink>,
 
     // new fields
     pub ui_thread: JoinHandle<()>,
-    pub client_sender: Arc<Mutex<Option<ChannelSender<ExecutorClientMessage>>>>,
+    pub client_senders: ClientSenders,
 }
 
 impl RuntimeContext {
@@ -84,9 +132,30 @@ impl RuntimeContext {
             .cache_mode(
                 CacheMode::try_from(&opt.no_cache, &VALID_TAGS).context("Invalid cache mode")?,
             )
+            .cache_ttl(
+                CacheTTL::try_from(&opt.cache_ttl, &VALID_TAGS).context("Invalid cache TTL")?,
+            )
             .copy_exe(opt.copy_exe)
             .copy_logs(opt.copy_logs)
-            .priority(opt.priority);
+            .keep_full_output(opt.keep_full_output)
+            .fetch_sandbox_logs(opt.fetch_sandbox_logs)
+            .priority(opt.priority)
+            .time_scaling(opt.time_scaling)
+            .verbose_resources(opt.verbose_resources)
+            .max_total_output_size(opt.max_total_output_size.map(|size| size * 1024 * 1024))
+            .max_batch_size(opt.batch_size.filter(|size| *size > 1))
+            .deterministic(opt.deterministic)
+            .stream_stderr(opt.stream_stderr)
+            .pool(opt.pool.clone())
+            .artifact_cache(!opt.no_artifact_cache)
+            .trace(opt.trace.clone())
+            .env(opt.env()?);
+        if opt.deterministic && opt.evaluate_on.len() > 1 {
+            bail!("A deterministic evaluation cannot be split among more servers");
+        }
+        if opt.pool.is_some() && opt.evaluate_on.is_empty() {
+            bail!("The pool of workers can be selected only with --evaluate-on");
+        }
         if let Some(extra_time) = opt.extra_time {
             if extra_time < 0.0 {
                 bail!("The extra time ({}) cannot be negative!", extra_time);
@@ -97,8 +166,13 @@ impl RuntimeContext {
             config.extra_memory(extra_memory);
         }
 
+        eval.keep_artifacts = opt.keep_artifacts.clone();
+
         // build the execution dag
         build_dag(&mut task, &mut eval)?;
+        if !opt.dry_run {
+            pin_container_images(&mut eval.dag, opt.sandbox.container_runtime.as_deref())?;
+        }
 
         trace!("The DAG is: {:#?}", eval.dag);
         if opt.copy_dag {
@@ -112,10 +186,22 @@ impl RuntimeContext {
             task,
             eval,
             ui_receiver,
-            sandbox_runner: ToolsSandboxRunner::default(),
+            sandbox_runner: opt.sandbox.sandbox_runner(opt.run_in_process())?,
         })
     }
 
+    /// Validate the DAG of the evaluation without running it. The orphan files are only reported
+    /// as warnings.
+    pub fn check_dag(&self) -> Result<(), Error> {
+        let watch_set = ExecutionDAGWatchSet::new(&self.eval.dag);
+        check_dag(&self.eval.dag.data, &watch_set)
+            .context("The DAG of the evaluation is not valid")?;
+        for orphan in find_orphan_files(&self.eval.dag.data, &watch_set) {
+            warn!("{}", orphan);
+        }
+        Ok(())
+    }
+
     /// Change the default sandbox runner for the local executor.
     pub fn sandbox_runner(&mut self, sandbox_runner: ToolsSandboxRunner) {
         self.sandbox_runner = sandbox_runner;
@@ -127,65 +213,22 @@ impl RuntimeContext {
         opt: &ExecutionOpt,
         storage_opt: &StorageOpt,
     ) -> Result<ConnectedExecutor, Error> {
-        // setup the file store
-        let store_path = storage_opt.store_dir();
-        let file_store = Arc::new(
-            FileStore::new(
-                store_path.join("store"),
-                storage_opt.max_cache * 1024 * 1024,
-                storage_opt.min_cache * 1024 * 1024,
-            )
-            .context(
-                "Cannot create the file store (You can try wiping it with task-maker-tools reset)",
-            )?,
-        );
-
-        // connect either to the remote executor or spawn a local one
-        let (tx, rx, local_executor) = if let Some(evaluate_on) = &opt.evaluate_on {
-            let (tx, rx) = connect_to_remote_server(evaluate_on, 27182)
-                .context("Cannot connect to the remote server")?;
-            let name = opt.name.clone().unwrap_or_else(|| {
-                format!(
-                    "{}@{}",
-                    whoami::username(),
-                    whoami::fallible::hostname().unwrap()
-                )
-            });
-            tx.send(RemoteEntityMessage::Welcome {
-                name,
-                version: VERSION.into(),
-            })
-            .context("Cannot send welcome to the server")?;
-            if let RemoteEntityMessageResponse::Rejected(err) =
-                rx.recv().context("Failed to receive welcome response")?
-            {
-                bail!("The server rejected the client connection: {}", err);
+        let file_store = open_file_store(storage_opt)?;
+
+        // connect either to the remote executors or spawn a local one
+        let mut extra_servers = vec![];
+        let (tx, rx, local_executor, in_process) = if let Some((first, others)) =
+            opt.evaluate_on.split_first()
+        {
+            let (tx, rx) = connect_to_executor(first, opt)?;
+            for evaluate_on in others {
+                extra_servers.push(connect_to_executor(evaluate_on, opt)?);
             }
-            (tx.change_type(), rx.change_type(), None)
+            (tx, rx, None, None)
         } else {
-            // start the server and the client
-            let (tx, rx_remote) = new_local_channel();
-            let (tx_remote, rx) = new_local_channel();
-
-            // setup the local cache
-            let cache_path = store_path.join("cache");
-            let cache = Cache::new(cache_path).context("Cannot create the cache")?;
-
-            // setup the local executor
-            let num_cores = opt.num_cores.unwrap_or_else(num_cpus::get_physical);
-            let sandbox_path = storage_opt.store_dir().join("sandboxes");
-            let executor = LocalExecutor::new(
-                file_store.clone(),
-                cache,
-                num_cores,
-                sandbox_path,
-                self.sandbox_runner,
-            )?;
-            let local_executor = std::thread::Builder::new()
-                .name("Executor thread".into())
-                .spawn(move || executor.evaluate(tx_remote, rx_remote))
-                .context("Failed to spawn the executor thread")?;
-            (tx, rx, Some(local_executor))
+            let local =
+                spawn_local_executor(file_store.clone(), opt, storage_opt, self.sandbox_runner)?;
+            (local.tx, local.rx, local.local_executor, local.in_process)
         };
 
         Ok(ConnectedExecutor {
@@ -196,9 +239,163 @@ impl RuntimeContext {
             file_store,
             tx,
             rx,
+            extra_servers,
             local_executor,
+            in_process,
         })
     }
+
+    /// Use an already open connection to an executor instead of connecting to a new one.
+    pub fn use_connection(self, connection: ExecutorConnection) -> ConnectedExecutor {
+        ConnectedExecutor {
+            task: self.task,
+            eval: self.eval,
+            ui_receiver: self.ui_receiver,
+
+            file_store: connection.file_store,
+            tx: connection.tx,
+            rx: connection.rx,
+            extra_servers: vec![],
+            local_executor: connection.local_executor,
+            in_process: connection.in_process,
+        }
+    }
+}
+
+impl ExecutorConnection {
+    /// Start the local executor or connect to a remote one. Only a single server is supported.
+    pub fn connect(
+        opt: &ExecutionOpt,
+        storage_opt: &StorageOpt,
+        sandbox_runner: ToolsSandboxRunner,
+    ) -> Result<ExecutorConnection, Error> {
+        let file_store = open_file_store(storage_opt)?;
+        match opt.evaluate_on.as_slice() {
+            [] => spawn_local_executor(file_store, opt, storage_opt, sandbox_runner),
+            [evaluate_on] => {
+                let (tx, rx) = connect_to_executor(evaluate_on, opt)?;
+                Ok(ExecutorConnection {
+                    file_store,
+                    tx,
+                    rx,
+                    local_executor: None,
+                    in_process: None,
+                })
+            }
+            _ => bail!("The connection to more than one server cannot be shared"),
+        }
+    }
+
+    /// Close the connection, waiting for the local executor to exit.
+    pub fn close(self) -> Result<(), Error> {
+        drop(self.tx);
+        drop(self.rx);
+        if let Some(local_executor) = self.local_executor {
+            local_executor
+                .join()
+                .map_err(|e| anyhow!("Executor panicked: {:?}", e))?
+                .context("Local executor failed")?;
+        }
+        Ok(())
+    }
+}
+
+/// Open the file store in the store directory.
+pub(crate) fn open_file_store(storage_opt: &StorageOpt) -> Result<Arc<FileStore>, Error> {
+    let file_store = FileStore::new(
+        storage_opt.store_dir().join("store"),
+        storage_opt.max_cache * 1024 * 1024,
+        storage_opt.min_cache * 1024 * 1024,
+    )
+    .context("Cannot create the file store (You can try wiping it with task-maker-tools reset)")?;
+    Ok(Arc::new(file_store))
+}
+
+/// Open the cache in the store directory, using also the remote cache if one is set.
+pub(crate) fn open_cache(
+    storage_opt: &StorageOpt,
+    file_store: &Arc<FileStore>,
+) -> Result<Cache, Error> {
+    let cache_path = storage_opt.store_dir().join("cache");
+    let mut cache = Cache::new(cache_path).context("Cannot create the cache")?;
+    if let Some(remote) = &storage_opt.remote_cache {
+        cache
+            .set_remote(remote.clone(), file_store.clone())
+            .context("Cannot setup the remote cache")?;
+    }
+    Ok(cache)
+}
+
+/// Spawn a local executor in a new thread, connecting to it.
+fn spawn_local_executor(
+    file_store: Arc<FileStore>,
+    opt: &ExecutionOpt,
+    storage_opt: &StorageOpt,
+    sandbox_runner: ToolsSandboxRunner,
+) -> Result<ExecutorConnection, Error> {
+    // start the server and the client
+    let (tx, rx_remote) = new_local_channel();
+    let (tx_remote, rx) = new_local_channel();
+
+    let cache = open_cache(storage_opt, &file_store)?;
+
+    // setup the local executor
+    let num_cores = opt.num_cores.unwrap_or_else(num_cpus::get_physical);
+    let sandbox_path = storage_opt.store_dir().join("sandboxes");
+    #[cfg(not(feature = "chaos"))]
+    let executor = LocalExecutor::new(
+        file_store.clone(),
+        cache,
+        num_cores,
+        sandbox_path,
+        sandbox_runner,
+        local_toolchain_versions(),
+    )?;
+    #[cfg(feature = "chaos")]
+    let executor = LocalExecutor::with_chaos(
+        file_store.clone(),
+        cache,
+        num_cores,
+        sandbox_path,
+        sandbox_runner,
+        local_toolchain_versions(),
+        opt.chaos.clone(),
+    )?;
+    // with chaos the files are uploaded through the channel, where the failures are injected
+    #[cfg(not(feature = "chaos"))]
+    let in_process = Some(executor.in_process_link());
+    #[cfg(feature = "chaos")]
+    let in_process = None;
+    let local_executor = std::thread::Builder::new()
+        .name("Executor thread".into())
+        .spawn(move || executor.evaluate(tx_remote, rx_remote))
+        .context("Failed to spawn the executor thread")?;
+    Ok(ExecutorConnection {
+        file_store,
+        tx,
+        rx,
+        local_executor: Some(local_executor),
+        in_process,
+    })
+}
+
+/// Connect to the remote executor at `evaluate_on`, introducing this client.
+fn connect_to_executor(evaluate_on: &str, opt: &ExecutionOpt) -> Result<RemoteServer, Error> {
+    let (tx, rx) = connect_to_remote_server(evaluate_on, 27182)
+        .with_context(|| format!("Cannot connect to the remote server at {}", evaluate_on))?;
+    let name = opt.name.clone().unwrap_or_else(|| {
+        format!(
+            "{}@{}",
+            whoami::username(),
+            whoami::fallible::hostname().unwrap()
+        )
+    });
+    let mut welcome = Welcome::client(name);
+    if opt.no_compression {
+        welcome = welcome.without_compression();
+    }
+    send_welcome(&tx, &rx, welcome, "client")?;
+    Ok((tx.change_type(), rx.change_type()))
 }
 
 impl ConnectedExecutor {
@@ -207,51 +404,134 @@ impl ConnectedExecutor {
     ///
     /// The callback takes 2 parameters, a reference to the current UI and the message produced.
     pub fn start_ui<OnMessage>(
+        self,
+        ui_type: &UIType,
+        on_message: OnMessage,
+    ) -> Result<ConnectedExecutorWithUI, Error>
+    where
+        OnMessage: FnMut(&mut dyn UI, UIMessage) + Send + 'static,
+    {
+        self.start_ui_with_epilogue(ui_type, on_message, Vec::new)
+    }
+
+    /// Like [`start_ui`](Self::start_ui), but when the execution is completed the messages returned
+    /// by `epilogue` are sent to the UI, before it prints its final summary.
+    pub fn start_ui_with_epilogue<OnMessage, Epilogue>(
+        self,
+        ui_type: &UIType,
+        on_message: OnMessage,
+        epilogue: Epilogue,
+    ) -> Result<ConnectedExecutorWithUI, Error>
+    where
+        OnMessage: FnMut(&mut dyn UI, UIMessage) + Send + 'static,
+        Epilogue: FnOnce() -> Vec<UIMessage> + Send + 'static,
+    {
+        self.start_ui_inner(ui_type, None, on_message, epilogue)
+    }
+
+    /// Like [`start_ui_with_epilogue`](Self::start_ui_with_epilogue), but show the evaluation on
+    /// `ui`, restarting it if a previous evaluation has already built it. The UI is not finished
+    /// when the execution is completed.
+    pub fn start_shared_ui_with_epilogue<OnMessage, Epilogue>(
+        self,
+        ui_type: &UIType,
+        ui: SharedUI,
+        on_message: OnMessage,
+        epilogue: Epilogue,
+    ) -> Result<ConnectedExecutorWithUI, Error>
+    where
+        OnMessage: FnMut(&mut dyn UI, UIMessage) + Send + 'static,
+        Epilogue: FnOnce() -> Vec<UIMessage> + Send + 'static,
+    {
+        self.start_ui_inner(ui_type, Some(ui), on_message, epilogue)
+    }
+
+    /// Start the UI thread, either with a new UI or with the shared one.
+    fn start_ui_inner<OnMessage, Epilogue>(
         mut self,
         ui_type: &UIType,
+        shared_ui: Option<SharedUI>,
         mut on_message: OnMessage,
+        epilogue: Epilogue,
     ) -> Result<ConnectedExecutorWithUI, Error>
     where
         OnMessage: FnMut(&mut dyn UI, UIMessage) + Send + 'static,
+        Epilogue: FnOnce() -> Vec<UIMessage> + Send + 'static,
     {
         let config = self.eval.dag.config_mut().clone();
         // setup the UI thread
-        let mut ui = self
-            .task
-            .ui(ui_type, config)
-            .context("This UI is not supported on this task type")?;
+        let finish = shared_ui.is_none();
+        let ui = shared_ui.unwrap_or_default();
+        {
+            let mut ui = ui.lock().unwrap();
+            match ui.as_mut() {
+                Some(ui) => ui.restart(&self.task, config)?,
+                None => {
+                    *ui = Some(
+                        self.task
+                            .ui(ui_type, config)
+                            .context("This UI is not supported on this task type")?,
+                    )
+                }
+            }
+        }
         let ui_receiver = self.ui_receiver;
         let ui_thread = std::thread::Builder::new()
             .name("UI".to_owned())
             .spawn(move || {
+                let mut guard = ui.lock().unwrap();
+                let ui = guard.as_mut().expect("The UI has not been built");
                 while let Ok(message) = ui_receiver.recv() {
                     if let UIMessage::StopUI = message {
                         break;
                     }
                     on_message(ui.as_mut(), message);
                 }
-                ui.finish();
+                for message in epilogue() {
+                    on_message(ui.as_mut(), message);
+                }
+                if finish {
+                    ui.finish();
+                }
             })
             .context("Failed to spawn UI thread")?;
 
-        // a shared sender for the ctrl-c handler, it has to be wrapped in Arc-Mutex-Option to be freed
+        // the shared senders for the ctrl-c handler, they have to be wrapped in Arc-Mutex to be freed
         // at the end of the computation to allow the client to exit.
-        let client_sender = Arc::new(Mutex::new(Some(self.tx.clone())));
-        // `ctrlc` crate doesn't allow multiple calls of set_handler, and the tests may call this
-        // function multiple times, so in the tests ^C handler is disabled.
+        let client_senders = std::iter::once(&self.tx)
+            .chain(self.extra_servers.iter().map(|(tx, _)| tx))
+            .cloned()
+            .collect();
+        let client_senders = Arc::new(Mutex::new(client_senders));
+        // `ctrlc` crate doesn't allow multiple calls of set_handler, so the handler is set only
+        // once and it stops the evaluation currently running: the server kills the running
+        // executions and the results obtained so far are shown. When no evaluation is running, or
+        // when it's already stopping, ^C just exits. The tests may run many evaluations at once, so
+        // in the tests ^C handler is disabled.
         #[cfg(not(test))]
         {
-            let client_sender = client_sender.clone();
-            if let Err(e) = ctrlc::set_handler(move || {
-                let sender = client_sender.lock().unwrap();
-                if let Some(sender) = sender.as_ref() {
-                    if sender.send(ExecutorClientMessage::Stop).is_err() {
-                        error!("Cannot tell the server to stop");
+            *CURRENT_CLIENT_SENDERS.lock().unwrap() = Some(client_senders.clone());
+            STOPPING.store(false, Ordering::SeqCst);
+            SET_CTRLC_HANDLER.call_once(|| {
+                if let Err(e) = ctrlc::set_handler(|| {
+                    let client_senders = CURRENT_CLIENT_SENDERS.lock().unwrap().clone();
+                    let senders = match &client_senders {
+                        Some(senders) => senders.lock().unwrap().clone(),
+                        None => vec![],
+                    };
+                    if senders.is_empty() || STOPPING.swap(true, Ordering::SeqCst) {
+                        std::process::exit(130);
+                    }
+                    eprintln!("Stopping the evaluation, press ^C again to force quit");
+                    for sender in senders {
+                        if sender.send(ExecutorClientMessage::Stop).is_err() {
+                            error!("Cannot tell the server to stop");
+                        }
                     }
+                }) {
+                    warn!("Cannot bind control-C handler: {:?}", e);
                 }
-            }) {
-                warn!("Cannot bind control-C handler: {:?}", e);
-            }
+            });
         }
 
         Ok(ConnectedExecutorWithUI {
@@ -260,17 +540,37 @@ impl ConnectedExecutor {
             file_store: self.file_store,
             tx: self.tx,
             rx: self.rx,
+            extra_servers: self.extra_servers,
             local_executor: self.local_executor,
+            in_process: self.in_process,
 
             ui_thread,
-            client_sender,
+            client_senders,
         })
     }
 }
 
 impl ConnectedExecutorWithUI {
     /// Finally, start the execution and wait until it ends or it is stopped.
-    pub fn execute(mut self) -> Result<(), Error> {
+    pub fn execute(self) -> Result<(), Error> {
+        self.run(false)?;
+        Ok(())
+    }
+
+    /// Like [`execute`](Self::execute), but keep the connection to the executor open and return it
+    /// for evaluating more DAGs. Only a single server is supported.
+    pub fn execute_keeping_connection(self) -> Result<ExecutorConnection, Error> {
+        if !self.extra_servers.is_empty() {
+            bail!("The connection to more than one server cannot be shared");
+        }
+        self.run(true)?
+            .context("The connection to the executor has not been kept")
+    }
+
+    /// Start the execution and wait until it ends or it is stopped. If `keep_connection` is set
+    /// the connection to the (single) executor is returned, otherwise it's closed and the local
+    /// executor is waited for.
+    fn run(mut self, keep_connection: bool) -> Result<Option<ExecutorConnection>, Error> {
         let ui_sender = self.eval.sender.clone();
         // Create a copy of the DAG, keeping the cloned object inside the EvaluationData, while the
         // original is stored in `dag`. This because after cloning a ExecutionDAG the copies don't
@@ -278,7 +578,12 @@ impl ConnectedExecutorWithUI {
         let mut dag = self.eval.dag.clone();
         std::mem::swap(&mut dag, &mut self.eval.dag);
 
-        let local_executor = self.local_executor;
+        // when the connection is kept the executor keeps running, so it's not waited for
+        let local_executor = if keep_connection {
+            None
+        } else {
+            self.local_executor.take()
+        };
         let ui_thread = self.ui_thread;
         let sender = self.eval.sender.clone();
         defer! {
@@ -297,23 +602,84 @@ impl ConnectedExecutorWithUI {
                 .unwrap();
         }
 
-        // run the actual computation and block until it ends
-        let client_sender = self.client_sender;
-        ExecutorClient::evaluate(dag, self.tx, &self.rx, self.file_store, move |status| {
-            ui_sender.send(UIMessage::ServerStatus { status })
-        })
-        .with_context(|| {
-            if let Some(tx) = client_sender.lock().unwrap().as_ref() {
+        // run the actual computation and block until it ends. Unless the connection is kept, the
+        // client gets the only sender, so that the local executor exits when the client is done.
+        let (client_tx, kept_tx) = if keep_connection {
+            (self.tx.clone(), Some(self.tx))
+        } else {
+            (self.tx, None)
+        };
+        let client_senders = self.client_senders;
+        let status_callback = move |status| ui_sender.send(UIMessage::ServerStatus { status });
+        let (res, rx) = if self.extra_servers.is_empty() {
+            let res = if let Some(link) = &self.in_process {
+                ExecutorClient::evaluate_in_process(dag, client_tx, &self.rx, link, status_callback)
+            } else {
+                let file_store = self.file_store.clone();
+                ExecutorClient::evaluate(dag, client_tx, &self.rx, file_store, status_callback)
+            };
+            (res, Some(self.rx))
+        } else {
+            let servers = std::iter::once((client_tx, self.rx))
+                .chain(self.extra_servers)
+                .collect();
+            let file_store = self.file_store.clone();
+            let res =
+                ExecutorClient::evaluate_distributed(dag, servers, file_store, status_callback);
+            (res, None)
+        };
+        res.with_context(|| {
+            for tx in client_senders.lock().unwrap().iter() {
                 let _ = tx.send(ExecutorClientMessage::Stop);
             }
             "Client failed"
         })?;
-        // disable the ctrl-c handler dropping the owned clone of the sender, letting the client exit
-        client_sender.lock().unwrap().take();
+        // disable the ctrl-c handler dropping the owned clones of the senders, letting the client
+        // exit
+        client_senders.lock().unwrap().clear();
 
         self.task
             .sanity_check_post_hook(&mut self.eval)
             .context("Sanity checks failed")?;
-        Ok(())
+        self.task.evaluation_post_hook(&mut self.eval)?;
+        Ok(kept_tx.zip(rx).map(|(tx, rx)| ExecutorConnection {
+            file_store: self.file_store,
+            tx,
+            rx,
+            local_executor: self.local_executor,
+            in_process: self.in_process,
+        }))
+    }
+}
+
+/// Pin the container images of the executions of the DAG to the digests their tags point to now,
+/// so that the cached results are not reused when a tag is moved to a different image.
+fn pin_container_images(dag: &mut ExecutionDAG, podman: Option<&Path>) -> Result<(), Error> {
+    let mut pinned: HashMap<String, String> = HashMap::new();
+    let executions = dag
+        .data
+        .execution_groups
+        .values_mut()
+        .flat_map(|group| group.executions.iter_mut());
+    for exec in executions {
+        let Some(image) = exec.effective_container_image().map(String::from) else {
+            continue;
+        };
+        if !pinned.contains_key(&image) {
+            let resolved = resolve_image(podman, &image)?;
+            pinned.insert(image.clone(), resolved);
+        }
+        exec.container_image = pinned.get(&image).cloned();
     }
+    Ok(())
+}
+
+/// Whether the user asked to stop the evaluation that was running last, with ^C.
+pub fn evaluation_stopped() -> bool {
+    STOPPING.load(Ordering::SeqCst)
+}
+
+/// The versions of the toolchains of all the known languages installed on this machine.
+pub fn local_toolchain_versions() -> ToolchainVersions {
+    probe_toolchains(&LanguageManager::toolchains())
 }
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::fmt::Write;
 
-use task_maker_dag::{Execution, ExecutionDAG, File, ProvidedFile};
+use task_maker_dag::{Execution, ExecutionDAG, File, ProvidedFile, TrustLevel};
 
 /// A node in the printed graph.
 #[allow(clippy::large_enum_variant)]
@@ -93,6 +93,10 @@ fn render_graph(nodes: Vec<Node>, edges: Vec<Edge>) -> String {
     res += "    rankdir=\"LR\";\n";
     for node in nodes {
         let style = match &node {
+            // the programs of the task authors are highlighted
+            Node::Execution(exec) if exec.trust == TrustLevel::Trusted => {
+                "style=rounded shape=record color=blue"
+            }
             Node::Execution(_) => "style=rounded shape=record",
             Node::File(_) => "style=dashed shape=box",
         };
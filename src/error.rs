@@ -1,6 +1,8 @@
 use anyhow::{anyhow, Error};
 use std::fmt::Display;
 
+use task_maker_diagnostics::Diagnostic;
+
 /// Adds methods for failing without panic. Like `expect` but without panic.
 pub trait NiceError<T, E> {
     /// Fail exiting with `1` if the value is not present. Otherwise return the content.
@@ -15,12 +17,20 @@ pub trait NiceError<T, E> {
     fn nice_expect_with<S: Display + Send + Sync + 'static, F: FnOnce() -> S>(self, f: F) -> T;
 }
 
-fn print_error(error: Error) {
+/// Print an error and all its causes to stderr. The causes that are a `Diagnostic` are printed with
+/// their code and help.
+pub fn print_error(error: Error) {
     debug!("{:?}", error);
     let mut fail: &dyn std::error::Error = error.as_ref();
-    eprintln!("Error: {}", fail);
+    match fail.downcast_ref::<Diagnostic>() {
+        Some(diagnostic) => eprint!("{}", diagnostic),
+        None => eprintln!("Error: {}", fail),
+    }
     while let Some(cause) = fail.source() {
-        eprintln!("\nCaused by:\n    {}", cause);
+        match cause.downcast_ref::<Diagnostic>() {
+            Some(diagnostic) => eprint!("\nCaused by:\n{}", diagnostic),
+            None => eprintln!("\nCaused by:\n    {}", cause),
+        }
         fail = cause;
     }
 }
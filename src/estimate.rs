@@ -0,0 +1,410 @@
+//! Estimate the cost of an evaluation without running it.
+//!
+//! The DAG is visited in topological order, querying the cache for each execution group whose
+//! inputs are known. The outputs of a cache hit are known as well, so the executions that depend on
+//! them can be looked up too; the outputs of an execution that would run are not known, so all the
+//! executions that depend on them are assumed to run. For this reason the estimate is an upper
+//! bound: an execution that produces the same outputs as before doesn't invalidate the cache of
+//! the following ones.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::Error;
+
+use task_maker_cache::CacheEstimate;
+use task_maker_dag::{
+    CacheMode, ExecutionDAG, ExecutionGroup, ExecutionResourcesUsage, ProvidedFile,
+};
+
+use crate::context::{local_toolchain_versions, open_cache, open_file_store};
+use crate::opt::StorageOpt;
+
+/// The name used for the executions without a tag.
+const UNTAGGED: &str = "untagged";
+
+/// The estimated cost of the executions with the same tag.
+#[derive(Debug, Clone, Default)]
+pub struct TagEstimate {
+    /// The number of executions that would be run.
+    pub to_run: usize,
+    /// The number of executions whose result is taken from the cache.
+    pub cached: usize,
+    /// The number of groups that would be run.
+    pub groups_to_run: usize,
+    /// The total wall time of the groups that would be run and have a previous execution recorded
+    /// in the cache, in seconds.
+    pub known_time: f64,
+    /// The number of groups that would be run and have a previous execution recorded in the cache.
+    pub known_groups: usize,
+    /// The total wall time of all the groups with this tag recorded in the cache, in seconds, used
+    /// for estimating the duration of the groups without a previous execution.
+    history_time: f64,
+    /// The number of groups with this tag recorded in the cache.
+    history_groups: usize,
+}
+
+/// The estimated cost of an evaluation.
+#[derive(Debug, Clone, Default)]
+pub struct Estimate {
+    /// The estimate of the executions of each tag.
+    pub tags: BTreeMap<String, TagEstimate>,
+    /// The number of cores used for the local evaluation.
+    pub num_cores: usize,
+}
+
+impl TagEstimate {
+    /// The expected wall time of the groups that would be run, in seconds. The groups without a
+    /// previous execution take the average time of the groups with this tag; `None` if no group
+    /// with this tag has ever been executed.
+    pub fn expected_time(&self) -> Option<f64> {
+        let unknown = self.groups_to_run - self.known_groups;
+        if unknown == 0 {
+            return Some(self.known_time);
+        }
+        if self.history_groups == 0 {
+            return None;
+        }
+        let average = self.history_time / self.history_groups as f64;
+        Some(self.known_time + average * unknown as f64)
+    }
+
+    /// Record the resources used by a previous execution of a group.
+    fn record(&mut self, resources: &[ExecutionResourcesUsage]) -> f64 {
+        // the executions of a group run in parallel
+        let time = resources.iter().map(|r| r.wall_time).fold(0.0, f64::max);
+        self.history_time += time;
+        self.history_groups += 1;
+        time
+    }
+
+    /// Account a group that would be run, with the resources of its previous execution, if any.
+    fn run(&mut self, group: &ExecutionGroup, resources: Option<&[ExecutionResourcesUsage]>) {
+        self.to_run += group.executions.len();
+        self.groups_to_run += 1;
+        if let Some(resources) = resources {
+            self.known_time += self.record(resources);
+            self.known_groups += 1;
+        }
+    }
+}
+
+impl Estimate {
+    /// Print the estimate to stdout.
+    pub fn print(&self) {
+        println!("Executions that would run, by tag:");
+        let width = self.tags.keys().map(|tag| tag.len()).max().unwrap_or(0);
+        for (tag, estimate) in &self.tags {
+            print!(
+                "  {:width$}  {:5} to run  {:5} cached",
+                tag,
+                estimate.to_run,
+                estimate.cached,
+                width = width
+            );
+            if estimate.to_run > 0 {
+                match estimate.expected_time() {
+                    Some(time) => print!("  ~{:.1}s", time),
+                    None => print!("  no previous executions"),
+                }
+            }
+            println!();
+        }
+
+        let to_run: usize = self.tags.values().map(|t| t.to_run).sum();
+        let cached: usize = self.tags.values().map(|t| t.cached).sum();
+        print!("Total: {} to run, {} cached", to_run, cached);
+        let times: Option<Vec<f64>> = self
+            .tags
+            .values()
+            .filter(|t| t.to_run > 0)
+            .map(|t| t.expected_time())
+            .collect();
+        if let Some(times) = times {
+            let total: f64 = times.iter().sum();
+            print!(
+                ", ~{:.1}s (~{:.1}s on {} cores)",
+                total,
+                total / self.num_cores.max(1) as f64,
+                self.num_cores
+            );
+        }
+        println!();
+    }
+}
+
+/// Estimate the cost of the evaluation of the DAG, using the cache in the store directory.
+pub fn estimate_dag(
+    dag: &ExecutionDAG,
+    storage: &StorageOpt,
+    num_cores: usize,
+) -> Result<Estimate, Error> {
+    let file_store = open_file_store(storage)?;
+    let mut cache = open_cache(storage, &file_store)?;
+
+    let data = &dag.data;
+    let mut file_keys = HashMap::new();
+    for file in data.provided_files.values() {
+        match file {
+            ProvidedFile::LocalFile { file, key, .. } | ProvidedFile::Content { file, key, .. } => {
+                file_keys.insert(file.uuid, key.clone());
+            }
+        }
+    }
+    // the files produced by the executions that would run
+    let mut unknown_files = HashSet::new();
+    // the executions are assumed to run on this machine
+    let toolchains = local_toolchain_versions();
+
+    let mut estimate = Estimate {
+        tags: BTreeMap::new(),
+        num_cores,
+    };
+    let mut pending: Vec<&ExecutionGroup> = data.execution_groups.values().collect();
+    loop {
+        let (ready, not_ready): (Vec<_>, Vec<_>) = pending.into_iter().partition(|group| {
+            group
+                .executions
+                .iter()
+                .flat_map(|exec| exec.dependencies())
+                .all(|dep| file_keys.contains_key(&dep) || unknown_files.contains(&dep))
+        });
+        if ready.is_empty() {
+            // the remaining groups (if any) depend on files that are never produced: they will
+            // never run
+            break;
+        }
+        pending = not_ready;
+
+        for group in ready {
+            let tag = group.tag();
+            let tag_estimate = estimate
+                .tags
+                .entry(
+                    tag.as_ref()
+                        .map_or(UNTAGGED, |tag| tag.name.as_str())
+                        .to_string(),
+                )
+                .or_default();
+            let outputs = group.executions.iter().flat_map(|exec| exec.outputs());
+            let depends_on_run = group
+                .executions
+                .iter()
+                .flat_map(|exec| exec.dependencies())
+                .any(|dep| unknown_files.contains(&dep));
+            if depends_on_run {
+                tag_estimate.run(group, None);
+                unknown_files.extend(outputs);
+                continue;
+            }
+
+            let ttl = data.config.cache_ttl.ttl(tag.as_ref());
+            let cacheable = match (&data.config.cache_mode, &tag) {
+                (CacheMode::Nothing, _) => false,
+                (CacheMode::Except(set), Some(tag)) => !set.contains(tag),
+                _ => true,
+            };
+            let mut versioned = group.clone();
+            versioned.set_toolchain_versions(&toolchains);
+            match cache.estimate(&versioned, &file_keys, &file_store, ttl) {
+                CacheEstimate::Hit {
+                    resources,
+                    outputs: keys,
+                    stale,
+                } if cacheable => {
+                    if stale {
+                        // the stale result is used, but the group is run again for refreshing it
+                        tag_estimate.run(group, Some(&resources));
+                    } else {
+                        tag_estimate.record(&resources);
+                        tag_estimate.cached += group.executions.len();
+                    }
+                    file_keys.extend(keys);
+                }
+                CacheEstimate::Hit { resources, .. } => {
+                    tag_estimate.run(group, Some(&resources));
+                    unknown_files.extend(outputs);
+                }
+                CacheEstimate::Miss { resources } => {
+                    tag_estimate.run(group, resources.as_deref());
+                    unknown_files.extend(outputs);
+                }
+            }
+        }
+    }
+    Ok(estimate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use task_maker_cache::Cache;
+    use task_maker_dag::{
+        Execution, ExecutionCommand, ExecutionResult, ExecutionStatus, File, FileUuid,
+    };
+    use task_maker_store::{FileStore, FileStoreKey};
+
+    fn resources(wall_time: f64) -> ExecutionResourcesUsage {
+        ExecutionResourcesUsage {
+            wall_time,
+            ..Default::default()
+        }
+    }
+
+    fn success(wall_time: f64) -> ExecutionResult {
+        ExecutionResult {
+            status: ExecutionStatus::Success,
+            resources: resources(wall_time),
+            ..Default::default()
+        }
+    }
+
+    /// Build a DAG with a generation reading a provided file, and an evaluation reading the output
+    /// of the generation. Returns the DAG, the input file and the generated file.
+    fn make_dag() -> (ExecutionDAG, File, File) {
+        let mut dag = ExecutionDAG::new();
+        let input = File::new("input");
+        dag.provide_content(input.clone(), b"input".to_vec());
+        let mut generation = Execution::new("generation", ExecutionCommand::system("gen"));
+        generation
+            .input(&input, "input", false)
+            .tag("generation".into());
+        let generated = generation.output("output");
+        dag.add_execution(generation);
+        let mut evaluation = Execution::new("evaluation", ExecutionCommand::system("sol"));
+        evaluation
+            .input(&generated, "input", false)
+            .tag("evaluation".into());
+        evaluation.output("output");
+        dag.add_execution(evaluation);
+        (dag, input, generated)
+    }
+
+    fn storage(dir: &std::path::Path) -> StorageOpt {
+        StorageOpt {
+            store_dir: Some(dir.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Insert in the cache the generation of the DAG made by `make_dag`.
+    fn cache_generation(dag: &ExecutionDAG, storage: &StorageOpt, input: &File, generated: &File) {
+        let store_dir = storage.store_dir();
+        let store = FileStore::new(store_dir.join("store"), 1 << 20, 1 << 20).unwrap();
+        let mut cache = Cache::new(store_dir.join("cache")).unwrap();
+        let mut handles: HashMap<FileUuid, _> = HashMap::new();
+        for (file, content) in [(input, b"input"), (generated, b"gener")] {
+            let key = FileStoreKey::from_content(content);
+            let handle = store
+                .store(&key, std::iter::once(content.to_vec()))
+                .unwrap();
+            handles.insert(file.uuid, handle);
+        }
+        let group = dag
+            .data
+            .execution_groups
+            .values()
+            .find(|group| group.description == "generation")
+            .unwrap();
+        cache.insert(group, &handles, vec![success(2.0)]);
+        cache.flush().unwrap();
+    }
+
+    #[test]
+    fn test_expected_time_known() {
+        let estimate = TagEstimate {
+            groups_to_run: 2,
+            known_groups: 2,
+            known_time: 3.0,
+            ..Default::default()
+        };
+        assert_eq!(estimate.expected_time(), Some(3.0));
+    }
+
+    #[test]
+    fn test_expected_time_average() {
+        let estimate = TagEstimate {
+            groups_to_run: 3,
+            known_groups: 1,
+            known_time: 1.0,
+            history_time: 6.0,
+            history_groups: 3,
+            ..Default::default()
+        };
+        // the 2 unknown groups take the average of the history
+        assert_eq!(estimate.expected_time(), Some(5.0));
+    }
+
+    #[test]
+    fn test_expected_time_no_history() {
+        let estimate = TagEstimate {
+            groups_to_run: 1,
+            ..Default::default()
+        };
+        assert_eq!(estimate.expected_time(), None);
+    }
+
+    #[test]
+    fn test_run_parallel_executions() {
+        let mut group = ExecutionGroup::new("group");
+        group.add_execution(Execution::new("a", ExecutionCommand::system("a")));
+        group.add_execution(Execution::new("b", ExecutionCommand::system("b")));
+        let mut estimate = TagEstimate::default();
+        estimate.run(&group, Some(&[resources(1.0), resources(3.0)]));
+        estimate.run(&group, None);
+        assert_eq!(estimate.to_run, 4);
+        assert_eq!(estimate.groups_to_run, 2);
+        assert_eq!(estimate.known_groups, 1);
+        // the executions of a group run in parallel, so the slowest one counts
+        assert_eq!(estimate.known_time, 3.0);
+        assert_eq!(estimate.expected_time(), Some(6.0));
+    }
+
+    #[test]
+    fn test_estimate_dag_empty_cache() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let (dag, _, _) = make_dag();
+        let estimate = estimate_dag(&dag, &storage(tmpdir.path()), 4).unwrap();
+        assert_eq!(estimate.num_cores, 4);
+        for tag in ["generation", "evaluation"] {
+            assert_eq!(estimate.tags[tag].to_run, 1);
+            assert_eq!(estimate.tags[tag].cached, 0);
+            assert_eq!(estimate.tags[tag].expected_time(), None);
+        }
+    }
+
+    #[test]
+    fn test_estimate_dag_cached_dependency() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let storage = storage(tmpdir.path());
+        let (dag, input, generated) = make_dag();
+        cache_generation(&dag, &storage, &input, &generated);
+
+        let estimate = estimate_dag(&dag, &storage, 4).unwrap();
+        let generation = &estimate.tags["generation"];
+        assert_eq!(generation.to_run, 0);
+        assert_eq!(generation.cached, 1);
+        assert_eq!(generation.expected_time(), Some(0.0));
+        // the output of the generation is known, but the evaluation was never run
+        let evaluation = &estimate.tags["evaluation"];
+        assert_eq!(evaluation.to_run, 1);
+        assert_eq!(evaluation.cached, 0);
+    }
+
+    #[test]
+    fn test_estimate_dag_no_cache() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let storage = storage(tmpdir.path());
+        let (mut dag, input, generated) = make_dag();
+        cache_generation(&dag, &storage, &input, &generated);
+        dag.config_mut().cache_mode(CacheMode::Nothing);
+
+        let estimate = estimate_dag(&dag, &storage, 4).unwrap();
+        // the generation is run again, taking the time of its previous execution
+        let generation = &estimate.tags["generation"];
+        assert_eq!(generation.to_run, 1);
+        assert_eq!(generation.cached, 0);
+        assert_eq!(generation.expected_time(), Some(2.0));
+        // the evaluation depends on a file that is not known anymore
+        assert_eq!(estimate.tags["evaluation"].to_run, 1);
+    }
+}
@@ -0,0 +1,387 @@
+//! Evaluation of a task from code, for using task-maker as a library.
+//!
+//! The command line entry point ([`run_evaluation`](crate::run_evaluation)) is a thin wrapper
+//! around [`EvaluationBuilder`], which can be used directly without building an [`Opt`](crate::Opt).
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context, Error};
+
+use task_maker_diagnostics::Diagnostic;
+#[cfg(feature = "chaos")]
+use task_maker_exec::ChaosConfig;
+use task_maker_format::ui::{ExitPolicy, UIMessage, UIStateT, UIType, UI};
+use task_maker_format::{ioi, terry, EvaluationConfig, TaskFormat};
+
+use crate::context::{ExecutorConnection, RuntimeContext, SharedUI};
+use crate::estimate::{estimate_dag, Estimate};
+use crate::opt::{ExecutionOpt, StorageOpt};
+use crate::repro::Repro;
+
+/// The final state of an evaluation, as seen by the UI.
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum EvaluationState {
+    /// The state of the evaluation of an IOI task.
+    IOI(ioi::UIState),
+    /// The state of the evaluation of a Terry task.
+    Terry(terry::UIState),
+}
+
+impl EvaluationState {
+    /// The initial state of the evaluation of the task of a context.
+    fn new(context: &RuntimeContext) -> EvaluationState {
+        match &context.task {
+            TaskFormat::IOI(task) => EvaluationState::IOI(ioi::UIState::new(
+                task,
+                context.eval.dag.data.config.clone(),
+            )),
+            TaskFormat::Terry(task) => EvaluationState::Terry(terry::UIState::new(task)),
+        }
+    }
+
+    /// The score of each evaluated solution, `None` if the solution has not been scored (e.g. it
+    /// failed to compile).
+    pub fn solution_scores(&self) -> BTreeMap<PathBuf, Option<f64>> {
+        match self {
+            EvaluationState::IOI(state) => state
+                .evaluations
+                .iter()
+                .map(|(path, eval)| (path.clone(), eval.score))
+                .collect(),
+            EvaluationState::Terry(state) => state
+                .solutions
+                .iter()
+                .map(|(path, solution)| {
+                    let score = match &solution.outcome {
+                        Some(Ok(outcome)) => Some(outcome.score * state.task.max_score),
+                        _ => None,
+                    };
+                    (path.clone(), score)
+                })
+                .collect(),
+        }
+    }
+
+    /// The diagnostics emitted during the evaluation.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match self {
+            EvaluationState::IOI(state) => state.diagnostics.diagnostics(),
+            EvaluationState::Terry(state) => state.diagnostics.diagnostics(),
+        }
+    }
+
+    /// The reasons for failing the evaluation according to the exit policy, empty if the
+    /// evaluation should succeed.
+    pub fn exit_failures(&self, policy: &ExitPolicy) -> Vec<String> {
+        match self {
+            EvaluationState::IOI(state) => state.exit_failures(policy),
+            EvaluationState::Terry(state) => state.exit_failures(policy),
+        }
+    }
+
+    /// Apply a message of the evaluation to the state.
+    fn apply(&mut self, message: UIMessage) {
+        match self {
+            EvaluationState::IOI(state) => state.apply(message),
+            EvaluationState::Terry(state) => state.apply(message),
+        }
+    }
+}
+
+/// Builder of the evaluation of a task.
+///
+/// ```no_run
+/// # use task_maker_format::{find_task, EvaluationConfig};
+/// # use task_maker_rust::EvaluationBuilder;
+/// # let config = EvaluationConfig::default();
+/// let task = find_task(Some("path/to/task".into()), 3, &config).unwrap();
+/// let state = EvaluationBuilder::new(task, config)
+///     .num_cores(4)
+///     .cache(false)
+///     .run()
+///     .unwrap();
+/// ```
+pub struct EvaluationBuilder {
+    /// The task to evaluate.
+    task: TaskFormat,
+    /// The configuration of the evaluation of the task.
+    config: EvaluationConfig,
+    /// The options of the execution.
+    execution: ExecutionOpt,
+    /// The options of the storage.
+    storage: StorageOpt,
+    /// The UI to show while evaluating.
+    ui: UIType,
+    /// Print the commands for running again the sandboxes of the executions whose description
+    /// contains this string.
+    print_repro: Option<String>,
+    /// Compute the diagnostics to emit from the final state of the evaluation.
+    final_diagnostics: Option<FinalDiagnostics>,
+    /// Receive the paths where the evaluation writes its files.
+    written_paths: Option<WrittenPaths>,
+    /// Show the evaluation on this UI instead of building a new one.
+    shared_ui: Option<SharedUI>,
+}
+
+/// A function computing some diagnostics from the final state of an evaluation.
+type FinalDiagnostics = Box<dyn FnOnce(&EvaluationState) -> Vec<Diagnostic> + Send>;
+
+/// A function receiving the paths where an evaluation writes its files.
+type WrittenPaths = Box<dyn FnOnce(Vec<PathBuf>) + Send>;
+
+impl EvaluationBuilder {
+    /// Prepare the evaluation of a task, by default locally and without any UI.
+    pub fn new(task: TaskFormat, config: EvaluationConfig) -> EvaluationBuilder {
+        EvaluationBuilder {
+            task,
+            config,
+            execution: ExecutionOpt::default(),
+            storage: StorageOpt::default(),
+            ui: UIType::Silent,
+            print_repro: None,
+            final_diagnostics: None,
+            written_paths: None,
+            shared_ui: None,
+        }
+    }
+
+    /// Use these options for the execution, replacing all the ones set so far.
+    pub fn execution_opt(mut self, execution: ExecutionOpt) -> Self {
+        self.execution = execution;
+        self
+    }
+
+    /// Use these options for the storage, replacing all the ones set so far.
+    pub fn storage_opt(mut self, storage: StorageOpt) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// The UI to show while evaluating.
+    pub fn ui(mut self, ui: UIType) -> Self {
+        self.ui = ui;
+        self
+    }
+
+    /// Show the evaluation on a UI shared with the other evaluations, instead of building a new
+    /// one. The first evaluation builds it with the type set with [`ui`](Self::ui), the following
+    /// ones restart it.
+    pub fn shared_ui(mut self, ui: SharedUI) -> Self {
+        self.shared_ui = Some(ui);
+        self
+    }
+
+    /// Where to store the files and the cache.
+    pub fn store_dir<P: Into<PathBuf>>(mut self, store_dir: P) -> Self {
+        self.storage.store_dir = Some(store_dir.into());
+        self
+    }
+
+    /// Whether to use the cache for the executions.
+    pub fn cache(mut self, cache: bool) -> Self {
+        self.execution.no_cache = if cache { None } else { Some(None) };
+        self
+    }
+
+    /// The number of CPU cores to use for the local evaluation.
+    pub fn num_cores(mut self, num_cores: usize) -> Self {
+        self.execution.num_cores = Some(num_cores);
+        self
+    }
+
+    /// Run the evaluation on a remote server instead of locally. When called more than once the
+    /// evaluation is split among the servers.
+    pub fn evaluate_on<S: Into<String>>(mut self, server: S) -> Self {
+        self.execution.evaluate_on.push(server.into());
+        self
+    }
+
+    /// Run the evaluation only on the workers of this pool of the remote servers.
+    pub fn pool<S: Into<String>>(mut self, pool: S) -> Self {
+        self.execution.pool = Some(pool.into());
+        self
+    }
+
+    /// Do not write any file inside the task directory.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.execution.dry_run = dry_run;
+        self
+    }
+
+    /// Give to the solutions some extra time before being killed.
+    pub fn extra_time(mut self, extra_time: f64) -> Self {
+        self.execution.extra_time = Some(extra_time);
+        self
+    }
+
+    /// Run the executions one at a time, in a stable order, for getting the results in the same
+    /// order on every machine.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.execution.deterministic = deterministic;
+        self
+    }
+
+    /// Inject the failures of `chaos` in the local executor, for testing how the evaluation
+    /// recovers from them.
+    #[cfg(feature = "chaos")]
+    pub fn chaos(mut self, chaos: ChaosConfig) -> Self {
+        self.execution.chaos = chaos;
+        self
+    }
+
+    /// After the evaluation, print to stdout the commands for running again by hand the sandboxes of
+    /// the executions whose description contains `execution`. The sandboxes are kept.
+    pub fn print_repro<S: Into<String>>(mut self, execution: S) -> Self {
+        self.print_repro = Some(execution.into());
+        self.execution.keep_sandboxes = true;
+        self
+    }
+
+    /// When all the executions are done, emit the diagnostics computed by `f` from the final state
+    /// of the evaluation. They are sent to the UI before it prints its final summary, so they are
+    /// shown like the other diagnostics.
+    pub fn final_diagnostics<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&EvaluationState) -> Vec<Diagnostic> + Send + 'static,
+    {
+        self.final_diagnostics = Some(Box::new(f));
+        self
+    }
+
+    /// Call `f` with the paths where the evaluation writes its files (e.g. the generated
+    /// testcases), as soon as they are known, before the evaluation starts.
+    pub fn written_paths<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(Vec<PathBuf>) + Send + 'static,
+    {
+        self.written_paths = Some(Box::new(f));
+        self
+    }
+
+    /// Build the DAG of the evaluation and check that it's valid, without evaluating anything.
+    pub fn check_dag(self) -> Result<(), Error> {
+        let config = self.config;
+        let context = RuntimeContext::new(self.task, &self.execution, |task, eval| {
+            task.build_dag(eval, &config)
+                .context("Cannot build the task DAG")
+        })?;
+        context.check_dag()
+    }
+
+    /// Build the DAG of the evaluation and estimate its cost using the cache, without evaluating
+    /// anything.
+    pub fn estimate(self) -> Result<Estimate, Error> {
+        let config = self.config;
+        let context = RuntimeContext::new(self.task, &self.execution, |task, eval| {
+            task.build_dag(eval, &config)
+                .context("Cannot build the task DAG")
+        })?;
+        let num_cores = self
+            .execution
+            .num_cores
+            .unwrap_or_else(num_cpus::get_physical);
+        estimate_dag(&context.eval.dag, &self.storage, num_cores)
+    }
+
+    /// Run the evaluation, blocking until it's done, and return its final state.
+    pub fn run(self) -> Result<EvaluationState, Error> {
+        self.run_with(|ui, message| ui.on_message(message))
+    }
+
+    /// Run the evaluation, blocking until it's done, and return its final state.
+    ///
+    /// `on_message` is called for every message produced by the evaluation, with the UI of the
+    /// evaluation; typically it just sends the message to the UI.
+    pub fn run_with<F>(self, on_message: F) -> Result<EvaluationState, Error>
+    where
+        F: FnMut(&mut dyn UI, UIMessage) + Send + 'static,
+    {
+        self.run_inner(None, on_message)
+    }
+
+    /// Run the evaluation using an already open connection to an executor, blocking until it's done,
+    /// and return its final state.
+    ///
+    /// The connection is taken only when the evaluation starts, and it's put back when it's done:
+    /// if the evaluation fails before starting the connection can still be used. `None` means that
+    /// the connection has been lost.
+    pub fn run_on<F>(
+        self,
+        connection: &mut Option<ExecutorConnection>,
+        on_message: F,
+    ) -> Result<EvaluationState, Error>
+    where
+        F: FnMut(&mut dyn UI, UIMessage) + Send + 'static,
+    {
+        self.run_inner(Some(connection), on_message)
+    }
+
+    /// Run the evaluation, either on its own executor or on the provided connection.
+    fn run_inner<F>(
+        self,
+        mut connection: Option<&mut Option<ExecutorConnection>>,
+        mut on_message: F,
+    ) -> Result<EvaluationState, Error>
+    where
+        F: FnMut(&mut dyn UI, UIMessage) + Send + 'static,
+    {
+        let config = self.config;
+        let mut context = RuntimeContext::new(self.task, &self.execution, |task, eval| {
+            task.build_dag(eval, &config)
+                .context("Cannot build the task DAG")
+        })?;
+        if let Some(written_paths) = self.written_paths {
+            written_paths(context.eval.dag.written_paths());
+        }
+        let state = Arc::new(Mutex::new(EvaluationState::new(&context)));
+        let repro = match &self.print_repro {
+            Some(execution) => Some(Repro::watch(&mut context.eval.dag, execution)?),
+            None => None,
+        };
+
+        let executor = match connection.as_deref_mut() {
+            Some(connection) => match connection.take() {
+                Some(connection) => context.use_connection(connection),
+                None => bail!("The connection to the executor has been lost"),
+            },
+            None => context.connect_executor(&self.execution, &self.storage)?,
+        };
+        let epilogue = {
+            let state = state.clone();
+            let final_diagnostics = self.final_diagnostics;
+            move || match final_diagnostics {
+                Some(f) => f(&state.lock().unwrap())
+                    .into_iter()
+                    .map(|diagnostic| UIMessage::Diagnostic { diagnostic })
+                    .collect(),
+                None => vec![],
+            }
+        };
+        let on_message = {
+            let state = state.clone();
+            move |ui: &mut dyn UI, message: UIMessage| {
+                state.lock().unwrap().apply(message.clone());
+                on_message(ui, message);
+            }
+        };
+        let executor = match self.shared_ui {
+            Some(ui) => {
+                executor.start_shared_ui_with_epilogue(&self.ui, ui, on_message, epilogue)?
+            }
+            None => executor.start_ui_with_epilogue(&self.ui, on_message, epilogue)?,
+        };
+        match connection {
+            Some(connection) => *connection = Some(executor.execute_keeping_connection()?),
+            None => executor.execute()?,
+        }
+        if let Some(repro) = repro {
+            repro.print();
+        }
+
+        let state = state.lock().unwrap().clone();
+        Ok(state)
+    }
+}
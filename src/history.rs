@@ -0,0 +1,404 @@
+//! History of the evaluations of the tasks, enabled with `--history`.
+//!
+//! After each evaluation the score of each solution and the verdict and the resources used on each
+//! testcase are appended to a JSONL file in the storage directory, one per task, together with the
+//! git commit of the task. `task-maker-tools history` prints the trend and the regressions between
+//! the last two evaluations.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Error};
+use serde::{Deserialize, Serialize};
+
+use task_maker_format::ioi::{SolutionTestcaseEvaluationState, TestcaseId, UIState};
+use task_maker_format::{git_commit, TestcaseEvaluationResult};
+use task_maker_store::FileStoreKey;
+
+use crate::evaluation::EvaluationState;
+use crate::opt::StorageOpt;
+
+/// The increases of the time of the testcases faster than this (in seconds) are not regressions,
+/// they are most likely just noise.
+const MIN_REGRESSION_TIME: f64 = 0.1;
+
+/// Which differences between two evaluations are reported by [`compare_solutions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffMode {
+    /// Only the regressions: the scores that decreased, the verdicts that got worse and the
+    /// increases of the used resources.
+    Regressions,
+    /// All the changes of the scores and of the verdicts, the solutions and testcases added or
+    /// removed, and the increases of the used resources.
+    Changes,
+}
+
+/// An evaluation of a task recorded in the history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the evaluation ended, in seconds since the Unix epoch.
+    pub time: u64,
+    /// The git commit of the task directory, if it's in a git repository.
+    pub commit: Option<String>,
+    /// Whether the task directory had uncommitted changes.
+    pub dirty: bool,
+    /// The evaluated solutions, indexed by their path relative to the task directory.
+    pub solutions: BTreeMap<String, HistorySolution>,
+}
+
+/// The evaluation of a solution recorded in the history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistorySolution {
+    /// The score of the solution.
+    pub score: Option<f64>,
+    /// The evaluation of the solution on each testcase.
+    pub testcases: BTreeMap<TestcaseId, HistoryTestcase>,
+}
+
+/// The evaluation of a solution on a testcase recorded in the history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryTestcase {
+    /// The verdict of the testcase, if the evaluation completed.
+    pub verdict: Option<TestcaseEvaluationResult>,
+    /// The score of the testcase.
+    pub score: Option<f64>,
+    /// The maximum CPU time used by the processes of the solution, in seconds.
+    pub cpu_time: f64,
+    /// The maximum wall time used by the processes of the solution, in seconds.
+    pub wall_time: f64,
+    /// The maximum memory used by the processes of the solution, in KiB.
+    pub memory: u64,
+}
+
+impl HistoryEntry {
+    /// Build the entry of the final state of the evaluation of an IOI task.
+    pub fn from_state(state: &UIState) -> HistoryEntry {
+        let (commit, dirty) = git_commit(&state.task.path);
+        HistoryEntry {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            commit,
+            dirty,
+            solutions: HistorySolution::from_state(state),
+        }
+    }
+
+    /// The short description of the version of the task, e.g. `1234abcd*` for a commit with some
+    /// uncommitted changes.
+    pub fn version(&self) -> String {
+        let commit = match &self.commit {
+            Some(commit) => commit.chars().take(8).collect(),
+            None => "no-git".to_string(),
+        };
+        if self.dirty {
+            commit + "*"
+        } else {
+            commit
+        }
+    }
+}
+
+impl HistorySolution {
+    /// Build the records of all the solutions evaluated in the final state of the evaluation of an
+    /// IOI task, indexed by their path relative to the task directory.
+    pub fn from_state(state: &UIState) -> BTreeMap<String, HistorySolution> {
+        state
+            .evaluations
+            .iter()
+            .map(|(path, eval)| {
+                let name = path.strip_prefix(&state.task.path).unwrap_or(path);
+                let solution = HistorySolution {
+                    score: eval.score,
+                    testcases: eval
+                        .testcases
+                        .iter()
+                        .map(|(id, testcase)| (*id, HistoryTestcase::from_state(testcase)))
+                        .collect(),
+                };
+                (name.display().to_string(), solution)
+            })
+            .collect()
+    }
+}
+
+impl HistoryTestcase {
+    /// Build the record of the evaluation of a solution on a testcase.
+    fn from_state(testcase: &SolutionTestcaseEvaluationState) -> HistoryTestcase {
+        let mut record = HistoryTestcase {
+            verdict: (&testcase.status).into(),
+            score: testcase.score,
+            cpu_time: 0.0,
+            wall_time: 0.0,
+            memory: 0,
+        };
+        for result in testcase.results.iter().flatten() {
+            record.cpu_time = record.cpu_time.max(result.resources.cpu_time);
+            record.wall_time = record.wall_time.max(result.resources.wall_time);
+            record.memory = record.memory.max(result.resources.memory);
+        }
+        record
+    }
+}
+
+/// The path of the history of a task, inside the storage directory.
+pub fn history_path(storage: &StorageOpt, task_path: &Path) -> PathBuf {
+    let task_path = task_path
+        .canonicalize()
+        .unwrap_or_else(|_| task_path.to_path_buf());
+    let key = FileStoreKey::from_content(task_path.to_string_lossy().as_bytes());
+    storage
+        .store_dir()
+        .join("history")
+        .join(format!("{}.jsonl", key))
+}
+
+/// Append the evaluation to the history of the task. Only the IOI tasks are supported, for the
+/// other ones a warning is emitted.
+pub(crate) fn record_history(storage: &StorageOpt, state: &EvaluationState) -> Result<(), Error> {
+    let EvaluationState::IOI(state) = state else {
+        warn!("The history is supported only by IOI tasks, the evaluation is not recorded");
+        return Ok(());
+    };
+    let path = history_path(storage, &state.task.path);
+    let entry = HistoryEntry::from_state(state);
+    std::fs::create_dir_all(path.parent().context("Invalid history path")?)
+        .context("Failed to create the history directory")?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open the history at {}", path.display()))?;
+    let mut line = serde_json::to_string(&entry).context("Failed to serialize the history")?;
+    line.push('\n');
+    file.write_all(line.as_bytes())
+        .with_context(|| format!("Failed to write the history at {}", path.display()))?;
+    Ok(())
+}
+
+/// Load all the evaluations in the history of a task, from the oldest to the newest.
+pub fn load_history(path: &Path) -> Result<Vec<HistoryEntry>, Error> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open the history at {}", path.display()))?;
+    let mut entries = vec![];
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.context("Failed to read the history")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid history entry at line {}", index + 1))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Compare two evaluations of the history, returning the regressions of the newer one: the
+/// solutions whose score decreased, and the testcases whose verdict got worse or whose time or
+/// memory increased by more than `threshold` (a fraction).
+pub fn find_regressions(old: &HistoryEntry, new: &HistoryEntry, threshold: f64) -> Vec<String> {
+    compare_solutions(
+        &old.solutions,
+        &new.solutions,
+        threshold,
+        DiffMode::Regressions,
+    )
+}
+
+/// Compare the solutions of two evaluations, returning the differences of the newer one selected
+/// by `mode`. The time or memory of a testcase is reported if it increased by more than
+/// `threshold` (a fraction).
+pub fn compare_solutions(
+    old: &BTreeMap<String, HistorySolution>,
+    new: &BTreeMap<String, HistorySolution>,
+    threshold: f64,
+    mode: DiffMode,
+) -> Vec<String> {
+    let all_changes = mode == DiffMode::Changes;
+    let mut differences = vec![];
+    if all_changes {
+        for name in old.keys().filter(|name| !new.contains_key(*name)) {
+            differences.push(format!("{}: not evaluated anymore", name));
+        }
+    }
+    for (name, new_solution) in new {
+        let Some(old_solution) = old.get(name) else {
+            if all_changes {
+                differences.push(format!("{}: new solution", name));
+            }
+            continue;
+        };
+        if is_changed(old_solution.score, new_solution.score, all_changes) {
+            differences.push(format!(
+                "{}: score {} -> {}",
+                name,
+                format_score(old_solution.score),
+                format_score(new_solution.score)
+            ));
+        }
+        if all_changes {
+            for testcase in old_solution.testcases.keys() {
+                if !new_solution.testcases.contains_key(testcase) {
+                    differences.push(format!("{}: testcase {} removed", name, testcase));
+                }
+            }
+        }
+        for (testcase, new_tc) in &new_solution.testcases {
+            let Some(old_tc) = old_solution.testcases.get(testcase) else {
+                if all_changes {
+                    differences.push(format!("{}: testcase {} added", name, testcase));
+                }
+                continue;
+            };
+            let prefix = format!("{}: testcase {}", name, testcase);
+            if is_changed(old_tc.verdict, new_tc.verdict, all_changes) {
+                differences.push(format!(
+                    "{}: {} -> {}",
+                    prefix,
+                    format_verdict(old_tc.verdict),
+                    format_verdict(new_tc.verdict)
+                ));
+            } else if all_changes && old_tc.score != new_tc.score {
+                differences.push(format!(
+                    "{}: score {} -> {}",
+                    prefix,
+                    format_score(old_tc.score),
+                    format_score(new_tc.score)
+                ));
+            }
+            if new_tc.cpu_time >= MIN_REGRESSION_TIME
+                && new_tc.cpu_time > old_tc.cpu_time * (1.0 + threshold)
+            {
+                differences.push(format!(
+                    "{}: time {:.3}s -> {:.3}s",
+                    prefix, old_tc.cpu_time, new_tc.cpu_time
+                ));
+            }
+            if new_tc.memory as f64 > old_tc.memory as f64 * (1.0 + threshold) {
+                differences.push(format!(
+                    "{}: memory {} KiB -> {} KiB",
+                    prefix, old_tc.memory, new_tc.memory
+                ));
+            }
+        }
+    }
+    differences
+}
+
+/// Whether the difference between two values (a score or a verdict, the bigger the better) has to
+/// be reported: any change if `all_changes` is set, otherwise only a decrease.
+fn is_changed<T: PartialOrd>(old: Option<T>, new: Option<T>, all_changes: bool) -> bool {
+    match (old, new) {
+        (Some(old), Some(new)) if !all_changes => new < old,
+        (old, new) => all_changes && old != new,
+    }
+}
+
+/// Format a score, or `-` if it's not known.
+fn format_score(score: Option<f64>) -> String {
+    match score {
+        Some(score) => score.to_string(),
+        None => "-".into(),
+    }
+}
+
+/// Format a verdict, or `-` if it's not known.
+fn format_verdict(verdict: Option<TestcaseEvaluationResult>) -> String {
+    match verdict {
+        Some(verdict) => format!("{:?}", verdict),
+        None => "-".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: f64, verdict: TestcaseEvaluationResult, cpu_time: f64) -> HistoryEntry {
+        let testcase = HistoryTestcase {
+            verdict: Some(verdict),
+            score: Some(score),
+            cpu_time,
+            wall_time: cpu_time,
+            memory: 1000,
+        };
+        let solution = HistorySolution {
+            score: Some(score),
+            testcases: BTreeMap::from([(0, testcase)]),
+        };
+        HistoryEntry {
+            time: 0,
+            commit: None,
+            dirty: false,
+            solutions: BTreeMap::from([("sol/sol.cpp".to_string(), solution)]),
+        }
+    }
+
+    #[test]
+    fn test_find_regressions() {
+        use TestcaseEvaluationResult::*;
+        let old = entry(100.0, Accepted, 0.5);
+        assert!(find_regressions(&old, &entry(100.0, Accepted, 0.55), 0.2).is_empty());
+        assert!(find_regressions(&old, &entry(100.0, Accepted, 0.2), 0.2).is_empty());
+        let regressions = find_regressions(&old, &entry(100.0, Accepted, 1.0), 0.2);
+        assert_eq!(
+            regressions,
+            vec!["sol/sol.cpp: testcase 0: time 0.500s -> 1.000s"]
+        );
+        let regressions = find_regressions(&old, &entry(0.0, WrongAnswer, 0.5), 0.2);
+        assert_eq!(regressions.len(), 2);
+    }
+
+    #[test]
+    fn test_compare_solutions_changes() {
+        use TestcaseEvaluationResult::*;
+        let old = entry(0.0, WrongAnswer, 0.5);
+        let new = entry(100.0, Accepted, 0.5);
+        // the improvements are changes, but not regressions
+        assert!(find_regressions(&old, &new, 0.2).is_empty());
+        let changes = compare_solutions(&old.solutions, &new.solutions, 0.2, DiffMode::Changes);
+        assert_eq!(
+            changes,
+            vec![
+                "sol/sol.cpp: score 0 -> 100",
+                "sol/sol.cpp: testcase 0: WrongAnswer -> Accepted"
+            ]
+        );
+
+        let mut renamed = new.clone();
+        let solution = renamed.solutions.remove("sol/sol.cpp").unwrap();
+        renamed.solutions.insert("sol/new.cpp".into(), solution);
+        assert!(find_regressions(&new, &renamed, 0.2).is_empty());
+        let changes = compare_solutions(&new.solutions, &renamed.solutions, 0.2, DiffMode::Changes);
+        assert_eq!(
+            changes,
+            vec![
+                "sol/sol.cpp: not evaluated anymore",
+                "sol/new.cpp: new solution"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_history_roundtrip() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("history.jsonl");
+        let line = serde_json::to_string(&entry(100.0, TestcaseEvaluationResult::Accepted, 0.5));
+        std::fs::write(&path, line.unwrap() + "\n").unwrap();
+        let entries = load_history(&path).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].solutions["sol/sol.cpp"].testcases[&0].cpu_time,
+            0.5
+        );
+        assert!(load_history(&tmpdir.path().join("missing"))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,493 @@
+//! Running the sandboxes with [isolate](https://github.com/ioi/isolate), the sandbox used by CMS.
+//!
+//! Using the same sandbox as the contest environment makes the measured times and memory usages
+//! much closer to the ones obtained during the contest. Each execution is run inside a box of
+//! isolate, which is initialized before the execution and cleaned up after it. The boxes are taken
+//! from a range of box ids, so more instances of task-maker can share the same machine as long as
+//! they use disjoint ranges.
+//!
+//! isolate runs the executions with a different user for each box, so the directories and the files
+//! mounted inside the box are made accessible to that user before the execution, using ACLs. The
+//! files hardlinked from the file store are replaced by a copy first, so that the permissions of
+//! the store are never changed.
+
+use std::collections::HashMap;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, bail, Context, Error};
+use tabox::configuration::SandboxConfiguration;
+use tabox::result::{ExitStatus, ResourceUsage, SandboxExecutionResult};
+
+use task_maker_exec::RawSandboxResult;
+
+/// Directory inside the box where the directories of the redirected files are mounted.
+const IO_DIR: &str = "/tm-io";
+
+/// A sandbox backend that spawns isolate.
+#[derive(Debug)]
+pub struct Isolate {
+    /// Path to the isolate executable.
+    path: PathBuf,
+    /// Whether to run isolate with its control groups support (`--cg`), measuring the memory of all
+    /// the processes of the execution.
+    use_cgroups: bool,
+    /// The uid of the user of the box with id 0, as set by `first_uid` in the configuration of
+    /// isolate. The box with id `i` runs with uid `first_uid + i`.
+    first_uid: u32,
+    /// The box ids that are not currently in use.
+    free_boxes: Mutex<Vec<u32>>,
+}
+
+/// A box of isolate, initialized and reserved for a single execution. When dropped the box is
+/// cleaned up and its id returned to the pool.
+struct IsolateBox<'a> {
+    /// The isolate instance owning the box.
+    isolate: &'a Isolate,
+    /// The id of the box.
+    id: u32,
+    /// The directory of the box, as returned by `isolate --init`.
+    path: PathBuf,
+}
+
+impl Isolate {
+    /// Make a new isolate backend that uses the boxes with id in
+    /// `first_box_id..first_box_id+num_boxes`. Fails if isolate cannot be executed.
+    pub fn new(
+        path: PathBuf,
+        first_box_id: u32,
+        num_boxes: u32,
+        first_uid: u32,
+        use_cgroups: bool,
+    ) -> Result<Isolate, Error> {
+        if num_boxes == 0 {
+            bail!("At least one isolate box is required");
+        }
+        let output = Command::new(&path)
+            .arg("--version")
+            .output()
+            .with_context(|| format!("Cannot execute isolate at {}", path.display()))?;
+        if !output.status.success() {
+            bail!(
+                "isolate --version failed: {}\n{}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        let last_box_id = first_box_id
+            .checked_add(num_boxes)
+            .context("Invalid range of isolate box ids")?;
+        last_box_id
+            .checked_add(first_uid)
+            .context("Invalid range of isolate uids")?;
+        Ok(Isolate {
+            path,
+            use_cgroups,
+            first_uid,
+            // reversed so that the boxes are used starting from the first one
+            free_boxes: Mutex::new((first_box_id..last_box_id).rev().collect()),
+        })
+    }
+
+    /// Run an execution inside a box of isolate, storing the pid of isolate inside `pid`.
+    pub fn run(
+        &self,
+        config: SandboxConfiguration,
+        pid: Arc<AtomicU32>,
+    ) -> Result<RawSandboxResult, Error> {
+        let config = config.build();
+        let isolate_box = self.acquire_box()?;
+        let uid = self.first_uid + isolate_box.id;
+        let meta = tempfile::NamedTempFile::new().context("Failed to create the meta file")?;
+
+        let mut cmd = self.command(isolate_box.id);
+        cmd.arg(format!("--meta={}", meta.path().display()));
+        let mut io_dirs = HashMap::new();
+        for mount in &config.mount_paths {
+            // the system directories are mounted at the same path, and they are already accessible
+            if mount.source != mount.target {
+                grant_access(&mount.source, uid, mount.writable)?;
+            }
+            cmd.arg(dir_rule(&mount.target, &mount.source, mount.writable));
+        }
+        for (flag, path) in [
+            ("--stdin", &config.stdin),
+            ("--stdout", &config.stdout),
+            ("--stderr", &config.stderr),
+        ] {
+            let Some(path) = path else {
+                continue;
+            };
+            let path = self.redirect_path(&config, path, uid, &mut io_dirs, &mut cmd)?;
+            cmd.arg(format!("{}={}", flag, path.display()));
+        }
+        cmd.arg(format!("--chdir={}", config.working_directory.display()));
+        if let Some(time) = config.time_limit {
+            cmd.arg(format!("--time={}", time));
+        }
+        if let Some(wall) = config.wall_time_limit {
+            cmd.arg(format!("--wall-time={}", wall));
+        }
+        if let Some(memory) = config.memory_limit {
+            if self.use_cgroups {
+                cmd.arg(format!("--cg-mem={}", memory / 1024));
+            } else {
+                cmd.arg(format!("--mem={}", memory / 1024));
+            }
+        }
+        if let Some(stack) = config.stack_limit {
+            cmd.arg(format!("--stack={}", stack / 1024));
+        }
+        // the syscall filter of tabox forbids fork only if the execution is not multiprocess
+        let multiprocess = config
+            .syscall_filter
+            .as_ref()
+            .map(|filter| !filter.rules.iter().any(|(name, _)| name == "fork"))
+            .unwrap_or(true);
+        if multiprocess {
+            cmd.arg("--processes");
+        }
+        for (key, value) in &config.env {
+            cmd.arg(format!("--env={}={}", key, value));
+        }
+        cmd.arg("--run").arg("--").arg(&config.executable);
+        cmd.args(&config.args);
+
+        trace!("Running isolate in box {}: {:?}", isolate_box.id, cmd);
+        let child = cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Cannot spawn isolate")?;
+        pid.store(child.id(), Ordering::SeqCst);
+        let output = child
+            .wait_with_output()
+            .context("Failed to wait for isolate")?;
+        // isolate exits with 1 if the execution failed, and with 2 on internal errors
+        if !matches!(output.status.code(), Some(0) | Some(1)) {
+            bail!(
+                "isolate failed: {}\n{}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        let meta = std::fs::read_to_string(meta.path()).context("Failed to read the meta file")?;
+        drop(isolate_box);
+        Ok(RawSandboxResult::Success(parse_meta(&meta)?))
+    }
+
+    /// Build the base command for invoking isolate on a box.
+    fn command(&self, box_id: u32) -> Command {
+        let mut cmd = Command::new(&self.path);
+        cmd.arg(format!("--box-id={}", box_id));
+        if self.use_cgroups {
+            cmd.arg("--cg");
+        }
+        cmd
+    }
+
+    /// Reserve a free box and initialize it.
+    fn acquire_box(&self) -> Result<IsolateBox<'_>, Error> {
+        let id = self
+            .free_boxes
+            .lock()
+            .unwrap()
+            .pop()
+            .context("No free isolate box available, increase --isolate-num-boxes")?;
+        // a previous run may have left the box dirty
+        let _ = self.command(id).arg("--cleanup").output();
+        let output = self.command(id).arg("--init").output();
+        let output = match output {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                self.free_boxes.lock().unwrap().push(id);
+                bail!(
+                    "Failed to initialize isolate box {}: {}",
+                    id,
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+            Err(e) => {
+                self.free_boxes.lock().unwrap().push(id);
+                return Err(e).context("Cannot spawn isolate");
+            }
+        };
+        let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+        Ok(IsolateBox {
+            isolate: self,
+            id,
+            path,
+        })
+    }
+
+    /// Find the path inside the box of a redirected file. If the file is not inside a mounted
+    /// directory, its parent directory is mounted inside [`IO_DIR`].
+    fn redirect_path(
+        &self,
+        config: &SandboxConfiguration,
+        path: &Path,
+        uid: u32,
+        io_dirs: &mut HashMap<PathBuf, PathBuf>,
+        cmd: &mut Command,
+    ) -> Result<PathBuf, Error> {
+        if path.starts_with("/dev") {
+            return Ok(path.to_owned());
+        }
+        let mount = config
+            .mount_paths
+            .iter()
+            .filter(|mount| path.starts_with(&mount.source))
+            .max_by_key(|mount| mount.source.components().count());
+        if let Some(mount) = mount {
+            let rest = path.strip_prefix(&mount.source).unwrap();
+            return Ok(mount.target.join(rest));
+        }
+        let parent = path
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid redirect path: {}", path.display()))?;
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("Invalid redirect path: {}", path.display()))?;
+        let next = io_dirs.len();
+        let target = match io_dirs.get(parent) {
+            Some(target) => target.clone(),
+            None => {
+                let target = Path::new(IO_DIR).join(next.to_string());
+                grant_access(parent, uid, true)?;
+                cmd.arg(dir_rule(&target, parent, true));
+                io_dirs.insert(parent.to_owned(), target.clone());
+                target
+            }
+        };
+        Ok(target.join(file_name))
+    }
+}
+
+impl Drop for IsolateBox<'_> {
+    fn drop(&mut self) {
+        match self.isolate.command(self.id).arg("--cleanup").output() {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => warn!(
+                "Failed to cleanup isolate box {} at {}: {}",
+                self.id,
+                self.path.display(),
+                String::from_utf8_lossy(&output.stderr)
+            ),
+            Err(e) => warn!("Failed to cleanup isolate box {}: {:?}", self.id, e),
+        }
+        self.isolate.free_boxes.lock().unwrap().push(self.id);
+    }
+}
+
+/// Build the `--dir` option for mounting `source` at `target` inside the box.
+fn dir_rule(target: &Path, source: &Path, writable: bool) -> String {
+    // isolate wants the paths inside the box relative to its root
+    let target = target.strip_prefix("/").unwrap_or(target);
+    let mut rule = format!("--dir={}={}", target.display(), source.display());
+    if writable {
+        rule += ":rw";
+    }
+    rule
+}
+
+/// Give to the user with the specified `uid` the same permissions the owner has on the files
+/// inside `path`, without changing the permissions of the other users. If `writable` the user can
+/// also create new files in the directories, and the current user is granted access to them, so
+/// that they can be read and removed after the execution.
+fn grant_access(path: &Path, uid: u32, writable: bool) -> Result<(), Error> {
+    let mut by_permissions: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for entry in walkdir::WalkDir::new(path) {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if entry.file_type().is_symlink() {
+            continue;
+        }
+        // the file is shared with the file store, a copy is made to keep the store untouched
+        if metadata.is_file() && metadata.nlink() > 1 {
+            unshare_file(entry.path())?;
+        }
+        let mode = metadata.permissions().mode();
+        let permissions = if entry.file_type().is_dir() {
+            if writable && mode & 0o200 != 0 {
+                // SAFETY: getuid has no preconditions and it cannot fail.
+                let current_uid = unsafe { libc::getuid() };
+                format!("u:{uid}:rwx,d:u:{uid}:rwx,d:u:{current_uid}:rwx")
+            } else {
+                format!("u:{}:r-x", uid)
+            }
+        } else {
+            let bit = |mask: u32, c: char| if mode & mask != 0 { c } else { '-' };
+            format!(
+                "u:{}:{}{}{}",
+                uid,
+                bit(0o400, 'r'),
+                bit(0o200, 'w'),
+                bit(0o100, 'x')
+            )
+        };
+        by_permissions
+            .entry(permissions)
+            .or_default()
+            .push(entry.into_path());
+    }
+    for (permissions, paths) in by_permissions {
+        for paths in paths.chunks(256) {
+            let output = Command::new("setfacl")
+                .arg("-m")
+                .arg(&permissions)
+                .arg("--")
+                .args(paths)
+                .output()
+                .context("Cannot execute setfacl, is the acl package installed?")?;
+            if !output.status.success() {
+                bail!(
+                    "Failed to grant access to {} to the isolate box: {}",
+                    path.display(),
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Replace the file at `path` with a copy of it, so that it no longer shares its inode (and its
+/// permissions) with the other hardlinks.
+fn unshare_file(path: &Path) -> Result<(), Error> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid path: {}", path.display()))?;
+    let mut copy_name = std::ffi::OsString::from(".tm-copy-");
+    copy_name.push(file_name);
+    let copy = path.with_file_name(copy_name);
+    std::fs::copy(path, &copy).with_context(|| format!("Failed to copy {}", path.display()))?;
+    std::fs::rename(&copy, path)
+        .with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+/// Parse the meta file written by isolate after an execution.
+fn parse_meta(meta: &str) -> Result<SandboxExecutionResult, Error> {
+    let meta: HashMap<&str, &str> = meta
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .collect();
+    let get_f64 = |key: &str| -> Result<f64, Error> {
+        match meta.get(key) {
+            Some(value) => value
+                .parse()
+                .with_context(|| format!("Invalid {} in isolate meta file", key)),
+            None => Ok(0.0),
+        }
+    };
+    let get_i32 = |key: &str| -> Result<i32, Error> {
+        match meta.get(key) {
+            Some(value) => value
+                .parse()
+                .with_context(|| format!("Invalid {} in isolate meta file", key)),
+            None => Ok(0),
+        }
+    };
+    let status = match meta.get("status").copied() {
+        None => ExitStatus::ExitCode(get_i32("exitcode")?),
+        Some("RE") => ExitStatus::ExitCode(get_i32("exitcode")?),
+        Some("SG") => ExitStatus::Signal(get_i32("exitsig")?),
+        Some("TO") => ExitStatus::Killed,
+        Some("XX") => bail!(
+            "isolate internal error: {}",
+            meta.get("message").copied().unwrap_or_default()
+        ),
+        Some(status) => bail!("Unknown status in isolate meta file: {}", status),
+    };
+    // the memory is reported in KiB, prefer the one of the control group if available
+    let memory = match meta.get("cg-mem") {
+        Some(_) => get_f64("cg-mem")?,
+        None => get_f64("max-rss")?,
+    };
+    Ok(SandboxExecutionResult {
+        status,
+        resource_usage: ResourceUsage {
+            memory_usage: memory as u64 * 1024,
+            // isolate reports only the total cpu time
+            user_cpu_time: get_f64("time")?,
+            system_cpu_time: 0.0,
+            wall_time_usage: get_f64("time-wall")?,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_meta_exit_code() {
+        let meta = "time:0.125\ntime-wall:0.250\nmax-rss:2048\nexitcode:0\n";
+        let result = parse_meta(meta).unwrap();
+        assert!(matches!(result.status, ExitStatus::ExitCode(0)));
+        assert_eq!(result.resource_usage.memory_usage, 2048 * 1024);
+        assert_eq!(result.resource_usage.user_cpu_time, 0.125);
+        assert_eq!(result.resource_usage.system_cpu_time, 0.0);
+        assert_eq!(result.resource_usage.wall_time_usage, 0.25);
+    }
+
+    #[test]
+    fn test_parse_meta_runtime_error() {
+        let meta = "status:RE\nexitcode:3\nmessage:Exited with error status 3\n";
+        let result = parse_meta(meta).unwrap();
+        assert!(matches!(result.status, ExitStatus::ExitCode(3)));
+    }
+
+    #[test]
+    fn test_parse_meta_signal() {
+        let meta = "status:SG\nexitsig:11\nmessage:Caught fatal signal 11\n";
+        let result = parse_meta(meta).unwrap();
+        assert!(matches!(result.status, ExitStatus::Signal(11)));
+    }
+
+    #[test]
+    fn test_parse_meta_timeout() {
+        let meta = "status:TO\ntime:1.001\ntime-wall:1.050\nmessage:Time limit exceeded\n";
+        let result = parse_meta(meta).unwrap();
+        assert!(matches!(result.status, ExitStatus::Killed));
+        assert_eq!(result.resource_usage.user_cpu_time, 1.001);
+    }
+
+    #[test]
+    fn test_parse_meta_cgroup_memory() {
+        let meta = "max-rss:1024\ncg-mem:4096\nexitcode:0\n";
+        let result = parse_meta(meta).unwrap();
+        assert_eq!(result.resource_usage.memory_usage, 4096 * 1024);
+    }
+
+    #[test]
+    fn test_parse_meta_internal_error() {
+        let error = parse_meta("status:XX\nmessage:Cannot run proxy\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "isolate internal error: Cannot run proxy"
+        );
+    }
+
+    #[test]
+    fn test_parse_meta_unknown_status() {
+        let error = parse_meta("status:ZZ\n").unwrap_err();
+        assert_eq!(error.to_string(), "Unknown status in isolate meta file: ZZ");
+    }
+
+    #[test]
+    fn test_parse_meta_malformed_lines() {
+        // the lines without a colon are ignored
+        let meta = "garbage\n\nexitcode:0\n";
+        let result = parse_meta(meta).unwrap();
+        assert!(matches!(result.status, ExitStatus::ExitCode(0)));
+        // the invalid values are reported
+        let error = parse_meta("time:fast\nexitcode:0\n").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid time in isolate meta file");
+        assert!(parse_meta("status:SG\nexitsig:SIGSEGV\n").is_err());
+    }
+}
@@ -2,6 +2,9 @@
 //!
 //! This is both an application and a library, the library can be used to achieve the same
 //! functionalities of the task-maker binary, inside your application.
+//!
+//! The library follows the internals of the binary and has no stability guarantees: for
+//! evaluating custom DAGs from other programs use the `task-maker-api` crate instead.
 #![allow(dead_code)]
 
 #[macro_use]
@@ -12,15 +15,29 @@ extern crate lazy_static;
 extern crate scopeguard;
 
 pub use copy_dag::*;
+pub use estimate::*;
+pub use evaluation::*;
 pub use local::*;
 pub use opt::*;
+pub use repro::*;
 pub use sandbox::*;
 
+pub mod batch;
+pub mod cgroup;
+pub mod container;
 pub mod context;
 pub mod copy_dag;
 pub mod error;
+pub mod estimate;
+pub mod evaluation;
+pub mod history;
+pub mod isolate;
 pub mod local;
 pub mod opt;
+pub mod portable;
+pub mod profiler;
 pub mod remote;
+pub mod repro;
 pub mod sandbox;
 pub mod tools;
+pub mod watch;
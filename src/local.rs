@@ -1,10 +1,18 @@
 use anyhow::{bail, Context, Error};
+use itertools::Itertools;
 
+use task_maker_diagnostics::Diagnostic;
+use task_maker_format::ioi::patch_task_yaml_time_limit;
 use task_maker_format::ui::{UIMessage, UI};
+use task_maker_format::{EvaluationConfig, TaskFormat};
 
-use crate::context::RuntimeContext;
+use crate::batch::evaluate_batch;
 use crate::error::NiceError;
+use crate::evaluation::{EvaluationBuilder, EvaluationState};
+use crate::history::record_history;
 use crate::opt::Opt;
+use crate::profiler::Profiler;
+use crate::watch::watch_task;
 
 /// The result of an evaluation.
 pub enum Evaluation {
@@ -33,10 +41,25 @@ pub enum Evaluation {
 pub fn run_evaluation<F>(opt: Opt, on_message: F) -> Result<Evaluation, Error>
 where
     F: FnMut(&mut dyn UI, UIMessage) + Send + 'static,
+{
+    evaluate(&opt, |builder| builder, on_message).map(|(evaluation, _)| evaluation)
+}
+
+/// Like [`run_evaluation`], but also return the final state of the evaluation, if the task has been
+/// evaluated. `configure` can change the builder of the evaluation before it's run.
+pub(crate) fn evaluate<C, F>(
+    opt: &Opt,
+    configure: C,
+    on_message: F,
+) -> Result<(Evaluation, Option<EvaluationState>), Error>
+where
+    C: FnOnce(EvaluationBuilder) -> EvaluationBuilder,
+    F: FnMut(&mut dyn UI, UIMessage) + Send + 'static,
 {
     if opt.exclusive {
         bail!("This option is not implemented yet");
     }
+    opt.ui.check_json_schema_version()?;
 
     // setup the task
     let eval_config = opt.to_config();
@@ -46,25 +69,146 @@ where
     if opt.clean {
         warn!("--clean is deprecated: use `task-maker-tools clear`");
         task.clean().context("Cannot clear the task directory")?;
-        return Ok(Evaluation::Clean);
+        return Ok((Evaluation::Clean, None));
     }
 
-    // setup the configuration and the evaluation metadata
-    let context = RuntimeContext::new(task, &opt.execution, |task, eval| {
-        // build the DAG for the task
-        task.build_dag(eval, &eval_config)
-            .context("Cannot build the task DAG")
-    })?;
+    if opt.time_limit.suggest_time_limit && !matches!(task, TaskFormat::IOI(_)) {
+        bail!("--suggest-time-limit is supported only by IOI tasks");
+    }
+    if opt.history && !matches!(task, TaskFormat::IOI(_)) {
+        bail!("--history is supported only by IOI tasks");
+    }
 
-    // start the execution
-    let executor = context.connect_executor(&opt.execution, &opt.storage)?;
-    let executor = executor.start_ui(&opt.ui.ui, on_message)?;
-    executor.execute()?;
+    let builder = configure(evaluation_builder(opt, task, eval_config)?);
+    if opt.check_dag {
+        builder.check_dag()?;
+        return Ok((Evaluation::Done, None));
+    }
+    if opt.estimate {
+        builder.estimate()?.print();
+        return Ok((Evaluation::Done, None));
+    }
+
+    let profiler = Profiler::start(&opt.execution)?;
+    let state = builder.run_with(on_message)?;
+    if let Some(profiler) = profiler {
+        profiler.finish()?;
+    }
+    if opt.history {
+        record_history(&opt.storage, &state)?;
+    }
+    check_denied_warnings(opt, &state)?;
+    check_exit_policy(opt, &state)?;
 
-    Ok(Evaluation::Done)
+    Ok((Evaluation::Done, Some(state)))
+}
+
+/// Prepare the evaluation of a task with the options of the command line.
+pub(crate) fn evaluation_builder(
+    opt: &Opt,
+    task: TaskFormat,
+    eval_config: EvaluationConfig,
+) -> Result<EvaluationBuilder, Error> {
+    if (!opt.filter.subtask.is_empty()
+        || !opt.filter.testcase.is_empty()
+        || !opt.filter.only_tags.is_empty())
+        && !matches!(task, TaskFormat::IOI(_))
+    {
+        bail!("--subtask, --testcase and --only-tags are supported only by IOI tasks");
+    }
+
+    let mut builder = EvaluationBuilder::new(task, eval_config)
+        .execution_opt(opt.execution.clone())
+        .storage_opt(opt.storage.clone())
+        .ui(opt.ui.ui.clone());
+    if let Some(execution) = &opt.print_repro {
+        builder = builder.print_repro(execution);
+    }
+    if opt.time_limit.suggest_time_limit {
+        builder = builder.final_diagnostics(suggest_time_limit(opt));
+    }
+    Ok(builder)
+}
+
+/// Fail if the evaluation emitted some warnings denied by `--deny`.
+pub(crate) fn check_denied_warnings(opt: &Opt, state: &EvaluationState) -> Result<(), Error> {
+    let Some(policy) = &opt.deny else {
+        return Ok(());
+    };
+    let denied = policy.denied_warnings(state.diagnostics());
+    if denied.is_empty() {
+        return Ok(());
+    }
+    let list = denied
+        .iter()
+        .map(|diagnostic| match diagnostic.category() {
+            Some(category) => format!("  [{}] {}", category, diagnostic.message()),
+            None => format!("  {}", diagnostic.message()),
+        })
+        .join("\n");
+    bail!("{} warnings are denied by --deny:\n{}", denied.len(), list);
+}
+
+/// Fail if the final state of the evaluation matches some conditions selected by `--exit-on`.
+pub(crate) fn check_exit_policy(opt: &Opt, state: &EvaluationState) -> Result<(), Error> {
+    let Some(policy) = &opt.exit_on else {
+        return Ok(());
+    };
+    let failures = state.exit_failures(policy);
+    if failures.is_empty() {
+        return Ok(());
+    }
+    let list = failures
+        .iter()
+        .map(|failure| format!("  {}", failure))
+        .join("\n");
+    bail!("The evaluation failed because of --exit-on:\n{}", list);
+}
+
+/// Suggest the time limit from the reference solutions with a diagnostic, patching the task.yaml if
+/// asked to.
+fn suggest_time_limit(opt: &Opt) -> impl FnOnce(&EvaluationState) -> Vec<Diagnostic> {
+    let policy = opt.time_limit.policy();
+    let write_time_limit = opt.time_limit.write_time_limit;
+    let dry_run = opt.execution.dry_run;
+    move |state| {
+        let EvaluationState::IOI(state) = state else {
+            return vec![];
+        };
+        let Some(suggestion) = state.suggest_time_limit(&policy) else {
+            return vec![Diagnostic::warning(
+                "No solution has been accepted on all the testcases, cannot suggest a time limit",
+            )];
+        };
+        let diagnostic = suggestion.diagnostic(state);
+        if !write_time_limit {
+            return vec![diagnostic];
+        }
+        if dry_run {
+            return vec![diagnostic
+                .with_help("Not writing the time limit to task.yaml because of --dry-run")];
+        }
+        match patch_task_yaml_time_limit(&state.task.path, suggestion.time_limit) {
+            Ok(path) => {
+                vec![diagnostic.with_help(format!("Time limit written to {}", path.display()))]
+            }
+            Err(e) => vec![
+                diagnostic,
+                Diagnostic::error(format!("Failed to write the time limit: {:?}", e)),
+            ],
+        }
+    }
 }
 
 /// Entry point of the local execution.
 pub fn main_local(opt: Opt) {
+    if opt.find_task.is_batch() {
+        evaluate_batch(opt).nice_unwrap();
+        return;
+    }
+    if opt.watch {
+        watch_task(opt).nice_unwrap();
+        return;
+    }
     run_evaluation(opt, |ui, mex| ui.on_message(mex)).nice_unwrap();
 }
@@ -60,6 +60,75 @@
 //! If you want, for example, just redo the evaluations (maybe for retrying the timings), use
 //! `--no-cache=evaluation`. The available options for `--no-cache` can be found with `--help`.
 //!
+//! The compiled programs are also kept in an artifact cache, keyed by the language, the version of
+//! the compiler, the flags and the sources, so they are reused even with `--no-cache` and with
+//! different compilation limits. When evaluating on a server the artifact cache of the server is
+//! used. Use `--no-artifact-cache` for compiling everything again.
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Share the cache</summary>
+//!
+//! The cache can be shared between more machines (for example the CI and the authors of the same
+//! tasks) using a remote cache as second-level cache:
+//!
+//! ```bash
+//! task-maker-rust --remote-cache http://cache.example.com:8080/task-maker
+//! ```
+//!
+//! When an execution is not in the local cache the remote one is queried, and the new results are
+//! published to it in background. The remote can be an HTTP server supporting `GET`, `HEAD` and `PUT`
+//! (like a WebDAV server) or a shared directory (`--remote-cache file:///mnt/shared/cache`). The
+//! option can also be passed to `task-maker-tools server`.
+//!
+//! A `task-maker-tools server` can also share its cache with the clients that evaluate locally:
+//!
+//! ```bash
+//! task-maker-tools server --remote-cache-addr 0.0.0.0:27184 --client-password secret
+//! task-maker-rust --remote-cache tcp://:secret@server.example.com:27184
+//! ```
+//!
+//! This remote cache is read-only for the clients: it contains only the results of the executions run
+//! by the server itself, so a client cannot alter the results seen by the others.
+//!
+//! A machine without network access can reuse the cache too, carrying it in an archive:
+//!
+//! ```bash
+//! task-maker-tools cache export cache.tmc --max-age 604800
+//! task-maker-tools cache import cache.tmc
+//! ```
+//!
+//! The archive contains the cache entries (optionally only the ones younger than `--max-age`
+//! seconds) together with their output files. Archives are valid only for the same version of
+//! task-maker.
+//!
+//! The content of the cache can be inspected with `task-maker-tools cache ls`, which prints an
+//! identifier, the age, the outcome and the command line of each entry. Entries can be selected
+//! with `--id`, `--command` (a substring of the command line) and `--file` (a prefix of the hash
+//! of an input or output file), and the same filters can be given to `task-maker-tools cache rm`
+//! for removing only some entries, for example the results of a broken checker:
+//!
+//! ```bash
+//! task-maker-tools cache ls --command checker
+//! task-maker-tools cache rm --command checker
+//! ```
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Estimate the cost of the evaluation</summary>
+//!
+//! Before starting a long evaluation you can check how much of it is already in the cache:
+//!
+//! ```bash
+//! task-maker-rust --estimate
+//! ```
+//!
+//! The executions that would run are counted by tag, and their duration is estimated from the previous
+//! executions recorded in the cache. This helps deciding whether to evaluate locally or on a remote
+//! server.
+//!
 //! </details>
 //!
 //! <details>
@@ -75,6 +144,144 @@
 //! Note that you may or may not specify the folder of the solution (sol/ or solution/). You can
 //! also specify only the prefix of the name of the solutions you want to check.
 //!
+//! For IOI tasks you can also evaluate only some subtasks or testcases, only the needed testcases are
+//! generated:
+//!
+//! ```bash
+//! task-maker-rust sol1.cpp --subtask 2 --subtask 3
+//! ```
+//!
+//! The testcases can also be selected by tag: in gen/GEN put a line like
+//! `# @tags: tree, line-graph` before a testcase to tag it, then evaluate only the testcases with
+//! at least one of the tags:
+//!
+//! ```bash
+//! task-maker-rust sol1.cpp --only-tags tree
+//! ```
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Multi-file solutions</summary>
+//!
+//! A solution can also be a directory with many source files, if it contains a `build.yaml`
+//! with the shell command that compiles it:
+//!
+//! ```yaml
+//! build: g++ -O2 -o solution main.cpp lib.cpp
+//! # optional, the path of the compiled executable, by default `solution`
+//! executable: solution
+//! ```
+//!
+//! Without a `build.yaml` the directory is compiled by running `make`, which must produce
+//! `solution`. All the files in the directory are used for the compilation, so changing any of
+//! them recompiles the solution. The graders of the task are available to the build in the
+//! `graders/` directory (e.g. `g++ -o solution main.cpp graders/grader.cpp`), and the symbolic
+//! links are not allowed. The `@check` comments of the solution go in its `build.yaml` or
+//! `Makefile`.
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Complexity of the solutions</summary>
+//!
+//! A solution can declare its intended complexity with a comment like `// @complexity: O(N log N)`.
+//! The complexity is shown next to the solution in the results, and `--suggest-time-limit` groups
+//! the solutions by complexity, printing the time used by each class and whether it fits in the
+//! suggested time limit.
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Duplicated solutions</summary>
+//!
+//! In IOI tasks the solutions with the same source file, like the copies of the official
+//! solution, are evaluated only once, and their results are shown for all of them with a note.
+//! The solutions are compared only with the ones in the same language. With
+//! `--dedup-solutions normalized` also the solutions that differ only in the comments, in the
+//! empty lines and in the trailing whitespace are evaluated once, while `--dedup-solutions off`
+//! evaluates all of them.
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Forbidden constructs</summary>
+//!
+//! The contest rules may prohibit some functions or libraries. The strings that the solutions of a
+//! IOI task cannot contain are listed in `task.yaml`, and a sanity check warns about each solution
+//! that uses them, pointing at the first occurrence:
+//! ```yaml
+//! forbidden_constructs:
+//!   - "#include <thread>"
+//!   - "system("
+//! ```
+//!
+//! The results also show the size and the number of lines of each solution, and the number of
+//! warnings emitted by the compiler for each source file.
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Time limits of the generators</summary>
+//!
+//! For IOI tasks the generator, the validator and the official solution producing the output files
+//! are stopped when they run for too long: by default after 60 seconds for the generation of the
+//! input and of the output files, and after 30 seconds for the validation. The limits can be
+//! changed in `task.yaml`, where 0 means no limit:
+//!
+//! ```yaml
+//! generation_time_limit: 120
+//! validation_time_limit: 10
+//! output_generation_time_limit: 0
+//! ```
+//!
+//! A single testcase can have its own limit, used for all of them, with a line like
+//! `# @timeout: 300` before it in gen/GEN.
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Tasks without generators</summary>
+//!
+//! An IOI task can be made only of its input files, without a generator: the files in `input/`
+//! are used as they are, and they are split in subtasks by a gen/GEN with only `#COPY:` lines.
+//! When the output files in `output/` are already correct, `--trust-outputs` uses them instead of
+//! running the official solution, which can also be missing:
+//!
+//! ```bash
+//! task-maker-rust --trust-outputs
+//! ```
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Two steps tasks</summary>
+//!
+//! In a task with `two_steps: true` in `task.yaml` each solution is run twice per testcase, like
+//! the TwoSteps tasks of CMS: first with `0` as argument, reading the input file, then with `1` as
+//! argument, reading the output of the first step instead of the input file. The output of the
+//! second step is checked like in a batch task. The official solution is run in the same way for
+//! generating the output files, and the grader (`sol/grader.*`) usually calls the right function
+//! of the solution according to the argument.
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Localize the messages of the checker</summary>
+//!
+//! The messages of the checker can be shown with different verdicts, for example translated in
+//! the language of the contest, adding a `checker_messages` map to `task.yaml`, or to
+//! `contest.yaml` for all the tasks of the contest (the ones of the task take precedence):
+//!
+//! ```yaml
+//! checker_messages:
+//!   Output is correct: Risposta corretta
+//!   Output is incorrect: Risposta errata
+//! ```
+//!
+//! The verdicts are shown in the UIs, while the JSON output (`--ui json`) keeps the original
+//! message in `message` and adds the verdict in `verdict`.
+//!
 //! </details>
 //!
 //! <details>
@@ -90,6 +297,71 @@
 //! </details>
 //!
 //! <details>
+//! <summary>Evaluate more tasks</summary>
+//!
+//! More tasks can be evaluated one after the other, sharing the same executor (local or remote), by
+//! repeating `--task-dir` or by listing the task directories in a file, one per line:
+//!
+//! ```bash
+//! task-maker-rust --task-dir ~/tasks/poldo --task-dir ~/tasks/tree
+//! task-maker-rust --task-list archive.txt --ui print
+//! ```
+//!
+//! The paths in the list are relative to its directory, the empty lines and the lines starting with
+//! `#` are ignored. A task that fails doesn't stop the others, and at the end a summary with the
+//! scores of the solutions of all the tasks is printed (with `--ui json`, a final `BatchSummary`
+//! message). This is useful for checking an entire archive of past tasks after upgrading
+//! task-maker.
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Fail on warnings</summary>
+//!
+//! In CI it's useful to make the evaluation fail when the task has some problems. With `--deny` the
+//! warnings make task-maker exit with an error at the end of the evaluation, either all of them or
+//! only the ones of some categories:
+//!
+//! ```bash
+//! task-maker-rust --deny warnings --ui print
+//! task-maker-rust --deny statement,attachments --ui print
+//! ```
+//!
+//! The categories are `task`, `statement`, `generation`, `solutions`, `checker`, `scoring`,
+//! `attachments` and `deprecation`. With more tasks, a task with denied warnings is marked as
+//! failed in the summary.
+//!
+//! With `--exit-on` the final results of the evaluation decide the exit code, so that the scripts
+//! don't have to parse the output of task-maker. The conditions are `compilation` (a compilation
+//! failed), `official-solution` (the official solution failed on a testcase), `sanity` (a sanity
+//! check emitted an error) and `scores` (a solution doesn't match its `@check` comments), or `all`:
+//!
+//! ```bash
+//! task-maker-rust --exit-on all --ui print
+//! task-maker-rust --exit-on compilation,scores --ui print
+//! ```
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Track the evaluations over time</summary>
+//!
+//! With `--history` the results of the evaluation are appended to the history of the task, kept
+//! next to the cache: the score of each solution, and the verdict, time and memory of each
+//! testcase, with the git commit of the task (marked with `*` when there are uncommitted changes).
+//!
+//! ```bash
+//! task-maker-rust --history
+//! task-maker-tools history --last 5
+//! ```
+//!
+//! The `history` tool prints the trend of the solutions in the last evaluations, and exits with
+//! an error if the last one has some regressions with respect to the previous one: a lower score,
+//! a worse verdict, or a testcase using more time or memory than `--threshold` (20% by default).
+//!
+//! </details>
+//!
+//! <details>
 //! <summary>Extracting executable files</summary>
 //!
 //! All the compiled files are kept in an internal folder but if you want to use them, for example
@@ -103,6 +375,72 @@
 //! </details>
 //!
 //! <details>
+//! <summary>Keep the artifacts of the evaluation</summary>
+//!
+//! For debugging a solution you can keep all the files of its evaluation in a directory, organized by
+//! solution, subtask and testcase:
+//!
+//! ```bash
+//! task-maker-rust --keep-artifacts artifacts/
+//! ```
+//!
+//! For each testcase the input file, the output of the solution, the correct output, the output of the
+//! checker and the result of each execution (with its status and the used resources) are kept, for
+//! example in `artifacts/sol/sol.cpp/subtask1/testcase3/`, where `sol/sol.cpp` is the path of the
+//! solution inside the task directory. This is available only for IOI tasks.
+//!
+//! The outputs of the executions are captured only up to a limit. For the full standard output
+//! and standard error use also `--keep-full-output`: they are stored in the file store and their
+//! keys are in the results of the executions. The file with key `aabbcc...` is at
+//! `<store-dir>/store/aa/bb/aabbcc...` until the store is flushed.
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Reproduce the sandbox of an execution</summary>
+//!
+//! When an execution behaves differently than expected you can run its sandbox again by hand. The
+//! sandboxes of the executions whose description contains the given string are kept, and at the end of
+//! the evaluation the commands for running them again are printed:
+//!
+//! ```bash
+//! task-maker-rust --print-repro "sol.cpp on testcase 3"
+//! ```
+//!
+//! The printed command runs the sandbox with the same configuration (executable, arguments, mounts and
+//! limits) and prints its raw result. If the result came from the cache the sandbox is not available,
+//! run with `--no-cache` to evaluate it again. In a remote evaluation the sandbox is kept on the
+//! worker that ran it.
+//!
+//! Inside the sandbox only `PATH` is taken from the environment of the worker, all the other
+//! environment variables are set by task-maker, so they are the same in local and remote
+//! evaluations. For debugging you can set more variables in all the executions, they are part of
+//! the cache key:
+//!
+//! ```bash
+//! task-maker-rust --env MALLOC_CHECK_=3 --env DEBUG=1
+//! ```
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Create a new task</summary>
+//!
+//! The skeleton of a new IOI task, with a generator, a validator, a checker, the solutions in C++
+//! and Python and a statement, can be created with:
+//! ```bash
+//! task-maker-tools new-task my_task --type batch
+//! ```
+//!
+//! The supported types are `batch`, `communication` (with a manager talking to the solution through
+//! stdin/stdout) and `output-only`. The new task can be evaluated right away.
+//!
+//! A Terry task can be created with `--format terry`: its managers follow the protocol of terry,
+//! and they are documented by the comments at their top.
+//!
+//! </details>
+//!
+//! <details>
 //! <summary>Statement</summary>
 //!
 //! If you don't want to build the statement files (and the booklet) just pass `--no-statement`.
@@ -119,6 +457,19 @@
 //!
 //! This tool can also be used to build the contest's booklet.
 //!
+//! The table of the subtasks doesn't need to be written by hand: the `\subtasktable` macro in the
+//! statement is replaced with a table of the subtasks of `gen/GEN` (or `cases.gen`), with their
+//! scores and constraints, so it's always in sync with the task.
+//!
+//! The auxiliary files of `latexmk` are kept in the storage directory and reused by the next
+//! compilation of the same booklet, so rebuilding it after editing the statement is faster.
+//!
+//! The files of a Terry task inside `public/`, like the sample inputs and the images, are published
+//! together with the statement, which links them with `{{baseURI}}name`. With
+//! `copy_public_files: true` in `task.yaml` they are copied inside `statement/`, where terry
+//! serves them, and `--clean` removes the copies. A sanity check reports the files linked by the
+//! statement that don't exist, and their hashes are part of `task-maker-tools task-info`.
+//!
 //! </details>
 //!
 //! <details>
@@ -135,6 +486,106 @@
 //! </details>
 //!
 //! <details>
+//! <summary>Debug a solution inside the sandbox</summary>
+//!
+//! If a solution behaves differently inside the sandbox you can open a shell in the same sandbox used
+//! for evaluating it on a testcase, with the same files, mounts and environment:
+//! ```bash
+//! task-maker-tools sandbox-shell sol/solution.cpp --testcase 3
+//! ```
+//!
+//! The time and memory limits of the evaluation are applied to the shell only with `--limits`. The
+//! shell runs on its own pseudo terminal and its sandbox is removed when it exits.
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Profile the memory usage of a solution</summary>
+//!
+//! To find where a solution allocates its memory, run it also under valgrind's massif on a
+//! testcase:
+//! ```bash
+//! task-maker --solution sol/solution.cpp --testcase 3 --profile massif
+//! ```
+//!
+//! The solutions are profiled only on the last evaluated testcase, usually the largest one, unless
+//! `--profile-all-testcases` is passed. The peak heap usage and the top allocation sites are shown
+//! at the end of the evaluation. The profile is written in `bin/profiles/`
+//! (`bin/profiles/sol/solution.cpp.testcase3.massif`), together with the heap at the peak in the
+//! folded format of the flame graph tools (`bin/profiles/sol/solution.cpp.testcase3.folded`, e.g.
+//! for `flamegraph.pl --countname bytes`). With `--profile time`, or if valgrind is not installed,
+//! only the peak resident memory is measured with `/usr/bin/time -v`. The profiled executions don't
+//! affect the scores.
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Skip the subtasks whose dependencies failed</summary>
+//!
+//! A subtask can depend on other subtasks (`#STDEP: name` in `gen/GEN`, `:STDEP name` in
+//! `cases.gen`), including their testcases. With `--skip-dependent-subtasks`, when a solution
+//! scores zero on a testcase of a subtask, the testcases of the subtasks depending on it are not
+//! evaluated anymore, since they are worth zero anyway:
+//! ```bash
+//! task-maker --skip-dependent-subtasks
+//! ```
+//!
+//! The skipped testcases are shown as skipped and scored zero, so the scores don't change. Only
+//! the testcases that haven't started yet are skipped, and since the testcases are evaluated in
+//! order the dependencies usually come first. It works with the batch tasks whose subtask score is
+//! the minimum of the testcase scores.
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Evaluate the solutions quickly on a few testcases</summary>
+//!
+//! When a task has many large testcases, evaluating all the solutions takes a while. With
+//! `--quick` the solutions are evaluated only on the first testcase of each subtask and on the
+//! last testcase of the task, which is usually the largest one:
+//! ```bash
+//! task-maker --quick
+//! ```
+//!
+//! All the testcases are still generated and validated, and the other testcases are shown as
+//! skipped. The scores are partial, since each subtask is scored only on its evaluated testcases,
+//! and they are marked as such at the end of the evaluation. It works only with the IOI tasks.
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Evaluate the randomized solutions with more seeds</summary>
+//!
+//! A randomized solution may pass a testcase only by chance. With `--seed-runs` each solution is
+//! evaluated more times on each testcase, each time with a different seed in the `TASK_MAKER_SEED`
+//! environment variable (1, 2, 3, ...):
+//! ```bash
+//! task-maker --seed-runs 5 sol/randomized.cpp
+//! ```
+//!
+//! By default the testcase gets the verdict of the worst run, use `--seed-aggregation best` or
+//! `--seed-aggregation median` to change it. The scores of all the runs are shown below each
+//! testcase, with a `*` on the selected one. A failing run can be reproduced by setting
+//! `TASK_MAKER_SEED` by hand. Only for the batch tasks.
+//!
+//! </details>
+//!
+//! <details>
+//! <summary>Grade the submissions of a Terry task</summary>
+//!
+//! The output files submitted by the contestants can be graded without the terry web stack. Put the
+//! submissions in a directory with a subdirectory for each user, containing `seed.txt` (the seed of
+//! the input file) and `output.txt` (the submitted output), then from the task directory run:
+//! ```bash
+//! task-maker-tools terry-grade submissions/ --format csv --output ranking.csv
+//! ```
+//!
+//! The input files are generated again from the seeds, and the ranking of the users is written in CSV
+//! or JSON format.
+//!
+//! </details>
+//!
+//! <details>
 //! <summary>Remote evaluation</summary>
 //!
 //! On a server (a machine accessible from clients and workers) run
@@ -160,6 +611,90 @@
 //! task-maker-rust --evaluate-on server_addr
 //! ```
 //!
+//! The option can be repeated for splitting the evaluation among more servers, each one running its
+//! own workers.
+//!
+//! The workers measure the speed of their machine when they start. If the worker machines are
+//! different, the time limits can be scaled according to their speed with `--time-scaling`, so that
+//! the verdicts are the same on all of them:
+//! ```bash
+//! task-maker-rust --evaluate-on server_addr --time-scaling all
+//! ```
+//!
+//! With `--time-scaling slower` only the workers slower than the reference machine get more time.
+//!
+//! When an execution fails because of the sandbox of a worker (an internal error), its logs can be
+//! fetched from the worker and printed at the end of the evaluation with
+//! `--fetch-sandbox-logs-on-failure`. The workers keep only the logs of the latest failures.
+//!
+//! An evaluation running on a server can be cancelled with
+//! ```bash
+//! task-maker-tools server-cancel server_addr evaluation_id
+//! ```
+//! The id of the evaluation is printed in the log of the server when its client connects; without
+//! it the evaluations running on the workers are listed. The executions of the evaluation are
+//! killed, its workers are released and its client exits with an error.
+//!
+//! With `--stream-stderr` the workers send to the client what the running executions write to
+//! their standard error, and the curses UI shows its last lines in a dedicated block. This is
+//! useful for watching the progress of a slow generator, also in the local evaluations. The output
+//! is truncated to limit the traffic, so some lines may not be shown.
+//!
+//! The workers check the versions of their compilers and interpreters when they start. The version
+//! used by each compilation is shown at the end of the evaluation, and it's part of the key of the
+//! cache: a result is not reused with a different compiler. The server warns when its workers have
+//! different versions of the same compiler.
+//!
+//! Some executions may need resources that are not available on all the workers, like a GPU or a
+//! specific interpreter. The workers tell the server which resources they provide, and the
+//! executions that require some resources are run only by the workers that provide all of them:
+//! ```bash
+//! task-maker-tools worker --resource gpu --resource python3.11 server_addr num
+//! ```
+//! A task requires some resources for all its executions by listing them in `task.yaml`, for
+//! example `required_resources: [gpu]`.
+//!
+//! A task can require a toolchain that is not installed on the workers by setting in `task.yaml`
+//! the OCI image in which its executions are run, for example `container_image: haskell:9.4`.
+//! The images are run with podman, and only by the workers started with `--container-runtime`,
+//! which measure the resources of the containers with cgroup v2 under `--cgroup-root`:
+//! ```bash
+//! task-maker-tools worker --container-runtime /usr/bin/podman \
+//!     --cgroup-root /sys/fs/cgroup/task-maker server_addr num
+//! ```
+//! When the evaluation starts the tag of the image is resolved to the digest it points to, using
+//! the podman of `--container-runtime` or the one in `PATH`, so that the cached results are not
+//! reused after the tag is moved. Without podman on the client, pin the image to a digest in
+//! `task.yaml`, e.g. `container_image: haskell@sha256:...`.
+//!
+//! The workers of a server can be split into named pools, for example to keep some workers free
+//! for the interactive evaluations while others re-judge lots of submissions. A worker joins a pool
+//! when it connects, and a client selects the pool that runs its evaluation:
+//! ```bash
+//! task-maker-tools worker --pool bulk server_addr num
+//! task-maker-rust --evaluate-on server_addr --pool bulk
+//! ```
+//!
+//! The workers that don't join any pool run only the evaluations that don't select a pool.
+//!
+//! The server can start and stop the workers by itself, running a command when too many jobs are
+//! waiting and another one when the workers have been idle for some minutes, for example for
+//! creating and destroying some cloud machines running the workers:
+//! ```bash
+//! task-maker-tools server --spawn-command ./start-vm.sh --spawn-threshold 20 \
+//!     --despawn-command ./stop-vms.sh --despawn-idle-minutes 15
+//! ```
+//!
+//! The commands get the load of the server in the `TM_WAITING_JOBS`, `TM_WORKERS` and
+//! `TM_BUSY_WORKERS` environment variables, and they should return quickly. The spawn command is
+//! not run again for `--spawn-cooldown` seconds, giving the new workers the time for connecting.
+//!
+//! With `--trace trace.json` the servers record how they scheduled the evaluation: when each
+//! execution waited in the queue, which worker ran it and when, and when its files were
+//! transferred. The client writes the trace in the Chrome tracing format, which can be opened with
+//! `chrome://tracing` or https://ui.perfetto.dev for finding the idle workers of a large
+//! evaluation. It works also for the local evaluations.
+//!
 //! </details>
 //!
 //! ### Using docker
@@ -1,15 +1,28 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
 use clap::{ArgAction, Parser};
 use itertools::Itertools;
 
-use task_maker_dag::DagPriority;
+use task_maker_cache::RemoteCache;
+use task_maker_dag::{DagPriority, TimeScaling};
+use task_maker_diagnostics::DenyPolicy;
+#[cfg(feature = "chaos")]
+use task_maker_exec::ChaosConfig;
+use task_maker_format::ioi::{
+    CheckerScorePolicy, ProfilerTool, SeedAggregation, SolutionDedup, TimeLimitPolicy,
+    TimeLimitRounding,
+};
 use task_maker_format::terry::Seed;
+use task_maker_format::ui::ExitPolicy;
 use task_maker_format::{find_task, get_sanity_check_list, TaskFormat};
 use task_maker_format::{EvaluationConfig, VALID_TAGS};
 
+use crate::container::Container;
+use crate::isolate::Isolate;
+use crate::sandbox::{SandboxBackend, ToolsSandboxRunner};
+
 #[derive(Parser, Debug)]
 #[clap(
     name = "task-maker",
@@ -38,19 +51,173 @@ pub struct Opt {
     #[clap(long = "clean")]
     pub clean: bool,
 
+    /// Check that the DAG of the evaluation is valid and exit, without evaluating anything
+    ///
+    /// Missing dependencies and cycles are reported as errors, the provided files that are never
+    /// used as warnings.
+    #[clap(long = "check-dag")]
+    pub check_dag: bool,
+
+    /// Estimate the cost of the evaluation and exit, without evaluating anything
+    ///
+    /// The executions that are not in the cache are counted by tag, and their duration is estimated
+    /// from the resources used by the previous executions recorded in the cache.
+    #[clap(long = "estimate")]
+    pub estimate: bool,
+
+    /// Print the commands for running again by hand the sandboxes of the executions whose
+    /// description contains this string
+    ///
+    /// The sandboxes are kept (like with --keep-sandboxes) and the commands are printed at the end
+    /// of the evaluation. In a remote evaluation the commands work only on the machine of the worker.
+    #[clap(long = "print-repro")]
+    pub print_repro: Option<String>,
+
+    /// Keep running, evaluating again the task every time one of its files changes
+    ///
+    /// Thanks to the cache only what's affected by the change is executed again. After each run the
+    /// score changes with respect to the previous one are printed. The curses UI stays open between
+    /// the runs and shows them at its bottom.
+    #[clap(long = "watch")]
+    pub watch: bool,
+
     #[clap(flatten, next_help_heading = Some("BOOKLET"))]
     pub booklet: BookletOpt,
 
+    /// What to do when a checker outputs a score outside [0, 1]: clamp, fail
+    ///
+    /// With clamp the score is clamped to the valid range (NaN becomes 0) and a warning is emitted,
+    /// with fail the testcase is not scored and an error is emitted.
+    #[clap(long = "checker-score-policy", default_value = "clamp")]
+    pub checker_score_policy: CheckerScorePolicy,
+
+    /// Generate the output files using this solution instead of the official one of the task
+    ///
+    /// Useful for checking that more reference solutions agree. Only for IOI batch tasks; the
+    /// outputs are cached separately for each solution, and they are not written to output/.
+    #[clap(long = "official-solution")]
+    pub official_solution: Option<PathBuf>,
+
+    /// Use the files in output/ as the correct outputs, instead of generating them
+    ///
+    /// Useful for the imported tasks that come with their input and output files: the official
+    /// solution, if any, is evaluated like the other solutions. Only for IOI batch tasks.
+    #[clap(long = "trust-outputs", conflicts_with = "official_solution")]
+    pub trust_outputs: bool,
+
+    /// Warn when the accepted solutions produce different outputs on the same testcase
+    ///
+    /// Useful for the tasks where the expected output is unique: if the checker accepts different
+    /// outputs it may be too permissive. Only for IOI batch tasks, the whitespaces are ignored.
+    #[clap(long = "check-output-determinism")]
+    pub check_output_determinism: bool,
+
+    /// Run the C/C++ solutions also compiled with the address and undefined behavior sanitizers
+    ///
+    /// They are run on the first testcase of each subtask, and the errors found by the sanitizers
+    /// are reported as warnings without affecting the scores. Only for IOI batch tasks.
+    #[clap(long = "sanitize")]
+    pub sanitize: bool,
+
+    /// Run the solutions also under a memory profiler: massif or time
+    ///
+    /// They are run on the last evaluated testcase, usually the largest one, and the peak memory
+    /// is reported without affecting the scores. With massif the profile is written in
+    /// `bin/profiles/`, also in the folded format of the flame graph tools. Without valgrind,
+    /// `/usr/bin/time -v` is used. Only for IOI batch tasks.
+    #[clap(long = "profile", value_name = "TOOL")]
+    pub profile: Option<ProfilerTool>,
+
+    /// Profile the solutions on every evaluated testcase, not only on the last one
+    ///
+    /// Select the testcases with --testcase.
+    #[clap(long = "profile-all-testcases", requires = "profile")]
+    pub profile_all_testcases: bool,
+
+    /// Skip the subtasks whose dependencies are already failed by the solution
+    ///
+    /// A subtask depending on another one includes its testcases, so when a solution scores zero on
+    /// one of them the subtask is worth zero anyway: its remaining testcases are not evaluated and
+    /// are shown as skipped. Only for IOI batch tasks whose subtask score is the minimum of the
+    /// testcases.
+    #[clap(long = "skip-dependent-subtasks")]
+    pub skip_dependent_subtasks: bool,
+
+    /// Evaluate the solutions only on a few testcases, for a fast check of a huge task
+    ///
+    /// The solutions are evaluated on the first testcase of each subtask and on the largest
+    /// testcase of the task: the one with the largest input when all the inputs are static files,
+    /// the last one otherwise. The other testcases are not generated and are shown as skipped, and
+    /// the scores are partial: each subtask is scored only on its evaluated testcases. Only for
+    /// IOI tasks.
+    #[clap(long = "quick")]
+    pub quick: bool,
+
+    /// Evaluate the solutions this many times on each testcase, with different seeds
+    ///
+    /// The seed of each run is in the TASK_MAKER_SEED environment variable of the solution, so the
+    /// randomized solutions can use it. The testcase gets the verdict of the run selected by
+    /// --seed-aggregation, and the results of all the runs are shown. Only for IOI batch tasks.
+    #[clap(
+        long = "seed-runs",
+        value_name = "RUNS",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub seed_runs: Option<usize>,
+
+    /// Which run gives the verdict of the testcase with --seed-runs: worst, best or median
+    #[clap(long = "seed-aggregation", value_name = "RUN", default_value = "worst")]
+    pub seed_aggregation: SeedAggregation,
+
+    /// Evaluate only once the solutions with the same source: off, exact or normalized
+    ///
+    /// With exact the solutions in the same language with identical files are evaluated once, and
+    /// the results are shown for all of them. With normalized also the differences in the comments,
+    /// in the empty lines and in the trailing whitespace are ignored. Only for IOI tasks.
+    #[clap(long = "dedup-solutions", value_name = "MODE", default_value = "exact")]
+    pub dedup_solutions: SolutionDedup,
+
     /// List of sanity checks to skip (--help for the list).
     #[clap(short = 'W', long = "skip-checks", long_help = skip_sanity_checks_long_help())]
     pub skip_sanity_checks: Vec<String>,
 
+    /// Fail when some warnings are emitted: warnings for all of them, or a comma separated list of
+    /// categories
+    ///
+    /// The categories are: task, statement, generation, solutions, checker, scoring, attachments
+    /// and deprecation. The warnings are still shown, and the evaluation fails at the end listing
+    /// the denied ones. Useful in CI, e.g. `--deny warnings` or `--deny statement,attachments`.
+    #[clap(long = "deny", value_name = "WARNINGS")]
+    pub deny: Option<DenyPolicy>,
+
+    /// Fail when the final state of the evaluation matches some conditions: all of them, or a comma
+    /// separated list of conditions
+    ///
+    /// The conditions are: compilation (a compilation failed), official-solution (the official
+    /// solution failed on a testcase), sanity (a sanity check emitted an error) and scores (a
+    /// solution doesn't match the expected results written in its source). The results are still
+    /// shown, and the evaluation fails at the end listing the reasons. Useful in CI, e.g.
+    /// `--exit-on all` or `--exit-on compilation,scores`.
+    #[clap(long = "exit-on", value_name = "CONDITIONS")]
+    pub exit_on: Option<ExitPolicy>,
+
+    /// Append the results of the evaluation to the history of the task
+    ///
+    /// The score of each solution, and the verdict and the resources used on each testcase are
+    /// saved in the storage directory together with the git commit of the task. See the trend and
+    /// the regressions with `task-maker-tools history`. Only for IOI tasks.
+    #[clap(long = "history")]
+    pub history: bool,
+
     #[clap(flatten, next_help_heading = Some("STORAGE"))]
     pub storage: StorageOpt,
 
     #[clap(flatten, next_help_heading = Some("EXECUTION"))]
     pub execution: ExecutionOpt,
 
+    #[clap(flatten, next_help_heading = Some("TIME LIMIT"))]
+    pub time_limit: TimeLimitOpt,
+
     #[clap(flatten, next_help_heading = Some("LOGGING"))]
     pub logger: LoggerOpt,
 }
@@ -65,8 +232,18 @@ pub struct LoggerOpt {
 #[derive(Parser, Debug, Clone)]
 pub struct FindTaskOpt {
     /// Directory of the task
+    ///
+    /// Can be repeated for evaluating more tasks one after the other, sharing the same executor.
+    /// At the end a summary of the scores of all the tasks is printed.
     #[clap(short = 't', long = "task-dir")]
-    pub task_dir: Option<PathBuf>,
+    pub task_dir: Vec<PathBuf>,
+
+    /// File with the list of the directories of the tasks to evaluate, one per line
+    ///
+    /// The paths are relative to the directory of the file, the empty lines and the lines starting
+    /// with # are ignored. The tasks are evaluated like with more --task-dir.
+    #[clap(long = "task-list")]
+    pub task_list: Option<PathBuf>,
 
     /// Look at most for this number of parents for searching the task
     #[clap(long = "max-depth", default_value = "3")]
@@ -80,6 +257,13 @@ pub struct UIOpt {
     /// Note that the JSON api is not stable yet.
     #[clap(long = "ui", default_value = "curses")]
     pub ui: task_maker_format::ui::UIType,
+
+    /// Fail if the JSON UI cannot emit messages compatible with this schema version (MAJOR.MINOR).
+    ///
+    /// Every run of the JSON UI starts with a header containing the version of task-maker and the
+    /// version of the schema of the messages.
+    #[clap(long = "json-schema-version")]
+    pub json_schema_version: Option<String>,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -97,6 +281,15 @@ pub struct ExecutionOpt {
     #[allow(clippy::option_option)]
     pub no_cache: Option<Option<String>>,
 
+    /// Consider stale the cache entries older than this number of seconds, optionally only for the
+    /// executions with a tag (TAG=SECONDS)
+    ///
+    /// The results of the stale entries are used anyway, and their executions are run again in
+    /// background for refreshing the cache, without delaying the results. A local evaluation waits
+    /// for them before exiting. Can be specified more than once.
+    #[clap(long = "cache-ttl")]
+    pub cache_ttl: Vec<String>,
+
     /// Give to the solution some extra time before being killed
     #[clap(long = "extra-time")]
     pub extra_time: Option<f64>,
@@ -105,6 +298,14 @@ pub struct ExecutionOpt {
     #[clap(long = "extra-memory")]
     pub extra_memory: Option<u64>,
 
+    /// Set an environment variable (KEY=VALUE) in all the executions, useful for debugging
+    ///
+    /// Only PATH is taken from the environment of the workers, all the other variables visible
+    /// inside the sandbox are set by task-maker; the ones set with this option override them and
+    /// are part of the cache key. Can be specified more than once.
+    #[clap(long = "env")]
+    pub env: Vec<String>,
+
     /// Copy the executables to the bin/ folder
     #[clap(long = "copy-exe")]
     pub copy_exe: bool,
@@ -113,7 +314,66 @@ pub struct ExecutionOpt {
     #[clap(long = "copy-logs")]
     pub copy_logs: bool,
 
+    /// Copy the files and the results of the evaluation of each solution on each testcase to this
+    /// directory, organized by solution, subtask and testcase
+    #[clap(long = "keep-artifacts")]
+    pub keep_artifacts: Option<PathBuf>,
+
+    /// Store the full stdout and stderr of the executions that capture them, beyond the capture
+    /// limits
+    ///
+    /// They are kept in the file store like the other files, and their keys are in the results of
+    /// the executions (e.g. in the JSON UI and in the results of --keep-artifacts): the file with
+    /// key `aabbcc...` is at `<store-dir>/store/aa/bb/aabbcc...` until it's flushed from the store.
+    #[clap(long = "keep-full-output")]
+    pub keep_full_output: bool,
+
+    /// Fetch from the workers the logs of the sandboxes of the executions that failed with an
+    /// internal error, and show them in the final summary
+    #[clap(long = "fetch-sandbox-logs-on-failure")]
+    pub fetch_sandbox_logs: bool,
+
+    /// Show the context switches, the page faults and the peak of the sampled memory usage of the
+    /// evaluations in the final summary
+    #[clap(long = "verbose-resources")]
+    pub verbose_resources: bool,
+
+    /// Abort the evaluation if the files produced by the executions exceed this total size, in MiB
+    #[clap(long = "max-total-output-size")]
+    pub max_total_output_size: Option<u64>,
+
+    /// Send the small executions with the same limits to the workers in batches of at most this
+    /// size, reducing the round trips with the workers
+    #[clap(long = "batch-size")]
+    pub batch_size: Option<usize>,
+
+    /// Run the executions one at a time, in a stable order
+    ///
+    /// The results are received, and shown by the UI, in the same order on every machine, which is
+    /// useful for comparing the outputs of different runs. The status of the executor is not
+    /// shown. The evaluation cannot be split among more servers.
+    #[clap(long = "deterministic")]
+    pub deterministic: bool,
+
+    /// Stream the standard error of the running executions from the workers, showing its last
+    /// lines in the curses UI
+    ///
+    /// Only what the executions write since the last update, up to 1KiB every half second, is sent
+    /// by the workers, so part of the output may not be shown.
+    #[clap(long = "stream-stderr")]
+    pub stream_stderr: bool,
+
+    /// Write the scheduling trace of the evaluation to this file, in the Chrome tracing format
+    ///
+    /// For each execution it shows when it was waiting in the queue, when its inputs were sent to
+    /// the worker, when it ran and when its outputs were received. The file can be opened with
+    /// chrome://tracing or https://ui.perfetto.dev.
+    #[clap(long = "trace")]
+    pub trace: Option<PathBuf>,
+
     /// Store the DAG in DOT format inside of bin/DAG.dot
+    ///
+    /// The trusted executions, the programs written by the task authors, are drawn in blue.
     #[clap(long = "copy-dag")]
     pub copy_dag: bool,
 
@@ -122,17 +382,192 @@ pub struct ExecutionOpt {
     pub num_cores: Option<usize>,
 
     /// Run the evaluation on a remote server instead of locally
+    ///
+    /// Can be specified more than once, in that case the evaluation is split among the servers.
     #[clap(long = "evaluate-on")]
-    pub evaluate_on: Option<String>,
+    pub evaluate_on: Vec<String>,
+
+    /// Run the evaluation only on the workers of this pool of the remote server
+    ///
+    /// Without this option only the workers that didn't join any pool are used.
+    #[clap(long = "pool")]
+    pub pool: Option<String>,
+
+    /// Do not take the compiled programs from the artifact cache
+    ///
+    /// The compiled programs are stored in the artifact cache, which is used even with --no-cache
+    /// and ignores the time and memory limits of the compilations.
+    #[clap(long = "no-artifact-cache")]
+    pub no_artifact_cache: bool,
 
     /// The name to use for the client in remote executions
     #[clap(long)]
     pub name: Option<String>,
 
+    /// Do not compress the files exchanged with the remote server
+    #[clap(long = "no-compression")]
+    pub no_compression: bool,
+
     /// Priority of the evaluations spawned by this invocation of task-maker; no effect if running
     /// locally.
     #[clap(long, default_value = "0")]
     pub priority: DagPriority,
+
+    /// Scale the time limits according to the speed of the remote workers: none, slower, all
+    ///
+    /// The workers measure their speed relative to a reference machine when they start. With
+    /// `slower` only the workers slower than the reference machine get more time, with `all` the
+    /// faster ones get less time too. The reported times are the ones of the reference machine. No
+    /// effect if running locally.
+    #[clap(long = "time-scaling", default_value = "none")]
+    pub time_scaling: TimeScaling,
+
+    /// Run the sandboxes inside this process instead of spawning task-maker-tools
+    ///
+    /// This way all the work of task-maker happens in a single process, which is useful for
+    /// profiling. Note that the executions cannot be killed before they reach their limits.
+    #[clap(long = "in-process", conflicts_with = "evaluate_on")]
+    pub in_process: bool,
+
+    #[clap(flatten)]
+    pub sandbox: SandboxBackendOpt,
+
+    /// Profile task-maker and write a flamegraph (in SVG format) to this path. Implies --in-process
+    #[cfg(feature = "profiling")]
+    #[clap(long = "flamegraph", conflicts_with = "evaluate_on")]
+    pub flamegraph: Option<PathBuf>,
+
+    /// The failures to inject in the local executor, not available from the command line.
+    #[cfg(feature = "chaos")]
+    #[clap(skip)]
+    pub chaos: ChaosConfig,
+}
+
+impl Default for ExecutionOpt {
+    /// The options used when none is specified in the command line. Unlike parsing an empty
+    /// command line, the environment variables are ignored.
+    fn default() -> Self {
+        ExecutionOpt {
+            keep_sandboxes: false,
+            dry_run: false,
+            no_cache: None,
+            cache_ttl: vec![],
+            extra_time: None,
+            extra_memory: None,
+            env: vec![],
+            copy_exe: false,
+            copy_logs: false,
+            keep_artifacts: None,
+            keep_full_output: false,
+            fetch_sandbox_logs: false,
+            verbose_resources: false,
+            max_total_output_size: None,
+            batch_size: None,
+            deterministic: false,
+            stream_stderr: false,
+            trace: None,
+            copy_dag: false,
+            num_cores: None,
+            evaluate_on: vec![],
+            pool: None,
+            no_artifact_cache: false,
+            name: None,
+            no_compression: false,
+            priority: 0,
+            time_scaling: TimeScaling::None,
+            in_process: false,
+            sandbox: SandboxBackendOpt::default(),
+            #[cfg(feature = "profiling")]
+            flamegraph: None,
+            #[cfg(feature = "chaos")]
+            chaos: ChaosConfig::default(),
+        }
+    }
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct SandboxBackendOpt {
+    /// Which sandbox backend to use: tabox, cgroup, isolate, portable
+    ///
+    /// With cgroup the memory and the cpu time are measured using cgroup v2, accounting all the
+    /// processes of an execution, instead of only the main one. With isolate the executions are run
+    /// using the sandbox of CMS, which must be installed and configured. With portable the
+    /// executions are only limited using rlimits, without any isolation (on macOS sandbox-exec
+    /// denies them the network and the writes outside of the sandbox): use it only with trusted
+    /// code, on the systems where the other backends are not available.
+    #[clap(
+        long = "sandbox-backend",
+        env = "TM_SANDBOX_BACKEND",
+        default_value = "tabox"
+    )]
+    pub sandbox_backend: SandboxBackend,
+
+    /// The cgroup where to create the cgroups of the sandboxes, when using the cgroup backend or
+    /// the containers
+    ///
+    /// It must be delegated to the current user, and the memory and pids controllers must be
+    /// available.
+    #[clap(long = "cgroup-root", default_value = "/sys/fs/cgroup/task-maker")]
+    pub cgroup_root: PathBuf,
+
+    /// Path to the isolate executable, when using the isolate backend
+    #[clap(
+        long = "isolate-path",
+        env = "TM_ISOLATE_PATH",
+        default_value = "isolate"
+    )]
+    pub isolate_path: PathBuf,
+
+    /// The id of the first isolate box to use
+    ///
+    /// Different instances of task-maker running on the same machine must use disjoint ranges of
+    /// boxes.
+    #[clap(
+        long = "isolate-first-box-id",
+        env = "TM_ISOLATE_FIRST_BOX_ID",
+        default_value = "0"
+    )]
+    pub isolate_first_box_id: u32,
+
+    /// The number of isolate boxes to use, it limits the number of concurrent executions
+    #[clap(long = "isolate-num-boxes", default_value = "100")]
+    pub isolate_num_boxes: u32,
+
+    /// The uid of the user of the first isolate box, as set by `first_uid` in the configuration of
+    /// isolate
+    ///
+    /// The executions inside the box with id `i` are run by the user `isolate-first-uid + i`, and
+    /// only that user is granted access to the files of the execution.
+    #[clap(long = "isolate-first-uid", default_value = "60000")]
+    pub isolate_first_uid: u32,
+
+    /// Run isolate with its control groups support, measuring the memory of all the processes
+    #[clap(long = "isolate-cg")]
+    pub isolate_cg: bool,
+
+    /// Path to podman, for running the executions of the tasks that require a container image
+    ///
+    /// The images are pulled the first time they are used. The resources used by the containers
+    /// are measured with cgroup v2, creating their cgroups inside --cgroup-root.
+    #[clap(long = "container-runtime", env = "TM_CONTAINER_RUNTIME")]
+    pub container_runtime: Option<PathBuf>,
+}
+
+impl Default for SandboxBackendOpt {
+    /// The options used when none is specified in the command line. Unlike parsing an empty
+    /// command line, the environment variables are ignored.
+    fn default() -> Self {
+        SandboxBackendOpt {
+            sandbox_backend: SandboxBackend::Tabox,
+            cgroup_root: PathBuf::from("/sys/fs/cgroup/task-maker"),
+            isolate_path: PathBuf::from("isolate"),
+            isolate_first_box_id: 0,
+            isolate_num_boxes: 100,
+            isolate_first_uid: 60000,
+            isolate_cg: false,
+            container_runtime: None,
+        }
+    }
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -148,6 +583,27 @@ pub struct StorageOpt {
     /// When the storage is flushed, this is the new maximum size, in MiB.
     #[clap(long = "min-cache", default_value = "2048")]
     pub min_cache: u64,
+
+    /// Remote cache to query when an execution is not in the local cache
+    ///
+    /// It can be a shared directory (file:///path/to/dir) or an HTTP server supporting GET, HEAD and
+    /// PUT (http://host:port/prefix). The new cache entries are published to it in background. It
+    /// can also be a task-maker server started with --remote-cache-addr
+    /// (tcp://[:password@]host:port), which is read-only.
+    #[clap(long = "remote-cache")]
+    pub remote_cache: Option<RemoteCache>,
+}
+
+impl Default for StorageOpt {
+    /// The options used when none is specified in the command line.
+    fn default() -> Self {
+        StorageOpt {
+            store_dir: None,
+            max_cache: 3072,
+            min_cache: 2048,
+            remote_cache: None,
+        }
+    }
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -163,6 +619,28 @@ pub struct FilterOpt {
     /// The solution can reside anywhere in the filesystem.
     #[clap(long, short)]
     pub solution: Vec<PathBuf>,
+
+    /// Evaluate only the testcases of the subtask with this id
+    ///
+    /// Only the needed testcases are generated, the sanity checks and the booklets are skipped.
+    /// Can be specified more than once.
+    #[clap(long)]
+    pub subtask: Vec<u32>,
+
+    /// Evaluate only the testcase with this id
+    ///
+    /// Only the needed testcases are generated, the sanity checks and the booklets are skipped.
+    /// Can be specified more than once.
+    #[clap(long)]
+    pub testcase: Vec<u32>,
+
+    /// Evaluate only the testcases with at least one of these tags
+    ///
+    /// The tags of a testcase are set in gen/GEN with a line like `# @tags: tree, line-graph`
+    /// before it. Only the needed testcases are generated, the sanity checks and the booklets are
+    /// skipped.
+    #[clap(long, value_delimiter = ',')]
+    pub only_tags: Vec<String>,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -183,6 +661,30 @@ pub struct BookletOpt {
     pub no_statement: bool,
 }
 
+#[derive(Parser, Debug, Clone)]
+pub struct TimeLimitOpt {
+    /// After the evaluation, suggest a time limit based on the solutions accepted on all the
+    /// testcases
+    #[clap(long = "suggest-time-limit")]
+    pub suggest_time_limit: bool,
+
+    /// The time used by the slowest accepted solution is multiplied by this factor
+    #[clap(long = "time-limit-multiplier", default_value = "2.0")]
+    pub multiplier: f64,
+
+    /// The suggested time limit is rounded to a multiple of this number of seconds
+    #[clap(long = "time-limit-step", default_value = "0.1")]
+    pub step: f64,
+
+    /// How to round the suggested time limit: up, nearest
+    #[clap(long = "time-limit-rounding", default_value = "up")]
+    pub rounding: TimeLimitRounding,
+
+    /// Write the suggested time limit to the task.yaml
+    #[clap(long = "write-time-limit", requires = "suggest_time_limit")]
+    pub write_time_limit: bool,
+}
+
 /// Returns the long-help for the "skip sanity checks" option.
 fn skip_sanity_checks_long_help() -> &'static str {
     lazy_static! {
@@ -229,6 +731,22 @@ impl Opt {
             disabled_sanity_checks: self.skip_sanity_checks.clone(),
             seed: self.terry.seed,
             dry_run: self.execution.dry_run,
+            checker_score_policy: self.checker_score_policy,
+            subtask_filter: self.filter.subtask.clone(),
+            testcase_filter: self.filter.testcase.clone(),
+            tag_filter: self.filter.only_tags.clone(),
+            official_solution: self.official_solution.clone(),
+            trust_outputs: self.trust_outputs,
+            check_output_determinism: self.check_output_determinism,
+            sanitize: self.sanitize,
+            profile: self.profile,
+            profile_all_testcases: self.profile_all_testcases,
+            skip_dependent_subtasks: self.skip_dependent_subtasks,
+            quick: self.quick,
+            seed_runs: self.seed_runs,
+            seed_aggregation: self.seed_aggregation,
+            latex_cache_dir: Some(self.storage.store_dir().join("latex")),
+            dedup_solutions: self.dedup_solutions,
         }
     }
 
@@ -239,6 +757,14 @@ impl Opt {
 }
 
 impl UIOpt {
+    /// Check that the requested version of the JSON UI schema, if any, is supported.
+    pub fn check_json_schema_version(&self) -> Result<(), Error> {
+        if let Some(version) = &self.json_schema_version {
+            task_maker_format::ui::check_json_schema_version(version)?;
+        }
+        Ok(())
+    }
+
     /// Disable the Curses UI and fallback to PrintUI if verbose output is enabled.
     pub fn disable_if_needed(&mut self, logger: &LoggerOpt) {
         let mut show_warning = false;
@@ -255,6 +781,92 @@ impl UIOpt {
     }
 }
 
+impl TimeLimitOpt {
+    /// The policy to use for suggesting the time limit.
+    pub fn policy(&self) -> TimeLimitPolicy {
+        TimeLimitPolicy {
+            multiplier: self.multiplier,
+            step: self.step,
+            rounding: self.rounding,
+        }
+    }
+}
+
+impl ExecutionOpt {
+    /// The environment variables to set in all the executions, parsed from `--env`.
+    pub fn env(&self) -> Result<BTreeMap<String, String>, Error> {
+        self.env
+            .iter()
+            .map(|var| match var.split_once('=') {
+                Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+                _ => bail!("Invalid environment variable '{}', expected KEY=VALUE", var),
+            })
+            .collect()
+    }
+
+    /// Whether the sandboxes should be run inside this process.
+    pub fn run_in_process(&self) -> bool {
+        #[cfg(feature = "profiling")]
+        if self.flamegraph.is_some() {
+            return true;
+        }
+        self.in_process
+    }
+}
+
+impl SandboxBackendOpt {
+    /// Make the sandbox runner for the selected backend. `in_process` selects whether the sandboxes
+    /// should be run inside the current process.
+    pub fn sandbox_runner(&self, in_process: bool) -> Result<ToolsSandboxRunner, Error> {
+        let runner = self.backend_runner(in_process)?;
+        match &self.container_runtime {
+            Some(path) => {
+                let container = Container::new(path.clone(), self.cgroup_root.clone())
+                    .context("Cannot run the executions inside containers")?;
+                Ok(runner.with_container(container))
+            }
+            None => Ok(runner),
+        }
+    }
+
+    /// Make the sandbox runner for the executions that are not run inside a container.
+    fn backend_runner(&self, in_process: bool) -> Result<ToolsSandboxRunner, Error> {
+        match self.sandbox_backend {
+            SandboxBackend::Tabox if in_process => Ok(ToolsSandboxRunner::in_process()),
+            SandboxBackend::Tabox => Ok(ToolsSandboxRunner::default()),
+            SandboxBackend::Cgroup if in_process => {
+                bail!("The cgroup sandbox backend cannot run the sandboxes in process")
+            }
+            SandboxBackend::Cgroup => ToolsSandboxRunner::cgroup(self.cgroup_root.clone())
+                .context("Cannot use the cgroup sandbox backend"),
+            SandboxBackend::Isolate if in_process => {
+                bail!("The isolate sandbox backend cannot run the sandboxes in process")
+            }
+            SandboxBackend::Isolate => {
+                let isolate = Isolate::new(
+                    self.isolate_path.clone(),
+                    self.isolate_first_box_id,
+                    self.isolate_num_boxes,
+                    self.isolate_first_uid,
+                    self.isolate_cg,
+                )
+                .context("Cannot use the isolate sandbox backend")?;
+                Ok(ToolsSandboxRunner::isolate(isolate))
+            }
+            SandboxBackend::Portable if in_process => {
+                bail!("The portable sandbox backend cannot run the sandboxes in process")
+            }
+            SandboxBackend::Portable => {
+                warn!(
+                    "Using the portable sandbox backend: {}",
+                    crate::portable::isolation_warning()
+                );
+                Ok(ToolsSandboxRunner::portable())
+            }
+        }
+    }
+}
+
 impl StorageOpt {
     /// Get the store directory of this configuration. If nothing is specified a cache directory is
     /// used if available, otherwise a temporary directory.
@@ -300,7 +912,55 @@ impl LoggerOpt {
 impl FindTaskOpt {
     /// Use the specified options to find a task.
     pub fn find_task(&self, eval_config: &EvaluationConfig) -> Result<TaskFormat, Error> {
-        find_task(self.task_dir.clone(), self.max_depth, eval_config)
+        if self.is_batch() {
+            bail!("Only one task can be evaluated here");
+        }
+        find_task(self.task_dir.first().cloned(), self.max_depth, eval_config)
             .context("Invalid task directory")
     }
+
+    /// Whether more than one task has been asked, with --task-dir or --task-list.
+    pub fn is_batch(&self) -> bool {
+        self.task_dir.len() > 1 || self.task_list.is_some()
+    }
+
+    /// The directories of all the tasks asked, in order: first the ones of --task-dir, then the ones
+    /// listed in --task-list.
+    pub fn task_dirs(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut task_dirs = self.task_dir.clone();
+        if let Some(task_list) = &self.task_list {
+            let content = std::fs::read_to_string(task_list)
+                .with_context(|| format!("Failed to read {}", task_list.display()))?;
+            let base = task_list.parent().unwrap_or_else(|| Path::new(""));
+            task_dirs.extend(
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|line| base.join(line)),
+            );
+        }
+        Ok(task_dirs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_matches_command_line() {
+        // the environment variables would change the parsed options
+        if std::env::vars().any(|(key, _)| key.starts_with("TM_")) {
+            return;
+        }
+        assert_eq!(
+            format!("{:?}", ExecutionOpt::default()),
+            format!("{:?}", ExecutionOpt::parse_from(["task-maker"]))
+        );
+        assert_eq!(
+            format!("{:?}", StorageOpt::default()),
+            format!("{:?}", StorageOpt::parse_from(["task-maker"]))
+        );
+    }
 }
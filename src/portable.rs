@@ -0,0 +1,239 @@
+//! A sandbox that only spawns the process with some resource limits set with rlimits, for the
+//! systems where tabox and isolate are not available. Like the rest of task-maker it's available
+//! only on unix, Windows is supported through WSL2.
+//!
+//! The executions are **not** isolated: they can read and write every file the user can access,
+//! use the network and spawn other processes. On macOS they are run with
+//! `sandbox-exec`, which denies them the network and the writes outside of the writable
+//! directories of the sandbox, but they can still read every file. The paths inside the sandbox
+//! (like `/box`) are translated to the directories they would be mounted from, so that the
+//! executions behave the same.
+
+use std::fs::{File, OpenOptions};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Error};
+use tabox::configuration::SandboxConfiguration;
+use tabox::result::{ExitStatus, ResourceUsage, SandboxExecutionResult};
+
+/// How often the process is checked for the wall time limit.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// The tool of macOS that runs a process restricted by a sandbox profile.
+const SANDBOX_EXEC: &str = "/usr/bin/sandbox-exec";
+
+/// What the executions run by this sandbox can access on the current system, to warn the user.
+pub fn isolation_warning() -> &'static str {
+    if use_sandbox_exec() {
+        "the executions are only partially isolated, they can read all the files of the current \
+        user, and the memory usage is measured less precisely"
+    } else {
+        "the executions are NOT isolated, they can access all the files of the current user and \
+        the network, and the memory usage is measured less precisely"
+    }
+}
+
+/// Run the execution described by `config`, waiting for it to complete.
+///
+/// The resource usage is measured with `getrusage(RUSAGE_CHILDREN)`, so this function should be
+/// called from a process that doesn't spawn other children (i.e. `task-maker-tools
+/// internal-sandbox`).
+pub fn run(config: SandboxConfiguration) -> Result<SandboxExecutionResult, Error> {
+    let executable = host_path(&config, &config.executable);
+    let mut cmd = if use_sandbox_exec() {
+        let mut cmd = Command::new(SANDBOX_EXEC);
+        cmd.arg("-p")
+            .arg(sandbox_exec_profile(&config))
+            .arg(executable);
+        cmd
+    } else {
+        Command::new(executable)
+    };
+    cmd.current_dir(host_path(&config, &config.working_directory));
+    cmd.args(config.args.iter().map(|arg| {
+        let path = Path::new(arg);
+        if path.is_absolute() {
+            host_path(&config, path).into_os_string()
+        } else {
+            arg.into()
+        }
+    }));
+    cmd.env_clear();
+    cmd.envs(config.env.iter().map(|(k, v)| (k, v)));
+    cmd.stdin(match &config.stdin {
+        Some(path) => File::open(path)
+            .with_context(|| format!("Failed to open stdin {}", path.display()))?
+            .into(),
+        None => Stdio::null(),
+    });
+    for (path, is_stdout) in [(&config.stdout, true), (&config.stderr, false)] {
+        let stdio = match path {
+            Some(path) => OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(path)
+                .with_context(|| format!("Failed to open output {}", path.display()))?
+                .into(),
+            None => Stdio::null(),
+        };
+        if is_stdout {
+            cmd.stdout(stdio);
+        } else {
+            cmd.stderr(stdio);
+        }
+    }
+
+    set_rlimits(&mut cmd, &config);
+
+    let start = Instant::now();
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to spawn {}", config.executable.display()))?;
+    let wall_time_limit = config.wall_time_limit.map(Duration::from_secs);
+    let mut killed = false;
+    let status = loop {
+        if let Some(status) = child.try_wait().context("Failed to wait the process")? {
+            break status;
+        }
+        if !killed && wall_time_limit.map_or(false, |limit| start.elapsed() > limit) {
+            child.kill().context("Failed to kill the process")?;
+            killed = true;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+    let wall_time_usage = start.elapsed().as_secs_f64();
+
+    let status = if killed {
+        ExitStatus::Killed
+    } else if let Some(signal) = status.signal() {
+        ExitStatus::Signal(signal)
+    } else {
+        ExitStatus::ExitCode(status.code().unwrap_or_default())
+    };
+    Ok(SandboxExecutionResult {
+        status,
+        resource_usage: ResourceUsage {
+            wall_time_usage,
+            ..children_usage()?
+        },
+    })
+}
+
+/// Limit the time, the memory and the stack of the process spawned by `cmd` with rlimits.
+fn set_rlimits(cmd: &mut Command, config: &SandboxConfiguration) {
+    let time_limit = config.time_limit;
+    let memory_limit = config.memory_limit;
+    let stack_limit = config.stack_limit;
+    // SAFETY: setrlimit is async-signal-safe, and the closure doesn't allocate.
+    unsafe {
+        cmd.pre_exec(move || {
+            if let Some(time) = time_limit {
+                // the process gets SIGXCPU when reaching the soft limit, and SIGKILL after one
+                // more second
+                rlimit::Resource::CPU.set(time, time + 1)?;
+            }
+            if let Some(memory) = memory_limit {
+                #[cfg(target_os = "linux")]
+                rlimit::Resource::AS.set(memory, memory)?;
+                // macOS doesn't enforce RLIMIT_AS
+                #[cfg(not(target_os = "linux"))]
+                rlimit::Resource::DATA.set(memory, memory)?;
+            }
+            if let Some(stack) = stack_limit {
+                rlimit::Resource::STACK.set(stack, stack)?;
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Translate a path inside the sandbox to the path of the host it would be mounted from. The paths
+/// outside the mounted directories are left untouched.
+fn host_path(config: &SandboxConfiguration, path: &Path) -> PathBuf {
+    let mount = config
+        .mount_paths
+        .iter()
+        .filter(|mount| path.starts_with(&mount.target))
+        .max_by_key(|mount| mount.target.components().count());
+    match mount {
+        Some(mount) => mount.source.join(path.strip_prefix(&mount.target).unwrap()),
+        None => path.to_owned(),
+    }
+}
+
+/// Whether the executions are restricted with `sandbox-exec`, which is available only on macOS.
+fn use_sandbox_exec() -> bool {
+    cfg!(target_os = "macos") && Path::new(SANDBOX_EXEC).exists()
+}
+
+/// The profile of `sandbox-exec` for an execution: the network is denied, and the files can be
+/// written only inside the writable mounted directories and the temporary ones.
+fn sandbox_exec_profile(config: &SandboxConfiguration) -> String {
+    // the profile matches the real paths, without symlinks (e.g. /tmp is /private/tmp)
+    let quote = |path: &Path| {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+        let path = path.to_string_lossy().replace('\\', "\\\\");
+        format!("\"{}\"", path.replace('"', "\\\""))
+    };
+    let mut profile = String::from("(version 1)\n(allow default)\n(deny network*)\n");
+    profile += "(deny file-write*)\n(allow file-write* (literal \"/dev/null\"))\n";
+    let mut writable: Vec<PathBuf> = config
+        .mount_paths
+        .iter()
+        .filter(|mount| mount.writable)
+        .map(|mount| mount.source.clone())
+        .collect();
+    // the other sandboxes have a private /tmp, here the one of the system is used
+    if config.mount_tmpfs {
+        writable.extend(["/tmp".into(), std::env::temp_dir()]);
+    }
+    for path in writable {
+        profile += &format!("(allow file-write* (subpath {}))\n", quote(&path));
+    }
+    profile
+}
+
+/// The resources used by the children of the current process that have been waited for.
+fn children_usage() -> Result<ResourceUsage, Error> {
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+    // SAFETY: getrusage initializes `usage` when it succeeds.
+    let usage = unsafe {
+        if libc::getrusage(libc::RUSAGE_CHILDREN, usage.as_mut_ptr()) != 0 {
+            return Err(std::io::Error::last_os_error()).context("getrusage failed");
+        }
+        usage.assume_init()
+    };
+    let to_secs = |time: libc::timeval| time.tv_sec as f64 + time.tv_usec as f64 / 1_000_000.0;
+    // ru_maxrss is in KiB on Linux, but in bytes on macOS
+    let memory_usage = if cfg!(target_os = "macos") {
+        usage.ru_maxrss as u64
+    } else {
+        usage.ru_maxrss as u64 * 1024
+    };
+    Ok(ResourceUsage {
+        memory_usage,
+        user_cpu_time: to_secs(usage.ru_utime),
+        system_cpu_time: to_secs(usage.ru_stime),
+        wall_time_usage: 0.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sandbox_exec_profile() {
+        let mut config = SandboxConfiguration::default();
+        config.mount("/not/existing/box", "/box", true);
+        config.mount("/usr", "/usr", false);
+        let profile = sandbox_exec_profile(&config);
+        assert!(profile.contains("(deny network*)"));
+        assert!(profile.contains("(deny file-write*)"));
+        assert!(profile.contains("(allow file-write* (subpath \"/not/existing/box\"))"));
+        assert!(!profile.contains("/usr"));
+    }
+}
@@ -0,0 +1,72 @@
+//! Optional profiling of task-maker itself, enabled with the `profiling` feature.
+//!
+//! The whole process is sampled and, at the end of the evaluation, a flamegraph is written to the
+//! path specified with `--flamegraph`.
+
+use anyhow::Error;
+
+use crate::ExecutionOpt;
+
+/// A running profiler. Drop it without calling [`Profiler::finish`] to discard the samples.
+pub struct Profiler {
+    #[cfg(feature = "profiling")]
+    guard: pprof::ProfilerGuard<'static>,
+    #[cfg(feature = "profiling")]
+    path: std::path::PathBuf,
+}
+
+impl Profiler {
+    /// Number of samples per second taken by the profiler.
+    #[cfg(feature = "profiling")]
+    const FREQUENCY: i32 = 997;
+
+    /// Start the profiler, if it has been requested with the command line options.
+    #[cfg(feature = "profiling")]
+    pub fn start(opt: &ExecutionOpt) -> Result<Option<Profiler>, Error> {
+        use anyhow::Context;
+
+        let Some(path) = &opt.flamegraph else {
+            return Ok(None);
+        };
+        let guard = pprof::ProfilerGuardBuilder::default()
+            .frequency(Self::FREQUENCY)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()
+            .context("Failed to start the profiler")?;
+        Ok(Some(Profiler {
+            guard,
+            path: path.clone(),
+        }))
+    }
+
+    /// Start the profiler, if it has been requested with the command line options.
+    #[cfg(not(feature = "profiling"))]
+    pub fn start(_opt: &ExecutionOpt) -> Result<Option<Profiler>, Error> {
+        Ok(None)
+    }
+
+    /// Stop the profiler and write the flamegraph.
+    #[cfg(feature = "profiling")]
+    pub fn finish(self) -> Result<(), Error> {
+        use anyhow::Context;
+
+        let report = self
+            .guard
+            .report()
+            .build()
+            .context("Failed to build the profiler report")?;
+        let file = std::fs::File::create(&self.path)
+            .with_context(|| format!("Failed to create {}", self.path.display()))?;
+        report
+            .flamegraph(file)
+            .context("Failed to write the flamegraph")?;
+        info!("Flamegraph written to {}", self.path.display());
+        Ok(())
+    }
+
+    /// Stop the profiler and write the flamegraph.
+    #[cfg(not(feature = "profiling"))]
+    pub fn finish(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
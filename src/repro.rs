@@ -0,0 +1,176 @@
+//! Print the commands for running again by hand the sandboxes of some executions.
+//!
+//! The sandboxes are kept after the evaluation, together with the configuration they were run
+//! with: the printed command runs the same sandbox runner with the same configuration, printing
+//! its raw result.
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Error};
+
+use task_maker_dag::{ExecutionDAG, ExecutionResult, SandboxInvocation};
+
+/// The executions whose sandbox should be reproduced, filled with their results as soon as they
+/// are done.
+#[derive(Debug, Clone, Default)]
+pub struct Repro {
+    /// The description of each matching execution, with its result if it has been run.
+    executions: Arc<Mutex<Vec<(String, Option<ExecutionResult>)>>>,
+}
+
+impl Repro {
+    /// Watch the executions of the DAG whose description contains `execution`, failing if there
+    /// are none.
+    pub fn watch(dag: &mut ExecutionDAG, execution: &str) -> Result<Repro, Error> {
+        let mut matching: Vec<_> = dag
+            .data
+            .execution_groups
+            .values()
+            .flat_map(|group| group.executions.iter())
+            .filter(|exec| exec.description.contains(execution))
+            .map(|exec| (exec.uuid, exec.description.clone()))
+            .collect();
+        if matching.is_empty() {
+            bail!("No execution matches '{}'", execution);
+        }
+        matching.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let repro = Repro::default();
+        for (index, (uuid, description)) in matching.into_iter().enumerate() {
+            repro.executions.lock().unwrap().push((description, None));
+            let executions = repro.executions.clone();
+            dag.on_execution_done(&uuid, move |result| {
+                executions.lock().unwrap()[index].1 = Some(result);
+                Ok(())
+            });
+        }
+        Ok(repro)
+    }
+
+    /// Print to stdout the commands for running again the sandboxes of the watched executions.
+    pub fn print(&self) {
+        for (description, result) in self.executions.lock().unwrap().iter() {
+            println!();
+            println!("Sandbox of '{}'", description);
+            let Some(result) = result else {
+                println!("  The execution has not been run");
+                continue;
+            };
+            match result.sandbox.as_ref().and_then(repro_command) {
+                Some(command) => println!("  {}", command),
+                None => println!("  The sandbox of the execution cannot be run again by hand"),
+            }
+            if result.was_cached {
+                println!(
+                    "  The result comes from the cache: the sandbox may have been deleted, run \
+                     with --no-cache for running it again"
+                );
+            }
+        }
+    }
+}
+
+/// The shell command that runs again the sandbox, using the configuration kept in its directory.
+pub fn repro_command(invocation: &SandboxInvocation) -> Option<String> {
+    let runner = invocation.runner.as_ref()?;
+    let config = invocation.boxdir.join("sandbox.json");
+    Some(format!(
+        "{} < {}",
+        shell_words::join(runner),
+        shell_words::quote(&config.to_string_lossy())
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use task_maker_dag::{Execution, ExecutionCommand};
+    use task_maker_exec::SandboxRunner;
+
+    use super::*;
+    use crate::sandbox::ToolsSandboxRunner;
+
+    fn invocation(runner: Option<Vec<String>>, boxdir: &str) -> SandboxInvocation {
+        SandboxInvocation {
+            runner,
+            boxdir: PathBuf::from(boxdir),
+            executable: PathBuf::from("sol"),
+            args: vec![],
+            working_directory: PathBuf::from("/box"),
+            env: Default::default(),
+            mounts: vec![],
+            stdin: None,
+            stdout: None,
+            stderr: None,
+            time_limit: None,
+            wall_time_limit: None,
+            memory_limit: None,
+            stack_limit: None,
+        }
+    }
+
+    #[test]
+    fn test_repro_command() {
+        let runner = vec![
+            "/usr/bin/task-maker-tools".into(),
+            "internal-sandbox".into(),
+        ];
+        let command = repro_command(&invocation(Some(runner), "/tmp/box")).unwrap();
+        assert_eq!(
+            command,
+            "/usr/bin/task-maker-tools internal-sandbox < /tmp/box/sandbox.json"
+        );
+    }
+
+    #[test]
+    fn test_repro_command_quoting() {
+        let runner = vec![
+            "/opt/task maker/task-maker-tools".into(),
+            "internal-sandbox".into(),
+            "--portable".into(),
+        ];
+        let command = repro_command(&invocation(Some(runner), "/tmp/it's a box")).unwrap();
+        // the command is split back by the shell into the same words
+        let words = shell_words::split(&command).unwrap();
+        assert_eq!(
+            words,
+            [
+                "/opt/task maker/task-maker-tools",
+                "internal-sandbox",
+                "--portable",
+                "<",
+                "/tmp/it's a box/sandbox.json"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_repro_command_no_runner() {
+        assert!(repro_command(&invocation(None, "/tmp/box")).is_none());
+    }
+
+    #[test]
+    fn test_repro_command_sandbox_options() {
+        let runner = ToolsSandboxRunner::default().command().unwrap();
+        assert_eq!(&runner[1..], ["internal-sandbox"]);
+        let runner = ToolsSandboxRunner::portable().command().unwrap();
+        assert_eq!(&runner[1..], ["internal-sandbox", "--portable"]);
+        let command = repro_command(&invocation(Some(runner), "/tmp/box")).unwrap();
+        assert!(command.ends_with(" internal-sandbox --portable < /tmp/box/sandbox.json"));
+    }
+
+    #[test]
+    fn test_watch() {
+        let mut dag = ExecutionDAG::new();
+        for name in ["sol2 on 1", "sol1 on 1", "checker"] {
+            dag.add_execution(Execution::new(name, ExecutionCommand::system("true")));
+        }
+        assert!(Repro::watch(&mut dag, "nope").is_err());
+        let repro = Repro::watch(&mut dag, "on 1").unwrap();
+        let executions = repro.executions.lock().unwrap();
+        let names: Vec<_> = executions.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["sol1 on 1", "sol2 on 1"]);
+        assert!(executions.iter().all(|(_, result)| result.is_none()));
+    }
+}
@@ -1,10 +1,11 @@
-use std::io::{stdin, stdout};
+use std::io::{stdin, stdout, Read};
+use std::os::unix::net::UnixStream;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
-use anyhow::{bail, Context, Error};
+use anyhow::{anyhow, bail, Context, Error};
 use tabox::configuration::SandboxConfiguration;
 use tabox::result::SandboxExecutionResult;
 use tabox::{Sandbox, SandboxImplementation};
@@ -12,21 +13,77 @@ use tabox::{Sandbox, SandboxImplementation};
 use task_maker_exec::find_tools::find_tools_path;
 use task_maker_exec::{RawSandboxResult, SandboxRunner};
 
+use crate::cgroup::Cgroup;
+use crate::container::Container;
+use crate::isolate::Isolate;
+
 /// Actually parse the input and return the result.
-fn run_sandbox() -> Result<SandboxExecutionResult, Error> {
+fn run_sandbox(cgroup: Option<PathBuf>, portable: bool) -> Result<SandboxExecutionResult, Error> {
     let config =
         serde_json::from_reader(stdin()).context("Cannot read configuration from stdin")?;
+    if let Some(cgroup) = cgroup {
+        return run_sandbox_in_cgroup(&cgroup, config);
+    }
+    if portable {
+        return crate::portable::run(config);
+    }
     let sandbox = SandboxImplementation::run(config).context("Failed to create sandbox")?;
     let res = sandbox.wait().context("Failed to wait sandbox")?;
     Ok(res)
 }
 
+/// Run the sandbox in a forked process that joins the cgroup, so that the memory and the cpu time
+/// used so far by this process are not accounted to the cgroup. The memory pages inherited from
+/// this process stay charged to the current cgroup.
+fn run_sandbox_in_cgroup(
+    cgroup: &Path,
+    config: SandboxConfiguration,
+) -> Result<SandboxExecutionResult, Error> {
+    let (mut reader, mut writer) =
+        UnixStream::pair().context("Failed to create the socket for the sandbox result")?;
+    // SAFETY: this process is single threaded, and the child only runs the sandbox and exits
+    let child = unsafe { libc::fork() };
+    if child < 0 {
+        bail!("Failed to fork: {}", std::io::Error::last_os_error());
+    }
+    if child == 0 {
+        drop(reader);
+        let res = Cgroup::join(cgroup)
+            .and_then(|_| SandboxImplementation::run(config).context("Failed to create sandbox"))
+            .and_then(|sandbox| sandbox.wait().context("Failed to wait sandbox"))
+            .map_err(|e| format!("{:?}", e));
+        let code = match serde_json::to_writer(&mut writer, &res) {
+            Ok(_) => 0,
+            Err(_) => 1,
+        };
+        // SAFETY: exit without running the destructors and the atexit handlers of the parent
+        unsafe { libc::_exit(code) };
+    }
+    drop(writer);
+    let mut output = Vec::new();
+    let read = reader.read_to_end(&mut output);
+    let mut status = 0;
+    // SAFETY: `child` is a child of this process that has not been waited yet
+    if unsafe { libc::waitpid(child, &mut status, 0) } < 0 {
+        bail!(
+            "Failed to wait for the sandbox process: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    read.context("Failed to read the result of the sandbox")?;
+    let res: Result<SandboxExecutionResult, String> =
+        serde_json::from_slice(&output).context("Invalid result from the sandbox process")?;
+    res.map_err(|e| anyhow!(e))
+}
+
 /// Run the sandbox for an execution.
 ///
 /// It takes a `SandboxConfiguration`, JSON serialized via standard input and prints to standard
-/// output a `RawSandboxResult`, JSON serialized.
-pub fn main_sandbox() {
-    match run_sandbox() {
+/// output a `RawSandboxResult`, JSON serialized. If `cgroup` is provided, the sandbox is run inside
+/// that cgroup. If `portable` is set, the execution is run with the portable sandbox, which only
+/// limits its resources without isolating it.
+pub fn main_sandbox(cgroup: Option<PathBuf>, portable: bool) {
+    match run_sandbox(cgroup, portable) {
         Ok(res) => {
             serde_json::to_writer(stdout(), &RawSandboxResult::Success(res))
                 .expect("Failed to print result");
@@ -39,38 +96,226 @@ pub fn main_sandbox() {
     }
 }
 
-/// Run the sandbox integrated in the task-maker-tools binary.
+/// The implementation used for running and measuring the sandboxes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxBackend {
+    /// Run the sandboxes using tabox, measuring the resources with the sandbox itself.
+    Tabox,
+    /// Run the sandboxes using tabox, measuring the resources with cgroup v2.
+    Cgroup,
+    /// Run the sandboxes using isolate, the sandbox used by CMS.
+    Isolate,
+    /// Run the executions without isolating them, only limiting their resources with rlimits. This
+    /// works also where tabox is not available.
+    Portable,
+}
+
+impl std::str::FromStr for SandboxBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SandboxBackend, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tabox" => Ok(SandboxBackend::Tabox),
+            "cgroup" => Ok(SandboxBackend::Cgroup),
+            "isolate" => Ok(SandboxBackend::Isolate),
+            "portable" => Ok(SandboxBackend::Portable),
+            _ => Err(format!("Unknown sandbox backend: {}", s)),
+        }
+    }
+}
+
+/// Run the sandbox integrated in the task-maker-tools binary, or an external one.
 #[derive(Clone, Debug)]
 pub struct ToolsSandboxRunner {
-    /// Path to the tools executable.
-    tools_path: PathBuf,
+    /// Path to the tools executable. When `None` the sandbox is run inside the current process.
+    tools_path: Option<PathBuf>,
+    /// When set, each sandbox is run in a new cgroup inside this one, and the resources are
+    /// measured using the cgroup.
+    cgroup_root: Option<PathBuf>,
+    /// When set, the sandboxes are run using isolate instead of the integrated sandbox.
+    isolate: Option<Arc<Isolate>>,
+    /// When set, the executions are run with the portable sandbox, without isolating them.
+    portable: bool,
+    /// When set, the executions that require a container image are run inside it.
+    container: Option<Arc<Container>>,
 }
 
 impl Default for ToolsSandboxRunner {
     fn default() -> Self {
         ToolsSandboxRunner {
-            tools_path: find_tools_path(),
+            tools_path: Some(find_tools_path()),
+            cgroup_root: None,
+            isolate: None,
+            portable: false,
+            container: None,
+        }
+    }
+}
+
+impl ToolsSandboxRunner {
+    /// Make a runner that doesn't spawn task-maker-tools, but runs the sandbox from the current
+    /// process. This is useful for profiling, since all the work of task-maker happens in a single
+    /// process.
+    ///
+    /// Note that the pid of the sandbox is not known, so the executions cannot be killed before
+    /// they reach their limits.
+    pub fn in_process() -> Self {
+        ToolsSandboxRunner {
+            tools_path: None,
+            cgroup_root: None,
+            isolate: None,
+            portable: false,
+            container: None,
+        }
+    }
+
+    /// Make a runner that spawns task-maker-tools inside a new cgroup for each sandbox, created
+    /// under `cgroup_root`. The cpu time and the memory usage are measured using the cgroup, so
+    /// they include all the processes of the execution.
+    pub fn cgroup(cgroup_root: PathBuf) -> Result<Self, Error> {
+        Cgroup::check_root(&cgroup_root)?;
+        Ok(ToolsSandboxRunner {
+            tools_path: Some(find_tools_path()),
+            cgroup_root: Some(cgroup_root),
+            isolate: None,
+            portable: false,
+            container: None,
+        })
+    }
+
+    /// Make a runner that runs the sandboxes using isolate, so that the resources are measured
+    /// in the same way as the contest environment.
+    pub fn isolate(isolate: Isolate) -> Self {
+        ToolsSandboxRunner {
+            tools_path: None,
+            cgroup_root: None,
+            isolate: Some(Arc::new(isolate)),
+            portable: false,
+            container: None,
         }
     }
+
+    /// Make a runner that spawns task-maker-tools running the executions with the portable
+    /// sandbox: the resources are limited using rlimits, but the executions are not isolated from
+    /// the rest of the system.
+    pub fn portable() -> Self {
+        ToolsSandboxRunner {
+            tools_path: Some(find_tools_path()),
+            cgroup_root: None,
+            isolate: None,
+            portable: true,
+            container: None,
+        }
+    }
+
+    /// Run the executions that require a container image inside it, using `container`. The other
+    /// executions are still run by this runner.
+    pub fn with_container(mut self, container: Container) -> Self {
+        self.container = Some(Arc::new(container));
+        self
+    }
 }
 
 impl SandboxRunner for ToolsSandboxRunner {
     fn run(&self, config: SandboxConfiguration, pid: Arc<AtomicU32>) -> RawSandboxResult {
-        match tools_sandbox_internal(&self.tools_path, config, pid) {
+        let res = match (&self.isolate, &self.tools_path, &self.cgroup_root) {
+            (Some(isolate), _, _) => isolate.run(config, pid),
+            (None, Some(tools_path), Some(cgroup_root)) => {
+                cgroup_sandbox_internal(tools_path, cgroup_root, config, pid)
+            }
+            (None, Some(tools_path), None) => {
+                tools_sandbox_internal(tools_path, None, self.portable, config, pid)
+            }
+            (None, None, _) => in_process_sandbox_internal(config),
+        };
+        match res {
             Ok(res) => res,
             Err(e) => RawSandboxResult::Error(e.to_string()),
         }
     }
+
+    fn run_in_container(
+        &self,
+        image: &str,
+        config: SandboxConfiguration,
+        pid: Arc<AtomicU32>,
+    ) -> RawSandboxResult {
+        let Some(container) = &self.container else {
+            return RawSandboxResult::Error(format!(
+                "Cannot run the container image {}: --container-runtime not set",
+                image
+            ));
+        };
+        match container.run(image, config, pid) {
+            Ok(res) => res,
+            Err(e) => RawSandboxResult::Error(format!("{:?}", e)),
+        }
+    }
+
+    fn command(&self) -> Option<Vec<String>> {
+        // isolate is configured differently, it cannot be run from the configuration of tabox
+        if self.isolate.is_some() {
+            return None;
+        }
+        // the cgroup of the sandbox is deleted after the execution, the resources are measured by
+        // the sandbox itself instead
+        let tools_path = self.tools_path.clone().unwrap_or_else(find_tools_path);
+        let mut command = vec![
+            tools_path.to_string_lossy().to_string(),
+            "internal-sandbox".to_string(),
+        ];
+        if self.portable {
+            command.push("--portable".to_string());
+        }
+        Some(command)
+    }
+}
+
+/// Run the sandbox from the current process, without spawning task-maker-tools.
+fn in_process_sandbox_internal(config: SandboxConfiguration) -> Result<RawSandboxResult, Error> {
+    let sandbox = SandboxImplementation::run(config).context("Failed to create sandbox")?;
+    let res = sandbox.wait().context("Failed to wait sandbox")?;
+    Ok(RawSandboxResult::Success(res))
+}
+
+/// Run the sandbox inside a new cgroup, replacing the measured resources with the ones of the
+/// cgroup.
+fn cgroup_sandbox_internal(
+    tools_path: &Path,
+    cgroup_root: &Path,
+    config: SandboxConfiguration,
+    pid: Arc<AtomicU32>,
+) -> Result<RawSandboxResult, Error> {
+    let cgroup = Cgroup::new_in(cgroup_root)?;
+    let mut res = tools_sandbox_internal(tools_path, Some(cgroup.path()), false, config, pid)?;
+    if let RawSandboxResult::Success(res) = &mut res {
+        let usage = cgroup.usage()?;
+        res.resource_usage.user_cpu_time = usage.user_time;
+        res.resource_usage.system_cpu_time = usage.system_time;
+        if let Some(memory_peak) = usage.memory_peak {
+            res.resource_usage.memory_usage = memory_peak;
+        }
+    }
+    Ok(res)
 }
 
 /// Actually run the sandbox, but with a return type that supports the `?` operator.
 fn tools_sandbox_internal(
     tools_path: &Path,
+    cgroup: Option<&Path>,
+    portable: bool,
     config: SandboxConfiguration,
     pid: Arc<AtomicU32>,
 ) -> Result<RawSandboxResult, Error> {
-    let mut cmd = Command::new(tools_path)
-        .arg("internal-sandbox")
+    let mut cmd = Command::new(tools_path);
+    cmd.arg("internal-sandbox");
+    if let Some(cgroup) = cgroup {
+        cmd.arg("--cgroup").arg(cgroup);
+    }
+    if portable {
+        cmd.arg("--portable");
+    }
+    let mut cmd = cmd
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -15,7 +15,7 @@ use task_maker_format::{
     cwrite, cwriteln, EvaluationConfig, SolutionCheck, SolutionCheckResult, TaskFormat,
     TestcaseEvaluationResult,
 };
-use task_maker_lang::LanguageManager;
+use task_maker_lang::{find_build_manifest, LanguageManager};
 
 use crate::context::RuntimeContext;
 use crate::{ExecutionOpt, FilterOpt, FindTaskOpt, LoggerOpt, StorageOpt, UIOpt};
@@ -55,14 +55,19 @@ pub fn main_add_solution_checks(
     logger_opt: LoggerOpt,
 ) -> Result<(), Error> {
     opt.ui.disable_if_needed(&logger_opt);
+    opt.ui.check_json_schema_version()?;
+    if !opt.filter.subtask.is_empty()
+        || !opt.filter.testcase.is_empty()
+        || !opt.filter.only_tags.is_empty()
+    {
+        bail!("The checks can be added only evaluating all the testcases, --subtask, --testcase and --only-tags are not supported");
+    }
     let eval_config = EvaluationConfig {
         solution_filter: opt.filter.filter,
-        booklet_solutions: false,
         no_statement: true,
         solution_paths: opt.filter.solution,
-        disabled_sanity_checks: Default::default(),
-        seed: Default::default(),
         dry_run: true,
+        ..Default::default()
     };
     let task = opt
         .find_task
@@ -206,13 +211,10 @@ fn process_solution(
         .collect_vec();
     let mut written = "";
     if in_place && !comments.is_empty() {
-        if let Err(e) = write_comments_to_file(&solution.path, &comments, checks_to_remove)
-            .with_context(|| {
-                format!(
-                    "Failed to write @check comments to '{}'",
-                    solution.path.display()
-                )
-            })
+        // the checks of a build directory are written in its build manifest
+        let path = find_build_manifest(&solution.path).unwrap_or_else(|| solution.path.clone());
+        if let Err(e) = write_comments_to_file(&path, &comments, checks_to_remove)
+            .with_context(|| format!("Failed to write @check comments to '{}'", path.display()))
         {
             eprintln!("Error: {:?}", e);
         } else {
@@ -7,7 +7,7 @@ use task_maker_format::ioi::{make_contest_booklets, Booklet, BookletConfig, IOIT
 use task_maker_format::{find_task, EvaluationConfig};
 
 use crate::context::RuntimeContext;
-use crate::{ExecutionOpt, LoggerOpt, StorageOpt, ToolsSandboxRunner, UIOpt};
+use crate::{ExecutionOpt, LoggerOpt, StorageOpt, UIOpt};
 
 #[derive(Parser, Debug, Clone)]
 pub struct BookletOpt {
@@ -43,14 +43,12 @@ pub struct BookletOpt {
 
 pub fn main_booklet(mut opt: BookletOpt, logger_opt: LoggerOpt) -> Result<(), Error> {
     opt.ui.disable_if_needed(&logger_opt);
+    opt.ui.check_json_schema_version()?;
     let eval_config = EvaluationConfig {
-        solution_filter: vec![],
         booklet_solutions: opt.booklet_solutions,
-        no_statement: false,
-        solution_paths: vec![],
-        disabled_sanity_checks: vec![],
-        seed: None,
         dry_run: opt.execution.dry_run,
+        latex_cache_dir: Some(opt.storage.store_dir().join("latex")),
+        ..Default::default()
     };
 
     if opt.contest_dir.is_some() && !opt.task_dir.is_empty() {
@@ -69,13 +67,12 @@ pub fn main_booklet(mut opt: BookletOpt, logger_opt: LoggerOpt) -> Result<(), Er
     task.subtasks.clear();
 
     // setup the configuration and the evaluation metadata
-    let mut context = RuntimeContext::new(task.into(), &opt.execution, |_task, eval| {
+    let context = RuntimeContext::new(task.into(), &opt.execution, |_task, eval| {
         for booklet in booklets {
             booklet.build(eval)?;
         }
         Ok(())
     })?;
-    context.sandbox_runner(ToolsSandboxRunner::default());
 
     // start the execution
     let executor = context.connect_executor(&opt.execution, &opt.storage)?;
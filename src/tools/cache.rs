@@ -0,0 +1,160 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+use itertools::Itertools;
+
+use task_maker_cache::{Cache, CacheArchiveSummary, CacheEntryInfo, CacheFilter};
+use task_maker_store::FileStore;
+
+use crate::tools::format_age;
+use crate::StorageOpt;
+
+#[derive(Parser, Debug, Clone)]
+pub struct CacheOpt {
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+
+    /// What to do with the cache
+    #[clap(subcommand)]
+    pub command: CacheCommand,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub enum CacheCommand {
+    /// Write the cache entries and their output files to an archive
+    Export {
+        /// Path of the archive to write
+        file: PathBuf,
+        /// Export only the entries younger than this number of seconds
+        #[clap(long)]
+        max_age: Option<u64>,
+    },
+    /// Add the cache entries and the files of an archive to the local cache
+    Import {
+        /// Path of the archive to read
+        file: PathBuf,
+    },
+    /// List the cache entries, with their id, age, outcome and command
+    Ls {
+        #[clap(flatten)]
+        filter: CacheFilterOpt,
+    },
+    /// Remove some cache entries, for example the ones of a broken execution
+    ///
+    /// Their output files are left in the storage, and they are removed when the space is needed.
+    /// At least one filter must be provided.
+    Rm {
+        #[clap(flatten)]
+        filter: CacheFilterOpt,
+    },
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct CacheFilterOpt {
+    /// Select only the entries whose id starts with this prefix
+    #[clap(long)]
+    pub id: Option<String>,
+    /// Select only the entries with a command line that contains this string
+    #[clap(long)]
+    pub command: Option<String>,
+    /// Select only the entries with an input or output file whose hash starts with this prefix
+    #[clap(long)]
+    pub file: Option<String>,
+}
+
+impl CacheFilterOpt {
+    /// The filter of the cache entries selected by the options.
+    fn filter(&self) -> CacheFilter {
+        CacheFilter {
+            id: self.id.clone(),
+            command: self.command.clone(),
+            file: self.file.clone(),
+        }
+    }
+}
+
+pub fn main_cache(opt: CacheOpt) -> Result<(), Error> {
+    let file_store = FileStore::new(
+        opt.storage.store_dir().join("store"),
+        opt.storage.max_cache * 1024 * 1024,
+        opt.storage.min_cache * 1024 * 1024,
+    )
+    .context("Cannot create the file store (You can try wiping it with task-maker-tools reset)")?;
+    let mut cache =
+        Cache::new(opt.storage.store_dir().join("cache")).context("Cannot create the cache")?;
+    match opt.command {
+        CacheCommand::Export { file, max_age } => {
+            let archive = std::fs::File::create(&file)
+                .with_context(|| format!("Failed to create {}", file.display()))?;
+            let summary = cache
+                .export(&file_store, max_age.map(Duration::from_secs), archive)
+                .context("Failed to export the cache")?;
+            print_summary("Exported", &summary);
+        }
+        CacheCommand::Import { file } => {
+            let archive = std::fs::File::open(&file)
+                .with_context(|| format!("Failed to open {}", file.display()))?;
+            let summary = cache
+                .import(&file_store, archive)
+                .context("Failed to import the cache")?;
+            print_summary("Imported", &summary);
+        }
+        CacheCommand::Ls { filter } => {
+            let entries = cache
+                .query(&filter.filter())
+                .into_iter()
+                .sorted_by_key(|entry| entry.created)
+                .collect_vec();
+            for entry in &entries {
+                print_entry(entry);
+            }
+            println!("{} cache entries", entries.len());
+        }
+        CacheCommand::Rm { filter } => {
+            let filter = filter.filter();
+            if filter == CacheFilter::default() {
+                bail!(
+                    "No filter provided, use `task-maker-tools reset` for wiping the whole cache"
+                );
+            }
+            let removed = cache.remove(&filter);
+            println!("Removed {} cache entries", removed);
+        }
+    }
+    Ok(())
+}
+
+/// Print a line with the information of a cache entry.
+fn print_entry(entry: &CacheEntryInfo) {
+    let age = entry.created.elapsed().unwrap_or_default();
+    let outcome = match entry.statuses.iter().find(|status| !status.is_success()) {
+        Some(status) => format!("{:?}", status),
+        None => "Success".to_string(),
+    };
+    let kind = if entry.artifact {
+        "artifact"
+    } else {
+        "execution"
+    };
+    println!(
+        "{}  {:>6}  {:<9}  {:<20}  {}",
+        entry.id,
+        format_age(age),
+        kind,
+        outcome,
+        entry.commands.join(" | ")
+    );
+}
+
+fn print_summary(action: &str, summary: &CacheArchiveSummary) {
+    println!(
+        "{} {} cache entries and {} artifacts, with {} files ({:.1} MiB)",
+        action,
+        summary.entries,
+        summary.artifacts,
+        summary.files,
+        summary.bytes as f64 / 1024.0 / 1024.0
+    );
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Error};
+use clap::Parser;
+use itertools::Itertools;
+
+use task_maker_cache::CacheKeyComponents;
+use task_maker_dag::ProvidedFile;
+
+use crate::context::{local_toolchain_versions, RuntimeContext};
+use crate::{ExecutionOpt, FindTaskOpt};
+
+#[derive(Parser, Debug, Clone)]
+pub struct CacheKeyOpt {
+    #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
+    pub find_task: FindTaskOpt,
+    /// Print only the executions whose description contains this string.
+    #[clap(long)]
+    pub filter: Option<String>,
+    /// Produce JSON output.
+    #[clap(long, short)]
+    pub json: bool,
+}
+
+pub fn main_cache_key(opt: CacheKeyOpt) -> Result<(), Error> {
+    let config = Default::default();
+    let task = opt.find_task.find_task(&config)?;
+    let context = RuntimeContext::new(task, &ExecutionOpt::default(), |task, eval| {
+        task.build_dag(eval, &config)
+            .context("Cannot build the task DAG")
+    })?;
+    let data = &context.eval.dag.data;
+
+    // the files produced by the executions are not known before running them, they are identified
+    // by the execution that produces them.
+    let mut file_keys = HashMap::new();
+    for file in data.provided_files.values() {
+        let (uuid, description) = match file {
+            ProvidedFile::LocalFile {
+                file,
+                key,
+                local_path,
+            } => (file.uuid, format!("{} ({})", key, local_path.display())),
+            ProvidedFile::Content { file, key, .. } => {
+                (file.uuid, format!("{} ({})", key, file.description))
+            }
+        };
+        file_keys.insert(uuid, description);
+    }
+    for group in data.execution_groups.values() {
+        for execution in &group.executions {
+            for output in execution.outputs() {
+                file_keys.insert(output, format!("output of '{}'", execution.description));
+            }
+        }
+    }
+
+    // the versions of the toolchains are the ones of this machine, the keys of the executions run
+    // by remote workers may differ.
+    let toolchains = local_toolchain_versions();
+    let components = data
+        .execution_groups
+        .values()
+        .flat_map(|group| {
+            let mut group = group.clone();
+            group.set_toolchain_versions(&toolchains);
+            CacheKeyComponents::from_execution_group(&group, &file_keys)
+        })
+        .filter(|c| match &opt.filter {
+            Some(filter) => c.description.contains(filter),
+            None => true,
+        })
+        .sorted_by(|a, b| a.description.cmp(&b.description))
+        .collect_vec();
+
+    if opt.json {
+        let json = serde_json::to_string(&components).context("Non-serializable cache keys")?;
+        println!("{}", json);
+        return Ok(());
+    }
+    for component in components {
+        println!("{}", component.description);
+        println!("    command: {:?}", component.command);
+        println!("    args: {:?}", component.args);
+        if let Some(stdin) = &component.stdin {
+            println!("    stdin: {}", stdin);
+        }
+        for (path, key, executable) in &component.inputs {
+            let kind = if component.directories.contains(path) {
+                " (directory)"
+            } else if *executable {
+                " (executable)"
+            } else {
+                ""
+            };
+            println!("    input {}{}: {}", path.display(), kind, key);
+        }
+        for (name, value) in &component.env {
+            println!("    env {}={}", name, value);
+        }
+        if let Some(toolchain) = &component.toolchain {
+            println!("    toolchain: {}", toolchain);
+        }
+        if let Some(image) = &component.container_image {
+            println!("    container image: {}", image);
+        }
+        println!("    limits: {:?}", component.limits);
+    }
+    Ok(())
+}
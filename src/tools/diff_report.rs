@@ -0,0 +1,109 @@
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+
+use task_maker_dag::ExecutionDAGConfig;
+use task_maker_format::ioi::UIState;
+use task_maker_format::ui::{check_json_schema_version, JsonUIHeader, UIMessage, UIStateT};
+
+use crate::history::{compare_solutions, DiffMode, HistorySolution};
+
+#[derive(Parser, Debug, Clone)]
+pub struct DiffReportOpt {
+    /// The output of the JSON UI (--ui json) of the first evaluation.
+    pub old: PathBuf,
+    /// The output of the JSON UI (--ui json) of the second evaluation.
+    pub new: PathBuf,
+    /// Report the testcases where the used time or memory increased by more than this fraction.
+    #[clap(long, default_value = "0.2")]
+    pub threshold: f64,
+}
+
+pub fn main_diff_report(opt: DiffReportOpt) -> Result<(), Error> {
+    let old = load_report(&opt.old)
+        .with_context(|| format!("Failed to load report {}", opt.old.display()))?;
+    let new = load_report(&opt.new)
+        .with_context(|| format!("Failed to load report {}", opt.new.display()))?;
+
+    let differences = compare_solutions(
+        &HistorySolution::from_state(&old),
+        &HistorySolution::from_state(&new),
+        opt.threshold,
+        DiffMode::Changes,
+    );
+    if differences.is_empty() {
+        println!("No differences found");
+        return Ok(());
+    }
+    for difference in &differences {
+        println!("{}", difference);
+    }
+    bail!("Found {} differences", differences.len());
+}
+
+/// Rebuild the final state of an evaluation replaying the messages emitted by the JSON UI.
+fn load_report(path: &Path) -> Result<UIState, Error> {
+    let file = std::fs::File::open(path).context("Failed to open the report")?;
+    let mut lines = BufReader::new(file).lines();
+    let header = lines
+        .next()
+        .context("The report is empty")?
+        .context("Failed to read the report")?;
+    let JsonUIHeader::Header { schema_version, .. } =
+        serde_json::from_str(&header).context("Invalid report header")?;
+    check_json_schema_version(&schema_version).context("Unsupported report")?;
+
+    let mut state: Option<UIState> = None;
+    for (index, line) in lines.enumerate() {
+        let line = line.context("Failed to read the report")?;
+        let message: UIMessage = serde_json::from_str(&line)
+            // the header is the first line
+            .with_context(|| format!("Invalid message at line {}", index + 2))?;
+        match (&mut state, message) {
+            (Some(state), message) => state.apply(message),
+            (None, UIMessage::IOITask { task }) => {
+                state = Some(UIState::new(&task, ExecutionDAGConfig::new()))
+            }
+            (None, UIMessage::TerryTask { .. }) => {
+                bail!("Only the reports of IOI tasks are supported")
+            }
+            (None, _) => {}
+        }
+    }
+    state.context("The report does not contain the task")
+}
+
+#[cfg(test)]
+mod tests {
+    use task_maker_format::ui::JSON_SCHEMA_VERSION;
+
+    use super::*;
+
+    fn write_report(dir: &Path, schema_version: &str) -> PathBuf {
+        let header = JsonUIHeader::Header {
+            version: "0.0.0".into(),
+            schema_version: schema_version.into(),
+        };
+        let path = dir.join("report.json");
+        std::fs::write(&path, serde_json::to_string(&header).unwrap() + "\n").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_report_unsupported() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = write_report(tmpdir.path(), "0.1");
+        let error = load_report(&path).unwrap_err().to_string();
+        assert_eq!(error, "Unsupported report");
+    }
+
+    #[test]
+    fn test_load_report_without_task() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = write_report(tmpdir.path(), JSON_SCHEMA_VERSION);
+        let error = load_report(&path).unwrap_err().to_string();
+        assert_eq!(error, "The report does not contain the task");
+    }
+}
@@ -0,0 +1,25 @@
+use anyhow::Error;
+use clap::Parser;
+
+use task_maker_diagnostics::DiagnosticCode;
+
+#[derive(Parser, Debug, Clone)]
+pub struct ExplainOpt {
+    /// The code to explain (e.g. E010). If not specified, all the codes are listed.
+    pub code: Option<DiagnosticCode>,
+}
+
+pub fn main_explain(opt: ExplainOpt) -> Result<(), Error> {
+    match opt.code {
+        Some(code) => {
+            println!("{}: {}", code, code.explanation());
+            println!("Help: {}", code.help());
+        }
+        None => {
+            for code in DiagnosticCode::ALL {
+                println!("{}  {}", code, code.explanation());
+            }
+        }
+    }
+    Ok(())
+}
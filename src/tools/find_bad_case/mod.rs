@@ -53,16 +53,13 @@ pub fn main_find_bad_case(opt: FindBadCaseOpt) -> Result<(), Error> {
     }
 
     let eval_config = EvaluationConfig {
-        solution_filter: vec![],
-        booklet_solutions: false,
         no_statement: true,
         solution_paths: vec![opt.solution.clone()],
         disabled_sanity_checks: get_sanity_check_list()
             .into_iter()
             .map(|check| check.0.into())
             .collect(),
-        seed: None,
-        dry_run: false,
+        ..Default::default()
     };
     let working_directory =
         tempfile::TempDir::new().context("Failed to create working directory")?;
@@ -163,18 +160,29 @@ pub fn main_find_bad_case(opt: FindBadCaseOpt) -> Result<(), Error> {
         // Run the actual computation and block until it ends.
         let sender = sender.clone();
         *current_executor_sender.lock().unwrap() = Some(executor.tx.clone());
-        ExecutorClient::evaluate(
-            dag,
-            executor.tx,
-            &executor.rx,
-            executor.file_store,
-            move |status| {
-                sender
-                    .send(Some(UIMessage::ServerStatus { status }))
-                    .map_err(|e| anyhow!("{:?}", e))
-            },
-        )
-        .with_context(|| {
+        let status_callback = move |status| {
+            sender
+                .send(Some(UIMessage::ServerStatus { status }))
+                .map_err(|e| anyhow!("{:?}", e))
+        };
+        let res = if let Some(link) = &executor.in_process {
+            ExecutorClient::evaluate_in_process(
+                dag,
+                executor.tx,
+                &executor.rx,
+                link,
+                status_callback,
+            )
+        } else {
+            ExecutorClient::evaluate(
+                dag,
+                executor.tx,
+                &executor.rx,
+                executor.file_store,
+                status_callback,
+            )
+        };
+        res.with_context(|| {
             shared_state.write().unwrap().should_stop = true;
             "Client failed"
         })?;
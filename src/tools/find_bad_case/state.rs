@@ -212,8 +212,10 @@ impl UIStateT for UIState {
                 testcase,
                 score,
                 message,
+                verdict,
                 ..
             } => {
+                let message = verdict.unwrap_or(message);
                 if score == 1.0 {
                     set(testcase, TestcaseStatus::Success);
                 } else {
@@ -0,0 +1,111 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Error};
+use clap::Parser;
+
+use crate::history::{find_regressions, history_path, load_history, HistoryEntry};
+use crate::tools::format_age;
+use crate::{FindTaskOpt, StorageOpt};
+
+#[derive(Parser, Debug, Clone)]
+pub struct HistoryOpt {
+    #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
+    pub find_task: FindTaskOpt,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+
+    /// Show only the last this many evaluations.
+    #[clap(long, default_value = "10")]
+    pub last: usize,
+
+    /// Report the testcases where the used time or memory increased by more than this fraction.
+    #[clap(long, default_value = "0.2")]
+    pub threshold: f64,
+}
+
+pub fn main_history(opt: HistoryOpt) -> Result<(), Error> {
+    let task = opt.find_task.find_task(&Default::default())?;
+    let path = history_path(&opt.storage, task.path());
+    let history = load_history(&path)?;
+    if history.is_empty() {
+        println!("No evaluations in the history of the task, evaluate it with --history");
+        return Ok(());
+    }
+    let shown = &history[history.len().saturating_sub(opt.last.max(1))..];
+    println!(
+        "Last {} of the {} evaluations of {}",
+        shown.len(),
+        history.len(),
+        task.path().display()
+    );
+    print_trend(shown);
+
+    let [.., old, new] = &history[..] else {
+        return Ok(());
+    };
+    println!();
+    let regressions = find_regressions(old, new, opt.threshold);
+    if regressions.is_empty() {
+        println!("No regressions since the previous evaluation");
+        return Ok(());
+    }
+    println!(
+        "Regressions since the previous evaluation ({}):",
+        old.version()
+    );
+    for regression in &regressions {
+        println!("{}", regression);
+    }
+    bail!("Found {} regressions", regressions.len());
+}
+
+/// Print, for each solution, its score and the maximum time and memory it used in each of the
+/// evaluations.
+fn print_trend(history: &[HistoryEntry]) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let mut names: Vec<_> = history
+        .iter()
+        .flat_map(|entry| entry.solutions.keys())
+        .collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        println!();
+        println!("{}", name);
+        for entry in history {
+            let Some(solution) = entry.solutions.get(name) else {
+                continue;
+            };
+            let score = match solution.score {
+                Some(score) => format!("{:.2}", score),
+                None => "-".to_string(),
+            };
+            let time = solution
+                .testcases
+                .values()
+                .map(|testcase| testcase.cpu_time)
+                .fold(0.0, f64::max);
+            let memory = solution
+                .testcases
+                .values()
+                .map(|testcase| testcase.memory)
+                .max()
+                .unwrap_or_default();
+            println!(
+                "  {:>10}  {:<9}  score {:>7}  time {:>7.3}s  memory {:>8} KiB",
+                format!(
+                    "{} ago",
+                    format_age(Duration::from_secs(now.saturating_sub(entry.time)))
+                ),
+                entry.version(),
+                score,
+                time,
+                memory
+            );
+        }
+    }
+}
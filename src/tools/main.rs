@@ -3,17 +3,28 @@ use clap::Parser;
 use task_maker_rust::error::NiceError;
 use task_maker_rust::tools::add_solution_checks::main_add_solution_checks;
 use task_maker_rust::tools::booklet::main_booklet;
+use task_maker_rust::tools::cache::main_cache;
+use task_maker_rust::tools::cache_key::main_cache_key;
 use task_maker_rust::tools::clear::main_clear;
+use task_maker_rust::tools::diff_report::main_diff_report;
+use task_maker_rust::tools::explain::main_explain;
 use task_maker_rust::tools::find_bad_case::main_find_bad_case;
 use task_maker_rust::tools::fuzz_checker::main_fuzz_checker;
 use task_maker_rust::tools::gen_autocompletion::main_get_autocompletion;
+use task_maker_rust::tools::history::main_history;
+use task_maker_rust::tools::new_task::main_new_task;
 use task_maker_rust::tools::opt::{Opt, Tool};
 use task_maker_rust::tools::reset::main_reset;
 use task_maker_rust::tools::sandbox::main_sandbox;
+use task_maker_rust::tools::sandbox_shell::main_sandbox_shell;
 use task_maker_rust::tools::server::main_server;
+use task_maker_rust::tools::server_cancel::main_server_cancel;
 use task_maker_rust::tools::task_info::main_task_info;
+use task_maker_rust::tools::terry_grade::main_terry_grade;
 use task_maker_rust::tools::typescriptify::main_typescriptify;
+use task_maker_rust::tools::white_diff::main_white_diff;
 use task_maker_rust::tools::worker::main_worker;
+use task_maker_rust::tools::zip::main_zip;
 
 fn main() {
     let base_opt = Opt::parse();
@@ -23,16 +34,29 @@ fn main() {
         Tool::Clear(opt) => main_clear(opt),
         Tool::GenAutocompletion(opt) => main_get_autocompletion(opt),
         Tool::Server(opt) => main_server(opt),
+        Tool::ServerCancel(opt) => main_server_cancel(opt),
         Tool::Worker(opt) => main_worker(opt),
         Tool::Typescriptify => main_typescriptify(),
         Tool::Reset(opt) => main_reset(opt),
         Tool::Sandbox(opt) => main_sandbox(opt),
+        Tool::SandboxShell(opt) => main_sandbox_shell(opt),
         Tool::TaskInfo(opt) => main_task_info(opt),
         Tool::Booklet(opt) => main_booklet(opt, base_opt.logger),
         Tool::FuzzChecker(opt) => main_fuzz_checker(opt),
         Tool::FindBadCase(opt) => main_find_bad_case(opt),
         Tool::AddSolutionChecks(opt) => main_add_solution_checks(opt, base_opt.logger),
-        Tool::InternalSandbox => return task_maker_rust::main_sandbox(),
+        Tool::CacheKey(opt) => main_cache_key(opt),
+        Tool::Cache(opt) => main_cache(opt),
+        Tool::DiffReport(opt) => main_diff_report(opt),
+        Tool::History(opt) => main_history(opt),
+        Tool::TerryGrade(opt) => main_terry_grade(opt),
+        Tool::Explain(opt) => main_explain(opt),
+        Tool::NewTask(opt) => main_new_task(opt),
+        Tool::InternalSandbox { cgroup, portable } => {
+            return task_maker_rust::main_sandbox(cgroup, portable)
+        }
+        Tool::InternalWhiteDiff { correct, test } => return main_white_diff(&correct, &test),
+        Tool::InternalZip { output, files } => return main_zip(&output, &files),
     }
     .nice_unwrap()
 }
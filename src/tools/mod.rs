@@ -1,13 +1,37 @@
 pub mod add_solution_checks;
 pub mod booklet;
+pub mod cache;
+pub mod cache_key;
 pub mod clear;
+pub mod diff_report;
+pub mod explain;
 pub mod find_bad_case;
 pub mod fuzz_checker;
 pub mod gen_autocompletion;
+pub mod history;
+pub mod new_task;
 pub mod opt;
 pub mod reset;
 pub mod sandbox;
+pub mod sandbox_shell;
 pub mod server;
+pub mod server_cancel;
 pub mod task_info;
+pub mod terry_grade;
 pub mod typescriptify;
+pub mod white_diff;
 pub mod worker;
+pub mod zip;
+
+use std::time::Duration;
+
+/// Format an age in its largest unit, e.g. `3h` or `12d`.
+pub(crate) fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
@@ -0,0 +1,188 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+
+#[derive(Parser, Debug, Clone)]
+pub struct NewTaskOpt {
+    /// Name of the task, it's also the name of the directory to create.
+    pub name: String,
+
+    /// Format of the task: ioi or terry.
+    #[clap(long, default_value = "ioi")]
+    pub format: NewTaskFormat,
+
+    /// Type of the task: batch, communication or output-only. Only for the IOI tasks.
+    #[clap(long = "type", default_value = "batch")]
+    pub task_type: NewTaskType,
+
+    /// Title of the task, shown in the statement.
+    ///
+    /// Defaults to the name of the task.
+    #[clap(long)]
+    pub title: Option<String>,
+
+    /// Directory where to create the task directory.
+    #[clap(long, default_value = ".")]
+    pub parent_dir: PathBuf,
+}
+
+/// The formats of the tasks that can be created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewTaskFormat {
+    /// A task in the italian_yaml format, evaluated on a set of testcases.
+    Ioi,
+    /// A Terry task, whose output files are generated by the contestants from a random input.
+    Terry,
+}
+
+impl std::str::FromStr for NewTaskFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ioi" => Ok(NewTaskFormat::Ioi),
+            "terry" => Ok(NewTaskFormat::Terry),
+            _ => Err(format!("Unknown task format: {}", s)),
+        }
+    }
+}
+
+/// The types of the IOI tasks that can be created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewTaskType {
+    /// The solution reads the input and writes the output, which is scored by a checker.
+    Batch,
+    /// The solution communicates with a manager, which computes the score.
+    Communication,
+    /// The contestants submit the output files, which are scored by a checker.
+    OutputOnly,
+}
+
+impl std::str::FromStr for NewTaskType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "batch" => Ok(NewTaskType::Batch),
+            "communication" => Ok(NewTaskType::Communication),
+            "output-only" => Ok(NewTaskType::OutputOnly),
+            _ => Err(format!("Unknown task type: {}", s)),
+        }
+    }
+}
+
+mod ioi {
+    pub const GEN: &str = include_str!("./new_task/ioi/GEN");
+    pub const GENERATOR: &str = include_str!("./new_task/ioi/generator.py");
+    pub const VALIDATOR: &str = include_str!("./new_task/ioi/validator.py");
+    pub const CHECKER: &str = include_str!("./new_task/ioi/checker.cpp");
+    pub const MANAGER: &str = include_str!("./new_task/ioi/manager.cpp");
+    pub const SOLUTION_CPP: &str = include_str!("./new_task/ioi/solution.cpp");
+    pub const SOLUTION_PY: &str = include_str!("./new_task/ioi/solution.py");
+    pub const STATEMENT: &str = include_str!("./new_task/ioi/statement.tex");
+}
+
+mod terry {
+    pub const GENERATOR: &str = include_str!("./new_task/terry/generator.py");
+    pub const VALIDATOR: &str = include_str!("./new_task/terry/validator.py");
+    pub const CHECKER: &str = include_str!("./new_task/terry/checker.py");
+    pub const SOLUTION: &str = include_str!("./new_task/terry/solution.py");
+    pub const STATEMENT: &str = include_str!("./new_task/terry/statement.md");
+}
+
+pub fn main_new_task(opt: NewTaskOpt) -> Result<(), Error> {
+    if opt.name.is_empty()
+        || !opt
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        bail!(
+            "Invalid task name {:?}: only letters, digits, '_' and '-' are allowed",
+            opt.name
+        );
+    }
+    let task_dir = opt.parent_dir.join(&opt.name);
+    if task_dir.exists() {
+        bail!("{} already exists", task_dir.display());
+    }
+    let title = opt.title.as_deref().unwrap_or(&opt.name);
+
+    let files = match opt.format {
+        NewTaskFormat::Ioi => ioi_files(&opt.name, title, opt.task_type),
+        NewTaskFormat::Terry => {
+            if opt.task_type != NewTaskType::Batch {
+                bail!("--type is supported only by the IOI tasks");
+            }
+            terry_files(&opt.name, title)
+        }
+    };
+    for (path, content) in files {
+        write_file(&task_dir.join(path), &content)?;
+    }
+    println!("Task {} created in {}", opt.name, task_dir.display());
+    Ok(())
+}
+
+/// The files of a new IOI task, with their content.
+fn ioi_files(name: &str, title: &str, task_type: NewTaskType) -> Vec<(&'static str, String)> {
+    let mut yaml = format!("name: {}\ntitle: {:?}\n", name, title);
+    match task_type {
+        NewTaskType::Batch => {
+            yaml += "time_limit: 1\nmemory_limit: 256\ninfile: \"\"\noutfile: \"\"\n";
+        }
+        NewTaskType::Communication => {
+            yaml += "time_limit: 1\nmemory_limit: 256\ninfile: input.txt\nuser_io: std_io\n";
+        }
+        NewTaskType::OutputOnly => {
+            yaml += "output_only: true\ninfile: \"\"\noutfile: \"\"\n";
+        }
+    }
+    let mut files = vec![
+        ("task.yaml", yaml),
+        ("gen/GEN", ioi::GEN.to_string()),
+        ("gen/generator.py", ioi::GENERATOR.to_string()),
+        ("gen/validator.py", ioi::VALIDATOR.to_string()),
+        ("sol/solution.cpp", ioi::SOLUTION_CPP.to_string()),
+        ("sol/solution_python.py", ioi::SOLUTION_PY.to_string()),
+        ("statement/english.tex", ioi::STATEMENT.to_string()),
+    ];
+    match task_type {
+        NewTaskType::Batch | NewTaskType::OutputOnly => {
+            files.push(("check/checker.cpp", ioi::CHECKER.to_string()))
+        }
+        NewTaskType::Communication => files.push(("check/manager.cpp", ioi::MANAGER.to_string())),
+    }
+    files
+}
+
+/// The files of a new Terry task, with their content. The official solution in managers/ is copied
+/// in the sandboxes of the other managers, the one in solutions/ is evaluated like the others.
+fn terry_files(name: &str, title: &str) -> Vec<(&'static str, String)> {
+    let yaml = format!("name: {}\ndescription: {:?}\nmax_score: 100\n", name, title);
+    vec![
+        ("task.yaml", yaml),
+        ("managers/generator.py", terry::GENERATOR.to_string()),
+        ("managers/validator.py", terry::VALIDATOR.to_string()),
+        ("managers/checker.py", terry::CHECKER.to_string()),
+        ("managers/solution.py", terry::SOLUTION.to_string()),
+        ("solutions/solution.py", terry::SOLUTION.to_string()),
+        ("statement/statement.md", terry::STATEMENT.to_string()),
+    ]
+}
+
+/// Write a file of the new task, creating its parent directory. The scripts are made executable.
+fn write_file(path: &Path, content: &str) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    if content.starts_with("#!") {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make {} executable", path.display()))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,28 @@
+// Checker of the output files. It's called with the paths of the input file, of the correct output
+// file and of the output file of the contestant. It prints the score (between 0.0 and 1.0) to
+// stdout and the message for the contestant to stderr.
+
+#include <fstream>
+#include <iostream>
+
+int main(int argc, char** argv) {
+  if (argc != 4) {
+    std::cerr << "Usage: checker <input> <correct output> <test output>" << std::endl;
+    return 1;
+  }
+  std::ifstream correct(argv[2]);
+  std::ifstream test(argv[3]);
+
+  long long expected, answer;
+  correct >> expected;
+  if (!(test >> answer)) {
+    std::cout << 0.0 << std::endl;
+    std::cerr << "Output not found" << std::endl;
+  } else if (answer == expected) {
+    std::cout << 1.0 << std::endl;
+    std::cerr << "Output is correct" << std::endl;
+  } else {
+    std::cout << 0.0 << std::endl;
+    std::cerr << "Output is incorrect" << std::endl;
+  }
+}
@@ -0,0 +1,19 @@
+#!/usr/bin/env python3
+
+# Generator of the input files. It's called with the arguments of a line of gen/GEN, and it prints
+# the input file to stdout. Use the seed for generating the same input file every time.
+
+import random
+import sys
+
+
+def main():
+    n, seed = map(int, sys.argv[1:])
+    random.seed(seed)
+    values = [random.randint(1, 10**9) for _ in range(n)]
+    print(n)
+    print(*values)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,40 @@
+// Manager of the communication with the solution. It reads the input file, it's called with the
+// paths of the pipes from and to the solution, and it prints the score (between 0.0 and 1.0) to
+// stdout and the message for the contestant to stderr.
+
+#include <signal.h>
+
+#include <cstdio>
+
+int main(int argc, char** argv) {
+  signal(SIGPIPE, SIG_IGN);
+
+  FILE* fin = fopen("input.txt", "r");
+  FILE* to_solution = fopen(argv[2], "w");
+  FILE* from_solution = fopen(argv[1], "r");
+
+  int n;
+  if (fscanf(fin, "%d", &n) != 1) return 1;
+  fprintf(to_solution, "%d\n", n);
+  long long expected = 0;
+  for (int i = 0; i < n; i++) {
+    long long value;
+    if (fscanf(fin, "%lld", &value) != 1) return 1;
+    fprintf(to_solution, "%lld ", value);
+    expected += value;
+  }
+  fprintf(to_solution, "\n");
+  fflush(to_solution);
+
+  long long answer;
+  if (fscanf(from_solution, "%lld", &answer) != 1) {
+    printf("0.0\n");
+    fprintf(stderr, "Output not found\n");
+  } else if (answer == expected) {
+    printf("1.0\n");
+    fprintf(stderr, "Output is correct\n");
+  } else {
+    printf("0.0\n");
+    fprintf(stderr, "Output is incorrect\n");
+  }
+}
@@ -0,0 +1,11 @@
+#!/usr/bin/env python3
+
+
+def main():
+    n = int(input())
+    values = list(map(int, input().split()))
+    print(sum(values[:n]))
+
+
+if __name__ == "__main__":
+    main()
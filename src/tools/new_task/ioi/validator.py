@@ -0,0 +1,27 @@
+#!/usr/bin/env python3
+
+# Validator of the input files. It's called with the path of the input file and the number of the
+# subtask (starting from 1), and it exits with an error if the input file is not valid.
+
+import sys
+
+MAX_N = 1000
+MAX_V = 10**9
+
+
+def main():
+    path, subtask = sys.argv[1], int(sys.argv[2])
+    with open(path) as f:
+        lines = f.read().splitlines()
+    assert len(lines) == 2, "Expected 2 lines"
+    n = int(lines[0])
+    assert 1 <= n <= MAX_N, "N out of range"
+    values = list(map(int, lines[1].split()))
+    assert len(values) == n, "Expected N values"
+    assert all(1 <= v <= MAX_V for v in values), "Value out of range"
+    if subtask == 1:
+        pass  # add here the constraints of the subtask
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,76 @@
+#!/usr/bin/env python3
+
+# Checker of the output files. It's called with the paths of the input file and of the output file
+# of the contestant, and it prints to stdout the outcome as JSON:
+#
+# {
+#   "score": 0.7,  // between 0.0 and 1.0
+#   "validation": {
+#     "cases": [{"status": "parsed", "message": "..."}, ...],  // or "missing" / "invalid"
+#     "alerts": [{"severity": "warning", "message": "..."}, ...]
+#   },
+#   "feedback": {
+#     "cases": [{"correct": true, "message": "..."}, ...],
+#     "alerts": [{"severity": "warning", "message": "..."}, ...]
+#   }
+# }
+#
+# The validation is shown to the contestant before the submission, the feedback after it. The
+# output file is sent by the contestant, so the checker must not crash on any content.
+
+import json
+import re
+import sys
+
+CASE_LINE = re.compile(r"^Case #(\d+): *(\S+)$")
+
+
+def main():
+    input_path, output_path = sys.argv[1:]
+    with open(input_path) as f:
+        lines = f.read().splitlines()
+    num_cases = int(lines[0])
+    expected = [sum(map(int, lines[2 * case + 2].split())) for case in range(num_cases)]
+
+    answers = [None] * num_cases
+    validation = [{"status": "missing", "message": "Case not found"} for _ in range(num_cases)]
+    alerts = []
+    with open(output_path, errors="replace") as f:
+        for line in f.read().splitlines():
+            match = CASE_LINE.match(line.strip())
+            if not match:
+                if line.strip():
+                    message = "Invalid line: %s" % line[:50]
+                    alerts.append({"severity": "warning", "message": message})
+                continue
+            case = int(match.group(1))
+            if not 1 <= case <= num_cases:
+                alerts.append({"severity": "warning", "message": "Invalid case number %d" % case})
+                continue
+            try:
+                answers[case - 1] = int(match.group(2))
+                validation[case - 1] = {"status": "parsed", "message": None}
+            except ValueError:
+                validation[case - 1] = {"status": "invalid", "message": "Not an integer"}
+
+    feedback = []
+    for answer, correct in zip(answers, expected):
+        if answer == correct:
+            feedback.append({"correct": True, "message": "Output is correct"})
+        else:
+            feedback.append({"correct": False, "message": "Output is incorrect"})
+    score = sum(case["correct"] for case in feedback) / num_cases
+
+    print(
+        json.dumps(
+            {
+                "score": score,
+                "validation": {"cases": validation, "alerts": alerts},
+                "feedback": {"cases": feedback, "alerts": []},
+            }
+        )
+    )
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,26 @@
+#!/usr/bin/env python3
+
+# Generator of the input files. It's called with the seed of the input file and with 0, and it
+# prints the input file to stdout. The same seed must always generate the same input file.
+
+import random
+import sys
+
+NUM_CASES = 10
+MAX_N = 1000
+MAX_V = 10**9
+
+
+def main():
+    seed = int(sys.argv[1])
+    random.seed(seed)
+    print(NUM_CASES)
+    for _ in range(NUM_CASES):
+        n = random.randint(1, MAX_N)
+        values = [random.randint(1, MAX_V) for _ in range(n)]
+        print(n)
+        print(*values)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,15 @@
+#!/usr/bin/env python3
+
+# Solution: it reads the input file from stdin and prints the output file to stdout.
+
+
+def main():
+    t = int(input())
+    for case in range(1, t + 1):
+        input()
+        values = list(map(int, input().split()))
+        print("Case #%d: %d" % (case, sum(values)))
+
+
+if __name__ == "__main__":
+    main()
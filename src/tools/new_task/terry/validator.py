@@ -0,0 +1,27 @@
+#!/usr/bin/env python3
+
+# Validator of the input files. It reads the input file from stdin, and it exits with an error if
+# the input file is not valid.
+
+import sys
+
+MAX_T = 100
+MAX_N = 1000
+MAX_V = 10**9
+
+
+def main():
+    lines = sys.stdin.read().splitlines()
+    t = int(lines[0])
+    assert 1 <= t <= MAX_T, "T out of range"
+    assert len(lines) == 2 * t + 1, "Expected 2 lines for each case"
+    for case in range(t):
+        n = int(lines[2 * case + 1])
+        assert 1 <= n <= MAX_N, "N out of range"
+        values = list(map(int, lines[2 * case + 2].split()))
+        assert len(values) == n, "Expected N values"
+        assert all(1 <= v <= MAX_V for v in values), "Value out of range"
+
+
+if __name__ == "__main__":
+    main()
@@ -1,15 +1,26 @@
+use std::path::PathBuf;
+
 use clap::Parser;
 
 use crate::tools::add_solution_checks::AddSolutionChecksOpt;
 use crate::tools::booklet::BookletOpt;
+use crate::tools::cache::CacheOpt;
+use crate::tools::cache_key::CacheKeyOpt;
 use crate::tools::clear::ClearOpt;
+use crate::tools::diff_report::DiffReportOpt;
+use crate::tools::explain::ExplainOpt;
 use crate::tools::find_bad_case::FindBadCaseOpt;
 use crate::tools::fuzz_checker::FuzzCheckerOpt;
 use crate::tools::gen_autocompletion::GenAutocompletionOpt;
+use crate::tools::history::HistoryOpt;
+use crate::tools::new_task::NewTaskOpt;
 use crate::tools::reset::ResetOpt;
 use crate::tools::sandbox::SandboxOpt;
+use crate::tools::sandbox_shell::SandboxShellOpt;
 use crate::tools::server::ServerOpt;
+use crate::tools::server_cancel::ServerCancelOpt;
 use crate::tools::task_info::TaskInfoOpt;
+use crate::tools::terry_grade::TerryGradeOpt;
 use crate::tools::worker::WorkerOpt;
 use crate::LoggerOpt;
 
@@ -32,6 +43,11 @@ pub enum Tool {
     GenAutocompletion(GenAutocompletionOpt),
     /// Spawn an instance of the server
     Server(ServerOpt),
+    /// Cancel an evaluation running on a server, or list the running ones.
+    ///
+    /// The executions of the evaluation are killed, its workers are released and its client exits
+    /// with an error.
+    ServerCancel(ServerCancelOpt),
     /// Spawn an instance of a worker
     Worker(WorkerOpt),
     /// Print the TypeScript type definitions
@@ -42,6 +58,12 @@ pub enum Tool {
     Reset(ResetOpt),
     /// Run a command inside a sandbox similar to the one used by task-maker
     Sandbox(SandboxOpt),
+    /// Open a shell inside the sandbox of the evaluation of a solution on a testcase.
+    ///
+    /// The evaluation is run again keeping its sandbox, then a shell is started inside it with the
+    /// same mounts and environment. Useful for debugging the solutions that behave differently
+    /// inside the sandbox.
+    SandboxShell(SandboxShellOpt),
     /// Obtain the information about a task.
     TaskInfo(TaskInfoOpt),
     /// Compile just the booklet for a task or a contest.
@@ -52,9 +74,70 @@ pub enum Tool {
     FindBadCase(FindBadCaseOpt),
     /// Add the @check comments to the solutions.
     AddSolutionChecks(AddSolutionChecksOpt),
+    /// Print the components of the cache keys of the executions of a task.
+    ///
+    /// Useful for finding out why an execution doesn't hit the cache between two runs.
+    CacheKey(CacheKeyOpt),
+    /// Export the cache to an archive, import it from one, or list and remove its entries.
+    ///
+    /// The archive contains the cache entries and their output files, and can be carried to a
+    /// machine without network access for reusing the evaluations done elsewhere.
+    ///
+    /// Warning: no other instances of task-maker should be running while importing an archive.
+    Cache(CacheOpt),
+    /// Compare the JSON UI outputs of two evaluations of an IOI task.
+    ///
+    /// Prints the solutions and the testcases whose verdict or score changed, and the testcases
+    /// whose resource usage increased too much. Exits with an error if any difference is found.
+    DiffReport(DiffReportOpt),
+    /// Print the history of the evaluations of a task, recorded with --history.
+    ///
+    /// For each solution, prints the score and the resources used in the last evaluations, with
+    /// the git commit of the task. Exits with an error if the last evaluation has some regressions
+    /// with respect to the previous one.
+    History(HistoryOpt),
+    /// Grade offline the output files submitted by the contestants of a Terry task.
+    ///
+    /// The input files are generated again from the seeds of the submissions, and the ranking of
+    /// the users is printed as CSV or JSON.
+    TerryGrade(TerryGradeOpt),
+    /// Explain the code of a diagnostic (e.g. E010), or list all the codes.
+    Explain(ExplainOpt),
+    /// Create the skeleton of a new IOI or Terry task.
+    ///
+    /// The task directory contains the task.yaml, a generator, a validator, a checker (or a
+    /// manager), some solutions and a statement, ready to be evaluated.
+    NewTask(NewTaskOpt),
     /// Run the sandbox instead of the normal task-maker.
     ///
     /// This option is left as undocumented as it's not part of the public API.
     #[clap(hide = true)]
-    InternalSandbox,
+    InternalSandbox {
+        /// Run the sandbox inside this cgroup.
+        #[clap(long)]
+        cgroup: Option<PathBuf>,
+        /// Run the execution with the portable sandbox, without isolating it.
+        #[clap(long)]
+        portable: bool,
+    },
+    /// Compare two files like the default checker, exiting with 0 if they are equal.
+    ///
+    /// This option is left as undocumented as it's not part of the public API.
+    #[clap(hide = true)]
+    InternalWhiteDiff {
+        /// The correct output file.
+        correct: PathBuf,
+        /// The output file to check.
+        test: PathBuf,
+    },
+    /// Pack some files in a zip file, like the attachment packages.
+    ///
+    /// This option is left as undocumented as it's not part of the public API.
+    #[clap(hide = true)]
+    InternalZip {
+        /// The zip file to write.
+        output: PathBuf,
+        /// The files to pack, stored with their file name.
+        files: Vec<PathBuf>,
+    },
 }
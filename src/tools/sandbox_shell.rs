@@ -0,0 +1,292 @@
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions, Permissions};
+use std::io::{Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Error};
+use clap::{Parser, ValueHint};
+use libc::STDIN_FILENO;
+use nix::fcntl::OFlag;
+#[cfg(not(target_os = "linux"))]
+use nix::pty::ptsname;
+#[cfg(target_os = "linux")]
+use nix::pty::ptsname_r;
+use nix::pty::{grantpt, posix_openpt, unlockpt};
+use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+use nix::unistd::isatty;
+use tabox::{Sandbox as _, SandboxImplementation};
+
+use task_maker_dag::{Execution, ExecutionTag};
+use task_maker_exec::sandbox::Sandbox;
+use task_maker_format::ui::UIType;
+use task_maker_format::{get_sanity_check_list, EvaluationConfig, TaskFormat};
+
+use crate::context::RuntimeContext;
+use crate::{ExecutionOpt, FindTaskOpt, StorageOpt};
+
+#[derive(Parser, Debug, Clone)]
+#[clap(trailing_var_arg = true)]
+pub struct SandboxShellOpt {
+    #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
+    pub find_task: FindTaskOpt,
+
+    #[clap(flatten, next_help_heading = Some("EXECUTION"))]
+    pub execution: ExecutionOpt,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+
+    /// The solution whose evaluation sandbox is replicated.
+    #[clap(value_hint = ValueHint::FilePath)]
+    pub solution: PathBuf,
+
+    /// The testcase whose evaluation sandbox is replicated.
+    #[clap(long, short)]
+    pub testcase: u32,
+
+    /// Apply the time, memory and stack limits of the evaluation to the shell.
+    ///
+    /// The wall time limit is never applied.
+    #[clap(long)]
+    pub limits: bool,
+
+    /// Command to execute inside the sandbox. If not specified, bash is executed.
+    pub command: Vec<String>,
+}
+
+pub fn main_sandbox_shell(mut opt: SandboxShellOpt) -> Result<(), Error> {
+    if !opt.solution.exists() {
+        bail!("Cannot find solution at {}", opt.solution.display());
+    }
+    if !opt.execution.evaluate_on.is_empty() {
+        bail!("The sandbox shell is not available with a remote evaluation");
+    }
+    let eval_config = EvaluationConfig {
+        no_statement: true,
+        solution_paths: vec![opt.solution.clone()],
+        disabled_sanity_checks: get_sanity_check_list()
+            .into_iter()
+            .map(|check| check.0.into())
+            .collect(),
+        testcase_filter: vec![opt.testcase],
+        ..Default::default()
+    };
+    let task = opt
+        .find_task
+        .find_task(&eval_config)
+        .context("Failed to locate the task")?;
+    if !matches!(task, TaskFormat::IOI(_)) {
+        bail!("The sandbox shell only supports IOI tasks");
+    }
+
+    // The evaluation is run again, without using the cache, keeping only the sandbox of the
+    // solution. The sandbox is then found by the UUID of its execution.
+    opt.execution.no_cache = Some(Some("evaluation".into()));
+    let mut target = None;
+    let context = RuntimeContext::new(task, &opt.execution, |task, eval| {
+        task.build_dag(eval, &eval_config)
+            .context("Cannot build the task DAG")?;
+        let exec = eval
+            .dag
+            .data
+            .execution_groups
+            .values_mut()
+            .flat_map(|group| group.executions.iter_mut())
+            .filter(|exec| exec.tag == Some(ExecutionTag::from("evaluation")))
+            .min_by(|a, b| a.description.cmp(&b.description));
+        target = exec.map(|exec| exec.keep_sandbox().uuid);
+        Ok(())
+    })?;
+    let Some(target) = target else {
+        bail!(
+            "The solution is not evaluated on testcase {}, does the testcase exist?",
+            opt.testcase
+        );
+    };
+
+    let sandboxes_dir = opt.storage.store_dir().join("sandboxes");
+    let old_sandboxes = list_sandboxes(&sandboxes_dir)?;
+    let executor = context.connect_executor(&opt.execution, &opt.storage)?;
+    let executor = executor.start_ui(&UIType::Silent, |ui, mex| ui.on_message(mex))?;
+    executor.execute()?;
+
+    // the other kept sandboxes may belong to other instances of task-maker, they are not touched
+    let found = list_sandboxes(&sandboxes_dir)?
+        .difference(&old_sandboxes)
+        .find_map(|boxdir| match read_execution(boxdir) {
+            Some(execution) if execution.uuid == target => Some((boxdir.clone(), execution)),
+            _ => None,
+        });
+    let Some((boxdir, execution)) = found else {
+        bail!("The evaluation of the solution did not run, did its compilation fail?");
+    };
+    // the sandbox has been created just for the shell, it's removed when the shell exits
+    defer! {
+        if let Err(e) = remove_sandbox(&boxdir) {
+            warn!("Failed to remove sandbox {}: {:?}", boxdir.display(), e);
+        }
+    }
+
+    let command = if opt.command.is_empty() {
+        vec!["/bin/bash".to_string()]
+    } else {
+        opt.command.clone()
+    };
+    let mut config = Sandbox::interactive_configuration(&boxdir, &execution, &command, opt.limits)
+        .context("Failed to configure the sandbox")?;
+    eprintln!("Sandbox of '{}'", execution.description);
+    eprintln!("The sandbox directory is {}", boxdir.display());
+
+    if !isatty(STDIN_FILENO).unwrap_or(false) {
+        debug!("Config: {:#?}", config);
+        let sandbox = SandboxImplementation::run(config).context("Failed to create sandbox")?;
+        let res = sandbox.wait().context("Failed to wait sandbox")?;
+        debug!("Result: {:#?}", res);
+        return Ok(());
+    }
+
+    // The command runs on a new pseudo terminal, whose master side is connected to the terminal of
+    // the current process, put in raw mode so that every key is forwarded to the command.
+    let pty = Pty::open().context("Failed to allocate a pseudo terminal")?;
+    config.stdin(&pty.slave_path);
+    config.stdout(&pty.slave_path);
+    config.stderr(&pty.slave_path);
+    debug!("Config: {:#?}", config);
+    let terminal = tcgetattr(STDIN_FILENO).context("Failed to get the terminal attributes")?;
+    let mut raw = terminal.clone();
+    cfmakeraw(&mut raw);
+    tcsetattr(STDIN_FILENO, SetArg::TCSANOW, &raw)
+        .context("Failed to set the terminal in raw mode")?;
+    defer! {
+        if let Err(e) = tcsetattr(STDIN_FILENO, SetArg::TCSANOW, &terminal) {
+            warn!("Failed to restore the terminal: {:?}", e);
+        }
+    }
+
+    let sandbox = SandboxImplementation::run(config).context("Failed to create sandbox")?;
+    let res = pty.relay(|| sandbox.wait().context("Failed to wait sandbox"))?;
+    debug!("Result: {:#?}", res);
+    Ok(())
+}
+
+/// A pseudo terminal: the command inside the sandbox opens its slave side by path, the master side
+/// is relayed to the terminal of the current process.
+struct Pty {
+    /// The master side of the pseudo terminal.
+    master: File,
+    /// The slave side of the pseudo terminal, kept open until the command exits: while no process
+    /// has it open, the reads from the master fail.
+    slave: File,
+    /// The path of the slave side of the pseudo terminal.
+    slave_path: PathBuf,
+}
+
+impl Pty {
+    /// Allocate a new pseudo terminal, with the same size of the current terminal.
+    fn open() -> Result<Pty, Error> {
+        let master = posix_openpt(OFlag::O_RDWR | OFlag::O_NOCTTY)?;
+        grantpt(&master)?;
+        unlockpt(&master)?;
+        #[cfg(target_os = "linux")]
+        let slave_path = ptsname_r(&master)?;
+        // SAFETY: ptsname is not reentrant, but no other thread allocates pseudo terminals
+        #[cfg(not(target_os = "linux"))]
+        let slave_path = unsafe { ptsname(&master)? };
+        // SAFETY: the file descriptor comes from `into_raw_fd`, so it's owned only by the file
+        let master = unsafe { File::from_raw_fd(master.into_raw_fd()) };
+        let slave = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&slave_path)
+            .with_context(|| format!("Failed to open {}", slave_path))?;
+        let mut size = libc::winsize {
+            ws_row: 0,
+            ws_col: 0,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        // SAFETY: TIOCGWINSZ and TIOCSWINSZ only read and write the `winsize` struct
+        unsafe {
+            if libc::ioctl(STDIN_FILENO, libc::TIOCGWINSZ, &mut size) == 0 {
+                libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &size);
+            }
+        }
+        Ok(Pty {
+            master,
+            slave,
+            slave_path: slave_path.into(),
+        })
+    }
+
+    /// Copy the input of the current process to the pseudo terminal and its output to the output
+    /// of the current process while `wait` waits for the command to exit.
+    fn relay<T, F: FnOnce() -> Result<T, Error>>(self, wait: F) -> Result<T, Error> {
+        let mut input = self.master.try_clone()?;
+        let mut output = self.master;
+        // this thread cannot be stopped while it's blocked reading, it ends with the process
+        std::thread::Builder::new()
+            .name("Sandbox shell input".into())
+            .spawn(move || std::io::copy(&mut std::io::stdin(), &mut input))
+            .context("Failed to spawn the input thread")?;
+        let output_thread = std::thread::Builder::new()
+            .name("Sandbox shell output".into())
+            .spawn(move || {
+                let mut stdout = std::io::stdout();
+                let mut buffer = [0; 4096];
+                // when the command exits (and the slave is closed) the read fails with EIO
+                loop {
+                    let n = match output.read(&mut buffer) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => n,
+                    };
+                    if stdout.write_all(&buffer[..n]).is_err() || stdout.flush().is_err() {
+                        break;
+                    }
+                }
+            })
+            .context("Failed to spawn the output thread")?;
+        let res = wait();
+        drop(self.slave);
+        output_thread
+            .join()
+            .map_err(|e| anyhow!("Output thread panicked: {:?}", e))?;
+        res
+    }
+}
+
+/// Remove a kept sandbox directory.
+fn remove_sandbox(boxdir: &Path) -> Result<(), Error> {
+    // the box directory may have been made read-only for the execution
+    let boxroot = boxdir.join("box");
+    if boxroot.exists() {
+        std::fs::set_permissions(&boxroot, Permissions::from_mode(0o700))
+            .with_context(|| format!("Failed to chmod {}", boxroot.display()))?;
+    }
+    std::fs::remove_dir_all(boxdir)
+        .with_context(|| format!("Failed to remove {}", boxdir.display()))
+}
+
+/// List the sandbox directories currently inside `dir`.
+fn list_sandboxes(dir: &Path) -> Result<HashSet<PathBuf>, Error> {
+    if !dir.exists() {
+        return Ok(HashSet::new());
+    }
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    let mut sandboxes = HashSet::new();
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Failed to read {}", dir.display()))?;
+        if entry.file_type()?.is_dir() {
+            sandboxes.insert(entry.path());
+        }
+    }
+    Ok(sandboxes)
+}
+
+/// Read the execution of a sandbox, if it has been kept.
+fn read_execution(boxdir: &Path) -> Option<Execution> {
+    let info = std::fs::read_to_string(boxdir.join("info.json")).ok()?;
+    serde_json::from_str(&info).ok()
+}
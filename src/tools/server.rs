@@ -1,10 +1,12 @@
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
 use clap::Parser;
 
-use task_maker_cache::Cache;
-use task_maker_exec::executors::RemoteExecutor;
+use task_maker_cache::{Cache, RemoteCache, RemoteCacheServer};
+use task_maker_exec::executors::{Capabilities, RemoteExecutor};
+use task_maker_exec::AutoscalerConfig;
 use task_maker_store::FileStore;
 
 use crate::StorageOpt;
@@ -27,8 +29,66 @@ pub struct ServerOpt {
     #[clap(long = "worker-password")]
     pub worker_password: Option<String>,
 
+    /// Address to bind the server on for sharing its cache with the clients
+    ///
+    /// The clients use it passing --remote-cache tcp://[:password@]<server>:<port>, with the
+    /// password of the clients. The remote cache is read-only: it contains only the results of the
+    /// executions run by this server, stored in the remote-cache directory inside the storage
+    /// directory, which is never flushed.
+    #[clap(long = "remote-cache-addr")]
+    pub remote_cache_addr: Option<String>,
+
+    /// Do not compress the files exchanged with the clients and the workers, even if they allow it
+    #[clap(long = "no-compression")]
+    pub no_compression: bool,
+
     #[clap(flatten, next_help_heading = Some("STORAGE"))]
     pub storage: StorageOpt,
+
+    #[clap(flatten, next_help_heading = Some("AUTOSCALING"))]
+    pub autoscaling: AutoscalingOpt,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct AutoscalingOpt {
+    /// Shell command to run for starting more workers when too many jobs are waiting
+    ///
+    /// The command knows the load of the server from the TM_WAITING_JOBS, TM_WORKERS and
+    /// TM_BUSY_WORKERS environment variables.
+    #[clap(long = "spawn-command")]
+    pub spawn_command: Option<String>,
+
+    /// Shell command to run for stopping the workers when they have been idle for long
+    #[clap(long = "despawn-command")]
+    pub despawn_command: Option<String>,
+
+    /// Number of waiting jobs from which the spawn command is run
+    #[clap(long = "spawn-threshold", default_value = "10")]
+    pub spawn_threshold: usize,
+
+    /// Minimum number of seconds between two runs of the spawn command
+    #[clap(long = "spawn-cooldown", default_value = "300")]
+    pub spawn_cooldown: u64,
+
+    /// Number of minutes the workers have to be idle before running the despawn command
+    #[clap(long = "despawn-idle-minutes", default_value = "10")]
+    pub despawn_idle_minutes: u64,
+}
+
+impl AutoscalingOpt {
+    /// The configuration of the autoscaler, if any hook is set.
+    fn config(&self) -> Option<AutoscalerConfig> {
+        if self.spawn_command.is_none() && self.despawn_command.is_none() {
+            return None;
+        }
+        Some(AutoscalerConfig {
+            spawn_command: self.spawn_command.clone(),
+            despawn_command: self.despawn_command.clone(),
+            spawn_threshold: self.spawn_threshold,
+            spawn_cooldown: Duration::from_secs(self.spawn_cooldown),
+            idle_timeout: Duration::from_secs(self.despawn_idle_minutes * 60),
+        })
+    }
 }
 
 /// Entry point for the server.
@@ -43,9 +103,53 @@ pub fn main_server(opt: ServerOpt) -> Result<(), Error> {
         )
         .context("Cannot create the file store")?,
     );
-    let cache = Cache::new(store_path.join("cache")).context("Cannot create the cache")?;
+    let mut cache = Cache::new(store_path.join("cache")).context("Cannot create the cache")?;
+    let remote = match (&opt.storage.remote_cache, &opt.remote_cache_addr) {
+        (Some(_), Some(_)) => bail!("--remote-cache cannot be used with --remote-cache-addr"),
+        (Some(remote), None) => Some(remote.clone()),
+        // the results of the executions are published to the directory shared with the clients
+        (None, Some(_)) => Some(RemoteCache::Directory(store_path.join("remote-cache"))),
+        (None, None) => None,
+    };
+    if let Some(remote) = remote {
+        cache
+            .set_remote(remote, file_store.clone())
+            .context("Cannot setup the remote cache")?;
+    }
+
+    if let Some(addr) = opt.remote_cache_addr.clone() {
+        let dir = store_path.join("remote-cache");
+        let password = opt.client_password.clone();
+        let (bound_sender, bound_receiver) = std::sync::mpsc::channel();
+        std::thread::Builder::new()
+            .name("Remote cache server".into())
+            .spawn(
+                move || match RemoteCacheServer::bind(dir, &addr, password.as_deref()) {
+                    Ok(server) => {
+                        let _ = bound_sender.send(Ok(()));
+                        if let Err(e) = server.serve() {
+                            error!("The remote cache server failed: {:?}", e);
+                        }
+                    }
+                    Err(e) => {
+                        let _ = bound_sender.send(Err(e));
+                    }
+                },
+            )
+            .context("Failed to spawn the remote cache server")?;
+        bound_receiver
+            .recv()
+            .context("The remote cache server thread is gone")??;
+    }
 
-    let remote_executor = RemoteExecutor::new(file_store);
+    let mut remote_executor = RemoteExecutor::new(file_store);
+    if let Some(config) = opt.autoscaling.config() {
+        remote_executor = remote_executor.with_autoscaler(config);
+    }
+    let mut capabilities = Capabilities::all();
+    if opt.no_compression {
+        capabilities.remove(Capabilities::COMPRESSION);
+    }
 
     remote_executor.start(
         &opt.client_addr,
@@ -53,5 +157,6 @@ pub fn main_server(opt: ServerOpt) -> Result<(), Error> {
         opt.client_password,
         opt.worker_password,
         cache,
+        capabilities,
     )
 }
@@ -0,0 +1,60 @@
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+
+use task_maker_exec::{ClientUuid, ExecutorClient};
+
+use crate::remote::{connect_to_remote_server, send_welcome, Welcome};
+
+#[derive(Parser, Debug, Clone)]
+pub struct ServerCancelOpt {
+    /// Address of the server, the same passed to --evaluate-on
+    pub server_addr: String,
+
+    /// The id of the evaluation to cancel, as printed in the log of the server
+    ///
+    /// Without it, the evaluations with some executions running on the workers are listed.
+    pub evaluation: Option<ClientUuid>,
+
+    /// The name to use for this client in the server
+    #[clap(long)]
+    pub name: Option<String>,
+}
+
+/// Entry point for cancelling an evaluation running on a server.
+pub fn main_server_cancel(opt: ServerCancelOpt) -> Result<(), Error> {
+    let (tx, rx) = connect_to_remote_server(&opt.server_addr, 27182)
+        .with_context(|| format!("Cannot connect to the remote server at {}", opt.server_addr))?;
+    let name = opt.name.unwrap_or_else(|| {
+        format!(
+            "{}@{}",
+            whoami::username(),
+            whoami::fallible::hostname().unwrap()
+        )
+    });
+    send_welcome(&tx, &rx, Welcome::client(name), "client")?;
+    let (tx, rx) = (tx.change_type(), rx.change_type());
+
+    let Some(evaluation) = opt.evaluation else {
+        let status = ExecutorClient::status(&tx, &rx)?;
+        let mut evaluations: Vec<_> = status
+            .connected_workers
+            .into_iter()
+            .filter_map(|worker| worker.current_job)
+            .map(|job| (job.client.uuid, job.client.name))
+            .collect();
+        evaluations.sort();
+        evaluations.dedup();
+        if evaluations.is_empty() {
+            println!("No evaluation is running on the workers");
+        }
+        for (uuid, name) in evaluations {
+            println!("{} {}", uuid, name);
+        }
+        return Ok(());
+    };
+    if !ExecutorClient::cancel(evaluation, &tx, &rx)? {
+        bail!("The evaluation {} is not running on the server", evaluation);
+    }
+    println!("The evaluation {} has been cancelled", evaluation);
+    Ok(())
+}
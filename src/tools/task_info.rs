@@ -1,5 +1,5 @@
 use anyhow::{Context, Error};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 
 use crate::FindTaskOpt;
 
@@ -7,19 +7,49 @@ use crate::FindTaskOpt;
 pub struct TaskInfoOpt {
     #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
     pub find_task: FindTaskOpt,
-    /// Produce JSON output.
-    #[clap(long, short)]
+    /// Produce JSON output. Same as `--format json`.
+    #[clap(long, short, conflicts_with = "format")]
     pub json: bool,
+    /// The format of the output.
+    ///
+    /// The json and yaml outputs follow a stable schema, whose version is stored in the `version`
+    /// field.
+    #[clap(long, value_enum, default_value_t = TaskInfoFormat::Debug)]
+    pub format: TaskInfoFormat,
+}
+
+/// The formats in which the task info can be printed.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskInfoFormat {
+    /// The debug representation of the task info, not meant to be parsed.
+    Debug,
+    /// The task info in JSON, on a single line.
+    Json,
+    /// The task info in YAML.
+    Yaml,
 }
 
 pub fn main_task_info(opt: TaskInfoOpt) -> Result<(), Error> {
     let task = opt.find_task.find_task(&Default::default())?;
     let info = task.task_info().context("Cannot produce task info")?;
-    if opt.json {
-        let json = serde_json::to_string(&info).context("Non-serializable task info")?;
-        println!("{}", json);
+    let format = if opt.json {
+        TaskInfoFormat::Json
     } else {
-        println!("{:#?} ", info);
+        opt.format
+    };
+    match format {
+        TaskInfoFormat::Json => {
+            let json = serde_json::to_string(&info).context("Non-serializable task info")?;
+            println!("{}", json);
+        }
+        TaskInfoFormat::Yaml => {
+            // going through json keeps the same structure of the json output, serde_yaml would
+            // use a yaml tag for the format of the task.
+            let value = serde_json::to_value(&info).context("Non-serializable task info")?;
+            let yaml = serde_yaml::to_string(&value).context("Non-serializable task info")?;
+            print!("{}", yaml);
+        }
+        TaskInfoFormat::Debug => println!("{:#?} ", info),
     }
     Ok(())
 }
@@ -0,0 +1,390 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context, Error};
+use clap::{Parser, ValueHint};
+use itertools::Itertools;
+use serde::Serialize;
+
+use task_maker_format::terry::{Seed, SolutionOutcome};
+use task_maker_format::ui::UIType;
+use task_maker_format::{EvaluationConfig, TaskFormat};
+
+use crate::context::RuntimeContext;
+use crate::{ExecutionOpt, FindTaskOpt, StorageOpt};
+
+/// The name of the file, inside the folder of each user, with the seed of the input file.
+const SEED_FILE: &str = "seed.txt";
+/// The name of the file, inside the folder of each user, with the submitted output file.
+const OUTPUT_FILE: &str = "output.txt";
+
+#[derive(Parser, Debug, Clone)]
+pub struct TerryGradeOpt {
+    #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
+    pub find_task: FindTaskOpt,
+
+    #[clap(flatten, next_help_heading = Some("EXECUTION"))]
+    pub execution: ExecutionOpt,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+
+    /// The directory with the submissions, with a subdirectory for each user.
+    ///
+    /// The directory of each user must contain `seed.txt`, with the seed used for generating the
+    /// input file, and `output.txt`, the output file submitted by the user.
+    #[clap(value_hint = ValueHint::DirPath)]
+    pub submissions: PathBuf,
+
+    /// The format of the ranking: csv, json
+    #[clap(long, default_value = "csv")]
+    pub format: RankingFormat,
+
+    /// Write the ranking to this file instead of the standard output.
+    #[clap(long, short, value_hint = ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
+}
+
+/// The format of the ranking produced by `terry-grade`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankingFormat {
+    /// One line per user, with a header line.
+    Csv,
+    /// A JSON array with an object per user.
+    Json,
+}
+
+impl std::str::FromStr for RankingFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<RankingFormat, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(RankingFormat::Csv),
+            "json" => Ok(RankingFormat::Json),
+            _ => Err(format!("Unknown ranking format: {}", s)),
+        }
+    }
+}
+
+/// A submission of a user to grade.
+#[derive(Debug, Clone)]
+struct Submission {
+    /// The name of the user, i.e. the name of its directory.
+    user: String,
+    /// The seed used for generating the input file.
+    seed: Seed,
+    /// The path to the output file submitted by the user.
+    output: PathBuf,
+}
+
+/// The result of the grading of a user, as reported in the ranking.
+#[derive(Debug, Clone, Serialize)]
+struct RankingEntry {
+    /// The position in the ranking, the users with the same score share the same position.
+    rank: usize,
+    /// The name of the user.
+    user: String,
+    /// The seed of the input file.
+    seed: Option<Seed>,
+    /// The score of the user, zero if the submission could not be graded.
+    score: f64,
+    /// Why the submission could not be graded, if it couldn't.
+    error: Option<String>,
+}
+
+pub fn main_terry_grade(opt: TerryGradeOpt) -> Result<(), Error> {
+    let eval_config = EvaluationConfig {
+        no_statement: true,
+        dry_run: opt.execution.dry_run,
+        ..Default::default()
+    };
+    let task = opt
+        .find_task
+        .find_task(&eval_config)
+        .context("Failed to locate the task")?;
+    let max_score = match &task {
+        TaskFormat::Terry(task) => task.max_score,
+        _ => bail!("The terry-grade tool only supports Terry tasks"),
+    };
+
+    let (submissions, mut errors) = find_submissions(&opt.submissions)?;
+    if submissions.is_empty() && errors.is_empty() {
+        bail!("No submissions found in {}", opt.submissions.display());
+    }
+    let seeds: BTreeMap<String, Seed> = submissions
+        .iter()
+        .map(|sub| (sub.user.clone(), sub.seed))
+        .collect();
+
+    // the scores are filled by the callbacks of the checkers, that are run in the executor thread
+    let scores = Arc::new(Mutex::new(BTreeMap::<String, Result<f64, String>>::new()));
+    let context = RuntimeContext::new(task, &opt.execution, |task, eval| {
+        let TaskFormat::Terry(task) = task else {
+            unreachable!("The task format has already been checked");
+        };
+        for submission in &submissions {
+            let scores = scores.clone();
+            let user = submission.user.clone();
+            task.grade_output(
+                eval,
+                &submission.user,
+                submission.seed,
+                &submission.output,
+                move |outcome| {
+                    scores
+                        .lock()
+                        .unwrap()
+                        .insert(user, outcome_score(outcome, max_score));
+                    Ok(())
+                },
+            )
+            .with_context(|| format!("Failed to grade the submission of {}", submission.user))?;
+        }
+        Ok(())
+    })?;
+
+    let executor = context.connect_executor(&opt.execution, &opt.storage)?;
+    let executor = executor.start_ui(&UIType::Silent, |ui, mex| ui.on_message(mex))?;
+    executor.execute()?;
+
+    let scores = std::mem::take(&mut *scores.lock().unwrap());
+    for submission in &submissions {
+        if !scores.contains_key(&submission.user) {
+            errors.insert(
+                submission.user.clone(),
+                "The generation, the validation or the checking failed".into(),
+            );
+        }
+    }
+    let ranking = make_ranking(scores, errors, &seeds);
+
+    let mut out: Box<dyn Write> = match &opt.output {
+        Some(path) => Box::new(
+            std::fs::File::create(path)
+                .with_context(|| format!("Failed to create {}", path.display()))?,
+        ),
+        None => Box::new(std::io::stdout()),
+    };
+    match opt.format {
+        RankingFormat::Csv => write_csv(&mut out, &ranking)?,
+        RankingFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, &ranking).context("Failed to write ranking")?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// The score of a user given the outcome of the checker, or why the submission could not be
+/// graded. The checker reports the score normalized from 0.0 to 1.0.
+fn outcome_score(outcome: Result<SolutionOutcome, Error>, max_score: f64) -> Result<f64, String> {
+    outcome
+        .map(|outcome| outcome.score * max_score)
+        .map_err(|e| format!("Invalid checker outcome: {}", e))
+}
+
+/// Find the submissions inside `dir`, one for each subdirectory. The users whose submission is
+/// malformed are returned separately with the reason.
+fn find_submissions(dir: &Path) -> Result<(Vec<Submission>, BTreeMap<String, String>), Error> {
+    let mut submissions = vec![];
+    let mut errors = BTreeMap::new();
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Failed to read {}", dir.display()))?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let user = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        let seed = match std::fs::read_to_string(path.join(SEED_FILE)) {
+            Ok(seed) => seed,
+            Err(e) => {
+                errors.insert(user, format!("Cannot read {}: {}", SEED_FILE, e));
+                continue;
+            }
+        };
+        let seed = match seed.trim().parse::<Seed>() {
+            Ok(seed) => seed,
+            Err(e) => {
+                errors.insert(user, format!("Invalid seed {:?}: {}", seed.trim(), e));
+                continue;
+            }
+        };
+        let output = path.join(OUTPUT_FILE);
+        if !output.is_file() {
+            errors.insert(user, format!("Missing {}", OUTPUT_FILE));
+            continue;
+        }
+        submissions.push(Submission { user, seed, output });
+    }
+    Ok((submissions, errors))
+}
+
+/// Sort the users by decreasing score, the users with an error are at the bottom with zero points.
+fn make_ranking(
+    scores: BTreeMap<String, Result<f64, String>>,
+    errors: BTreeMap<String, String>,
+    seeds: &BTreeMap<String, Seed>,
+) -> Vec<RankingEntry> {
+    let mut entries = scores
+        .into_iter()
+        .map(|(user, score)| match score {
+            Ok(score) => (user, score, None),
+            Err(e) => (user, 0.0, Some(e)),
+        })
+        .chain(errors.into_iter().map(|(user, e)| (user, 0.0, Some(e))))
+        .map(|(user, score, error)| RankingEntry {
+            rank: 0,
+            seed: seeds.get(&user).copied(),
+            user,
+            score,
+            error,
+        })
+        .sorted_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.user.cmp(&b.user))
+        })
+        .collect_vec();
+    for i in 0..entries.len() {
+        entries[i].rank = if i > 0 && entries[i - 1].score == entries[i].score {
+            entries[i - 1].rank
+        } else {
+            i + 1
+        };
+    }
+    entries
+}
+
+/// Write the ranking in CSV format, with a header line.
+fn write_csv<W: Write>(out: &mut W, ranking: &[RankingEntry]) -> Result<(), Error> {
+    writeln!(out, "rank,user,seed,score,error")?;
+    for entry in ranking {
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            entry.rank,
+            csv_field(&entry.user),
+            entry.seed.map(|seed| seed.to_string()).unwrap_or_default(),
+            entry.score,
+            csv_field(entry.error.as_deref().unwrap_or_default())
+        )?;
+    }
+    Ok(())
+}
+
+/// Quote a CSV field if needed.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    fn add_submission(dir: &Path, user: &str, seed: Option<&str>, output: bool) {
+        let user_dir = dir.join(user);
+        std::fs::create_dir(&user_dir).unwrap();
+        if let Some(seed) = seed {
+            std::fs::write(user_dir.join(SEED_FILE), seed).unwrap();
+        }
+        if output {
+            std::fs::write(user_dir.join(OUTPUT_FILE), "42").unwrap();
+        }
+    }
+
+    #[test]
+    fn test_outcome_score() {
+        let outcome = serde_json::from_str(
+            r#"{
+                "score": 0.25,
+                "validation": {"cases": [], "alerts": []},
+                "feedback": {"cases": [], "alerts": []}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(outcome_score(Ok(outcome), 40.0), Ok(10.0));
+        assert_eq!(
+            outcome_score(Err(anyhow!("missing field `score`")), 40.0),
+            Err("Invalid checker outcome: missing field `score`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_find_submissions() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        add_submission(tmpdir.path(), "alice", Some(" 1234\n"), true);
+        add_submission(tmpdir.path(), "bob", Some("not a seed"), true);
+        add_submission(tmpdir.path(), "carol", None, true);
+        add_submission(tmpdir.path(), "dave", Some("42"), false);
+        std::fs::write(tmpdir.path().join("README"), "not a user").unwrap();
+
+        let (submissions, errors) = find_submissions(tmpdir.path()).unwrap();
+        assert_eq!(submissions.len(), 1);
+        assert_eq!(submissions[0].user, "alice");
+        assert_eq!(submissions[0].seed, 1234);
+        assert_eq!(errors.keys().collect_vec(), vec!["bob", "carol", "dave"]);
+        assert!(errors["bob"].starts_with("Invalid seed \"not a seed\""));
+        assert!(errors["carol"].starts_with("Cannot read seed.txt"));
+        assert_eq!(errors["dave"], "Missing output.txt");
+    }
+
+    #[test]
+    fn test_make_ranking() {
+        let scores = BTreeMap::from([
+            ("alice".to_string(), Ok(50.0)),
+            ("bob".to_string(), Ok(100.0)),
+            ("carol".to_string(), Ok(50.0)),
+            (
+                "dave".to_string(),
+                Err("Invalid checker outcome".to_string()),
+            ),
+        ]);
+        let errors = BTreeMap::from([("eve".to_string(), "Missing output.txt".to_string())]);
+        let seeds = BTreeMap::from([("alice".to_string(), 1), ("bob".to_string(), 2)]);
+        let ranking = make_ranking(scores, errors, &seeds);
+        let ranks = ranking
+            .iter()
+            .map(|entry| (entry.rank, entry.user.as_str(), entry.score))
+            .collect_vec();
+        assert_eq!(
+            ranks,
+            vec![
+                (1, "bob", 100.0),
+                (2, "alice", 50.0),
+                (2, "carol", 50.0),
+                (4, "dave", 0.0),
+                (4, "eve", 0.0),
+            ]
+        );
+        assert_eq!(ranking[0].seed, Some(2));
+        assert_eq!(ranking[2].seed, None);
+        assert_eq!(ranking[4].error.as_deref(), Some("Missing output.txt"));
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let ranking = [RankingEntry {
+            rank: 1,
+            user: "alice, \"the best\"".into(),
+            seed: Some(42),
+            score: 12.5,
+            error: None,
+        }];
+        let mut out = vec![];
+        write_csv(&mut out, &ranking).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "rank,user,seed,score,error\n1,\"alice, \"\"the best\"\"\",42,12.5,\n"
+        );
+    }
+}
@@ -7,20 +7,30 @@ use anyhow::Error;
 use regex::Regex;
 use typescript_definitions::TypeScriptifyTrait;
 
-use task_maker_dag::{ExecutionResourcesUsage, ExecutionResult, ExecutionStatus, File};
-use task_maker_exec::{ClientInfo, ExecutorStatus, ExecutorWorkerStatus, WorkerCurrentJobStatus};
+use task_maker_dag::{
+    ExecutionResourcesUsage, ExecutionResult, ExecutionStatus, File, MemorySample,
+    SandboxInvocation, SandboxLogs, SandboxMount, Toolchain,
+};
+use task_maker_exec::{
+    ClientInfo, ExecutorStatus, ExecutorWorkerStatus, TransferStats, WorkerCurrentJobStatus,
+};
+use task_maker_format::ioi::italian_yaml::{Constraint, ConstraintOperand, ConstraintOperator};
 use task_maker_format::ioi::{
     BatchTypeData, Booklet, BookletConfig, Checker, CommunicationTypeData, IOITask, InputGenerator,
-    InputValidator, OutputGenerator, Statement, StatementConfig, SubtaskInfo, TaskInfoScoring,
-    TaskInfoStatement, TaskType, TestcaseInfo,
+    InputValidator, OutputGenerator, Statement, StatementConfig, StatementSubtask, SubtaskInfo,
+    TaskInfoScoring, TaskInfoStatement, TaskType, TestcaseInfo, ToolTimeLimits,
+};
+use task_maker_format::ioi::{
+    IOITaskInfo, TaskInfoAttachment, TaskInfoLimits, TaskInfoSubtask, TaskInfoTestcase,
 };
-use task_maker_format::ioi::{IOITaskInfo, TaskInfoAttachment, TaskInfoLimits, TaskInfoSubtask};
-use task_maker_format::terry::TerryTaskInfo;
 use task_maker_format::terry::{
     CaseStatus, SolutionAlert, SolutionFeedback, SolutionFeedbackCase, SolutionOutcome,
     SolutionValidation, SolutionValidationCase, TerryTask,
 };
-use task_maker_format::ui::{UIExecutionStatus, UIMessage};
+use task_maker_format::terry::{TaskInfoPublicFile, TerryTaskInfo};
+use task_maker_format::ui::{
+    BatchTaskOutcome, JsonUIBatchSummary, JsonUIHeader, UIExecutionStatus, UIMessage,
+};
 use task_maker_format::TaskInfo;
 use task_maker_lang::{Dependency, GraderMap, SourceFile};
 
@@ -51,19 +61,31 @@ pub fn main_typescriptify() -> Result<(), Error> {
     println!("export type Seed = number;");
     println!("export type Language = string;");
     println!("export type Mutex<T> = T;");
+    export_ts!(JsonUIHeader);
+    export_ts!(JsonUIBatchSummary);
+    export_ts!(BatchTaskOutcome);
     export_ts!(UIMessage);
     export_ts!(UIExecutionStatus);
     export_ts!(ExecutorStatus<SystemTime>);
     export_ts!(ExecutorWorkerStatus<SystemTime>);
     export_ts!(WorkerCurrentJobStatus<SystemTime>);
+    export_ts!(TransferStats);
     export_ts!(ClientInfo);
     export_ts!(IOITask);
     export_ts!(TerryTask);
     export_ts!(SolutionOutcome);
     export_ts!(ExecutionResult);
+    export_ts!(SandboxInvocation);
+    export_ts!(SandboxMount);
+    export_ts!(SandboxLogs);
+    export_ts!(Toolchain);
     export_ts!(TaskType);
     export_ts!(SubtaskInfo);
+    export_ts!(Constraint);
+    export_ts!(ConstraintOperand);
+    export_ts!(ConstraintOperator);
     export_ts!(TestcaseInfo);
+    export_ts!(ToolTimeLimits);
     export_ts!(GraderMap);
     export_ts!(Dependency);
     export_ts!(File);
@@ -71,6 +93,7 @@ pub fn main_typescriptify() -> Result<(), Error> {
     export_ts!(BookletConfig);
     export_ts!(Statement);
     export_ts!(StatementConfig);
+    export_ts!(StatementSubtask);
     export_ts!(SolutionValidation);
     export_ts!(SolutionValidationCase);
     export_ts!(SolutionAlert);
@@ -79,6 +102,7 @@ pub fn main_typescriptify() -> Result<(), Error> {
     export_ts!(SolutionFeedbackCase);
     export_ts!(ExecutionStatus);
     export_ts!(ExecutionResourcesUsage);
+    export_ts!(MemorySample);
     export_ts!(BatchTypeData);
     export_ts!(CommunicationTypeData);
     export_ts!(Checker);
@@ -91,9 +115,11 @@ pub fn main_typescriptify() -> Result<(), Error> {
     export_ts!(TaskInfoLimits);
     export_ts!(TaskInfoAttachment);
     export_ts!(TaskInfoSubtask);
+    export_ts!(TaskInfoTestcase);
     export_ts!(TaskInfoScoring);
     export_ts!(TaskInfoStatement);
     export_ts!(TerryTaskInfo);
+    export_ts!(TaskInfoPublicFile);
     Ok(())
 }
 
@@ -0,0 +1,30 @@
+use std::fs::File;
+use std::path::Path;
+
+use anyhow::{Context, Error};
+
+use task_maker_format::white_diff::white_diff;
+
+/// Compare the two files like the default checker does, exiting with 0 if they are equal, with 1 if
+/// they are different and with 2 if they cannot be read.
+///
+/// This is the implementation of the builtin `WhiteDiff` command, run inside the sandbox.
+pub fn main_white_diff(correct: &Path, test: &Path) {
+    match compare(correct, test) {
+        Ok(true) => std::process::exit(0),
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Actually compare the files, with a return type that supports the `?` operator.
+fn compare(correct: &Path, test: &Path) -> Result<bool, Error> {
+    let correct_file =
+        File::open(correct).with_context(|| format!("Failed to open {}", correct.display()))?;
+    let test_file =
+        File::open(test).with_context(|| format!("Failed to open {}", test.display()))?;
+    white_diff(correct_file, test_file).context("Failed to compare the files")
+}
@@ -1,15 +1,15 @@
 use std::sync::Arc;
 
-use anyhow::{bail, Context, Error};
+use anyhow::{Context, Error};
 use clap::Parser;
 
-use task_maker_exec::executors::{RemoteEntityMessage, RemoteEntityMessageResponse};
-use task_maker_exec::Worker;
+use task_maker_dag::{ResourceTags, CONTAINER_RESOURCE};
+use task_maker_exec::{measure_speed_factor, parse_speed_factor, Worker};
 use task_maker_store::FileStore;
 
-use crate::remote::connect_to_remote_server;
-use crate::sandbox::ToolsSandboxRunner;
-use crate::StorageOpt;
+use crate::context::local_toolchain_versions;
+use crate::remote::{connect_to_remote_server, send_welcome, Welcome};
+use crate::{SandboxBackendOpt, StorageOpt};
 
 #[derive(Parser, Debug, Clone)]
 pub struct WorkerOpt {
@@ -23,12 +23,37 @@ pub struct WorkerOpt {
     #[clap(long)]
     pub name: Option<String>,
 
+    /// Do not compress the files exchanged with the server
+    #[clap(long = "no-compression")]
+    pub no_compression: bool,
+
+    /// Report this speed factor to the server instead of measuring it at startup
+    ///
+    /// The speed factor is how many times this machine is faster than the reference one, and it's
+    /// used by the clients that scale the time limits.
+    #[clap(long = "speed-factor", value_parser = parse_speed_factor)]
+    pub speed_factor: Option<f64>,
+
+    /// Tell the server that this worker provides the resource with this tag (e.g. gpu)
+    ///
+    /// The executions that require some resources are run only by the workers that provide all of
+    /// them. Can be specified more than once.
+    #[clap(long = "resource")]
+    pub resources: Vec<String>,
+
+    /// Join the pool of workers with this name
+    ///
+    /// The workers of a pool run only the evaluations of the clients that select that pool. Without
+    /// this option the worker joins the default pool.
+    #[clap(long = "pool")]
+    pub pool: Option<String>,
+
     #[clap(flatten, next_help_heading = Some("STORAGE"))]
     pub storage: StorageOpt,
-}
 
-/// Version of task-maker
-const VERSION: &str = env!("CARGO_PKG_VERSION");
+    #[clap(flatten, next_help_heading = Some("SANDBOX"))]
+    pub sandbox: SandboxBackendOpt,
+}
 
 /// Entry point for the worker.
 pub fn main_worker(opt: WorkerOpt) -> Result<(), Error> {
@@ -50,20 +75,37 @@ pub fn main_worker(opt: WorkerOpt) -> Result<(), Error> {
             whoami::fallible::hostname().unwrap()
         )
     });
+    let speed_factor = match opt.speed_factor {
+        Some(speed_factor) => speed_factor,
+        None => {
+            let speed_factor = measure_speed_factor();
+            info!("Measured speed factor: {:.2}", speed_factor);
+            speed_factor
+        }
+    };
+    let toolchains = local_toolchain_versions();
+    for (toolchain, version) in &toolchains {
+        info!("Toolchain {}: {}", toolchain, version);
+    }
+    let mut resources: ResourceTags = opt.resources.into_iter().collect();
+    // the executions that require a container image are sent only to the workers that can run it
+    if opt.sandbox.container_runtime.is_some() {
+        resources.insert(CONTAINER_RESOURCE.to_string());
+    }
+    if !resources.is_empty() {
+        info!("Provided resources: {:?}", resources);
+    }
+    if let Some(pool) = &opt.pool {
+        info!("Joining pool {}", pool);
+    }
+
     let (executor_tx, executor_rx) = connect_to_remote_server(&opt.server_addr, 27183)
         .context("Failed to connect to the server")?;
-    executor_tx
-        .send(RemoteEntityMessage::Welcome {
-            name: name.clone(),
-            version: VERSION.into(),
-        })
-        .context("Cannot send welcome to the server")?;
-    if let RemoteEntityMessageResponse::Rejected(err) = executor_rx
-        .recv()
-        .context("Remote executor didn't reply to the welcome message")?
-    {
-        bail!("The server rejected the worker connection: {}", err);
+    let mut welcome = Welcome::worker(name.clone(), speed_factor, toolchains, resources, opt.pool);
+    if opt.no_compression {
+        welcome = welcome.without_compression();
     }
+    send_welcome(&executor_tx, &executor_rx, welcome, "worker")?;
 
     let name = if let Some(wid) = opt.worker_id {
         format!("{} {}", name, wid)
@@ -77,7 +119,7 @@ pub fn main_worker(opt: WorkerOpt) -> Result<(), Error> {
         sandbox_path,
         executor_tx.change_type(),
         executor_rx.change_type(),
-        Arc::new(ToolsSandboxRunner::default()),
+        Arc::new(opt.sandbox.sandbox_runner(false)?),
     )
     .context("Failed to start worker")?;
     worker.work()
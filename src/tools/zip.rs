@@ -0,0 +1,13 @@
+use std::path::{Path, PathBuf};
+
+use task_maker_format::ioi::write_zip;
+
+/// Pack the files in a zip file, exiting with 0 on success and with 1 on failure.
+///
+/// This is the implementation of the builtin `Zip` command, run inside the sandbox.
+pub fn main_zip(output: &Path, files: &[PathBuf]) {
+    if let Err(e) = write_zip(output, files) {
+        eprintln!("Error: {:?}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,328 @@
+//! Watch mode: evaluate the task again every time one of its files changes.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Error};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use task_maker_format::ui::UIType;
+
+use crate::context::SharedUI;
+use crate::error::print_error;
+use crate::evaluation::EvaluationBuilder;
+use crate::local::evaluate;
+use crate::opt::Opt;
+
+/// How long to wait for more changes after the first one before starting a new evaluation. Editors
+/// usually write more than one file (or the same file more than once) when saving.
+const DEBOUNCE_TIME: Duration = Duration::from_millis(300);
+
+/// Evaluate the task and keep evaluating it again when its files change. This function never
+/// returns, unless an error occurs while watching the task directory.
+///
+/// After every evaluation the changes of the scores of the solutions with respect to the previous
+/// evaluation are printed. The curses UI is kept open across the evaluations, and it shows them
+/// instead. The files written by the evaluation itself (e.g. the generated testcases or the
+/// compiled checker) do not trigger a new evaluation.
+pub fn watch_task(opt: Opt) -> Result<(), Error> {
+    if opt.clean || opt.check_dag || opt.estimate || opt.print_repro.is_some() {
+        bail!("--watch cannot be used with --clean, --check-dag, --estimate or --print-repro");
+    }
+    if opt.time_limit.suggest_time_limit {
+        bail!("--watch cannot be used with --suggest-time-limit, since it writes task.yaml");
+    }
+    let task = opt.find_task.find_task(&opt.to_config())?;
+    let task_dir = task.path().to_owned();
+    // the paths of the events are absolute
+    let watch_dir = task_dir
+        .canonicalize()
+        .with_context(|| format!("Failed to find {}", task_dir.display()))?;
+
+    let (tx, rx) = channel();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to create the watcher")?;
+    watcher
+        .watch(&watch_dir, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", task_dir.display()))?;
+
+    let written = Arc::new(Mutex::new(HashSet::new()));
+    // the curses UI takes the whole terminal, so it's not closed after each evaluation
+    let shared_ui: Option<SharedUI> = match opt.ui.ui {
+        UIType::Curses => Some(Default::default()),
+        _ => None,
+    };
+    let mut previous_scores: Option<BTreeMap<PathBuf, Option<f64>>> = None;
+    loop {
+        let configure = |builder: EvaluationBuilder| {
+            let written = written.clone();
+            let task_dir = task_dir.clone();
+            let builder = builder.written_paths(move |paths| {
+                let mut written = written.lock().unwrap();
+                written.extend(
+                    paths
+                        .iter()
+                        .filter_map(|path| path.strip_prefix(&task_dir).ok())
+                        .map(Path::to_path_buf),
+                );
+            });
+            match &shared_ui {
+                Some(ui) => builder.shared_ui(ui.clone()),
+                None => builder,
+            }
+        };
+        let mut notice = vec![];
+        match evaluate(&opt, configure, |ui, mex| ui.on_message(mex)) {
+            Ok((_, Some(state))) => {
+                let scores = state.solution_scores();
+                if let Some(previous_scores) = &previous_scores {
+                    notice = score_changes(&task_dir, previous_scores, &scores);
+                    notice.push(String::new());
+                }
+                previous_scores = Some(scores);
+            }
+            Ok((_, None)) => {}
+            // a broken task should not stop the watch, the next change may fix it
+            Err(e) => {
+                let e = e.context("Evaluation failed");
+                if is_shown(shared_ui.as_ref()) {
+                    notice.extend(format!("Error: {:#}", e).lines().map(String::from));
+                    notice.push(String::new());
+                } else {
+                    print_error(e);
+                }
+            }
+        }
+        let written_paths = written.lock().unwrap();
+        // the changes made while the evaluation was running are not lost
+        if settle(&watch_dir, &written_paths, &rx)? {
+            notice.push("The task changed during the evaluation, evaluating it again...".into());
+            show(shared_ui.as_ref(), notice);
+            continue;
+        }
+        notice.push(format!("Watching {} for changes...", task_dir.display()));
+        show(shared_ui.as_ref(), notice);
+        wait_for_changes(&watch_dir, &written_paths, &rx)?;
+    }
+}
+
+/// Whether the shared UI has been built by an evaluation, and it's showing it.
+fn is_shown(ui: Option<&SharedUI>) -> bool {
+    ui.map_or(false, |ui| ui.lock().unwrap().is_some())
+}
+
+/// Show some lines of text inside the shared UI, or print them when there is no such UI.
+fn show(ui: Option<&SharedUI>, lines: Vec<String>) {
+    if let Some(ui) = ui {
+        if let Some(ui) = ui.lock().unwrap().as_mut() {
+            ui.set_notice(lines);
+            return;
+        }
+    }
+    println!();
+    for line in lines {
+        println!("{}", line);
+    }
+}
+
+/// Block until a relevant change happens in the watched directory, then wait for the following
+/// changes to settle.
+fn wait_for_changes(
+    watch_dir: &Path,
+    written: &HashSet<PathBuf>,
+    rx: &Receiver<notify::Result<Event>>,
+) -> Result<(), Error> {
+    loop {
+        let event = rx.recv().context("The watcher has stopped")?;
+        let event = event.context("Failed to watch the task directory")?;
+        if is_relevant(watch_dir, written, &event) {
+            break;
+        }
+    }
+    settle(watch_dir, written, rx)?;
+    Ok(())
+}
+
+/// Wait until no change happens for [`DEBOUNCE_TIME`], returning whether any of the received
+/// changes is relevant.
+fn settle(
+    watch_dir: &Path,
+    written: &HashSet<PathBuf>,
+    rx: &Receiver<notify::Result<Event>>,
+) -> Result<bool, Error> {
+    let mut relevant = false;
+    loop {
+        match rx.recv_timeout(DEBOUNCE_TIME) {
+            Ok(event) => {
+                let event = event.context("Failed to watch the task directory")?;
+                relevant |= is_relevant(watch_dir, written, &event);
+            }
+            Err(RecvTimeoutError::Timeout) => return Ok(relevant),
+            Err(RecvTimeoutError::Disconnected) => bail!("The watcher has stopped"),
+        }
+    }
+}
+
+/// Whether an event should trigger a new evaluation. Only the modifications of the files count,
+/// ignoring the hidden files (like the swap files of the editors and the `.git` directory), the
+/// backup files and the files written by the evaluation (`written`, relative to the task
+/// directory) with their directories.
+fn is_relevant(watch_dir: &Path, written: &HashSet<PathBuf>, event: &Event) -> bool {
+    if matches!(event.kind, EventKind::Access(_) | EventKind::Other) {
+        return false;
+    }
+    event.paths.iter().any(|path| {
+        let path = path.strip_prefix(watch_dir).unwrap_or(path);
+        if written.contains(path) || written.iter().any(|written| written.starts_with(path)) {
+            return false;
+        }
+        let hidden = path.components().any(|comp| {
+            comp.as_os_str()
+                .to_str()
+                .map(|name| name.starts_with('.') && name != "." && name != "..")
+                .unwrap_or(false)
+        });
+        let backup = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.ends_with('~'))
+            .unwrap_or(false);
+        !hidden && !backup
+    })
+}
+
+/// The lines describing the solutions whose score changed between two evaluations.
+fn score_changes(
+    task_dir: &Path,
+    previous: &BTreeMap<PathBuf, Option<f64>>,
+    current: &BTreeMap<PathBuf, Option<f64>>,
+) -> Vec<String> {
+    let format_score = |score: &Option<f64>| match score {
+        Some(score) => format!("{}", score),
+        None => "-".to_string(),
+    };
+    let mut changes = vec![];
+    for (path, score) in current {
+        let name = path.strip_prefix(task_dir).unwrap_or(path).display();
+        match previous.get(path) {
+            Some(prev) if prev == score => {}
+            Some(prev) => changes.push(format!(
+                "{}: {} -> {}",
+                name,
+                format_score(prev),
+                format_score(score)
+            )),
+            None => changes.push(format!("{}: new, {}", name, format_score(score))),
+        }
+    }
+    for path in previous.keys().filter(|path| !current.contains_key(*path)) {
+        let name = path.strip_prefix(task_dir).unwrap_or(path).display();
+        changes.push(format!("{}: not evaluated anymore", name));
+    }
+    if changes.is_empty() {
+        return vec!["No score changed since the previous evaluation".into()];
+    }
+    std::iter::once("Score changes since the previous evaluation:".to_string())
+        .chain(changes.into_iter().map(|change| format!("  {}", change)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use notify::event::{AccessKind, ModifyKind};
+
+    use super::*;
+
+    fn modify(path: &str) -> Event {
+        Event::new(EventKind::Modify(ModifyKind::Any)).add_path(path.into())
+    }
+
+    #[test]
+    fn test_is_relevant() {
+        let watch_dir = Path::new("/task");
+        let written = HashSet::new();
+        assert!(is_relevant(
+            watch_dir,
+            &written,
+            &modify("/task/sol/sol.cpp")
+        ));
+        assert!(is_relevant(watch_dir, &written, &modify("/task/task.yaml")));
+        let access =
+            Event::new(EventKind::Access(AccessKind::Any)).add_path("/task/task.yaml".into());
+        assert!(!is_relevant(watch_dir, &written, &access));
+    }
+
+    #[test]
+    fn test_is_relevant_hidden_and_backup() {
+        let watch_dir = Path::new("/task");
+        let written = HashSet::new();
+        assert!(!is_relevant(
+            watch_dir,
+            &written,
+            &modify("/task/.git/index")
+        ));
+        assert!(!is_relevant(
+            watch_dir,
+            &written,
+            &modify("/task/sol/.sol.cpp.swp")
+        ));
+        assert!(!is_relevant(
+            watch_dir,
+            &written,
+            &modify("/task/sol/sol.cpp~")
+        ));
+    }
+
+    #[test]
+    fn test_is_relevant_written() {
+        let watch_dir = Path::new("/task");
+        let written = HashSet::from([PathBuf::from("input/input0.txt")]);
+        assert!(!is_relevant(
+            watch_dir,
+            &written,
+            &modify("/task/input/input0.txt")
+        ));
+        // the directories of the written files are created by the evaluation
+        assert!(!is_relevant(watch_dir, &written, &modify("/task/input")));
+        assert!(is_relevant(
+            watch_dir,
+            &written,
+            &modify("/task/input/input1.txt")
+        ));
+    }
+
+    #[test]
+    fn test_score_changes() {
+        let task_dir = Path::new("/task");
+        let previous = BTreeMap::from([
+            (PathBuf::from("/task/sol/a.cpp"), Some(100.0)),
+            (PathBuf::from("/task/sol/b.cpp"), Some(50.0)),
+            (PathBuf::from("/task/sol/c.cpp"), None),
+        ]);
+        let current = BTreeMap::from([
+            (PathBuf::from("/task/sol/a.cpp"), Some(100.0)),
+            (PathBuf::from("/task/sol/b.cpp"), None),
+            (PathBuf::from("/task/sol/d.cpp"), Some(30.0)),
+        ]);
+        assert_eq!(
+            score_changes(task_dir, &previous, &current),
+            vec![
+                "Score changes since the previous evaluation:",
+                "  sol/b.cpp: 50 -> -",
+                "  sol/d.cpp: new, 30",
+                "  sol/c.cpp: not evaluated anymore",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_score_changes_unchanged() {
+        let task_dir = Path::new("/task");
+        let scores = BTreeMap::from([(PathBuf::from("/task/sol/a.cpp"), Some(100.0))]);
+        assert_eq!(
+            score_changes(task_dir, &scores, &scores),
+            vec!["No score changed since the previous evaluation"]
+        );
+    }
+}
@@ -0,0 +1,268 @@
+//! API for building custom execution DAGs and evaluating them with task-maker.
+//!
+//! This crate re-exports the parts of the other crates of task-maker needed for building an
+//! [`ExecutionDAG`] and it provides an [`Evaluator`] for running it on a local or a remote
+//! executor. The re-exported types are the ones used internally by task-maker, so they change
+//! as the tool evolves (e.g. new fields of [`ExecutionResult`]) and this crate doesn't follow
+//! semver: depend on an exact version of it, the same of the workers and of the server.
+//!
+//! The results are received with the typed callbacks of the DAG (e.g.
+//! [`ExecutionDAG::on_execution_done`] and [`ExecutionDAG::get_file_content`]), while the status of
+//! the executor is passed to the callback of [`Evaluator::evaluate`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use task_maker_api::{
+//!     Evaluator, Execution, ExecutionCommand, ExecutionDAG, SuccessSandboxRunner,
+//! };
+//!
+//! let mut dag = ExecutionDAG::new();
+//! let mut exec = Execution::new("Say hello", ExecutionCommand::system("echo"));
+//! exec.args(vec!["hello"]);
+//! let stdout = exec.stdout();
+//! dag.on_execution_done(&exec.uuid, |result| {
+//!     println!("Exited with {:?}", result.status);
+//!     Ok(())
+//! });
+//! dag.get_file_content(stdout, 1024, |content| {
+//!     println!("Printed {}", String::from_utf8_lossy(&content));
+//!     Ok(())
+//! });
+//! dag.add_execution(exec);
+//!
+//! // a real sandbox runner should be used instead
+//! Evaluator::local("/tmp/task-maker-store", SuccessSandboxRunner)
+//!     .num_workers(2)
+//!     .evaluate(dag, |_status| Ok(()))
+//!     .expect("Evaluation failed");
+//! ```
+
+#![deny(missing_docs)]
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Context};
+
+use task_maker_cache::Cache;
+use task_maker_exec::ductile::new_local_channel;
+use task_maker_exec::executors::{LocalExecutor, Welcome};
+use task_maker_exec::{connect_to_remote_server, probe_toolchains, send_welcome, ExecutorClient};
+use task_maker_store::FileStore;
+
+pub use anyhow::Error;
+pub use task_maker_dag::{
+    CacheMode, Execution, ExecutionCommand, ExecutionDAG, ExecutionDAGConfig, ExecutionGroup,
+    ExecutionLimits, ExecutionResourcesUsage, ExecutionResult, ExecutionStatus, ExecutionTag,
+    ExecutionUuid, File, FileUuid, Priority, Toolchain,
+};
+pub use task_maker_exec::{
+    ErrorSandboxRunner, ExecutorStatus, ExecutorWorkerStatus, RawSandboxResult, SandboxRunner,
+    SuccessSandboxRunner,
+};
+
+/// The default port of the server for the clients.
+const DEFAULT_CLIENT_PORT: u16 = 27182;
+
+/// Where the DAGs are evaluated.
+enum Executor {
+    /// A local executor, spawned for each evaluation.
+    Local {
+        /// The number of local workers to spawn.
+        num_workers: usize,
+        /// The runner of the sandboxes of the local workers.
+        sandbox_runner: Arc<dyn SandboxRunner>,
+    },
+    /// A remote server.
+    Remote {
+        /// The address of the server.
+        address: String,
+        /// The name of this client, shown by the server.
+        name: String,
+    },
+}
+
+/// Evaluates the execution DAGs, either with a local executor or on a remote server.
+///
+/// The files used and produced by the executions are kept in a store, in a directory of the local
+/// machine; the cache of a local executor is kept there too.
+pub struct Evaluator {
+    /// The directory of the store.
+    store_dir: PathBuf,
+    /// The maximum size of the store, in MiB.
+    max_cache: u64,
+    /// The size of the store after it's flushed, in MiB.
+    min_cache: u64,
+    /// The executor to use.
+    executor: Executor,
+}
+
+impl Evaluator {
+    /// An evaluator that spawns a local executor, running the sandboxes with `sandbox_runner`. By
+    /// default it spawns a worker for each core of the machine.
+    pub fn local<P: Into<PathBuf>, R: SandboxRunner + 'static>(
+        store_dir: P,
+        sandbox_runner: R,
+    ) -> Evaluator {
+        let num_workers = thread::available_parallelism().map_or(1, |n| n.get());
+        Evaluator::new(
+            store_dir.into(),
+            Executor::Local {
+                num_workers,
+                sandbox_runner: Arc::new(sandbox_runner),
+            },
+        )
+    }
+
+    /// An evaluator that sends the DAGs to the remote server at `address`, like `--evaluate-on`
+    /// does.
+    pub fn remote<P: Into<PathBuf>, S: Into<String>>(store_dir: P, address: S) -> Evaluator {
+        Evaluator::new(
+            store_dir.into(),
+            Executor::Remote {
+                address: address.into(),
+                name: "task-maker-api".into(),
+            },
+        )
+    }
+
+    /// Make a new `Evaluator` with the default sizes of the store.
+    fn new(store_dir: PathBuf, executor: Executor) -> Evaluator {
+        Evaluator {
+            store_dir,
+            max_cache: 3 * 1024,
+            min_cache: 2 * 1024,
+            executor,
+        }
+    }
+
+    /// Set the number of workers of the local executor. Ignored by the remote evaluators.
+    pub fn num_workers(mut self, workers: usize) -> Self {
+        if let Executor::Local { num_workers, .. } = &mut self.executor {
+            *num_workers = workers;
+        }
+        self
+    }
+
+    /// Set the name of this client shown by the remote server. Ignored by the local evaluators.
+    pub fn name<S: Into<String>>(mut self, client_name: S) -> Self {
+        if let Executor::Remote { name, .. } = &mut self.executor {
+            *name = client_name.into();
+        }
+        self
+    }
+
+    /// Set the maximum size of the store, and the size it's reduced to when it's full, in MiB.
+    pub fn store_size(mut self, max_size: u64, min_size: u64) -> Self {
+        self.max_cache = max_size;
+        self.min_cache = min_size;
+        self
+    }
+
+    /// Evaluate the DAG, blocking until all its executions are completed. The status of the
+    /// executor is passed to `status_callback` every time it's received.
+    pub fn evaluate<F>(&self, dag: ExecutionDAG, status_callback: F) -> Result<(), Error>
+    where
+        F: FnMut(ExecutorStatus<SystemTime>) -> Result<(), Error>,
+    {
+        let file_store = Arc::new(
+            FileStore::new(
+                self.store_dir.join("store"),
+                self.max_cache * 1024 * 1024,
+                self.min_cache * 1024 * 1024,
+            )
+            .context("Cannot create the file store")?,
+        );
+        match &self.executor {
+            Executor::Local {
+                num_workers,
+                sandbox_runner,
+            } => {
+                let (tx, rx_remote) = new_local_channel();
+                let (tx_remote, rx) = new_local_channel();
+                let cache =
+                    Cache::new(self.store_dir.join("cache")).context("Cannot create the cache")?;
+                let toolchains = probe_toolchains(&dag_toolchains(&dag));
+                let executor = LocalExecutor::new(
+                    file_store.clone(),
+                    cache,
+                    *num_workers,
+                    self.store_dir.join("sandboxes"),
+                    sandbox_runner.clone(),
+                    toolchains,
+                )?;
+                let server = thread::Builder::new()
+                    .name("Local executor".into())
+                    .spawn(move || executor.evaluate(tx_remote, rx_remote))
+                    .context("Failed to spawn the executor thread")?;
+                let res = ExecutorClient::evaluate(dag, tx, &rx, file_store, status_callback);
+                server
+                    .join()
+                    .map_err(|e| anyhow!("Executor panicked: {:?}", e))?
+                    .context("Executor failed")?;
+                res
+            }
+            Executor::Remote { address, name } => {
+                let (tx, rx) = connect_to_remote_server(address, DEFAULT_CLIENT_PORT)
+                    .with_context(|| {
+                        format!("Cannot connect to the remote server at {}", address)
+                    })?;
+                send_welcome(&tx, &rx, Welcome::client(name.clone()), "client")?;
+                ExecutorClient::evaluate(
+                    dag,
+                    tx.change_type(),
+                    &rx.change_type(),
+                    file_store,
+                    status_callback,
+                )
+            }
+        }
+    }
+}
+
+/// The toolchains used by the executions of the DAG, without duplicates.
+fn dag_toolchains(dag: &ExecutionDAG) -> Vec<Toolchain> {
+    let mut toolchains: Vec<Toolchain> = vec![];
+    let executions = dag
+        .data
+        .execution_groups
+        .values()
+        .flat_map(|group| group.executions.iter());
+    for toolchain in executions.filter_map(|exec| exec.toolchain.as_ref()) {
+        if !toolchains.contains(toolchain) {
+            toolchains.push(toolchain.clone());
+        }
+    }
+    toolchains
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn test_evaluate_locally() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let mut dag = ExecutionDAG::new();
+        let exec = Execution::new("exec", ExecutionCommand::system("true"));
+        let done = Arc::new(AtomicBool::new(false));
+        let done2 = done.clone();
+        dag.on_execution_done(&exec.uuid, move |result| {
+            assert_eq!(result.status, ExecutionStatus::Success);
+            done2.store(true, Ordering::Relaxed);
+            Ok(())
+        });
+        dag.add_execution(exec);
+
+        Evaluator::local(tmpdir.path(), SuccessSandboxRunner)
+            .num_workers(1)
+            .evaluate(dag, |_| Ok(()))
+            .unwrap();
+        assert!(done.load(Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Error};
+use const_format::formatcp;
+use serde::{Deserialize, Serialize};
+
+use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey};
+
+use crate::entry::CacheEntry;
+use crate::key::{ArtifactKey, CacheKey};
+use crate::storage::CACHE_FORMAT_VERSION;
+use crate::Cache;
+
+/// Magic string at the beginning of a cache archive. Like the magic of the cache file, it includes
+/// the version of task-maker and of the format of the entries, since the entries are not compatible
+/// between versions.
+const ARCHIVE_MAGIC: &[u8] = formatcp!(
+    "task-maker-cache-archive v{} format {}\n",
+    env!("CARGO_PKG_VERSION"),
+    CACHE_FORMAT_VERSION
+)
+.as_bytes();
+
+/// The index of a cache archive, written right after the magic string. It's followed by the
+/// content of the files, in the same order as `files`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ArchiveIndex {
+    /// The entries of the cache of the executions.
+    entries: Vec<(CacheKey, Vec<CacheEntry>)>,
+    /// The entries of the artifact cache.
+    artifacts: Vec<(ArtifactKey, Vec<CacheEntry>)>,
+    /// The key and the size of the files in the archive.
+    files: Vec<(FileStoreKey, u64)>,
+}
+
+/// What has been exported to, or imported from, a cache archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheArchiveSummary {
+    /// The number of entries of the cache of the executions.
+    pub entries: usize,
+    /// The number of entries of the artifact cache.
+    pub artifacts: usize,
+    /// The number of files.
+    pub files: usize,
+    /// The total size of the files, in bytes.
+    pub bytes: u64,
+}
+
+impl Cache {
+    /// Write to `writer` an archive with the entries of the cache and their output files, for
+    /// moving them to another machine with [`Cache::import`].
+    ///
+    /// Only the entries whose outputs are all still in the `FileStore` are exported. If `max_age`
+    /// is provided, the entries older than it are skipped as well.
+    pub fn export<W: Write>(
+        &self,
+        file_store: &FileStore,
+        max_age: Option<Duration>,
+        writer: W,
+    ) -> Result<CacheArchiveSummary, Error> {
+        // the handles keep the files in the store until they are written
+        let mut files: BTreeMap<FileStoreKey, FileStoreHandle> = BTreeMap::new();
+        let mut select = |entries: &Vec<CacheEntry>| -> Vec<CacheEntry> {
+            entries
+                .iter()
+                .filter(|entry| !max_age.is_some_and(|age| entry.is_stale(age)))
+                .filter(|entry| {
+                    let handles: Option<Vec<_>> =
+                        entry.output_keys().map(|key| file_store.get(key)).collect();
+                    match handles {
+                        Some(handles) => {
+                            for handle in handles {
+                                files.insert(handle.key().clone(), handle);
+                            }
+                            true
+                        }
+                        None => false,
+                    }
+                })
+                .cloned()
+                .collect()
+        };
+
+        let mut index = ArchiveIndex::default();
+        let mut summary = CacheArchiveSummary::default();
+        for (key, entries) in self.file.iter() {
+            let entries = select(entries);
+            if !entries.is_empty() {
+                summary.entries += entries.len();
+                index.entries.push((key.clone(), entries));
+            }
+        }
+        for (key, entries) in self.artifacts.iter() {
+            let entries = select(entries);
+            if !entries.is_empty() {
+                summary.artifacts += entries.len();
+                index.artifacts.push((key.clone(), entries));
+            }
+        }
+        for (key, handle) in &files {
+            let size = std::fs::metadata(handle.path())
+                .with_context(|| format!("Failed to stat {}", handle.path().display()))?
+                .len();
+            index.files.push((key.clone(), size));
+            summary.bytes += size;
+        }
+        summary.files = files.len();
+
+        let mut writer = BufWriter::new(writer);
+        writer
+            .write_all(ARCHIVE_MAGIC)
+            .context("Failed to write the archive magic number")?;
+        bincode::serialize_into(&mut writer, &index)
+            .context("Failed to write the archive index")?;
+        for handle in files.values() {
+            let mut file = std::fs::File::open(handle.path())
+                .with_context(|| format!("Failed to open {}", handle.path().display()))?;
+            std::io::copy(&mut file, &mut writer)
+                .with_context(|| format!("Failed to archive {}", handle.path().display()))?;
+        }
+        writer.flush().context("Failed to write the archive")?;
+        Ok(summary)
+    }
+
+    /// Read an archive made by [`Cache::export`], adding its files to the `FileStore` and its
+    /// entries to the cache. The entries replace the ones already present with the same limits, and
+    /// the cache is written to disk right away.
+    pub fn import<R: Read>(
+        &mut self,
+        file_store: &FileStore,
+        reader: R,
+    ) -> Result<CacheArchiveSummary, Error> {
+        let mut reader = BufReader::new(reader);
+        let mut magic = vec![0u8; ARCHIVE_MAGIC.len()];
+        reader
+            .read_exact(&mut magic)
+            .context("Failed to read the archive magic number")?;
+        if magic != ARCHIVE_MAGIC {
+            bail!(
+                "Not a cache archive of this version of task-maker: expected {:?}, found {:?}",
+                String::from_utf8_lossy(ARCHIVE_MAGIC),
+                String::from_utf8_lossy(&magic)
+            );
+        }
+        let index: ArchiveIndex =
+            bincode::deserialize_from(&mut reader).context("Failed to read the archive index")?;
+
+        let mut summary = CacheArchiveSummary {
+            files: index.files.len(),
+            ..Default::default()
+        };
+        // the handles keep the imported files in the store until the entries are added
+        let mut handles = Vec::new();
+        for (key, size) in &index.files {
+            let handle = file_store
+                .store_from_reader(key, (&mut reader).take(*size))
+                .with_context(|| format!("Failed to import file {}", key))?;
+            handles.push(handle);
+            summary.bytes += size;
+        }
+        for (key, entries) in index.entries {
+            summary.entries += entries.len();
+            for entry in entries {
+                self.insert_entry(key.clone(), entry);
+            }
+        }
+        for (key, entries) in index.artifacts {
+            summary.artifacts += entries.len();
+            *self.artifacts.entry(key).or_default() = entries;
+            self.artifacts.mark_dirty();
+        }
+        self.flush()?;
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CacheResult;
+    use std::collections::HashMap;
+    use task_maker_dag::{
+        Execution, ExecutionCommand, ExecutionGroup, ExecutionResult, ExecutionStatus,
+    };
+
+    #[test]
+    fn test_export_import() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let mut exec = Execution::new("exec", ExecutionCommand::system("true"));
+        let output = exec.output("out");
+        let group: ExecutionGroup = exec.into();
+        let result = ExecutionResult {
+            status: ExecutionStatus::Success,
+            ..Default::default()
+        };
+
+        let mut archive = Vec::new();
+        {
+            let store = FileStore::new(tmpdir.path().join("store1"), 1000, 1000).unwrap();
+            let mut cache = Cache::new(tmpdir.path().join("cache1")).unwrap();
+            let key = FileStoreKey::from_content(b"output");
+            let handle = store
+                .store(&key, std::iter::once(b"output".to_vec()))
+                .unwrap();
+            let file_keys = HashMap::from([(output.uuid, handle)]);
+            cache.insert(&group, &file_keys, vec![result]);
+            let summary = cache.export(&store, None, &mut archive).unwrap();
+            assert_eq!(summary.entries, 1);
+            assert_eq!(summary.files, 1);
+            assert_eq!(summary.bytes, 6);
+        }
+
+        let store = FileStore::new(tmpdir.path().join("store2"), 1000, 1000).unwrap();
+        let mut cache = Cache::new(tmpdir.path().join("cache2")).unwrap();
+        let summary = cache.import(&store, archive.as_slice()).unwrap();
+        assert_eq!(summary.entries, 1);
+        assert_eq!(summary.files, 1);
+        match cache.get(&group, &HashMap::new(), &store, None) {
+            CacheResult::Miss | CacheResult::Pending => panic!("Expecting a hit from the archive"),
+            CacheResult::Hit {
+                result, outputs, ..
+            } => {
+                assert_eq!(result[0].status, ExecutionStatus::Success);
+                let content = std::fs::read(outputs[&output.uuid].path()).unwrap();
+                assert_eq!(content, b"output");
+            }
+        }
+    }
+
+    #[test]
+    fn test_import_reject_wrong_magic() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(tmpdir.path().join("store"), 1000, 1000).unwrap();
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let archive = b"totally-not-a-cache-archive-of-this-version".to_vec();
+        assert!(cache.import(&store, archive.as_slice()).is_err());
+    }
+}
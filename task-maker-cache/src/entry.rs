@@ -1,13 +1,14 @@
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 use task_maker_dag::{
     Execution, ExecutionGroup, ExecutionLimits, ExecutionResult, ExecutionStatus, FileUuid,
 };
 use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey};
 
 /// The entry relative to an execution inside the group.
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CacheEntryItem {
     /// The result of the `Execution`.
     pub result: ExecutionResult,
@@ -31,10 +32,12 @@ pub struct CacheEntryItem {
 ///
 /// The entry is composed by a number of item, one for each execution in the group. The order of the
 /// items is the same as the order of the executions in the group.
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CacheEntry {
     /// The items of the entry, one for each execution in the group, in the same order.
     pub items: Vec<CacheEntryItem>,
+    /// When the executions of this entry were run.
+    pub created: SystemTime,
 }
 
 impl CacheEntryItem {
@@ -82,7 +85,19 @@ impl CacheEntry {
         for (exec, res) in group.executions.iter().zip(result.into_iter()) {
             items.push(CacheEntryItem::from_execution(exec, file_keys, res));
         }
-        CacheEntry { items }
+        CacheEntry {
+            items,
+            created: SystemTime::now(),
+        }
+    }
+
+    /// Whether this entry is older than the provided TTL.
+    pub fn is_stale(&self, ttl: Duration) -> bool {
+        match self.created.elapsed() {
+            Ok(age) => age > ttl,
+            // the clock went backwards, the entry is from the future
+            Err(_) => false,
+        }
     }
 
     pub fn same_limits(&self, other: &CacheEntry) -> bool {
@@ -97,6 +112,16 @@ impl CacheEntry {
         true
     }
 
+    /// The keys of all the files produced by the executions of this entry.
+    pub fn output_keys(&self) -> impl Iterator<Item = &FileStoreKey> {
+        self.items.iter().flat_map(|item| {
+            item.stdout
+                .iter()
+                .chain(item.stderr.iter())
+                .chain(item.outputs.values())
+        })
+    }
+
     /// Search in the file store the handles of all the output files. Will return `None` if at least
     /// one of them is missing.
     pub fn outputs(
@@ -233,9 +258,7 @@ mod tests {
     use std::fs::File;
     use std::io::Write;
     use std::path::{Path, PathBuf};
-    use task_maker_dag::{
-        Execution, ExecutionCommand, ExecutionResourcesUsage, ExecutionResult, ExecutionStatus,
-    };
+    use task_maker_dag::{Execution, ExecutionCommand, ExecutionResult, ExecutionStatus};
     use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey, ReadFileIterator};
 
     fn fake_file<P: AsRef<Path>>(path: P, content: &str, store: &FileStore) -> FileStoreHandle {
@@ -255,16 +278,7 @@ mod tests {
                 items: vec![CacheEntryItem {
                     result: ExecutionResult {
                         status: ExecutionStatus::Success,
-                        was_killed: false,
-                        was_cached: false,
-                        resources: ExecutionResourcesUsage {
-                            cpu_time: 0.0,
-                            sys_time: 0.0,
-                            wall_time: 0.0,
-                            memory: 0,
-                        },
-                        stdout: None,
-                        stderr: None,
+                        ..Default::default()
                     },
                     limits: Default::default(),
                     extra_time: exec.config().extra_time,
@@ -273,6 +287,7 @@ mod tests {
                     stderr: None,
                     outputs: Default::default(),
                 }],
+                created: SystemTime::now(),
             },
             exec,
         )
@@ -468,4 +483,13 @@ mod tests {
         exec2.limits.read_only = true;
         assert!(entry.is_compatible(&exec2.into()));
     }
+
+    #[test]
+    fn test_is_stale() {
+        let (mut entry, _) = empty_entry();
+        assert!(!entry.is_stale(Duration::from_secs(60)));
+        entry.created = SystemTime::now() - Duration::from_secs(120);
+        assert!(entry.is_stale(Duration::from_secs(60)));
+        assert!(!entry.is_stale(Duration::from_secs(600)));
+    }
 }
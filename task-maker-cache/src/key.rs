@@ -2,7 +2,7 @@ use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
-use task_maker_dag::{Execution, ExecutionCommand, ExecutionGroup, FileUuid};
+use task_maker_dag::{Execution, ExecutionCommand, ExecutionGroup, ExecutionLimits, FileUuid};
 use task_maker_store::{FileStoreHandle, FileStoreKey};
 
 /// The cache key of a single execution of a group.
@@ -19,8 +19,16 @@ struct CacheKeyItem {
     /// order matters here (it changes the final hash of the key) those values are sorted
     /// lexicographically.
     pub inputs: Vec<(PathBuf, FileStoreKey, bool)>,
+    /// The paths of the inputs that are archives of a directory tree, expanded in the sandbox.
+    /// Sorted lexicographically.
+    pub directories: Vec<PathBuf>,
     /// The list of environment variables to set. Sorted by the variable name.
     pub env: Vec<(String, String)>,
+    /// The version of the toolchain used by the execution, if known: the results obtained with a
+    /// different version of the compiler or of the interpreter are not used.
+    pub toolchain: Option<String>,
+    /// The container image in which the execution is run, if any.
+    pub container_image: Option<String>,
 }
 
 /// The cache key used to address the cache entries. It is composed by a key item for each execution
@@ -31,6 +39,96 @@ pub struct CacheKey {
     items: Vec<CacheKeyItem>,
 }
 
+/// The key used to address the compilations in the artifact cache: the language of the source file
+/// and the version of its compiler, together with the command, the flags and the sources of the
+/// compilation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ArtifactKey {
+    /// The language of the compiled source file.
+    language: String,
+    /// The key of the compilation, which includes the version of the compiler.
+    item: CacheKeyItem,
+}
+
+/// The components of the cache key of an execution in a human readable form, for finding out why an
+/// execution doesn't hit the cache. They are derived like the real key, but the input files are
+/// identified by a textual description (e.g. their hash) instead of their `FileStoreKey`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheKeyComponents {
+    /// The description of the execution.
+    pub description: String,
+    /// The command of the execution.
+    pub command: ExecutionCommand,
+    /// The list of command line arguments, with the paths of the FIFOs normalized.
+    pub args: Vec<String>,
+    /// The description of the stdin, if any.
+    pub stdin: Option<String>,
+    /// The description of the input files, and if they are executable, sorted by path.
+    pub inputs: Vec<(PathBuf, String, bool)>,
+    /// The paths of the inputs that are archives of a directory tree, sorted.
+    pub directories: Vec<PathBuf>,
+    /// The list of environment variables to set. Sorted by the variable name.
+    pub env: Vec<(String, String)>,
+    /// The version of the toolchain used by the execution, if known.
+    pub toolchain: Option<String>,
+    /// The container image in which the execution is run, if any.
+    pub container_image: Option<String>,
+    /// The limits of the execution. They are not part of the key, but a cached result is used only
+    /// if it's compatible with them.
+    pub limits: ExecutionLimits,
+}
+
+/// The command line arguments of the execution as they are used in the key: the paths of the FIFOs
+/// of the group change at every evaluation, so they are replaced with stable names.
+fn key_args(execution: &Execution, group: Option<&ExecutionGroup>) -> Vec<String> {
+    let Some(group) = group else {
+        return execution.args.clone();
+    };
+    let mut fifos = HashMap::new();
+    for (i, fifo) in group.fifo.iter().enumerate() {
+        let name = fifo.sandbox_path().to_string_lossy().to_string();
+        fifos.insert(name, format!("tm_fifo_{}", i));
+    }
+    let mut args = Vec::new();
+    for arg in &execution.args {
+        if let Some(name) = fifos.get(arg) {
+            args.push(name.clone());
+        } else {
+            args.push(arg.clone());
+        }
+    }
+    args
+}
+
+/// The version of the toolchain of the execution, as it's used in the key.
+fn key_toolchain(execution: &Execution) -> Option<String> {
+    execution.toolchain.as_ref()?.version.clone()
+}
+
+/// The input files of the execution, identified by `key_of`, sorted by path.
+fn key_inputs<K: Ord, F: Fn(&FileUuid) -> K>(
+    execution: &Execution,
+    key_of: F,
+) -> Vec<(PathBuf, K, bool)> {
+    execution
+        .inputs
+        .iter()
+        .map(|(p, f)| (p.clone(), key_of(&f.file), f.executable))
+        .sorted()
+        .collect_vec()
+}
+
+/// The paths of the inputs of the execution that are expanded as directories, sorted.
+fn key_directories(execution: &Execution) -> Vec<PathBuf> {
+    execution
+        .inputs
+        .iter()
+        .filter(|(_, input)| input.directory)
+        .map(|(path, _)| path.clone())
+        .sorted()
+        .collect_vec()
+}
+
 impl CacheKeyItem {
     /// Make a new `CacheKeyItem` based on an `Execution` and on the mapping of its input files,
     /// from the UUIDs of the current DAG to the persisted `FileStoreKey`s.
@@ -39,43 +137,86 @@ impl CacheKeyItem {
         file_keys: &HashMap<FileUuid, FileStoreHandle>,
         group: Option<&ExecutionGroup>,
     ) -> CacheKeyItem {
-        let stdin = execution.stdin.as_ref().map(|f| file_keys[f].key().clone());
-        let inputs = execution
-            .inputs
-            .clone()
-            .into_iter()
-            .map(|(p, f)| (p, file_keys[&f.file].key().clone(), f.executable))
-            .sorted()
-            .collect_vec();
-        let env = execution.env.clone().into_iter().sorted().collect_vec();
-        let args = if let Some(group) = group {
-            let mut fifos = HashMap::new();
-            for (i, fifo) in group.fifo.iter().enumerate() {
-                let name = fifo.sandbox_path().to_string_lossy().to_string();
-                fifos.insert(name, format!("tm_fifo_{}", i));
-            }
-            let mut args = Vec::new();
-            for arg in &execution.args {
-                if let Some(name) = fifos.get(arg) {
-                    args.push(name.clone());
-                } else {
-                    args.push(arg.clone());
-                }
-            }
-            args
-        } else {
-            execution.args.clone()
-        };
+        CacheKeyItem::from_execution_with(execution, |f| file_keys[f].key().clone(), group)
+    }
+
+    /// Make a new `CacheKeyItem` based on an `Execution`, using `key_of` for finding the
+    /// `FileStoreKey` of its input files.
+    fn from_execution_with<F: Fn(&FileUuid) -> FileStoreKey>(
+        execution: &Execution,
+        key_of: F,
+        group: Option<&ExecutionGroup>,
+    ) -> CacheKeyItem {
+        let stdin = execution.stdin.as_ref().map(&key_of);
+        let inputs = key_inputs(execution, key_of);
+        let env = execution.effective_env().into_iter().collect_vec();
         CacheKeyItem {
             command: execution.command.clone(),
-            args,
+            args: key_args(execution, group),
             stdin,
             inputs,
+            directories: key_directories(execution),
             env,
+            toolchain: key_toolchain(execution),
+            container_image: execution.effective_container_image().map(String::from),
         }
     }
 }
 
+impl CacheKeyItem {
+    /// The command line of the execution, for showing it to the user.
+    fn command_line(&self) -> String {
+        let command = match &self.command {
+            ExecutionCommand::System(path) | ExecutionCommand::Local(path) => {
+                path.to_string_lossy().to_string()
+            }
+            ExecutionCommand::Builtin(command) => format!("{:?}", command),
+        };
+        std::iter::once(command)
+            .chain(self.args.iter().cloned())
+            .join(" ")
+    }
+
+    /// The keys of the input files of the execution, including the stdin.
+    fn input_keys(&self) -> impl Iterator<Item = &FileStoreKey> {
+        self.stdin
+            .iter()
+            .chain(self.inputs.iter().map(|(_, key, _)| key))
+    }
+}
+
+/// The information about a key of a cache file, for showing and filtering its entries.
+pub(crate) trait KeyInfo {
+    /// The command lines of the executions of the key, in the same order as the executions.
+    fn command_lines(&self) -> Vec<String>;
+    /// The keys of the input files of the executions of the key.
+    fn input_keys(&self) -> Vec<FileStoreKey>;
+}
+
+impl KeyInfo for CacheKey {
+    fn command_lines(&self) -> Vec<String> {
+        self.items.iter().map(CacheKeyItem::command_line).collect()
+    }
+
+    fn input_keys(&self) -> Vec<FileStoreKey> {
+        self.items
+            .iter()
+            .flat_map(CacheKeyItem::input_keys)
+            .cloned()
+            .collect()
+    }
+}
+
+impl KeyInfo for ArtifactKey {
+    fn command_lines(&self) -> Vec<String> {
+        vec![self.item.command_line()]
+    }
+
+    fn input_keys(&self) -> Vec<FileStoreKey> {
+        self.item.input_keys().cloned().collect()
+    }
+}
+
 impl CacheKey {
     /// Make a new `CacheKey` based on an `Execution` and on the mapping of its input files, from
     /// the UUIDs of the current DAG to the persisted `FileStoreKey`s.
@@ -91,6 +232,70 @@ impl CacheKey {
                 .collect(),
         }
     }
+
+    /// Make a new `CacheKey` like [`CacheKey::from_execution_group`], but knowing only the keys of
+    /// the input files, for example when they are not in the `FileStore`.
+    pub fn from_file_keys(
+        group: &ExecutionGroup,
+        file_keys: &HashMap<FileUuid, FileStoreKey>,
+    ) -> CacheKey {
+        CacheKey {
+            items: group
+                .executions
+                .iter()
+                .map(|e| {
+                    CacheKeyItem::from_execution_with(e, |f| file_keys[f].clone(), Some(group))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ArtifactKey {
+    /// Make a new `ArtifactKey` for the compilation of a group, `None` if the group is not a
+    /// compilation whose outputs are kept in the artifact cache.
+    pub fn from_execution_group(
+        group: &ExecutionGroup,
+        file_keys: &HashMap<FileUuid, FileStoreHandle>,
+    ) -> Option<ArtifactKey> {
+        let language = group.artifact()?;
+        Some(ArtifactKey {
+            language: language.to_string(),
+            item: CacheKeyItem::from_execution(&group.executions[0], file_keys, Some(group)),
+        })
+    }
+}
+
+impl CacheKeyComponents {
+    /// Describe the components of the cache keys of the executions of a group. `file_keys` maps
+    /// the UUIDs of the files to their description, the missing ones are marked as unknown.
+    pub fn from_execution_group(
+        group: &ExecutionGroup,
+        file_keys: &HashMap<FileUuid, String>,
+    ) -> Vec<CacheKeyComponents> {
+        let key_of = |f: &FileUuid| {
+            file_keys
+                .get(f)
+                .cloned()
+                .unwrap_or_else(|| format!("unknown file {}", f))
+        };
+        group
+            .executions
+            .iter()
+            .map(|execution| CacheKeyComponents {
+                description: execution.description.clone(),
+                command: execution.command.clone(),
+                args: key_args(execution, Some(group)),
+                stdin: execution.stdin.as_ref().map(key_of),
+                inputs: key_inputs(execution, key_of),
+                directories: key_directories(execution),
+                env: execution.effective_env().into_iter().collect_vec(),
+                toolchain: key_toolchain(execution),
+                container_image: execution.effective_container_image().map(String::from),
+                limits: execution.limits.clone(),
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -102,6 +307,7 @@ mod tests {
     use std::hash::{Hash, Hasher};
     use std::io::Write;
     use std::path::Path;
+    use task_maker_dag::Toolchain;
     use task_maker_store::{FileStore, ReadFileIterator};
 
     fn fake_file<P: AsRef<Path>>(path: P, content: &str, store: &FileStore) -> FileStoreHandle {
@@ -224,6 +430,28 @@ mod tests {
         assert_eq!(hash(&key1), hash(&key2));
         assert_ne!(hash(&key1), hash(&key3));
         assert_ne!(hash(&key1), hash(&key4));
+
+        let mut exec5 = Execution::new("exec5", ExecutionCommand::local("foo"));
+        exec5.input_directory(file1.uuid, "file1");
+        exec5.input(file2.uuid, "file2", false);
+        let key5 = CacheKeyItem::from_execution(&exec5, &map, None);
+        assert_ne!(key1, key5);
+        assert_eq!(key5.directories, vec![PathBuf::from("file1")]);
+    }
+
+    #[test]
+    fn test_container_image() {
+        let exec1 = Execution::new("exec1", ExecutionCommand::local("foo"));
+        let mut exec2 = Execution::new("exec2", ExecutionCommand::local("foo"));
+        exec2.container_image("alpine:3");
+        let mut exec3 = Execution::new("exec3", ExecutionCommand::local("foo"));
+        exec3.container_image("alpine:3");
+        let key1 = CacheKeyItem::from_execution(&exec1, &HashMap::new(), None);
+        let key2 = CacheKeyItem::from_execution(&exec2, &HashMap::new(), None);
+        let key3 = CacheKeyItem::from_execution(&exec3, &HashMap::new(), None);
+        assert_ne!(key1, key2);
+        assert_eq!(key2, key3);
+        assert_ne!(hash(&key1), hash(&key2));
     }
 
     #[test]
@@ -251,6 +479,73 @@ mod tests {
         assert_ne!(hash(&key1), hash(&key4));
     }
 
+    #[test]
+    fn test_env_config() {
+        let mut exec = Execution::new("exec", ExecutionCommand::local("foo"));
+        exec.env("foo", "bar");
+        let key1 = CacheKeyItem::from_execution(&exec, &HashMap::new(), None);
+        let mut dag = task_maker_dag::ExecutionDAG::new();
+        dag.config_mut()
+            .env([("foo".to_string(), "baz".to_string())].into());
+        dag.add_execution(exec);
+        let group = dag.data.execution_groups.values().next().unwrap();
+        let key2 = CacheKeyItem::from_execution(&group.executions[0], &HashMap::new(), None);
+        assert_ne!(key1, key2);
+        assert_eq!(key2.env, vec![("foo".to_string(), "baz".to_string())]);
+    }
+
+    #[test]
+    fn test_toolchain() {
+        let toolchain = |version: Option<&str>| Toolchain {
+            probe: vec!["g++".into(), "--version".into()],
+            version: version.map(String::from),
+        };
+        let mut exec1 = Execution::new("exec1", ExecutionCommand::system("g++"));
+        exec1.toolchain = Some(toolchain(Some("g++ 13.2.0")));
+        let mut exec2 = Execution::new("exec2", ExecutionCommand::system("g++"));
+        exec2.toolchain = Some(toolchain(Some("g++ 13.2.0")));
+        let mut exec3 = Execution::new("exec3", ExecutionCommand::system("g++"));
+        exec3.toolchain = Some(toolchain(Some("g++ 14.1.0")));
+        let mut exec4 = Execution::new("exec4", ExecutionCommand::system("g++"));
+        exec4.toolchain = Some(toolchain(None));
+        let key1 = CacheKeyItem::from_execution(&exec1, &HashMap::new(), None);
+        let key2 = CacheKeyItem::from_execution(&exec2, &HashMap::new(), None);
+        let key3 = CacheKeyItem::from_execution(&exec3, &HashMap::new(), None);
+        let key4 = CacheKeyItem::from_execution(&exec4, &HashMap::new(), None);
+        assert_eq!(key1, key2);
+        assert_ne!(key1, key3);
+        assert_ne!(key1, key4);
+        assert_eq!(hash(&key1), hash(&key2));
+        assert_ne!(hash(&key1), hash(&key3));
+        assert_ne!(hash(&key1), hash(&key4));
+    }
+
+    #[test]
+    fn test_artifact() {
+        let compilation = |description: &str, version: &str, language: Option<&str>| {
+            let mut exec = Execution::new(description, ExecutionCommand::system("g++"));
+            exec.toolchain = Some(Toolchain {
+                probe: vec!["g++".into(), "--version".into()],
+                version: Some(version.into()),
+            });
+            exec.limits_mut().cpu_time(description.len() as f64);
+            if let Some(language) = language {
+                exec.artifact(language);
+            }
+            ExecutionGroup::from(exec)
+        };
+        let key =
+            |group: &ExecutionGroup| ArtifactKey::from_execution_group(group, &HashMap::new());
+        let key1 = key(&compilation("comp1", "g++ 13.2.0", Some("C++"))).unwrap();
+        let key2 = key(&compilation("compilation2", "g++ 13.2.0", Some("C++"))).unwrap();
+        let key3 = key(&compilation("comp3", "g++ 14.1.0", Some("C++"))).unwrap();
+        let key4 = key(&compilation("comp4", "g++ 13.2.0", Some("C"))).unwrap();
+        assert_eq!(key1, key2);
+        assert_ne!(key1, key3);
+        assert_ne!(key1, key4);
+        assert!(key(&compilation("exec", "g++ 13.2.0", None)).is_none());
+    }
+
     #[test]
     fn test_fifo_arg_replace() {
         let mut group = ExecutionGroup::new("group");
@@ -295,4 +590,59 @@ mod tests {
         let key2 = CacheKey::from_execution_group(&group2, &HashMap::new());
         assert_eq!(key1, key2);
     }
+
+    #[test]
+    fn test_from_file_keys() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(tmpdir.path(), 1000, 1000).unwrap();
+        let handle1 = fake_file(tmpdir.path().join("file1"), "foo", &store);
+        let handle2 = fake_file(tmpdir.path().join("file2"), "bar", &store);
+        let file1 = task_maker_dag::File::new("file1");
+        let file2 = task_maker_dag::File::new("file2");
+        let mut exec = Execution::new("exec", ExecutionCommand::local("foo"));
+        exec.stdin(file1.uuid);
+        exec.input(&file2, "file2", false);
+        let group = ExecutionGroup::from(exec);
+        let handles: HashMap<_, _> = [(file1.uuid, handle1), (file2.uuid, handle2)]
+            .into_iter()
+            .collect();
+        let keys: HashMap<_, _> = handles
+            .iter()
+            .map(|(uuid, handle)| (*uuid, handle.key().clone()))
+            .collect();
+        assert_eq!(
+            CacheKey::from_execution_group(&group, &handles),
+            CacheKey::from_file_keys(&group, &keys)
+        );
+    }
+
+    #[test]
+    fn test_components() {
+        let file1 = task_maker_dag::File::new("file1");
+        let file2 = task_maker_dag::File::new("file2");
+        let mut exec = Execution::new("exec", ExecutionCommand::local("foo"));
+        exec.args(vec!["bar"]);
+        exec.stdin(file1.uuid);
+        exec.input(&file2, "b", true);
+        exec.input(&file1, "a", false);
+        let group = ExecutionGroup::from(exec);
+        let file_keys: HashMap<_, _> = [(file1.uuid, "hash1".to_string())].into_iter().collect();
+        let components = CacheKeyComponents::from_execution_group(&group, &file_keys);
+        assert_eq!(components.len(), 1);
+        let components = &components[0];
+        assert_eq!(components.description, "exec");
+        assert_eq!(components.args, vec!["bar"]);
+        assert_eq!(components.stdin.as_deref(), Some("hash1"));
+        assert_eq!(
+            components.inputs,
+            vec![
+                (PathBuf::from("a"), "hash1".to_string(), false),
+                (
+                    PathBuf::from("b"),
+                    format!("unknown file {}", file2.uuid),
+                    true
+                ),
+            ]
+        );
+    }
 }
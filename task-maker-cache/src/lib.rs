@@ -17,6 +17,26 @@
 //! than the corresponding one in `L2`. If a limit is not present, its value is assumed to be
 //! _infinite_.
 //!
+//! The entries may have a TTL, provided when querying the cache: an entry older than that is still
+//! returned, but it's marked as _stale_ and the caller is expected to run the executions again for
+//! refreshing it.
+//!
+//! The successful compilations are also stored in a separate _artifact cache_, keyed by the
+//! language, the version of the compiler, the flags and the sources. The artifact cache can be
+//! queried even when the cache of the executions is disabled, and it ignores the limits of the
+//! compilations, so the compiled binaries are reused as much as possible.
+//!
+//! A [`RemoteCache`] can be used as a second-level cache, shared between more instances of
+//! task-maker: when an entry is not found locally the remote is queried in background, downloading
+//! the entry and its output files, and the new entries are published to the remote in background.
+//! While the remote is being queried the cache returns [`CacheResult::Pending`], and the entry can
+//! be asked again after the lookup completes, see [`Cache::set_remote_listener`]. A directory can
+//! be served to the clients as a read-only remote cache with [`RemoteCacheServer`].
+//!
+//! The entries and their output files can also be moved to another machine by hand, with
+//! [`Cache::export`] and [`Cache::import`]. The entries can be inspected with [`Cache::iter`] and
+//! [`Cache::query`], and the selected ones removed with [`Cache::remove`].
+//!
 //! # Example
 //!
 //! ```
@@ -47,12 +67,10 @@
 //!         cpu_time: 1.123,
 //!         sys_time: 0.2,
 //!         wall_time: 1.5,
-//!         memory: 12345
+//!         memory: 12345,
+//!         ..Default::default()
 //!     },
-//!     was_killed: false,
-//!     was_cached: false,
-//!     stderr: None,
-//!     stdout: None,
+//!     ..Default::default()
 //! };
 //!
 //! // make the FileUuid -> FileStoreHandle map
@@ -64,10 +82,11 @@
 //! cache.insert(&exec.clone().into(), &file_keys, vec![result]);
 //!
 //! // retrieve the result from the cache
-//! let res = cache.get(&exec.into(), &file_keys, &mut store);
+//! let res = cache.get(&exec.into(), &file_keys, &mut store, None);
 //! match res {
-//!     CacheResult::Miss => panic!("Expecting a hit"),
-//!     CacheResult::Hit { result, outputs } => {
+//!     CacheResult::Miss | CacheResult::Pending => panic!("Expecting a hit"),
+//!     CacheResult::Hit { result, outputs, stale } => {
+//!         assert!(!stale);
 //!         assert_eq!(result[0].status, ExecutionStatus::Success);
 //!         assert_eq!(result[0].resources.memory, 12345);
 //!     }
@@ -80,44 +99,94 @@
 #[macro_use]
 extern crate log;
 
+mod archive;
 mod entry;
 mod key;
+mod query;
+mod remote;
+mod server;
 mod storage;
+pub use archive::CacheArchiveSummary;
 use entry::CacheEntry;
-use key::CacheKey;
+pub use key::CacheKeyComponents;
+use key::{ArtifactKey, CacheKey};
+pub use query::{CacheEntryInfo, CacheFilter};
+pub use remote::RemoteCache;
+use remote::RemoteCacheClient;
+pub use server::{RemoteCacheServer, REMOTE_CACHE_PORT};
 use storage::CacheFile;
 
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::create_dir_all;
 use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Context, Error};
 use itertools::Itertools;
 
-use task_maker_dag::{ExecutionGroup, ExecutionResult, ExecutionStatus, FileUuid};
-use task_maker_store::{FileStore, FileStoreHandle};
+use task_maker_dag::{
+    ExecutionGroup, ExecutionResourcesUsage, ExecutionResult, ExecutionStatus, FileUuid,
+};
+use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey};
 
 /// The name of the file which holds the cache data.
 const CACHE_FILE: &str = "cache.bin";
+/// The name of the file which holds the artifact cache.
+const ARTIFACTS_FILE: &str = "artifacts.bin";
 
 /// Handle the cached executions, loading and storing them to disk.
 #[derive(Debug)]
 pub struct Cache {
     /// Cache entries.
     file: CacheFile,
+    /// The entries of the artifact cache, a single one for each key.
+    artifacts: CacheFile<ArtifactKey>,
+    /// The client of the second-level cache, if any.
+    remote: Option<RemoteCacheClient>,
+    /// The keys of the files removed from the `FileStore`, received after the first lookup.
+    evictions: Option<Receiver<FileStoreKey>>,
 }
 
-/// The result of a cache query, can be either successful (`Hit`) or unsuccessful (`Miss`).
+/// The result of a cache query, can be either successful (`Hit`) or unsuccessful (`Miss`), or it
+/// can be still waiting for the remote cache (`Pending`).
 pub enum CacheResult {
     /// The requested entry is not present in the cache.
     Miss,
+    /// The requested entry is not present in the local cache and it's being looked up in the remote
+    /// one. The query should be repeated after the lookup completes.
+    Pending,
     /// The requested entry is present in the cache.
     Hit {
         /// The result of the execution.
         result: Vec<ExecutionResult>,
         /// The outputs of the execution.
         outputs: HashMap<FileUuid, FileStoreHandle>,
+        /// Whether the entry is older than the requested TTL and should be refreshed.
+        stale: bool,
+    },
+}
+
+/// The result of a cache query for an execution that is not going to be run, see
+/// [`Cache::estimate`].
+pub enum CacheEstimate {
+    /// The requested entry is not present in the cache.
+    Miss {
+        /// The resources used by a previous execution with the same key, if any. For example the
+        /// entry may have been computed with different limits, or its outputs may have been
+        /// flushed from the `FileStore`.
+        resources: Option<Vec<ExecutionResourcesUsage>>,
+    },
+    /// The requested entry is present in the cache.
+    Hit {
+        /// The resources used by the cached execution.
+        resources: Vec<ExecutionResourcesUsage>,
+        /// The keys of the outputs of the execution.
+        outputs: HashMap<FileUuid, FileStoreKey>,
+        /// Whether the entry is older than the requested TTL and should be refreshed.
+        stale: bool,
     },
 }
 
@@ -131,21 +200,80 @@ impl Cache {
         })?;
         let path = cache_dir.join(CACHE_FILE);
         let file = CacheFile::load(path).context("Failed to load cache file")?;
-        Ok(Self { file })
+        let artifacts = CacheFile::load(cache_dir.join(ARTIFACTS_FILE))
+            .context("Failed to load artifact cache file")?;
+        Ok(Self {
+            file,
+            artifacts,
+            remote: None,
+            evictions: None,
+        })
+    }
+
+    /// Use a remote cache as second-level cache, shared with other instances of task-maker. The
+    /// files found in the remote are downloaded in `file_store`.
+    pub fn set_remote(
+        &mut self,
+        remote: RemoteCache,
+        file_store: Arc<FileStore>,
+    ) -> Result<(), Error> {
+        self.remote = Some(RemoteCacheClient::new(remote, file_store)?);
+        Ok(())
+    }
+
+    /// Call `listener`, from another thread, every time a lookup in the remote cache completes, so
+    /// that the queries that returned [`CacheResult::Pending`] can be repeated.
+    pub fn set_remote_listener<F: Fn() + Send + 'static>(&self, listener: F) {
+        if let Some(remote) = &self.remote {
+            remote.set_listener(Box::new(listener));
+        }
+    }
+
+    /// Wait until at least one of the lookups in the remote cache completes, if any is in progress.
+    pub fn wait_remote(&mut self) {
+        let Some(remote) = self.remote.as_mut() else {
+            return;
+        };
+        for (key, entry) in remote.wait() {
+            self.insert_entry(key, entry);
+        }
     }
 
     /// Insert a new entry inside the cache. They key is computed based on the execution's metadata
     /// and on the hash of it's inputs, defined by the mapping `file_keys` from the UUIDs of the DAG
     /// to the persistent `FileStoreKey`s.
+    ///
+    /// If the group is a successful compilation, it's stored also in the artifact cache.
     pub fn insert(
         &mut self,
         group: &ExecutionGroup,
         file_keys: &HashMap<FileUuid, FileStoreHandle>,
         result: Vec<ExecutionResult>,
     ) {
+        if let Some(key) = ArtifactKey::from_execution_group(group, file_keys) {
+            if result.iter().all(|r| r.status.is_success()) {
+                let entry = CacheEntry::from_execution_group(group, file_keys, result.clone());
+                *self.artifacts.entry(key).or_default() = vec![entry];
+                self.artifacts.mark_dirty();
+            }
+        }
         let key = CacheKey::from_execution_group(group, file_keys);
-        let set = self.file.entry(key).or_default();
         let entry = CacheEntry::from_execution_group(group, file_keys, result);
+        if let Some(remote) = &self.remote {
+            let outputs: HashSet<_> = entry.output_keys().collect();
+            let files = file_keys
+                .values()
+                .filter(|handle| outputs.contains(handle.key()))
+                .cloned()
+                .collect();
+            remote.publish(key.clone(), entry.clone(), files);
+        }
+        self.insert_entry(key, entry);
+    }
+
+    /// Insert an entry for the key, replacing the one with the same limits.
+    fn insert_entry(&mut self, key: CacheKey, entry: CacheEntry) {
+        let set = self.file.entry(key).or_default();
         // Do not insert duplicated keys, replace if the limits are the same.
         let pos = set.iter().find_position(|e| e.same_limits(&entry));
         if let Some((pos, _)) = pos {
@@ -157,17 +285,163 @@ impl Cache {
     }
 
     /// Search in the cache for a valid entry, returning a cache hit if it's found or a cache miss
-    /// if not.
+    /// if not. If `ttl` is provided, the entries older than it are marked as stale.
     ///
     /// The result contains the handles to the files in the `FileStore`, preventing the flushing
     /// from erasing them.
+    ///
+    /// If the entry is not found and there is a remote cache, the entry is looked up in the remote
+    /// in background and `Pending` is returned.
     pub fn get(
         &mut self,
         group: &ExecutionGroup,
         file_keys: &HashMap<FileUuid, FileStoreHandle>,
         file_store: &FileStore,
+        ttl: Option<Duration>,
     ) -> CacheResult {
         let key = CacheKey::from_execution_group(group, file_keys);
+        self.get_key(key, group, file_store, ttl)
+    }
+
+    /// Search in the artifact cache for the outputs of a compilation. Unlike [`Cache::get`] the
+    /// limits of the compilation are not checked, and the entries never become stale.
+    pub fn get_artifact(
+        &mut self,
+        group: &ExecutionGroup,
+        file_keys: &HashMap<FileUuid, FileStoreHandle>,
+        file_store: &FileStore,
+    ) -> CacheResult {
+        self.invalidate_evicted(file_store);
+        let Some(key) = ArtifactKey::from_execution_group(group, file_keys) else {
+            return CacheResult::Miss;
+        };
+        let Entry::Occupied(entries) = self.artifacts.entry(key) else {
+            return CacheResult::Miss;
+        };
+        for entry in entries.get() {
+            if let Some(outputs) = entry.outputs(file_store, group) {
+                return CacheResult::Hit {
+                    result: cached_results(group, entry),
+                    outputs,
+                    stale: false,
+                };
+            }
+        }
+        CacheResult::Miss
+    }
+
+    /// Search in the cache for an entry of an execution without running it, for estimating the
+    /// cost of an evaluation. The input files are identified by their `FileStoreKey` since they
+    /// may not be in the `FileStore` yet.
+    ///
+    /// When the entry is not found, the resources recorded by a previous execution with the same
+    /// key are returned, if any.
+    pub fn estimate(
+        &mut self,
+        group: &ExecutionGroup,
+        file_keys: &HashMap<FileUuid, FileStoreKey>,
+        file_store: &FileStore,
+        ttl: Option<Duration>,
+    ) -> CacheEstimate {
+        let key = CacheKey::from_file_keys(group, file_keys);
+        let mut result = self.get_key(key.clone(), group, file_store, ttl);
+        while let CacheResult::Pending = result {
+            self.wait_remote();
+            result = self.get_key(key.clone(), group, file_store, ttl);
+        }
+        match result {
+            CacheResult::Hit {
+                result,
+                outputs,
+                stale,
+            } => CacheEstimate::Hit {
+                resources: result.into_iter().map(|r| r.resources).collect(),
+                outputs: outputs
+                    .into_iter()
+                    .map(|(uuid, handle)| (uuid, handle.key().clone()))
+                    .collect(),
+                stale,
+            },
+            CacheResult::Miss | CacheResult::Pending => {
+                let resources = match self.file.entry(key) {
+                    Entry::Occupied(entry) => entry.get().last().map(|entry| {
+                        entry
+                            .items
+                            .iter()
+                            .map(|item| item.result.resources.clone())
+                            .collect()
+                    }),
+                    Entry::Vacant(_) => None,
+                };
+                CacheEstimate::Miss { resources }
+            }
+        }
+    }
+
+    /// Remove all the entries with an output among the provided files, for example because they
+    /// have been removed from the `FileStore`. Returns the number of removed entries.
+    pub fn invalidate_outputs(&mut self, files: &HashSet<FileStoreKey>) -> usize {
+        let keep = |entry: &CacheEntry| !entry.output_keys().any(|key| files.contains(key));
+        self.file.retain(|_, entry| keep(entry)) + self.artifacts.retain(|_, entry| keep(entry))
+    }
+
+    /// Invalidate the entries whose outputs have been removed from the `FileStore` since the last
+    /// call. The first call subscribes to the removals of the store.
+    fn invalidate_evicted(&mut self, file_store: &FileStore) {
+        let evictions = self
+            .evictions
+            .get_or_insert_with(|| file_store.subscribe_evictions());
+        let evicted: HashSet<_> = evictions.try_iter().collect();
+        if evicted.is_empty() {
+            return;
+        }
+        let removed = self.invalidate_outputs(&evicted);
+        debug!(
+            "{} files removed from the store, {} cache entries invalidated",
+            evicted.len(),
+            removed
+        );
+    }
+
+    /// Search for a valid entry with that key in the local cache, with the entries found so far in
+    /// the remote one. If it's not found, start looking it up in the remote.
+    fn get_key(
+        &mut self,
+        key: CacheKey,
+        group: &ExecutionGroup,
+        file_store: &FileStore,
+        ttl: Option<Duration>,
+    ) -> CacheResult {
+        self.invalidate_evicted(file_store);
+        if let Some(remote) = self.remote.as_mut() {
+            let entries = remote.receive();
+            if !entries.is_empty() {
+                debug!("Found {} entries in the remote cache", entries.len());
+            }
+            for (key, entry) in entries {
+                self.insert_entry(key, entry);
+            }
+        }
+        let result = self.lookup(key.clone(), group, file_store, ttl);
+        let Some(remote) = self.remote.as_mut() else {
+            return result;
+        };
+        // the outputs of the entries are now held by the result, if any
+        remote.release(&key);
+        match result {
+            CacheResult::Miss if remote.lookup(&key, group) => CacheResult::Pending,
+            result => result,
+        }
+    }
+
+    /// Search in the local cache for a valid entry with that key.
+    fn lookup(
+        &mut self,
+        key: CacheKey,
+        group: &ExecutionGroup,
+        file_store: &FileStore,
+        ttl: Option<Duration>,
+    ) -> CacheResult {
         let entry = self.file.entry(key);
         let entry = match &entry {
             Entry::Vacant(_) => return CacheResult::Miss,
@@ -181,25 +455,10 @@ impl Cache {
                 }
                 Some(outputs) => {
                     if entry.is_compatible(group) {
-                        let mut results = Vec::new();
-                        for (exec, item) in group.executions.iter().zip(entry.items.iter()) {
-                            let (exit_status, signal) = match &item.result.status {
-                                ExecutionStatus::ReturnCode(c) => (*c, None),
-                                ExecutionStatus::Signal(s, name) => (0, Some((*s, name.clone()))),
-                                _ => (0, None),
-                            };
-                            results.push(ExecutionResult {
-                                status: exec.status(exit_status, signal, &item.result.resources),
-                                was_killed: item.result.was_killed,
-                                was_cached: true,
-                                resources: item.result.resources.clone(),
-                                stdout: item.result.stdout.clone(),
-                                stderr: item.result.stderr.clone(),
-                            });
-                        }
                         return CacheResult::Hit {
-                            result: results,
+                            result: cached_results(group, entry),
                             outputs,
+                            stale: ttl.is_some_and(|ttl| entry.is_stale(ttl)),
                         };
                     }
                 }
@@ -208,6 +467,12 @@ impl Cache {
         CacheResult::Miss
     }
 
+    /// Write the cache to disk now, without waiting for it to be dropped.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.file.store()?;
+        self.artifacts.store()
+    }
+
     /// Checks whether a result is allowed in the cache.
     pub fn is_cacheable(result: &ExecutionResult) -> bool {
         !matches!(result.status, ExecutionStatus::InternalError(_))
@@ -219,5 +484,76 @@ impl Drop for Cache {
         if let Err(e) = self.file.store() {
             warn!("Failed to store cache file: {:?}", e);
         }
+        if let Err(e) = self.artifacts.store() {
+            warn!("Failed to store artifact cache file: {:?}", e);
+        }
+    }
+}
+
+/// The results of the executions of a group, as stored in a cache entry. The statuses are computed
+/// again with the limits of the group.
+fn cached_results(group: &ExecutionGroup, entry: &CacheEntry) -> Vec<ExecutionResult> {
+    let mut results = Vec::new();
+    for (exec, item) in group.executions.iter().zip(entry.items.iter()) {
+        let (exit_status, signal) = match &item.result.status {
+            ExecutionStatus::ReturnCode(c) => (*c, None),
+            ExecutionStatus::Signal(s, name) => (0, Some((*s, name.clone()))),
+            _ => (0, None),
+        };
+        results.push(ExecutionResult {
+            status: exec.status(exit_status, signal, &item.result.resources),
+            was_killed: item.result.was_killed,
+            was_cached: true,
+            resources: item.result.resources.clone(),
+            stdout: item.result.stdout.clone(),
+            stderr: item.result.stderr.clone(),
+            sandbox: item.result.sandbox.clone(),
+            toolchain: item.result.toolchain.clone(),
+            ..Default::default()
+        });
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use task_maker_dag::{Execution, ExecutionCommand};
+
+    #[test]
+    fn test_invalidate_evicted() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(tmpdir.path().join("store"), 20, 10).unwrap();
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let mut exec = Execution::new("exec", ExecutionCommand::system("true"));
+        let output = exec.output("out");
+        let group: ExecutionGroup = exec.into();
+        let result = ExecutionResult {
+            status: ExecutionStatus::Success,
+            ..Default::default()
+        };
+        {
+            let key = FileStoreKey::from_content(b"output");
+            let handle = store
+                .store(&key, std::iter::once(b"output".to_vec()))
+                .unwrap();
+            let file_keys = HashMap::from([(output.uuid, handle)]);
+            cache.insert(&group, &file_keys, vec![result]);
+        }
+        assert!(matches!(
+            cache.get(&group, &HashMap::new(), &store, None),
+            CacheResult::Hit { .. }
+        ));
+        assert_eq!(cache.file.iter().count(), 1);
+
+        // storing a big file removes the output of the execution from the store
+        let content = vec![b'x'; 20];
+        let key = FileStoreKey::from_content(&content);
+        let _handle = store.store(&key, std::iter::once(content)).unwrap();
+        assert!(matches!(
+            cache.get(&group, &HashMap::new(), &store, None),
+            CacheResult::Miss
+        ));
+        assert_eq!(cache.file.iter().count(), 0);
     }
 }
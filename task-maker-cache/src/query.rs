@@ -0,0 +1,204 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::SystemTime;
+
+use task_maker_dag::ExecutionStatus;
+use task_maker_store::FileStoreKey;
+
+use crate::entry::CacheEntry;
+use crate::key::KeyInfo;
+use crate::Cache;
+
+/// The information about an entry of the cache, for inspecting its content. See [`Cache::iter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheEntryInfo {
+    /// The identifier of the entry, derived from its key and from when it was created. It doesn't
+    /// change while the entry is in the cache.
+    pub id: String,
+    /// Whether the entry is in the artifact cache instead of in the cache of the executions.
+    pub artifact: bool,
+    /// The command line of each execution of the entry.
+    pub commands: Vec<String>,
+    /// The keys of the input files of the executions, including their stdin.
+    pub inputs: Vec<FileStoreKey>,
+    /// The keys of the files produced by the executions, including their stdout and stderr.
+    pub outputs: Vec<FileStoreKey>,
+    /// The status of each execution of the entry.
+    pub statuses: Vec<ExecutionStatus>,
+    /// When the executions of the entry were run.
+    pub created: SystemTime,
+}
+
+/// Which entries of the cache to select. An entry is selected if it matches all the conditions
+/// that are set, the default filter selects all the entries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheFilter {
+    /// Select the entries whose id starts with this prefix.
+    pub id: Option<String>,
+    /// Select the entries with a command line that contains this string.
+    pub command: Option<String>,
+    /// Select the entries with an input or an output file whose key starts with this prefix.
+    pub file: Option<String>,
+}
+
+impl CacheEntryInfo {
+    /// Build the information of an entry of a cache file.
+    fn new<K: KeyInfo + Hash>(key: &K, entry: &CacheEntry, artifact: bool) -> CacheEntryInfo {
+        let mut hasher = DefaultHasher::new();
+        artifact.hash(&mut hasher);
+        key.hash(&mut hasher);
+        entry.created.hash(&mut hasher);
+        CacheEntryInfo {
+            id: format!("{:016x}", hasher.finish()),
+            artifact,
+            commands: key.command_lines(),
+            inputs: key.input_keys(),
+            outputs: entry.output_keys().cloned().collect(),
+            statuses: entry
+                .items
+                .iter()
+                .map(|item| item.result.status.clone())
+                .collect(),
+            created: entry.created,
+        }
+    }
+}
+
+impl CacheFilter {
+    /// Whether the entry is selected by this filter.
+    pub fn matches(&self, info: &CacheEntryInfo) -> bool {
+        if let Some(id) = &self.id {
+            if !info.id.starts_with(id.as_str()) {
+                return false;
+            }
+        }
+        if let Some(command) = &self.command {
+            if !info.commands.iter().any(|c| c.contains(command.as_str())) {
+                return false;
+            }
+        }
+        if let Some(file) = &self.file {
+            let file = file.to_ascii_lowercase();
+            let mut keys = info.inputs.iter().chain(info.outputs.iter());
+            if !keys.any(|key| key.to_string().starts_with(&file)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Cache {
+    /// Iterate over the entries of the cache, both of the executions and of the artifacts, in no
+    /// particular order.
+    pub fn iter(&self) -> impl Iterator<Item = CacheEntryInfo> + '_ {
+        let entries = self.file.iter().flat_map(|(key, entries)| {
+            entries
+                .iter()
+                .map(move |entry| CacheEntryInfo::new(key, entry, false))
+        });
+        let artifacts = self.artifacts.iter().flat_map(|(key, entries)| {
+            entries
+                .iter()
+                .map(move |entry| CacheEntryInfo::new(key, entry, true))
+        });
+        entries.chain(artifacts)
+    }
+
+    /// The entries of the cache selected by the filter.
+    pub fn query(&self, filter: &CacheFilter) -> Vec<CacheEntryInfo> {
+        self.iter().filter(|info| filter.matches(info)).collect()
+    }
+
+    /// Remove from the cache the entries selected by the filter, returning the number of removed
+    /// entries. Their output files are left in the `FileStore`.
+    pub fn remove(&mut self, filter: &CacheFilter) -> usize {
+        let removed = self
+            .file
+            .retain(|key, entry| !filter.matches(&CacheEntryInfo::new(key, entry, false)));
+        removed
+            + self
+                .artifacts
+                .retain(|key, entry| !filter.matches(&CacheEntryInfo::new(key, entry, true)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use task_maker_dag::{Execution, ExecutionCommand, ExecutionResult, ExecutionStatus, File};
+    use task_maker_store::FileStore;
+
+    use super::*;
+
+    fn result(status: ExecutionStatus) -> ExecutionResult {
+        ExecutionResult {
+            status,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_query_and_remove() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(tmpdir.path().join("store"), 1000, 1000).unwrap();
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+
+        let key = FileStoreKey::from_content(b"input");
+        let handle = store
+            .store(&key, std::iter::once(b"input".to_vec()))
+            .unwrap();
+        let mut exec = Execution::new("solution", ExecutionCommand::local("solution"));
+        exec.args(vec!["--fast"]);
+        let input = File::new("input");
+        exec.stdin(&input);
+        let file_keys = HashMap::from([(input.uuid, handle)]);
+        cache.insert(
+            &exec.into(),
+            &file_keys,
+            vec![result(ExecutionStatus::Success)],
+        );
+        let exec = Execution::new("checker", ExecutionCommand::system("diff"));
+        cache.insert(
+            &exec.into(),
+            &HashMap::new(),
+            vec![result(ExecutionStatus::ReturnCode(1))],
+        );
+
+        let entries = cache.query(&CacheFilter::default());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(cache.iter().count(), 2);
+
+        let filter = CacheFilter {
+            command: Some("solution --fast".into()),
+            ..Default::default()
+        };
+        let entries = cache.query(&filter);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].inputs, vec![key.clone()]);
+        assert_eq!(entries[0].statuses, vec![ExecutionStatus::Success]);
+
+        let filter = CacheFilter {
+            file: Some(
+                key.to_string()
+                    .chars()
+                    .take(8)
+                    .collect::<String>()
+                    .to_uppercase(),
+            ),
+            ..Default::default()
+        };
+        assert_eq!(cache.query(&filter), entries);
+        let filter = CacheFilter {
+            id: Some(entries[0].id.clone()),
+            ..Default::default()
+        };
+        assert_eq!(cache.query(&filter), entries);
+
+        assert_eq!(cache.remove(&filter), 1);
+        let entries = cache.query(&CacheFilter::default());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].commands, vec!["diff".to_string()]);
+    }
+}
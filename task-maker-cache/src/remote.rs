@@ -0,0 +1,930 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context, Error};
+
+use task_maker_dag::{ExecutionGroup, ExecutionLimits};
+use task_maker_store::{ContentMismatchError, FileStore, FileStoreHandle, FileStoreKey};
+
+use crate::entry::CacheEntry;
+use crate::key::CacheKey;
+use crate::server::{server_contains, server_open, REMOTE_CACHE_PORT};
+use crate::storage::MAGIC;
+
+/// Timeout for connecting to an HTTP remote cache.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Timeout for a single read or write on the connection to an HTTP remote cache.
+pub(crate) const IO_TIMEOUT: Duration = Duration::from_secs(60);
+/// Number of threads looking up the entries in the remote cache.
+const LOOKUP_THREADS: usize = 4;
+/// For how long the remote is not queried after a failure. The delay doubles at each consecutive
+/// failure, up to `MAX_RETRY_DELAY`.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+/// The maximum delay before querying again a remote that keeps failing.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// A remote cache, shared between more instances of task-maker, used as a second-level cache.
+///
+/// The remote is a simple key-value store with 2 namespaces: `ac/` contains the cache entries,
+/// addressed by the hash of their cache key and of their limits, and `cas/` contains the output
+/// files of the executions, addressed by their `FileStoreKey`. Each value is written at once and
+/// never modified, so more instances can publish to the same remote concurrently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteCache {
+    /// The remote cache is a directory, for example a network file system shared by more machines.
+    /// Parsed from `file:///path/to/dir`.
+    Directory(PathBuf),
+    /// The remote cache is an HTTP server supporting `GET`, `HEAD` and `PUT`, like a WebDAV server
+    /// or a build cache server. Parsed from `http://host[:port][/prefix]`.
+    Http {
+        /// The host name of the server.
+        host: String,
+        /// The port of the server.
+        port: u16,
+        /// The prefix of the paths of the keys, without the trailing slash.
+        prefix: String,
+    },
+    /// The remote cache is served by a task-maker server started with `--remote-cache-addr`. It's
+    /// read-only: the server publishes only the results of the executions it ran itself. Parsed
+    /// from `tcp://[:password@]host[:port]`.
+    Server {
+        /// The address of the server, with the port.
+        address: String,
+        /// The password for connecting to the server, if any.
+        password: Option<String>,
+    },
+}
+
+impl FromStr for RemoteCache {
+    type Err = Error;
+
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = url.strip_prefix("file://") {
+            if path.is_empty() {
+                bail!("Missing the path of the remote cache directory");
+            }
+            Ok(RemoteCache::Directory(PathBuf::from(path)))
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            let (address, prefix) = match rest.find('/') {
+                Some(pos) => rest.split_at(pos),
+                None => (rest, ""),
+            };
+            let (host, port) = match address.rsplit_once(':') {
+                Some((host, port)) => (
+                    host,
+                    port.parse()
+                        .with_context(|| format!("Invalid port in remote cache URL: {}", port))?,
+                ),
+                None => (address, 80),
+            };
+            if host.is_empty() {
+                bail!("Missing the host of the remote cache");
+            }
+            Ok(RemoteCache::Http {
+                host: host.into(),
+                port,
+                prefix: prefix.trim_end_matches('/').into(),
+            })
+        } else if let Some(rest) = url.strip_prefix("tcp://") {
+            let (password, address) = match rest.rsplit_once('@') {
+                Some((user, address)) => {
+                    let password = user.split_once(':').map(|(_, password)| password.into());
+                    (password, address)
+                }
+                None => (None, rest),
+            };
+            if address.is_empty() || address.starts_with(':') {
+                bail!("Missing the host of the remote cache");
+            }
+            let address = if address.rsplit_once(':').is_some() {
+                address.into()
+            } else {
+                format!("{}:{}", address, REMOTE_CACHE_PORT)
+            };
+            Ok(RemoteCache::Server { address, password })
+        } else if url.starts_with("https://") {
+            bail!("HTTPS remote caches are not supported, use a local proxy to reach them")
+        } else {
+            bail!(
+                "Invalid remote cache URL '{}', it must start with file://, http:// or tcp://",
+                url
+            )
+        }
+    }
+}
+
+impl RemoteCache {
+    /// Open the value with the specified name for reading it, returning `None` if it's not in the
+    /// remote.
+    fn open(&self, name: &str) -> Result<Option<Box<dyn Read + Send>>, Error> {
+        match self {
+            RemoteCache::Directory(dir) => {
+                let path = dir.join(name);
+                if !path.exists() {
+                    return Ok(None);
+                }
+                let file = std::fs::File::open(&path)
+                    .with_context(|| format!("Failed to open {}", path.display()))?;
+                Ok(Some(Box::new(file)))
+            }
+            RemoteCache::Http { .. } => {
+                let (status, body) = self.http_request("GET", name, None)?;
+                match status {
+                    200 => Ok(Some(Box::new(body))),
+                    404 => Ok(None),
+                    _ => bail!("GET {} failed with status {}", name, status),
+                }
+            }
+            RemoteCache::Server { address, password } => {
+                let value = server_open(address, password.as_deref(), name)?;
+                Ok(value.map(|value| Box::new(value) as Box<dyn Read + Send>))
+            }
+        }
+    }
+
+    /// Fetch the value with the specified name, returning `None` if it's not in the remote.
+    pub(crate) fn get(&self, name: &str) -> Result<Option<Vec<u8>>, Error> {
+        let Some(mut reader) = self.open(name)? else {
+            return Ok(None);
+        };
+        let mut content = Vec::new();
+        reader
+            .read_to_end(&mut content)
+            .with_context(|| format!("Failed to read {}", name))?;
+        Ok(Some(content))
+    }
+
+    /// Check whether the value with the specified name is in the remote.
+    pub(crate) fn contains(&self, name: &str) -> Result<bool, Error> {
+        match self {
+            RemoteCache::Directory(dir) => Ok(dir.join(name).exists()),
+            RemoteCache::Http { .. } => {
+                let (status, _) = self.http_request("HEAD", name, None)?;
+                match status {
+                    200 => Ok(true),
+                    404 => Ok(false),
+                    _ => bail!("HEAD {} failed with status {}", name, status),
+                }
+            }
+            RemoteCache::Server { address, password } => {
+                server_contains(address, password.as_deref(), name)
+            }
+        }
+    }
+
+    /// Whether the remote cannot be written, so the new entries are not published to it.
+    pub fn is_read_only(&self) -> bool {
+        matches!(self, RemoteCache::Server { .. })
+    }
+
+    /// Store a value with the specified name in the remote, reading its `len` bytes from
+    /// `content`.
+    pub(crate) fn put(&self, name: &str, content: &mut dyn Read, len: u64) -> Result<(), Error> {
+        match self {
+            RemoteCache::Directory(dir) => {
+                let path = dir.join(name);
+                let parent = path.parent().context("Invalid remote cache path")?;
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+                // write to a temporary file first, so that the readers never see a partial value
+                let mut tmp = tempfile::NamedTempFile::new_in(parent)
+                    .context("Failed to create temporary file")?;
+                let written = std::io::copy(&mut content.take(len), &mut tmp)
+                    .context("Failed to write temporary file")?;
+                if written != len {
+                    bail!("The value {} is shorter than {} bytes", name, len);
+                }
+                tmp.persist(&path)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                Ok(())
+            }
+            RemoteCache::Http { .. } => {
+                let (status, _) = self.http_request("PUT", name, Some((content, len)))?;
+                if !(200..300).contains(&status) {
+                    bail!("PUT {} failed with status {}", name, status);
+                }
+                Ok(())
+            }
+            RemoteCache::Server { .. } => {
+                bail!("The remote cache of a task-maker server is read-only")
+            }
+        }
+    }
+
+    /// Send an HTTP/1.1 request to the remote, with the body of the given length read from the
+    /// reader. Returns the status code and a reader of the body of the response, the connection
+    /// is not reused.
+    fn http_request(
+        &self,
+        method: &str,
+        name: &str,
+        body: Option<(&mut dyn Read, u64)>,
+    ) -> Result<(u16, HttpBody<BufReader<TcpStream>>), Error> {
+        let RemoteCache::Http { host, port, prefix } = self else {
+            bail!("Not an HTTP remote cache");
+        };
+        let address = (host.as_str(), *port)
+            .to_socket_addrs()
+            .with_context(|| format!("Failed to resolve {}", host))?
+            .next()
+            .ok_or_else(|| anyhow!("No address found for {}", host))?;
+        let mut stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)
+            .with_context(|| format!("Failed to connect to {}:{}", host, port))?;
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+        let header = format!(
+            "{} {}/{} HTTP/1.1\r\nHost: {}:{}\r\nConnection: close\r\nContent-Length: {}\r\n\r\n",
+            method,
+            prefix,
+            name,
+            host,
+            port,
+            body.as_ref().map_or(0, |(_, len)| *len)
+        );
+        stream
+            .write_all(header.as_bytes())
+            .context("Failed to send the request")?;
+        if let Some((content, len)) = body {
+            let sent = std::io::copy(&mut content.take(len), &mut stream)
+                .context("Failed to send the request")?;
+            if sent != len {
+                bail!("The body of the request is shorter than {} bytes", len);
+            }
+        }
+        read_http_response(BufReader::new(stream), method == "HEAD")
+    }
+}
+
+/// The body of an HTTP response, decoded while it's read.
+enum HttpBody<R> {
+    /// The body has a known length.
+    Sized(std::io::Take<R>),
+    /// The body is sent in chunks, with `remaining` bytes left in the current one.
+    Chunked {
+        /// The reader of the response.
+        reader: R,
+        /// The bytes left in the current chunk.
+        remaining: usize,
+        /// Whether the last chunk has been read.
+        done: bool,
+    },
+    /// The body ends when the connection is closed.
+    UntilClose(R),
+}
+
+impl<R: BufRead> Read for HttpBody<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+        match self {
+            HttpBody::Sized(reader) => reader.read(buf),
+            HttpBody::UntilClose(reader) => reader.read(buf),
+            HttpBody::Chunked {
+                reader,
+                remaining,
+                done,
+            } => {
+                if *done || buf.is_empty() {
+                    return Ok(0);
+                }
+                if *remaining == 0 {
+                    let mut line = String::new();
+                    reader.read_line(&mut line)?;
+                    *remaining = line
+                        .split(';')
+                        .next()
+                        .and_then(|size| usize::from_str_radix(size.trim(), 16).ok())
+                        .ok_or_else(|| invalid("Invalid HTTP chunk size"))?;
+                    if *remaining == 0 {
+                        *done = true;
+                        return Ok(0);
+                    }
+                }
+                let len = buf.len().min(*remaining);
+                let read = reader.read(&mut buf[..len])?;
+                if read == 0 {
+                    return Err(invalid("Truncated HTTP chunk"));
+                }
+                *remaining -= read;
+                if *remaining == 0 {
+                    // each chunk is followed by a line break
+                    let mut crlf = [0; 2];
+                    reader.read_exact(&mut crlf)?;
+                }
+                Ok(read)
+            }
+        }
+    }
+}
+
+/// Read the header of an HTTP/1.1 response, returning the status code and a reader of the
+/// decoded body.
+fn read_http_response<R: BufRead>(
+    mut reader: R,
+    no_body: bool,
+) -> Result<(u16, HttpBody<R>), Error> {
+    let mut status = None;
+    let mut length = None;
+    let mut chunked = false;
+    loop {
+        let mut line = String::new();
+        if reader
+            .read_line(&mut line)
+            .context("Failed to read the response")?
+            == 0
+        {
+            bail!("Truncated HTTP response");
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if status.is_none() {
+            status = Some(
+                line.split(' ')
+                    .nth(1)
+                    .and_then(|status| status.parse().ok())
+                    .context("Invalid HTTP status line")?,
+            );
+            continue;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => length = value.trim().parse().ok(),
+            "transfer-encoding" => chunked = value.to_ascii_lowercase().contains("chunked"),
+            _ => {}
+        }
+    }
+    let status = status.context("Invalid HTTP status line")?;
+    let body = if no_body {
+        HttpBody::Sized(reader.take(0))
+    } else if chunked {
+        HttpBody::Chunked {
+            reader,
+            remaining: 0,
+            done: false,
+        }
+    } else if let Some(length) = length {
+        HttpBody::Sized(reader.take(length))
+    } else {
+        HttpBody::UntilClose(reader)
+    };
+    Ok((status, body))
+}
+
+/// A cache entry to publish to the remote, with the handles of its output files that prevent them
+/// from being flushed away before they are sent.
+struct Publication {
+    /// The name of the remote value of the entry.
+    name: String,
+    /// The entry to publish.
+    entry: CacheEntry,
+    /// The output files of the entry.
+    files: Vec<FileStoreHandle>,
+}
+
+/// An entry to look up in the remote.
+struct Lookup {
+    /// The key of the entry.
+    key: CacheKey,
+    /// The name of the remote value of the entry.
+    name: String,
+}
+
+/// The outcome of the lookup of an entry in the remote.
+struct LookupResult {
+    /// The lookup that has been done.
+    lookup: Lookup,
+    /// The entry, if it's in the remote with all its output files.
+    entry: Option<CacheEntry>,
+    /// The handles of the output files of the entry, downloaded in the `FileStore`.
+    files: Vec<FileStoreHandle>,
+    /// Whether the lookup failed, and it should be done again later.
+    failed: bool,
+}
+
+/// The state of the remote shared between the client and the lookup threads.
+#[derive(Default)]
+struct RemoteState {
+    /// The number of consecutive failures and until when the remote is not queried because of
+    /// them.
+    backoff: Mutex<(u32, Option<Instant>)>,
+    /// The function called every time a lookup completes.
+    listener: Mutex<Option<Box<dyn Fn() + Send>>>,
+    /// Whether the client is being dropped, the lookups still queued are not done.
+    closed: AtomicBool,
+}
+
+impl RemoteState {
+    /// Whether the remote is not being queried, because of the last failures.
+    fn backing_off(&self) -> bool {
+        let (_, until) = *self.backoff.lock().unwrap();
+        until.is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Record the outcome of a query to the remote, delaying the next ones after a failure.
+    fn record(&self, success: bool) {
+        let mut backoff = self.backoff.lock().unwrap();
+        if success {
+            *backoff = (0, None);
+            return;
+        }
+        let failures = backoff.0 + 1;
+        let delay = RETRY_DELAY
+            .saturating_mul(1 << (failures - 1).min(16))
+            .min(MAX_RETRY_DELAY);
+        warn!(
+            "The remote cache is not queried for {:?} after {} failures",
+            delay, failures
+        );
+        *backoff = (failures, Some(Instant::now() + delay));
+    }
+
+    /// Tell the listener that a lookup completed.
+    fn notify(&self) {
+        if let Some(listener) = self.listener.lock().unwrap().as_ref() {
+            listener();
+        }
+    }
+}
+
+impl std::fmt::Debug for RemoteState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteState")
+            .field("backoff", &self.backoff)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The client of a `RemoteCache` used by the `Cache`. The lookups are done in background by a pool
+/// of threads, which download the output files of the entries in the `FileStore`, and the
+/// publications are sent by another thread. None of them blocks the caller.
+#[derive(Debug)]
+pub(crate) struct RemoteCacheClient {
+    /// The names of the entries that were already looked up in the remote, they are not asked
+    /// again.
+    looked_up: HashSet<String>,
+    /// The names of the entries that are being looked up.
+    pending: HashSet<String>,
+    /// The handles of the output files of the entries found in the remote, that keep them in the
+    /// `FileStore` until the entries are used.
+    found_files: HashMap<CacheKey, Vec<FileStoreHandle>>,
+    /// The state shared with the lookup threads.
+    state: Arc<RemoteState>,
+    /// The channel for sending the entries to look up to the lookup threads.
+    lookups: Option<Sender<Lookup>>,
+    /// The channel with the outcomes of the lookups.
+    results: Receiver<LookupResult>,
+    /// The handles of the lookup threads.
+    lookup_threads: Vec<JoinHandle<()>>,
+    /// The channel for sending the entries to the publisher thread.
+    publisher: Option<Sender<Publication>>,
+    /// The handle of the publisher thread.
+    publisher_thread: Option<JoinHandle<()>>,
+}
+
+impl RemoteCacheClient {
+    /// Make a new client for the remote cache, starting the lookup threads, which download the
+    /// files in `file_store`, and the publisher thread.
+    pub fn new(
+        remote: RemoteCache,
+        file_store: Arc<FileStore>,
+    ) -> Result<RemoteCacheClient, Error> {
+        let remote = Arc::new(remote);
+        let state = Arc::new(RemoteState::default());
+
+        let (lookup_sender, lookup_receiver) = channel::<Lookup>();
+        let lookup_receiver = Arc::new(Mutex::new(lookup_receiver));
+        let (result_sender, results) = channel::<LookupResult>();
+        let mut lookup_threads = Vec::with_capacity(LOOKUP_THREADS);
+        for index in 0..LOOKUP_THREADS {
+            let remote = remote.clone();
+            let file_store = file_store.clone();
+            let receiver = lookup_receiver.clone();
+            let sender = result_sender.clone();
+            let state = state.clone();
+            let thread = std::thread::Builder::new()
+                .name(format!("Remote cache lookup {}", index))
+                .spawn(move || loop {
+                    let lookup = match receiver.lock().unwrap().recv() {
+                        Ok(lookup) => lookup,
+                        Err(_) => break,
+                    };
+                    if state.closed.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let result = lookup_entry(&remote, &state, &file_store, lookup);
+                    if sender.send(result).is_err() {
+                        break;
+                    }
+                    state.notify();
+                })
+                .context("Failed to spawn the remote cache lookup thread")?;
+            lookup_threads.push(thread);
+        }
+
+        // nothing is published to the read-only remotes
+        let (publisher, publisher_thread) = if remote.is_read_only() {
+            (None, None)
+        } else {
+            let (sender, receiver) = channel::<Publication>();
+            let thread = std::thread::Builder::new()
+                .name("Remote cache publisher".into())
+                .spawn(move || {
+                    while let Ok(publication) = receiver.recv() {
+                        if let Err(e) = publish(&remote, publication) {
+                            warn!("Failed to publish to the remote cache: {:?}", e);
+                        }
+                    }
+                })
+                .context("Failed to spawn the remote cache publisher thread")?;
+            (Some(sender), Some(thread))
+        };
+        Ok(RemoteCacheClient {
+            looked_up: HashSet::new(),
+            pending: HashSet::new(),
+            found_files: HashMap::new(),
+            state,
+            lookups: Some(lookup_sender),
+            results,
+            lookup_threads,
+            publisher,
+            publisher_thread,
+        })
+    }
+
+    /// Call `listener`, from another thread, every time a lookup completes.
+    pub fn set_listener(&self, listener: Box<dyn Fn() + Send>) {
+        *self.state.listener.lock().unwrap() = Some(listener);
+    }
+
+    /// Start looking up in background the entry of the key with the limits of the group, returning
+    /// whether the lookup is in progress. Each entry is looked up only once, unless the lookup
+    /// fails, and nothing is looked up while the remote is failing.
+    pub fn lookup(&mut self, key: &CacheKey, group: &ExecutionGroup) -> bool {
+        let limits = group.executions.iter().map(|exec| &exec.limits);
+        let name = match entry_name(key, limits) {
+            Ok(name) => name,
+            Err(e) => {
+                warn!("Cannot look up the entry in the remote cache: {:?}", e);
+                return false;
+            }
+        };
+        if self.pending.contains(&name) {
+            return true;
+        }
+        if self.looked_up.contains(&name) || self.state.backing_off() {
+            return false;
+        }
+        let Some(lookups) = &self.lookups else {
+            return false;
+        };
+        let lookup = Lookup {
+            key: key.clone(),
+            name: name.clone(),
+        };
+        if lookups.send(lookup).is_err() {
+            return false;
+        }
+        self.looked_up.insert(name.clone());
+        self.pending.insert(name);
+        true
+    }
+
+    /// The entries found by the lookups completed since the last call, without blocking.
+    pub fn receive(&mut self) -> Vec<(CacheKey, CacheEntry)> {
+        let mut found = Vec::new();
+        while let Ok(result) = self.results.try_recv() {
+            self.accept(result, &mut found);
+        }
+        found
+    }
+
+    /// Like [`RemoteCacheClient::receive`], but if some lookups are in progress it blocks until at
+    /// least one of them completes.
+    pub fn wait(&mut self) -> Vec<(CacheKey, CacheEntry)> {
+        let mut found = Vec::new();
+        if !self.pending.is_empty() {
+            if let Ok(result) = self.results.recv() {
+                self.accept(result, &mut found);
+            }
+        }
+        found.extend(self.receive());
+        found
+    }
+
+    /// Record the outcome of a lookup, adding the entry to `found` if any.
+    fn accept(&mut self, result: LookupResult, found: &mut Vec<(CacheKey, CacheEntry)>) {
+        let LookupResult {
+            lookup,
+            entry,
+            files,
+            failed,
+        } = result;
+        self.pending.remove(&lookup.name);
+        if failed {
+            // it's asked again when the remote is back
+            self.looked_up.remove(&lookup.name);
+        }
+        if let Some(entry) = entry {
+            self.found_files
+                .entry(lookup.key.clone())
+                .or_default()
+                .extend(files);
+            found.push((lookup.key, entry));
+        }
+    }
+
+    /// Forget the output files of the entries of the key found in the remote, after they have
+    /// been looked up locally.
+    pub fn release(&mut self, key: &CacheKey) {
+        self.found_files.remove(key);
+    }
+
+    /// Send an entry to the remote in background.
+    pub fn publish(&self, key: CacheKey, entry: CacheEntry, files: Vec<FileStoreHandle>) {
+        let name = match entry_name(&key, entry.items.iter().map(|item| &item.limits)) {
+            Ok(name) => name,
+            Err(e) => {
+                warn!("Cannot publish the entry to the remote cache: {:?}", e);
+                return;
+            }
+        };
+        if let Some(publisher) = &self.publisher {
+            let _ = publisher.send(Publication { name, entry, files });
+        }
+    }
+}
+
+impl Drop for RemoteCacheClient {
+    fn drop(&mut self) {
+        // closing the channels makes the threads exit, the publisher after sending the pending
+        // entries
+        self.state.closed.store(true, Ordering::SeqCst);
+        self.lookups.take();
+        self.publisher.take();
+        for thread in self.lookup_threads.drain(..) {
+            if thread.join().is_err() {
+                warn!("A remote cache lookup thread panicked");
+            }
+        }
+        if let Some(thread) = self.publisher_thread.take() {
+            if thread.join().is_err() {
+                warn!("The remote cache publisher thread panicked");
+            }
+        }
+    }
+}
+
+/// The name of the remote value with the entry of the key computed with the given limits. The
+/// version of the cache is part of the name, so that different versions of task-maker do not read
+/// each other's entries.
+fn entry_name<'a>(
+    key: &CacheKey,
+    limits: impl Iterator<Item = &'a ExecutionLimits>,
+) -> Result<String, Error> {
+    let mut content = MAGIC.to_vec();
+    bincode::serialize_into(&mut content, key).context("Failed to serialize cache key")?;
+    for limits in limits {
+        bincode::serialize_into(&mut content, limits).context("Failed to serialize limits")?;
+    }
+    Ok(format!("ac/{}", FileStoreKey::from_content(&content)))
+}
+
+/// The name of the remote value with the content of a file.
+fn file_name(key: &FileStoreKey) -> String {
+    format!("cas/{}", key)
+}
+
+/// Look up an entry in the remote, unless it's failing, logging the errors.
+fn lookup_entry(
+    remote: &RemoteCache,
+    state: &RemoteState,
+    file_store: &FileStore,
+    lookup: Lookup,
+) -> LookupResult {
+    if state.backing_off() {
+        return LookupResult {
+            lookup,
+            entry: None,
+            files: vec![],
+            failed: true,
+        };
+    }
+    let result = fetch(remote, &lookup.name, file_store);
+    state.record(result.is_ok());
+    match result {
+        Ok(Some((entry, files))) => LookupResult {
+            lookup,
+            entry: Some(entry),
+            files,
+            failed: false,
+        },
+        Ok(None) => LookupResult {
+            lookup,
+            entry: None,
+            files: vec![],
+            failed: false,
+        },
+        Err(e) => {
+            warn!("Remote cache lookup failed: {:?}", e);
+            LookupResult {
+                lookup,
+                entry: None,
+                files: vec![],
+                failed: true,
+            }
+        }
+    }
+}
+
+/// Download an entry and its output files, streaming them in the file store. Returns the entry and
+/// the handles of its files, or `None` if the entry or some of its files are not in the remote.
+fn fetch(
+    remote: &RemoteCache,
+    name: &str,
+    file_store: &FileStore,
+) -> Result<Option<(CacheEntry, Vec<FileStoreHandle>)>, Error> {
+    let Some(content) = remote.get(name)? else {
+        return Ok(None);
+    };
+    let entry: CacheEntry = match bincode::deserialize(&content) {
+        Ok(entry) => entry,
+        Err(e) => {
+            warn!("Invalid entry in the remote cache: {:?}", e);
+            return Ok(None);
+        }
+    };
+    // the handles keep the downloaded files in the store while the others are downloaded
+    let mut handles = Vec::new();
+    for file in entry.output_keys() {
+        if let Some(handle) = file_store.get(file) {
+            handles.push(handle);
+            continue;
+        }
+        let Some(reader) = remote.open(&file_name(file))? else {
+            debug!("File {} is missing from the remote cache", file);
+            return Ok(None);
+        };
+        match file_store.store_from_reader(file, reader) {
+            Ok(handle) => handles.push(handle),
+            Err(e) if e.is::<ContentMismatchError>() => {
+                warn!("File {} in the remote cache is corrupted", file);
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(Some((entry, handles)))
+}
+
+/// Send the output files of an entry and then the entry itself to the remote.
+fn publish(remote: &RemoteCache, publication: Publication) -> Result<(), Error> {
+    for file in &publication.files {
+        let name = file_name(file.key());
+        if remote.contains(&name)? {
+            continue;
+        }
+        let mut content = std::fs::File::open(file.path())
+            .with_context(|| format!("Failed to open {}", file.path().display()))?;
+        let len = content
+            .metadata()
+            .with_context(|| format!("Failed to get the size of {}", file.path().display()))?
+            .len();
+        remote.put(&name, &mut content, len)?;
+    }
+    let content =
+        bincode::serialize(&publication.entry).context("Failed to serialize cache entry")?;
+    remote.put(
+        &publication.name,
+        &mut content.as_slice(),
+        content.len() as u64,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cache, CacheResult};
+    use std::collections::HashMap;
+    use task_maker_dag::{
+        Execution, ExecutionCommand, ExecutionGroup, ExecutionResult, ExecutionStatus,
+    };
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            RemoteCache::from_str("file:///mnt/cache").unwrap(),
+            RemoteCache::Directory("/mnt/cache".into())
+        );
+        assert_eq!(
+            RemoteCache::from_str("http://cache.example.com:8080/task-maker/").unwrap(),
+            RemoteCache::Http {
+                host: "cache.example.com".into(),
+                port: 8080,
+                prefix: "/task-maker".into()
+            }
+        );
+        assert_eq!(
+            RemoteCache::from_str("http://cache").unwrap(),
+            RemoteCache::Http {
+                host: "cache".into(),
+                port: 80,
+                prefix: "".into()
+            }
+        );
+        assert_eq!(
+            RemoteCache::from_str("tcp://:secret@server:1234").unwrap(),
+            RemoteCache::Server {
+                address: "server:1234".into(),
+                password: Some("secret".into())
+            }
+        );
+        assert_eq!(
+            RemoteCache::from_str("tcp://server").unwrap(),
+            RemoteCache::Server {
+                address: format!("server:{}", REMOTE_CACHE_PORT),
+                password: None
+            }
+        );
+        assert!(RemoteCache::from_str("tcp://").is_err());
+        assert!(RemoteCache::from_str("https://cache").is_err());
+        assert!(RemoteCache::from_str("cache").is_err());
+    }
+
+    #[test]
+    fn test_parse_chunked_response() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                         3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n";
+        let (status, mut body) = read_http_response(&response[..], false).unwrap();
+        assert_eq!(status, 200);
+        let mut content = Vec::new();
+        body.read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"abcde");
+    }
+
+    #[test]
+    fn test_parse_plain_response() {
+        let response = b"HTTP/1.1 404 Not Found\r\nContent-Length: 3\r\n\r\nnop";
+        let (status, mut body) = read_http_response(&response[..], false).unwrap();
+        assert_eq!(status, 404);
+        let mut content = Vec::new();
+        body.read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"nop");
+    }
+
+    #[test]
+    fn test_directory_remote() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let remote = RemoteCache::Directory(tmpdir.path().join("remote"));
+        let mut exec = Execution::new("exec", ExecutionCommand::system("true"));
+        let output = exec.output("out");
+        let group: ExecutionGroup = exec.into();
+        let result = ExecutionResult {
+            status: ExecutionStatus::Success,
+            ..Default::default()
+        };
+
+        {
+            let store = Arc::new(FileStore::new(tmpdir.path().join("store1"), 1000, 1000).unwrap());
+            let mut cache = Cache::new(tmpdir.path().join("cache1")).unwrap();
+            cache.set_remote(remote.clone(), store.clone()).unwrap();
+            let key = FileStoreKey::from_content(b"output");
+            let handle = store
+                .store(&key, std::iter::once(b"output".to_vec()))
+                .unwrap();
+            let file_keys = HashMap::from([(output.uuid, handle)]);
+            cache.insert(&group, &file_keys, vec![result]);
+            // dropping the cache waits for the publication
+        }
+
+        let store = Arc::new(FileStore::new(tmpdir.path().join("store2"), 1000, 1000).unwrap());
+        let mut cache = Cache::new(tmpdir.path().join("cache2")).unwrap();
+        cache.set_remote(remote, store.clone()).unwrap();
+        match cache.get(&group, &HashMap::new(), &store, None) {
+            CacheResult::Pending => {}
+            _ => panic!("Expecting the lookup in the remote to start"),
+        }
+        cache.wait_remote();
+        match cache.get(&group, &HashMap::new(), &store, None) {
+            CacheResult::Miss | CacheResult::Pending => panic!("Expecting a hit from the remote"),
+            CacheResult::Hit {
+                result, outputs, ..
+            } => {
+                assert_eq!(result[0].status, ExecutionStatus::Success);
+                let content = std::fs::read(outputs[&output.uuid].path()).unwrap();
+                assert_eq!(content, b"output");
+            }
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use std::io::Read;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Error};
+use ductile::{
+    connect_channel, connect_channel_with_enc, ChannelReceiver, ChannelSender, ChannelServer,
+};
+use serde::{Deserialize, Serialize};
+
+/// The default port of a [`RemoteCacheServer`].
+pub const REMOTE_CACHE_PORT: u16 = 27184;
+/// Used for deriving the encryption key of the connections from the password.
+const KEY_MATERIAL: &str = "task-maker-rust remote cache 5f0c2b1e8d7a4c3b9e6f1a2d";
+/// Size of the chunks in which the values are sent.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A request of a client of a [`RemoteCacheServer`]. Each connection carries a single request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum RemoteCacheRequest {
+    /// Check whether the value with that name is present. The server replies with
+    /// [`RemoteCacheResponse::Found`].
+    Contains(String),
+    /// Ask for the value with that name. The server replies with [`RemoteCacheResponse::Found`]
+    /// and, if it's present, sends its content with `send_raw`, terminated by an empty chunk.
+    Get(String),
+}
+
+/// The response of a [`RemoteCacheServer`] to a request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum RemoteCacheResponse {
+    /// Whether the value is present.
+    Found(bool),
+    /// The request is not valid.
+    Error(String),
+}
+
+/// A read-only remote cache served by a machine (for example the one running
+/// `task-maker-tools server`) to its clients, which use it with [`RemoteCache::Server`].
+///
+/// The values are read from a directory with the same layout of a [`RemoteCache::Directory`],
+/// which is filled only by the machine serving it, so the clients can never alter each other's
+/// results. The connections use the same channels of the clients and the workers of the server,
+/// encrypted when a password is set.
+///
+/// [`RemoteCache::Server`]: crate::RemoteCache::Server
+/// [`RemoteCache::Directory`]: crate::RemoteCache::Directory
+pub struct RemoteCacheServer {
+    /// The directory with the values.
+    dir: PathBuf,
+    /// The server accepting the connections of the clients.
+    server: ChannelServer<RemoteCacheResponse, RemoteCacheRequest>,
+}
+
+impl RemoteCacheServer {
+    /// Bind a server to the specified address, serving the values in the directory. When a
+    /// password is provided the clients must know it for connecting.
+    pub fn bind<P: Into<PathBuf>>(
+        dir: P,
+        addr: &str,
+        password: Option<&str>,
+    ) -> Result<RemoteCacheServer, Error> {
+        let server = match password {
+            Some(password) => ChannelServer::bind_with_enc(addr, derive_key(password)),
+            None => ChannelServer::bind(addr),
+        }
+        .with_context(|| format!("Failed to bind the remote cache server to {}", addr))?;
+        Ok(RemoteCacheServer {
+            dir: dir.into(),
+            server,
+        })
+    }
+
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> Result<Option<SocketAddr>, Error> {
+        self.server
+            .local_addr()
+            .context("Failed to get the address of the remote cache server")
+    }
+
+    /// Serve the connections of the clients, each one in its own thread. This never returns,
+    /// unless the thread of a connection cannot be spawned.
+    pub fn serve(self) -> Result<(), Error> {
+        info!("Serving the remote cache in {}", self.dir.display());
+        for (sender, receiver, addr) in self.server {
+            let dir = self.dir.clone();
+            std::thread::Builder::new()
+                .name("Remote cache connection".into())
+                .spawn(move || {
+                    if let Err(e) = handle_request(&dir, sender, receiver) {
+                        warn!("Remote cache request from {:?} failed: {:?}", addr, e);
+                    }
+                })
+                .context("Failed to spawn the remote cache connection thread")?;
+        }
+        Ok(())
+    }
+}
+
+/// Serve the request of a connection, reading the values from `dir`.
+fn handle_request(
+    dir: &std::path::Path,
+    sender: ChannelSender<RemoteCacheResponse>,
+    receiver: ChannelReceiver<RemoteCacheRequest>,
+) -> Result<(), Error> {
+    let request = receiver.recv().context("Failed to receive the request")?;
+    debug!("Remote cache request: {:?}", request);
+    let (RemoteCacheRequest::Contains(name) | RemoteCacheRequest::Get(name)) = &request;
+    if !is_valid_name(name) {
+        sender.send(RemoteCacheResponse::Error(format!(
+            "Invalid name: {}",
+            name
+        )))?;
+        return Ok(());
+    }
+    let path = dir.join(name);
+    let mut file = match (&request, std::fs::File::open(&path)) {
+        (RemoteCacheRequest::Contains(_), file) => {
+            return sender.send(RemoteCacheResponse::Found(file.is_ok()));
+        }
+        (_, Ok(file)) => file,
+        (_, Err(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            return sender.send(RemoteCacheResponse::Found(false));
+        }
+        (_, Err(e)) => {
+            sender.send(RemoteCacheResponse::Error(
+                "Failed to read the value".into(),
+            ))?;
+            return Err(e).with_context(|| format!("Failed to open {}", path.display()));
+        }
+    };
+    sender.send(RemoteCacheResponse::Found(true))?;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let len = file
+            .read(&mut buffer)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        sender.send_raw(&buffer[..len])?;
+        if len == 0 {
+            return Ok(());
+        }
+    }
+}
+
+/// Whether the name of a value is valid: the hexadecimal key of an entry in `ac/` or of a file in
+/// `cas/`. The valid names never escape the directory.
+fn is_valid_name(name: &str) -> bool {
+    let Some((namespace, key)) = name.split_once('/') else {
+        return false;
+    };
+    let valid_key = !key.is_empty() && key.chars().all(|c| c.is_ascii_hexdigit());
+    matches!(namespace, "ac" | "cas") && valid_key
+}
+
+/// Derive the encryption key of the connections from the password.
+fn derive_key(password: &str) -> [u8; 32] {
+    blake3::derive_key(KEY_MATERIAL, password.as_bytes())
+}
+
+/// Connect to a remote cache server and send it the request, returning whether the value is
+/// present together with the channel the value can be received from.
+fn send_request(
+    address: &str,
+    password: Option<&str>,
+    request: RemoteCacheRequest,
+) -> Result<(bool, ServerValue), Error> {
+    let addr = address
+        .to_socket_addrs()
+        .with_context(|| format!("Failed to resolve {}", address))?
+        .next()
+        .ok_or_else(|| anyhow!("No address found for {}", address))?;
+    let (sender, receiver) = match password {
+        Some(password) => connect_channel_with_enc(addr, &derive_key(password)),
+        None => connect_channel(addr),
+    }
+    .with_context(|| format!("Failed to connect to the remote cache at {}", address))?;
+    sender
+        .send(request)
+        .context("Failed to send the request to the remote cache")?;
+    let response = receiver
+        .recv()
+        .context("Failed to receive the response of the remote cache")?;
+    let value = ServerValue {
+        _sender: sender,
+        receiver,
+        chunk: vec![],
+        position: 0,
+        done: false,
+    };
+    match response {
+        RemoteCacheResponse::Found(found) => Ok((found, value)),
+        RemoteCacheResponse::Error(e) => bail!("The remote cache server failed: {}", e),
+    }
+}
+
+/// Check whether the value with the specified name is in the remote cache server.
+pub(crate) fn server_contains(
+    address: &str,
+    password: Option<&str>,
+    name: &str,
+) -> Result<bool, Error> {
+    let request = RemoteCacheRequest::Contains(name.into());
+    let (found, _) = send_request(address, password, request)?;
+    Ok(found)
+}
+
+/// Open the value with the specified name in the remote cache server, returning `None` if it's not
+/// there.
+pub(crate) fn server_open(
+    address: &str,
+    password: Option<&str>,
+    name: &str,
+) -> Result<Option<ServerValue>, Error> {
+    let request = RemoteCacheRequest::Get(name.into());
+    let (found, value) = send_request(address, password, request)?;
+    Ok(found.then_some(value))
+}
+
+/// The content of a value sent by a [`RemoteCacheServer`], received in chunks while it's read.
+pub(crate) struct ServerValue {
+    /// Keeps the connection open while the value is received.
+    _sender: ChannelSender<RemoteCacheRequest>,
+    /// The channel the chunks are received from.
+    receiver: ChannelReceiver<RemoteCacheResponse>,
+    /// The last chunk received.
+    chunk: Vec<u8>,
+    /// How much of the last chunk has been read.
+    position: usize,
+    /// Whether the last chunk has been received.
+    done: bool,
+}
+
+impl Read for ServerValue {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.chunk.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.chunk = self
+                .receiver
+                .recv_raw()
+                .map_err(|e| std::io::Error::other(format!("{:?}", e)))?;
+            self.position = 0;
+            self.done = self.chunk.is_empty();
+        }
+        let len = buf.len().min(self.chunk.len() - self.position);
+        buf[..len].copy_from_slice(&self.chunk[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RemoteCache;
+
+    /// Start a server on a random port of localhost, returning its address.
+    fn start_server(dir: PathBuf, password: Option<&'static str>) -> String {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let server = RemoteCacheServer::bind(dir, "127.0.0.1:0", password).unwrap();
+            sender.send(server.local_addr().unwrap().unwrap()).unwrap();
+            server.serve()
+        });
+        receiver.recv().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_valid_name() {
+        assert!(is_valid_name("ac/0123abcdef"));
+        assert!(is_valid_name("cas/0123abcdef"));
+        assert!(!is_valid_name("cas/"));
+        assert!(!is_valid_name("foo/0123"));
+        assert!(!is_valid_name("cas/../../etc/passwd"));
+        assert!(!is_valid_name("/cas/0123"));
+    }
+
+    #[test]
+    fn test_server_remote() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let local = RemoteCache::Directory(tmpdir.path().into());
+        let content = vec![42; CHUNK_SIZE * 2 + 10];
+        local
+            .put("cas/abcd", &mut content.as_slice(), content.len() as u64)
+            .unwrap();
+
+        let address = start_server(tmpdir.path().into(), None);
+        let remote = RemoteCache::Server {
+            address,
+            password: None,
+        };
+        assert!(remote.contains("cas/abcd").unwrap());
+        assert_eq!(remote.get("cas/abcd").unwrap().unwrap(), content);
+        assert!(!remote.contains("ac/1234").unwrap());
+        assert!(remote.get("ac/1234").unwrap().is_none());
+        assert!(remote.get("../secret").is_err());
+    }
+
+    #[test]
+    fn test_server_remote_read_only() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let address = start_server(tmpdir.path().into(), Some("secret"));
+        let remote = RemoteCache::Server {
+            address,
+            password: Some("secret".into()),
+        };
+        assert!(remote.is_read_only());
+        assert!(remote.put("ac/1234", &mut &b"entry"[..], 5).is_err());
+        assert!(!remote.contains("ac/1234").unwrap());
+        assert!(!tmpdir.path().join("ac/1234").exists());
+    }
+}
@@ -1,38 +1,50 @@
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::hash::Hash;
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::PathBuf;
 
 use anyhow::{Context, Error};
 use const_format::formatcp;
 use itertools::Itertools;
-use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 use crate::entry::CacheEntry;
 use crate::key::CacheKey;
 
+/// Version of the serialization format of the cache entries. It has to be bumped every time
+/// `CacheEntry` changes, so that the caches written with the old format are discarded instead of
+/// being misread.
+pub(crate) const CACHE_FORMAT_VERSION: u32 = 2;
+
 /// Magic string that is prepended to the cache file to avoid accidental loading of invalid cache
 /// files.
 ///
 /// The newline at the end of the string is required. For example, let's say there are 2 versions:
 /// v0.1 and v0.11; running v0.11 first, and then v0.1, without the newline the magic of the old
 /// version is a prefix of the magic of the new version.
-const MAGIC: &[u8] = formatcp!("task-maker-cache v{}\n", env!("CARGO_PKG_VERSION")).as_bytes();
-
-/// A cache file.
-#[derive(Debug, Serialize, Deserialize)]
-pub(crate) struct CacheFile {
+pub(crate) const MAGIC: &[u8] = formatcp!(
+    "task-maker-cache v{} format {}\n",
+    env!("CARGO_PKG_VERSION"),
+    CACHE_FORMAT_VERSION
+)
+.as_bytes();
+
+/// A cache file, with the entries indexed by keys of type `K`.
+#[derive(Debug)]
+pub(crate) struct CacheFile<K = CacheKey> {
     /// The set of entries in this cache file.
-    entries: HashMap<CacheKey, Vec<CacheEntry>>,
+    entries: HashMap<K, Vec<CacheEntry>>,
     /// Where this file is stored.
     path: PathBuf,
     /// Whether this file should be flushed.
     dirty: bool,
 }
 
-impl CacheFile {
+impl<K: Eq + Hash + Serialize + DeserializeOwned> CacheFile<K> {
     /// Read the cache file, check the magic string and deserialize all the entries in it.
-    pub fn load(path: PathBuf) -> Result<CacheFile, Error> {
+    pub fn load(path: PathBuf) -> Result<CacheFile<K>, Error> {
         if !path.exists() {
             return Ok(Self {
                 entries: Default::default(),
@@ -61,7 +73,7 @@ impl CacheFile {
             });
         }
 
-        let entries = bincode::deserialize_from::<_, HashMap<CacheKey, Vec<CacheEntry>>>(reader)
+        let entries = bincode::deserialize_from::<_, HashMap<K, Vec<CacheEntry>>>(reader)
             .context("Failed to deserialize cache content")?;
 
         Ok(Self {
@@ -72,7 +84,7 @@ impl CacheFile {
     }
 
     /// Store the content of the cache to the cache file, including the magic string.
-    pub fn store(&self) -> Result<(), Error> {
+    pub fn store(&mut self) -> Result<(), Error> {
         // Do not write the file if it's not dirty.
         if !self.dirty {
             return Ok(());
@@ -98,16 +110,38 @@ impl CacheFile {
                 self.path.display()
             )
         })?;
+        self.dirty = false;
         Ok(())
     }
 
-    pub fn entry(&mut self, key: CacheKey) -> Entry<CacheKey, Vec<CacheEntry>> {
+    pub fn entry(&mut self, key: K) -> Entry<K, Vec<CacheEntry>> {
         self.entries.entry(key)
     }
 
     pub fn mark_dirty(&mut self) {
         self.dirty = true;
     }
+
+    /// Iterate over all the keys and their entries.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &Vec<CacheEntry>)> {
+        self.entries.iter()
+    }
+
+    /// Keep only the entries for which `keep` returns true, removing the keys left without
+    /// entries. Returns the number of removed entries.
+    pub fn retain<F: FnMut(&K, &CacheEntry) -> bool>(&mut self, mut keep: F) -> usize {
+        let mut removed = 0;
+        self.entries.retain(|key, entries| {
+            let before = entries.len();
+            entries.retain(|entry| keep(key, entry));
+            removed += before - entries.len();
+            !entries.is_empty()
+        });
+        if removed > 0 {
+            self.dirty = true;
+        }
+        removed
+    }
 }
 
 #[cfg(test)]
@@ -122,7 +156,7 @@ mod tests {
         let mut f = File::create(&path).unwrap();
         f.write_all(b"totally-not-the-magic").unwrap();
 
-        assert!(CacheFile::load(path).is_err());
+        assert!(CacheFile::<CacheKey>::load(path).is_err());
     }
 
     #[test]
@@ -133,6 +167,6 @@ mod tests {
         f.write_all(MAGIC).unwrap();
         f.write_all(b"wrong-version").unwrap();
 
-        assert!(CacheFile::load(path).is_err());
+        assert!(CacheFile::<CacheKey>::load(path).is_err());
     }
 }
@@ -1,11 +1,14 @@
-use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::{bail, Context, Error};
 use serde::{Deserialize, Serialize};
 
 use task_maker_store::*;
 
+use crate::directory::archive_directory;
 use crate::file::*;
 use crate::*;
 
@@ -20,6 +23,32 @@ pub enum CacheMode {
     Except(HashSet<ExecutionTag>),
 }
 
+/// How the time limits are adapted to the speed of the worker running the execution. The workers
+/// measure their speed relative to a reference machine at startup; on the scaled workers the time
+/// limits are divided by the speed factor, and the measured times multiplied by it, so that the
+/// results are the ones of the reference machine.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum TimeScaling {
+    /// The time limits are never scaled.
+    #[default]
+    None,
+    /// The time limits are scaled only on the workers slower than the reference machine.
+    Slower,
+    /// The time limits are scaled on all the workers, reducing them on the faster ones.
+    All,
+}
+
+/// After how much time the cache entries are considered stale. A stale entry is still used, but
+/// its executions are run again in background to refresh it.
+#[derive(PartialEq, Eq, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheTTL {
+    /// The TTL of the entries of the executions without a more specific one. `None` means that the
+    /// entries never become stale.
+    pub default: Option<Duration>,
+    /// The TTL of the entries of the executions with a tag, indexed by the name of the tag.
+    pub tags: HashMap<String, Duration>,
+}
+
 /// Configuration setting of an `ExecutionDAG`, some of the values set here will be inherited in the
 /// configuration of the executions added.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +61,8 @@ pub struct ExecutionDAGConfig {
     pub dry_run: bool,
     /// The cache mode for this DAG.
     pub cache_mode: CacheMode,
+    /// After how much time the cache entries of this DAG are considered stale.
+    pub cache_ttl: CacheTTL,
     /// Extra time to give to the sandbox before killing the process, in seconds.
     pub extra_time: f64,
     /// Extra memory to give to the sandbox before killing the process, in KiB.
@@ -42,6 +73,44 @@ pub struct ExecutionDAGConfig {
     pub copy_logs: bool,
     /// Priority of this DAG.
     pub priority: DagPriority,
+    /// Whether the detailed resource usage of the executions (context switches, page faults,
+    /// memory samples) is sampled while they run and shown by the UIs.
+    pub verbose_resources: bool,
+    /// Maximum total size, in bytes, of the files produced by the executions of this DAG. When
+    /// exceeded the evaluation is aborted.
+    pub max_total_output_size: Option<u64>,
+    /// Maximum number of executions to send to a worker in a single batch. Only the groups with a
+    /// single execution, without FIFOs and with the same limits and tag are batched together.
+    /// `None` disables the batching.
+    pub max_batch_size: Option<usize>,
+    /// How the time limits are adapted to the speed of the workers.
+    pub time_scaling: TimeScaling,
+    /// Whether to ask the workers for the logs of the sandboxes of the executions that failed with
+    /// an internal error. The logs are added to their results.
+    pub fetch_sandbox_logs: bool,
+    /// Run a single execution group at a time, in a stable order, so that the results are sent to
+    /// the client in the same order on every machine.
+    pub deterministic: bool,
+    /// Whether the workers should stream the standard error of the running executions, so that the
+    /// UIs can show it while they run.
+    pub stream_stderr: bool,
+    /// The pool of workers of the server that evaluates this DAG, `None` for the default one.
+    pub pool: Option<String>,
+    /// Whether to take the outputs of the compilations from the artifact cache, independently of
+    /// the cache mode.
+    pub artifact_cache: bool,
+    /// Whether to store in the `FileStore` the full standard output and standard error of the
+    /// executions that capture them, beyond the capture limits.
+    pub keep_full_output: bool,
+    /// File where to write the scheduling trace of the evaluation, in the Chrome tracing format,
+    /// if any. The trace is recorded by the servers and written by the client.
+    pub trace: Option<PathBuf>,
+    /// Environment variables to set in all the executions of this DAG, overriding the ones set by
+    /// the executions themselves.
+    pub env: BTreeMap<String, String>,
+    /// The OCI container image in which the executions of this DAG are run, except the builtin
+    /// commands and the executions with their own image.
+    pub container_image: Option<String>,
 }
 
 /// A wrapper around a `File` provided by the client, this means that the client knows the
@@ -91,6 +160,59 @@ pub struct ExecutionDAGCallbacks {
     /// others will be sent at the end of the evaluation. Note that sending big files during the
     /// evaluation can cause performance degradations.
     pub urgent_files: HashSet<FileUuid>,
+    /// The callbacks of the executions with a given tag.
+    pub tag_callbacks: HashMap<ExecutionTag, TaggedExecutionCallbacks>,
+    /// The executions the callbacks asked to skip, not yet sent to the server.
+    pub skipper: ExecutionSkipper,
+    /// The temporary files provided for the computation (i.e. the archives of the directories),
+    /// deleted together with the DAG.
+    pub temp_files: Vec<tempfile::TempPath>,
+}
+
+/// A handle for asking, from the callbacks, to skip some executions that are not needed anymore.
+/// The executions that haven't started yet are skipped together with all the executions that
+/// depend on them, like when one of their dependencies fails.
+///
+/// ```
+/// use task_maker_dag::{Execution, ExecutionCommand, ExecutionDAG};
+///
+/// let mut dag = ExecutionDAG::new();
+/// let exec = Execution::new("exec", ExecutionCommand::local("foo"));
+/// let skipper = dag.skipper();
+/// skipper.skip(exec.uuid);
+/// assert_eq!(dag.skipper().take(), vec![exec.uuid]);
+/// assert!(skipper.take().is_empty());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionSkipper {
+    /// The executions to skip.
+    requests: Arc<Mutex<Vec<ExecutionUuid>>>,
+}
+
+impl ExecutionSkipper {
+    /// Ask to skip an execution, if it hasn't started yet.
+    pub fn skip(&self, execution: ExecutionUuid) {
+        self.requests.lock().unwrap().push(execution);
+    }
+
+    /// Take the executions asked to skip since the last call.
+    pub fn take(&self) -> Vec<ExecutionUuid> {
+        std::mem::take(&mut self.requests.lock().unwrap())
+    }
+}
+
+/// A part of an `ExecutionDAGData` that can be evaluated independently from the other parts,
+/// obtained with [`ExecutionDAGData::split`].
+#[derive(Debug, Clone)]
+pub struct ExecutionDAGPart {
+    /// The executions and the files of this part.
+    pub data: ExecutionDAGData,
+    /// The executions whose callbacks should be handled by this part. An execution can be present
+    /// in more parts, but it's owned by only one of them.
+    pub executions: HashSet<ExecutionUuid>,
+    /// The files whose callbacks should be handled by this part. A file can be present in more
+    /// parts, but it's owned by only one of them.
+    pub files: HashSet<FileUuid>,
 }
 
 /// A computation DAG, this is not serializable because it contains the callbacks of the client.
@@ -118,6 +240,9 @@ impl ExecutionDAG {
                 execution_callbacks: HashMap::new(),
                 file_callbacks: HashMap::new(),
                 urgent_files: HashSet::new(),
+                tag_callbacks: HashMap::new(),
+                skipper: Default::default(),
+                temp_files: Vec::new(),
             }),
         }
     }
@@ -149,9 +274,34 @@ impl ExecutionDAG {
         );
     }
 
+    /// Provide a whole directory tree for the computation, packed in a single file that can be
+    /// given to the executions with
+    /// [`Execution::input_directory`](struct.Execution.html#method.input_directory).
+    ///
+    /// The archive is written to a temporary file, which lives as long as this DAG, and it depends
+    /// only on the names, on the content and on the executable bit of the files, so it hits the
+    /// cache as long as they don't change. Symlinks are followed.
+    pub fn provide_directory<P: AsRef<Path>>(&mut self, file: File, dir: P) -> Result<(), Error> {
+        let dir = dir.as_ref();
+        let mut archive =
+            tempfile::NamedTempFile::new().context("Failed to create the archive file")?;
+        archive_directory(dir, archive.as_file_mut())
+            .with_context(|| format!("Failed to archive directory {}", dir.display()))?;
+        let archive = archive.into_temp_path();
+        self.provide_file(file, archive.to_path_buf())?;
+        self.callbacks
+            .as_mut()
+            .expect("Cannot provide a directory to a cloned DAG")
+            .temp_files
+            .push(archive);
+        Ok(())
+    }
+
     /// Add an execution to the DAG.
     pub fn add_execution(&mut self, mut execution: Execution) {
         execution.config = self.data.config.clone();
+        execution.resolve_copy_env();
+        self.keep_full_output(&mut execution);
         let mut group = ExecutionGroup::new(execution.description.clone());
         group.add_execution(execution);
         self.data.execution_groups.insert(group.uuid, group);
@@ -161,10 +311,27 @@ impl ExecutionDAG {
     pub fn add_execution_group(&mut self, mut group: ExecutionGroup) {
         for exec in group.executions.iter_mut() {
             exec.config = self.data.config.clone();
+            exec.resolve_copy_env();
+            self.keep_full_output(exec);
         }
         self.data.execution_groups.insert(group.uuid, group);
     }
 
+    /// When the full output of the executions is kept, make the standard output and standard error
+    /// that are captured also output files of the execution, so that they are stored in the
+    /// `FileStore`.
+    fn keep_full_output(&self, execution: &mut Execution) {
+        if !self.data.config.keep_full_output {
+            return;
+        }
+        if execution.capture_stdout.is_some() {
+            execution.stdout();
+        }
+        if execution.capture_stderr.is_some() {
+            execution.stderr();
+        }
+    }
+
     /// When `file` is ready it will be written to `path`. The file must be present in the dag
     /// before the evaluation starts.
     ///
@@ -203,6 +370,17 @@ impl ExecutionDAG {
         }
     }
 
+    /// Write the file also to `path`, in addition to the destination set with `write_file_to`. A
+    /// file can be copied to more than one path, and it's copied even if the generation of the
+    /// file failed.
+    ///
+    /// If the config `dry_run` is set to true the calls to this function are no-op.
+    pub fn copy_file_to<F: Into<FileUuid>, P: Into<PathBuf>>(&mut self, file: F, path: P) {
+        if !self.data.config.dry_run {
+            self.file_callback(file.into()).copy_to.push(path.into());
+        }
+    }
+
     /// Call `callback` with the first `limit` bytes of the file when it's ready. The file must be
     /// present in the DAG before the evaluation starts.
     ///
@@ -272,6 +450,26 @@ impl ExecutionDAG {
             .push(Box::new(callback));
     }
 
+    /// Add a callback that will be called when any execution with the specified tag ends, before
+    /// the callbacks of that execution. The callback receives the execution and its result, and can
+    /// add some custom metrics to the result.
+    ///
+    /// This is useful for collecting metrics from a whole class of executions, for example parsing
+    /// the number of queries made by a solution from the standard error of the interactor.
+    pub fn on_tagged_execution_done<F>(&mut self, tag: ExecutionTag, callback: F)
+    where
+        F: (FnMut(&Execution, &mut ExecutionResult) -> Result<(), Error>) + 'static,
+    {
+        self.callbacks
+            .as_mut()
+            .expect("Cannot change callbacks after cloning")
+            .tag_callbacks
+            .entry(tag)
+            .or_default()
+            .on_done
+            .push(Box::new(callback));
+    }
+
     /// Get a mutable reference to the config of this DAG.
     pub fn config_mut(&mut self) -> &mut ExecutionDAGConfig {
         &mut self.data.config
@@ -292,6 +490,24 @@ impl ExecutionDAG {
         &mut self.callbacks.as_mut().unwrap().file_callbacks
     }
 
+    /// Get the paths where the files of the DAG will be written on the client, sorted.
+    pub fn written_paths(&self) -> Vec<PathBuf> {
+        let Some(callbacks) = &self.callbacks else {
+            return vec![];
+        };
+        let mut paths: Vec<_> = callbacks
+            .file_callbacks
+            .values()
+            .flat_map(|callbacks| {
+                let write_to = callbacks.write_to.iter().map(|write| write.dest.clone());
+                write_to.chain(callbacks.copy_to.iter().cloned())
+            })
+            .collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
     /// Makes sure that a callback item exists for that execution and returns a &mut to it.
     fn execution_callback(&mut self, execution: &ExecutionUuid) -> &mut ExecutionCallbacks {
         self.callbacks
@@ -320,6 +536,130 @@ impl ExecutionDAG {
     pub fn urgent_files(&mut self) -> &mut HashSet<FileUuid> {
         &mut self.callbacks.as_mut().unwrap().urgent_files
     }
+
+    /// A handle for skipping the executions of this DAG from its callbacks.
+    pub fn skipper(&self) -> ExecutionSkipper {
+        self.callbacks.as_ref().unwrap().skipper.clone()
+    }
+}
+
+impl ExecutionDAGData {
+    /// Split the DAG in at most `num_parts` parts that can be evaluated independently.
+    ///
+    /// The execution groups whose outputs are not used by any other group (i.e. the last step of
+    /// each chain, like the checker of a solution on a testcase) are assigned to the parts in a
+    /// round-robin fashion, sorted by description. Each part contains also all the groups needed
+    /// for producing the inputs of its groups, so the groups shared by more chains (like the
+    /// compilation of the checker) are present in more than one part. Each group and each file is
+    /// owned by exactly one part, the only one that should report its callbacks.
+    pub fn split(&self, num_parts: usize) -> Vec<ExecutionDAGPart> {
+        let mut producers = HashMap::new();
+        for (uuid, group) in &self.execution_groups {
+            for exec in &group.executions {
+                for output in exec.outputs() {
+                    producers.insert(output, *uuid);
+                }
+            }
+        }
+        let mut dependencies: HashMap<ExecutionGroupUuid, Vec<ExecutionGroupUuid>> = HashMap::new();
+        let mut used = HashSet::new();
+        for (uuid, group) in &self.execution_groups {
+            for exec in &group.executions {
+                for input in exec.dependencies() {
+                    if let Some(producer) = producers.get(&input) {
+                        if producer != uuid {
+                            dependencies.entry(*uuid).or_default().push(*producer);
+                            used.insert(*producer);
+                        }
+                    }
+                }
+            }
+        }
+        let mut sinks: Vec<_> = self
+            .execution_groups
+            .values()
+            .filter(|group| !used.contains(&group.uuid))
+            .collect();
+        sinks.sort_by(|a, b| (&a.description, a.uuid).cmp(&(&b.description, b.uuid)));
+
+        let num_parts = num_parts.min(sinks.len()).max(1);
+        let mut members = vec![HashSet::new(); num_parts];
+        for (index, sink) in sinks.iter().enumerate() {
+            let members = &mut members[index % num_parts];
+            let mut stack = vec![sink.uuid];
+            while let Some(group) = stack.pop() {
+                if members.insert(group) {
+                    stack.extend(dependencies.get(&group).into_iter().flatten());
+                }
+            }
+        }
+
+        // a group is owned by the first part containing it
+        let mut group_owners = HashMap::new();
+        for (part, members) in members.iter().enumerate() {
+            for group in members {
+                group_owners.entry(*group).or_insert(part);
+            }
+        }
+        let inputs = |part: usize| -> HashSet<FileUuid> {
+            members[part]
+                .iter()
+                .flat_map(|group| &self.execution_groups[group].executions)
+                .flat_map(|exec| exec.dependencies())
+                .collect()
+        };
+        let inputs: Vec<_> = (0..num_parts).map(inputs).collect();
+        // the provided files not used by any execution are owned by the first part
+        let file_owner = |file: &FileUuid| -> usize {
+            (0..num_parts)
+                .find(|part| inputs[*part].contains(file))
+                .unwrap_or(0)
+        };
+
+        (0..num_parts)
+            .map(|part| {
+                let execution_groups: HashMap<_, _> = members[part]
+                    .iter()
+                    .map(|group| (*group, self.execution_groups[group].clone()))
+                    .collect();
+                let provided_files: HashMap<_, _> = self
+                    .provided_files
+                    .iter()
+                    .filter(|(uuid, _)| inputs[part].contains(uuid) || file_owner(uuid) == part)
+                    .map(|(uuid, file)| (*uuid, file.clone()))
+                    .collect();
+                let owned_groups: Vec<_> = execution_groups
+                    .values()
+                    .filter(|group| group_owners[&group.uuid] == part)
+                    .collect();
+                let executions = owned_groups
+                    .iter()
+                    .flat_map(|group| &group.executions)
+                    .map(|exec| exec.uuid)
+                    .collect();
+                let files = owned_groups
+                    .iter()
+                    .flat_map(|group| &group.executions)
+                    .flat_map(|exec| exec.outputs())
+                    .chain(
+                        provided_files
+                            .keys()
+                            .filter(|uuid| file_owner(uuid) == part)
+                            .cloned(),
+                    )
+                    .collect();
+                ExecutionDAGPart {
+                    data: ExecutionDAGData {
+                        provided_files,
+                        execution_groups,
+                        config: self.config.clone(),
+                    },
+                    executions,
+                    files,
+                }
+            })
+            .collect()
+    }
 }
 
 impl Clone for ExecutionDAG {
@@ -340,11 +680,25 @@ impl ExecutionDAGConfig {
             keep_sandboxes: false,
             dry_run: false,
             cache_mode: CacheMode::Everything,
+            cache_ttl: CacheTTL::default(),
             extra_time: 0.5,
             extra_memory: 8 * 1024, // 8 MiB
             copy_exe: false,
             copy_logs: false,
             priority: 0,
+            verbose_resources: false,
+            max_total_output_size: None,
+            max_batch_size: None,
+            time_scaling: TimeScaling::None,
+            fetch_sandbox_logs: false,
+            deterministic: false,
+            stream_stderr: false,
+            pool: None,
+            artifact_cache: true,
+            keep_full_output: false,
+            trace: None,
+            env: BTreeMap::new(),
+            container_image: None,
         }
     }
 
@@ -366,6 +720,12 @@ impl ExecutionDAGConfig {
         self
     }
 
+    /// Set after how much time the cache entries of the executions of this DAG are stale.
+    pub fn cache_ttl(&mut self, cache_ttl: CacheTTL) -> &mut Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
     /// Set the extra time to give to the executions before being killed by the sandbox.
     pub fn extra_time(&mut self, extra_time: f64) -> &mut Self {
         assert!(extra_time >= 0.0);
@@ -396,6 +756,85 @@ impl ExecutionDAGConfig {
         self.priority = priority;
         self
     }
+
+    /// Set whether the detailed resource usage of the executions is sampled and shown by the UIs.
+    pub fn verbose_resources(&mut self, verbose_resources: bool) -> &mut Self {
+        self.verbose_resources = verbose_resources;
+        self
+    }
+
+    /// Set the maximum total size, in bytes, of the files produced by the executions.
+    pub fn max_total_output_size(&mut self, max_total_output_size: Option<u64>) -> &mut Self {
+        self.max_total_output_size = max_total_output_size;
+        self
+    }
+
+    /// Set the maximum number of executions to send to a worker in a single batch.
+    pub fn max_batch_size(&mut self, max_batch_size: Option<usize>) -> &mut Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    /// Set how the time limits are adapted to the speed of the workers.
+    pub fn time_scaling(&mut self, time_scaling: TimeScaling) -> &mut Self {
+        self.time_scaling = time_scaling;
+        self
+    }
+
+    /// Whether to fetch the logs of the sandboxes of the executions that failed with an internal
+    /// error.
+    pub fn fetch_sandbox_logs(&mut self, fetch_sandbox_logs: bool) -> &mut Self {
+        self.fetch_sandbox_logs = fetch_sandbox_logs;
+        self
+    }
+
+    /// Set whether to run the executions one at a time, in a stable order.
+    pub fn deterministic(&mut self, deterministic: bool) -> &mut Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Set whether the workers should stream the standard error of the running executions.
+    pub fn stream_stderr(&mut self, stream_stderr: bool) -> &mut Self {
+        self.stream_stderr = stream_stderr;
+        self
+    }
+
+    /// Set the pool of workers that evaluates this DAG.
+    pub fn pool(&mut self, pool: Option<String>) -> &mut Self {
+        self.pool = pool;
+        self
+    }
+
+    /// Set whether to store the full standard output and standard error of the executions.
+    pub fn keep_full_output(&mut self, keep_full_output: bool) -> &mut Self {
+        self.keep_full_output = keep_full_output;
+        self
+    }
+
+    /// Set whether to take the outputs of the compilations from the artifact cache.
+    pub fn artifact_cache(&mut self, artifact_cache: bool) -> &mut Self {
+        self.artifact_cache = artifact_cache;
+        self
+    }
+
+    /// Set the file where to write the scheduling trace of the evaluation.
+    pub fn trace(&mut self, trace: Option<PathBuf>) -> &mut Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Set the environment variables to set in all the executions.
+    pub fn env(&mut self, env: BTreeMap<String, String>) -> &mut Self {
+        self.env = env;
+        self
+    }
+
+    /// Set the container image in which the executions are run.
+    pub fn container_image(&mut self, container_image: Option<String>) -> &mut Self {
+        self.container_image = container_image;
+        self
+    }
 }
 
 impl Default for ExecutionDAGConfig {
@@ -440,6 +879,70 @@ impl CacheMode {
     }
 }
 
+impl TimeScaling {
+    /// The speed factor to use for the executions on a worker with the measured speed factor, if
+    /// known. `1.0` means that the time limits are not scaled.
+    pub fn speed_factor(self, measured: Option<f64>) -> f64 {
+        match (self, measured) {
+            (TimeScaling::Slower, Some(measured)) => measured.min(1.0),
+            (TimeScaling::All, Some(measured)) => measured,
+            _ => 1.0,
+        }
+    }
+}
+
+impl std::str::FromStr for TimeScaling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TimeScaling, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(TimeScaling::None),
+            "slower" => Ok(TimeScaling::Slower),
+            "all" => Ok(TimeScaling::All),
+            _ => Err(format!("Unknown time scaling: {}", s)),
+        }
+    }
+}
+
+impl CacheTTL {
+    /// Make a `CacheTTL` from the command line arguments, a list of `[TAG=]SECONDS`. The values
+    /// without the tag set the TTL of the executions without a more specific one.
+    pub fn try_from(conf: &[String], valid_tags: &[String]) -> Result<CacheTTL, Error> {
+        let mut ttl = CacheTTL::default();
+        for item in conf {
+            let (tag, seconds) = match item.split_once('=') {
+                Some((tag, seconds)) => (Some(tag), seconds),
+                None => (None, item.as_str()),
+            };
+            let seconds: u64 = seconds
+                .parse()
+                .with_context(|| format!("Invalid cache TTL: {}", item))?;
+            let duration = Duration::from_secs(seconds);
+            match tag {
+                Some(tag) => {
+                    if !valid_tags.iter().any(|t| t == tag) {
+                        bail!(
+                            "Invalid cache TTL tag: {} (valid are: {})",
+                            tag,
+                            valid_tags.join(", ")
+                        );
+                    }
+                    ttl.tags.insert(tag.to_string(), duration);
+                }
+                None => ttl.default = Some(duration),
+            }
+        }
+        Ok(ttl)
+    }
+
+    /// The TTL of the cache entries of the executions with the provided tag, if any.
+    pub fn ttl(&self, tag: Option<&ExecutionTag>) -> Option<Duration> {
+        tag.and_then(|tag| self.tags.get(&tag.name))
+            .copied()
+            .or(self.default)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -464,6 +967,82 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_env() {
+        let mut dag = ExecutionDAG::new();
+        dag.config_mut()
+            .env(BTreeMap::from([("foo".to_string(), "dag".to_string())]));
+        let mut exec = Execution::new("exec", ExecutionCommand::local("foo"));
+        exec.env("foo", "exec").env("bar", "exec");
+        exec.copy_env("PATH")
+            .copy_env("TASK_MAKER_SURELY_MISSING_VARIABLE");
+        dag.add_execution(exec);
+        let exec = &dag
+            .data
+            .execution_groups
+            .values()
+            .next()
+            .unwrap()
+            .executions[0];
+        assert!(exec.copy_env.is_empty());
+        let env = exec.effective_env();
+        assert_eq!(env["foo"], "dag");
+        assert_eq!(env["bar"], "exec");
+        assert_eq!(env["PATH"], std::env::var("PATH").unwrap());
+        assert!(!env.contains_key("TASK_MAKER_SURELY_MISSING_VARIABLE"));
+    }
+
+    #[test]
+    fn test_split() {
+        let mut dag = ExecutionDAG::new();
+        let source = File::new("source");
+        dag.provide_content(source.clone(), vec![]);
+        let unused = File::new("unused");
+        dag.provide_content(unused.clone(), vec![]);
+        let mut compilation = Execution::new("compilation", ExecutionCommand::local("foo"));
+        compilation.stdin(&source);
+        let exe = compilation.stdout();
+        let mut checks = vec![];
+        for i in 0..3 {
+            let mut check = Execution::new(format!("check {}", i), ExecutionCommand::local("foo"));
+            check.stdin(&exe);
+            check.stdout();
+            checks.push(check.uuid);
+            dag.add_execution(check);
+        }
+        let compilation_uuid = compilation.uuid;
+        dag.add_execution(compilation);
+
+        let parts = dag.data.split(2);
+        assert_eq!(parts.len(), 2);
+        // the compilation is needed by both parts, but only the first one owns it
+        for part in &parts {
+            assert!(part.data.provided_files.contains_key(&source.uuid));
+            let execs: Vec<_> = part
+                .data
+                .execution_groups
+                .values()
+                .flat_map(|group| &group.executions)
+                .map(|exec| exec.uuid)
+                .collect();
+            assert!(execs.contains(&compilation_uuid));
+        }
+        assert!(parts[0].executions.contains(&compilation_uuid));
+        assert!(!parts[1].executions.contains(&compilation_uuid));
+        assert!(parts[0].files.contains(&exe.uuid));
+        assert!(!parts[1].files.contains(&exe.uuid));
+        assert_eq!(parts[0].data.execution_groups.len(), 3);
+        assert_eq!(parts[1].data.execution_groups.len(), 2);
+        assert!(parts[0].executions.contains(&checks[0]));
+        assert!(parts[1].executions.contains(&checks[1]));
+        assert!(parts[0].executions.contains(&checks[2]));
+        // the unused file goes to the first part
+        assert!(parts[0].files.contains(&unused.uuid));
+        assert!(!parts[1].data.provided_files.contains_key(&unused.uuid));
+
+        assert_eq!(dag.data.split(10).len(), 3);
+    }
+
     #[test]
     fn test_provide_file_not_existing() {
         let mut dag = ExecutionDAG::new();
@@ -485,6 +1064,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_provide_directory() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        std::fs::write(tmpdir.path().join("foo"), "bar").unwrap();
+        let mut dag = ExecutionDAG::new();
+        let file = File::new("dir");
+        dag.provide_directory(file.clone(), tmpdir.path()).unwrap();
+        let archive = match &dag.data.provided_files[&file.uuid] {
+            ProvidedFile::LocalFile { local_path, .. } => local_path.clone(),
+            _ => panic!("Invalid provided file type"),
+        };
+        assert!(archive.exists());
+        assert!(std::fs::metadata(&archive).unwrap().len() > 0);
+        drop(dag);
+        assert!(!archive.exists());
+    }
+
     #[test]
     fn test_add_execution() {
         let mut dag = ExecutionDAG::new();
@@ -499,6 +1095,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_add_execution_keep_full_output() {
+        let mut dag = ExecutionDAG::new();
+        dag.config_mut().keep_full_output(true);
+        let mut exec = Execution::new("exec", ExecutionCommand::local("foo"));
+        exec.capture_stderr(10);
+        dag.add_execution(exec);
+        let group = dag.data.execution_groups.values().next().unwrap();
+        assert!(group.executions[0].stdout.is_none());
+        assert!(group.executions[0].stderr.is_some());
+    }
+
     #[test]
     fn test_write_file_to() {
         let mut dag = ExecutionDAG::new();
@@ -555,6 +1163,41 @@ mod tests {
         assert!(write_to.executable);
     }
 
+    #[test]
+    fn test_copy_file_to() {
+        let mut dag = ExecutionDAG::new();
+        let file = File::new("file");
+        dag.write_file_to(file.clone(), "foo", false);
+        dag.copy_file_to(file.clone(), "bar");
+        dag.copy_file_to(file.clone(), "baz");
+        let callbacks = &dag.callbacks.as_mut().unwrap().file_callbacks[&file.uuid];
+        assert_eq!(Path::new("foo"), callbacks.write_to.as_ref().unwrap().dest);
+        assert_eq!(
+            callbacks.copy_to,
+            vec![PathBuf::from("bar"), PathBuf::from("baz")]
+        );
+    }
+
+    #[test]
+    fn test_written_paths() {
+        let mut dag = ExecutionDAG::new();
+        let file = File::new("file");
+        let other = File::new("other");
+        dag.write_file_to(file.clone(), "foo", false);
+        dag.copy_file_to(file, "bar");
+        dag.write_file_to_allow_fail(other.clone(), "baz", false);
+        dag.copy_file_to(other, "bar");
+        dag.get_file_content(File::new("content"), 1234, |_| Ok(()));
+        assert_eq!(
+            dag.written_paths(),
+            vec![
+                PathBuf::from("bar"),
+                PathBuf::from("baz"),
+                PathBuf::from("foo")
+            ]
+        );
+    }
+
     #[test]
     fn test_get_file_content() {
         let mut dag = ExecutionDAG::new();
@@ -637,4 +1280,32 @@ mod tests {
         );
         assert!(CacheMode::try_from(&Some(Some("tag1".to_string())), &[]).is_err());
     }
+
+    #[test]
+    fn test_cache_ttl_try_from() {
+        let tags = ["tag1".to_string(), "tag2".to_string()];
+        let ttl = CacheTTL::try_from(&["60".into(), "tag1=10".into()], &tags).unwrap();
+        assert_eq!(ttl.default, Some(Duration::from_secs(60)));
+        let tag1 = ExecutionTag::from("tag1");
+        let tag2 = ExecutionTag::from("tag2");
+        assert_eq!(ttl.ttl(Some(&tag1)), Some(Duration::from_secs(10)));
+        assert_eq!(ttl.ttl(Some(&tag2)), Some(Duration::from_secs(60)));
+        assert_eq!(ttl.ttl(None), Some(Duration::from_secs(60)));
+
+        let ttl = CacheTTL::try_from(&["tag1=10".into()], &tags).unwrap();
+        assert_eq!(ttl.ttl(Some(&tag2)), None);
+        assert!(CacheTTL::try_from(&["tag3=10".into()], &tags).is_err());
+        assert!(CacheTTL::try_from(&["tag1=soon".into()], &tags).is_err());
+    }
+
+    #[test]
+    fn test_time_scaling_speed_factor() {
+        assert_eq!(TimeScaling::None.speed_factor(Some(0.5)), 1.0);
+        assert_eq!(TimeScaling::Slower.speed_factor(Some(0.5)), 0.5);
+        assert_eq!(TimeScaling::Slower.speed_factor(Some(2.0)), 1.0);
+        assert_eq!(TimeScaling::All.speed_factor(Some(2.0)), 2.0);
+        assert_eq!(TimeScaling::All.speed_factor(None), 1.0);
+        assert_eq!("slower".parse(), Ok(TimeScaling::Slower));
+        assert!("faster".parse::<TimeScaling>().is_err());
+    }
 }
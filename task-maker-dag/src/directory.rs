@@ -0,0 +1,126 @@
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use anyhow::{bail, Context, Error};
+
+/// Pack a directory tree in a tar archive, for providing it to the executions as a single file.
+/// The archive is streamed to `writer`, which is returned when the archive is complete.
+///
+/// The entries are sorted by name and all their metadata except the executable bit is dropped, so
+/// that the same tree always produces the same archive (and the same `FileStoreKey`).
+pub(crate) fn archive_directory<W: Write>(dir: &Path, writer: W) -> Result<W, Error> {
+    if !dir.is_dir() {
+        bail!("{} is not a directory", dir.display());
+    }
+    let mut builder = tar::Builder::new(writer);
+    append_directory(&mut builder, dir, Path::new(""))?;
+    builder.into_inner().context("Failed to finish the archive")
+}
+
+/// Append to the archive the content of `dir`, placing it under `prefix`.
+fn append_directory<W: Write>(
+    builder: &mut tar::Builder<W>,
+    dir: &Path,
+    prefix: &Path,
+) -> Result<(), Error> {
+    let mut entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to list {}", dir.display()))?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to list {}", dir.display()))?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let name = prefix.join(entry.file_name());
+        // follow the symlinks, the archive contains only files and directories
+        let metadata = std::fs::metadata(&path)
+            .with_context(|| format!("Failed to get file metadata of {}", path.display()))?;
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+        if metadata.is_dir() {
+            header.set_entry_type(tar::EntryType::Directory);
+            header.set_mode(0o755);
+            header.set_size(0);
+            builder
+                .append_data(&mut header, &name, std::io::empty())
+                .with_context(|| format!("Failed to archive {}", path.display()))?;
+            append_directory(builder, &path, &name)?;
+        } else {
+            let executable = metadata.permissions().mode() & 0o111 != 0;
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_mode(if executable { 0o755 } else { 0o644 });
+            header.set_size(metadata.len());
+            let file = std::fs::File::open(&path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            builder
+                .append_data(&mut header, &name, file)
+                .with_context(|| format!("Failed to archive {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_tree(root: &Path) {
+        std::fs::create_dir_all(root.join("dict/it")).unwrap();
+        std::fs::write(root.join("dict/it/words.txt"), "ciao\n").unwrap();
+        std::fs::write(root.join("model.bin"), [1, 2, 3]).unwrap();
+        std::fs::write(root.join("run.sh"), "#!/bin/sh\n").unwrap();
+        let permissions = std::fs::Permissions::from_mode(0o755);
+        std::fs::set_permissions(root.join("run.sh"), permissions).unwrap();
+    }
+
+    #[test]
+    fn test_archive_directory() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        make_tree(tmpdir.path());
+        let content = archive_directory(tmpdir.path(), vec![]).unwrap();
+
+        let mut archive = tar::Archive::new(content.as_slice());
+        let entries = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let path = entry.path().unwrap().to_string_lossy().to_string();
+                (path, entry.header().mode().unwrap())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![
+                ("dict".to_string(), 0o755),
+                ("dict/it".to_string(), 0o755),
+                ("dict/it/words.txt".to_string(), 0o644),
+                ("model.bin".to_string(), 0o644),
+                ("run.sh".to_string(), 0o755),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_archive_directory_deterministic() {
+        let tmpdir1 = tempfile::TempDir::new().unwrap();
+        let tmpdir2 = tempfile::TempDir::new().unwrap();
+        make_tree(tmpdir1.path());
+        make_tree(tmpdir2.path());
+        let archive1 = archive_directory(tmpdir1.path(), vec![]).unwrap();
+        assert_eq!(archive1, archive_directory(tmpdir2.path(), vec![]).unwrap());
+
+        std::fs::write(tmpdir2.path().join("model.bin"), [1, 2, 4]).unwrap();
+        assert_ne!(archive1, archive_directory(tmpdir2.path(), vec![]).unwrap());
+    }
+
+    #[test]
+    fn test_archive_not_a_directory() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        std::fs::write(tmpdir.path().join("file"), "").unwrap();
+        assert!(archive_directory(&tmpdir.path().join("file"), vec![]).is_err());
+        assert!(archive_directory(&tmpdir.path().join("missing"), vec![]).is_err());
+    }
+}
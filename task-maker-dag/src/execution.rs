@@ -1,5 +1,5 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
@@ -7,6 +7,7 @@ use typescript_definitions::TypeScriptify;
 use uuid::Uuid;
 
 use crate::file::*;
+use crate::find_tools::find_tools_path;
 use crate::ExecutionDAGConfig;
 
 /// The identifier of an execution, it's globally unique and it identifies an execution only during
@@ -28,6 +29,12 @@ pub type OnDoneCallback = Box<dyn FnOnce(ExecutionResult) -> Result<(), Error> +
 /// Type of the callback called when an [`Execution`](struct.Execution.html) is skipped.
 pub type OnSkipCallback = Box<dyn FnOnce() -> Result<(), Error> + 'static>;
 
+/// Type of the callback called when an [`Execution`](struct.Execution.html) with a given tag ends.
+/// The callback may alter the result, for example adding some custom metrics, before it's passed to
+/// the `on_done` callbacks of the execution.
+pub type OnTaggedDoneCallback =
+    Box<dyn FnMut(&Execution, &mut ExecutionResult) -> Result<(), Error> + 'static>;
+
 /// Type of the priority value of an `Execution`.
 pub type Priority = i64;
 
@@ -49,14 +56,16 @@ pub struct ExecutionTag {
 /// Command of an [`Execution`](struct.Execution.html) to execute.
 ///
 /// There is a distinction between a `System` command, which has to be searched in the `PATH`
-/// env var, and a `Local` command, which is relative to the sandbox directory.
+/// env var, a `Local` command, which is relative to the sandbox directory, and a `Builtin` command,
+/// which is implemented by task-maker itself.
 ///
 /// ```
-/// use task_maker_dag::ExecutionCommand;
+/// use task_maker_dag::{BuiltinCommand, ExecutionCommand};
 ///
 /// let sys_cmd = ExecutionCommand::system("/usr/bin/env");
 /// let sys_cmd = ExecutionCommand::system("env"); // looking at $PATH
 /// let local_cmd = ExecutionCommand::local("generator"); // local to the cwd of the sandbox
+/// let builtin_cmd = ExecutionCommand::builtin(BuiltinCommand::WhiteDiff);
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum ExecutionCommand {
@@ -65,8 +74,49 @@ pub enum ExecutionCommand {
     System(PathBuf),
     /// A command relative to the sandbox directory, not to be searched in the `$PATH`.
     Local(PathBuf),
+    /// A command implemented by task-maker, that the workers run inside the sandbox with their own
+    /// `task-maker-tools` executable. It doesn't depend on the programs installed on the workers.
+    Builtin(BuiltinCommand),
+}
+
+/// A command implemented by task-maker, see [`ExecutionCommand::Builtin`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum BuiltinCommand {
+    /// Compare the two files in the arguments ignoring the whitespaces and the blank lines,
+    /// exiting with 0 if they contain the same lines of tokens and with 1 otherwise.
+    WhiteDiff,
+    /// Pack the files in the arguments, after the first one, in the zip file named by the first
+    /// argument. The files are stored with their file name and a fixed modification time, so the
+    /// same files produce the same zip.
+    Zip,
+}
+
+/// The toolchain (a compiler or an interpreter) used by an execution, identified by the command
+/// that prints its version (e.g. `g++ --version`).
+///
+/// The workers run the probes of the known toolchains when they start, the version of the toolchain
+/// used by an execution is then reported in its result and it's part of its cache key.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, TypeScriptify)]
+pub struct Toolchain {
+    /// The command line that prints the version of the toolchain.
+    pub probe: Vec<String>,
+    /// The version of the toolchain, the first line printed by the probe. `None` if it's not known
+    /// yet (e.g. in the DAG built by the client) or if the probe failed.
+    pub version: Option<String>,
 }
 
+/// The versions of the toolchains of a worker, indexed by the name of the toolchain (see
+/// [`Toolchain::name`]).
+pub type ToolchainVersions = BTreeMap<String, String>;
+
+/// A set of tags of resources (e.g. `gpu` or `python3.11`) that some executions require and some
+/// workers provide. An execution is run only by a worker that provides all the tags it requires.
+pub type ResourceTags = BTreeSet<String>;
+
+/// The resource provided by the workers that can run the executions inside a container image (see
+/// [`Execution::container_image`](struct.Execution.html#method.container_image)).
+pub const CONTAINER_RESOURCE: &str = "container";
+
 /// An input file of an [`Execution`](struct.Execution.html), can be marked as executable if it has
 /// to be run inside the sandbox.
 #[derive(Debug, Clone, Serialize, Deserialize, Hash)]
@@ -75,6 +125,24 @@ pub struct ExecutionInput {
     pub file: FileUuid,
     /// Whether this file should be marked as executable.
     pub executable: bool,
+    /// Whether this file is an archive of a directory tree (see
+    /// [`ExecutionDAG::provide_directory`](struct.ExecutionDAG.html#method.provide_directory)),
+    /// which is expanded at its path inside the sandbox.
+    #[serde(default)]
+    pub directory: bool,
+}
+
+/// How much the program run by an [`Execution`](struct.Execution.html) is trusted, which selects
+/// the default policy of its sandbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TrustLevel {
+    /// A program written by the task authors (e.g. a generator, a checker or the official
+    /// solution): it can spawn processes and use `/tmp`, `/dev/null` and `/proc`.
+    Trusted,
+    /// A program that is being evaluated (e.g. a contestant solution): it runs with the strictest
+    /// policy, relaxed only by the limits set explicitly.
+    #[default]
+    Untrusted,
 }
 
 /// The callbacks to be called when an event of an execution occurs.
@@ -88,6 +156,13 @@ pub struct ExecutionCallbacks {
     pub on_skip: Vec<OnSkipCallback>,
 }
 
+/// The callbacks to be called when an execution with a given tag completes.
+#[derive(Default)]
+pub struct TaggedExecutionCallbacks {
+    /// The callbacks called when an execution with the tag has completed.
+    pub on_done: Vec<OnTaggedDoneCallback>,
+}
+
 /// An [`Execution`](struct.Execution.html) is a process that will be executed by a worker inside a
 /// sandbox. The sandbox will limit the execution of the process (e.g. killing it after a time limit
 /// occurs, or preventing it from reading/writing files).
@@ -165,7 +240,9 @@ pub struct Execution {
 
     /// Environment variables to set.
     pub env: HashMap<String, String>,
-    /// Environment variables to copy from the sandbox host.
+    /// Environment variables to copy from the environment of the client. They are moved to `env`
+    /// when the execution is added to the DAG, so that they are the same on every worker and they
+    /// are part of the cache key.
     pub copy_env: Vec<String>,
 
     /// Limits on the execution.
@@ -181,6 +258,22 @@ pub struct Execution {
     /// priority order is followed only between ready executions, i.e. a lower priority one can be
     /// executed before if its dependencies are ready earlier.
     pub priority: Priority,
+    /// The toolchain used by this execution, if it runs a system compiler or interpreter.
+    pub toolchain: Option<Toolchain>,
+    /// The tags of the resources that the worker running this execution must provide.
+    pub required_resources: ResourceTags,
+    /// The concurrency classes of this execution: a machine runs at most one execution of each
+    /// class at a time, even if it has more workers. Useful for the executions that use a lot of
+    /// memory, like the compilation of the booklets.
+    pub concurrency_classes: ResourceTags,
+    /// When set, this execution is the compilation of a source file in this language, and its
+    /// outputs are stored in the artifact cache when it succeeds.
+    pub artifact: Option<String>,
+    /// How much the program of this execution is trusted.
+    pub trust: TrustLevel,
+    /// The OCI container image in which this execution is run, instead of using the programs
+    /// installed on the worker. When `None` the image of the DAG configuration is used, if any.
+    pub container_image: Option<String>,
 }
 
 /// Limits on an [`Execution`](struct.Execution.html). On some worker platforms some of the fields
@@ -218,9 +311,10 @@ pub struct ExecutionLimits {
 }
 
 /// Status of a completed [`Execution`](struct.Execution.html).
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, TypeScriptify)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, TypeScriptify)]
 pub enum ExecutionStatus {
     /// The program exited with status 0 within the limits.
+    #[default]
     Success,
     /// The program exited with a non-zero status code, which is attached.
     ReturnCode(u32),
@@ -250,10 +344,31 @@ pub struct ExecutionResourcesUsage {
     pub wall_time: f64,
     /// Number of KiB used _at most_ by the process.
     pub memory: u64,
+    /// Number of voluntary context switches of the process, if measured.
+    pub voluntary_context_switches: Option<u64>,
+    /// Number of involuntary context switches of the process, if measured.
+    pub involuntary_context_switches: Option<u64>,
+    /// Number of page faults of the process that didn't require any I/O, if measured.
+    pub minor_page_faults: Option<u64>,
+    /// Number of page faults of the process that required I/O, if measured.
+    pub major_page_faults: Option<u64>,
+    /// The resident memory of the process, sampled periodically during the execution. Empty if it
+    /// was not measured.
+    pub memory_samples: Vec<MemorySample>,
 }
 
-/// The result of an [`Execution`](struct.Execution.html).
-#[derive(Clone, Serialize, Deserialize, PartialEq, TypeScriptify)]
+/// The resident memory used by a process at some point of its execution.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default, TypeScriptify)]
+pub struct MemorySample {
+    /// Number of seconds from the start of the process.
+    pub time: f64,
+    /// Number of KiB of resident memory used by the process at that time.
+    pub memory: u64,
+}
+
+/// The result of an [`Execution`](struct.Execution.html). The default one is a successful
+/// execution that used no resources.
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq, TypeScriptify)]
 pub struct ExecutionResult {
     /// Status of the completed execution.
     pub status: ExecutionStatus,
@@ -267,6 +382,82 @@ pub struct ExecutionResult {
     pub stdout: Option<Vec<u8>>,
     /// Captured standard error of the execution, if the capture was requested.
     pub stderr: Option<Vec<u8>>,
+    /// Custom metrics of the execution, added by the callbacks registered with
+    /// `ExecutionDAG::on_tagged_execution_done`.
+    #[serde(default)]
+    pub custom_metrics: BTreeMap<String, f64>,
+    /// How the sandbox of the execution has been run, if it has been run.
+    #[serde(default)]
+    pub sandbox: Option<SandboxInvocation>,
+    /// The logs of the sandbox of a failed execution, fetched from the worker that ran it if the
+    /// DAG asked for them.
+    #[serde(default)]
+    pub sandbox_logs: Option<SandboxLogs>,
+    /// The toolchain used by the execution, with the version of the worker that ran it.
+    #[serde(default)]
+    pub toolchain: Option<Toolchain>,
+    /// The key in the `FileStore` of the full standard output of the execution, when the DAG keeps
+    /// the full output of the executions.
+    #[serde(default)]
+    pub stdout_key: Option<String>,
+    /// The key in the `FileStore` of the full standard error of the execution, when the DAG keeps
+    /// the full output of the executions.
+    #[serde(default)]
+    pub stderr_key: Option<String>,
+}
+
+/// How the sandbox of an execution has been run, with the limits already including the extra time
+/// and memory. The paths are the ones on the machine of the worker that ran the execution.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TypeScriptify)]
+pub struct SandboxInvocation {
+    /// The command line of the process that ran the sandbox, reading its configuration from the
+    /// standard input. `None` if the sandbox cannot be run again this way.
+    pub runner: Option<Vec<String>>,
+    /// The directory of the sandbox. It's deleted after the execution, unless the sandboxes are
+    /// kept: in that case it also contains the configuration of the sandbox, in `sandbox.json`.
+    pub boxdir: PathBuf,
+    /// The executable run inside the sandbox.
+    pub executable: PathBuf,
+    /// The arguments passed to the executable.
+    pub args: Vec<String>,
+    /// The working directory inside the sandbox.
+    pub working_directory: PathBuf,
+    /// The environment variables set inside the sandbox.
+    pub env: BTreeMap<String, String>,
+    /// The directories mounted inside the sandbox.
+    pub mounts: Vec<SandboxMount>,
+    /// The file the standard input is read from.
+    pub stdin: Option<PathBuf>,
+    /// The file the standard output is written to.
+    pub stdout: Option<PathBuf>,
+    /// The file the standard error is written to.
+    pub stderr: Option<PathBuf>,
+    /// Limit on the cpu time, in seconds.
+    pub time_limit: Option<u64>,
+    /// Limit on the wall time, in seconds.
+    pub wall_time_limit: Option<u64>,
+    /// Limit on the memory, in bytes.
+    pub memory_limit: Option<u64>,
+    /// Limit on the stack, in bytes.
+    pub stack_limit: Option<u64>,
+}
+
+/// A directory mounted inside the sandbox.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TypeScriptify)]
+pub struct SandboxMount {
+    /// The path of the directory outside the sandbox.
+    pub source: PathBuf,
+    /// The path of the directory inside the sandbox.
+    pub target: PathBuf,
+    /// Whether the directory is writable from inside the sandbox.
+    pub writable: bool,
+}
+
+/// The log files of the sandbox of an execution, kept by the worker that ran it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, TypeScriptify)]
+pub struct SandboxLogs {
+    /// The content of each log file, indexed by its name.
+    pub files: BTreeMap<String, String>,
 }
 
 impl ExecutionLimits {
@@ -374,6 +565,35 @@ impl ExecutionLimits {
         self.extra_readable_dirs.push(dir.into());
         self
     }
+
+    /// Relax the policy of the sandbox for a trusted program, allowing it to spawn processes and to
+    /// use `/tmp`, `/dev/null` and `/proc`. The time and memory limits are not changed.
+    pub fn trusted_policy(&mut self) -> &mut Self {
+        self.allow_multiprocess = true;
+        self.mount_tmpfs = true;
+        self.mount_proc = true;
+        self
+    }
+
+    /// Scale the time limits for a machine `speed_factor` times faster than the reference one: a
+    /// machine twice as fast gets half the time.
+    pub fn scale_time(&mut self, speed_factor: f64) -> &mut Self {
+        let limits = [&mut self.cpu_time, &mut self.sys_time, &mut self.wall_time];
+        for limit in limits.into_iter().flatten() {
+            *limit /= speed_factor;
+        }
+        self
+    }
+}
+
+impl ExecutionResourcesUsage {
+    /// Convert the times measured on a machine `speed_factor` times faster than the reference one
+    /// to the times it would have taken on the reference machine.
+    pub fn normalize_time(&mut self, speed_factor: f64) {
+        self.cpu_time *= speed_factor;
+        self.sys_time *= speed_factor;
+        self.wall_time *= speed_factor;
+    }
 }
 
 impl Default for ExecutionLimits {
@@ -425,6 +645,48 @@ impl ExecutionCommand {
     pub fn local<P: Into<PathBuf>>(path: P) -> ExecutionCommand {
         ExecutionCommand::Local(path.into())
     }
+
+    /// Make a new `ExecutionCommand::Builtin`.
+    pub fn builtin(command: BuiltinCommand) -> ExecutionCommand {
+        ExecutionCommand::Builtin(command)
+    }
+}
+
+impl BuiltinCommand {
+    /// The subcommand of `task-maker-tools` that implements this command.
+    pub fn subcommand(&self) -> &'static str {
+        match self {
+            BuiltinCommand::WhiteDiff => "internal-white-diff",
+            BuiltinCommand::Zip => "internal-zip",
+        }
+    }
+}
+
+impl Toolchain {
+    /// The toolchain of a command, if it's a system command: the version is printed by running it
+    /// with `--version`.
+    pub fn of_command(command: &ExecutionCommand) -> Option<Toolchain> {
+        match command {
+            ExecutionCommand::System(program) => Some(Toolchain {
+                probe: vec![program.to_string_lossy().to_string(), "--version".into()],
+                version: None,
+            }),
+            ExecutionCommand::Local(_) | ExecutionCommand::Builtin(_) => None,
+        }
+    }
+
+    /// The name of the toolchain, its probe command line.
+    pub fn name(&self) -> String {
+        self.probe.join(" ")
+    }
+
+    /// This toolchain with the version taken from `versions`, `None` if it's not there.
+    pub fn with_version(&self, versions: &ToolchainVersions) -> Toolchain {
+        Toolchain {
+            probe: self.probe.clone(),
+            version: versions.get(&self.name()).cloned(),
+        }
+    }
 }
 
 impl Execution {
@@ -463,10 +725,17 @@ impl Execution {
 
             tag: None,
             priority: Priority::default(),
+            toolchain: None,
+            required_resources: ResourceTags::new(),
+            concurrency_classes: ResourceTags::new(),
+            artifact: None,
+            trust: TrustLevel::default(),
+            container_image: None,
         }
     }
 
     /// List of all the [File](struct.File.html) dependencies of the execution, including `stdin`.
+    /// The inputs follow the order of their paths.
     ///
     /// ```
     /// use task_maker_dag::{Execution, ExecutionCommand, File};
@@ -482,14 +751,14 @@ impl Execution {
         if let Some(stdin) = self.stdin {
             deps.push(stdin);
         }
-        for input in self.inputs.values() {
-            deps.push(input.file);
-        }
+        let mut inputs: Vec<_> = self.inputs.iter().collect();
+        inputs.sort_by_key(|(path, _)| *path);
+        deps.extend(inputs.into_iter().map(|(_, input)| input.file));
         deps
     }
 
     /// List of all the [File](struct.File.html) produced by the execution, including `stdout` and
-    /// `stderr`.
+    /// `stderr`. The outputs follow the order of their paths.
     ///
     /// ```
     /// use task_maker_dag::{Execution, ExecutionCommand};
@@ -507,9 +776,9 @@ impl Execution {
         if let Some(stderr) = &self.stderr {
             outs.push(stderr.uuid);
         }
-        for output in self.outputs.values() {
-            outs.push(output.uuid);
-        }
+        let mut outputs: Vec<_> = self.outputs.iter().collect();
+        outputs.sort_by_key(|(path, _)| *path);
+        outs.extend(outputs.into_iter().map(|(_, output)| output.uuid));
         outs
     }
 
@@ -683,6 +952,37 @@ impl Execution {
             ExecutionInput {
                 file: file.into(),
                 executable,
+                directory: false,
+            },
+        );
+        self
+    }
+
+    /// Bind a directory inside the sandbox to the specified file, which should be an archive made
+    /// by [`ExecutionDAG::provide_directory`](struct.ExecutionDAG.html#method.provide_directory).
+    /// The whole tree is expanded in the sandbox, keeping the executable bit of the files. Calling
+    /// again this method will overwrite the previous value.
+    ///
+    /// ```
+    /// use task_maker_dag::{Execution, ExecutionCommand, File};
+    /// use std::path::PathBuf;
+    ///
+    /// let mut exec = Execution::new("manager", ExecutionCommand::local("manager"));
+    /// let file = File::new("dictionaries");
+    /// exec.input_directory(file, "data");
+    /// assert!(exec.inputs[&PathBuf::from("data")].directory);
+    /// ```
+    pub fn input_directory<F: Into<FileUuid>, P: Into<PathBuf>>(
+        &mut self,
+        file: F,
+        path: P,
+    ) -> &mut Self {
+        self.inputs.insert(
+            path.into(),
+            ExecutionInput {
+                file: file.into(),
+                executable: false,
+                directory: true,
             },
         );
         self
@@ -719,7 +1019,8 @@ impl Execution {
         self
     }
 
-    /// Add an environment variable to the execution copying the value from the sandbox host.
+    /// Add an environment variable to the execution copying the value from the environment of the
+    /// client. If the variable is not set in the client, it's not set in the sandbox either.
     ///
     /// ```
     /// use task_maker_dag::{Execution, ExecutionCommand};
@@ -733,6 +1034,33 @@ impl Execution {
         self
     }
 
+    /// The environment variables set inside the sandbox, beside the ones of the worker that are
+    /// always allowed (like `PATH`): the ones of the execution, overridden by the ones of the
+    /// configuration of the DAG.
+    ///
+    /// ```
+    /// use task_maker_dag::{Execution, ExecutionCommand};
+    ///
+    /// let mut exec = Execution::new("random exec", ExecutionCommand::local("foo"));
+    /// exec.env("foo", "bar");
+    /// assert_eq!(exec.effective_env()["foo"], "bar");
+    /// ```
+    pub fn effective_env(&self) -> BTreeMap<String, String> {
+        let mut env: BTreeMap<_, _> = self.env.clone().into_iter().collect();
+        env.extend(self.config.env.clone());
+        env
+    }
+
+    /// Move the variables of `copy_env` to `env`, taking their values from the current
+    /// environment. The variables already in `env` are not overwritten.
+    pub(crate) fn resolve_copy_env(&mut self) {
+        for key in std::mem::take(&mut self.copy_env) {
+            if let Ok(value) = std::env::var(&key) {
+                self.env.entry(key).or_insert(value);
+            }
+        }
+    }
+
     /// Get a mutable reference to the execution limits.
     ///
     /// ```
@@ -750,11 +1078,54 @@ impl Execution {
         &mut self.limits
     }
 
+    /// Run the command of this execution through a wrapper (e.g. a profiler): the wrapper is
+    /// executed with `args` followed by the original command line. The local commands are passed
+    /// to the wrapper relative to the working directory of the sandbox, the builtin commands with
+    /// the `task-maker-tools` executable found by [`find_tools_path`].
+    ///
+    /// ```
+    /// use task_maker_dag::{Execution, ExecutionCommand};
+    ///
+    /// let mut exec = Execution::new("solution", ExecutionCommand::local("sol"));
+    /// exec.args(vec!["42"]);
+    /// exec.wrap_command(ExecutionCommand::system("time"), vec!["-v"]);
+    /// assert_eq!(exec.command, ExecutionCommand::system("time"));
+    /// assert_eq!(exec.args, vec!["-v", "./sol", "42"]);
+    /// ```
+    pub fn wrap_command<S: Into<String>, I: IntoIterator<Item = S>>(
+        &mut self,
+        wrapper: ExecutionCommand,
+        args: I,
+    ) -> &mut Self {
+        let program = match &self.command {
+            ExecutionCommand::System(path) => path.clone(),
+            ExecutionCommand::Local(path) => Path::new(".").join(path),
+            ExecutionCommand::Builtin(_) => find_tools_path(),
+        };
+        let mut new_args: Vec<String> = args.into_iter().map(|s| s.into()).collect();
+        new_args.push(program.to_string_lossy().to_string());
+        if let ExecutionCommand::Builtin(command) = &self.command {
+            new_args.push(command.subcommand().to_string());
+        }
+        new_args.append(&mut self.args);
+        self.command = wrapper;
+        self.args = new_args;
+        self
+    }
+
     /// A reference to the configuration of the underlying DAG.
     pub fn config(&self) -> &ExecutionDAGConfig {
         &self.config
     }
 
+    /// Keep the sandbox directory of this execution, even if the DAG does not keep all of them.
+    /// Since adding the execution to the DAG overwrites its configuration, this has to be called
+    /// after that.
+    pub fn keep_sandbox(&mut self) -> &mut Self {
+        self.config.keep_sandboxes = true;
+        self
+    }
+
     /// Set the tag of this `Execution`.
     pub fn tag(&mut self, tag: ExecutionTag) -> &mut Self {
         self.tag = Some(tag);
@@ -767,6 +1138,93 @@ impl Execution {
         self
     }
 
+    /// Run this `Execution` inside an OCI container image, for example one with a toolchain that is
+    /// not installed on the workers. The image is pulled by the workers the first time it's used.
+    ///
+    /// ```
+    /// use task_maker_dag::{Execution, ExecutionCommand};
+    ///
+    /// let mut exec = Execution::new("compilation", ExecutionCommand::system("ghc"));
+    /// exec.container_image("docker.io/library/haskell:9");
+    /// assert_eq!(exec.effective_container_image(), Some("docker.io/library/haskell:9"));
+    /// ```
+    pub fn container_image<S: Into<String>>(&mut self, image: S) -> &mut Self {
+        self.container_image = Some(image.into());
+        self
+    }
+
+    /// The container image in which this execution is actually run: its own, or the one of the
+    /// configuration of the DAG. The builtin commands are run by task-maker itself, so they are
+    /// never run inside the image of the DAG.
+    ///
+    /// ```
+    /// use task_maker_dag::{Execution, ExecutionCommand, ExecutionDAG};
+    ///
+    /// let mut dag = ExecutionDAG::new();
+    /// dag.config_mut().container_image(Some("alpine:3".into()));
+    /// let exec = Execution::new("random exec", ExecutionCommand::local("foo"));
+    /// assert_eq!(exec.effective_container_image(), None);
+    /// dag.add_execution(exec);
+    /// let group = dag.data.execution_groups.values().next().unwrap();
+    /// assert_eq!(group.executions[0].effective_container_image(), Some("alpine:3"));
+    /// ```
+    pub fn effective_container_image(&self) -> Option<&str> {
+        if let Some(image) = &self.container_image {
+            return Some(image);
+        }
+        if let ExecutionCommand::Builtin(_) = self.command {
+            return None;
+        }
+        self.config.container_image.as_deref()
+    }
+
+    /// Require that this `Execution` runs on a worker that provides the resource with this tag.
+    ///
+    /// ```
+    /// use task_maker_dag::{Execution, ExecutionCommand};
+    ///
+    /// let mut exec = Execution::new("checker", ExecutionCommand::system("python3.11"));
+    /// exec.require_resource("python3.11").require_resource("gpu");
+    /// assert!(exec.required_resources.contains("gpu"));
+    /// ```
+    pub fn require_resource<S: Into<String>>(&mut self, tag: S) -> &mut Self {
+        self.required_resources.insert(tag.into());
+        self
+    }
+
+    /// Add this `Execution` to a concurrency class: the workers running on the same machine never
+    /// run at the same time more than one execution of the class.
+    ///
+    /// ```
+    /// use task_maker_dag::{Execution, ExecutionCommand};
+    ///
+    /// let mut exec = Execution::new("booklet", ExecutionCommand::system("latexmk"));
+    /// exec.concurrency_class("latex");
+    /// assert!(exec.concurrency_classes.contains("latex"));
+    /// ```
+    pub fn concurrency_class<S: Into<String>>(&mut self, class: S) -> &mut Self {
+        self.concurrency_classes.insert(class.into());
+        self
+    }
+
+    /// Mark this `Execution` as the compilation of a source file in the specified language, whose
+    /// outputs are kept in the artifact cache, independently of the cache of the executions.
+    pub fn artifact<S: Into<String>>(&mut self, language: S) -> &mut Self {
+        self.artifact = Some(language.into());
+        self
+    }
+
+    /// Set how much the program of this `Execution` is trusted. Trusting an execution relaxes the
+    /// policy of its sandbox, see [`ExecutionLimits::trusted_policy`]; the untrusted executions
+    /// keep the limits they already have.
+    pub fn trust(&mut self, trust: TrustLevel) -> &mut Self {
+        self.trust = trust;
+        if trust == TrustLevel::Trusted {
+            self.limits.trusted_policy();
+        }
+        self
+    }
+
     /// Compute the [`ExecutionStatus`](struct.ExecutionStatus.html) based on the result of the
     /// execution, checking the signals, the return code and the time/memory constraints.
     pub fn status(
@@ -819,6 +1277,15 @@ impl std::fmt::Debug for ExecutionCallbacks {
     }
 }
 
+impl std::fmt::Debug for TaggedExecutionCallbacks {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter
+            .debug_struct("TaggedExecutionCallbacks")
+            .field("on_done", &self.on_done.len())
+            .finish()
+    }
+}
+
 impl std::fmt::Debug for ExecutionResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ExecutionResult")
@@ -840,6 +1307,12 @@ impl std::fmt::Debug for ExecutionResult {
                     .as_ref()
                     .map(|s| String::from_utf8_lossy(s).to_string()),
             )
+            .field("custom_metrics", &self.custom_metrics)
+            .field("sandbox", &self.sandbox)
+            .field("sandbox_logs", &self.sandbox_logs)
+            .field("toolchain", &self.toolchain)
+            .field("stdout_key", &self.stdout_key)
+            .field("stderr_key", &self.stderr_key)
             .finish()
     }
 }
@@ -865,6 +1338,7 @@ mod tests {
                 sys_time: 0.0,
                 wall_time: 0.0,
                 memory: 0,
+                ..Default::default()
             },
         );
         assert_eq!(ExecutionStatus::Success, status);
@@ -882,6 +1356,7 @@ mod tests {
                 sys_time: 0.0,
                 wall_time: 0.0,
                 memory: 0,
+                ..Default::default()
             },
         );
         assert_eq!(ExecutionStatus::TimeLimitExceeded, status);
@@ -899,6 +1374,7 @@ mod tests {
                 sys_time: 1.1,
                 wall_time: 0.0,
                 memory: 0,
+                ..Default::default()
             },
         );
         assert_eq!(ExecutionStatus::SysTimeLimitExceeded, status);
@@ -916,6 +1392,7 @@ mod tests {
                 sys_time: 0.0,
                 wall_time: 1.1,
                 memory: 0,
+                ..Default::default()
             },
         );
         assert_eq!(ExecutionStatus::WallTimeLimitExceeded, status);
@@ -933,6 +1410,7 @@ mod tests {
                 sys_time: 0.0,
                 wall_time: 0.0,
                 memory: 1235,
+                ..Default::default()
             },
         );
         assert_eq!(ExecutionStatus::MemoryLimitExceeded, status);
@@ -949,6 +1427,7 @@ mod tests {
                 sys_time: 0.0,
                 wall_time: 0.0,
                 memory: 0,
+                ..Default::default()
             },
         );
         assert_eq!(ExecutionStatus::Signal(11, "Killed".into()), status);
@@ -965,8 +1444,25 @@ mod tests {
                 sys_time: 0.0,
                 wall_time: 0.0,
                 memory: 0,
+                ..Default::default()
             },
         );
         assert_eq!(ExecutionStatus::ReturnCode(1), status);
     }
+
+    #[test]
+    fn test_trust() {
+        let mut exec = Execution::new("foo", ExecutionCommand::local("foo"));
+        assert_eq!(exec.trust, TrustLevel::Untrusted);
+        assert!(!exec.limits.allow_multiprocess);
+        assert!(!exec.limits.mount_proc);
+        exec.limits_mut().cpu_time(1.0);
+        exec.trust(TrustLevel::Trusted);
+        assert_eq!(exec.trust, TrustLevel::Trusted);
+        assert!(exec.limits.allow_multiprocess);
+        assert!(exec.limits.mount_tmpfs);
+        assert!(exec.limits.mount_proc);
+        assert!(exec.limits.read_only);
+        assert_eq!(exec.limits.cpu_time, Some(1.0));
+    }
 }
@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{Execution, ExecutionDAGConfig, ExecutionTag, Priority};
+use crate::{
+    Execution, ExecutionDAGConfig, ExecutionTag, Priority, ResourceTags, ToolchainVersions,
+    CONTAINER_RESOURCE,
+};
 use std::path::{Path, PathBuf};
 
 /// Directory inside the sandbox where to place all the pipes of the group. This is used to allow
@@ -102,6 +105,49 @@ impl ExecutionGroup {
             .tag
             .clone()
     }
+
+    /// The tags of the resources required by any of the executions of this group. The executions
+    /// run inside a container image require also [`CONTAINER_RESOURCE`].
+    pub fn required_resources(&self) -> ResourceTags {
+        let mut resources: ResourceTags = self
+            .executions
+            .iter()
+            .flat_map(|exec| exec.required_resources.iter().cloned())
+            .collect();
+        if self
+            .executions
+            .iter()
+            .any(|exec| exec.effective_container_image().is_some())
+        {
+            resources.insert(CONTAINER_RESOURCE.to_string());
+        }
+        resources
+    }
+
+    /// The concurrency classes of any of the executions of this group.
+    pub fn concurrency_classes(&self) -> ResourceTags {
+        self.executions
+            .iter()
+            .flat_map(|exec| exec.concurrency_classes.iter().cloned())
+            .collect()
+    }
+
+    /// The language of the compilation of this group, if it's made of a single execution whose
+    /// outputs are stored in the artifact cache.
+    pub fn artifact(&self) -> Option<&str> {
+        match self.executions.as_slice() {
+            [exec] => exec.artifact.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Set the versions of the toolchains used by the executions of this group, taking them from
+    /// `versions`.
+    pub fn set_toolchain_versions(&mut self, versions: &ToolchainVersions) {
+        for exec in self.executions.iter_mut() {
+            exec.toolchain = exec.toolchain.as_ref().map(|t| t.with_version(versions));
+        }
+    }
 }
 
 impl From<Execution> for ExecutionGroup {
@@ -30,6 +30,8 @@ pub struct WriteToCallback {
 pub struct FileCallbacks {
     /// Destination of the file if it has to be stored in the disk of the client.
     pub write_to: Option<WriteToCallback>,
+    /// Other destinations of the file, written even if the execution that generated it failed.
+    pub copy_to: Vec<PathBuf>,
     /// Callback to be called with the first bytes of the file.
     pub get_content: Option<(usize, GetContentCallback)>,
     /// Callbacks to be called with the chunks of a file ready.
@@ -69,6 +71,7 @@ impl std::fmt::Debug for FileCallbacks {
             .debug_struct("FileCallbacks")
             .field("get_content", &self.get_content.is_some())
             .field("write_to", &self.write_to)
+            .field("copy_to", &self.copy_to)
             .finish()?;
         Ok(())
     }
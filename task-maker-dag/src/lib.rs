@@ -45,11 +45,14 @@
 extern crate approx;
 
 mod dag;
+mod directory;
 mod execution;
 mod execution_group;
 mod file;
+mod find_tools;
 
 pub use dag::*;
 pub use execution::*;
 pub use execution_group::*;
 pub use file::*;
+pub use find_tools::find_tools_path;
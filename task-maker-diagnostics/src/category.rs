@@ -0,0 +1,164 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{bail, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::{Diagnostic, DiagnosticLevel};
+
+/// The part of the task a diagnostic is about, used for selecting the warnings that make the
+/// evaluation fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum DiagnosticCategory {
+    /// General properties of the task, like its files and its configuration.
+    Task,
+    /// The statement and the booklets.
+    Statement,
+    /// The generation and the validation of the input and output files.
+    Generation,
+    /// The solutions and their behavior.
+    Solutions,
+    /// The checker and its output.
+    Checker,
+    /// The subtasks and how the solutions are scored.
+    Scoring,
+    /// The attachments of the task, like the templates and the sample cases.
+    Attachments,
+    /// The usage of deprecated features.
+    Deprecation,
+}
+
+impl DiagnosticCategory {
+    /// All the categories.
+    pub const ALL: &'static [DiagnosticCategory] = &[
+        DiagnosticCategory::Task,
+        DiagnosticCategory::Statement,
+        DiagnosticCategory::Generation,
+        DiagnosticCategory::Solutions,
+        DiagnosticCategory::Checker,
+        DiagnosticCategory::Scoring,
+        DiagnosticCategory::Attachments,
+        DiagnosticCategory::Deprecation,
+    ];
+
+    /// The name of the category, as used on the command line.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticCategory::Task => "task",
+            DiagnosticCategory::Statement => "statement",
+            DiagnosticCategory::Generation => "generation",
+            DiagnosticCategory::Solutions => "solutions",
+            DiagnosticCategory::Checker => "checker",
+            DiagnosticCategory::Scoring => "scoring",
+            DiagnosticCategory::Attachments => "attachments",
+            DiagnosticCategory::Deprecation => "deprecation",
+        }
+    }
+}
+
+impl Display for DiagnosticCategory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DiagnosticCategory {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        match DiagnosticCategory::ALL.iter().find(|c| c.as_str() == s) {
+            Some(category) => Ok(*category),
+            None => bail!("Unknown warning category: {}", s),
+        }
+    }
+}
+
+/// Which warnings are treated as errors: all of them, or only the ones of some categories.
+///
+/// It's parsed from a comma separated list, where `warnings` selects all the warnings and the other
+/// items are names of categories, e.g. `warnings` or `statement,attachments`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DenyPolicy {
+    /// Whether all the warnings are denied.
+    all: bool,
+    /// The categories of the denied warnings.
+    categories: BTreeSet<DiagnosticCategory>,
+}
+
+impl DenyPolicy {
+    /// Whether this policy treats the diagnostic as an error. The errors are always denied, the
+    /// warnings only if they match the policy, and the info messages never.
+    pub fn denies(&self, diagnostic: &Diagnostic) -> bool {
+        match diagnostic.level() {
+            DiagnosticLevel::Error => true,
+            DiagnosticLevel::Info => false,
+            DiagnosticLevel::Warning => {
+                self.all
+                    || diagnostic
+                        .category()
+                        .is_some_and(|category| self.categories.contains(&category))
+            }
+        }
+    }
+
+    /// The warnings that are denied by this policy.
+    pub fn denied_warnings<'a>(&self, diagnostics: &'a [Diagnostic]) -> Vec<&'a Diagnostic> {
+        diagnostics
+            .iter()
+            .filter(|d| d.level() == DiagnosticLevel::Warning && self.denies(d))
+            .collect()
+    }
+}
+
+impl FromStr for DenyPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut policy = DenyPolicy::default();
+        for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            if item.eq_ignore_ascii_case("warnings") {
+                policy.all = true;
+            } else {
+                policy.categories.insert(item.parse()?);
+            }
+        }
+        if !policy.all && policy.categories.is_empty() {
+            bail!("No warning category to deny");
+        }
+        Ok(policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_policy() {
+        let policy = DenyPolicy::from_str("warnings").unwrap();
+        assert!(policy.all);
+        let policy = DenyPolicy::from_str("Statement, attachments").unwrap();
+        assert!(!policy.all);
+        assert_eq!(policy.categories.len(), 2);
+        assert!(DenyPolicy::from_str("statement,foo").is_err());
+        assert!(DenyPolicy::from_str(",").is_err());
+    }
+
+    #[test]
+    fn test_denied_warnings() {
+        let diagnostics = vec![
+            Diagnostic::warning("a").with_category(DiagnosticCategory::Statement),
+            Diagnostic::warning("b").with_category(DiagnosticCategory::Generation),
+            Diagnostic::warning("c"),
+            Diagnostic::info("d").with_category(DiagnosticCategory::Statement),
+        ];
+        let policy = DenyPolicy::from_str("statement").unwrap();
+        let denied = policy.denied_warnings(&diagnostics);
+        assert_eq!(denied.len(), 1);
+        assert_eq!(denied[0].message(), "a");
+        let policy = DenyPolicy::from_str("warnings").unwrap();
+        assert_eq!(policy.denied_warnings(&diagnostics).len(), 3);
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{bail, Error};
+use serde::{Deserialize, Serialize};
+
+/// Define the [`DiagnosticCode`] enum from the catalogue of the codes. The doc comment of each code
+/// is its explanation, and `help` is the suggested fix.
+macro_rules! diagnostic_codes {
+    ($(
+        $(#[doc = $doc:literal])*
+        $name:ident = $code:literal, help = $help:literal;
+    )*) => {
+        /// The code of a class of diagnostics, shown to the user as `E` followed by 3 digits.
+        ///
+        /// The codes are stable: a code is never reused for a different class of problems. The
+        /// codes in the `E0xx` range are about the parsing of the task, the ones in `E1xx` are
+        /// about building the DAG and the ones in `E2xx` are about the executions.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+        #[serde(into = "String", try_from = "String")]
+        pub enum DiagnosticCode {
+            $(
+                $(#[doc = $doc])*
+                $name,
+            )*
+        }
+
+        impl DiagnosticCode {
+            /// All the codes of the catalogue.
+            pub const ALL: &'static [DiagnosticCode] = &[$(DiagnosticCode::$name,)*];
+
+            /// The code as shown to the user, e.g. `E010`.
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(DiagnosticCode::$name => $code,)*
+                }
+            }
+
+            /// The explanation of the problem identified by this code.
+            pub fn explanation(&self) -> String {
+                let lines: &[&str] = match self {
+                    $(DiagnosticCode::$name => &[$($doc),*],)*
+                };
+                lines.iter().map(|line| line.trim()).collect::<Vec<_>>().join(" ")
+            }
+
+            /// The suggested fix for the problem identified by this code.
+            pub fn help(&self) -> &'static str {
+                match self {
+                    $(DiagnosticCode::$name => $help,)*
+                }
+            }
+        }
+    };
+}
+
+diagnostic_codes! {
+    /// The task directory cannot be found, or its format is not recognized.
+    TaskNotFound = "E001",
+    help = "Run task-maker inside the task directory or pass it with --task-dir";
+    /// The task.yaml file of the task cannot be parsed.
+    InvalidTaskYaml = "E002",
+    help = "Check the syntax of task.yaml and the types of its fields";
+    /// The task has no generator, or a testcase of gen/cases.gen has no generator to use.
+    NoGenerator = "E010",
+    help = "Add a single generator in gen/ (e.g. gen/generator.cpp) or use gen/cases.gen";
+    /// There are more files that can be used for the same role (e.g. more checkers).
+    MultipleCandidates = "E011",
+    help = "Keep only one of the files, or remove the extension of the unused ones";
+    /// The language of a source file cannot be detected from its extension.
+    UnknownLanguage = "E012",
+    help = "Check the extension of the file, it must be one of a supported language";
+    /// A generator, a validator or a variable used in gen/cases.gen is not declared.
+    UndeclaredName = "E013",
+    help = "Declare it before using it in gen/cases.gen";
+    /// A testcase, a generator or a validator of gen/cases.gen is set outside a subtask.
+    OutsideSubtask = "E014",
+    help = "Start a subtask with :SUBTASK before adding testcases to it";
+    /// The output files need to be generated, but the task has no official solution.
+    MissingOfficialSolution = "E015",
+    help = "Add the official solution as sol/solution.* or sol/template.*";
+    /// A file used by the task (e.g. a file copied in a testcase) does not exist.
+    MissingFile = "E016",
+    help = "Check the path of the file, it is relative to the task directory";
+    /// No testcase is selected by the --subtask and --testcase filters.
+    NoTestcaseSelected = "E100",
+    help = "Check the numbers passed to --subtask and --testcase";
+    /// An option is not supported by the type of the task.
+    UnsupportedOption = "E101",
+    help = "Remove the option, it is supported only by Batch tasks";
+    /// The compilation of a source file failed.
+    CompilationFailed = "E200",
+    help = "Fix the compilation errors shown in the output of the compiler";
+    /// The generator of an input file failed.
+    GenerationFailed = "E201",
+    help = "Run the generator with the same arguments to reproduce the failure";
+    /// The validator rejected an input file.
+    ValidationFailed = "E202",
+    help = "Check the constraints of the subtask and the arguments of the generator";
+    /// The official solution failed to generate an output file.
+    OutputGenerationFailed = "E203",
+    help = "Run the official solution on the input file to reproduce the failure";
+    /// The checker failed on an output file.
+    CheckerFailed = "E204",
+    help = "Run the checker on the input and output files to reproduce the failure";
+    /// The checker produced an output that cannot be parsed, or a score outside [0, 1].
+    InvalidCheckerOutput = "E205",
+    help = "The checker must print the score (from 0.0 to 1.0) to stdout and a message to stderr";
+    /// The accepted solutions produced different outputs on the same testcase.
+    NonDeterministicOutput = "E206",
+    help = "Check if the output of the task is unique or if the solutions are nondeterministic";
+    /// A solution compiled with the sanitizers showed undefined behavior or a memory error.
+    SanitizerError = "E207",
+    help = "Fix the solution, the error is in the report of the sanitizer";
+    /// The checker of a Terry task printed an outcome that doesn't follow the protocol.
+    InvalidTerryOutcome = "E208",
+    help = "The checker must print to stdout the outcome as JSON, with the score and the cases";
+}
+
+impl Display for DiagnosticCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DiagnosticCode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_uppercase();
+        match DiagnosticCode::ALL.iter().find(|code| code.as_str() == s) {
+            Some(code) => Ok(*code),
+            None => bail!("Unknown diagnostic code: {}", s),
+        }
+    }
+}
+
+impl From<DiagnosticCode> for String {
+    fn from(code: DiagnosticCode) -> Self {
+        code.as_str().to_string()
+    }
+}
+
+impl TryFrom<String> for DiagnosticCode {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        DiagnosticCode::from_str(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_codes_are_unique() {
+        let codes: HashSet<_> = DiagnosticCode::ALL.iter().map(|c| c.as_str()).collect();
+        assert_eq!(codes.len(), DiagnosticCode::ALL.len());
+    }
+
+    #[test]
+    fn test_parse_code() {
+        assert_eq!(
+            DiagnosticCode::from_str("e010").unwrap(),
+            DiagnosticCode::NoGenerator
+        );
+        assert!(DiagnosticCode::from_str("E999").is_err());
+    }
+}
@@ -3,6 +3,8 @@
 
 #![deny(missing_docs)]
 
+mod category;
+mod code;
 mod span;
 
 use std::fmt::{Display, Formatter};
@@ -10,6 +12,8 @@ use std::fmt::{Display, Formatter};
 use colored::{Color, Colorize};
 use serde::{Deserialize, Serialize};
 
+pub use category::{DenyPolicy, DiagnosticCategory};
+pub use code::DiagnosticCode;
 pub use span::CodeSpan;
 
 /// The level of the message.
@@ -17,6 +21,8 @@ pub use span::CodeSpan;
 /// This influences the color of the output, and the order in which the diagnostics are shown.
 #[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum DiagnosticLevel {
+    /// The message is just informative, nothing is wrong.
+    Info,
     /// The message is just a warning.
     Warning,
     /// The message is an error.
@@ -29,12 +35,14 @@ impl DiagnosticLevel {
         match self {
             DiagnosticLevel::Error => "Error",
             DiagnosticLevel::Warning => "Warning",
+            DiagnosticLevel::Info => "Info",
         }
     }
 
     /// The color in which this message should be printed.
     pub fn color(&self) -> Color {
         match self {
+            DiagnosticLevel::Info => Color::BrightBlue,
             DiagnosticLevel::Warning => Color::BrightYellow,
             DiagnosticLevel::Error => Color::BrightRed,
         }
@@ -49,10 +57,18 @@ impl Display for DiagnosticLevel {
 
 /// A [`Diagnostic`] is a message, with some extra information attached, such as the message level,
 /// additional information about what happened or some help on how to fix the issue.
+///
+/// A [`Diagnostic`] is also an error: it can be returned (e.g. with `bail!`) where the problem is
+/// found and it will be shown to the user with all its information.
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct Diagnostic {
     /// The level of this message.
     level: DiagnosticLevel,
+    /// The code of the class of this diagnostic, if any.
+    code: Option<DiagnosticCode>,
+    /// The part of the task this diagnostic is about, if any.
+    #[serde(default)]
+    category: Option<DiagnosticCategory>,
     /// The main message to report.
     message: String,
     /// Additional notes to show next to the main message.
@@ -71,6 +87,8 @@ impl Diagnostic {
     pub fn error(message: impl Into<String>) -> Self {
         Self {
             level: DiagnosticLevel::Error,
+            code: None,
+            category: None,
             message: message.into(),
             note: None,
             help: None,
@@ -83,6 +101,8 @@ impl Diagnostic {
     pub fn warning(message: impl Into<String>) -> Self {
         Self {
             level: DiagnosticLevel::Warning,
+            code: None,
+            category: None,
             message: message.into(),
             note: None,
             help: None,
@@ -91,6 +111,33 @@ impl Diagnostic {
         }
     }
 
+    /// Create a new [`Diagnostic`] with [`DiagnosticLevel::Info`].
+    pub fn info(message: impl Into<String>) -> Self {
+        Self {
+            level: DiagnosticLevel::Info,
+            code: None,
+            category: None,
+            message: message.into(),
+            note: None,
+            help: None,
+            help_attachment: None,
+            code_spans: Default::default(),
+        }
+    }
+
+    /// Attach a code to the diagnostic. If no help message is attached, the suggested fix of the
+    /// code is shown.
+    pub fn with_code(mut self, code: DiagnosticCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Attach the category of the diagnostic.
+    pub fn with_category(mut self, category: DiagnosticCategory) -> Self {
+        self.category = Some(category);
+        self
+    }
+
     /// Attach a note to the diagnostic.
     pub fn with_note(mut self, note: impl Into<String>) -> Self {
         self.note = Some(note.into());
@@ -120,10 +167,14 @@ impl Diagnostic {
         // TODO: additional printing options (e.g. no colors, compact, ...)
         let level = self.level.as_str();
         let pad = level.len();
+        let header = match self.code {
+            Some(code) => format!("{}[{}]", level, code),
+            None => level.to_string(),
+        };
         writeln!(
             f,
             "{}: {}",
-            level.color(self.level.color()).bold(),
+            header.color(self.level.color()).bold(),
             self.message
         )?;
         if let Some(note) = &self.note {
@@ -136,7 +187,8 @@ impl Diagnostic {
                 writeln!(f, "{:>pad$}  {}", "", line, pad = pad)?;
             }
         }
-        if let Some(help) = &self.help {
+        let help = self.help.as_deref().or(self.code.map(|code| code.help()));
+        if let Some(help) = help {
             writeln!(f, "{:>pad$}: {}", "Help".bold(), help, pad = pad)?;
         }
         if let Some(attachment) = &self.help_attachment {
@@ -174,6 +226,16 @@ impl Diagnostic {
     pub fn message(&self) -> &str {
         &self.message
     }
+
+    /// Get the code of this diagnostic, if any.
+    pub fn code(&self) -> Option<DiagnosticCode> {
+        self.code
+    }
+
+    /// Get the category of this diagnostic, if any.
+    pub fn category(&self) -> Option<DiagnosticCategory> {
+        self.category
+    }
 }
 
 impl Display for Diagnostic {
@@ -182,6 +244,8 @@ impl Display for Diagnostic {
     }
 }
 
+impl std::error::Error for Diagnostic {}
+
 /// The context that contains all the emitted diagnostic messages.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct DiagnosticContext {
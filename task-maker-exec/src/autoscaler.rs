@@ -0,0 +1,385 @@
+//! Hooks for scaling the workers of a server, running user-provided commands when the jobs waiting
+//! in the queue of the scheduler are too many, or when the workers have been idle for too long.
+//!
+//! The scheduler publishes its load after every event it handles, and the autoscaler samples it
+//! periodically. The commands are run with `sh -c`, and know the current load from their
+//! environment: `TM_WAITING_JOBS`, `TM_WORKERS` and `TM_BUSY_WORKERS`. The commands are expected
+//! to start (or stop) the workers asynchronously, for example by asking a cloud provider for some
+//! VMs, and to return shortly.
+//!
+//! For not starting too many workers, a command is never run while the previous one is still
+//! running, and after the spawn command the autoscaler waits for some time before running it
+//! again, giving the new workers the time for connecting.
+
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Error};
+
+/// Interval between two samples of the load of the scheduler.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+/// Maximum time a command can run before being killed.
+const HOOK_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// The configuration of the hooks for scaling the workers of a server.
+#[derive(Debug, Clone)]
+pub struct AutoscalerConfig {
+    /// The shell command that starts more workers, if any.
+    pub spawn_command: Option<String>,
+    /// The shell command that stops the idle workers, if any.
+    pub despawn_command: Option<String>,
+    /// The number of jobs waiting in the queue from which the spawn command is run.
+    pub spawn_threshold: usize,
+    /// The minimum time between two runs of the spawn command.
+    pub spawn_cooldown: Duration,
+    /// How long all the workers have to be idle, with no job waiting, before the despawn command
+    /// is run.
+    pub idle_timeout: Duration,
+}
+
+/// The load of the scheduler, as published by it for the autoscaler.
+#[derive(Debug, Default)]
+pub(crate) struct SchedulerLoad {
+    /// The number of jobs waiting in the queue for a worker.
+    waiting: AtomicUsize,
+    /// The number of connected workers.
+    workers: AtomicUsize,
+    /// The number of workers currently running a job.
+    busy_workers: AtomicUsize,
+}
+
+/// A snapshot of the `SchedulerLoad`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LoadSample {
+    /// The number of jobs waiting in the queue for a worker.
+    waiting: usize,
+    /// The number of connected workers.
+    workers: usize,
+    /// The number of workers currently running a job.
+    busy_workers: usize,
+}
+
+/// The hook the autoscaler decided to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScalingAction {
+    /// Start more workers.
+    Spawn,
+    /// Stop the idle workers.
+    Despawn,
+}
+
+/// The state of the autoscaler, deciding when to run the hooks.
+#[derive(Debug)]
+struct AutoscalerState {
+    /// The configuration of the hooks.
+    config: AutoscalerConfig,
+    /// When the spawn command has been run for the last time.
+    last_spawn: Option<Instant>,
+    /// Since when all the workers are idle and no job is waiting.
+    idle_since: Option<Instant>,
+}
+
+/// A thread that runs the scaling hooks based on the load of the scheduler, until it's stopped.
+pub(crate) struct Autoscaler {
+    /// Channel for stopping the autoscaler thread.
+    stop: crossbeam_channel::Sender<()>,
+    /// The autoscaler thread.
+    handle: JoinHandle<()>,
+}
+
+impl SchedulerLoad {
+    /// Publish the current load of the scheduler.
+    pub fn update(&self, waiting: usize, workers: usize, busy_workers: usize) {
+        self.waiting.store(waiting, Ordering::Relaxed);
+        self.workers.store(workers, Ordering::Relaxed);
+        self.busy_workers.store(busy_workers, Ordering::Relaxed);
+    }
+
+    /// Take a snapshot of the load.
+    fn sample(&self) -> LoadSample {
+        LoadSample {
+            waiting: self.waiting.load(Ordering::Relaxed),
+            workers: self.workers.load(Ordering::Relaxed),
+            busy_workers: self.busy_workers.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl AutoscalerState {
+    /// Make a new state, with the hooks never run.
+    fn new(config: AutoscalerConfig) -> AutoscalerState {
+        AutoscalerState {
+            config,
+            last_spawn: None,
+            idle_since: None,
+        }
+    }
+
+    /// Decide which hook to run, if any, given the load of the scheduler at `now`. The returned
+    /// action is considered as done.
+    fn decide(&mut self, now: Instant, load: LoadSample) -> Option<ScalingAction> {
+        if load.waiting == 0 && load.busy_workers == 0 {
+            self.idle_since.get_or_insert(now);
+        } else {
+            self.idle_since = None;
+        }
+        if self.config.spawn_command.is_some()
+            && load.waiting > 0
+            && load.waiting >= self.config.spawn_threshold
+        {
+            let cooled_down = self
+                .last_spawn
+                .map(|last| now.duration_since(last) >= self.config.spawn_cooldown)
+                .unwrap_or(true);
+            if cooled_down {
+                self.last_spawn = Some(now);
+                return Some(ScalingAction::Spawn);
+            }
+        }
+        if self.config.despawn_command.is_some() && load.workers > 0 {
+            if let Some(idle_since) = self.idle_since {
+                if now.duration_since(idle_since) >= self.config.idle_timeout {
+                    // if the workers are still there, wait again before retrying
+                    self.idle_since = Some(now);
+                    return Some(ScalingAction::Despawn);
+                }
+            }
+        }
+        None
+    }
+
+    /// The command of a hook.
+    fn command(&self, action: ScalingAction) -> Option<&str> {
+        match action {
+            ScalingAction::Spawn => self.config.spawn_command.as_deref(),
+            ScalingAction::Despawn => self.config.despawn_command.as_deref(),
+        }
+    }
+}
+
+impl Autoscaler {
+    /// Start sampling the `load` of the scheduler, running the hooks of `config` when needed.
+    pub fn start(config: AutoscalerConfig, load: Arc<SchedulerLoad>) -> Result<Autoscaler, Error> {
+        let (stop, stopped) = crossbeam_channel::bounded(1);
+        let handle = std::thread::Builder::new()
+            .name("Autoscaler".into())
+            .spawn(move || {
+                let clock = crossbeam_channel::tick(SAMPLE_INTERVAL);
+                let mut state = AutoscalerState::new(config);
+                let running = Arc::new(AtomicBool::new(false));
+                loop {
+                    crossbeam_channel::select! {
+                        recv(clock) -> _ => {}
+                        recv(stopped) -> _ => break,
+                    }
+                    // never run two hooks at the same time
+                    if running.load(Ordering::SeqCst) {
+                        continue;
+                    }
+                    let sample = load.sample();
+                    let Some(action) = state.decide(Instant::now(), sample) else {
+                        continue;
+                    };
+                    let command = state.command(action).unwrap_or_default().to_string();
+                    info!("Running the {:?} hook, the load is {:?}", action, sample);
+                    running.store(true, Ordering::SeqCst);
+                    let running = running.clone();
+                    let spawned = std::thread::Builder::new()
+                        .name("Autoscaler hook".into())
+                        .spawn(move || {
+                            match run_hook(&command, sample) {
+                                Ok(status) if status.success() => {
+                                    info!("The {:?} hook completed", action);
+                                }
+                                Ok(status) => {
+                                    warn!("The {:?} hook failed: {}", action, status);
+                                }
+                                Err(e) => warn!("The {:?} hook failed: {:?}", action, e),
+                            }
+                            running.store(false, Ordering::SeqCst);
+                        });
+                    if let Err(e) = spawned {
+                        warn!("Cannot spawn the autoscaler hook thread: {:?}", e);
+                    }
+                }
+            })
+            .context("Failed to spawn the autoscaler thread")?;
+        Ok(Autoscaler { stop, handle })
+    }
+
+    /// Stop the autoscaler. The hooks that are still running are not waited for.
+    pub fn stop(self) {
+        let _ = self.stop.send(());
+        if self.handle.join().is_err() {
+            warn!("The autoscaler thread panicked");
+        }
+    }
+}
+
+/// Run the command of a hook with the load in its environment, logging its output. The command is
+/// killed if it runs for too long.
+fn run_hook(command: &str, load: LoadSample) -> Result<ExitStatus, Error> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("TM_WAITING_JOBS", load.waiting.to_string())
+        .env("TM_WORKERS", load.workers.to_string())
+        .env("TM_BUSY_WORKERS", load.busy_workers.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run '{}'", command))?;
+    let stdout = child.stdout.take().map(|stdout| log_lines(stdout, false));
+    let stderr = child.stderr.take().map(|stderr| log_lines(stderr, true));
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() > HOOK_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("'{}' has been killed after {:?}", command, HOOK_TIMEOUT);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    };
+    for handle in stdout.into_iter().chain(stderr) {
+        let _ = handle.join();
+    }
+    Ok(status)
+}
+
+/// Log the lines written by a hook in a separate thread, as warnings if they are written to its
+/// standard error.
+fn log_lines<R: Read + Send + 'static>(stream: R, is_stderr: bool) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        for line in BufReader::new(stream).lines().map_while(Result::ok) {
+            if is_stderr {
+                warn!("[hook] {}", line);
+            } else {
+                info!("[hook] {}", line);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AutoscalerConfig {
+        AutoscalerConfig {
+            spawn_command: Some("true".into()),
+            despawn_command: Some("true".into()),
+            spawn_threshold: 10,
+            spawn_cooldown: Duration::from_secs(60),
+            idle_timeout: Duration::from_secs(300),
+        }
+    }
+
+    fn load(waiting: usize, workers: usize, busy_workers: usize) -> LoadSample {
+        LoadSample {
+            waiting,
+            workers,
+            busy_workers,
+        }
+    }
+
+    #[test]
+    fn test_spawn_threshold() {
+        let mut state = AutoscalerState::new(config());
+        let now = Instant::now();
+        assert_eq!(state.decide(now, load(9, 1, 1)), None);
+        assert_eq!(
+            state.decide(now, load(10, 1, 1)),
+            Some(ScalingAction::Spawn)
+        );
+    }
+
+    #[test]
+    fn test_spawn_without_workers() {
+        let mut state = AutoscalerState::new(AutoscalerConfig {
+            spawn_threshold: 0,
+            ..config()
+        });
+        let now = Instant::now();
+        assert_eq!(state.decide(now, load(0, 0, 0)), None);
+        assert_eq!(state.decide(now, load(1, 0, 0)), Some(ScalingAction::Spawn));
+    }
+
+    #[test]
+    fn test_spawn_cooldown() {
+        let mut state = AutoscalerState::new(config());
+        let now = Instant::now();
+        assert_eq!(
+            state.decide(now, load(20, 1, 1)),
+            Some(ScalingAction::Spawn)
+        );
+        let later = now + Duration::from_secs(30);
+        assert_eq!(state.decide(later, load(20, 1, 1)), None);
+        let later = now + Duration::from_secs(60);
+        assert_eq!(
+            state.decide(later, load(20, 1, 1)),
+            Some(ScalingAction::Spawn)
+        );
+    }
+
+    #[test]
+    fn test_despawn_idle() {
+        let mut state = AutoscalerState::new(config());
+        let now = Instant::now();
+        assert_eq!(state.decide(now, load(0, 2, 0)), None);
+        let later = now + Duration::from_secs(299);
+        assert_eq!(state.decide(later, load(0, 2, 0)), None);
+        let later = now + Duration::from_secs(300);
+        assert_eq!(
+            state.decide(later, load(0, 2, 0)),
+            Some(ScalingAction::Despawn)
+        );
+        // the workers are still there, wait again
+        let later = now + Duration::from_secs(301);
+        assert_eq!(state.decide(later, load(0, 2, 0)), None);
+    }
+
+    #[test]
+    fn test_despawn_not_idle() {
+        let mut state = AutoscalerState::new(config());
+        let now = Instant::now();
+        assert_eq!(state.decide(now, load(0, 2, 0)), None);
+        let later = now + Duration::from_secs(200);
+        assert_eq!(state.decide(later, load(0, 2, 1)), None);
+        let later = now + Duration::from_secs(300);
+        assert_eq!(state.decide(later, load(0, 2, 0)), None);
+        let later = now + Duration::from_secs(599);
+        assert_eq!(state.decide(later, load(0, 2, 0)), None);
+        let later = now + Duration::from_secs(600);
+        assert_eq!(
+            state.decide(later, load(0, 2, 0)),
+            Some(ScalingAction::Despawn)
+        );
+    }
+
+    #[test]
+    fn test_despawn_without_workers() {
+        let mut state = AutoscalerState::new(config());
+        let now = Instant::now();
+        assert_eq!(state.decide(now, load(0, 0, 0)), None);
+        let later = now + Duration::from_secs(1000);
+        assert_eq!(state.decide(later, load(0, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_run_hook_env() {
+        let status = run_hook(
+            "test \"$TM_WAITING_JOBS $TM_WORKERS $TM_BUSY_WORKERS\" = \"3 2 1\"",
+            load(3, 2, 1),
+        )
+        .unwrap();
+        assert!(status.success());
+    }
+}
@@ -0,0 +1,80 @@
+//! Measure the speed of the machine of a worker, relative to a reference machine.
+//!
+//! The workers run a short CPU-bound benchmark at startup and report to the server how much faster
+//! than the reference machine they are. The scheduler can then scale the time limits of the
+//! executions sent to each worker, so that a fleet of different machines gives the same verdicts.
+
+use std::time::Instant;
+
+/// Number of iterations of the benchmark loop.
+const ITERATIONS: u64 = 50_000_000;
+/// Time taken by the benchmark on the reference machine, in seconds.
+const REFERENCE_TIME: f64 = 0.15;
+/// Number of times the benchmark is run, only the fastest run is considered for reducing the noise.
+const RUNS: usize = 5;
+
+/// Run the benchmark and return the speed factor of this machine: how many times it is faster than
+/// the reference machine (e.g. `2.0` means twice as fast, `0.5` twice as slow).
+pub fn measure_speed_factor() -> f64 {
+    let best = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            std::hint::black_box(benchmark(std::hint::black_box(ITERATIONS)));
+            start.elapsed().as_secs_f64()
+        })
+        .fold(f64::INFINITY, f64::min);
+    REFERENCE_TIME / best
+}
+
+/// Parse a speed factor given on the command line, it must be a finite positive number since the
+/// time limits are scaled by it.
+pub fn parse_speed_factor(s: &str) -> Result<f64, String> {
+    let speed_factor: f64 = s
+        .parse()
+        .map_err(|e| format!("Invalid speed factor: {}", e))?;
+    if !speed_factor.is_finite() || speed_factor <= 0.0 {
+        return Err(format!(
+            "The speed factor must be a positive number, found {}",
+            s
+        ));
+    }
+    Ok(speed_factor)
+}
+
+/// A CPU-bound workload mixing integer arithmetic, branches and random memory accesses, like a
+/// typical solution does.
+fn benchmark(iterations: u64) -> u64 {
+    let mut table = vec![0u64; 1 << 16];
+    let mut x: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut acc: u64 = 0;
+    for i in 0..iterations {
+        // xorshift64
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        let index = (x as usize) & (table.len() - 1);
+        table[index] = table[index].wrapping_add(i);
+        if x & 1 == 0 {
+            acc = acc.wrapping_add(table[index]);
+        } else {
+            acc ^= x;
+        }
+    }
+    acc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_speed_factor() {
+        assert_eq!(parse_speed_factor("1.5"), Ok(1.5));
+        assert_eq!(parse_speed_factor("0.25"), Ok(0.25));
+        assert!(parse_speed_factor("0").is_err());
+        assert!(parse_speed_factor("-2").is_err());
+        assert!(parse_speed_factor("NaN").is_err());
+        assert!(parse_speed_factor("inf").is_err());
+        assert!(parse_speed_factor("fast").is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Failure injection for testing how the executor recovers from the errors of its workers.
+//!
+//! A local executor in chaos mode randomly delays and kills the sandboxes, disconnects the workers
+//! and corrupts the files uploaded by the client, with the probabilities of a [`ChaosConfig`]. It's
+//! available only with the `chaos` feature, and it's meant to be used only by the tests.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use tabox::configuration::SandboxConfiguration;
+
+use crate::{RawSandboxResult, SandboxRunner};
+
+/// The failures injected by a local executor in chaos mode. Each probability is between 0 and 1,
+/// and it's checked independently for every sandbox, job or file. The default configuration
+/// doesn't inject any failure.
+#[derive(Debug, Clone)]
+pub struct ChaosConfig {
+    /// The seed of the random generator.
+    pub seed: u64,
+    /// The probability that a sandbox starts after a random delay.
+    pub delay_probability: f64,
+    /// The maximum delay of a sandbox.
+    pub max_delay: Duration,
+    /// The probability that the process of a sandbox is killed as soon as it starts.
+    pub kill_probability: f64,
+    /// The probability that a worker disconnects when it receives a job. The last connected
+    /// worker never disconnects, so that the evaluation can always complete.
+    pub disconnect_probability: f64,
+    /// The probability that a file uploaded by the client is corrupted.
+    pub corrupt_probability: f64,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        ChaosConfig {
+            seed: 0,
+            delay_probability: 0.0,
+            max_delay: Duration::from_millis(100),
+            kill_probability: 0.0,
+            disconnect_probability: 0.0,
+            corrupt_probability: 0.0,
+        }
+    }
+}
+
+/// Decides which failures to inject, shared by the executor and all its local workers.
+#[derive(Debug)]
+pub(crate) struct ChaosMonkey {
+    /// The probabilities of the failures.
+    config: ChaosConfig,
+    /// The random generator.
+    rng: Mutex<fastrand::Rng>,
+    /// The number of workers that haven't been disconnected yet.
+    connected_workers: AtomicUsize,
+}
+
+impl ChaosMonkey {
+    /// Make a new `ChaosMonkey` for an executor with that number of workers.
+    pub(crate) fn new(config: ChaosConfig, num_workers: usize) -> ChaosMonkey {
+        let rng = fastrand::Rng::with_seed(config.seed);
+        ChaosMonkey {
+            config,
+            rng: Mutex::new(rng),
+            connected_workers: AtomicUsize::new(num_workers),
+        }
+    }
+
+    /// Whether an event with that probability happens.
+    fn happens(&self, probability: f64) -> bool {
+        probability > 0.0 && self.rng.lock().unwrap().f64() < probability
+    }
+
+    /// Maybe wait for a random time before starting a sandbox.
+    fn delay(&self) {
+        if self.happens(self.config.delay_probability) {
+            let delay = self
+                .config
+                .max_delay
+                .mul_f64(self.rng.lock().unwrap().f64());
+            debug!("Chaos: delaying the sandbox by {:?}", delay);
+            thread::sleep(delay);
+        }
+    }
+
+    /// Whether a worker that received a job should disconnect, never disconnecting the last one.
+    pub(crate) fn disconnects_worker(&self) -> bool {
+        if !self.happens(self.config.disconnect_probability) {
+            return false;
+        }
+        self.connected_workers
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |workers| {
+                if workers > 1 {
+                    Some(workers - 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+
+    /// Maybe corrupt a chunk of a file uploaded by the client, flipping one of its bytes.
+    pub(crate) fn corrupt(&self, mut chunk: Vec<u8>) -> Vec<u8> {
+        if !chunk.is_empty() && self.happens(self.config.corrupt_probability) {
+            let index = self.rng.lock().unwrap().usize(..chunk.len());
+            debug!("Chaos: corrupting byte {} of a chunk", index);
+            chunk[index] ^= 0xff;
+        }
+        chunk
+    }
+}
+
+/// A [`SandboxRunner`] that delays and kills the sandboxes spawned by another runner.
+pub(crate) struct ChaosSandboxRunner<R> {
+    /// The runner that actually spawns the sandboxes.
+    runner: R,
+    /// Decides the failures to inject.
+    chaos: Arc<ChaosMonkey>,
+}
+
+impl<R> ChaosSandboxRunner<R> {
+    /// Inject the failures decided by `chaos` in the sandboxes of `runner`.
+    pub(crate) fn new(runner: R, chaos: Arc<ChaosMonkey>) -> ChaosSandboxRunner<R> {
+        ChaosSandboxRunner { runner, chaos }
+    }
+
+    /// Run a sandbox with `run`, delaying it and killing its process if the chaos decides so.
+    fn run_with_chaos<F>(&self, pid: Arc<AtomicU32>, run: F) -> RawSandboxResult
+    where
+        F: FnOnce(Arc<AtomicU32>) -> RawSandboxResult,
+    {
+        self.chaos.delay();
+        if !self.chaos.happens(self.chaos.config.kill_probability) {
+            return run(pid);
+        }
+        // kill the process as soon as the runner tells its pid
+        let done = Arc::new(AtomicBool::new(false));
+        let killer = {
+            let pid = pid.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                while !done.load(Ordering::SeqCst) {
+                    let pid = pid.load(Ordering::SeqCst);
+                    if pid != 0 {
+                        debug!("Chaos: killing the sandbox with pid {}", pid);
+                        let _ = kill(Pid::from_raw(pid as i32), Signal::SIGKILL);
+                        return;
+                    }
+                    thread::sleep(Duration::from_millis(1));
+                }
+            })
+        };
+        let result = run(pid);
+        done.store(true, Ordering::SeqCst);
+        let _ = killer.join();
+        result
+    }
+}
+
+impl<R: SandboxRunner> SandboxRunner for ChaosSandboxRunner<R> {
+    fn run(&self, config: SandboxConfiguration, pid: Arc<AtomicU32>) -> RawSandboxResult {
+        self.run_with_chaos(pid, |pid| self.runner.run(config, pid))
+    }
+
+    fn run_in_container(
+        &self,
+        image: &str,
+        config: SandboxConfiguration,
+        pid: Arc<AtomicU32>,
+    ) -> RawSandboxResult {
+        self.run_with_chaos(pid, |pid| self.runner.run_in_container(image, config, pid))
+    }
+
+    fn command(&self) -> Option<Vec<String>> {
+        self.runner.command()
+    }
+}
@@ -1,6 +1,9 @@
 use crate::executor::ExecutionDAGWatchSet;
+use itertools::Itertools;
 use std::collections::{HashMap, HashSet, VecDeque};
-use task_maker_dag::{ExecutionDAGData, ExecutionGroupUuid, ExecutionUuid, FifoUuid, FileUuid};
+use task_maker_dag::{
+    ExecutionDAGData, ExecutionGroupUuid, ExecutionUuid, FifoUuid, FileUuid, ProvidedFile,
+};
 use thiserror::Error;
 
 /// An error in the DAG structure.
@@ -25,6 +28,13 @@ pub enum DAGError {
         /// The description of the missing file.
         description: String,
     },
+    /// Some files are used as input by some executions, but no execution produces them and they
+    /// are not provided.
+    #[error("missing files: {}", .files.iter().join("; "))]
+    MissingDependencies {
+        /// The missing files, sorted by the executions that need them.
+        files: Vec<MissingDependency>,
+    },
     /// A callback is registered on an execution but it's missing.
     #[error("missing execution {uuid}")]
     MissingExecution {
@@ -32,10 +42,13 @@ pub enum DAGError {
         uuid: ExecutionUuid,
     },
     /// There is a dependency cycle in the DAG.
-    #[error("detected dependency cycle, '{description}' is in the cycle")]
+    #[error(
+        "detected dependency cycle, these executions cannot start: {}",
+        .executions.join(", ")
+    )]
     CycleDetected {
-        /// The description of an execution inside the cycle.
-        description: String,
+        /// The descriptions of the executions inside the cycle, or that depend on it.
+        executions: Vec<String>,
     },
     /// There is a duplicate file UUID.
     #[error("duplicate file UUID {uuid}")]
@@ -61,6 +74,35 @@ pub enum DAGError {
         /// The UUID of the execution group.
         uuid: ExecutionGroupUuid,
     },
+    /// A file is provided, but no execution uses it and no callback is registered on it.
+    #[error("orphan file {description} ({uuid})")]
+    OrphanFile {
+        /// The UUID of the unused file.
+        uuid: FileUuid,
+        /// The description of the unused file.
+        description: String,
+    },
+}
+
+/// A file that is used as input by some executions, but no execution produces it and it's not
+/// provided.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MissingDependency {
+    /// The descriptions of the executions that need the file, sorted.
+    pub executions: Vec<String>,
+    /// The UUID of the missing file.
+    pub uuid: FileUuid,
+}
+
+impl std::fmt::Display for MissingDependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} required by: {}",
+            self.uuid,
+            self.executions.join(", ")
+        )
+    }
 }
 
 /// Validate the DAG checking if all the required pieces are present and they actually make a DAG.
@@ -165,23 +207,44 @@ pub fn check_dag(dag: &ExecutionDAGData, callbacks: &ExecutionDAGWatchSet) -> Re
         }
     }
     // search for unreachable execution / cycles
-    for (group_uuid, count) in num_dependencies.iter() {
-        if *count == 0 {
-            continue;
-        }
-        let group = &dag.execution_groups[group_uuid];
-        for exec in &group.executions {
-            for dep in exec.dependencies().iter() {
-                if !known_files.contains(dep) {
-                    return Err(DAGError::MissingFile {
-                        uuid: *dep,
-                        description: format!("Dependency of '{}'", exec.description),
-                    });
+    let mut stuck_groups: Vec<_> = num_dependencies
+        .iter()
+        .filter(|(_, count)| **count > 0)
+        .map(|(group_uuid, _)| &dag.execution_groups[group_uuid])
+        .collect();
+    if !stuck_groups.is_empty() {
+        stuck_groups.sort_by(|a, b| a.description.cmp(&b.description));
+        // the executions that are not ready because of a missing file
+        let mut missing: HashMap<FileUuid, Vec<String>> = HashMap::new();
+        for group in &stuck_groups {
+            for exec in &group.executions {
+                for dep in exec.dependencies() {
+                    if !known_files.contains(&dep) {
+                        missing
+                            .entry(dep)
+                            .or_default()
+                            .push(exec.description.clone());
+                    }
                 }
             }
         }
+        if !missing.is_empty() {
+            let mut files: Vec<_> = missing
+                .into_iter()
+                .map(|(uuid, mut executions)| {
+                    executions.sort();
+                    MissingDependency { executions, uuid }
+                })
+                .collect();
+            files.sort();
+            return Err(DAGError::MissingDependencies { files });
+        }
         return Err(DAGError::CycleDetected {
-            description: dag.execution_groups[group_uuid].description.clone(),
+            executions: stuck_groups
+                .iter()
+                .flat_map(|group| &group.executions)
+                .map(|exec| exec.description.clone())
+                .collect(),
         });
     }
     // check the file callbacks
@@ -202,6 +265,38 @@ pub fn check_dag(dag: &ExecutionDAGData, callbacks: &ExecutionDAGWatchSet) -> Re
     Ok(())
 }
 
+/// Find the provided files that are not used by any execution and that have no callbacks. They are
+/// not an error for the evaluation, but they are likely caused by a bug in the construction of the
+/// DAG.
+pub fn find_orphan_files(
+    dag: &ExecutionDAGData,
+    callbacks: &ExecutionDAGWatchSet,
+) -> Vec<DAGError> {
+    let used: HashSet<FileUuid> = dag
+        .execution_groups
+        .values()
+        .flat_map(|group| &group.executions)
+        .flat_map(|exec| exec.dependencies())
+        .collect();
+    let mut orphans: Vec<_> = dag
+        .provided_files
+        .iter()
+        .filter(|(uuid, _)| !used.contains(uuid) && !callbacks.files.contains(uuid))
+        .map(|(uuid, file)| {
+            let file = match file {
+                ProvidedFile::Content { file, .. } => file,
+                ProvidedFile::LocalFile { file, .. } => file,
+            };
+            DAGError::OrphanFile {
+                uuid: *uuid,
+                description: file.description.clone(),
+            }
+        })
+        .collect();
+    orphans.sort_by_key(|orphan| orphan.to_string());
+    orphans
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -214,7 +309,37 @@ mod tests {
         let file = File::new("file");
         exec.stdin(file);
         dag.add_execution(exec);
-        assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default()).is_err());
+        let err = check_dag(&dag.data, &ExecutionDAGWatchSet::default()).unwrap_err();
+        let DAGError::MissingDependencies { files } = err else {
+            panic!("Unexpected error: {:?}", err);
+        };
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].executions, vec!["exec"]);
+    }
+
+    #[test]
+    fn test_missing_files_all_sorted() {
+        let mut dag = ExecutionDAG::new();
+        let missing_b = File::new("b");
+        let missing_a = File::new("a");
+        for (desc, file) in [
+            ("exec3", &missing_b),
+            ("exec2", &missing_a),
+            ("exec1", &missing_a),
+        ] {
+            let mut exec = Execution::new(desc, ExecutionCommand::local("foo"));
+            exec.stdin(file);
+            dag.add_execution(exec);
+        }
+        let err = check_dag(&dag.data, &ExecutionDAGWatchSet::default()).unwrap_err();
+        let DAGError::MissingDependencies { files } = err else {
+            panic!("Unexpected error: {:?}", err);
+        };
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].uuid, missing_a.uuid);
+        assert_eq!(files[0].executions, vec!["exec1", "exec2"]);
+        assert_eq!(files[1].uuid, missing_b.uuid);
+        assert_eq!(files[1].executions, vec!["exec3"]);
     }
 
     #[test]
@@ -260,7 +385,8 @@ mod tests {
         exec2.stdin(exec1.stdout());
         dag.add_execution(exec1);
         dag.add_execution(exec2);
-        assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default()).is_err());
+        let err = check_dag(&dag.data, &ExecutionDAGWatchSet::default()).unwrap_err();
+        assert!(matches!(&err, DAGError::CycleDetected { executions } if executions.len() == 2));
     }
 
     #[test]
@@ -285,4 +411,27 @@ mod tests {
         dag.provide_file(file, "/dev/null").unwrap();
         assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default()).is_err());
     }
+
+    #[test]
+    fn test_orphan_files() {
+        let mut dag = ExecutionDAG::new();
+        let mut exec = Execution::new("exec", ExecutionCommand::local("foo"));
+        let used = File::new("used");
+        let watched = File::new("watched");
+        let orphan = File::new("orphan");
+        exec.stdin(&used);
+        dag.add_execution(exec);
+        dag.provide_file(used, "/dev/null").unwrap();
+        dag.provide_file(watched.clone(), "/dev/null").unwrap();
+        dag.provide_file(orphan.clone(), "/dev/null").unwrap();
+        let watch = ExecutionDAGWatchSet {
+            executions: Default::default(),
+            files: [watched.uuid].iter().cloned().collect(),
+            urgent_files: Default::default(),
+        };
+        assert!(check_dag(&dag.data, &watch).is_ok());
+        let orphans = find_orphan_files(&dag.data, &watch);
+        assert_eq!(orphans.len(), 1);
+        assert!(matches!(&orphans[0], DAGError::OrphanFile { uuid, .. } if *uuid == orphan.uuid));
+    }
 }
@@ -10,14 +10,22 @@ use std::time::{Duration, SystemTime};
 use anyhow::{anyhow, Context, Error};
 use ductile::{ChannelReceiver, ChannelSender};
 
-use task_maker_dag::{ExecutionDAG, FileCallbacks, FileUuid, ProvidedFile, WriteToCallback};
+use task_maker_dag::{
+    ExecutionDAG, ExecutionResult, ExecutionUuid, FileCallbacks, FileUuid, ProvidedFile,
+    SandboxLogs, WriteToCallback,
+};
 use task_maker_store::*;
 
-use crate::executor::{ExecutionDAGWatchSet, ExecutorStatus, ExecutorWorkerStatus};
+use crate::executor::{ExecutionDAGWatchSet, ExecutorStatus, ExecutorWorkerStatus, TransferStats};
+use crate::executors::InProcessLink;
 use crate::proto::*;
+use crate::{ClientUuid, SchedulingTrace};
 
 /// Interval between each Status message is sent asking for server status updates.
 const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+/// Maximum number of chunks of a file received from a server that are kept in memory, waiting to
+/// be processed, during an evaluation on more servers.
+const FILE_CHUNKS_IN_FLIGHT: usize = 16;
 
 /// This is a client of the `Executor`, the client is who sends a DAG for an evaluation, provides
 /// some files and receives the callbacks from the server. When the server notifies a callback
@@ -59,7 +67,7 @@ impl ExecutorClient {
     /// // make a new local executor in a second thread
     /// let server = thread::spawn(move || {
     ///     let cache = Cache::new(&path).expect("Cannot create the cache");
-    ///     let mut executor = LocalExecutor::new(server_file_store, cache, 4, path, sandbox_runner).expect("Failed to create local executor");
+    ///     let mut executor = LocalExecutor::new(server_file_store, cache, 4, path, sandbox_runner, Default::default()).expect("Failed to create local executor");
     ///     executor.evaluate(tx_remote, rx_remote).unwrap();
     /// });
     ///
@@ -67,170 +75,434 @@ impl ExecutorClient {
     ///
     /// server.join().expect("Server paniced");
     /// ```
-    #[allow(clippy::cognitive_complexity)]
     pub fn evaluate<F>(
+        dag: ExecutionDAG,
+        sender: ChannelSender<ExecutorClientMessage>,
+        receiver: &ChannelReceiver<ExecutorServerMessage>,
+        file_store: Arc<FileStore>,
+        status_callback: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(ExecutorStatus<SystemTime>) -> Result<(), Error>,
+    {
+        ExecutorClient::evaluate_with(dag, sender, receiver, file_store, None, status_callback)
+    }
+
+    /// Like [`ExecutorClient::evaluate`], but for a
+    /// [`LocalExecutor`](crate::executors::LocalExecutor) in the same process, connected with the
+    /// channels and the link of the executor.
+    ///
+    /// The DAG is handed over to the executor in memory instead of being serialized through the
+    /// channel, and the provided files are written directly in the file store of the executor
+    /// instead of being uploaded. This saves most of the time spent starting small evaluations.
+    pub fn evaluate_in_process<F>(
+        dag: ExecutionDAG,
+        sender: ChannelSender<ExecutorClientMessage>,
+        receiver: &ChannelReceiver<ExecutorServerMessage>,
+        link: &InProcessLink,
+        status_callback: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(ExecutorStatus<SystemTime>) -> Result<(), Error>,
+    {
+        let file_store = link.file_store().clone();
+        ExecutorClient::evaluate_with(
+            dag,
+            sender,
+            receiver,
+            file_store,
+            Some(link),
+            status_callback,
+        )
+    }
+
+    /// Evaluate the DAG on a single server, handing it over through `in_process` if the executor
+    /// is in the same process.
+    fn evaluate_with<F>(
         mut dag: ExecutionDAG,
         sender: ChannelSender<ExecutorClientMessage>,
         receiver: &ChannelReceiver<ExecutorServerMessage>,
         file_store: Arc<FileStore>,
-        mut status_callback: F,
+        in_process: Option<&InProcessLink>,
+        status_callback: F,
     ) -> Result<(), Error>
     where
         F: FnMut(ExecutorStatus<SystemTime>) -> Result<(), Error>,
     {
         trace!("ExecutorClient started");
-        ExecutorClient::start_evaluation(&mut dag, &sender)?;
+        // the handles keep the provided files in the store until the evaluation is done
+        let _provided_files = ExecutorClient::start_evaluation(&mut dag, &sender, in_process)?;
+        ExecutorClient::receive_results(
+            dag,
+            vec![sender],
+            || Some(next_server_event(0, receiver)),
+            file_store,
+            status_callback,
+        )
+    }
 
-        // setup the status poller that will send to the server a Status message every
-        // STATUS_POLL_INTERVAL_MS milliseconds.
-        let (done_sender, done_receiver) = crossbeam_channel::bounded(1);
-        let file_mode = Arc::new(Mutex::new(()));
-        let status_poller =
-            ExecutorClient::spawn_status_poller(done_receiver, file_mode.clone(), sender.clone());
+    /// Evaluate the DAG splitting it among more servers, each one identified by a pair of channels
+    /// like the ones of [`ExecutorClient::evaluate`]. The DAG is split using
+    /// [`ExecutionDAGData::split`](task_maker_dag::ExecutionDAGData::split), all the callbacks are
+    /// called from the current thread and the statuses of the servers are merged into one.
+    ///
+    /// This method is blocking until all the servers end the computation, if one of them fails all
+    /// the others are stopped.
+    ///
+    /// A deterministic DAG is not split, since the order of its executions would depend on the
+    /// timing of the servers: it's evaluated only on the first server.
+    pub fn evaluate_distributed<F>(
+        mut dag: ExecutionDAG,
+        servers: Vec<(
+            ChannelSender<ExecutorClientMessage>,
+            ChannelReceiver<ExecutorServerMessage>,
+        )>,
+        file_store: Arc<FileStore>,
+        status_callback: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(ExecutorStatus<SystemTime>) -> Result<(), Error>,
+    {
+        if servers.len() > 1 && dag.data.config.deterministic {
+            warn!("The deterministic DAG is evaluated only on the first server");
+        }
+        if servers.len() <= 1 || dag.data.config.deterministic {
+            let (sender, receiver) = servers
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow!("No server to evaluate the DAG on"))?;
+            return ExecutorClient::evaluate(dag, sender, &receiver, file_store, status_callback);
+        }
+        trace!("ExecutorClient started on {} servers", servers.len());
+        let parts = dag.data.split(servers.len());
+        // if the DAG is too small some servers are not used
+        let (senders, receivers): (Vec<_>, Vec<_>) = servers.into_iter().take(parts.len()).unzip();
 
+        ExecutorClient::process_local_files(&mut dag)?;
+        for (part, sender) in parts.into_iter().zip(senders.iter()) {
+            let callbacks = dag.callbacks.as_ref().unwrap();
+            let watch_set = ExecutionDAGWatchSet {
+                executions: callbacks
+                    .execution_callbacks
+                    .keys()
+                    .filter(|uuid| part.executions.contains(uuid))
+                    .cloned()
+                    .collect(),
+                files: callbacks
+                    .file_callbacks
+                    .keys()
+                    .filter(|uuid| part.files.contains(uuid))
+                    .cloned()
+                    .collect(),
+                urgent_files: callbacks
+                    .urgent_files
+                    .iter()
+                    .filter(|uuid| part.files.contains(uuid))
+                    .cloned()
+                    .collect(),
+            };
+            sender
+                .send(ExecutorClientMessage::Evaluate {
+                    dag: Box::new(part.data),
+                    callbacks: Box::new(watch_set),
+                })
+                .context("Failed to send the DAG to the server")?;
+        }
+
+        // the messages of all the servers are received by a thread per server, and processed here
+        let (event_sender, event_receiver) = crossbeam_channel::unbounded();
+        for (index, receiver) in receivers.into_iter().enumerate() {
+            let event_sender = event_sender.clone();
+            thread::Builder::new()
+                .name(format!("Client receiver {}", index))
+                .spawn(move || receive_server_events(index, receiver, event_sender))
+                .context("Failed to start client receiver thread")?;
+        }
+        drop(event_sender);
+        ExecutorClient::receive_results(
+            dag,
+            senders,
+            || event_receiver.recv().ok(),
+            file_store,
+            status_callback,
+        )
+    }
+
+    /// Process the messages of the servers the DAG is evaluated on, after its evaluation started,
+    /// until all the servers are done. The server with index `i` receives the messages from
+    /// `senders[i]`, and `next_event` gives the next event of any of them, or `None` when they are
+    /// all disconnected.
+    ///
+    /// The files asked to the servers are streamed to the local store, and the statuses of the
+    /// servers are merged into one.
+    fn receive_results<C, E, F>(
+        mut dag: ExecutionDAG,
+        senders: Vec<ChannelSender<ExecutorClientMessage>>,
+        mut next_event: E,
+        file_store: Arc<FileStore>,
+        mut status_callback: F,
+    ) -> Result<(), Error>
+    where
+        C: IntoIterator<Item = Vec<u8>>,
+        E: FnMut() -> Option<ServerEvent<C>>,
+        F: FnMut(ExecutorStatus<SystemTime>) -> Result<(), Error>,
+    {
+        // setup the status pollers that will send to the servers a Status message every
+        // STATUS_POLL_INTERVAL_MS milliseconds. The deterministic DAGs don't ask for the status,
+        // since it depends on the timing of the evaluation.
+        let (done_sender, done_receiver) = crossbeam_channel::bounded(senders.len());
+        let file_mode = Arc::new(Mutex::new(()));
+        let status_pollers: Vec<_> = senders
+            .iter()
+            .filter(|_| !dag.data.config.deterministic)
+            .map(|sender| {
+                ExecutorClient::spawn_status_poller(
+                    done_receiver.clone(),
+                    file_mode.clone(),
+                    sender.clone(),
+                )
+            })
+            .collect();
         defer! {{
             info!("Client has done, exiting");
-            done_sender.send(()).context("Failed to send done signal to status poller").unwrap();
-            status_poller
-                .join()
-                .map_err(|e| anyhow!("Failed to join status poller: {:?}", e)).unwrap();
+            for _ in &status_pollers {
+                done_sender.send(()).context("Failed to send done signal to status poller").unwrap();
+            }
+            for status_poller in status_pollers {
+                status_poller
+                    .join()
+                    .map_err(|e| anyhow!("Failed to join status poller: {:?}", e)).unwrap();
+            }
         }}
 
-        let mut missing_files = None;
-        while missing_files.unwrap_or(1) > 0 {
-            match receiver.recv() {
-                Ok(ExecutorServerMessage::AskFile(uuid)) => {
-                    info!("Server is asking for {}", uuid);
+        let mut missing_files = vec![None; senders.len()];
+        let mut asked_files = HashMap::new();
+        let mut sandbox_logs = SandboxLogsRequests::default();
+        let mut finished = vec![false; senders.len()];
+        let mut statuses = vec![None; senders.len()];
+        let mut traces = vec![None; senders.len()];
+        while finished.iter().any(|finished| !finished) {
+            let Some(event) = next_event() else {
+                break;
+            };
+            match event {
+                ServerEvent::Message(index, ExecutorServerMessage::AskFile(uuid, encoding)) => {
+                    info!("Server {} is asking for {}", index, uuid);
                     // prevent the status poller for sending messages while sending the file
                     let _lock = file_mode
                         .lock()
                         .map_err(|_| anyhow!("Failed to obtain file_mode lock"))?;
                     let provided_files = &dag.data.provided_files;
-                    handle_server_ask_file(uuid, provided_files, &sender).with_context(|| {
-                        format!("Failed to process AskFile({}) from the server", uuid)
-                    })?;
-                }
-                Ok(ExecutorServerMessage::ProvideFile(uuid, success)) => {
-                    info!("Server sent the file {}, success: {}", uuid, success);
-                    if let Some(missing) = missing_files {
-                        missing_files = Some(missing - 1);
-                    }
-                    let iterator = ChannelFileIterator::new(receiver);
-                    process_provided_file(dag.file_callbacks(), uuid, success, iterator, None)
+                    handle_server_ask_file(uuid, encoding, provided_files, &senders[index])
                         .with_context(|| {
-                            format!(
-                                "Failed to process ProvideFile({}, {}) from the server",
-                                uuid, success
-                            )
+                            format!("Failed to process AskFile({}) from the server", uuid)
                         })?;
                 }
-                Ok(ExecutorServerMessage::NotifyStart(uuid, worker)) => {
-                    info!("Execution {} started on {}", uuid, worker);
-                    if let Some(callbacks) = dag.execution_callbacks().get_mut(&uuid) {
-                        for callback in callbacks.on_start.drain(..) {
-                            if let Err(e) = callback(worker) {
-                                warn!("Start callback for {} failed: {:?}", uuid, e);
-                                return Err(e);
-                            }
-                        }
+                ServerEvent::File(index, uuid, success, chunks) => {
+                    info!(
+                        "Server {} sent the file {}, success: {}",
+                        index, uuid, success
+                    );
+                    if let Some(missing) = &mut missing_files[index] {
+                        *missing -= 1;
+                        finished[index] = *missing == 0 && !sandbox_logs.is_waiting(index);
                     }
+                    let context = || {
+                        format!(
+                            "Failed to process ProvideFile({}, {}) from the server",
+                            uuid, success
+                        )
+                    };
+                    if let Some(key) = asked_files.remove(&uuid) {
+                        // the file is streamed to the local store, and the handle keeps it there
+                        // while it's read by the callbacks
+                        let handle = file_store.store(&key, chunks).with_context(context)?;
+                        let iterator =
+                            ReadFileIterator::new(handle.path()).with_context(context)?;
+                        process_provided_file(dag.file_callbacks(), uuid, success, iterator, None)
+                            .with_context(context)?;
+                    } else {
+                        // the urgent files are sent without asking, so their key is not known
+                        process_provided_file(dag.file_callbacks(), uuid, success, chunks, None)
+                            .with_context(context)?;
+                    }
+                    send_skip_requests(&dag, &senders);
                 }
-                Ok(ExecutorServerMessage::NotifyDone(uuid, result)) => {
-                    info!("Execution {} completed with {:?}", uuid, result);
-                    if let Some(callbacks) = dag.execution_callbacks().get_mut(&uuid) {
-                        for callback in callbacks.on_done.drain(..) {
-                            if let Err(e) = callback(result.clone()) {
-                                warn!("Done callback for {} failed: {:?}", uuid, e);
-                                return Err(e);
-                            }
-                        }
+                ServerEvent::Message(_, ExecutorServerMessage::Error(error)) => {
+                    error!("Error occurred: {}", error);
+                    for sender in &senders {
+                        let _ = sender.send(ExecutorClientMessage::Stop);
                     }
+                    break;
                 }
-                Ok(ExecutorServerMessage::NotifySkip(uuid)) => {
-                    info!("Execution {} skipped", uuid);
-                    if let Some(callbacks) = dag.execution_callbacks().get_mut(&uuid) {
-                        for callback in callbacks.on_skip.drain(..) {
-                            if let Err(e) = callback() {
-                                warn!("Skip callback for {} failed: {:?}", uuid, e);
-                                return Err(e);
-                            }
-                        }
+                ServerEvent::Message(
+                    _,
+                    ExecutorServerMessage::OutputSizeLimitExceeded(size, limit),
+                ) => {
+                    for sender in &senders {
+                        let _ = sender.send(ExecutorClientMessage::Stop);
                     }
+                    return Err(output_size_limit_error(size, limit));
                 }
-                Ok(ExecutorServerMessage::Error(error)) => {
-                    error!("Error occurred: {}", error);
-                    sender
-                        .send(ExecutorClientMessage::Stop)
-                        .context("Failed to send Stop message to the server after an error")?;
-                    break;
+                ServerEvent::Message(_, ExecutorServerMessage::Cancelled) => {
+                    for sender in &senders {
+                        let _ = sender.send(ExecutorClientMessage::Stop);
+                    }
+                    return Err(cancelled_error());
+                }
+                ServerEvent::Message(_, ExecutorServerMessage::CancelOutcome(_)) => {
+                    warn!("The server sent the outcome of a cancellation that was not asked");
                 }
-                Ok(ExecutorServerMessage::Status(status)) => {
-                    info!("Server status: {:#?}", status);
+                ServerEvent::Message(index, ExecutorServerMessage::Status(status)) => {
+                    info!("Server {} status: {:#?}", index, status);
+                    statuses[index] = Some(status);
+                    let status = ExecutorStatus {
+                        connected_workers: statuses
+                            .iter()
+                            .flatten()
+                            .flat_map(|status| status.connected_workers.clone())
+                            .collect(),
+                        ready_execs: statuses.iter().flatten().map(|s| s.ready_execs).sum(),
+                        waiting_execs: statuses.iter().flatten().map(|s| s.waiting_execs).sum(),
+                        transfers: statuses.iter().flatten().fold(
+                            TransferStats::default(),
+                            |mut total, s| {
+                                total += s.transfers;
+                                total
+                            },
+                        ),
+                        client_transfers: statuses.iter().flatten().fold(
+                            TransferStats::default(),
+                            |mut total, s| {
+                                total += s.client_transfers;
+                                total
+                            },
+                        ),
+                    };
                     handle_server_status(status, &mut status_callback)
                         .context("Failed to process Status() from the server")?;
                 }
-                Ok(ExecutorServerMessage::Done(result)) => {
-                    info!("Execution completed producing {} files!", result.len());
-                    let mut missing = 0;
-                    for (uuid, key, success) in result {
-                        if let Some(handle) = file_store.get(&key) {
-                            let iterator =
-                                ReadFileIterator::new(handle.path()).with_context(|| {
-                                    format!(
-                                        "Failed to read produced file ({}) from the local storage",
-                                        handle
-                                    )
-                                })?;
-                            process_provided_file(
-                                dag.file_callbacks(),
-                                uuid,
-                                success,
-                                iterator,
-                                None,
-                            )
-                            .with_context(|| {
-                                format!(
-                                    "Failed to process produced file ({}) from the local storage",
-                                    handle
-                                )
-                            })?;
-                        } else {
-                            sender
-                                .send(ExecutorClientMessage::AskFile(uuid, key, success))
-                                .with_context(|| {
-                                    format!("Failed to ask for a completed file ({})", uuid)
-                                })?;
-                            missing += 1;
-                        }
+                ServerEvent::Message(index, ExecutorServerMessage::Done(result)) => {
+                    info!(
+                        "Execution on server {} completed producing {} files!",
+                        index,
+                        result.len()
+                    );
+                    let asked = handle_server_done(&mut dag, result, &file_store, &senders[index])?;
+                    missing_files[index] = Some(asked.len());
+                    finished[index] = asked.is_empty() && !sandbox_logs.is_waiting(index);
+                    asked_files.extend(asked);
+                }
+                ServerEvent::Message(index, ExecutorServerMessage::Trace(trace)) => {
+                    traces[index] = Some(*trace);
+                }
+                ServerEvent::Message(index, ExecutorServerMessage::SandboxLogs(uuid, logs)) => {
+                    sandbox_logs.received(&mut dag, uuid, logs)?;
+                    finished[index] =
+                        missing_files[index] == Some(0) && !sandbox_logs.is_waiting(index);
+                }
+                ServerEvent::Message(index, message) => {
+                    if let Some(message) =
+                        sandbox_logs.ask(&dag, index, message, &senders[index])?
+                    {
+                        handle_execution_notification(&mut dag, message)?;
+                        send_skip_requests(&dag, &senders);
                     }
-                    missing_files = Some(missing);
                 }
-                Err(e) => {
-                    let cause = e.root_cause().to_string();
+                ServerEvent::Disconnected(index, cause) => {
                     if cause == "receiving on an empty and disconnected channel" {
-                        trace!("Connection closed: {}", cause);
+                        trace!("Connection to server {} closed: {}", index, cause);
                     } else {
-                        error!("Connection error: {}", cause);
+                        error!("Connection error with server {}: {}", index, cause);
                     }
-                    break;
+                    sandbox_logs.flush(&mut dag, Some(index))?;
+                    finished[index] = true;
                 }
             }
         }
+        sandbox_logs.flush(&mut dag, None)?;
+        let traces: Vec<SchedulingTrace> = traces.into_iter().flatten().collect();
+        if !traces.is_empty() {
+            write_trace(&dag, &traces);
+        }
         Ok(())
     }
 
+    /// Ask the server to cancel the evaluation of another client, identified by the uuid of that
+    /// client (the one shown in the status of the server). Its running executions are killed and
+    /// the client is told its evaluation has been cancelled.
+    ///
+    /// Returns whether the evaluation was running on the server.
+    pub fn cancel(
+        evaluation: ClientUuid,
+        sender: &ChannelSender<ExecutorClientMessage>,
+        receiver: &ChannelReceiver<ExecutorServerMessage>,
+    ) -> Result<bool, Error> {
+        sender
+            .send(ExecutorClientMessage::Cancel(evaluation))
+            .context("Failed to send Cancel to the server")?;
+        loop {
+            match receiver
+                .recv()
+                .context("Failed to receive the outcome of Cancel")?
+            {
+                ExecutorServerMessage::CancelOutcome(found) => return Ok(found),
+                message => debug!("Ignoring message while cancelling: {:?}", message),
+            }
+        }
+    }
+
+    /// Ask the server for its status, without evaluating anything.
+    pub fn status(
+        sender: &ChannelSender<ExecutorClientMessage>,
+        receiver: &ChannelReceiver<ExecutorServerMessage>,
+    ) -> Result<ExecutorStatus<SystemTime>, Error> {
+        sender
+            .send(ExecutorClientMessage::Status)
+            .context("Failed to send Status to the server")?;
+        loop {
+            match receiver.recv().context("Failed to receive the status")? {
+                ExecutorServerMessage::Status(status) => {
+                    let mut result = None;
+                    handle_server_status(status, &mut |status| {
+                        result = Some(status);
+                        Ok(())
+                    })?;
+                    return Ok(result.expect("The status callback is always called"));
+                }
+                message => debug!("Ignoring message while asking the status: {:?}", message),
+            }
+        }
+    }
+
     /// Start the evaluation calling the file callbacks on the input files and sending the start
     /// message to the Executor.
+    ///
+    /// If the Executor is in the same process the provided files are stored directly in its file
+    /// store, and their handles are returned.
     fn start_evaluation(
         dag: &mut ExecutionDAG,
         sender: &ChannelSender<ExecutorClientMessage>,
-    ) -> Result<(), Error> {
+        in_process: Option<&InProcessLink>,
+    ) -> Result<Vec<FileStoreHandle>, Error> {
         // list all the files/executions that want callbacks
-        let dag_callbacks = ExecutionDAGWatchSet {
-            executions: dag.execution_callbacks().keys().cloned().collect(),
-            files: dag.file_callbacks().keys().cloned().collect(),
-            urgent_files: dag.urgent_files().clone(),
+        let dag_callbacks = ExecutionDAGWatchSet::new(dag);
+        ExecutorClient::process_local_files(dag)?;
+        let Some(link) = in_process else {
+            sender.send(ExecutorClientMessage::Evaluate {
+                dag: Box::new(dag.data.clone()),
+                callbacks: Box::new(dag_callbacks),
+            })?;
+            return Ok(vec![]);
         };
+        let handles = store_provided_files(&dag.data.provided_files, link.file_store())?;
+        let id = link.put((Box::new(dag.data.clone()), Box::new(dag_callbacks)));
+        sender.send(ExecutorClientMessage::EvaluateInProcess(id))?;
+        Ok(handles)
+    }
+
+    /// Call the file callbacks on the files provided by the client.
+    fn process_local_files(dag: &mut ExecutionDAG) -> Result<(), Error> {
         for (uuid, file) in dag.data.provided_files.iter() {
             match file {
                 ProvidedFile::LocalFile { local_path, .. } => {
@@ -258,10 +530,7 @@ impl ExecutorClient {
                 }
             }
         }
-        sender.send(ExecutorClientMessage::Evaluate {
-            dag: Box::new(dag.data.clone()),
-            callbacks: Box::new(dag_callbacks),
-        })
+        Ok(())
     }
 
     /// Spawn a thread that will ask the server status every `STATUS_POLL_INTERVAL_MS`, making sure
@@ -295,11 +564,185 @@ impl ExecutorClient {
     }
 }
 
-/// Server is asking for a file, handle the request sending the local file or the provided content.
-/// Note that this will trigger a protocol change for sending the file, no messages should be sent
-/// meanwhile.
+/// The results of the executions that failed with an internal error, waiting for the logs of their
+/// sandboxes before calling their callbacks. The logs are asked only if the DAG wants them.
+#[derive(Default)]
+struct SandboxLogsRequests {
+    /// The result of each execution, with the index of the server that sent it.
+    pending: HashMap<ExecutionUuid, (usize, ExecutionResult)>,
+}
+
+impl SandboxLogsRequests {
+    /// Ask to the server the logs of the sandbox of the execution, if the message is the result of
+    /// a failed execution and the DAG asks for them. Otherwise the message is given back, for being
+    /// processed as usual.
+    fn ask(
+        &mut self,
+        dag: &ExecutionDAG,
+        server: usize,
+        message: ExecutorServerMessage,
+        sender: &ChannelSender<ExecutorClientMessage>,
+    ) -> Result<Option<ExecutorServerMessage>, Error> {
+        match message {
+            ExecutorServerMessage::NotifyDone(uuid, result)
+                if dag.data.config.fetch_sandbox_logs && result.status.is_internal_error() =>
+            {
+                info!("Asking the sandbox logs of {}", uuid);
+                sender
+                    .send(ExecutorClientMessage::AskSandboxLogs(uuid))
+                    .context("Failed to send AskSandboxLogs to the server")?;
+                self.pending.insert(uuid, (server, result));
+                Ok(None)
+            }
+            message => Ok(Some(message)),
+        }
+    }
+
+    /// The server sent the logs of the sandbox of an execution: add them to its result and call
+    /// its callbacks.
+    fn received(
+        &mut self,
+        dag: &mut ExecutionDAG,
+        uuid: ExecutionUuid,
+        logs: Option<SandboxLogs>,
+    ) -> Result<(), Error> {
+        match self.pending.remove(&uuid) {
+            Some((_, mut result)) => {
+                result.sandbox_logs = logs;
+                handle_execution_notification(dag, ExecutorServerMessage::NotifyDone(uuid, result))
+            }
+            None => {
+                warn!("The server sent the sandbox logs of {}, not asked", uuid);
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether the logs asked to the server with that index didn't arrive yet.
+    fn is_waiting(&self, server: usize) -> bool {
+        self.pending.values().any(|(index, _)| *index == server)
+    }
+
+    /// Call the callbacks of the executions still waiting for their logs from the server with
+    /// that index (from all the servers if `None`), without the logs.
+    fn flush(&mut self, dag: &mut ExecutionDAG, server: Option<usize>) -> Result<(), Error> {
+        let uuids: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|(_, (index, _))| server.map_or(true, |server| server == *index))
+            .map(|(uuid, _)| *uuid)
+            .collect();
+        for uuid in uuids {
+            self.received(dag, uuid, None)?;
+        }
+        Ok(())
+    }
+}
+
+/// Something received from a server, during an evaluation.
+#[allow(clippy::large_enum_variant)]
+enum ServerEvent<C> {
+    /// The server with that index sent a message, other than `ProvideFile`.
+    Message(usize, ExecutorServerMessage),
+    /// The server with that index sent a file, with its uuid, whether it was generated successfully
+    /// and the chunks of its content, read while they are received.
+    File(usize, FileUuid, bool, C),
+    /// The connection with the server with that index is closed, with the reason.
+    Disconnected(usize, String),
+}
+
+/// Receive the next message from the server with that index. The content of a file has to be
+/// read from the returned event before receiving the next message.
+fn next_server_event(
+    index: usize,
+    receiver: &ChannelReceiver<ExecutorServerMessage>,
+) -> ServerEvent<ChannelFileIterator<'_, ExecutorServerMessage>> {
+    match receiver.recv() {
+        Ok(ExecutorServerMessage::ProvideFile(uuid, success)) => {
+            ServerEvent::File(index, uuid, success, ChannelFileIterator::new(receiver))
+        }
+        Ok(message) => ServerEvent::Message(index, message),
+        Err(e) => ServerEvent::Disconnected(index, e.root_cause().to_string()),
+    }
+}
+
+/// Receive the messages from a server, forwarding them to `events`. The chunks of the files are
+/// forwarded while they are received, and the next message is received only after the whole file,
+/// so that the messages of the server are never interleaved with the content of a file.
+fn receive_server_events(
+    index: usize,
+    receiver: ChannelReceiver<ExecutorServerMessage>,
+    events: crossbeam_channel::Sender<ServerEvent<crossbeam_channel::Receiver<Vec<u8>>>>,
+) {
+    loop {
+        let event = match next_server_event(index, &receiver) {
+            ServerEvent::File(index, uuid, success, chunks) => {
+                let (chunk_sender, chunk_receiver) =
+                    crossbeam_channel::bounded(FILE_CHUNKS_IN_FLIGHT);
+                if events
+                    .send(ServerEvent::File(index, uuid, success, chunk_receiver))
+                    .is_err()
+                {
+                    break;
+                }
+                // the whole file has to be received even if the chunks are not used anymore
+                for chunk in chunks {
+                    let _ = chunk_sender.send(chunk);
+                }
+                continue;
+            }
+            ServerEvent::Message(index, message) => ServerEvent::Message(index, message),
+            ServerEvent::Disconnected(index, cause) => {
+                let _ = events.send(ServerEvent::Disconnected(index, cause));
+                break;
+            }
+        };
+        if events.send(event).is_err() {
+            break;
+        }
+    }
+}
+
+/// Store the files provided by the client in the file store of an executor in the same process,
+/// so that it doesn't ask for them. The files already in the store are not read again.
+fn store_provided_files(
+    provided_files: &HashMap<FileUuid, ProvidedFile>,
+    file_store: &FileStore,
+) -> Result<Vec<FileStoreHandle>, Error> {
+    let mut handles = Vec::with_capacity(provided_files.len());
+    for file in provided_files.values() {
+        let handle = match file {
+            ProvidedFile::LocalFile {
+                local_path, key, ..
+            } => match file_store.get(key) {
+                Some(handle) => handle,
+                None => {
+                    let iterator = ReadFileIterator::new(local_path).with_context(|| {
+                        format!("Failed to read local file: {}", local_path.display())
+                    })?;
+                    file_store.store(key, iterator).with_context(|| {
+                        format!("Failed to store local file {}", local_path.display())
+                    })?
+                }
+            },
+            ProvidedFile::Content { content, key, .. } => match file_store.get(key) {
+                Some(handle) => handle,
+                None => file_store
+                    .store(key, vec![content.clone()])
+                    .context("Failed to store file content")?,
+            },
+        };
+        handles.push(handle);
+    }
+    Ok(handles)
+}
+
+/// Server is asking for a file, handle the request sending the local file or the provided content
+/// with the encoding requested by the server. Note that this will trigger a protocol change for
+/// sending the file, no messages should be sent meanwhile.
 fn handle_server_ask_file(
     uuid: FileUuid,
+    encoding: FileEncoding,
     provided_files: &HashMap<FileUuid, ProvidedFile>,
     sender: &ChannelSender<ExecutorClientMessage>,
 ) -> Result<(), Error> {
@@ -310,7 +753,7 @@ fn handle_server_ask_file(
             sender
                 .send(ExecutorClientMessage::ProvideFile(uuid, key.clone()))
                 .context("Failed to send ExecutorClientMessage::ProvideFile")?;
-            ChannelFileSender::send(local_path, sender).with_context(|| {
+            ChannelFileSender::send(local_path, sender, encoding).with_context(|| {
                 format!("Failed to send local file from {}", local_path.display())
             })?;
         }
@@ -318,13 +761,170 @@ fn handle_server_ask_file(
             sender
                 .send(ExecutorClientMessage::ProvideFile(uuid, key.clone()))
                 .context("Failed to send ExecutorClientMessage::ProvideFile")?;
-            ChannelFileSender::send_data(content.clone(), sender)
+            ChannelFileSender::send_data(content.clone(), sender, encoding)
                 .context("Failed to send file content")?;
         }
     }
     Ok(())
 }
 
+/// Call the callbacks of an execution after a `NotifyStart`, `NotifyDone` or `NotifySkip` from the
+/// server. The other messages are ignored.
+fn handle_execution_notification(
+    dag: &mut ExecutionDAG,
+    message: ExecutorServerMessage,
+) -> Result<(), Error> {
+    match message {
+        ExecutorServerMessage::NotifyStart(uuid, worker) => {
+            info!("Execution {} started on {}", uuid, worker);
+            if let Some(callbacks) = dag.execution_callbacks().get_mut(&uuid) {
+                for callback in callbacks.on_start.drain(..) {
+                    if let Err(e) = callback(worker) {
+                        warn!("Start callback for {} failed: {:?}", uuid, e);
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        ExecutorServerMessage::NotifyDone(uuid, mut result) => {
+            info!("Execution {} completed with {:?}", uuid, result);
+            call_tagged_callbacks(dag, &uuid, &mut result)?;
+            if let Some(callbacks) = dag.execution_callbacks().get_mut(&uuid) {
+                for callback in callbacks.on_done.drain(..) {
+                    if let Err(e) = callback(result.clone()) {
+                        warn!("Done callback for {} failed: {:?}", uuid, e);
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        ExecutorServerMessage::NotifySkip(uuid) => {
+            info!("Execution {} skipped", uuid);
+            if let Some(callbacks) = dag.execution_callbacks().get_mut(&uuid) {
+                for callback in callbacks.on_skip.drain(..) {
+                    if let Err(e) = callback() {
+                        warn!("Skip callback for {} failed: {:?}", uuid, e);
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Write the scheduling traces sent by the servers to the file asked by the DAG. The evaluation
+/// doesn't fail if the file cannot be written.
+fn write_trace(dag: &ExecutionDAG, traces: &[SchedulingTrace]) {
+    if let Some(path) = &dag.data.config.trace {
+        if let Err(e) = SchedulingTrace::write_chrome_trace(path, traces) {
+            warn!("Failed to write the scheduling trace: {:?}", e);
+        }
+    }
+}
+
+/// Send to the servers the executions the callbacks asked to skip, if any. Each server skips the
+/// ones of its part of the DAG.
+fn send_skip_requests(dag: &ExecutionDAG, senders: &[ChannelSender<ExecutorClientMessage>]) {
+    let executions = dag.skipper().take();
+    if executions.is_empty() {
+        return;
+    }
+    info!("Asking to skip {} executions", executions.len());
+    for sender in senders {
+        // This may fail if the server is done, but then there is nothing left to skip.
+        let _ = sender.send(ExecutorClientMessage::Skip(executions.clone()));
+    }
+}
+
+/// The error for an evaluation stopped because its output files are too big.
+fn output_size_limit_error(size: u64, limit: u64) -> Error {
+    anyhow!(
+        "The evaluation has been stopped: the produced files take at least {:.1}MiB, more than \
+        the limit of {:.1}MiB. Maybe a generator or a solution is printing too much?",
+        size as f64 / 1024.0 / 1024.0,
+        limit as f64 / 1024.0 / 1024.0
+    )
+}
+
+/// The error of an evaluation cancelled by another client.
+fn cancelled_error() -> Error {
+    anyhow!("The evaluation has been cancelled by an operator of the server")
+}
+
+/// Call the callbacks registered for the tag of the completed execution, if any.
+fn call_tagged_callbacks(
+    dag: &mut ExecutionDAG,
+    uuid: &ExecutionUuid,
+    result: &mut ExecutionResult,
+) -> Result<(), Error> {
+    let Some(callbacks) = dag.callbacks.as_mut() else {
+        return Ok(());
+    };
+    if callbacks.tag_callbacks.is_empty() {
+        return Ok(());
+    }
+    let execution = dag
+        .data
+        .execution_groups
+        .values()
+        .flat_map(|group| &group.executions)
+        .find(|exec| &exec.uuid == uuid);
+    let Some(execution) = execution else {
+        return Ok(());
+    };
+    let Some(tag_callbacks) = execution
+        .tag
+        .as_ref()
+        .and_then(|tag| callbacks.tag_callbacks.get_mut(tag))
+    else {
+        return Ok(());
+    };
+    for callback in tag_callbacks.on_done.iter_mut() {
+        if let Err(e) = callback(execution, result) {
+            warn!("Tagged done callback for {} failed: {:?}", uuid, e);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Handle the list of files produced by the evaluation, sent by the server with `Done`. The files
+/// already present in the local store are processed immediately, the others are asked to the
+/// server. Returns the files asked to the server, with their keys.
+fn handle_server_done(
+    dag: &mut ExecutionDAG,
+    result: Vec<(FileUuid, FileStoreKey, bool)>,
+    file_store: &FileStore,
+    sender: &ChannelSender<ExecutorClientMessage>,
+) -> Result<Vec<(FileUuid, FileStoreKey)>, Error> {
+    let mut asked = vec![];
+    for (uuid, key, success) in result {
+        if let Some(handle) = file_store.get(&key) {
+            let iterator = ReadFileIterator::new(handle.path()).with_context(|| {
+                format!(
+                    "Failed to read produced file ({}) from the local storage",
+                    handle
+                )
+            })?;
+            process_provided_file(dag.file_callbacks(), uuid, success, iterator, None)
+                .with_context(|| {
+                    format!(
+                        "Failed to process produced file ({}) from the local storage",
+                        handle
+                    )
+                })?;
+        } else {
+            sender
+                .send(ExecutorClientMessage::AskFile(uuid, key.clone(), success))
+                .with_context(|| format!("Failed to ask for a completed file ({})", uuid))?;
+            asked.push((uuid, key));
+        }
+    }
+    Ok(asked)
+}
+
 /// Handle the server response to the status request.
 fn handle_server_status<F>(
     status: ExecutorStatus<Duration>,
@@ -341,10 +941,13 @@ where
                 uuid: worker.uuid,
                 name: worker.name,
                 current_job: worker.current_job.map(|status| status.into_system_time()),
+                transfers: worker.transfers,
             })
             .collect(),
         ready_execs: status.ready_execs,
         waiting_execs: status.waiting_execs,
+        transfers: status.transfers,
+        client_transfers: status.client_transfers,
     })
 }
 
@@ -392,30 +995,26 @@ fn process_provided_file<I: IntoIterator<Item = Vec<u8>>>(
                         (None, None)
                     } else {
                         info!("Writing file {} to {}", uuid, dest.display());
-                        let parent = dest.parent().ok_or_else(|| {
-                            anyhow!("Invalid file destination path: {}", dest.display())
-                        })?;
-                        std::fs::create_dir_all(parent).with_context(|| {
-                            format!(
-                                "Failed to create parent directory ({}) for {}",
-                                parent.display(),
-                                dest.display()
-                            )
-                        })?;
-                        let file = std::fs::File::create(dest).with_context(|| {
-                            format!("Failed to create file: {}", dest.display())
-                        })?;
-                        (Some(file), Some(dest.clone()))
+                        (Some(create_dest_file(dest)?), Some(dest.clone()))
                     }
                 }
             }
             _ => (None, None),
         };
+        let mut copies = Vec::new();
+        for dest in &callback.copy_to {
+            info!("Copying file {} to {}", uuid, dest.display());
+            copies.push((create_dest_file(dest)?, dest));
+        }
         for chunk in iterator {
             if let (Some(file), Some(dest)) = (&mut file, &dest) {
                 file.write_all(&chunk)
                     .with_context(|| format!("Failed to write chunk to {}", dest.display()))?;
             }
+            for (file, dest) in &mut copies {
+                file.write_all(&chunk)
+                    .with_context(|| format!("Failed to write chunk to {}", dest.display()))?;
+            }
             for get_content_chunked in &mut callback.get_content_chunked {
                 get_content_chunked(&chunk).context("Get content chunked callback failed")?;
             }
@@ -429,17 +1028,23 @@ fn process_provided_file<I: IntoIterator<Item = Vec<u8>>>(
             get_content_chunked(&[]).context("Get content chunked callback failed")?;
         }
         drop(file);
+        drop(copies);
         if let Some(write_to) = &callback.write_to {
-            if write_to.executable && write_to.dest.exists() {
-                let mut perm = std::fs::metadata(&write_to.dest)
-                    .with_context(|| {
-                        format!("Failed to get file metadata of {}", write_to.dest.display())
-                    })?
-                    .permissions();
-                perm.set_mode(0o755);
-                std::fs::set_permissions(&write_to.dest, perm).with_context(|| {
-                    format!("Failed to make {} executable", write_to.dest.display())
-                })?;
+            if write_to.executable {
+                // the copies of an executable are executable too
+                for dest in std::iter::once(&write_to.dest).chain(&callback.copy_to) {
+                    if !dest.exists() {
+                        continue;
+                    }
+                    let mut perm = std::fs::metadata(dest)
+                        .with_context(|| {
+                            format!("Failed to get file metadata of {}", dest.display())
+                        })?
+                        .permissions();
+                    perm.set_mode(0o755);
+                    std::fs::set_permissions(dest, perm)
+                        .with_context(|| format!("Failed to make {} executable", dest.display()))?;
+                }
             }
         }
 
@@ -452,3 +1057,20 @@ fn process_provided_file<I: IntoIterator<Item = Vec<u8>>>(
     }
     Ok(())
 }
+
+/// Create the file at `dest` for writing a file of the evaluation, creating also its parent
+/// directory.
+fn create_dest_file(dest: &Path) -> Result<std::fs::File, Error> {
+    let parent = dest
+        .parent()
+        .ok_or_else(|| anyhow!("Invalid file destination path: {}", dest.display()))?;
+    std::fs::create_dir_all(parent).with_context(|| {
+        format!(
+            "Failed to create parent directory ({}) for {}",
+            parent.display(),
+            dest.display()
+        )
+    })?;
+    std::fs::File::create(dest)
+        .with_context(|| format!("Failed to create file: {}", dest.display()))
+}
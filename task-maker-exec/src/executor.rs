@@ -3,20 +3,28 @@ use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
-use std::time::SystemTime;
+use std::time::{Instant, SystemTime};
 
 use anyhow::{anyhow, Context, Error};
 use ductile::{ChannelReceiver, ChannelSender};
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
+use uuid::Uuid;
 
 use task_maker_cache::Cache;
-use task_maker_dag::{ExecutionGroup, ExecutionUuid, FileUuid, ProvidedFile, WorkerUuid};
-use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey};
+use task_maker_dag::{
+    ExecutionDAG, ExecutionGroup, ExecutionUuid, FileUuid, ProvidedFile, WorkerUuid,
+};
+use task_maker_store::{ContentMismatchError, FileStore, FileStoreHandle, FileStoreKey};
 
+use crate::autoscaler::{Autoscaler, AutoscalerConfig, SchedulerLoad};
+#[cfg(feature = "chaos")]
+use crate::chaos::ChaosMonkey;
 use crate::check_dag::check_dag;
+use crate::executors::InProcessLink;
 use crate::proto::{
     ChannelFileIterator, ChannelFileSender, ExecutorClientMessage, ExecutorServerMessage,
+    FileEncoding,
 };
 use crate::scheduler::{
     ClientInfo, ClientUuid, Scheduler, SchedulerExecutorMessage, SchedulerExecutorMessageData,
@@ -25,6 +33,10 @@ use crate::scheduler::{
 use crate::worker_manager::{WorkerManager, WorkerManagerInMessage};
 use crate::WorkerConn;
 
+/// How many times a file provided by the client is uploaded before giving up, if its content
+/// doesn't match its key.
+const MAX_UPLOAD_ATTEMPTS: usize = 5;
+
 /// List of the _interesting_ files and executions, only the callbacks listed here will be called by
 /// the server. Every other callback is not sent to the client for performance reasons.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -39,6 +51,20 @@ pub struct ExecutionDAGWatchSet {
     pub urgent_files: HashSet<FileUuid>,
 }
 
+impl ExecutionDAGWatchSet {
+    /// The set of all the executions and files of the DAG that have at least a callback.
+    pub fn new(dag: &ExecutionDAG) -> ExecutionDAGWatchSet {
+        let Some(callbacks) = dag.callbacks.as_ref() else {
+            return ExecutionDAGWatchSet::default();
+        };
+        ExecutionDAGWatchSet {
+            executions: callbacks.execution_callbacks.keys().cloned().collect(),
+            files: callbacks.file_callbacks.keys().cloned().collect(),
+            urgent_files: callbacks.urgent_files.clone(),
+        }
+    }
+}
+
 /// A job that is sent to a worker, this should include all the information the worker needs to
 /// start the evaluation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +73,17 @@ pub struct WorkerJob {
     pub group: ExecutionGroup,
     /// The `FileStoreKey`s the worker has to know to start the evaluation.
     pub dep_keys: HashMap<FileUuid, FileStoreKey>,
+    /// Whether the worker should stream to the server the standard error of the executions while
+    /// they run.
+    pub stream_stderr: bool,
+}
+
+/// A batch of small jobs of the same client that is sent to a worker in a single message. The
+/// worker runs them one after the other and reports all the results together.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerJobBatch {
+    /// The jobs to run, in order.
+    pub jobs: Vec<WorkerJob>,
 }
 
 /// Information about the job the worker is currently doing.
@@ -58,6 +95,9 @@ pub struct WorkerCurrentJobStatus<T> {
     pub client: ClientInfo,
     /// Since when the job started.
     pub duration: T,
+    /// The last part of what the job wrote to its standard error, if it's streamed and the job
+    /// belongs to the client asking the status.
+    pub stderr: Option<String>,
 }
 
 impl WorkerCurrentJobStatus<Duration> {
@@ -68,8 +108,130 @@ impl WorkerCurrentJobStatus<Duration> {
             job: self.job,
             client: self.client,
             duration: SystemTime::now() - self.duration,
+            stderr: self.stderr,
+        }
+    }
+}
+
+/// Amount of file data exchanged by the `Executor`, or by a client, with one or more of its peers.
+/// The sizes are the ones of the data actually transferred, after the compression.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, TypeScriptify)]
+pub struct TransferStats {
+    /// Number of bytes sent.
+    pub sent_bytes: u64,
+    /// Number of bytes received.
+    pub received_bytes: u64,
+    /// Number of files sent.
+    pub sent_files: u64,
+    /// Number of files received.
+    pub received_files: u64,
+    /// Total time spent transferring the files, in microseconds.
+    pub transfer_time_us: u64,
+}
+
+impl TransferStats {
+    /// Account a file of `bytes` bytes sent in `time`.
+    pub fn add_sent(&mut self, bytes: u64, time: Duration) {
+        self.sent_bytes += bytes;
+        self.sent_files += 1;
+        self.transfer_time_us += time.as_micros() as u64;
+    }
+
+    /// Account a file of `bytes` bytes received in `time`.
+    pub fn add_received(&mut self, bytes: u64, time: Duration) {
+        self.received_bytes += bytes;
+        self.received_files += 1;
+        self.transfer_time_us += time.as_micros() as u64;
+    }
+
+    /// The same transfers, seen from the other side: what was sent is received and vice versa.
+    pub fn reversed(self) -> TransferStats {
+        TransferStats {
+            sent_bytes: self.received_bytes,
+            received_bytes: self.sent_bytes,
+            sent_files: self.received_files,
+            received_files: self.sent_files,
+            transfer_time_us: self.transfer_time_us,
         }
     }
+
+    /// The average speed of the transfers, in bytes per second. `None` if nothing has been
+    /// transferred yet.
+    pub fn bandwidth(&self) -> Option<f64> {
+        if self.transfer_time_us == 0 {
+            return None;
+        }
+        let bytes = (self.sent_bytes + self.received_bytes) as f64;
+        Some(bytes / (self.transfer_time_us as f64 / 1_000_000.0))
+    }
+}
+
+impl std::ops::AddAssign for TransferStats {
+    fn add_assign(&mut self, other: TransferStats) {
+        self.sent_bytes += other.sent_bytes;
+        self.received_bytes += other.received_bytes;
+        self.sent_files += other.sent_files;
+        self.received_files += other.received_files;
+        self.transfer_time_us += other.transfer_time_us;
+    }
+}
+
+/// The counters of the file transfers of the `Executor`, kept for each connected peer (either a
+/// client or a worker). They are shared between the threads that talk with the peers and the
+/// scheduler, which includes them in the `ExecutorStatus`.
+#[derive(Debug, Default)]
+pub(crate) struct TransferCounters {
+    /// The statistics of the connected peers and of the ones already gone.
+    inner: Mutex<TransferCountersInner>,
+}
+
+/// The actual content of `TransferCounters`, behind a single lock.
+#[derive(Debug, Default)]
+struct TransferCountersInner {
+    /// The statistics of each connected peer.
+    peers: HashMap<Uuid, TransferStats>,
+    /// The statistics of all the peers already disconnected, merged together.
+    disconnected: TransferStats,
+}
+
+impl TransferCounters {
+    /// Account a file of `bytes` bytes sent to `peer` in `time`.
+    pub fn sent(&self, peer: Uuid, bytes: u64, time: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        let stats = inner.peers.entry(peer).or_default();
+        stats.add_sent(bytes, time);
+    }
+
+    /// Account a file of `bytes` bytes received from `peer` in `time`.
+    pub fn received(&self, peer: Uuid, bytes: u64, time: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        let stats = inner.peers.entry(peer).or_default();
+        stats.add_received(bytes, time);
+    }
+
+    /// Forget about a peer that has disconnected, its statistics are still included in the total.
+    pub fn disconnected(&self, peer: Uuid) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(stats) = inner.peers.remove(&peer) {
+            inner.disconnected += stats;
+        }
+    }
+
+    /// The statistics of the transfers with a connected peer.
+    pub fn peer(&self, peer: Uuid) -> TransferStats {
+        let inner = self.inner.lock().unwrap();
+        inner.peers.get(&peer).copied().unwrap_or_default()
+    }
+
+    /// The statistics of the transfers with all the peers, including the disconnected ones.
+    pub fn total(&self) -> TransferStats {
+        let inner = self.inner.lock().unwrap();
+        let mut total = inner.disconnected;
+        for stats in inner.peers.values() {
+            total += *stats;
+        }
+        total
+    }
 }
 
 /// Status of a worker of an `Executor`.
@@ -81,6 +243,8 @@ pub struct ExecutorWorkerStatus<T> {
     pub name: String,
     /// What the worker is currently working on.
     pub current_job: Option<WorkerCurrentJobStatus<T>>,
+    /// The files exchanged with the worker.
+    pub transfers: TransferStats,
 }
 
 /// The current status of the `Executor`, this is sent to the user when the server status is asked.
@@ -95,8 +259,18 @@ pub struct ExecutorStatus<T> {
     pub ready_execs: usize,
     /// Number of executions waiting for dependencies.
     pub waiting_execs: usize,
+    /// The files exchanged by the server with all its clients and workers.
+    pub transfers: TransferStats,
+    /// The files exchanged by the client that asked for the status with the server, seen from the
+    /// client: the sent files are the ones the client sent to the server.
+    pub client_transfers: TransferStats,
 }
 
+/// The clients connected to the executor, with the channel for sending them messages and the
+/// encoding of the files exchanged with them.
+type ConnectedClients =
+    Arc<Mutex<HashMap<ClientUuid, (ChannelSender<ExecutorServerMessage>, FileEncoding)>>>;
+
 /// Message telling the executor that a new client connected or a new worker connected. The handling
 /// of the new peer is done by this executor.
 pub enum ExecutorInMessage {
@@ -109,6 +283,8 @@ pub enum ExecutorInMessage {
         sender: ChannelSender<ExecutorServerMessage>,
         /// A channel for received the messages from the client.
         receiver: ChannelReceiver<ExecutorClientMessage>,
+        /// The encoding of the files exchanged with the client.
+        file_encoding: FileEncoding,
     },
     /// A new worker has connected, the executor starts listening for the messages and will directly
     /// interact with it.
@@ -135,6 +311,13 @@ pub(crate) struct Executor {
     /// flag is set to false, after the first client is done the Scheduler, the WorkerManager and
     /// this Executor will exit.
     long_running: bool,
+    /// The hooks for scaling the workers, if any.
+    autoscaler: Option<AutoscalerConfig>,
+    /// The link with the clients in the same process, if any.
+    in_process: Option<InProcessLink>,
+    /// The failures to inject in the uploads of the clients, if running in chaos mode.
+    #[cfg(feature = "chaos")]
+    chaos: Option<Arc<ChaosMonkey>>,
 }
 
 impl Executor {
@@ -153,9 +336,32 @@ impl Executor {
             cache,
             receiver,
             long_running,
+            autoscaler: None,
+            in_process: None,
+            #[cfg(feature = "chaos")]
+            chaos: None,
         }
     }
 
+    /// Accept the DAGs handed over by the clients in the same process through `link`.
+    pub(crate) fn with_in_process(mut self, link: InProcessLink) -> Self {
+        self.in_process = Some(link);
+        self
+    }
+
+    /// Run the hooks of `config` for scaling the workers based on the load of the scheduler.
+    pub(crate) fn with_autoscaler(mut self, config: AutoscalerConfig) -> Self {
+        self.autoscaler = Some(config);
+        self
+    }
+
+    /// Inject the failures decided by `chaos` in the uploads of the clients.
+    #[cfg(feature = "chaos")]
+    pub(crate) fn with_chaos(mut self, chaos: Option<Arc<ChaosMonkey>>) -> Self {
+        self.chaos = chaos;
+        self
+    }
+
     /// Run the `Executor`, listening for client and worker connections. This will block until the
     /// first client is done (if `long_running` is false) or until the scheduler is stopped.
     pub fn run(self) -> Result<(), Error> {
@@ -164,19 +370,33 @@ impl Executor {
         let (sched_executor_tx, sched_executor_rx) = channel();
 
         let clients = Arc::new(Mutex::new(HashMap::new()));
+        let transfers = Arc::new(TransferCounters::default());
+        let load = Arc::new(SchedulerLoad::default());
+        let autoscaler = match self.autoscaler {
+            Some(config) => Some(Autoscaler::start(config, load.clone())?),
+            None => None,
+        };
 
+        // the groups waiting for the remote cache are looked up again as soon as it answers
+        let remote_listener = scheduler_tx.clone();
+        self.cache.set_remote_listener(move || {
+            let _ = remote_listener.send(SchedulerInMessage::RemoteCacheLookup);
+        });
         let scheduler = Scheduler::new(
             self.file_store.clone(),
             self.cache,
             scheduler_rx,
             sched_executor_tx,
             worker_manager_tx.clone(),
+            transfers.clone(),
+            load,
         );
         let worker_manager = WorkerManager::new(
             self.file_store.clone(),
             scheduler_tx.clone(),
             worker_manager_tx.clone(),
             worker_manager_rx,
+            transfers.clone(),
         );
         let scheduler_thread = thread::Builder::new()
             .name("Scheduler thread".to_string())
@@ -187,9 +407,12 @@ impl Executor {
             .spawn(move || worker_manager.run())
             .expect("Failed to spawn worker manager");
         let clients2 = clients.clone();
+        let transfers2 = transfers.clone();
         let scheduler_binder_thread = thread::Builder::new()
             .name("Scheduler binder".to_string())
-            .spawn(move || Executor::handle_scheduler_messages(sched_executor_rx, clients2))
+            .spawn(move || {
+                Executor::handle_scheduler_messages(sched_executor_rx, clients2, transfers2)
+            })
             .expect("Failed to spawn scheduler binder");
 
         while let Ok(message) = self.receiver.recv() {
@@ -198,14 +421,19 @@ impl Executor {
                     client,
                     sender,
                     receiver,
+                    file_encoding,
                 } => {
                     {
                         let mut clients = clients.lock().unwrap();
-                        clients.insert(client.uuid, sender.clone());
+                        clients.insert(client.uuid, (sender.clone(), file_encoding));
                     }
                     let scheduler = scheduler_tx.clone();
                     let file_store = self.file_store.clone();
+                    let transfers = transfers.clone();
                     let long_running = self.long_running;
+                    let in_process = self.in_process.clone();
+                    #[cfg(feature = "chaos")]
+                    let chaos = self.chaos.clone();
                     // handle the new client in a new thread called "Client Manager"
                     // FIXME: this thread is leaked, maybe we can join it as well
                     thread::Builder::new()
@@ -214,14 +442,21 @@ impl Executor {
                             client.name, client.uuid
                         ))
                         .spawn(move || -> Result<(), Error> {
+                            let client_uuid = client.uuid;
                             Executor::handle_client_messages(
                                 file_store,
                                 client,
                                 sender,
                                 receiver,
+                                file_encoding,
                                 scheduler.clone(),
+                                transfers.clone(),
+                                in_process,
+                                #[cfg(feature = "chaos")]
+                                chaos,
                             )
                             .unwrap();
+                            transfers.disconnected(client_uuid);
                             // if not in long running mode, the first client should tear down the
                             // executor. To do so it's just required to tell the scheduler to exit,
                             // it will bring down the WorkerManager and all should exit.
@@ -256,6 +491,9 @@ impl Executor {
             .join()
             .map_err(|e| anyhow!("Scheduler binder panicked: {:?}", e))?
             .context("Scheduler binder thread failed")?;
+        if let Some(autoscaler) = autoscaler {
+            autoscaler.stop();
+        }
         Ok(())
     }
 
@@ -263,18 +501,20 @@ impl Executor {
     #[allow(clippy::unnecessary_wraps)]
     fn handle_scheduler_messages(
         receiver: Receiver<SchedulerExecutorMessage>,
-        clients: Arc<Mutex<HashMap<ClientUuid, ChannelSender<ExecutorServerMessage>>>>,
+        clients: ConnectedClients,
+        transfers: Arc<TransferCounters>,
     ) -> Result<(), Error> {
         let mut ready_files: HashMap<ClientUuid, Vec<(FileUuid, FileStoreHandle, bool)>> =
             HashMap::new();
         while let Ok((client_uuid, message)) = receiver.recv() {
             let clients = clients.lock().unwrap();
-            let client = if let Some(client) = clients.get(&client_uuid) {
-                client
-            } else {
-                // ignore messages for a disconnected client
-                continue;
-            };
+            let (client, file_encoding) =
+                if let Some((client, encoding)) = clients.get(&client_uuid) {
+                    (client, *encoding)
+                } else {
+                    // ignore messages for a disconnected client
+                    continue;
+                };
             let message = match message {
                 SchedulerExecutorMessageData::ExecutionStarted { execution, worker } => {
                     ExecutorServerMessage::NotifyStart(execution, worker)
@@ -296,8 +536,12 @@ impl Executor {
                             client.send(ExecutorServerMessage::ProvideFile(file, successful))
                         {
                             warn!("Failed to send urgent file: {:?}", e);
-                        } else if let Err(e) = ChannelFileSender::send(handle.path(), client) {
-                            warn!("Failed to send urgent file content: {:?}", e);
+                        } else {
+                            let start = Instant::now();
+                            match ChannelFileSender::send(handle.path(), client, file_encoding) {
+                                Ok(bytes) => transfers.sent(client_uuid, bytes, start.elapsed()),
+                                Err(e) => warn!("Failed to send urgent file content: {:?}", e),
+                            }
                         }
                     } else {
                         ready_files
@@ -310,6 +554,19 @@ impl Executor {
                 SchedulerExecutorMessageData::Status { status } => {
                     ExecutorServerMessage::Status(status)
                 }
+                SchedulerExecutorMessageData::SandboxLogs { execution, logs } => {
+                    ExecutorServerMessage::SandboxLogs(execution, logs)
+                }
+                SchedulerExecutorMessageData::OutputSizeLimitExceeded { size, limit } => {
+                    ExecutorServerMessage::OutputSizeLimitExceeded(size, limit)
+                }
+                SchedulerExecutorMessageData::Cancelled => ExecutorServerMessage::Cancelled,
+                SchedulerExecutorMessageData::CancelOutcome { found } => {
+                    ExecutorServerMessage::CancelOutcome(found)
+                }
+                SchedulerExecutorMessageData::Trace { trace } => {
+                    ExecutorServerMessage::Trace(Box::new(trace))
+                }
                 SchedulerExecutorMessageData::EvaluationDone => {
                     let files = ready_files
                         .remove(&client_uuid)
@@ -329,15 +586,45 @@ impl Executor {
     }
 
     /// Handle the messages from a client.
+    #[allow(clippy::too_many_arguments)]
     fn handle_client_messages(
         file_store: Arc<FileStore>,
         client: ClientInfo,
         sender: ChannelSender<ExecutorServerMessage>,
         receiver: ChannelReceiver<ExecutorClientMessage>,
+        file_encoding: FileEncoding,
         scheduler: Sender<SchedulerInMessage>,
+        transfers: Arc<TransferCounters>,
+        in_process: Option<InProcessLink>,
+        #[cfg(feature = "chaos")] chaos: Option<Arc<ChaosMonkey>>,
     ) -> Result<(), Error> {
         let mut scheduler = Some(scheduler);
+        // the provided files asked to the client, grouped by key: files with the same content are
+        // asked (and uploaded) only once.
+        let mut asked_files: HashMap<FileStoreKey, Vec<FileUuid>> = HashMap::new();
+        // how many times the content of each provided file didn't match its key
+        let mut failed_uploads: HashMap<FileStoreKey, usize> = HashMap::new();
         while let Ok(message) = receiver.recv() {
+            // the DAGs of the clients in the same process are handed over in memory
+            let message = match message {
+                ExecutorClientMessage::EvaluateInProcess(id) => {
+                    match in_process.as_ref().and_then(|link| link.take(id)) {
+                        Some((dag, callbacks)) => {
+                            ExecutorClientMessage::Evaluate { dag, callbacks }
+                        }
+                        None => {
+                            warn!("The DAG {} is not available in this process", id);
+                            sender
+                                .send(ExecutorServerMessage::Error(
+                                    "The DAG is not available to this server".into(),
+                                ))
+                                .context("Failed to send Error message to client")?;
+                            break;
+                        }
+                    }
+                }
+                message => message,
+            };
             match message {
                 ExecutorClientMessage::Evaluate { dag, callbacks } => {
                     if let Err(e) = check_dag(&dag, &callbacks) {
@@ -350,7 +637,8 @@ impl Executor {
                         trace!("DAG looks valid!");
                     }
                     // for each file marked as provided check if a local copy is present, otherwise
-                    // ask the client to send it.
+                    // ask the client to send it. The DAG contains the keys of all the provided
+                    // files, so only the missing ones are uploaded.
                     let mut ready_files = Vec::new();
                     for (uuid, file) in dag.provided_files.iter() {
                         let key = match file {
@@ -361,9 +649,13 @@ impl Executor {
                         if let Some(handle) = handle {
                             ready_files.push((*uuid, handle));
                         } else {
-                            sender
-                                .send(ExecutorServerMessage::AskFile(*uuid))
-                                .with_context(|| format!("Failed to send AskFile({})", uuid))?;
+                            let waiting = asked_files.entry(key.clone()).or_default();
+                            waiting.push(*uuid);
+                            if waiting.len() == 1 {
+                                sender
+                                    .send(ExecutorServerMessage::AskFile(*uuid, file_encoding))
+                                    .with_context(|| format!("Failed to send AskFile({})", uuid))?;
+                            }
                         }
                     }
                     // tell the scheduler that a new DAG is ready to be executed.
@@ -394,20 +686,60 @@ impl Executor {
                     info!("Client provided file {}", uuid);
                     // the client provided a file that was not present locally, store it and tell
                     // the scheduler that it's now ready.
-                    let handle = file_store
-                        .store(&key, ChannelFileIterator::new(&receiver))
-                        .with_context(|| {
-                            format!("Failed to store client provided file {} ({})", uuid, key)
-                        })?;
-                    scheduler
-                        .as_ref()
-                        .ok_or_else(|| anyhow!("Stopped execution"))?
-                        .send(SchedulerInMessage::FileReady {
-                            client: client.uuid,
-                            uuid,
-                            handle,
-                        })
-                        .context("Failed to send FileReady to the scheduler")?;
+                    let start = Instant::now();
+                    let mut iterator = ChannelFileIterator::new(&receiver);
+                    #[cfg(feature = "chaos")]
+                    let stored = match &chaos {
+                        Some(chaos) => file_store
+                            .store(&key, (&mut iterator).map(|chunk| chaos.corrupt(chunk))),
+                        None => file_store.store(&key, &mut iterator),
+                    };
+                    #[cfg(not(feature = "chaos"))]
+                    let stored = file_store.store(&key, &mut iterator);
+                    transfers.received(client.uuid, iterator.received_bytes(), start.elapsed());
+                    let handle = match stored {
+                        Ok(handle) => handle,
+                        // the file has been corrupted during the upload, ask it again
+                        Err(e) if e.is::<ContentMismatchError>() => {
+                            let failures = failed_uploads.entry(key.clone()).or_default();
+                            *failures += 1;
+                            if *failures < MAX_UPLOAD_ATTEMPTS {
+                                warn!("The upload of file {} is corrupted, asking it again", uuid);
+                                sender
+                                    .send(ExecutorServerMessage::AskFile(uuid, file_encoding))
+                                    .with_context(|| format!("Failed to send AskFile({})", uuid))?;
+                                continue;
+                            }
+                            let error = format!(
+                                "The upload of file {} has been corrupted {} times",
+                                uuid, MAX_UPLOAD_ATTEMPTS
+                            );
+                            warn!("{}", error);
+                            sender
+                                .send(ExecutorServerMessage::Error(error))
+                                .context("Failed to send Error message to client")?;
+                            break;
+                        }
+                        Err(e) => {
+                            return Err(e.context(format!(
+                                "Failed to store client provided file {} ({})",
+                                uuid, key
+                            )))
+                        }
+                    };
+                    // all the provided files with the same content are now ready
+                    let uuids = asked_files.remove(&key).unwrap_or_else(|| vec![uuid]);
+                    for uuid in uuids {
+                        scheduler
+                            .as_ref()
+                            .ok_or_else(|| anyhow!("Stopped execution"))?
+                            .send(SchedulerInMessage::FileReady {
+                                client: client.uuid,
+                                uuid,
+                                handle: handle.clone(),
+                            })
+                            .context("Failed to send FileReady to the scheduler")?;
+                    }
                 }
                 ExecutorClientMessage::AskFile(uuid, key, success) => {
                     info!("Client asking file {:?}", key);
@@ -417,9 +749,12 @@ impl Executor {
                         sender
                             .send(ExecutorServerMessage::ProvideFile(uuid, success))
                             .context("Failed to send ProvideFile to the client")?;
-                        ChannelFileSender::send(handle.path(), &sender).with_context(|| {
-                            format!("Failed to send file {} to the client", handle)
-                        })?;
+                        let start = Instant::now();
+                        let bytes = ChannelFileSender::send(handle.path(), &sender, file_encoding)
+                            .with_context(|| {
+                                format!("Failed to send file {} to the client", handle)
+                            })?;
+                        transfers.sent(client.uuid, bytes, start.elapsed());
                     } else {
                         sender
                             .send(ExecutorServerMessage::Error(format!(
@@ -438,6 +773,32 @@ impl Executor {
                         });
                     }
                 }
+                ExecutorClientMessage::AskSandboxLogs(execution) => {
+                    info!("Client asking the sandbox logs of {}", execution);
+                    if let Some(scheduler) = scheduler.as_ref() {
+                        scheduler
+                            .send(SchedulerInMessage::AskSandboxLogs {
+                                client: client.uuid,
+                                execution,
+                            })
+                            .context("Failed to send AskSandboxLogs to the scheduler")?;
+                    } else {
+                        // the evaluation has been stopped, the logs won't be fetched
+                        sender
+                            .send(ExecutorServerMessage::SandboxLogs(execution, None))
+                            .context("Failed to send SandboxLogs to the client")?;
+                    }
+                }
+                ExecutorClientMessage::Skip(executions) => {
+                    info!("Client asking to skip {} executions", executions.len());
+                    // the scheduler may be gone, but then there is nothing left to skip
+                    if let Some(scheduler) = scheduler.as_ref() {
+                        let _ = scheduler.send(SchedulerInMessage::SkipExecutions {
+                            client: client.uuid,
+                            executions,
+                        });
+                    }
+                }
                 ExecutorClientMessage::Stop => {
                     info!("Client asking to stop");
                     if let Some(scheduler) = scheduler.take() {
@@ -448,6 +809,31 @@ impl Executor {
                             .context("Failed to send ClientDisconnected to the scheduler")?
                     }
                 }
+                ExecutorClientMessage::Cancel(target) => {
+                    info!("Client asking to cancel the evaluation of {}", target);
+                    if let Some(scheduler) = scheduler.as_ref() {
+                        scheduler
+                            .send(SchedulerInMessage::CancelEvaluation {
+                                client: client.uuid,
+                                target,
+                            })
+                            .context("Failed to send CancelEvaluation to the scheduler")?;
+                    } else {
+                        sender
+                            .send(ExecutorServerMessage::CancelOutcome(false))
+                            .context("Failed to send CancelOutcome to the client")?;
+                    }
+                }
+                // already replaced by Evaluate above, don't trust the peer anyway
+                ExecutorClientMessage::EvaluateInProcess(id) => {
+                    warn!("Unexpected in-process DAG {}", id);
+                    sender
+                        .send(ExecutorServerMessage::Error(
+                            "The DAG is not available to this server".into(),
+                        ))
+                        .context("Failed to send Error message to client")?;
+                    break;
+                }
             }
         }
         if let Some(scheduler) = scheduler.take() {
@@ -460,3 +846,59 @@ impl Executor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_counters_disconnected() {
+        let counters = TransferCounters::default();
+        let (client, worker) = (Uuid::new_v4(), Uuid::new_v4());
+        counters.sent(client, 100, Duration::from_millis(1));
+        counters.received(worker, 50, Duration::from_millis(1));
+        counters.disconnected(client);
+        assert_eq!(counters.inner.lock().unwrap().peers.len(), 1);
+        assert_eq!(counters.peer(client), TransferStats::default());
+        let total = counters.total();
+        assert_eq!(total.sent_bytes, 100);
+        assert_eq!(total.received_bytes, 50);
+        assert_eq!(total.sent_files + total.received_files, 2);
+        assert_eq!(total.transfer_time_us, 2000);
+    }
+
+    #[test]
+    fn test_remote_evaluate_in_process() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let file_store = Arc::new(FileStore::new(tmpdir.path(), 1000, 1000).unwrap());
+        let client = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "remote".into(),
+        };
+        let (client_sender, receiver) = ductile::new_local_channel();
+        let (sender, client_receiver) = ductile::new_local_channel();
+        let (scheduler, _scheduler_receiver) = channel();
+        client_sender
+            .send(ExecutorClientMessage::EvaluateInProcess(Uuid::new_v4()))
+            .unwrap();
+        // a remote client cannot refer to the DAGs handed over in memory, the server must reply
+        // with an error and drop the connection instead of panicking
+        Executor::handle_client_messages(
+            file_store,
+            client,
+            sender,
+            receiver,
+            FileEncoding::Plain,
+            scheduler,
+            Arc::new(TransferCounters::default()),
+            None,
+            #[cfg(feature = "chaos")]
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            client_receiver.recv().unwrap(),
+            ExecutorServerMessage::Error(_)
+        ));
+    }
+}
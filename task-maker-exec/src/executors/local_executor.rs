@@ -1,6 +1,7 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::mpsc::{channel, Sender};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 
 use anyhow::{anyhow, Context, Error};
@@ -8,10 +9,13 @@ use ductile::{ChannelReceiver, ChannelSender};
 use uuid::Uuid;
 
 use task_maker_cache::Cache;
+use task_maker_dag::{ExecutionDAGData, ToolchainVersions};
 use task_maker_store::FileStore;
 
-use crate::executor::{Executor, ExecutorInMessage};
-use crate::proto::{ExecutorClientMessage, ExecutorServerMessage};
+#[cfg(feature = "chaos")]
+use crate::chaos::{ChaosConfig, ChaosMonkey, ChaosSandboxRunner};
+use crate::executor::{ExecutionDAGWatchSet, Executor, ExecutorInMessage};
+use crate::proto::{ExecutorClientMessage, ExecutorServerMessage, FileEncoding};
 use crate::sandbox_runner::SandboxRunner;
 use crate::scheduler::ClientInfo;
 use crate::Worker;
@@ -26,45 +30,146 @@ pub struct LocalExecutor {
     executor_tx: Sender<ExecutorInMessage>,
     /// Join handle of the spawned workers.
     workers: Vec<JoinHandle<Result<(), Error>>>,
+    /// The link with the clients in the same process.
+    in_process: InProcessLink,
+}
+
+/// The link between a [`LocalExecutor`] and a client in the same process, for evaluating a DAG
+/// without serializing it through the channels.
+///
+/// The client hands the DAG over in memory and sends only its id, and the files it provides are
+/// written directly in the file store of the executor instead of being uploaded.
+#[derive(Debug, Clone)]
+pub struct InProcessLink {
+    /// The file store of the executor.
+    file_store: Arc<FileStore>,
+    /// The DAGs handed over by the client, not yet taken by the executor.
+    dags: Arc<Mutex<HashMap<Uuid, InProcessDag>>>,
+}
+
+/// A DAG handed over to the executor, with the executions and files the client keeps track of.
+type InProcessDag = (Box<ExecutionDAGData>, Box<ExecutionDAGWatchSet>);
+
+impl InProcessLink {
+    /// Make a new link with the executor that uses that file store.
+    fn new(file_store: Arc<FileStore>) -> InProcessLink {
+        InProcessLink {
+            file_store,
+            dags: Default::default(),
+        }
+    }
+
+    /// The file store of the executor.
+    pub fn file_store(&self) -> &Arc<FileStore> {
+        &self.file_store
+    }
+
+    /// Hand a DAG over to the executor, returning the id to send it.
+    pub(crate) fn put(&self, dag: InProcessDag) -> Uuid {
+        let id = Uuid::new_v4();
+        self.dags.lock().unwrap().insert(id, dag);
+        id
+    }
+
+    /// Take the DAG with that id, if it has been handed over.
+    pub(crate) fn take(&self, id: Uuid) -> Option<InProcessDag> {
+        self.dags.lock().unwrap().remove(&id)
+    }
 }
 
 impl LocalExecutor {
     /// Make a new [`LocalExecutor`] based on a [`FileStore`] and ready to spawn that number of
-    /// workers using a [`Cache`].
+    /// workers using a [`Cache`]. `toolchains` are the versions of the toolchains of this machine.
     pub fn new<P: Into<PathBuf>, R>(
         file_store: Arc<FileStore>,
         cache: Cache,
         num_workers: usize,
         sandbox_path: P,
         sandbox_runner: R,
+        toolchains: ToolchainVersions,
     ) -> Result<LocalExecutor, Error>
     where
         R: SandboxRunner + 'static,
     {
-        let sandbox_path = sandbox_path.into();
-        let (executor_tx, executor_rx) = channel();
-        let executor = Executor::new(file_store.clone(), cache, executor_rx, false);
+        LocalExecutor::spawn(
+            file_store,
+            cache,
+            num_workers,
+            sandbox_path.into(),
+            Arc::new(sandbox_runner),
+            toolchains,
+            #[cfg(feature = "chaos")]
+            None,
+        )
+    }
+
+    /// Make a new [`LocalExecutor`] like [`LocalExecutor::new`], that injects the failures of
+    /// `chaos` in its workers.
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos<P: Into<PathBuf>, R>(
+        file_store: Arc<FileStore>,
+        cache: Cache,
+        num_workers: usize,
+        sandbox_path: P,
+        sandbox_runner: R,
+        toolchains: ToolchainVersions,
+        chaos: ChaosConfig,
+    ) -> Result<LocalExecutor, Error>
+    where
+        R: SandboxRunner + 'static,
+    {
+        let chaos = Arc::new(ChaosMonkey::new(chaos, num_workers));
+        LocalExecutor::spawn(
+            file_store,
+            cache,
+            num_workers,
+            sandbox_path.into(),
+            Arc::new(ChaosSandboxRunner::new(sandbox_runner, chaos.clone())),
+            toolchains,
+            Some(chaos),
+        )
+    }
 
-        // share the runner for all the workers
-        let sandbox_runner = Arc::new(sandbox_runner);
+    /// Spawn the workers and connect them to a new executor.
+    fn spawn(
+        file_store: Arc<FileStore>,
+        cache: Cache,
+        num_workers: usize,
+        sandbox_path: PathBuf,
+        sandbox_runner: Arc<dyn SandboxRunner>,
+        toolchains: ToolchainVersions,
+        #[cfg(feature = "chaos")] chaos: Option<Arc<ChaosMonkey>>,
+    ) -> Result<LocalExecutor, Error> {
+        let (executor_tx, executor_rx) = channel();
+        let in_process = InProcessLink::new(file_store.clone());
+        let executor = Executor::new(file_store.clone(), cache, executor_rx, false)
+            .with_in_process(in_process.clone());
+        #[cfg(feature = "chaos")]
+        let executor = executor.with_chaos(chaos.clone());
+        let toolchains = Arc::new(toolchains);
 
         info!("Spawning {} workers", num_workers);
         let mut workers = vec![];
         // spawn the workers and connect them to the executor
         for i in 0..num_workers {
             let runner = sandbox_runner.clone();
+            // the thread name doesn't include the uuid of the worker, so that it's the same across
+            // different runs.
+            let worker_name = format!("Local worker {}", i);
             let (worker, conn) = Worker::new(
-                format!("Local worker {}", i),
+                worker_name.clone(),
                 file_store.clone(),
                 #[allow(clippy::needless_borrow)]
                 &sandbox_path,
                 runner,
+                toolchains.clone(),
             )
             .context("Failed to start local worker")?;
+            #[cfg(feature = "chaos")]
+            let worker = worker.with_chaos(chaos.clone());
             executor_tx
                 .send(ExecutorInMessage::WorkerConnected { worker: conn })
                 .map_err(|e| anyhow!("Failed to send WorkerConnected: {:?}", e))?;
-            let worker_name = format!("Worker {}", worker);
             workers.push(
                 thread::Builder::new()
                     .name(worker_name.clone())
@@ -80,9 +185,16 @@ impl LocalExecutor {
             executor,
             executor_tx,
             workers,
+            in_process,
         })
     }
 
+    /// The link for evaluating the DAGs of a client in the same process, with
+    /// [`ExecutorClient::evaluate_in_process`](crate::ExecutorClient::evaluate_in_process).
+    pub fn in_process_link(&self) -> InProcessLink {
+        self.in_process.clone()
+    }
+
     /// Starts the Executor spawning the workers on new threads and blocking on the `Executor`
     /// thread.
     ///
@@ -103,6 +215,7 @@ impl LocalExecutor {
                 },
                 sender,
                 receiver,
+                file_encoding: FileEncoding::Plain,
             })
             .map_err(|e| anyhow!("Failed to send ClientConnected: {:?}", e))?;
 
@@ -22,7 +22,7 @@
 //! let cache = Cache::new(path).unwrap();
 //! let num_cores = 4;
 //! # let sandbox_runner = Arc::new(SuccessSandboxRunner::default());
-//! let mut executor = LocalExecutor::new(Arc::new(store), cache, num_cores, path, sandbox_runner).expect("failed to start executor");
+//! let mut executor = LocalExecutor::new(Arc::new(store), cache, num_cores, path, sandbox_runner, Default::default()).expect("failed to start executor");
 //! // the communication channels for the client
 //! let (tx, rx_remote) = new_local_channel();
 //! let (tx_remote, rx) = new_local_channel();
@@ -6,38 +6,141 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use task_maker_cache::Cache;
+use task_maker_dag::{ResourceTags, ToolchainVersions};
 use task_maker_store::FileStore;
 
+use crate::autoscaler::AutoscalerConfig;
 use crate::executor::{Executor, ExecutorInMessage};
+use crate::proto::FileEncoding;
 use crate::scheduler::ClientInfo;
 use crate::{derive_key_from_password, WorkerConn};
 use anyhow::{anyhow, Context, Error};
 
 /// Version of task-maker
 const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Version of the protocol between the server and its clients and workers. Peers with a different
+/// protocol version cannot talk with the server, it has to be bumped every time a message of the
+/// protocol changes.
+pub const PROTOCOL_VERSION: u32 = 23;
 
 /// An executor that accepts remote connections from clients and workers.
 pub struct RemoteExecutor {
     file_store: Arc<FileStore>,
+    autoscaler: Option<AutoscalerConfig>,
+}
+
+/// The optional features of the protocol supported by a peer, as a bitmap. The features used in a
+/// connection are the ones supported by both the server and the client (or the worker); the
+/// unknown bits sent by newer peers are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+    /// The files can be compressed.
+    pub const COMPRESSION: Capabilities = Capabilities(1);
+
+    /// All the capabilities supported by this version of task-maker.
+    pub fn all() -> Capabilities {
+        Capabilities::COMPRESSION
+    }
+
+    /// Whether all the capabilities in `other` are present.
+    pub fn contains(self, other: Capabilities) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Remove the capabilities in `other`.
+    pub fn remove(&mut self, other: Capabilities) {
+        self.0 &= !other.0;
+    }
+
+    /// The capabilities present in both `self` and `other`.
+    pub fn intersection(self, other: Capabilities) -> Capabilities {
+        Capabilities(self.0 & other.0)
+    }
+}
+
+/// How a client or a worker introduces itself to the server it connects to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Welcome {
+    /// The version of the protocol spoken by the client or the worker.
+    pub protocol_version: u32,
+    /// The name of the client or of the worker.
+    pub name: String,
+    /// The version of task-maker of the client or of the worker.
+    pub version: String,
+    /// The optional features supported by the client or the worker.
+    pub capabilities: Capabilities,
+    /// The speed of the machine of the worker relative to the reference machine, measured at
+    /// startup. Always `None` for the clients.
+    pub speed_factor: Option<f64>,
+    /// The versions of the toolchains of the worker, probed at startup. Always empty for the
+    /// clients.
+    pub toolchains: ToolchainVersions,
+    /// The tags of the resources provided by the worker. Always empty for the clients.
+    pub resources: ResourceTags,
+    /// The pool of workers the worker joins, `None` for the default one. Always `None` for the
+    /// clients, which select the pool in the configuration of their DAGs.
+    pub pool: Option<String>,
+}
+
+impl Welcome {
+    /// The welcome of a client with that name, supporting all the capabilities of this version of
+    /// task-maker.
+    pub fn client<S: Into<String>>(name: S) -> Welcome {
+        Welcome {
+            protocol_version: PROTOCOL_VERSION,
+            name: name.into(),
+            version: VERSION.into(),
+            capabilities: Capabilities::all(),
+            speed_factor: None,
+            toolchains: Default::default(),
+            resources: Default::default(),
+            pool: None,
+        }
+    }
+
+    /// The welcome of a worker with that name, supporting all the capabilities of this version of
+    /// task-maker.
+    pub fn worker<S: Into<String>>(
+        name: S,
+        speed_factor: f64,
+        toolchains: ToolchainVersions,
+        resources: ResourceTags,
+        pool: Option<String>,
+    ) -> Welcome {
+        Welcome {
+            speed_factor: Some(speed_factor),
+            toolchains,
+            resources,
+            pool,
+            ..Welcome::client(name)
+        }
+    }
+
+    /// Don't compress the files exchanged with the server.
+    pub fn without_compression(mut self) -> Welcome {
+        self.capabilities.remove(Capabilities::COMPRESSION);
+        self
+    }
 }
 
 /// Message sent only by remote clients and workers for connecting to the server.
 #[derive(Debug, Serialize, Deserialize)]
 pub enum RemoteEntityMessage {
-    /// Tell the remote executor the name of the client or of the worker.
-    Welcome {
-        /// The name of the client or of the worker.
-        name: String,
-        /// The required version of task-maker.
-        version: String,
-    },
+    /// Tell the remote executor who the client or the worker is.
+    Welcome(Welcome),
 }
 
 /// Message sent only by the server in response of a `RemoteEntityMessage`.
+///
+/// This message should never change, so that a peer with an incompatible protocol is still able to
+/// read the reason of the rejection.
 #[derive(Debug, Serialize, Deserialize)]
 pub enum RemoteEntityMessageResponse {
-    /// The server accepted the connection of the client, the communication can continue.
-    Accepted,
+    /// The server accepted the connection of the client, the communication can continue using the
+    /// capabilities supported by both the sides.
+    Accepted(Capabilities),
     /// The server rejected the connection of the client, the channel will be closed.
     Rejected(String),
 }
@@ -45,10 +148,21 @@ pub enum RemoteEntityMessageResponse {
 impl RemoteExecutor {
     /// Make a new `RemoteExecutor`.
     pub fn new(file_store: Arc<FileStore>) -> Self {
-        RemoteExecutor { file_store }
+        RemoteExecutor {
+            file_store,
+            autoscaler: None,
+        }
+    }
+
+    /// Run the hooks of `config` for starting and stopping the workers based on the load.
+    pub fn with_autoscaler(mut self, config: AutoscalerConfig) -> Self {
+        self.autoscaler = Some(config);
+        self
     }
 
     /// Start the executor binding the TCP sockets and waiting for clients and workers connections.
+    ///
+    /// Only the `capabilities` that are supported also by the peer are used in a connection.
     pub fn start<S: Into<String>, S2: Into<String>>(
         self,
         bind_client_addr: S,
@@ -56,24 +170,35 @@ impl RemoteExecutor {
         client_password: Option<String>,
         worker_password: Option<String>,
         cache: Cache,
+        capabilities: Capabilities,
     ) -> Result<(), Error> {
         let file_store = self.file_store;
         let bind_client_addr = bind_client_addr.into();
         let bind_worker_addr = bind_worker_addr.into();
 
         let (executor_tx, executor_rx) = channel();
-        let executor = Executor::new(file_store, cache, executor_rx, true);
+        let mut executor = Executor::new(file_store, cache, executor_rx, true);
+        if let Some(config) = self.autoscaler {
+            executor = executor.with_autoscaler(config);
+        }
 
         let client_executor_tx = executor_tx.clone();
         let client_listener_thread = std::thread::Builder::new()
             .name("Client listener".to_string())
             .spawn(move || {
-                Self::client_listener(client_password, bind_client_addr, client_executor_tx)
+                Self::client_listener(
+                    client_password,
+                    bind_client_addr,
+                    client_executor_tx,
+                    capabilities,
+                )
             })
             .context("Cannot spawn client listener thread")?;
         let worker_listener_thread = std::thread::Builder::new()
             .name("Worker listener".to_string())
-            .spawn(move || Self::worker_listener(worker_password, bind_worker_addr, executor_tx))
+            .spawn(move || {
+                Self::worker_listener(worker_password, bind_worker_addr, executor_tx, capabilities)
+            })
             .context("Cannot spawn worker listener thread")?;
 
         executor.run()?;
@@ -93,6 +218,7 @@ impl RemoteExecutor {
         client_password: Option<String>,
         bind_client_addr: String,
         client_executor_tx: Sender<ExecutorInMessage>,
+        capabilities: Capabilities,
     ) -> Result<(), Error> {
         let server = if let Some(path) = bind_client_addr.strip_prefix("unix://") {
             ChannelServer::bind_unix(path)
@@ -126,24 +252,23 @@ impl RemoteExecutor {
                 .unwrap_or_else(|| "(local)".into());
             info!("Client connected from {}", addr);
             let uuid = Uuid::new_v4();
-            let name = if let Ok(RemoteEntityMessage::Welcome { name, version }) = receiver.recv() {
-                if !validate_welcome(&addr, &name, version, &sender, "Client") {
-                    continue;
-                }
-                name
-            } else {
-                warn!(
-                    "Client at {} has not sent the correct welcome message!",
-                    addr
-                );
+            let Some(welcome) =
+                handle_welcome(&addr, receiver.recv(), &sender, capabilities, "Client")
+            else {
                 continue;
             };
-            let client = ClientInfo { uuid, name };
+            let file_encoding = file_encoding(welcome.capabilities);
+            info!("Client {} has evaluation id {}", welcome.name, uuid);
+            let client = ClientInfo {
+                uuid,
+                name: welcome.name,
+            };
             client_executor_tx
                 .send(ExecutorInMessage::ClientConnected {
                     client,
                     sender: sender.change_type(),
                     receiver: receiver.change_type(),
+                    file_encoding,
                 })
                 .map_err(|e| anyhow!("Executor is gone: {:?}", e))?;
         }
@@ -154,6 +279,7 @@ impl RemoteExecutor {
         worker_password: Option<String>,
         bind_worker_addr: String,
         executor_tx: Sender<ExecutorInMessage>,
+        capabilities: Capabilities,
     ) -> Result<(), Error> {
         let server = if let Some(path) = bind_worker_addr.strip_prefix("unix://") {
             ChannelServer::bind_unix(path)
@@ -186,23 +312,22 @@ impl RemoteExecutor {
                 .unwrap_or_else(|| "(local)".into());
             info!("Worker connected from {}", addr);
             let uuid = Uuid::new_v4();
-            let name = if let Ok(RemoteEntityMessage::Welcome { name, version }) = receiver.recv() {
-                if !validate_welcome(&addr, &name, version, &sender, "Worker") {
-                    continue;
-                }
-                name
-            } else {
-                warn!(
-                    "Worker at {} has not sent the correct welcome message!",
-                    addr
-                );
+            let Some(welcome) =
+                handle_welcome(&addr, receiver.recv(), &sender, capabilities, "Worker")
+            else {
                 continue;
             };
             let worker = WorkerConn {
                 uuid,
-                name,
+                name: welcome.name,
                 sender: sender.change_type(),
                 receiver: receiver.change_type(),
+                file_encoding: file_encoding(welcome.capabilities),
+                speed_factor: welcome.speed_factor,
+                toolchains: Arc::new(welcome.toolchains),
+                resources: Some(Arc::new(welcome.resources)),
+                pool: welcome.pool,
+                machine: worker_machine(&addr),
             };
             executor_tx
                 .send(ExecutorInMessage::WorkerConnected { worker })
@@ -212,25 +337,75 @@ impl RemoteExecutor {
     }
 }
 
-fn validate_welcome(
+/// Check the welcome message of a client or of a worker, replying with the outcome of the check.
+/// Returns the welcome of the peer if it's accepted, with the capabilities used in the connection:
+/// the ones supported by both the sides.
+fn handle_welcome(
     addr: &str,
-    name: &str,
-    version: String,
+    message: Result<RemoteEntityMessage, Error>,
     sender: &ChannelSender<RemoteEntityMessageResponse>,
+    capabilities: Capabilities,
     client: &str,
-) -> bool {
-    if version != VERSION {
+) -> Option<Welcome> {
+    let mut welcome = match message {
+        Ok(RemoteEntityMessage::Welcome(welcome)) => welcome,
+        Err(e) => {
+            // a peer with a different protocol may not be able to send a welcome message that
+            // this server understands
+            warn!(
+                "{} at {} has not sent a valid welcome message: {:?}",
+                client, addr, e
+            );
+            let _ = sender.send(RemoteEntityMessageResponse::Rejected(format!(
+                "Invalid welcome message, the server uses task-maker {} (protocol version {})",
+                VERSION, PROTOCOL_VERSION
+            )));
+            return None;
+        }
+    };
+    let Welcome {
+        protocol_version,
+        name,
+        version,
+        ..
+    } = &welcome;
+    if *protocol_version != PROTOCOL_VERSION {
         warn!(
-            "{} '{}' from {} connected with version {}, server has {}",
-            client, name, addr, version, VERSION
+            "{} '{}' from {} connected with protocol version {} (task-maker {}), server has {}",
+            client, name, addr, protocol_version, version, PROTOCOL_VERSION
         );
         let _ = sender.send(RemoteEntityMessageResponse::Rejected(format!(
-            "Wrong task-maker version, you have {}, server has {}",
-            version, VERSION
+            "Incompatible protocol version, you have {} (task-maker {}), server has {} \
+             (task-maker {})",
+            protocol_version, version, PROTOCOL_VERSION, VERSION
         )));
-        false
+        return None;
+    }
+    if version != VERSION {
+        info!(
+            "{} '{}' from {} uses task-maker {}, server has {}",
+            client, name, addr, version, VERSION
+        );
+    }
+    welcome.capabilities = capabilities.intersection(welcome.capabilities);
+    let _ = sender.send(RemoteEntityMessageResponse::Accepted(welcome.capabilities));
+    Some(welcome)
+}
+
+/// The machine of a worker connected from `addr`: the workers connected from the same host share
+/// it, the ones connected through a unix socket run on the machine of the server.
+fn worker_machine(addr: &str) -> String {
+    match addr.rsplit_once(':') {
+        Some((host, _port)) => host.to_string(),
+        None => addr.to_string(),
+    }
+}
+
+/// The encoding of the files exchanged with a peer, given the capabilities of the connection.
+fn file_encoding(capabilities: Capabilities) -> FileEncoding {
+    if capabilities.contains(Capabilities::COMPRESSION) {
+        FileEncoding::Zstd
     } else {
-        let _ = sender.send(RemoteEntityMessageResponse::Accepted);
-        true
+        FileEncoding::Plain
     }
 }
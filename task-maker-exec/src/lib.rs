@@ -77,30 +77,50 @@ use std::sync::Arc;
 use std::thread;
 
 use anyhow::Error;
+pub use autoscaler::AutoscalerConfig;
+pub use calibration::{measure_speed_factor, parse_speed_factor};
+#[cfg(feature = "chaos")]
+pub use chaos::ChaosConfig;
+pub use check_dag::{check_dag, find_orphan_files, DAGError, MissingDependency};
 /// Re-export `ductile` since it's sensible to any version change
 pub use ductile;
 use ductile::new_local_channel;
 
 pub use client::ExecutorClient;
-pub use executor::{ExecutorStatus, ExecutorWorkerStatus, WorkerCurrentJobStatus};
+pub use connect::{connect_to_remote_server, send_welcome};
+pub use executor::{
+    ExecutionDAGWatchSet, ExecutorStatus, ExecutorWorkerStatus, TransferStats,
+    WorkerCurrentJobStatus,
+};
 pub use sandbox::RawSandboxResult;
 pub use sandbox_runner::{ErrorSandboxRunner, SandboxRunner, SuccessSandboxRunner};
-pub use scheduler::ClientInfo;
+pub use scheduler::{ClientInfo, ClientUuid};
+pub use scheduling_trace::{SchedulingTrace, TraceSpan, TraceSpanKind};
 use task_maker_cache::Cache;
 use task_maker_dag::ExecutionDAG;
 use task_maker_store::FileStore;
+pub use toolchain::probe_toolchains;
 pub use worker::{Worker, WorkerConn};
 
+mod autoscaler;
+mod calibration;
+#[cfg(feature = "chaos")]
+mod chaos;
 mod check_dag;
 mod client;
+mod connect;
 mod detect_exe;
 mod executor;
 pub mod executors;
 pub mod find_tools;
 pub mod proto;
+mod resource_sampler;
 pub mod sandbox;
 mod sandbox_runner;
 mod scheduler;
+mod scheduling_trace;
+mod stderr_streamer;
+mod toolchain;
 mod worker;
 mod worker_manager;
 
@@ -132,25 +152,26 @@ pub fn eval_dag_locally<P: Into<PathBuf>, P2: Into<PathBuf>, R>(
     let file_store = Arc::new(
         FileStore::new(&store_dir, max_cache, min_cache).expect("Cannot create the file store"),
     );
-    let server_file_store = file_store.clone();
+    let cache = Cache::new(store_dir).expect("Cannot create the cache");
+    let executor = executors::LocalExecutor::new(
+        file_store,
+        cache,
+        num_cores,
+        sandbox_path,
+        sandbox_runner,
+        Default::default(),
+    )
+    .expect("Failed to create local executor");
+    let link = executor.in_process_link();
     let server = thread::Builder::new()
         .name("Local executor".into())
         .spawn(move || {
-            let cache = Cache::new(store_dir).expect("Cannot create the cache");
-            let executor = executors::LocalExecutor::new(
-                server_file_store,
-                cache,
-                num_cores,
-                sandbox_path,
-                sandbox_runner,
-            )
-            .expect("Failed to create local executor");
             executor
                 .evaluate(tx_remote, rx_remote)
                 .expect("Executor failed");
         })
         .expect("Failed to spawn local executor thread");
-    ExecutorClient::evaluate(dag, tx, &rx, file_store, |_| Ok(())).expect("Client failed");
+    ExecutorClient::evaluate_in_process(dag, tx, &rx, &link, |_| Ok(())).expect("Client failed");
     server.join().expect("Server panicked");
 }
 
@@ -159,8 +180,8 @@ mod tests {
     extern crate pretty_assertions;
 
     use std::path::Path;
-    use std::sync::atomic::{AtomicBool, Ordering};
-    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
 
     use tempfile::TempDir;
 
@@ -250,4 +271,491 @@ mod tests {
         assert!(!cwd.path().join("stdout2").exists());
         assert!(!cwd.path().join("output3").exists());
     }
+
+    #[test]
+    fn test_tagged_execution_done() {
+        let cwd = TempDir::new().unwrap();
+        let mut dag = ExecutionDAG::new();
+
+        let mut exec = Execution::new("Tagged", ExecutionCommand::system("echo"));
+        exec.args(vec!["42"])
+            .capture_stdout(100)
+            .tag(ExecutionTag::from("queries"));
+        let mut exec2 = Execution::new("Not tagged", ExecutionCommand::system("true"));
+        exec2.capture_stdout(100);
+
+        let num_tagged = Arc::new(AtomicUsize::new(0));
+        let num_tagged2 = num_tagged.clone();
+        dag.on_tagged_execution_done(ExecutionTag::from("queries"), move |exec, result| {
+            assert_eq!(exec.description, "Tagged");
+            let stdout = String::from_utf8_lossy(result.stdout.as_ref().unwrap());
+            let queries = stdout.trim().parse()?;
+            result.custom_metrics.insert("queries".into(), queries);
+            num_tagged.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        });
+        let metric_seen = Arc::new(AtomicBool::new(false));
+        let metric_seen2 = metric_seen.clone();
+        dag.on_execution_done(&exec.uuid, move |result| {
+            assert_eq!(result.custom_metrics.get("queries"), Some(&42.0));
+            metric_seen.store(true, Ordering::Relaxed);
+            Ok(())
+        });
+        dag.on_execution_done(&exec2.uuid, |result| {
+            assert!(result.custom_metrics.is_empty());
+            Ok(())
+        });
+        dag.add_execution(exec);
+        dag.add_execution(exec2);
+
+        eval_dag_locally(
+            dag,
+            cwd.path(),
+            2,
+            cwd.path(),
+            1000,
+            1000,
+            UnsafeSandboxRunner,
+        );
+
+        assert_eq!(num_tagged2.load(Ordering::Relaxed), 1);
+        assert!(metric_seen2.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_scheduling_trace() {
+        let cwd = TempDir::new().unwrap();
+        let mut dag = ExecutionDAG::new();
+        let trace_path = cwd.path().join("trace.json");
+        dag.config_mut().trace(Some(trace_path.clone()));
+
+        let exec = Execution::new("Traced", ExecutionCommand::system("true"));
+        dag.add_execution(exec);
+
+        eval_dag_locally(
+            dag,
+            cwd.path(),
+            2,
+            cwd.path(),
+            1000,
+            1000,
+            UnsafeSandboxRunner,
+        );
+
+        let trace = std::fs::read_to_string(&trace_path).unwrap();
+        let trace: serde_json::Value = serde_json::from_str(&trace).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+        let run = events
+            .iter()
+            .find(|event| event["cat"] == "run")
+            .expect("The run of the execution is not in the trace");
+        assert_eq!(run["name"], "Traced");
+        assert_eq!(run["ph"], "X");
+    }
+
+    #[test]
+    fn test_provided_files_same_content() {
+        use crate::proto::{ChannelFileSender, ExecutorClientMessage, ExecutorServerMessage};
+        use task_maker_dag::ProvidedFile;
+
+        let cwd = TempDir::new().unwrap();
+        let mut dag = ExecutionDAG::new();
+
+        let file1 = File::new("First file");
+        let file2 = File::new("Second file");
+        let mut exec1 = Execution::new("First", ExecutionCommand::system("cat"));
+        exec1.stdin(&file1);
+        let mut exec2 = Execution::new("Second", ExecutionCommand::system("cat"));
+        exec2.stdin(&file2);
+        dag.provide_content(file1, b"same content".to_vec());
+        dag.provide_content(file2, b"same content".to_vec());
+        for exec in [exec1, exec2] {
+            dag.on_execution_done(&exec.uuid, |_res| Ok(()));
+            dag.add_execution(exec);
+        }
+
+        let (tx, rx_remote) = new_local_channel();
+        let (tx_remote, rx) = new_local_channel();
+        let file_store = Arc::new(FileStore::new(cwd.path(), 1000, 1000).unwrap());
+        let store_dir = cwd.path().to_owned();
+        let server = thread::spawn(move || {
+            let cache = Cache::new(&store_dir).unwrap();
+            let executor = executors::LocalExecutor::new(
+                file_store,
+                cache,
+                2,
+                store_dir,
+                UnsafeSandboxRunner,
+                Default::default(),
+            )
+            .unwrap();
+            executor.evaluate(tx_remote, rx_remote).unwrap();
+        });
+        // speak the protocol directly, counting the files the server asks for
+        tx.send(ExecutorClientMessage::Evaluate {
+            dag: Box::new(dag.data.clone()),
+            callbacks: Box::new(ExecutionDAGWatchSet::new(&dag)),
+        })
+        .unwrap();
+        let mut uploads = 0;
+        let mut num_done = 0;
+        loop {
+            match rx.recv().unwrap() {
+                ExecutorServerMessage::AskFile(uuid, encoding) => {
+                    uploads += 1;
+                    let ProvidedFile::Content { content, key, .. } =
+                        &dag.data.provided_files[&uuid]
+                    else {
+                        panic!("Unexpected provided file");
+                    };
+                    tx.send(ExecutorClientMessage::ProvideFile(uuid, key.clone()))
+                        .unwrap();
+                    ChannelFileSender::send_data(content.clone(), &tx, encoding).unwrap();
+                }
+                ExecutorServerMessage::NotifyDone(..) => num_done += 1,
+                ExecutorServerMessage::Done(_) => break,
+                _ => {}
+            }
+        }
+        drop(tx);
+        drop(rx);
+        server.join().unwrap();
+
+        // the second file with the same content is not uploaded again
+        assert_eq!(uploads, 1);
+        assert_eq!(num_done, 2);
+    }
+
+    #[test]
+    fn test_batched_executions() {
+        let cwd = TempDir::new().unwrap();
+        let mut dag = ExecutionDAG::new();
+        dag.config_mut().max_batch_size(Some(8));
+
+        let num_done = Arc::new(AtomicUsize::new(0));
+        for i in 0..20 {
+            let mut exec = Execution::new(format!("Echo {}", i), ExecutionCommand::system("echo"));
+            exec.args(vec![i.to_string()]).capture_stdout(100);
+            let stdout = exec.stdout();
+            let num_done = num_done.clone();
+            dag.on_execution_done(&exec.uuid, move |result| {
+                assert!(result.status.is_success());
+                let stdout = String::from_utf8_lossy(result.stdout.as_ref().unwrap());
+                assert_eq!(stdout.trim(), i.to_string());
+                num_done.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            });
+            dag.add_execution(exec);
+            dag.write_file_to(&stdout, cwd.path().join(format!("stdout{}", i)), false);
+        }
+
+        eval_dag_locally(
+            dag,
+            cwd.path(),
+            1,
+            cwd.path(),
+            1000,
+            1000,
+            UnsafeSandboxRunner,
+        );
+
+        assert_eq!(num_done.load(Ordering::Relaxed), 20);
+        for i in 0..20 {
+            let stdout = std::fs::read_to_string(cwd.path().join(format!("stdout{}", i))).unwrap();
+            assert_eq!(stdout.trim(), i.to_string());
+        }
+    }
+
+    #[test]
+    fn test_skip_executions() {
+        let cwd = TempDir::new().unwrap();
+        let mut dag = ExecutionDAG::new();
+
+        let fast = Execution::new("Fast", ExecutionCommand::system("true"));
+        let mut slow = Execution::new("Slow", ExecutionCommand::system("sleep"));
+        slow.args(vec!["1"]);
+        let slow_stdout = slow.stdout();
+        // exec is still waiting for slow when fast completes
+        let mut exec = Execution::new("Skipped", ExecutionCommand::system("true"));
+        exec.stdin(&slow_stdout);
+        let stdout = exec.stdout();
+        let mut exec2 = Execution::new("Skipped too", ExecutionCommand::system("true"));
+        exec2.stdin(&stdout);
+
+        let skipper = dag.skipper();
+        let exec_uuid = exec.uuid;
+        dag.on_execution_done(&fast.uuid, move |_res| {
+            skipper.skip(exec_uuid);
+            Ok(())
+        });
+        let slow_done = Arc::new(AtomicBool::new(false));
+        let slow_done2 = slow_done.clone();
+        dag.on_execution_done(&slow.uuid, move |_res| {
+            slow_done.store(true, Ordering::Relaxed);
+            Ok(())
+        });
+        let skipped = Arc::new(AtomicUsize::new(0));
+        for exec in [&exec, &exec2] {
+            let skipped = skipped.clone();
+            dag.on_execution_skip(&exec.uuid, move || {
+                skipped.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            });
+            dag.on_execution_start(&exec.uuid, |_w| panic!("exec has not been skipped"));
+        }
+        dag.add_execution(fast);
+        dag.add_execution(slow);
+        dag.add_execution(exec);
+        dag.add_execution(exec2);
+
+        eval_dag_locally(
+            dag,
+            cwd.path(),
+            2,
+            cwd.path(),
+            1000,
+            1000,
+            UnsafeSandboxRunner,
+        );
+
+        assert!(slow_done2.load(Ordering::Relaxed));
+        assert_eq!(skipped.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_deterministic_order() {
+        let cwd = TempDir::new().unwrap();
+        let mut dag = ExecutionDAG::new();
+        dag.config_mut().deterministic(true);
+
+        let order = Arc::new(Mutex::new(vec![]));
+        // the executions are added in a shuffled order
+        for i in [3, 7, 0, 9, 5, 1, 8, 2, 6, 4] {
+            let exec = Execution::new(format!("Exec {}", i), ExecutionCommand::system("true"));
+            let order = order.clone();
+            dag.on_execution_done(&exec.uuid, move |result| {
+                assert!(result.status.is_success());
+                order.lock().unwrap().push(i);
+                Ok(())
+            });
+            dag.add_execution(exec);
+        }
+
+        eval_dag_locally(
+            dag,
+            cwd.path(),
+            4,
+            cwd.path(),
+            1000,
+            1000,
+            UnsafeSandboxRunner,
+        );
+
+        assert_eq!(*order.lock().unwrap(), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_required_resources_locally() {
+        let cwd = TempDir::new().unwrap();
+        let mut dag = ExecutionDAG::new();
+        dag.config_mut().max_batch_size(Some(8));
+
+        let num_done = Arc::new(AtomicUsize::new(0));
+        for i in 0..4 {
+            let mut exec = Execution::new(format!("Exec {}", i), ExecutionCommand::system("true"));
+            if i % 2 == 0 {
+                exec.require_resource("gpu");
+            }
+            let num_done = num_done.clone();
+            dag.on_execution_done(&exec.uuid, move |result| {
+                assert!(result.status.is_success());
+                num_done.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            });
+            dag.add_execution(exec);
+        }
+
+        // the local workers provide all the resources
+        eval_dag_locally(
+            dag,
+            cwd.path(),
+            1,
+            cwd.path(),
+            1000,
+            1000,
+            UnsafeSandboxRunner,
+        );
+
+        assert_eq!(num_done.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn test_concurrency_classes() {
+        let cwd = TempDir::new().unwrap();
+        let mut dag = ExecutionDAG::new();
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let num_done = Arc::new(AtomicUsize::new(0));
+        for i in 0..4 {
+            let mut exec =
+                Execution::new(format!("Heavy {}", i), ExecutionCommand::system("sleep"));
+            exec.args(vec!["0.1"]).concurrency_class("heavy");
+            let running_start = running.clone();
+            dag.on_execution_start(&exec.uuid, move |_w| {
+                // the local workers are all on the same machine
+                assert_eq!(running_start.fetch_add(1, Ordering::Relaxed), 0);
+                Ok(())
+            });
+            let running = running.clone();
+            let num_done = num_done.clone();
+            dag.on_execution_done(&exec.uuid, move |result| {
+                assert!(result.status.is_success());
+                running.fetch_sub(1, Ordering::Relaxed);
+                num_done.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            });
+            dag.add_execution(exec);
+        }
+
+        eval_dag_locally(
+            dag,
+            cwd.path(),
+            4,
+            cwd.path(),
+            1000,
+            1000,
+            UnsafeSandboxRunner,
+        );
+
+        assert_eq!(num_done.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn test_output_size_limit() {
+        let cwd = TempDir::new().unwrap();
+        let mut dag = ExecutionDAG::new();
+        dag.config_mut().max_total_output_size(Some(1000));
+
+        let mut exec = Execution::new("Big output", ExecutionCommand::system("head"));
+        exec.args(vec!["-c", "10000", "/dev/zero"]);
+        let stdout = exec.stdout();
+        let mut exec2 = Execution::new("After", ExecutionCommand::system("true"));
+        exec2.stdin(&stdout);
+        dag.on_execution_start(&exec2.uuid, |_w| panic!("exec2 should not start"));
+        dag.add_execution(exec);
+        dag.add_execution(exec2);
+
+        let (tx, rx_remote) = new_local_channel();
+        let (tx_remote, rx) = new_local_channel();
+        let file_store = Arc::new(FileStore::new(cwd.path(), 1000, 1000).unwrap());
+        let server_file_store = file_store.clone();
+        let store_dir = cwd.path().to_owned();
+        let server = thread::spawn(move || {
+            let cache = Cache::new(&store_dir).unwrap();
+            let executor = executors::LocalExecutor::new(
+                server_file_store,
+                cache,
+                2,
+                store_dir,
+                UnsafeSandboxRunner,
+                Default::default(),
+            )
+            .unwrap();
+            executor.evaluate(tx_remote, rx_remote).unwrap();
+        });
+        let result = ExecutorClient::evaluate(dag, tx, &rx, file_store.clone(), |_| Ok(()));
+        drop(rx);
+        server.join().unwrap();
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("limit"), "{}", error);
+        // the limit is checked before storing the output
+        let key = task_maker_store::FileStoreKey::from_content(&[0; 10000]);
+        assert!(file_store.get(&key).is_none());
+    }
+
+    #[test]
+    fn test_in_process_provided_files() {
+        let cwd = TempDir::new().unwrap();
+        let mut dag = ExecutionDAG::new();
+
+        let input = File::new("Input file");
+        let mut exec = Execution::new("Copy", ExecutionCommand::system("cat"));
+        exec.stdin(&input);
+        let stdout = exec.stdout();
+        dag.provide_content(input, b"in process".to_vec());
+        dag.add_execution(exec);
+        dag.write_file_to(&stdout, cwd.path().join("stdout"), false);
+
+        let (tx, rx_remote) = new_local_channel();
+        let (tx_remote, rx) = new_local_channel();
+        let file_store = Arc::new(FileStore::new(cwd.path(), 1000, 1000).unwrap());
+        let cache = Cache::new(cwd.path()).unwrap();
+        let executor = executors::LocalExecutor::new(
+            file_store,
+            cache,
+            1,
+            cwd.path(),
+            UnsafeSandboxRunner,
+            Default::default(),
+        )
+        .unwrap();
+        let link = executor.in_process_link();
+        let server = thread::spawn(move || executor.evaluate(tx_remote, rx_remote).unwrap());
+        ExecutorClient::evaluate_in_process(dag, tx, &rx, &link, |_| Ok(())).unwrap();
+        drop(rx);
+        server.join().unwrap();
+
+        let stdout = std::fs::read_to_string(cwd.path().join("stdout")).unwrap();
+        assert_eq!(stdout, "in process");
+    }
+
+    #[test]
+    fn test_distributed_evaluation() {
+        let cwd = TempDir::new().unwrap();
+        let mut dag = ExecutionDAG::new();
+        for name in ["first", "second"] {
+            let mut exec = Execution::new(name, ExecutionCommand::system("echo"));
+            exec.args(vec![name]);
+            let stdout = exec.stdout();
+            dag.add_execution(exec);
+            dag.write_file_to(&stdout, cwd.path().join(name), false);
+        }
+
+        let mut servers = vec![];
+        let mut threads = vec![];
+        for index in 0..2 {
+            let (tx, rx_remote) = new_local_channel();
+            let (tx_remote, rx) = new_local_channel();
+            let path = cwd.path().join(format!("server{}", index));
+            let file_store = Arc::new(FileStore::new(&path, 1000, 1000).unwrap());
+            let cache = Cache::new(&path).unwrap();
+            let executor = executors::LocalExecutor::new(
+                file_store,
+                cache,
+                1,
+                &path,
+                UnsafeSandboxRunner,
+                Default::default(),
+            )
+            .unwrap();
+            threads.push(thread::spawn(move || {
+                executor.evaluate(tx_remote, rx_remote).unwrap()
+            }));
+            servers.push((tx, rx));
+        }
+        let client_store = Arc::new(FileStore::new(cwd.path().join("client"), 1000, 1000).unwrap());
+        ExecutorClient::evaluate_distributed(dag, servers, client_store.clone(), |_| Ok(()))
+            .unwrap();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        for name in ["first", "second"] {
+            let content = std::fs::read_to_string(cwd.path().join(name)).unwrap();
+            assert_eq!(content, format!("{}\n", name));
+            // the files are streamed to the local store
+            let key = task_maker_store::FileStoreKey::from_content(content.as_bytes());
+            assert!(client_store.get(&key).is_some());
+        }
+    }
 }
@@ -28,20 +28,26 @@
 //! file from `B`:
 //! - `A` sends a `AskFile` to `B`
 //! - `B` answers with `ProvideFile` which triggers a protocol switch for sending the file
+//! - `B` sends a raw header with the [`FileEncoding`](enum.FileEncoding.html) of the data
 //! - `B` sends raw data (`send_raw`) zero or more times
 //! - `B` sends empty raw data which triggers a protocol switch, back into normal mode
+//!
+//! The encoding of the files is chosen by the server when a client or a worker connects: the files
+//! are compressed only if both the sides of the connection agree.
 
-use crate::executor::{ExecutionDAGWatchSet, ExecutorStatus, WorkerJob};
+use crate::executor::{ExecutionDAGWatchSet, ExecutorStatus, WorkerJob, WorkerJobBatch};
 use crate::*;
-use anyhow::Context;
+use anyhow::{bail, Context};
 use ductile::{ChannelReceiver, ChannelSender};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::Write;
 use std::path::Path;
 use std::time::Duration;
 use task_maker_dag::*;
 use task_maker_store::*;
+use uuid::Uuid;
 
 /// Messages that the client sends to the server.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +59,10 @@ pub enum ExecutorClientMessage {
         /// The list of the executions and files to keep track of.
         callbacks: Box<ExecutionDAGWatchSet>,
     },
+    /// The client is asking to evaluate the DAG with that id, that it handed over to the executor
+    /// through an [`InProcessLink`](crate::executors::InProcessLink) since they are in the same
+    /// process.
+    EvaluateInProcess(Uuid),
     /// The client is providing a file. After this message there is a protocol switch for the file
     /// transmission.
     ProvideFile(FileUuid, FileStoreKey),
@@ -60,19 +70,35 @@ pub enum ExecutorClientMessage {
     /// switch for the file transmission.
     AskFile(FileUuid, FileStoreKey, bool),
     /// The client is asking to stop the evaluation. All the running executions will be killed and
-    /// no more execution will be run. All the callbacks will be called as usual.
+    /// no more execution will be run. All the callbacks will be called as usual. The server sends
+    /// [`Done`](enum.ExecutorServerMessage.html#variant.Done) after all the workers have stopped.
     Stop,
     /// The client is asking for the server status. After this message the client should expect a
     /// [`Status`](enum.ExecutorServerMessage.html#variant.Status) message back.
     Status,
+    /// The client is asking for the logs of the sandbox of an execution that failed with an
+    /// internal error. It can be sent also after
+    /// [`Done`](enum.ExecutorServerMessage.html#variant.Done), the client should expect a
+    /// [`SandboxLogs`](enum.ExecutorServerMessage.html#variant.SandboxLogs) message back.
+    AskSandboxLogs(ExecutionUuid),
+    /// The client is asking to skip some executions, since their results are not needed anymore.
+    /// The ones that haven't started yet are skipped, together with all the executions that depend
+    /// on them, as if one of their dependencies failed. The others are left untouched.
+    Skip(Vec<ExecutionUuid>),
+    /// The client is asking to cancel the evaluation of another client, identified by the uuid of
+    /// that client, killing its running executions. The other client is told its evaluation has
+    /// been cancelled, and this client should expect a
+    /// [`CancelOutcome`](enum.ExecutorServerMessage.html#variant.CancelOutcome) message back.
+    Cancel(Uuid),
 }
 
 /// Messages that the server sends to the client.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ExecutorServerMessage {
-    /// The server needs the file with that Uuid. The client must send back that file in order to
-    /// proceed with the execution.
-    AskFile(FileUuid),
+    /// The server needs the file with that Uuid. The client must send back that file, with that
+    /// encoding, in order to proceed with the execution.
+    AskFile(FileUuid, FileEncoding),
     /// The server is sending a file. After this message there is a protocol switch for the file
     /// transmission protocol. The second entry is true if the generation of the file was
     /// successful.
@@ -85,8 +111,23 @@ pub enum ExecutorServerMessage {
     NotifySkip(ExecutionUuid),
     /// There was an error during the evaluation.
     Error(String),
+    /// The files produced by the evaluation exceeded the limit on their total size, the evaluation
+    /// has been stopped. The values are the total size of the produced files and the limit, in
+    /// bytes.
+    OutputSizeLimitExceeded(u64, u64),
+    /// The evaluation has been cancelled by another client, usually an operator of the server, and
+    /// it's being stopped.
+    Cancelled,
+    /// Whether the evaluation that the client asked to cancel was running on the server.
+    CancelOutcome(bool),
     /// The server status as asked by the client.
     Status(ExecutorStatus<Duration>),
+    /// The logs of the sandbox of an execution as asked by the client, `None` if they are not
+    /// available (e.g. the worker that ran it is gone).
+    SandboxLogs(ExecutionUuid, Option<SandboxLogs>),
+    /// The scheduling trace of the evaluation, sent just before
+    /// [`Done`](enum.ExecutorServerMessage.html#variant.Done) if the DAG asks for it.
+    Trace(Box<SchedulingTrace>),
     /// The evaluation of the DAG is complete, this message will close the connection.
     Done(Vec<(FileUuid, FileStoreKey, bool)>),
 }
@@ -97,17 +138,30 @@ pub enum WorkerClientMessage {
     /// The worker is ready for some job. The worker will wait for a
     /// [`Work`](enum.WorkerServerMessage.html#variant.Work) message.
     GetWork,
-    /// The worker completed the job with this result producing those files. The actual files will
-    /// be sent immediately after using `ProvideFile` messages.
+    /// The worker completed the job with this result producing those files, with their sizes in
+    /// bytes. The actual files will be sent immediately after using `ProvideFile` messages.
     /// The list of `ExecutionResult` contains the results of all the executions inside the group,
     /// in the same order.
-    WorkerDone(Vec<ExecutionResult>, HashMap<FileUuid, FileStoreKey>),
+    WorkerDone(Vec<ExecutionResult>, HashMap<FileUuid, (FileStoreKey, u64)>),
+    /// The worker completed a batch of jobs. Like `WorkerDone`, but with the results of each job of
+    /// the batch, in the same order, and the files produced by all of them. If the server asked to
+    /// release the batch there are only the results of its first jobs, the others are given back.
+    WorkerBatchDone(
+        Vec<Vec<ExecutionResult>>,
+        HashMap<FileUuid, (FileStoreKey, u64)>,
+    ),
     /// The worker is sending a file to the server. After this message there is a protocol switch
     /// for the file transmission.
     ProvideFile(FileUuid, FileStoreKey),
     /// The worker needs a file from the server. The server should send back that file in order to
     /// run the execution on the worker.
     AskFile(FileStoreKey),
+    /// The logs of the sandbox of an execution as asked by the server, `None` if the worker doesn't
+    /// have them anymore.
+    SandboxLogs(ExecutionUuid, Option<SandboxLogs>),
+    /// The last part of what the executions of the current job wrote to their standard error since
+    /// the previous message. Sent periodically only if the job asks for it.
+    Stderr(Vec<u8>),
 }
 
 /// Messages sent by the server to the worker.
@@ -115,16 +169,64 @@ pub enum WorkerClientMessage {
 pub enum WorkerServerMessage {
     /// The job the worker should do. Boxed to reduce the enum size.
     Work(Box<WorkerJob>),
-    /// Stop the current worker sandbox if currently running the specified execution.
+    /// A batch of jobs the worker should do one after the other, reporting their results with a
+    /// single `WorkerBatchDone`.
+    WorkBatch(Box<WorkerJobBatch>),
+    /// Stop the current worker sandbox if currently running the specified execution. If the
+    /// execution is part of a batch, the rest of the batch is not run.
     KillJob(ExecutionGroupUuid),
+    /// Give back the jobs of the current batch that are not started yet, if the batch contains the
+    /// specified group, so that the server can assign them to other workers. The first job of the
+    /// batch is always run.
+    ReleaseJobs(ExecutionGroupUuid),
     /// The file the workers as asked. After this message there is a protocol switch for the file
     /// transmission.
     ProvideFile(FileStoreKey),
     /// The worker completed the execution and produced some files, the server asks the ones that
-    /// are missing using this message.
-    AskFiles(Vec<FileUuid>),
+    /// are missing, with that encoding, using this message.
+    AskFiles(Vec<FileUuid>, FileEncoding),
     /// Ask the worker to exit.
     Exit,
+    /// Ask the worker for the logs of the sandbox of an execution it ran. If the worker is doing a
+    /// job it answers after sending its results.
+    AskSandboxLogs(ExecutionUuid),
+}
+
+/// How the content of a file is encoded while it's sent in a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum FileEncoding {
+    /// The content is sent as is.
+    #[default]
+    Plain,
+    /// The content is compressed with zstd. Useful when the bandwidth is limited.
+    Zstd,
+}
+
+impl FileEncoding {
+    /// The raw header sent before the content of a file.
+    fn header(self) -> [u8; 1] {
+        match self {
+            FileEncoding::Plain => [0],
+            FileEncoding::Zstd => [1],
+        }
+    }
+
+    /// Parse the raw header sent before the content of a file.
+    fn from_header(header: &[u8]) -> Result<FileEncoding, Error> {
+        match header {
+            [0] => Ok(FileEncoding::Plain),
+            [1] => Ok(FileEncoding::Zstd),
+            _ => bail!("Invalid file header: {:?}", header),
+        }
+    }
+}
+
+/// The decoder of the content of a file received in a channel.
+enum FileDecoder {
+    /// The content is not encoded.
+    Plain,
+    /// The content is compressed with zstd, the decompressed data is accumulated in the buffer.
+    Zstd(zstd::stream::write::Decoder<'static, Vec<u8>>),
 }
 
 /// An iterator over the byte chunks sent during the file transfer mode in a channel.
@@ -134,6 +236,10 @@ where
 {
     /// Reference to the channel from where to read
     reader: &'a ChannelReceiver<T>,
+    /// The decoder of the file, `None` until the header is received.
+    decoder: Option<FileDecoder>,
+    /// Number of (encoded) bytes received so far.
+    received_bytes: u64,
 }
 
 impl<'a, T> ChannelFileIterator<'a, T>
@@ -142,7 +248,17 @@ where
 {
     /// Create a new iterator over a receiver channel.
     pub fn new(reader: &'a ChannelReceiver<T>) -> ChannelFileIterator<'a, T> {
-        ChannelFileIterator { reader }
+        ChannelFileIterator {
+            reader,
+            decoder: None,
+            received_bytes: 0,
+        }
+    }
+
+    /// Number of bytes received from the channel so far. If the file is compressed this is the
+    /// size of the compressed data.
+    pub fn received_bytes(&self) -> u64 {
+        self.received_bytes
     }
 }
 
@@ -153,11 +269,35 @@ where
     type Item = Vec<u8>;
     fn next(&mut self) -> Option<Self::Item> {
         // errors cannot be handled in this iterator yet
-        let data = self.reader.recv_raw().expect("deserialize error");
-        if data.is_empty() {
-            None
-        } else {
-            Some(data)
+        let decoder = self.decoder.get_or_insert_with(|| {
+            let header = self.reader.recv_raw().expect("deserialize error");
+            match FileEncoding::from_header(&header).expect("invalid file header") {
+                FileEncoding::Plain => FileDecoder::Plain,
+                FileEncoding::Zstd => FileDecoder::Zstd(
+                    zstd::stream::write::Decoder::new(Vec::new()).expect("zstd init error"),
+                ),
+            }
+        });
+        loop {
+            let data = self.reader.recv_raw().expect("deserialize error");
+            if data.is_empty() {
+                return None;
+            }
+            self.received_bytes += data.len() as u64;
+            match decoder {
+                FileDecoder::Plain => return Some(data),
+                FileDecoder::Zstd(decoder) => {
+                    decoder
+                        .write_all(&data)
+                        .and_then(|_| decoder.flush())
+                        .expect("decompression error");
+                    let data = std::mem::take(decoder.get_mut());
+                    // a compressed chunk may not produce any data
+                    if !data.is_empty() {
+                        return Some(data);
+                    }
+                }
+            }
         }
     }
 }
@@ -166,39 +306,87 @@ where
 pub struct ChannelFileSender;
 
 impl ChannelFileSender {
-    /// Send a local file to a channel using `send_raw`.
-    pub fn send<P: AsRef<Path>, T>(path: P, sender: &ChannelSender<T>) -> Result<(), Error>
+    /// Send a local file to a channel using `send_raw`. Returns the number of (encoded) bytes sent.
+    pub fn send<P: AsRef<Path>, T>(
+        path: P,
+        sender: &ChannelSender<T>,
+        encoding: FileEncoding,
+    ) -> Result<u64, Error>
     where
         T: 'static + Send + Sync + Serialize,
     {
         let path = path.as_ref();
         let iterator = ReadFileIterator::new(path)
             .with_context(|| format!("Failed to read file to send: {}", path.display()))?;
-        for buf in iterator {
-            sender.send_raw(&buf).context("Failed to send file chunk")?;
-        }
-        sender
-            .send_raw(&[])
-            .context("Failed to send file terminator")?;
-        Ok(())
+        ChannelFileSender::send_chunks(iterator, sender, encoding)
     }
 
-    /// Send the file content to a channel using `send_raw`.
-    pub fn send_data<T>(data: Vec<u8>, sender: &ChannelSender<T>) -> Result<(), Error>
+    /// Send the file content to a channel using `send_raw`. Returns the number of (encoded) bytes
+    /// sent.
+    pub fn send_data<T>(
+        data: Vec<u8>,
+        sender: &ChannelSender<T>,
+        encoding: FileEncoding,
+    ) -> Result<u64, Error>
     where
         T: 'static + Send + Sync + Serialize,
+    {
+        ChannelFileSender::send_chunks(std::iter::once(data), sender, encoding)
+    }
+
+    /// Send the header, the encoded chunks and the terminator of a file. Empty chunks are never
+    /// sent since they would terminate the file transfer.
+    fn send_chunks<I, T>(
+        chunks: I,
+        sender: &ChannelSender<T>,
+        encoding: FileEncoding,
+    ) -> Result<u64, Error>
+    where
+        I: IntoIterator<Item = Vec<u8>>,
+        T: 'static + Send + Sync + Serialize,
     {
         sender
-            .send_raw(&data)
-            .context("Failed to send file chunk")?;
-        // Send the EOF chunk only if the buffer is not empty (otherwise we would send EOF twice
-        // breaking the protocol).
-        if !data.is_empty() {
-            sender
-                .send_raw(&[])
-                .context("Failed to send file terminator")?;
+            .send_raw(&encoding.header())
+            .context("Failed to send file header")?;
+        let mut sent_bytes = 0;
+        match encoding {
+            FileEncoding::Plain => {
+                for chunk in chunks.into_iter().filter(|chunk| !chunk.is_empty()) {
+                    sender
+                        .send_raw(&chunk)
+                        .context("Failed to send file chunk")?;
+                    sent_bytes += chunk.len() as u64;
+                }
+            }
+            FileEncoding::Zstd => {
+                let mut encoder =
+                    zstd::stream::write::Encoder::new(Vec::new(), zstd::DEFAULT_COMPRESSION_LEVEL)
+                        .context("Failed to create the zstd encoder")?;
+                for chunk in chunks {
+                    encoder
+                        .write_all(&chunk)
+                        .context("Failed to compress file chunk")?;
+                    let compressed = std::mem::take(encoder.get_mut());
+                    if !compressed.is_empty() {
+                        sender
+                            .send_raw(&compressed)
+                            .context("Failed to send file chunk")?;
+                        sent_bytes += compressed.len() as u64;
+                    }
+                }
+                let compressed = encoder.finish().context("Failed to compress file")?;
+                if !compressed.is_empty() {
+                    sender
+                        .send_raw(&compressed)
+                        .context("Failed to send file chunk")?;
+                    sent_bytes += compressed.len() as u64;
+                }
+            }
         }
-        Ok(())
+        sender
+            .send_raw(&[])
+            .context("Failed to send file terminator")?;
+        Ok(sent_bytes)
     }
 }
 
@@ -213,7 +401,8 @@ mod tests {
 
         let (sender, receiver) = new_local_channel::<()>();
         let receiver = ChannelFileIterator::new(&receiver);
-        ChannelFileSender::send(tmpdir.path().join("file.txt"), &sender).unwrap();
+        ChannelFileSender::send(tmpdir.path().join("file.txt"), &sender, FileEncoding::Plain)
+            .unwrap();
         let data: Vec<u8> = receiver.flat_map(|d| d.into_iter()).collect();
         assert_eq!(String::from_utf8(data).unwrap(), "hello world");
     }
@@ -222,8 +411,34 @@ mod tests {
     fn test_send_content() {
         let (sender, receiver) = new_local_channel::<()>();
         let receiver = ChannelFileIterator::new(&receiver);
-        ChannelFileSender::send_data(b"hello world".to_vec(), &sender).unwrap();
+        ChannelFileSender::send_data(b"hello world".to_vec(), &sender, FileEncoding::Plain)
+            .unwrap();
         let data: Vec<u8> = receiver.flat_map(|d| d.into_iter()).collect();
         assert_eq!(String::from_utf8(data).unwrap(), "hello world");
     }
+
+    #[test]
+    fn test_send_empty_content() {
+        let (sender, receiver) = new_local_channel::<()>();
+        let receiver = ChannelFileIterator::new(&receiver);
+        ChannelFileSender::send_data(vec![], &sender, FileEncoding::Zstd).unwrap();
+        assert_eq!(receiver.count(), 0);
+    }
+
+    #[test]
+    fn test_send_compressed_file() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let content = "hello world\n".repeat(100_000);
+        std::fs::write(tmpdir.path().join("file.txt"), &content).unwrap();
+
+        let (sender, receiver) = new_local_channel::<()>();
+        let mut receiver = ChannelFileIterator::new(&receiver);
+        let sent =
+            ChannelFileSender::send(tmpdir.path().join("file.txt"), &sender, FileEncoding::Zstd)
+                .unwrap();
+        let data: Vec<u8> = receiver.by_ref().flat_map(|d| d.into_iter()).collect();
+        assert_eq!(String::from_utf8(data).unwrap(), content);
+        assert_eq!(receiver.received_bytes(), sent);
+        assert!(sent < content.len() as u64);
+    }
 }
@@ -0,0 +1,228 @@
+//! Periodic sampling of the resources used by the processes of a sandbox, reading the information
+//! exposed by the kernel in `/proc`.
+//!
+//! The sandbox itself reports only the total cpu time and the peak memory usage; the sampler
+//! collects some additional metrics (context switches, page faults and the resident memory over
+//! time) that are useful for diagnosing solutions close to the limits. Since the values are read
+//! periodically they are approximate: the processes that live less than the sampling interval may
+//! not be observed at all.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use task_maker_dag::{ExecutionResourcesUsage, MemorySample};
+
+/// Interval between two samples.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(20);
+/// Maximum number of memory samples to keep. When reached, half of the samples are dropped and the
+/// following ones are kept less frequently.
+const MAX_MEMORY_SAMPLES: usize = 256;
+
+/// The counters of a single process, as read from `/proc`.
+#[derive(Debug, Clone, Copy, Default)]
+struct ProcessCounters {
+    /// Number of voluntary context switches.
+    voluntary_context_switches: u64,
+    /// Number of involuntary context switches.
+    involuntary_context_switches: u64,
+    /// Number of minor page faults.
+    minor_page_faults: u64,
+    /// Number of major page faults.
+    major_page_faults: u64,
+    /// Resident memory, in KiB.
+    rss: u64,
+}
+
+/// The metrics collected by the sampler.
+#[derive(Debug, Default)]
+struct SampledResources {
+    /// The last counters read for each process ever observed.
+    counters: HashMap<u32, ProcessCounters>,
+    /// The samples of the total resident memory of the processes.
+    memory_samples: Vec<MemorySample>,
+    /// Keep only a sample every `stride`.
+    stride: usize,
+    /// Number of samples taken since the last one kept.
+    skipped: usize,
+}
+
+/// A thread that samples the resources used by the descendants of a process, until it's stopped.
+///
+/// The process whose pid is sampled is the one spawned by the `SandboxRunner`, its descendants are
+/// the processes of the execution. The runner process itself is not accounted.
+pub(crate) struct ResourceSampler {
+    /// Channel for stopping the sampler thread.
+    stop: crossbeam_channel::Sender<()>,
+    /// The sampler thread.
+    handle: JoinHandle<SampledResources>,
+}
+
+impl ResourceSampler {
+    /// Start sampling the descendants of the process with the pid stored in `pid`. The pid may not
+    /// be known yet, in that case the sampling starts as soon as it's set.
+    pub fn start(pid: Arc<AtomicU32>) -> Option<ResourceSampler> {
+        let (stop, stopped) = crossbeam_channel::bounded(1);
+        let handle = std::thread::Builder::new()
+            .name("Resource sampler".into())
+            .spawn(move || {
+                let start = Instant::now();
+                let clock = crossbeam_channel::tick(SAMPLE_INTERVAL);
+                let mut resources = SampledResources {
+                    stride: 1,
+                    ..Default::default()
+                };
+                loop {
+                    let pid = pid.load(Ordering::SeqCst);
+                    if pid != 0 {
+                        resources.sample(pid, start.elapsed());
+                    }
+                    crossbeam_channel::select! {
+                        recv(clock) -> _ => {}
+                        recv(stopped) -> _ => break,
+                    }
+                }
+                resources
+            });
+        match handle {
+            Ok(handle) => Some(ResourceSampler { stop, handle }),
+            Err(e) => {
+                warn!("Failed to start the resource sampler: {:?}", e);
+                None
+            }
+        }
+    }
+
+    /// Stop the sampler and store the collected metrics inside `resources`.
+    pub fn finish(self, resources: &mut ExecutionResourcesUsage) {
+        let _ = self.stop.send(());
+        let sampled = match self.handle.join() {
+            Ok(sampled) => sampled,
+            Err(e) => {
+                warn!("Resource sampler panicked: {:?}", e);
+                return;
+            }
+        };
+        if sampled.counters.is_empty() {
+            return;
+        }
+        let total = |f: fn(&ProcessCounters) -> u64| sampled.counters.values().map(f).sum();
+        resources.voluntary_context_switches = Some(total(|c| c.voluntary_context_switches));
+        resources.involuntary_context_switches = Some(total(|c| c.involuntary_context_switches));
+        resources.minor_page_faults = Some(total(|c| c.minor_page_faults));
+        resources.major_page_faults = Some(total(|c| c.major_page_faults));
+        resources.memory_samples = sampled.memory_samples;
+    }
+}
+
+impl SampledResources {
+    /// Read the counters of all the descendants of `root`.
+    fn sample(&mut self, root: u32, time: Duration) {
+        let mut rss = 0;
+        let mut found = false;
+        let mut stack = children(root);
+        while let Some(pid) = stack.pop() {
+            if let Some(counters) = read_counters(pid) {
+                found = true;
+                rss += counters.rss;
+                self.counters.insert(pid, counters);
+            }
+            stack.extend(children(pid));
+        }
+        if !found {
+            return;
+        }
+        self.skipped += 1;
+        if self.skipped < self.stride {
+            return;
+        }
+        self.skipped = 0;
+        self.memory_samples.push(MemorySample {
+            time: time.as_secs_f64(),
+            memory: rss,
+        });
+        if self.memory_samples.len() >= MAX_MEMORY_SAMPLES {
+            let mut index = 0;
+            self.memory_samples.retain(|_| {
+                index += 1;
+                index % 2 == 1
+            });
+            self.stride *= 2;
+        }
+    }
+}
+
+/// The pids of the children of a process, reading the children of all its threads.
+fn children(pid: u32) -> Vec<u32> {
+    let Ok(tasks) = std::fs::read_dir(format!("/proc/{}/task", pid)) else {
+        return vec![];
+    };
+    tasks
+        .flatten()
+        .filter_map(|task| std::fs::read_to_string(task.path().join("children")).ok())
+        .flat_map(|children| {
+            children
+                .split_whitespace()
+                .filter_map(|pid| pid.parse().ok())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Read the counters of a process. Returns `None` if the process is gone.
+fn read_counters(pid: u32) -> Option<ProcessCounters> {
+    let proc = Path::new("/proc").join(pid.to_string());
+    let status = std::fs::read_to_string(proc.join("status")).ok()?;
+    let stat = std::fs::read_to_string(proc.join("stat")).ok()?;
+    let mut counters = ProcessCounters::default();
+    for line in status.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.split_whitespace().next().and_then(|v| v.parse().ok());
+        match (key, value) {
+            ("VmRSS", Some(value)) => counters.rss = value,
+            ("voluntary_ctxt_switches", Some(value)) => counters.voluntary_context_switches = value,
+            ("nonvoluntary_ctxt_switches", Some(value)) => {
+                counters.involuntary_context_switches = value
+            }
+            _ => {}
+        }
+    }
+    // the name of the process may contain spaces, the fields are after the last parenthesis.
+    // minflt and majflt are the fields 10 and 12, the first one after the name is the 3rd.
+    let fields: Vec<_> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    counters.minor_page_faults = fields.get(7)?.parse().ok()?;
+    counters.major_page_faults = fields.get(9)?.parse().ok()?;
+    Some(counters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_child_process() {
+        let pid = std::process::id();
+        if !Path::new(&format!("/proc/{}/task/{}/children", pid, pid)).exists() {
+            return;
+        }
+        let mut child = std::process::Command::new("sleep")
+            .arg("1")
+            .spawn()
+            .unwrap();
+        let mut resources = SampledResources {
+            stride: 1,
+            ..Default::default()
+        };
+        resources.sample(pid, Duration::from_secs(1));
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(resources.counters.contains_key(&child.id()));
+        assert_eq!(resources.memory_samples.len(), 1);
+        assert_eq!(resources.memory_samples[0].time, 1.0);
+    }
+}
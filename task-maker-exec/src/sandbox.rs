@@ -4,7 +4,7 @@
 use std::collections::{HashMap, HashSet};
 use std::fs::Permissions;
 use std::os::unix::fs::PermissionsExt;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
@@ -22,6 +22,8 @@ use task_maker_dag::*;
 use task_maker_store::*;
 
 use crate::detect_exe::detect_exe;
+use crate::find_tools::find_tools_path;
+use crate::resource_sampler::ResourceSampler;
 use crate::sandbox_runner::SandboxRunner;
 
 /// The list of all the system-wide readable directories inside the sandbox.
@@ -38,6 +40,13 @@ pub const READABLE_DIRS: &[&str] = &[
     "/var/lib/texmf/",
 ];
 
+/// The environment variables of the worker that are visible inside the sandbox. All the other
+/// variables have to be set explicitly by the execution, or by the configuration of the DAG.
+pub const ENV_WHITELIST: &[&str] = &["PATH"];
+
+/// The maximum number of cleared sandbox directories kept in a `SandboxPool`.
+const MAX_POOLED_SANDBOXES: usize = 16;
+
 /// Result of the execution of the sandbox.
 #[derive(Debug)]
 pub enum SandboxResult {
@@ -51,6 +60,8 @@ pub enum SandboxResult {
         resources: ExecutionResourcesUsage,
         /// Whether the sandbox killed the process.
         was_killed: bool,
+        /// How the sandbox has been run.
+        invocation: SandboxInvocation,
     },
     /// The sandbox failed to execute the process, an error message is reported. Note that this
     /// represents a sandbox error, not the process failure.
@@ -74,6 +85,21 @@ struct SandboxData {
     fifo_dir: Option<PathBuf>,
     /// The PID of the sandbox process, zero if not available or not spawned yet.
     box_pid: Arc<AtomicU32>,
+    /// The pool where to put back the sandbox directory after the execution, if any.
+    pool: Option<Arc<SandboxPool>>,
+    /// The configuration and the raw result of the sandbox, filled while it runs.
+    logs: SandboxLogs,
+}
+
+/// A pool of sandbox directories, reused between the executions for avoiding the cost of creating
+/// and deleting them every time. The directories in the pool contain only the skeleton of the
+/// sandbox, they are cleared when they are put back in the pool.
+#[derive(Debug)]
+pub struct SandboxPool {
+    /// The directory where the sandboxes are created.
+    sandboxes_dir: PathBuf,
+    /// The cleared sandbox directories, ready to be used again.
+    free: Mutex<Vec<TempDir>>,
 }
 
 /// Response of the internal implementation of the sandbox.
@@ -105,6 +131,24 @@ impl Sandbox {
         dep_keys: &HashMap<FileUuid, FileStoreHandle>,
         fifo_dir: Option<PathBuf>,
     ) -> Result<Sandbox, Error> {
+        let boxdir = Sandbox::new_boxdir(sandboxes_dir)?;
+        Sandbox::with_boxdir(boxdir, execution, dep_keys, fifo_dir, None)
+    }
+
+    /// Make a new sandbox for the specified execution, like `new`, but reusing a directory from the
+    /// pool. When the sandbox is dropped its directory is cleared and put back in the pool.
+    pub fn from_pool(
+        pool: &Arc<SandboxPool>,
+        execution: &Execution,
+        dep_keys: &HashMap<FileUuid, FileStoreHandle>,
+        fifo_dir: Option<PathBuf>,
+    ) -> Result<Sandbox, Error> {
+        let boxdir = pool.acquire()?;
+        Sandbox::with_boxdir(boxdir, execution, dep_keys, fifo_dir, Some(pool.clone()))
+    }
+
+    /// Create a new sandbox directory, with the skeleton already inside.
+    fn new_boxdir(sandboxes_dir: &Path) -> Result<TempDir, Error> {
         std::fs::create_dir_all(sandboxes_dir).with_context(|| {
             format!(
                 "Failed to create sandbox directory at {}",
@@ -113,6 +157,19 @@ impl Sandbox {
         })?;
         let boxdir = TempDir::new_in(sandboxes_dir)
             .context("Failed to create sandbox temporary directory")?;
+        Sandbox::setup_skeleton(boxdir.path()).context("Sandbox skeleton setup failed")?;
+        Ok(boxdir)
+    }
+
+    /// Make the sandbox using the provided directory, which should contain only the skeleton.
+    fn with_boxdir(
+        boxdir: TempDir,
+        execution: &Execution,
+        dep_keys: &HashMap<FileUuid, FileStoreHandle>,
+        fifo_dir: Option<PathBuf>,
+        pool: Option<Arc<SandboxPool>>,
+    ) -> Result<Sandbox, Error> {
+        // if the setup fails the directory is dropped, not put back in the pool.
         Sandbox::setup(boxdir.path(), execution, dep_keys).context("Sandbox setup failed")?;
         Ok(Sandbox {
             data: Arc::new(Mutex::new(SandboxData {
@@ -121,6 +178,8 @@ impl Sandbox {
                 keep_sandbox: false,
                 fifo_dir,
                 box_pid: Arc::new(AtomicU32::new(0)),
+                pool,
+                logs: SandboxLogs::default(),
             })),
         })
     }
@@ -128,13 +187,15 @@ impl Sandbox {
     /// Starts the sandbox and blocks the thread until the sandbox exits.
     pub fn run(&self, runner: &dyn SandboxRunner) -> Result<SandboxResult, Error> {
         let mut config = SandboxConfiguration::default();
-        let (boxdir, pid, keep, cmd) = {
+        let (boxdir, pid, keep, sample, image, cmd) = {
             let data = self.data.lock().unwrap();
             (
                 data.path().to_owned(),
                 data.box_pid.clone(),
                 data.keep_sandbox,
-                self.build_command(
+                data.execution.config().verbose_resources,
+                data.execution.effective_container_image().map(String::from),
+                Sandbox::build_command(
                     data.path(),
                     &data.execution,
                     &mut config,
@@ -151,12 +212,39 @@ impl Sandbox {
         }
         trace!("Sandbox configuration: {:#?}", config);
 
-        let raw_result = runner.run(config.build(), pid);
+        let config = config.build();
+        // the runner command would run the execution outside of the container
+        let runner_command = if image.is_some() {
+            None
+        } else {
+            runner.command()
+        };
+        let invocation = Sandbox::invocation(&boxdir, &config, runner_command);
+        let serialized = serde_json::to_string_pretty(&config)
+            .context("Failed to serialize sandbox configuration")?;
+        if keep {
+            let target = boxdir.join("sandbox.json");
+            std::fs::write(&target, &serialized)
+                .with_context(|| format!("Failed to write {}", target.display()))?;
+        }
+        self.add_log("sandbox.json", serialized);
+        // sampling costs a thread per execution, do it only if someone is going to look at it
+        let sampler = if sample {
+            ResourceSampler::start(pid.clone())
+        } else {
+            None
+        };
+        let raw_result = match &image {
+            Some(image) => runner.run_in_container(image, config, pid),
+            None => runner.run(config, pid),
+        };
+        let raw_result_log = format!("{:#?}", raw_result);
         if keep {
             let target = boxdir.join("result.txt");
-            std::fs::write(&target, format!("{:#?}", raw_result))
+            std::fs::write(&target, &raw_result_log)
                 .with_context(|| format!("Failed to write {}", target.display()))?;
         }
+        self.add_log("result.txt", raw_result_log);
 
         let res = match raw_result {
             RawSandboxResult::Success(res) => res,
@@ -164,12 +252,16 @@ impl Sandbox {
         };
         trace!("Sandbox output: {:?}", res);
 
-        let resources = ExecutionResourcesUsage {
+        let mut resources = ExecutionResourcesUsage {
             cpu_time: res.resource_usage.user_cpu_time,
             sys_time: res.resource_usage.system_cpu_time,
             wall_time: res.resource_usage.wall_time_usage,
             memory: res.resource_usage.memory_usage / 1024,
+            ..Default::default()
         };
+        if let Some(sampler) = sampler {
+            sampler.finish(&mut resources);
+        }
 
         use tabox::result::ExitStatus::*;
         match res.status {
@@ -178,6 +270,7 @@ impl Sandbox {
                 signal: None,
                 resources,
                 was_killed: false,
+                invocation,
             }),
             Signal(s) => Ok(SandboxResult::Success {
                 exit_status: 0,
@@ -187,12 +280,14 @@ impl Sandbox {
                 )),
                 resources,
                 was_killed: false,
+                invocation,
             }),
             Killed => Ok(SandboxResult::Success {
                 exit_status: 1,
                 signal: Some((9, "Killed by sandbox".into())),
                 resources,
                 was_killed: true,
+                invocation,
             }),
         }
     }
@@ -247,7 +342,7 @@ impl Sandbox {
             .context("Cannot write execution info inside sandbox")?;
         let mut config = SandboxConfiguration::default();
         if let Ok(()) =
-            self.build_command(&path, &data.execution, &mut config, data.fifo_dir.clone())
+            Sandbox::build_command(&path, &data.execution, &mut config, data.fifo_dir.clone())
         {
             std::fs::write(path.join("tabox.txt"), format!("{:#?}\n", config))
                 .context("Cannot write command info inside sandbox")?;
@@ -255,6 +350,17 @@ impl Sandbox {
         Ok(())
     }
 
+    /// The logs of the sandbox: its configuration and its raw result, if it has been run.
+    pub fn logs(&self) -> SandboxLogs {
+        self.data.lock().unwrap().logs.clone()
+    }
+
+    /// Add a log file to the logs of the sandbox.
+    fn add_log(&self, name: &str, content: String) {
+        let mut data = self.data.lock().unwrap();
+        data.logs.files.insert(name.to_string(), content);
+    }
+
     /// Path of the file where the standard output is written to (in the host).
     pub fn stdout_path(&self) -> PathBuf {
         let data = self.data.lock().unwrap();
@@ -282,9 +388,73 @@ impl Sandbox {
         self.data.lock().unwrap().path().join("box").join(output)
     }
 
+    /// Build the configuration for running `command` interactively inside a sandbox directory that
+    /// has been kept after its execution, with the same mounts and environment of the original
+    /// execution.
+    ///
+    /// The standard streams are not redirected: the command is attached to the terminal of the
+    /// current process. Since the command is usually a shell, forking is always allowed and there
+    /// is no wall time limit. The other limits of the execution are applied only if `with_limits`
+    /// is set.
+    pub fn interactive_configuration(
+        boxdir: &Path,
+        execution: &Execution,
+        command: &[String],
+        with_limits: bool,
+    ) -> Result<SandboxConfiguration, Error> {
+        let (executable, args) = command.split_first().context("Missing command")?;
+        let mut config = SandboxConfiguration::default();
+        // the FIFOs of the original execution are removed together with its group
+        Sandbox::build_command(boxdir, execution, &mut config, None)?;
+        config.stdin = None;
+        config.stdout = None;
+        config.stderr = None;
+        config.executable = executable.into();
+        config.args = args.to_vec();
+        config.syscall_filter(SyscallFilter::build(true, !execution.limits.read_only));
+        config.wall_time_limit = None;
+        if !with_limits {
+            config.time_limit = None;
+            config.memory_limit = None;
+            config.stack_limit = None;
+        }
+        Ok(config)
+    }
+
+    /// Describe how the sandbox at `boxdir` is run with the provided configuration.
+    fn invocation(
+        boxdir: &Path,
+        config: &SandboxConfiguration,
+        runner: Option<Vec<String>>,
+    ) -> SandboxInvocation {
+        SandboxInvocation {
+            runner,
+            boxdir: boxdir.to_owned(),
+            executable: config.executable.clone(),
+            args: config.args.clone(),
+            working_directory: config.working_directory.clone(),
+            env: config.env.iter().cloned().collect(),
+            mounts: config
+                .mount_paths
+                .iter()
+                .map(|mount| SandboxMount {
+                    source: mount.source.clone(),
+                    target: mount.target.clone(),
+                    writable: mount.writable,
+                })
+                .collect(),
+            stdin: config.stdin.clone(),
+            stdout: config.stdout.clone(),
+            stderr: config.stderr.clone(),
+            time_limit: config.time_limit,
+            wall_time_limit: config.wall_time_limit,
+            memory_limit: config.memory_limit,
+            stack_limit: config.stack_limit,
+        }
+    }
+
     /// Find the path in the host corresponding to the path in the sandbox provided.
     fn sandbox_to_host_path(
-        &self,
         path_in_sandbox: &Path,
         boxdir: &Path,
         fifo_dir: Option<&Path>,
@@ -298,7 +468,7 @@ impl Sandbox {
             // Absolute path -> go the box root
             Ok(path) => boxdir.join(path),
             // Relative path -> go to the /box directory
-            Err(_) => self.box_root(boxdir).join(path_in_sandbox),
+            Err(_) => Sandbox::box_root(boxdir).join(path_in_sandbox),
         }
     }
 
@@ -309,7 +479,7 @@ impl Sandbox {
     /// this limitation, only under macos the original path is kept. This leaks some information
     /// about the host, but since the sandbox is pretty fake anyway this is not really a problem.
     #[allow(unused_variables)]
-    fn box_root(&self, boxdir: &Path) -> PathBuf {
+    fn box_root(boxdir: &Path) -> PathBuf {
         #[cfg(not(target_os = "macos"))]
         {
             PathBuf::from("/box")
@@ -321,45 +491,65 @@ impl Sandbox {
     }
 
     /// Build the configuration of the tabox sandbox.
+    ///
+    /// When the execution is run inside a container image the system directories and the
+    /// environment of the worker are not used, and the system commands are looked up in the image.
     fn build_command(
-        &self,
         boxdir: &Path,
         execution: &Execution,
         config: &mut SandboxConfiguration,
         fifo_dir: Option<PathBuf>,
     ) -> Result<(), Error> {
-        let box_root = self.box_root(boxdir);
+        let container = execution.effective_container_image().is_some();
+        let box_root = Sandbox::box_root(boxdir);
         config.working_directory(&box_root);
         // the box directory must be writable otherwise the output files cannot be written
         config.mount(boxdir.join("box"), &box_root, true);
-        config.env("PATH", std::env::var("PATH").unwrap_or_default());
+        if !container {
+            for key in ENV_WHITELIST {
+                config.env(key, std::env::var(key).unwrap_or_default());
+            }
+        }
         if let Some(path) = &execution.stdin_redirect_path {
-            config.stdin(self.sandbox_to_host_path(path, boxdir, fifo_dir.as_deref()));
+            config.stdin(Sandbox::sandbox_to_host_path(
+                path,
+                boxdir,
+                fifo_dir.as_deref(),
+            ));
         } else if execution.stdin.is_some() {
             config.stdin(boxdir.join("stdin"));
         } else {
             config.stdin("/dev/null");
         }
         if let Some(path) = &execution.stdout_redirect_path {
-            config.stdout(self.sandbox_to_host_path(path, boxdir, fifo_dir.as_deref()));
+            config.stdout(Sandbox::sandbox_to_host_path(
+                path,
+                boxdir,
+                fifo_dir.as_deref(),
+            ));
         } else if execution.stdout.is_some() {
             config.stdout(boxdir.join("stdout"));
         } else {
             config.stdout("/dev/null");
         }
         if let Some(path) = &execution.stderr_redirect_path {
-            config.stderr(self.sandbox_to_host_path(path, boxdir, fifo_dir.as_deref()));
+            config.stderr(Sandbox::sandbox_to_host_path(
+                path,
+                boxdir,
+                fifo_dir.as_deref(),
+            ));
         } else if execution.stderr.is_some() {
             config.stderr(boxdir.join("stderr"));
         } else {
             config.stderr("/dev/null");
         }
+        // the executions added to a DAG have copy_env already resolved by the client
         for key in execution.copy_env.iter() {
             if let Ok(value) = std::env::var(key) {
                 config.env(key, value);
             }
         }
-        for (key, value) in execution.env.iter() {
+        for (key, value) in execution.effective_env() {
             config.env(key, value);
         }
 
@@ -389,7 +579,9 @@ impl Sandbox {
             !execution.limits.read_only,
         ));
         // has to be writable for mounting stuff in it
-        config.mount(boxdir.join("etc"), "/etc", true);
+        if !container {
+            config.mount(boxdir.join("etc"), "/etc", true);
+        }
         if let Some(path) = fifo_dir {
             // allow access knowing the path but prevent listing the dir content
             Sandbox::set_permissions(&path, 0o111)
@@ -398,7 +590,8 @@ impl Sandbox {
         }
         // The list of mounted system directories.
         let mut mounted_dirs = HashSet::new();
-        for dir in READABLE_DIRS {
+        let readable_dirs: &[&str] = if container { &[] } else { READABLE_DIRS };
+        for dir in readable_dirs {
             let dir = Path::new(dir);
             if !mounted_dirs.contains(dir) && dir.is_dir() {
                 config.mount(dir, dir, false);
@@ -418,6 +611,12 @@ impl Sandbox {
             config.mount_proc(true);
         }
         match &execution.command {
+            ExecutionCommand::System(cmd) if container => {
+                config.executable(cmd);
+            }
+            ExecutionCommand::Builtin(_) if container => {
+                bail!("The builtin commands cannot be run inside a container image");
+            }
             ExecutionCommand::System(cmd) => {
                 if let Ok(cmd) = which::which(cmd) {
                     // Always mount the directory with the executable.
@@ -434,7 +633,7 @@ impl Sandbox {
             }
             ExecutionCommand::Local(cmd) => {
                 let host_cmd = boxdir.join("box").join(cmd);
-                self.validate_local_executable(&host_cmd).with_context(|| {
+                Sandbox::validate_local_executable(&host_cmd).with_context(|| {
                     format!(
                         "Local sandbox executable validation failed: {}",
                         &host_cmd.display()
@@ -442,6 +641,19 @@ impl Sandbox {
                 })?;
                 config.executable(box_root.join(cmd));
             }
+            ExecutionCommand::Builtin(builtin) => {
+                let tools = find_tools_path();
+                let tools = which::which(&tools)
+                    .with_context(|| format!("task-maker-tools not found at {:?}", tools))?;
+                let tools = std::fs::canonicalize(tools).context("Failed to canonicalize path")?;
+                let path = tools.parent().expect("invalid binary path");
+                if !mounted_dirs.contains(path) {
+                    config.mount(path, path, false);
+                    mounted_dirs.insert(path);
+                }
+                config.executable(tools.clone());
+                config.arg(builtin.subcommand());
+            }
         };
         for arg in execution.args.iter() {
             config.arg(arg);
@@ -452,18 +664,9 @@ impl Sandbox {
         Ok(())
     }
 
-    /// Setup the sandbox directory with all the files required for the execution.
-    fn setup<P: AsRef<Path>>(
-        box_dir: P,
-        execution: &Execution,
-        dep_keys: &HashMap<FileUuid, FileStoreHandle>,
-    ) -> Result<(), Error> {
-        let box_dir = box_dir.as_ref();
-        trace!(
-            "Setting up sandbox at {:?} for '{}'",
-            box_dir,
-            execution.description
-        );
+    /// Setup the skeleton of the sandbox directory: the files and directories that do not depend on
+    /// the execution.
+    fn setup_skeleton(box_dir: &Path) -> Result<(), Error> {
         Self::create_sandbox_dir(box_dir, "box")?;
         // put /etc/passwd inside the sandbox
         Self::create_sandbox_dir(box_dir, "etc")?;
@@ -478,7 +681,22 @@ impl Sandbox {
                 box_dir.display()
             )
         })?;
+        Ok(())
+    }
 
+    /// Setup the sandbox directory with all the files required for the execution. The skeleton of
+    /// the sandbox should already be present.
+    fn setup<P: AsRef<Path>>(
+        box_dir: P,
+        execution: &Execution,
+        dep_keys: &HashMap<FileUuid, FileStoreHandle>,
+    ) -> Result<(), Error> {
+        let box_dir = box_dir.as_ref();
+        trace!(
+            "Setting up sandbox at {:?} for '{}'",
+            box_dir,
+            execution.description
+        );
         if let Some(stdin) = execution.stdin {
             Sandbox::write_sandbox_file(
                 &box_dir.join("stdin"),
@@ -493,14 +711,20 @@ impl Sandbox {
             Sandbox::touch_file(&box_dir.join("stderr"), 0o600)?;
         }
         for (path, input) in execution.inputs.iter() {
-            Sandbox::write_sandbox_file(
-                &box_dir.join("box").join(path),
-                dep_keys
-                    .get(&input.file)
-                    .context("file not provided")?
-                    .path(),
-                input.executable,
-            )?;
+            let source = dep_keys
+                .get(&input.file)
+                .context("file not provided")?
+                .path();
+            if input.directory {
+                Sandbox::write_sandbox_directory(&box_dir.join("box").join(path), source)
+                    .with_context(|| format!("Failed to expand directory {}", path.display()))?;
+            } else {
+                Sandbox::write_sandbox_file(
+                    &box_dir.join("box").join(path),
+                    source,
+                    input.executable,
+                )?;
+            }
         }
         for path in execution.outputs.keys() {
             Sandbox::touch_file(&box_dir.join("box").join(path), 0o600)?;
@@ -523,9 +747,21 @@ impl Sandbox {
     /// Put a file inside the sandbox, creating the directories if needed and making it executable
     /// if needed.
     ///
+    /// When possible the file is not copied: it's hardlinked or, if the filesystem supports it,
+    /// reflinked from the source. A copy is made only if both fail (e.g. the source is on another
+    /// filesystem).
+    ///
     /// The file will have the most restrictive permissions possible:
     /// - `r--------` (0o400) if not executable.
     /// - `r-x------` (0o500) if executable.
+    ///
+    /// A hardlinked file shares the permissions with the source, which is read by everyone:
+    /// - `r--r--r--` (0o444) if not executable.
+    /// - `r-xr-xr-x` (0o555) if executable.
+    ///
+    /// The source is shared with the file store and the other sandboxes, so its permissions are
+    /// never changed: it's hardlinked only if it already has the permissions of the destination,
+    /// otherwise it's reflinked or copied.
     fn write_sandbox_file(dest: &Path, source: &Path, executable: bool) -> Result<(), Error> {
         std::fs::create_dir_all(dest.parent().context("Invalid destination path")?)
             .with_context(|| format!("Failed to create parent directory of {}", dest.display()))?;
@@ -533,7 +769,22 @@ impl Sandbox {
         // conditions. If another thread forks while copying the executable (for example spawning a
         // sandbox of another worker) the file descriptor won't be closed while this sandbox tries
         // to exec the process, failing with "Text file busy".
-        if std::fs::hard_link(source, dest).is_err() {
+        let mode = std::fs::metadata(source)
+            .with_context(|| format!("Failed to get file metadata of {}", source.display()))?
+            .permissions()
+            .mode()
+            & 0o777;
+        let linked_mode = if executable { 0o555 } else { 0o444 };
+        if mode == linked_mode && std::fs::hard_link(source, dest).is_ok() {
+            return Ok(());
+        }
+        if let Err(e) = Sandbox::reflink_file(source, dest) {
+            trace!(
+                "Cannot reflink {} -> {}: {:?}",
+                source.display(),
+                dest.display(),
+                e
+            );
             std::fs::copy(source, dest).with_context(|| {
                 format!("Failed to copy {} -> {}", source.display(), dest.display())
             })?;
@@ -546,6 +797,82 @@ impl Sandbox {
         Ok(())
     }
 
+    /// Expand inside the sandbox the archive of a directory tree, creating `dest` and its content.
+    ///
+    /// Only directories and regular files are allowed in the archive, and their paths cannot leave
+    /// `dest`. The files get the same permissions of the ones written by `write_sandbox_file`.
+    fn write_sandbox_directory(dest: &Path, source: &Path) -> Result<(), Error> {
+        Self::create_sandbox_dir(dest, "")?;
+        let archive = std::fs::File::open(source)
+            .with_context(|| format!("Failed to open {}", source.display()))?;
+        let mut archive = tar::Archive::new(archive);
+        for entry in archive.entries().context("Failed to read the archive")? {
+            let mut entry = entry.context("Failed to read the archive")?;
+            let path = entry
+                .path()
+                .context("Invalid path in the archive")?
+                .into_owned();
+            if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+                bail!("Invalid path in the archive: {}", path.display());
+            }
+            let target = dest.join(&path);
+            match entry.header().entry_type() {
+                tar::EntryType::Directory => Self::create_sandbox_dir(dest, &path)?,
+                tar::EntryType::Regular => {
+                    let executable = entry.header().mode().unwrap_or(0) & 0o111 != 0;
+                    Self::create_sandbox_dir(dest, path.parent().context("Invalid file path")?)?;
+                    let mut file = std::fs::OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(&target)
+                        .with_context(|| format!("Failed to create {}", target.display()))?;
+                    std::io::copy(&mut entry, &mut file)
+                        .with_context(|| format!("Failed to write {}", target.display()))?;
+                    drop(file);
+                    Sandbox::set_permissions(&target, if executable { 0o500 } else { 0o400 })?;
+                }
+                kind => bail!(
+                    "Unsupported entry in the archive: {} ({:?})",
+                    path.display(),
+                    kind
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Clone the content of `source` into a new file at `dest`, sharing the data blocks between the
+    /// two files. This works only on the filesystems with copy-on-write support (e.g. btrfs or xfs).
+    #[cfg(target_os = "linux")]
+    fn reflink_file(source: &Path, dest: &Path) -> Result<(), Error> {
+        use std::os::unix::io::AsRawFd;
+
+        nix::ioctl_write_int!(ficlone, 0x94, 9);
+
+        let source_file = std::fs::File::open(source)
+            .with_context(|| format!("Failed to open {}", source.display()))?;
+        let dest_file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dest)
+            .with_context(|| format!("Failed to create {}", dest.display()))?;
+        // SAFETY: both the file descriptors are valid and open for the duration of the call.
+        let res = unsafe { ficlone(dest_file.as_raw_fd(), source_file.as_raw_fd() as _) };
+        if let Err(e) = res {
+            drop(dest_file);
+            let _ = std::fs::remove_file(dest);
+            bail!("FICLONE failed: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Clone the content of `source` into a new file at `dest`, sharing the data blocks between the
+    /// two files. Not supported on this platform.
+    #[cfg(not(target_os = "linux"))]
+    fn reflink_file(_source: &Path, _dest: &Path) -> Result<(), Error> {
+        bail!("Reflinks are not supported on this platform")
+    }
+
     /// Create an empty file inside the sandbox and chmod-it.
     fn touch_file(dest: &Path, mode: u32) -> Result<(), Error> {
         std::fs::create_dir_all(dest.parent().context("Invalid file path")?)
@@ -567,7 +894,7 @@ impl Sandbox {
     ///
     /// To be a valid executable the file must be _a file_ and should be in a recognized executable
     /// format.
-    fn validate_local_executable<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+    fn validate_local_executable<P: AsRef<Path>>(path: P) -> Result<(), Error> {
         let path = path.as_ref();
         if !path.is_file() {
             bail!("Executable is not a file");
@@ -580,6 +907,94 @@ impl Sandbox {
     }
 }
 
+impl SandboxPool {
+    /// Make a new empty pool of sandboxes, they will be created inside `sandboxes_dir`.
+    pub fn new<P: Into<PathBuf>>(sandboxes_dir: P) -> SandboxPool {
+        SandboxPool {
+            sandboxes_dir: sandboxes_dir.into(),
+            free: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The number of sandbox directories ready to be used.
+    pub fn len(&self) -> usize {
+        self.free.lock().unwrap().len()
+    }
+
+    /// Whether there are no sandbox directories ready to be used.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Take a sandbox directory from the pool, creating a new one if the pool is empty.
+    fn acquire(&self) -> Result<TempDir, Error> {
+        if let Some(boxdir) = self.free.lock().unwrap().pop() {
+            trace!("Reusing sandbox at {:?}", boxdir.path());
+            return Ok(boxdir);
+        }
+        Sandbox::new_boxdir(&self.sandboxes_dir)
+    }
+
+    /// Clear a sandbox directory and put it back in the pool. If the directory cannot be cleared,
+    /// or the pool is full, the directory is deleted.
+    fn release(&self, boxdir: TempDir) {
+        if let Err(e) = SandboxPool::clear(boxdir.path()) {
+            warn!(
+                "Cannot clear the sandbox at {}, not reusing it: {:?}",
+                boxdir.path().display(),
+                e
+            );
+            return;
+        }
+        let mut free = self.free.lock().unwrap();
+        if free.len() < MAX_POOLED_SANDBOXES {
+            free.push(boxdir);
+        }
+    }
+
+    /// Remove from the sandbox directory everything that is not part of the skeleton, and restore
+    /// the skeleton itself since the execution may have changed it.
+    fn clear(box_dir: &Path) -> Result<(), Error> {
+        for entry in std::fs::read_dir(box_dir)
+            .with_context(|| format!("Failed to list {}", box_dir.display()))?
+        {
+            let entry = entry.context("Failed to read directory entry")?;
+            let path = entry.path();
+            if entry.file_name() == "box" {
+                SandboxPool::clear_dir(&path)?;
+            } else if entry.file_type()?.is_dir() {
+                std::fs::remove_dir_all(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            } else {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+        Sandbox::setup_skeleton(box_dir)
+    }
+
+    /// Remove all the content of a directory, keeping the directory itself.
+    fn clear_dir(dir: &Path) -> Result<(), Error> {
+        for entry in
+            std::fs::read_dir(dir).with_context(|| format!("Failed to list {}", dir.display()))?
+        {
+            let entry = entry.context("Failed to read directory entry")?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                // the execution may have removed the write bit from its directories
+                Sandbox::set_permissions(&path, 0o700)?;
+                SandboxPool::clear_dir(&path)?;
+                std::fs::remove_dir(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            } else {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl SandboxData {
     fn path(&self) -> &Path {
         // this unwrap is safe since only `Drop` will remove the boxdir
@@ -594,6 +1009,8 @@ impl Drop for SandboxData {
             self.boxdir.take().map(TempDir::into_path);
         } else if Sandbox::set_permissions(&self.path().join("box"), 0o700).is_err() {
             warn!("Cannot 'chmod 700' the sandbox directory");
+        } else if let (Some(pool), Some(boxdir)) = (self.pool.take(), self.boxdir.take()) {
+            pool.release(boxdir);
         }
     }
 }
@@ -602,13 +1019,14 @@ impl Drop for SandboxData {
 mod tests {
     use std::collections::HashMap;
     use std::path::Path;
+    use std::sync::Arc;
 
     use tabox::configuration::{DirectoryMount, SandboxConfiguration};
     use tabox::syscall_filter::SyscallFilterAction;
 
     use task_maker_dag::{Execution, ExecutionCommand};
 
-    use crate::sandbox::Sandbox;
+    use crate::sandbox::{Sandbox, SandboxPool};
     use crate::ErrorSandboxRunner;
 
     #[test]
@@ -630,6 +1048,129 @@ mod tests {
         assert!(!outfile.parent().unwrap().parent().unwrap().exists()); // the sandbox dir
     }
 
+    #[test]
+    fn test_write_sandbox_file_hardlink() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let source = tmpdir.path().join("source");
+        std::fs::write(&source, "hello").unwrap();
+        std::fs::set_permissions(&source, std::fs::Permissions::from_mode(0o444)).unwrap();
+
+        let dest = tmpdir.path().join("box/dir/dest");
+        Sandbox::write_sandbox_file(&dest, &source, false).unwrap();
+        let source_meta = std::fs::metadata(&source).unwrap();
+        let dest_meta = std::fs::metadata(&dest).unwrap();
+        assert_eq!(source_meta.ino(), dest_meta.ino());
+        assert_eq!(dest_meta.permissions().mode() & 0o777, 0o444);
+
+        // the permissions of the source cannot be changed, so it's copied for being executable
+        let dest2 = tmpdir.path().join("box/dest2");
+        Sandbox::write_sandbox_file(&dest2, &source, true).unwrap();
+        let dest2_meta = std::fs::metadata(&dest2).unwrap();
+        assert_ne!(source_meta.ino(), dest2_meta.ino());
+        assert_eq!(dest2_meta.permissions().mode() & 0o777, 0o500);
+        let source_mode = std::fs::metadata(&source).unwrap().permissions().mode() & 0o777;
+        assert_eq!(source_mode, 0o444);
+        assert_eq!(std::fs::read_to_string(&dest2).unwrap(), "hello");
+
+        let executable = tmpdir.path().join("executable");
+        std::fs::write(&executable, "hello").unwrap();
+        std::fs::set_permissions(&executable, std::fs::Permissions::from_mode(0o555)).unwrap();
+        let executable_meta = std::fs::metadata(&executable).unwrap();
+        let dest3 = tmpdir.path().join("box/dest3");
+        Sandbox::write_sandbox_file(&dest3, &executable, true).unwrap();
+        let dest3_meta = std::fs::metadata(&dest3).unwrap();
+        assert_eq!(executable_meta.ino(), dest3_meta.ino());
+        assert_eq!(dest3_meta.permissions().mode() & 0o777, 0o555);
+
+        // an executable source is not hardlinked as a file that is not executable
+        let dest4 = tmpdir.path().join("box/dest4");
+        Sandbox::write_sandbox_file(&dest4, &executable, false).unwrap();
+        let dest4_meta = std::fs::metadata(&dest4).unwrap();
+        assert_ne!(executable_meta.ino(), dest4_meta.ino());
+        assert_eq!(dest4_meta.permissions().mode() & 0o777, 0o400);
+        let executable_mode = executable_meta.permissions().mode() & 0o777;
+        assert_eq!(executable_mode, 0o555);
+    }
+
+    fn make_archive(entries: &[(&str, tar::EntryType, u32)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, kind, mode) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(*kind);
+            header.set_mode(*mode);
+            if *kind == tar::EntryType::Symlink {
+                header.set_size(0);
+                builder.append_link(&mut header, path, "/etc").unwrap();
+            } else {
+                let content = if kind.is_file() { path.as_bytes() } else { b"" };
+                header.set_size(content.len() as u64);
+                builder.append_data(&mut header, path, content).unwrap();
+            }
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_write_sandbox_directory() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let source = tmpdir.path().join("source.tar");
+        let archive = make_archive(&[
+            ("dict/", tar::EntryType::Directory, 0o755),
+            ("dict/words.txt", tar::EntryType::Regular, 0o644),
+            ("bin/run.sh", tar::EntryType::Regular, 0o755),
+        ]);
+        std::fs::write(&source, archive).unwrap();
+
+        let dest = tmpdir.path().join("box/data");
+        Sandbox::write_sandbox_directory(&dest, &source).unwrap();
+        let words = dest.join("dict/words.txt");
+        assert_eq!(std::fs::read_to_string(&words).unwrap(), "dict/words.txt");
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(words.as_path()), 0o400);
+        assert_eq!(mode(dest.join("bin/run.sh").as_path()), 0o500);
+    }
+
+    #[test]
+    fn test_write_sandbox_directory_symlink() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let source = tmpdir.path().join("source.tar");
+        let archive = make_archive(&[("etc", tar::EntryType::Symlink, 0o777)]);
+        std::fs::write(&source, archive).unwrap();
+
+        let dest = tmpdir.path().join("box/data");
+        let err = Sandbox::write_sandbox_directory(&dest, &source).unwrap_err();
+        assert!(err.to_string().contains("Unsupported entry"));
+        assert!(!dest.join("etc").exists());
+    }
+
+    #[test]
+    fn test_reuse_sandbox_from_pool() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let pool = Arc::new(SandboxPool::new(tmpdir.path()));
+        let mut exec = Execution::new("test", ExecutionCommand::system("true"));
+        exec.output("fooo");
+        exec.limits_mut().read_only(true);
+        let sandbox = Sandbox::from_pool(&pool, &exec, &HashMap::new(), None).unwrap();
+        let outfile = sandbox.output_path(Path::new("fooo"));
+        assert!(outfile.exists());
+        assert!(pool.is_empty());
+        drop(sandbox);
+        assert_eq!(pool.len(), 1);
+        // the directory is kept, but it's cleared
+        assert!(!outfile.exists());
+        assert!(outfile.parent().unwrap().exists());
+
+        let exec = Execution::new("test", ExecutionCommand::system("true"));
+        let sandbox = Sandbox::from_pool(&pool, &exec, &HashMap::new(), None).unwrap();
+        assert_eq!(sandbox.output_path(Path::new("fooo")), outfile);
+        assert!(!outfile.exists());
+        assert!(pool.is_empty());
+    }
+
     #[cfg(not(target_os = "macos"))]
     #[test]
     fn test_command_args() {
@@ -645,11 +1186,8 @@ mod tests {
             .allow_multiprocess()
             .memory(1234);
         exec.env("foo", "bar");
-        let sandbox = Sandbox::new(tmpdir.path(), &exec, &HashMap::new(), None).unwrap();
         let mut config = SandboxConfiguration::default();
-        sandbox
-            .build_command(tmpdir.path(), &exec, &mut config, None)
-            .unwrap();
+        Sandbox::build_command(tmpdir.path(), &exec, &mut config, None).unwrap();
         let extra_time = exec.config().extra_time;
         let total_time = (1.0 + 2.6 + extra_time).ceil() as u64;
         let wall_time = (10.0 + extra_time).ceil() as u64;
@@ -11,6 +11,28 @@ pub trait SandboxRunner: Send + Sync {
     /// Spawn a sandbox with the provided configuration, set the PID as soon as possible and wait
     /// for it to exit. Parse the outcome of the sandbox and return it.
     fn run(&self, config: SandboxConfiguration, pid: Arc<AtomicU32>) -> RawSandboxResult;
+
+    /// Like `run`, but running the execution inside the OCI container `image`. The system
+    /// directories of the worker are not mounted in `config`: the programs are the ones of the
+    /// image.
+    fn run_in_container(
+        &self,
+        image: &str,
+        _config: SandboxConfiguration,
+        _pid: Arc<AtomicU32>,
+    ) -> RawSandboxResult {
+        RawSandboxResult::Error(format!(
+            "This worker cannot run the executions inside the container image {}",
+            image
+        ))
+    }
+
+    /// The command line of a process that runs a sandbox like this runner does, reading its
+    /// configuration from the standard input and printing the result. Used for running a sandbox
+    /// again by hand, `None` if this runner cannot be reproduced this way.
+    fn command(&self) -> Option<Vec<String>> {
+        None
+    }
 }
 
 /// A fake sandbox that don't actually spawn anything and always return an error.
@@ -84,8 +106,21 @@ impl SandboxRunner for UnsafeSandboxRunner {
     }
 }
 
-impl<S: SandboxRunner> SandboxRunner for Arc<S> {
+impl<S: SandboxRunner + ?Sized> SandboxRunner for Arc<S> {
     fn run(&self, conf: SandboxConfiguration, pid: Arc<AtomicU32>) -> RawSandboxResult {
         self.as_ref().run(conf, pid)
     }
+
+    fn run_in_container(
+        &self,
+        image: &str,
+        conf: SandboxConfiguration,
+        pid: Arc<AtomicU32>,
+    ) -> RawSandboxResult {
+        self.as_ref().run_in_container(image, conf, pid)
+    }
+
+    fn command(&self) -> Option<Vec<String>> {
+        self.as_ref().command()
+    }
 }
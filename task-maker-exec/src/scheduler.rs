@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -11,17 +12,75 @@ use uuid::Uuid;
 use task_maker_cache::{Cache, CacheResult};
 use task_maker_dag::{
     CacheMode, DagPriority, ExecutionDAGData, ExecutionGroup, ExecutionGroupUuid, ExecutionResult,
-    ExecutionUuid, FileUuid, Priority, WorkerUuid, HIGH_PRIORITY,
+    ExecutionUuid, File, FileUuid, Priority, ResourceTags, SandboxLogs, ToolchainVersions,
+    WorkerUuid, HIGH_PRIORITY,
 };
 use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey};
 
+use crate::autoscaler::SchedulerLoad;
 use crate::executor::{
-    ExecutionDAGWatchSet, ExecutorStatus, ExecutorWorkerStatus, WorkerCurrentJobStatus, WorkerJob,
+    ExecutionDAGWatchSet, ExecutorStatus, ExecutorWorkerStatus, TransferCounters,
+    WorkerCurrentJobStatus, WorkerJob,
 };
+use crate::scheduling_trace::{SchedulingTrace, TraceRecorder};
 use crate::worker_manager::WorkerManagerInMessage;
 
 pub type ClientUuid = Uuid;
 
+/// Number of bytes of the standard error streamed by each worker that are kept for the status.
+const STDERR_TAIL_LENGTH: usize = 4096;
+/// Maximum number of files remembered for each worker. The store of the worker evicts the old
+/// files anyway, so the oldest ones are forgotten first.
+const MAX_WORKER_FILES: usize = 100_000;
+
+/// The keys of the files a worker should already have in its store, forgetting the oldest ones
+/// after `MAX_WORKER_FILES` files.
+#[derive(Debug, Default)]
+struct WorkerFiles {
+    /// The keys of the files.
+    keys: HashSet<FileStoreKey>,
+    /// The same keys, in the order they were added.
+    order: VecDeque<FileStoreKey>,
+}
+
+impl WorkerFiles {
+    /// Whether the worker should have the file with this key.
+    fn contains(&self, key: &FileStoreKey) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// Remember the files with these keys, forgetting the oldest files if there are too many.
+    fn extend<I: IntoIterator<Item = FileStoreKey>>(&mut self, keys: I) {
+        for key in keys {
+            if self.keys.insert(key.clone()) {
+                self.order.push_back(key);
+            }
+        }
+        while self.order.len() > MAX_WORKER_FILES {
+            if let Some(key) = self.order.pop_front() {
+                self.keys.remove(&key);
+            }
+        }
+    }
+}
+
+/// An execution group waiting in the priority queue of the scheduler. The groups are sorted by the
+/// priority of their DAG, then by their own priority, then by their position in the stable order
+/// of the deterministic DAGs: the order of the fields matters.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct ReadyGroup {
+    /// The priority of the DAG of the group.
+    dag_priority: DagPriority,
+    /// The priority of the group.
+    priority: Priority,
+    /// The position of the group in the stable order of the DAG, the first ones are run first.
+    position: Reverse<usize>,
+    /// The group waiting to be run.
+    group: ExecutionGroupUuid,
+    /// The client owning the group.
+    client: ClientUuid,
+}
+
 /// Information about a client of the scheduler.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, TypeScriptify)]
 pub struct ClientInfo {
@@ -31,6 +90,25 @@ pub struct ClientInfo {
     pub name: String,
 }
 
+/// Information about a worker of the scheduler.
+#[derive(Debug, Clone)]
+pub(crate) struct WorkerInfo {
+    /// The uuid of the worker.
+    pub uuid: WorkerUuid,
+    /// The name of the worker.
+    pub name: String,
+    /// How many times the machine of the worker is faster than the reference machine, if known.
+    pub speed_factor: Option<f64>,
+    /// The versions of the toolchains of the worker.
+    pub toolchains: Arc<ToolchainVersions>,
+    /// The tags of the resources provided by the worker, `None` if it provides all of them.
+    pub resources: Option<Arc<ResourceTags>>,
+    /// The pool of workers the worker belongs to, `None` for the default one.
+    pub pool: Option<String>,
+    /// The machine the worker runs on, shared by the workers on the same machine.
+    pub machine: String,
+}
+
 /// Message coming in for the `Scheduler` from either an `Executor` or a `WorkerManager`.
 pub(crate) enum SchedulerInMessage {
     /// A client asked to evaluate a DAG.
@@ -57,21 +135,34 @@ pub(crate) enum SchedulerInMessage {
         /// The handle to the file in the store.
         handle: FileStoreHandle,
     },
-    /// A worker completed its job.
+    /// A worker completed its job, or its batch of jobs.
     WorkerResult {
         /// The uuid of the worker that was doing the job.
         worker: WorkerUuid,
-        /// The list of the results of all the executions inside the group, in the same order.
-        result: Vec<ExecutionResult>,
-        /// The outputs that the worker produced.
+        /// For each job, in the order they were sent, the list of the results of all the executions
+        /// inside the group, in the same order.
+        result: Vec<Vec<ExecutionResult>>,
+        /// The outputs that the worker produced, for all the jobs.
         outputs: HashMap<FileUuid, FileStoreHandle>,
+        /// When the worker reported the results, before sending the outputs the server didn't
+        /// have.
+        done: Instant,
+    },
+    /// A worker completed its job and is about to send the files it produced that are missing in
+    /// the store, of that total size. The scheduler answers whether they fit in the limit on the
+    /// total output size of the client, if they don't the evaluation of the client is stopped.
+    ReserveOutputSize {
+        /// The uuid of the worker that did the job.
+        worker: WorkerUuid,
+        /// The total size of the files, in bytes.
+        size: u64,
+        /// The channel where to send the answer.
+        reply: Sender<bool>,
     },
     /// A new worker is ready for executing some work.
     WorkerConnected {
-        /// The uuid of the worker.
-        uuid: WorkerUuid,
-        /// The name of the worker.
-        name: String,
+        /// The information about the worker.
+        worker: WorkerInfo,
     },
     /// A previously ready worker is not ready anymore.
     WorkerDisconnected {
@@ -80,6 +171,46 @@ pub(crate) enum SchedulerInMessage {
     },
     /// The executor is asking for the status of the scheduler.
     Status { client: ClientUuid },
+    /// A client asked for the logs of the sandbox of an execution that failed.
+    AskSandboxLogs {
+        /// The identifier of the client.
+        client: ClientUuid,
+        /// The execution whose logs are asked.
+        execution: ExecutionUuid,
+    },
+    /// A client asked to skip some executions that haven't started yet.
+    SkipExecutions {
+        /// The identifier of the client.
+        client: ClientUuid,
+        /// The executions to skip.
+        executions: Vec<ExecutionUuid>,
+    },
+    /// A client asked to cancel the evaluation of another client.
+    CancelEvaluation {
+        /// The identifier of the client asking.
+        client: ClientUuid,
+        /// The identifier of the client whose evaluation is cancelled.
+        target: ClientUuid,
+    },
+    /// A worker sent the logs of the sandbox of an execution, as asked.
+    SandboxLogs {
+        /// The uuid of the worker that ran the execution.
+        worker: WorkerUuid,
+        /// The execution the logs refer to.
+        execution: ExecutionUuid,
+        /// The logs, if the worker still has them.
+        logs: Option<SandboxLogs>,
+    },
+    /// A worker sent what the executions of its current job wrote to their standard error.
+    WorkerStderr {
+        /// The uuid of the worker that is running the executions.
+        worker: WorkerUuid,
+        /// The last part of the new data written to the standard error.
+        stderr: Vec<u8>,
+    },
+    /// Some lookups in the remote cache completed, the groups waiting for them can be looked up
+    /// again.
+    RemoteCacheLookup,
     /// The executor is asking to exit.
     Exit,
 }
@@ -118,10 +249,35 @@ pub(crate) enum SchedulerExecutorMessageData {
         /// This file is urgent, it should be sent to the client ASAP.
         urgent: bool,
     },
+    /// The files produced by the DAG exceeded the limit on their total size, the evaluation is
+    /// being stopped.
+    OutputSizeLimitExceeded {
+        /// The total size of the produced files, in bytes.
+        size: u64,
+        /// The limit on the total size, in bytes.
+        limit: u64,
+    },
+    /// The evaluation has been cancelled by another client, it's being stopped.
+    Cancelled,
+    /// Whether the evaluation a client asked to cancel was running.
+    CancelOutcome {
+        /// Whether the evaluation was found.
+        found: bool,
+    },
+    /// The scheduling trace of the evaluation, sent just before `EvaluationDone` if the DAG asks
+    /// for it.
+    Trace { trace: SchedulingTrace },
     /// The evaluation has been completed.
     EvaluationDone,
     /// The status of the execution.
     Status { status: ExecutorStatus<Duration> },
+    /// The logs of the sandbox of an execution, as asked by the client.
+    SandboxLogs {
+        /// The uuid of the execution.
+        execution: ExecutionUuid,
+        /// The logs, `None` if they are not available.
+        logs: Option<SandboxLogs>,
+    },
 }
 
 /// The actual message sent from the Scheduler to an Executor. Since all the fields of the
@@ -135,8 +291,34 @@ struct ConnectedWorker {
     uuid: WorkerUuid,
     /// The name of the worker.
     name: String,
-    /// The job the worker is currently working on, with the instant of the start.
-    current_job: Option<(ClientUuid, ExecutionGroupUuid, Instant)>,
+    /// How many times the machine of the worker is faster than the reference machine, if known.
+    speed_factor: Option<f64>,
+    /// The versions of the toolchains of the worker.
+    toolchains: Arc<ToolchainVersions>,
+    /// The tags of the resources provided by the worker, `None` if it provides all of them.
+    resources: Option<Arc<ResourceTags>>,
+    /// The pool of workers the worker belongs to, `None` for the default one.
+    pool: Option<String>,
+    /// The machine the worker runs on, shared by the workers on the same machine.
+    machine: String,
+    /// The job the worker is currently working on, with the instant of the start. There is more
+    /// than one group if the worker got a batch of jobs.
+    current_job: Option<(ClientUuid, Vec<ExecutionGroupUuid>, Instant)>,
+    /// Whether the worker has been asked to give back the jobs of its batch that are not started
+    /// yet.
+    released: bool,
+    /// The last bytes the current job wrote to its standard error, if the worker is streaming it.
+    stderr: Vec<u8>,
+}
+
+impl ConnectedWorker {
+    /// Whether this worker provides all the `required` resources.
+    fn provides(&self, required: &ResourceTags) -> bool {
+        match &self.resources {
+            Some(resources) => required.is_subset(resources),
+            None => true,
+        }
+    }
 }
 
 /// The scheduling information about the DAG of a single client.
@@ -157,12 +339,32 @@ struct SchedulerClientData {
     ready_groups: HashSet<ExecutionGroupUuid>,
     /// The set of executions that are currently running in a worker.
     running_groups: HashSet<ExecutionGroupUuid>,
+    /// The set of executions whose results were taken from a stale cache entry, and that are run
+    /// again just for refreshing it. They are either ready or running, but their results are not
+    /// sent to the client. Only the detached clients have them.
+    revalidating_groups: HashSet<ExecutionGroupUuid>,
+    /// Whether this is not an actual client, but the refresh of a stale cache entry of another
+    /// one, which is done in background even after the other client is done or gone.
+    detached: bool,
     /// The list of tasks waiting for some dependencies, each with the list of missing files, when a
     /// task is ready it's removed from the map.
     missing_deps: HashMap<ExecutionGroupUuid, HashSet<FileUuid>>,
     /// The list of known [`FileStoreHandle`](../task_maker_store/struct.FileStoreHandle.html)s.
     /// Storing them here prevents the `FileStore` from flushing them away.
     file_handles: HashMap<FileUuid, FileStoreHandle>,
+    /// The total size, in bytes, of the files produced by the executions so far that the server had
+    /// to store. It's kept only if the DAG has a limit on it.
+    output_size: u64,
+    /// The executions that failed with an internal error, with the worker that ran them. It's kept
+    /// only if the DAG asks for the logs of their sandboxes.
+    failed_executions: HashMap<ExecutionUuid, WorkerUuid>,
+    /// The position of each group in the stable order of the DAG, used for breaking the ties
+    /// between the groups with the same priority. It's kept only if the DAG is deterministic.
+    order: HashMap<ExecutionGroupUuid, usize>,
+    /// The recorder of the scheduling trace, if the DAG asks for it.
+    trace: Option<TraceRecorder>,
+    /// The sets of required resources that no connected worker provided, already reported.
+    unprovided_resources: HashSet<ResourceTags>,
 }
 
 impl SchedulerClientData {
@@ -172,6 +374,24 @@ impl SchedulerClientData {
         dag: ExecutionDAGData,
         callbacks: ExecutionDAGWatchSet,
     ) -> SchedulerClientData {
+        let mut order = HashMap::new();
+        let trace = dag.config.trace.is_some().then(TraceRecorder::new);
+        if dag.config.deterministic {
+            // the uuids are random, the descriptions are the same on every run
+            let mut groups: Vec<&ExecutionGroup> = dag.execution_groups.values().collect();
+            groups.sort_by(|a, b| {
+                a.description.cmp(&b.description).then_with(|| {
+                    let a = a.executions.iter().map(|exec| &exec.description);
+                    let b = b.executions.iter().map(|exec| &exec.description);
+                    a.cmp(b)
+                })
+            });
+            order = groups
+                .into_iter()
+                .enumerate()
+                .map(|(index, group)| (group.uuid, index))
+                .collect();
+        }
         SchedulerClientData {
             name,
             dag,
@@ -179,16 +399,60 @@ impl SchedulerClientData {
             input_of: HashMap::new(),
             ready_groups: HashSet::new(),
             running_groups: HashSet::new(),
+            revalidating_groups: HashSet::new(),
+            detached: false,
             missing_deps: HashMap::new(),
             file_handles: HashMap::new(),
+            output_size: 0,
+            failed_executions: HashMap::new(),
+            order,
+            trace,
+            unprovided_resources: HashSet::new(),
         }
     }
 
+    /// Make a detached client that runs again a group of `origin` just for refreshing its stale
+    /// cache entry, without delaying the end of the evaluation of `origin`.
+    fn revalidation(
+        origin: &SchedulerClientData,
+        group: &ExecutionGroupUuid,
+    ) -> SchedulerClientData {
+        let group = origin.dag.execution_groups[group].clone();
+        let mut config = origin.dag.config.clone();
+        config.trace = None;
+        // the group is ready, so all its dependencies are known
+        let file_handles = group
+            .executions
+            .iter()
+            .flat_map(|exec| exec.dependencies())
+            .filter_map(|file| Some((file, origin.file_handles.get(&file)?.clone())))
+            .collect();
+        let uuid = group.uuid;
+        let dag = ExecutionDAGData {
+            provided_files: HashMap::new(),
+            execution_groups: HashMap::from([(uuid, group)]),
+            config,
+        };
+        let name = format!("{} (refreshing the cache)", origin.name);
+        let mut client = SchedulerClientData::new(name, dag, ExecutionDAGWatchSet::default());
+        client.file_handles = file_handles;
+        client.revalidating_groups.insert(uuid);
+        client.detached = true;
+        client
+    }
+
+    /// The position of the group in the stable order, for sorting the ready groups. The groups of
+    /// the non-deterministic DAGs are all in the same position.
+    fn position(&self, group: &ExecutionGroupUuid) -> Reverse<usize> {
+        Reverse(self.order.get(group).copied().unwrap_or_default())
+    }
+
     /// True if the client has completed all the executions and there are no more ready nor running
     /// ones.
     fn is_done(&self) -> bool {
         self.ready_groups.is_empty()
             && self.running_groups.is_empty()
+            && self.revalidating_groups.is_empty()
             && self.missing_deps.is_empty()
     }
 }
@@ -212,14 +476,37 @@ pub(crate) struct Scheduler {
     executor: Sender<SchedulerExecutorMessage>,
     /// Sender of the messages to the WorkerManager, aka the messages to the workers.
     worker_manager: Sender<WorkerManagerInMessage>,
+    /// The counters of the files exchanged with the clients and the workers.
+    transfers: Arc<TransferCounters>,
+    /// Where the load of the scheduler is published, for the autoscaler.
+    load: Arc<SchedulerLoad>,
 
     /// The priority queue of the ready tasks, waiting for the workers.
-    ready_execs: BinaryHeap<(DagPriority, Priority, ExecutionGroupUuid, ClientUuid)>,
+    ready_execs: BinaryHeap<ReadyGroup>,
+    /// The ready tasks whose cache entry is being looked up in the remote cache. They are put back
+    /// in `ready_execs` when a lookup completes.
+    waiting_remote: Vec<ReadyGroup>,
     /// The data about the clients currently working.
     clients: HashMap<ClientUuid, SchedulerClientData>,
 
     /// The list of the workers that are either ready for some work or already working on a job.
     connected_workers: HashMap<WorkerUuid, ConnectedWorker>,
+    /// The keys of the files each worker should already have in its store, since they were sent to
+    /// it or produced by it. Used for sending the jobs to the workers that won't need to download
+    /// their inputs again.
+    worker_files: HashMap<WorkerUuid, WorkerFiles>,
+    /// The versions of the toolchains of the workers that are still connected, even if they are
+    /// working. The cache is looked up only with the versions all of them agree on.
+    worker_toolchains: HashMap<WorkerUuid, Arc<ToolchainVersions>>,
+    /// The clients that asked to stop, with the workers whose jobs are being killed. The client is
+    /// told the evaluation is done only after all of them have stopped.
+    stopping_clients: HashMap<ClientUuid, HashSet<WorkerUuid>>,
+    /// The executions whose sandbox logs have been asked to a worker, with the client that asked
+    /// them and the worker.
+    asked_sandbox_logs: HashMap<ExecutionUuid, (ClientUuid, WorkerUuid)>,
+    /// The scheduler has been asked to exit, and it's waiting for the detached clients to finish
+    /// refreshing the stale cache entries.
+    exiting: bool,
 }
 
 #[allow(clippy::unnecessary_wraps)]
@@ -233,6 +520,8 @@ impl Scheduler {
         receiver: Receiver<SchedulerInMessage>,
         executor: Sender<SchedulerExecutorMessage>,
         worker_manager: Sender<WorkerManagerInMessage>,
+        transfers: Arc<TransferCounters>,
+        load: Arc<SchedulerLoad>,
     ) -> Scheduler {
         Scheduler {
             file_store,
@@ -240,11 +529,19 @@ impl Scheduler {
             receiver,
             executor,
             worker_manager,
+            transfers,
+            load,
 
             ready_execs: BinaryHeap::new(),
+            waiting_remote: Vec::new(),
             clients: HashMap::new(),
 
             connected_workers: HashMap::new(),
+            worker_files: HashMap::new(),
+            worker_toolchains: HashMap::new(),
+            stopping_clients: HashMap::new(),
+            asked_sandbox_logs: HashMap::new(),
+            exiting: false,
         }
     }
 
@@ -255,7 +552,11 @@ impl Scheduler {
             match message {
                 SchedulerInMessage::Exit => {
                     debug!("Scheduler asked to exit");
-                    break;
+                    if !self.has_detached_clients() {
+                        break;
+                    }
+                    info!("Waiting for the stale cache entries to be refreshed before exiting");
+                    self.exiting = true;
                 }
                 SchedulerInMessage::EvaluateDAG {
                     client,
@@ -277,12 +578,24 @@ impl Scheduler {
                     worker,
                     result,
                     outputs,
+                    done,
                 } => {
-                    self.handle_worker_result(worker, result, outputs)
+                    self.handle_worker_result(worker, result, outputs, done)
                         .context("Failed to handle WorkerResult")?;
                 }
-                SchedulerInMessage::WorkerConnected { uuid, name } => {
-                    self.handle_worker_connected(uuid, name)
+                SchedulerInMessage::ReserveOutputSize {
+                    worker,
+                    size,
+                    reply,
+                } => {
+                    let fits = self
+                        .handle_reserve_output_size(worker, size)
+                        .context("Failed to handle ReserveOutputSize")?;
+                    // the worker may be gone in the meantime
+                    let _ = reply.send(fits);
+                }
+                SchedulerInMessage::WorkerConnected { worker } => {
+                    self.handle_worker_connected(worker)
                         .context("Failed to handle WorkerConnected")?;
                 }
                 SchedulerInMessage::WorkerDisconnected { uuid } => {
@@ -297,6 +610,37 @@ impl Scheduler {
                     self.handle_status_request(client)
                         .context("Failed to handle Status")?;
                 }
+                SchedulerInMessage::AskSandboxLogs { client, execution } => {
+                    self.handle_ask_sandbox_logs(client, execution)
+                        .context("Failed to handle AskSandboxLogs")?;
+                }
+                SchedulerInMessage::SkipExecutions { client, executions } => {
+                    self.handle_skip_executions(client, executions)
+                        .context("Failed to handle SkipExecutions")?;
+                }
+                SchedulerInMessage::CancelEvaluation { client, target } => {
+                    self.handle_cancel_evaluation(client, target)
+                        .context("Failed to handle CancelEvaluation")?;
+                }
+                SchedulerInMessage::SandboxLogs {
+                    worker,
+                    execution,
+                    logs,
+                } => {
+                    self.handle_sandbox_logs(worker, execution, logs)
+                        .context("Failed to handle SandboxLogs")?;
+                }
+                SchedulerInMessage::WorkerStderr { worker, stderr } => {
+                    self.handle_worker_stderr(worker, stderr);
+                }
+                SchedulerInMessage::RemoteCacheLookup => {
+                    self.handle_remote_cache_lookup()
+                        .context("Failed to handle RemoteCacheLookup")?;
+                }
+            }
+            self.publish_load();
+            if self.exiting && !self.has_detached_clients() {
+                break;
             }
         }
         debug!("Scheduler exiting");
@@ -306,6 +650,11 @@ impl Scheduler {
         Ok(())
     }
 
+    /// Whether some stale cache entries are still being refreshed.
+    fn has_detached_clients(&self) -> bool {
+        self.clients.values().any(|client| client.detached)
+    }
+
     /// Handle the client request to evaluate a DAG.
     fn handle_evaluate_dag(
         &mut self,
@@ -331,9 +680,18 @@ impl Scheduler {
             if missing_dep.is_empty() {
                 client_data.missing_deps.remove(&group.uuid);
                 client_data.ready_groups.insert(group.uuid);
+                if let Some(trace) = &mut client_data.trace {
+                    trace.queued(group.uuid);
+                }
+                let position = client_data.position(&group.uuid);
                 for exec in &group.executions {
-                    self.ready_execs
-                        .push((dag_priority, exec.priority, group.uuid, client.uuid));
+                    self.ready_execs.push(ReadyGroup {
+                        dag_priority,
+                        priority: exec.priority,
+                        position,
+                        group: group.uuid,
+                        client: client.uuid,
+                    });
                 }
             }
         }
@@ -367,8 +725,9 @@ impl Scheduler {
     fn handle_worker_result(
         &mut self,
         worker: WorkerUuid,
-        result: Vec<ExecutionResult>,
+        result: Vec<Vec<ExecutionResult>>,
         outputs: HashMap<FileUuid, FileStoreHandle>,
+        done: Instant,
     ) -> Result<(), Error> {
         let worker = match self.connected_workers.remove(&worker) {
             Some(worker) => worker,
@@ -377,8 +736,8 @@ impl Scheduler {
                 return Ok(());
             }
         };
-        let (client_uuid, group_uuid) = match worker.current_job {
-            Some((client, exec, _)) => (client, exec),
+        let (client_uuid, mut group_uuids) = match worker.current_job {
+            Some((client, ref groups, _)) => (client, groups.clone()),
             None => {
                 warn!(
                     "Worker {} ({}) completed a job that wasn't doing",
@@ -387,94 +746,279 @@ impl Scheduler {
                 return Ok(());
             }
         };
-        let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
-            client
-        } else {
-            warn!("Worker completed execution but client is gone");
-            self.assign_jobs()?;
-            self.check_completion(client_uuid)?;
-            return Ok(());
-        };
-        let group = client.dag.execution_groups[&group_uuid].clone();
-        info!(
-            "Worker {:?} completed execution group {}",
-            worker, group.uuid
-        );
-        if group.executions.len() != result.len() {
-            // FIXME: this is a pretty bad way to handle this error, it should never happen but if
-            //        the workers are not trusted it can cause a DoS of the server. Maybe just
-            //        rescheduling the job or disconnecting the client is a better choice.
-            bail!("Invalid worker result: the number of results ({}) does not match the number of executions ({})", result.len(), group.executions.len());
-        }
-        client.running_groups.remove(&group_uuid);
-        self.exec_completed(client_uuid, &group, result, outputs, false)?;
+        self.job_stopped(client_uuid, worker.uuid)?;
+        self.worker_files
+            .entry(worker.uuid)
+            .or_default()
+            .extend(outputs.values().map(|handle| handle.key().clone()));
+        // a released batch has only the results of its first jobs
+        let partial = worker.released && !result.is_empty() && result.len() < group_uuids.len();
+        if group_uuids.len() != result.len() && !partial {
+            bail!(
+                "Invalid worker result: the number of results ({}) does not match the number of jobs ({})",
+                result.len(),
+                group_uuids.len()
+            );
+        }
+        let released = group_uuids.split_off(result.len());
+        if !released.is_empty() {
+            info!(
+                "Worker {} ({}) gave back {} jobs of its batch",
+                worker.name,
+                worker.uuid,
+                released.len()
+            );
+            self.requeue_jobs(client_uuid, released);
+        }
+        if let Some(client) = self.clients.get_mut(&client_uuid) {
+            if let Some(trace) = &mut client.trace {
+                let groups = &client.dag.execution_groups;
+                let jobs: Vec<_> = group_uuids
+                    .iter()
+                    .zip(result.iter())
+                    .map(|(group, result)| {
+                        // the executions of a group run in parallel
+                        let duration = result
+                            .iter()
+                            .map(|result| result.resources.wall_time)
+                            .fold(0.0, f64::max);
+                        (*group, groups[group].description.as_str(), duration)
+                    })
+                    .collect();
+                trace.completed(&jobs, done);
+            }
+        }
+        for (group_uuid, mut result) in group_uuids.into_iter().zip(result) {
+            let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
+                client
+            } else {
+                warn!("Worker completed execution but client is gone");
+                break;
+            };
+            // the time limits were scaled for the worker, report the times of the reference
+            // machine
+            let speed_factor = client
+                .dag
+                .config
+                .time_scaling
+                .speed_factor(worker.speed_factor);
+            if speed_factor != 1.0 {
+                for result in result.iter_mut() {
+                    result.resources.normalize_time(speed_factor);
+                }
+            }
+            let mut group = client.dag.execution_groups[&group_uuid].clone();
+            info!(
+                "Worker {:?} completed execution group {}",
+                worker, group.uuid
+            );
+            if group.executions.len() != result.len() {
+                // FIXME: this is a pretty bad way to handle this error, it should never happen but if
+                //        the workers are not trusted it can cause a DoS of the server. Maybe just
+                //        rescheduling the job or disconnecting the client is a better choice.
+                bail!("Invalid worker result: the number of results ({}) does not match the number of executions ({})", result.len(), group.executions.len());
+            }
+            // the results are cached with the versions of the toolchains of the worker
+            group.set_toolchain_versions(&worker.toolchains);
+            for (exec, result) in group.executions.iter().zip(result.iter_mut()) {
+                result.toolchain.clone_from(&exec.toolchain);
+            }
+            // in a batch the outputs of all the jobs are sent together
+            let group_outputs: HashSet<FileUuid> = group
+                .executions
+                .iter()
+                .flat_map(|exec| exec.outputs())
+                .collect();
+            let outputs = outputs
+                .iter()
+                .filter(|(uuid, _)| group_outputs.contains(uuid))
+                .map(|(uuid, handle)| (*uuid, handle.clone()))
+                .collect();
+            if client.revalidating_groups.remove(&group_uuid) {
+                info!("Cache entry of {} refreshed", group.uuid);
+                if !result.iter().any(|r| r.status.is_internal_error()) {
+                    self.cache_execution(client_uuid, &group, outputs, result);
+                }
+                continue;
+            }
+            client.running_groups.remove(&group_uuid);
+            if client.dag.config.fetch_sandbox_logs {
+                for (exec, result) in group.executions.iter().zip(result.iter()) {
+                    if result.status.is_internal_error() {
+                        client.failed_executions.insert(exec.uuid, worker.uuid);
+                    }
+                }
+            }
+            self.exec_completed(client_uuid, &group, result, outputs, false)?;
+        }
         self.assign_jobs()?;
         self.check_completion(client_uuid)?;
         Ok(())
     }
 
     /// Handle the connection of a worker.
-    fn handle_worker_connected(&mut self, uuid: WorkerUuid, name: String) -> Result<(), Error> {
+    fn handle_worker_connected(&mut self, worker: WorkerInfo) -> Result<(), Error> {
+        let WorkerInfo {
+            uuid,
+            name,
+            speed_factor,
+            toolchains,
+            resources,
+            pool,
+            machine,
+        } = worker;
         info!("Worker {} ({}) connected", name, uuid);
+        let new_worker = !self.worker_toolchains.contains_key(&uuid);
+        if new_worker {
+            self.check_toolchains(&name, &toolchains);
+            self.worker_toolchains.insert(uuid, toolchains.clone());
+        }
         self.connected_workers.insert(
             uuid,
             ConnectedWorker {
                 uuid,
                 name,
+                speed_factor,
+                toolchains,
+                resources,
+                pool,
+                machine,
                 current_job: None,
+                released: false,
+                stderr: Vec::new(),
             },
         );
+        if new_worker {
+            // the versions of the toolchains may be known now, look again in the cache before
+            // sending the executions to the worker
+            self.schedule_cached()?;
+        }
         self.assign_jobs()?;
         Ok(())
     }
 
+    /// Warn if the versions of the toolchains of a new worker differ from the ones of the other
+    /// workers.
+    fn check_toolchains(&self, name: &str, toolchains: &ToolchainVersions) {
+        for (toolchain, version) in toolchains {
+            let other = self.worker_toolchains.iter().find_map(|(uuid, other)| {
+                match other.get(toolchain) {
+                    Some(other) if other != version => Some((uuid, other)),
+                    _ => None,
+                }
+            });
+            if let Some((uuid, other)) = other {
+                warn!(
+                    "Worker {} has '{}' for {}, but worker {} has '{}': the results of the \
+                     executions that use it may differ and won't be taken from the cache",
+                    name, version, toolchain, uuid, other
+                );
+            }
+        }
+    }
+
+    /// The versions of the toolchains that all the connected workers agree on.
+    fn common_toolchains(&self) -> ToolchainVersions {
+        let mut workers = self.worker_toolchains.values();
+        let mut common = match workers.next() {
+            Some(toolchains) => toolchains.as_ref().clone(),
+            None => return ToolchainVersions::new(),
+        };
+        for toolchains in workers {
+            common.retain(|toolchain, version| toolchains.get(toolchain) == Some(version));
+        }
+        common
+    }
+
     /// Handle the disconnection of a worker.
     fn handle_worker_disconnected(&mut self, uuid: WorkerUuid) -> Result<(), Error> {
         info!("Worker {} disconnected", uuid);
+        self.worker_files.remove(&uuid);
+        self.worker_toolchains.remove(&uuid);
+        // the logs of the sandboxes of the worker are gone with it
+        for client in self.clients.values_mut() {
+            client.failed_executions.retain(|_, worker| *worker != uuid);
+        }
+        let gone_logs: Vec<_> = self
+            .asked_sandbox_logs
+            .iter()
+            .filter(|(_, (_, worker))| *worker == uuid)
+            .map(|(execution, _)| *execution)
+            .collect();
+        for execution in gone_logs {
+            self.handle_sandbox_logs(uuid, execution, None)?;
+        }
         if let Some(worker) = self.connected_workers.remove(&uuid) {
-            // reschedule the job if the worker failed
-            if let Some((client_uuid, job, _)) = worker.current_job {
-                let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
-                    client
-                } else {
+            // reschedule the jobs if the worker failed
+            if let Some((client_uuid, jobs, _)) = worker.current_job {
+                if !self.clients.contains_key(&client_uuid) {
                     warn!("Worker was doing something for a gone client");
+                    self.job_stopped(client_uuid, uuid)?;
                     return Ok(());
-                };
-                let priority = client.dag.execution_groups[&job].priority();
-                self.ready_execs
-                    .push((HIGH_PRIORITY, priority, job, client_uuid));
-                client.ready_groups.insert(job);
-                client.running_groups.remove(&job);
+                }
+                self.requeue_jobs(client_uuid, jobs);
             }
         }
         Ok(())
     }
 
+    /// Put back in the queue the jobs of a client that were sent to a worker that didn't run them.
+    fn requeue_jobs(&mut self, client_uuid: ClientUuid, jobs: Vec<ExecutionGroupUuid>) {
+        let Some(client) = self.clients.get_mut(&client_uuid) else {
+            return;
+        };
+        for job in jobs {
+            let position = client.position(&job);
+            if client.revalidating_groups.contains(&job) {
+                self.ready_execs.push(ReadyGroup {
+                    dag_priority: DagPriority::MIN,
+                    priority: Priority::MIN,
+                    position,
+                    group: job,
+                    client: client_uuid,
+                });
+                continue;
+            }
+            let priority = client.dag.execution_groups[&job].priority();
+            self.ready_execs.push(ReadyGroup {
+                dag_priority: HIGH_PRIORITY,
+                priority,
+                position,
+                group: job,
+                client: client_uuid,
+            });
+            client.ready_groups.insert(job);
+            if let Some(trace) = &mut client.trace {
+                trace.queued(job);
+            }
+            client.running_groups.remove(&job);
+        }
+    }
+
     /// Handle the disconnection of a client.
+    ///
+    /// The jobs of the client still running in the workers are killed. If the evaluation wasn't
+    /// completed yet, the client is told it's done only after all the workers have stopped them.
     fn handle_client_disconnected(&mut self, client_uuid: ClientUuid) -> Result<(), Error> {
         info!("Client {} disconnected", client_uuid);
-        if let Some(client) = self.clients.get(&client_uuid) {
-            if !client.is_done() {
-                warn!("The client's evaluation wasn't completed yet");
-                // Even if the computation has not been completed, send the EvaluationDone so that
-                // the executor can exit cleanly.
-                self.executor
-                    .send((client_uuid, SchedulerExecutorMessageData::EvaluationDone))
-                    .context("Failed to send EvaluationDone to the executor")?;
-            }
-        }
-        self.clients.remove(&client_uuid);
+        let was_done = match self.clients.remove(&client_uuid) {
+            Some(client) => client.is_done(),
+            None => true,
+        };
         let mut remaining = BinaryHeap::new();
-        while let Some((dag_priority, priority, exec, client)) = self.ready_execs.pop() {
-            if self.clients.contains_key(&client) {
-                remaining.push((dag_priority, priority, exec, client));
+        while let Some(ready) = self.ready_execs.pop() {
+            if self.clients.contains_key(&ready.client) {
+                remaining.push(ready);
             }
         }
         self.ready_execs = remaining;
         // stop the jobs that are still running in the workers
+        let mut stopping = HashSet::new();
         for (uuid, worker) in self.connected_workers.iter() {
-            if let Some((owner, exec, _)) = worker.current_job {
-                if owner == client_uuid {
+            if let Some((owner, execs, _)) = &worker.current_job {
+                // stopping a job of a batch stops the whole batch
+                let exec = execs[0];
+                if *owner == client_uuid {
                     warn!(
                         "Worker {} is doing {} owned by disconnected client, killing",
                         uuid, exec
@@ -485,12 +1029,95 @@ impl Scheduler {
                             job: exec,
                         })
                         .map_err(|e| anyhow!("Failed to send StopWorkerJob to worker: {:?}", e))?;
+                    stopping.insert(*uuid);
                 }
             }
         }
+        if !was_done {
+            warn!("The client's evaluation wasn't completed yet");
+            // Even if the computation has not been completed, send the EvaluationDone so that
+            // the executor can exit cleanly, but only after the workers have stopped.
+            if stopping.is_empty() {
+                self.client_stopped(client_uuid)?;
+            } else {
+                self.stopping_clients.insert(client_uuid, stopping);
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle the request of a client to cancel the evaluation of `target`: its jobs are killed as
+    /// if it disconnected, and it's told that its evaluation has been cancelled.
+    fn handle_cancel_evaluation(
+        &mut self,
+        client_uuid: ClientUuid,
+        target: ClientUuid,
+    ) -> Result<(), Error> {
+        let found = self.clients.contains_key(&target);
+        if found {
+            warn!(
+                "Client {} is cancelling the evaluation of {}",
+                client_uuid, target
+            );
+            if let Err(e) = self
+                .executor
+                .send((target, SchedulerExecutorMessageData::Cancelled))
+            {
+                warn!("Cannot tell the client it was cancelled: {:?}", e);
+            }
+            self.handle_client_disconnected(target)?;
+        }
+        self.executor
+            .send((
+                client_uuid,
+                SchedulerExecutorMessageData::CancelOutcome { found },
+            ))
+            .context("Failed to send CancelOutcome to the executor")?;
         Ok(())
     }
 
+    /// Mark the job of a stopping client on the worker as stopped. When all the jobs of the client
+    /// are stopped the client is told the evaluation is done.
+    fn job_stopped(&mut self, client_uuid: ClientUuid, worker: WorkerUuid) -> Result<(), Error> {
+        let workers = match self.stopping_clients.get_mut(&client_uuid) {
+            Some(workers) => workers,
+            None => return Ok(()),
+        };
+        workers.remove(&worker);
+        if workers.is_empty() {
+            self.stopping_clients.remove(&client_uuid);
+            self.client_stopped(client_uuid)?;
+        }
+        Ok(())
+    }
+
+    /// Complete the evaluation of a client that asked to stop, writing the cache to disk so that
+    /// the results obtained so far are not lost.
+    fn client_stopped(&mut self, client_uuid: ClientUuid) -> Result<(), Error> {
+        debug!("All the jobs of client {} are stopped", client_uuid);
+        if let Err(e) = self.cache.flush() {
+            warn!("Failed to store the cache: {:?}", e);
+        }
+        self.executor
+            .send((client_uuid, SchedulerExecutorMessageData::EvaluationDone))
+            .context("Failed to send EvaluationDone to the executor")?;
+        Ok(())
+    }
+
+    /// Publish the current load of the scheduler for the autoscaler.
+    fn publish_load(&self) {
+        let busy_workers = self
+            .connected_workers
+            .values()
+            .filter(|worker| worker.current_job.is_some())
+            .count();
+        self.load.update(
+            self.ready_execs.len(),
+            self.connected_workers.len(),
+            busy_workers,
+        );
+    }
+
     /// Handle the status request of a client.
     fn handle_status_request(&mut self, client_uuid: ClientUuid) -> Result<(), Error> {
         let mut ready_execs = 0;
@@ -507,23 +1134,42 @@ impl Scheduler {
                     uuid: worker.uuid,
                     name: worker.name.clone(),
                     current_job: worker.current_job.as_ref().and_then(
-                        |(client_uuid, exec_uuid, start)| {
-                            let client = self.clients.get(client_uuid)?;
-                            let exec = &client.dag.execution_groups[exec_uuid];
+                        |(owner, exec_uuids, start)| {
+                            let client = self.clients.get(owner)?;
+                            let exec = &client.dag.execution_groups[&exec_uuids[0]];
+                            let job = if exec_uuids.len() > 1 {
+                                format!(
+                                    "{} (and {} more in the batch)",
+                                    exec.description,
+                                    exec_uuids.len() - 1
+                                )
+                            } else {
+                                exec.description.clone()
+                            };
+                            // the other clients are not allowed to see the output of the executions
+                            let stderr = if *owner == client_uuid && !worker.stderr.is_empty() {
+                                Some(String::from_utf8_lossy(&worker.stderr).into_owned())
+                            } else {
+                                None
+                            };
                             Some(WorkerCurrentJobStatus {
-                                job: exec.description.clone(),
+                                job,
                                 client: ClientInfo {
-                                    uuid: *client_uuid,
+                                    uuid: *owner,
                                     name: client.name.clone(),
                                 },
                                 duration: start.elapsed(),
+                                stderr,
                             })
                         },
                     ),
+                    transfers: self.transfers.peer(worker.uuid),
                 })
                 .collect(),
             ready_execs,
             waiting_execs,
+            transfers: self.transfers.total(),
+            client_transfers: self.transfers.peer(client_uuid).reversed(),
         };
 
         if let Err(e) = self
@@ -535,16 +1181,181 @@ impl Scheduler {
         Ok(())
     }
 
-    /// Check if the client has completed the evaluation, if so tell the client we are done.
-    fn check_completion(&self, client_uuid: ClientUuid) -> Result<(), Error> {
-        let client = if let Some(client) = self.clients.get(&client_uuid) {
+    /// Handle the request of a client for the logs of the sandbox of an execution, forwarding it to
+    /// the worker that ran the execution. If that worker is not known anymore the client is told
+    /// the logs are not available.
+    fn handle_ask_sandbox_logs(
+        &mut self,
+        client_uuid: ClientUuid,
+        execution: ExecutionUuid,
+    ) -> Result<(), Error> {
+        let worker = self
+            .clients
+            .get(&client_uuid)
+            .and_then(|client| client.failed_executions.get(&execution));
+        let worker = match worker {
+            Some(worker) => *worker,
+            None => {
+                debug!("The logs of the sandbox of {} are not available", execution);
+                let message = SchedulerExecutorMessageData::SandboxLogs {
+                    execution,
+                    logs: None,
+                };
+                if let Err(e) = self.executor.send((client_uuid, message)) {
+                    warn!("Cannot send the sandbox logs to the client: {:?}", e);
+                }
+                return Ok(());
+            }
+        };
+        self.asked_sandbox_logs
+            .insert(execution, (client_uuid, worker));
+        self.worker_manager
+            .send(WorkerManagerInMessage::AskSandboxLogs { worker, execution })
+            .map_err(|e| anyhow!("Failed to send AskSandboxLogs to worker: {:?}", e))?;
+        Ok(())
+    }
+
+    /// Handle the logs of the sandbox of an execution sent by a worker, forwarding them to the
+    /// client that asked them.
+    fn handle_sandbox_logs(
+        &mut self,
+        worker: WorkerUuid,
+        execution: ExecutionUuid,
+        logs: Option<SandboxLogs>,
+    ) -> Result<(), Error> {
+        let client_uuid = match self.asked_sandbox_logs.get(&execution) {
+            Some((client, asked_worker)) if *asked_worker == worker => *client,
+            _ => {
+                warn!(
+                    "Worker {} sent the sandbox logs of {}, which were not asked",
+                    worker, execution
+                );
+                return Ok(());
+            }
+        };
+        self.asked_sandbox_logs.remove(&execution);
+        let message = SchedulerExecutorMessageData::SandboxLogs { execution, logs };
+        if let Err(e) = self.executor.send((client_uuid, message)) {
+            warn!("Cannot send the sandbox logs to the client: {:?}", e);
+        }
+        Ok(())
+    }
+
+    /// Handle the standard error streamed by a worker, keeping only its last part for the status.
+    fn handle_worker_stderr(&mut self, worker: WorkerUuid, stderr: Vec<u8>) {
+        let worker = match self.connected_workers.get_mut(&worker) {
+            Some(worker) if worker.current_job.is_some() => worker,
+            _ => return,
+        };
+        worker.stderr.extend_from_slice(&stderr);
+        if worker.stderr.len() > STDERR_TAIL_LENGTH {
+            let excess = worker.stderr.len() - STDERR_TAIL_LENGTH;
+            worker.stderr.drain(..excess);
+        }
+    }
+
+    /// Handle the completion of some lookups in the remote cache, looking up again the groups that
+    /// were waiting for them. The groups that have been skipped or whose client is gone meanwhile
+    /// are dropped.
+    fn handle_remote_cache_lookup(&mut self) -> Result<(), Error> {
+        for ready in std::mem::take(&mut self.waiting_remote) {
+            let still_ready = self
+                .clients
+                .get(&ready.client)
+                .is_some_and(|client| client.ready_groups.contains(&ready.group));
+            if still_ready {
+                self.ready_execs.push(ready);
+            }
+        }
+        self.schedule_cached()?;
+        self.assign_jobs()?;
+        Ok(())
+    }
+
+    /// Handle the request of a client to skip some executions. The groups of the executions that
+    /// are still waiting for their dependencies or for a worker are skipped, and so are all the
+    /// executions that depend on their outputs. The running and completed ones are left untouched.
+    fn handle_skip_executions(
+        &mut self,
+        client_uuid: ClientUuid,
+        executions: Vec<ExecutionUuid>,
+    ) -> Result<(), Error> {
+        let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
+            client
+        } else {
+            // client is gone, dont worry to much about it
+            return Ok(());
+        };
+        let group_of: HashMap<ExecutionUuid, ExecutionGroupUuid> = client
+            .dag
+            .execution_groups
+            .values()
+            .flat_map(|group| group.executions.iter().map(|exec| (exec.uuid, group.uuid)))
+            .collect();
+        let mut skipped_groups = HashSet::new();
+        let mut failed_files = Vec::new();
+        for execution in executions {
+            let group_uuid = match group_of.get(&execution) {
+                Some(group_uuid) => *group_uuid,
+                None => {
+                    warn!("Client asked to skip the unknown execution {}", execution);
+                    continue;
+                }
+            };
+            let pending = client.missing_deps.remove(&group_uuid).is_some()
+                || client.ready_groups.remove(&group_uuid);
+            if !pending {
+                continue;
+            }
+            info!("Skipping execution group {} as asked", group_uuid);
+            skipped_groups.insert(group_uuid);
+            for exec in &client.dag.execution_groups[&group_uuid].executions {
+                if client.callbacks.executions.contains(&exec.uuid) {
+                    if let Err(e) = self.executor.send((
+                        client_uuid,
+                        SchedulerExecutorMessageData::ExecutionSkipped {
+                            execution: exec.uuid,
+                        },
+                    )) {
+                        warn!("Cannot tell the client the execution was skipped: {:?}", e);
+                    }
+                }
+                failed_files.extend(exec.outputs());
+            }
+        }
+        if skipped_groups.is_empty() {
+            return Ok(());
+        }
+        self.ready_execs
+            .retain(|ready| ready.client != client_uuid || !skipped_groups.contains(&ready.group));
+        for file in failed_files {
+            self.file_failed(client_uuid, file)?;
+        }
+        self.check_completion(client_uuid)?;
+        Ok(())
+    }
+
+    /// Check if the client has completed the evaluation, if so tell the client we are done, sending
+    /// the scheduling trace first if it was recorded.
+    fn check_completion(&mut self, client_uuid: ClientUuid) -> Result<(), Error> {
+        let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
             client
         } else {
             // client is gone, dont worry to much about it
             return Ok(());
         };
+        if client.is_done() && client.detached {
+            self.clients.remove(&client_uuid);
+            return Ok(());
+        }
         if client.is_done() {
             debug!("Computation completed for client: {}", client_uuid);
+            if let Some(trace) = client.trace.take() {
+                let trace = trace.finish();
+                self.executor
+                    .send((client_uuid, SchedulerExecutorMessageData::Trace { trace }))
+                    .context("Failed to send Trace to the executor")?;
+            }
             self.executor
                 .send((client_uuid, SchedulerExecutorMessageData::EvaluationDone))
                 .context("Failed to send EvaluationDone to the executor")?;
@@ -625,12 +1436,16 @@ impl Scheduler {
                 files.remove(&file);
                 if files.is_empty() {
                     client.missing_deps.remove(group_uuid);
-                    self.ready_execs.push((
-                        HIGH_PRIORITY,
-                        group.priority(),
-                        *group_uuid,
-                        client_uuid,
-                    ));
+                    if let Some(trace) = &mut client.trace {
+                        trace.queued(*group_uuid);
+                    }
+                    self.ready_execs.push(ReadyGroup {
+                        dag_priority: HIGH_PRIORITY,
+                        priority: group.priority(),
+                        position: client.position(group_uuid),
+                        group: *group_uuid,
+                        client: client_uuid,
+                    });
                     client.ready_groups.insert(*group_uuid);
                 }
             }
@@ -671,6 +1486,45 @@ impl Scheduler {
         Ok(())
     }
 
+    /// Handle the request of a worker to store the files produced by its current job, of `size`
+    /// bytes in total. Returns whether they fit in the limit on the total output size of the client
+    /// owning the job, if they don't the evaluation of the client is stopped.
+    fn handle_reserve_output_size(&mut self, worker: WorkerUuid, size: u64) -> Result<bool, Error> {
+        let client_uuid = match self.connected_workers.get(&worker) {
+            Some(ConnectedWorker {
+                current_job: Some((client, _, _)),
+                ..
+            }) => *client,
+            _ => return Ok(true),
+        };
+        let Some(client) = self.clients.get_mut(&client_uuid) else {
+            return Ok(true);
+        };
+        let Some(limit) = client.dag.config.max_total_output_size else {
+            return Ok(true);
+        };
+        let size = client.output_size + size;
+        if size <= limit {
+            client.output_size = size;
+            return Ok(true);
+        }
+        warn!(
+            "Client {} exceeded the output size limit ({} > {}), stopping",
+            client_uuid, size, limit
+        );
+        if let Err(e) = self.executor.send((
+            client_uuid,
+            SchedulerExecutorMessageData::OutputSizeLimitExceeded { size, limit },
+        )) {
+            warn!(
+                "Cannot tell the client the output limit was exceeded: {:?}",
+                e
+            );
+        }
+        self.handle_client_disconnected(client_uuid)?;
+        Ok(false)
+    }
+
     /// Mark an execution as completed, sending the notification to the client and marking all the
     /// produced files as done. Add the execution to the cache and schedule all the new executions
     /// that become ready.
@@ -690,9 +1544,18 @@ impl Scheduler {
         };
         for (exec, res) in group.executions.iter().zip(result.iter()) {
             if client.callbacks.executions.contains(&exec.uuid) {
+                let mut res = res.clone();
+                if client.dag.config.keep_full_output {
+                    let key_of = |file: &Option<File>| {
+                        let handle = outputs.get(&file.as_ref()?.uuid)?;
+                        Some(handle.key().to_string())
+                    };
+                    res.stdout_key = key_of(&exec.stdout);
+                    res.stderr_key = key_of(&exec.stderr);
+                }
                 let mex = SchedulerExecutorMessageData::ExecutionDone {
                     execution: exec.uuid,
-                    result: res.clone(),
+                    result: res,
                 };
                 if let Err(e) = self.executor.send((client_uuid, mex)) {
                     warn!("Cannot tell the client the execution is done: {:?}", e);
@@ -702,7 +1565,6 @@ impl Scheduler {
         for (uuid, handle) in outputs.iter() {
             client.file_handles.insert(*uuid, handle.clone());
         }
-
         let successful = result.iter().all(|r| r.status.is_success());
         let internal_error = result.iter().any(|r| r.status.is_internal_error());
         if !from_cache && !internal_error {
@@ -743,16 +1605,16 @@ impl Scheduler {
             // client is gone, dont worry to much about it
             return;
         };
-        let mut file_keys: HashMap<FileUuid, FileStoreKey> = group
+        // the outputs may not be the ones known by the client, if the execution is refreshing a
+        // stale cache entry.
+        let mut file_handles: HashMap<FileUuid, FileStoreHandle> = group
             .executions
             .iter()
             .flat_map(|e| e.dependencies())
-            .map(|f| (f, client.file_handles[&f].key().clone()))
+            .map(|f| (f, client.file_handles[&f].clone()))
             .collect();
-        for output in group.executions.iter().flat_map(|e| e.outputs()) {
-            file_keys.insert(output, outputs[&output].key().clone());
-        }
-        self.cache.insert(group, &client.file_handles, result);
+        file_handles.extend(outputs);
+        self.cache.insert(group, &file_handles, result);
     }
 
     /// Look at all the ready executions and mark as completed all the ones that are inside the
@@ -760,9 +1622,15 @@ impl Scheduler {
     fn schedule_cached(&mut self) -> Result<(), Error> {
         let mut not_cached = BinaryHeap::new();
         let mut cached = Vec::new();
+        let mut revalidations = Vec::new();
+        let toolchains = self.common_toolchains();
 
-        for (dag_priority, priority, group_uuid, client_uuid) in self.ready_execs.iter() {
-            let client = if let Some(client) = self.clients.get_mut(client_uuid) {
+        // visit the groups in priority order, so that the results from the cache are sent to the
+        // clients in a stable order
+        let ready_execs = std::mem::take(&mut self.ready_execs).into_sorted_vec();
+        for ready in ready_execs.into_iter().rev() {
+            let (group_uuid, client_uuid) = (ready.group, ready.client);
+            let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
                 client
             } else {
                 // client is gone, dont worry to much about it
@@ -770,32 +1638,81 @@ impl Scheduler {
             };
             let dag = &client.dag;
             let cache_mode = &dag.config.cache_mode;
-            // disable the cache for the execution
-            if let CacheMode::Nothing = cache_mode {
-                not_cached.push((*dag_priority, *priority, *group_uuid, *client_uuid));
+            let mut group = dag.execution_groups[&group_uuid].clone();
+            // the compilations are looked up in the artifact cache even if the cache of the
+            // executions is disabled
+            let use_cache = !matches!(cache_mode, CacheMode::Nothing)
+                && Scheduler::is_cacheable(&group, cache_mode);
+            let use_artifacts = dag.config.artifact_cache && group.artifact().is_some();
+            // the versions of the toolchains are not known until a worker connects
+            let uses_toolchain = group.executions.iter().any(|e| e.toolchain.is_some());
+            if !(use_cache || use_artifacts)
+                || client.revalidating_groups.contains(&group_uuid)
+                || (uses_toolchain && self.worker_toolchains.is_empty())
+            {
+                not_cached.push(ready);
                 continue;
             }
-            let group = dag.execution_groups[group_uuid].clone();
-            if !Scheduler::is_cacheable(&group, cache_mode) {
-                not_cached.push((*dag_priority, *priority, group.uuid, *client_uuid));
-                continue;
+            group.set_toolchain_versions(&toolchains);
+            let mut result = CacheResult::Miss;
+            if use_cache {
+                let ttl = dag.config.cache_ttl.ttl(group.tag().as_ref());
+                result =
+                    self.cache
+                        .get(&group, &client.file_handles, self.file_store.as_ref(), ttl);
+            }
+            if use_artifacts && matches!(result, CacheResult::Miss) {
+                result =
+                    self.cache
+                        .get_artifact(&group, &client.file_handles, self.file_store.as_ref());
+                if matches!(result, CacheResult::Hit { .. }) {
+                    info!("Compilation {} found in the artifact cache", group.uuid);
+                }
             }
-            let result = self
-                .cache
-                .get(&group, &client.file_handles, self.file_store.as_ref());
             match result {
-                CacheResult::Hit { result, outputs } => {
+                CacheResult::Hit {
+                    result,
+                    outputs,
+                    stale,
+                } => {
                     info!("Execution {} is a cache hit!", group.uuid);
                     client.ready_groups.remove(&group.uuid);
-                    cached.push((*client_uuid, group, result, outputs));
+                    if let Some(trace) = &mut client.trace {
+                        trace.cache_hit(group.uuid, &group.description);
+                    }
+                    if stale {
+                        // use the stale result anyway, but run the group again with the lowest
+                        // priority for refreshing the cache entry, detached from the client so
+                        // that its evaluation can end before.
+                        info!("Cache entry of {} is stale, refreshing it", group.uuid);
+                        let revalidation = SchedulerClientData::revalidation(client, &group.uuid);
+                        let revalidation_uuid = ClientUuid::new_v4();
+                        not_cached.push(ReadyGroup {
+                            dag_priority: DagPriority::MIN,
+                            priority: Priority::MIN,
+                            position: ready.position,
+                            group: group.uuid,
+                            client: revalidation_uuid,
+                        });
+                        revalidations.push((revalidation_uuid, revalidation));
+                    }
+                    cached.push((client_uuid, group, result, outputs));
                 }
                 CacheResult::Miss => {
-                    not_cached.push((*dag_priority, *priority, group.uuid, *client_uuid));
+                    not_cached.push(ready);
+                }
+                CacheResult::Pending => {
+                    debug!(
+                        "Execution {} is being looked up in the remote cache",
+                        group.uuid
+                    );
+                    self.waiting_remote.push(ready);
                 }
             }
         }
 
         self.ready_execs = not_cached;
+        self.clients.extend(revalidations);
         for (client, exec, result, outputs) in cached.into_iter() {
             self.exec_completed(client, &exec, result, outputs, true)?;
         }
@@ -813,63 +1730,453 @@ impl Scheduler {
         true
     }
 
-    /// Give to each free worker a job from the ready executions.
-    fn assign_jobs(&mut self) -> Result<(), Error> {
-        for (worker_uuid, worker) in self.connected_workers.iter_mut() {
-            if worker.current_job.is_some() {
+    /// Whether `other` can be sent to a worker in the same batch as `head`: they both have a
+    /// single execution, without FIFOs, and they share the same limits and tag.
+    fn can_batch(head: &ExecutionGroup, other: &ExecutionGroup) -> bool {
+        let is_simple =
+            |group: &ExecutionGroup| group.executions.len() == 1 && group.fifo.is_empty();
+        is_simple(head)
+            && is_simple(other)
+            && head.executions[0].limits == other.executions[0].limits
+            && head.tag() == other.tag()
+            && head.required_resources() == other.required_resources()
+            && head.concurrency_classes() == other.concurrency_classes()
+    }
+
+    /// The concurrency classes of the jobs that are running on each machine.
+    fn busy_concurrency_classes(&self) -> HashMap<String, ResourceTags> {
+        let mut busy: HashMap<String, ResourceTags> = HashMap::new();
+        for worker in self.connected_workers.values() {
+            let Some((client_uuid, group_uuids, _)) = &worker.current_job else {
                 continue;
+            };
+            let Some(client) = self.clients.get(client_uuid) else {
+                continue;
+            };
+            for group_uuid in group_uuids {
+                if let Some(group) = client.dag.execution_groups.get(group_uuid) {
+                    busy.entry(worker.machine.clone())
+                        .or_default()
+                        .extend(group.concurrency_classes());
+                }
             }
-            let (_, _, group_uuid, client_uuid) = match self.ready_execs.pop() {
+        }
+        busy
+    }
+
+    /// Give to each free worker a job from the ready executions.
+    ///
+    /// The workers pull a single job at a time, so the jobs are never queued on a busy worker while
+    /// another one is idle. The jobs are taken in priority order, and each one is given to the free
+    /// worker that already has most of its input files, avoiding to send them again.
+    ///
+    /// If the DAG allows it, the small jobs that are next in the queue are sent to the same worker
+    /// in a single batch together with the first one.
+    ///
+    /// A job is given only to a worker of the pool selected by its DAG that provides all the
+    /// resources it requires: the jobs that no free worker can run stay in the queue, waiting for a
+    /// compatible worker. If none of the connected workers can ever run them a warning is emitted.
+    /// The jobs of a deterministic DAG are run one at a time.
+    ///
+    /// A job with some concurrency classes is not given to the workers on a machine that is already
+    /// running a job of one of those classes.
+    ///
+    /// The workers that are still free afterwards steal the jobs not started yet of the batches of
+    /// the busy workers, see [`Scheduler::steal_jobs`].
+    fn assign_jobs(&mut self) -> Result<(), Error> {
+        let mut free_workers: Vec<WorkerUuid> = self
+            .connected_workers
+            .values()
+            .filter(|worker| worker.current_job.is_none())
+            .map(|worker| worker.uuid)
+            .collect();
+        let mut busy_classes = self.busy_concurrency_classes();
+        let mut waiting = vec![];
+        while !free_workers.is_empty() {
+            let ready = match self.ready_execs.pop() {
                 Some(exec) => exec,
                 None => break,
             };
-            trace!("Assigning {} to worker {}", group_uuid, worker_uuid);
-            worker.current_job = Some((client_uuid, group_uuid, Instant::now()));
+            let (group_uuid, client_uuid) = (ready.group, ready.client);
             let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
                 client
             } else {
                 // client is gone, dont worry to much about it
                 continue;
             };
-            client.ready_groups.remove(&group_uuid);
-            client.running_groups.insert(group_uuid);
-            let group = &client.dag.execution_groups[&group_uuid];
-            let mut dep_keys: HashMap<FileUuid, FileStoreKey> = HashMap::new();
-            for exec in &group.executions {
-                for file in exec.dependencies() {
-                    let handle = client
-                        .file_handles
-                        .get(&file)
-                        .unwrap_or_else(|| panic!("Unknown file key of {}", file))
-                        .key()
-                        .clone();
-                    dep_keys.insert(file, handle);
-                }
-            }
-            let job = WorkerJob {
-                group: group.clone(),
-                dep_keys,
-            };
+            if client.dag.config.deterministic && !client.running_groups.is_empty() {
+                waiting.push(ready);
+                continue;
+            }
+            let required = client.dag.execution_groups[&group_uuid].required_resources();
+            let classes = client.dag.execution_groups[&group_uuid].concurrency_classes();
+            let pool = &client.dag.config.pool;
+            let connected_workers = &self.connected_workers;
+            let compatible: Vec<usize> = free_workers
+                .iter()
+                .enumerate()
+                .filter(|(_, worker)| {
+                    let worker = &connected_workers[*worker];
+                    let busy = busy_classes
+                        .get(&worker.machine)
+                        .is_some_and(|busy| !busy.is_disjoint(&classes));
+                    &worker.pool == pool && worker.provides(&required) && !busy
+                })
+                .map(|(index, _)| index)
+                .collect();
+            if compatible.is_empty() {
+                trace!(
+                    "No free worker of pool {:?} provides {:?} required by {} without running {:?}",
+                    pool,
+                    required,
+                    group_uuid,
+                    classes
+                );
+                let provided = connected_workers
+                    .values()
+                    .any(|worker| &worker.pool == pool && worker.provides(&required));
+                if !provided && client.unprovided_resources.insert(required.clone()) {
+                    warn!(
+                        "No worker of pool {:?} provides {:?}, the executions of client {} that \
+                         require them are waiting for such a worker to connect",
+                        pool, required, client.name
+                    );
+                }
+                waiting.push(ready);
+                continue;
+            }
+            let mut group_uuids = vec![group_uuid];
+            if let Some(max_batch_size) = client.dag.config.max_batch_size {
+                let head = &client.dag.execution_groups[&group_uuid];
+                // look only at the first jobs of the queue, for not delaying the others too much
+                let mut skipped = vec![];
+                while group_uuids.len() < max_batch_size && skipped.len() < max_batch_size {
+                    let next = match self.ready_execs.pop() {
+                        Some(next) => next,
+                        None => break,
+                    };
+                    if next.client == client_uuid
+                        && Scheduler::can_batch(head, &client.dag.execution_groups[&next.group])
+                    {
+                        group_uuids.push(next.group);
+                    } else {
+                        skipped.push(next);
+                    }
+                }
+                self.ready_execs.extend(skipped);
+            }
+            let mut jobs = Vec::with_capacity(group_uuids.len());
+            for group_uuid in &group_uuids {
+                let group = &client.dag.execution_groups[group_uuid];
+                let mut dep_keys: HashMap<FileUuid, FileStoreKey> = HashMap::new();
+                for exec in &group.executions {
+                    for file in exec.dependencies() {
+                        let handle = client
+                            .file_handles
+                            .get(&file)
+                            .unwrap_or_else(|| panic!("Unknown file key of {}", file))
+                            .key()
+                            .clone();
+                        dep_keys.insert(file, handle);
+                    }
+                }
+                jobs.push(WorkerJob {
+                    group: group.clone(),
+                    dep_keys,
+                    stream_stderr: client.dag.config.stream_stderr,
+                });
+            }
+            let worker_files = &self.worker_files;
+            let index = compatible
+                .into_iter()
+                .max_by_key(|index| {
+                    worker_files.get(&free_workers[*index]).map_or(0, |files| {
+                        jobs.iter()
+                            .flat_map(|job| job.dep_keys.values())
+                            .filter(|key| files.contains(key))
+                            .count()
+                    })
+                })
+                .expect("There is at least a compatible free worker");
+            let worker_uuid = free_workers.swap_remove(index);
+            let worker = self
+                .connected_workers
+                .get_mut(&worker_uuid)
+                .expect("Free worker is not connected");
+            let speed_factor = client
+                .dag
+                .config
+                .time_scaling
+                .speed_factor(worker.speed_factor);
+            if speed_factor != 1.0 {
+                for job in jobs.iter_mut() {
+                    for exec in job.group.executions.iter_mut() {
+                        exec.limits.scale_time(speed_factor);
+                    }
+                }
+            }
+            trace!("Assigning {:?} to worker {}", group_uuids, worker_uuid);
+            if let Some(trace) = &mut client.trace {
+                for group_uuid in &group_uuids {
+                    let name = &client.dag.execution_groups[group_uuid].description;
+                    trace.dispatched(*group_uuid, name, &worker.name);
+                }
+            }
+            if !classes.is_empty() {
+                busy_classes
+                    .entry(worker.machine.clone())
+                    .or_default()
+                    .extend(classes);
+            }
+            worker.current_job = Some((client_uuid, group_uuids.clone(), Instant::now()));
+            worker.stderr.clear();
+            let mut started = vec![];
+            for group_uuid in &group_uuids {
+                if !client.revalidating_groups.contains(group_uuid) {
+                    client.ready_groups.remove(group_uuid);
+                    client.running_groups.insert(*group_uuid);
+                    started.push(*group_uuid);
+                }
+            }
+            self.worker_files
+                .entry(worker_uuid)
+                .or_default()
+                .extend(jobs.iter().flat_map(|job| job.dep_keys.values().cloned()));
             self.worker_manager
                 .send(WorkerManagerInMessage::WorkerJob {
-                    worker: *worker_uuid,
-                    job,
+                    worker: worker_uuid,
+                    jobs,
                 })
                 .map_err(|e| anyhow!("Failed to send WorkerJob to worker: {:?}", e))?;
-            for exec in &group.executions {
-                if client.callbacks.executions.contains(&exec.uuid) {
-                    if let Err(e) = self.executor.send((
-                        client_uuid,
-                        SchedulerExecutorMessageData::ExecutionStarted {
-                            execution: exec.uuid,
-                            worker: *worker_uuid,
-                        },
-                    )) {
-                        warn!("Cannot tell the client the execution started: {:?}", e);
+            for group_uuid in started {
+                for exec in &client.dag.execution_groups[&group_uuid].executions {
+                    if client.callbacks.executions.contains(&exec.uuid) {
+                        if let Err(e) = self.executor.send((
+                            client_uuid,
+                            SchedulerExecutorMessageData::ExecutionStarted {
+                                execution: exec.uuid,
+                                worker: worker_uuid,
+                            },
+                        )) {
+                            warn!("Cannot tell the client the execution started: {:?}", e);
+                        }
                     }
                 }
             }
         }
+        self.ready_execs.extend(waiting);
+        self.steal_jobs(&free_workers)
+    }
+
+    /// Ask the busy workers to give back the jobs of their batches that are not started yet, so
+    /// that the free workers don't stay idle while a batch is run one job after the other. Each free
+    /// worker steals from the busy worker with the longest batch it can run, and every batch is
+    /// released at most once. The jobs given back are queued again, and assigned like the other
+    /// ready jobs to the free workers that already have most of their inputs.
+    fn steal_jobs(&mut self, free_workers: &[WorkerUuid]) -> Result<(), Error> {
+        for free_worker in free_workers {
+            let free_worker = &self.connected_workers[free_worker];
+            let victim = self
+                .connected_workers
+                .values()
+                .filter(|worker| !worker.released)
+                .filter_map(|worker| {
+                    let (client_uuid, groups, _) = worker.current_job.as_ref()?;
+                    let client = self.clients.get(client_uuid)?;
+                    // all the jobs of a batch require the same resources
+                    let head = client.dag.execution_groups.get(&groups[0])?;
+                    let compatible = free_worker.pool == client.dag.config.pool
+                        && free_worker.provides(&head.required_resources());
+                    (groups.len() > 1 && compatible).then_some((groups.len(), worker.uuid))
+                })
+                .max_by_key(|(len, _)| *len);
+            let Some((_, victim)) = victim else {
+                continue;
+            };
+            let worker = self
+                .connected_workers
+                .get_mut(&victim)
+                .expect("Busy worker is not connected");
+            let (_, groups, _) = worker.current_job.as_ref().expect("Worker is not busy");
+            trace!("Asking worker {} to release its batch", victim);
+            worker.released = true;
+            self.worker_manager
+                .send(WorkerManagerInMessage::ReleaseWorkerJobs {
+                    worker: victim,
+                    job: groups[0],
+                })
+                .map_err(|e| anyhow!("Failed to send ReleaseWorkerJobs to worker: {:?}", e))?;
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::mpsc::channel;
+
+    use tempfile::TempDir;
+
+    use task_maker_dag::{Execution, ExecutionCommand, ExecutionDAG};
+
+    use super::*;
+
+    /// Make a scheduler that is driven by calling its handlers directly, returning also the
+    /// receiver of the messages for the worker manager.
+    fn scheduler(dir: &Path) -> (Scheduler, Receiver<WorkerManagerInMessage>) {
+        let file_store = Arc::new(FileStore::new(dir.join("store"), 1000, 1000).unwrap());
+        let cache = Cache::new(dir.join("cache")).unwrap();
+        let (_, receiver) = channel();
+        let (executor_tx, _) = channel();
+        let (worker_manager_tx, worker_manager_rx) = channel();
+        let scheduler = Scheduler::new(
+            file_store,
+            cache,
+            receiver,
+            executor_tx,
+            worker_manager_tx,
+            Default::default(),
+            Default::default(),
+        );
+        (scheduler, worker_manager_rx)
+    }
+
+    /// Connect a worker providing only the `resources`.
+    fn connect_worker(scheduler: &mut Scheduler, name: &str, resources: &[&str]) -> WorkerUuid {
+        let uuid = WorkerUuid::new_v4();
+        let resources = resources.iter().map(|tag| tag.to_string()).collect();
+        scheduler
+            .handle_worker_connected(WorkerInfo {
+                uuid,
+                name: name.into(),
+                speed_factor: None,
+                toolchains: Default::default(),
+                resources: Some(Arc::new(resources)),
+                pool: None,
+                machine: name.into(),
+            })
+            .unwrap();
+        uuid
+    }
+
+    #[test]
+    fn test_required_resources() {
+        let dir = TempDir::new().unwrap();
+        let (mut scheduler, worker_manager_rx) = scheduler(dir.path());
+        connect_worker(&mut scheduler, "cpu", &["python3.11"]);
+
+        let mut dag = ExecutionDAG::new();
+        let mut exec = Execution::new("needs a gpu", ExecutionCommand::system("true"));
+        exec.require_resource("gpu");
+        dag.add_execution(exec);
+        let client = ClientInfo {
+            uuid: ClientUuid::new_v4(),
+            name: "client".into(),
+        };
+        scheduler
+            .handle_evaluate_dag(client, dag.data, Default::default())
+            .unwrap();
+        // the only worker does not provide the gpu
+        assert!(worker_manager_rx.try_recv().is_err());
+        assert_eq!(scheduler.ready_execs.len(), 1);
+
+        let gpu = connect_worker(&mut scheduler, "gpu", &["gpu"]);
+        match worker_manager_rx.try_recv() {
+            Ok(WorkerManagerInMessage::WorkerJob { worker, jobs }) => {
+                assert_eq!(worker, gpu);
+                assert_eq!(jobs.len(), 1);
+                assert_eq!(jobs[0].group.description, "needs a gpu");
+            }
+            _ => panic!("The job was not given to the worker with the gpu"),
+        }
+        assert!(scheduler.ready_execs.is_empty());
+    }
+
+    #[test]
+    fn test_steal_batch() {
+        let dir = TempDir::new().unwrap();
+        let (mut scheduler, worker_manager_rx) = scheduler(dir.path());
+        let first = connect_worker(&mut scheduler, "first", &[]);
+
+        let mut dag = ExecutionDAG::new();
+        dag.config_mut().max_batch_size(Some(3));
+        for i in 0..3 {
+            let exec = Execution::new(format!("job {}", i), ExecutionCommand::system("true"));
+            dag.add_execution(exec);
+        }
+        let client = ClientInfo {
+            uuid: ClientUuid::new_v4(),
+            name: "client".into(),
+        };
+        scheduler
+            .handle_evaluate_dag(client, dag.data, Default::default())
+            .unwrap();
+        let batch = match worker_manager_rx.try_recv() {
+            Ok(WorkerManagerInMessage::WorkerJob { worker, jobs }) => {
+                assert_eq!(worker, first);
+                jobs
+            }
+            _ => panic!("The batch was not given to the worker"),
+        };
+        assert_eq!(batch.len(), 3);
+
+        // the new worker is free, so the first one is asked to give back the rest of its batch
+        let second = connect_worker(&mut scheduler, "second", &[]);
+        match worker_manager_rx.try_recv() {
+            Ok(WorkerManagerInMessage::ReleaseWorkerJobs { worker, job }) => {
+                assert_eq!(worker, first);
+                assert_eq!(job, batch[0].group.uuid);
+            }
+            _ => panic!("The batch was not released"),
+        }
+        // a batch is released only once
+        scheduler.assign_jobs().unwrap();
+        assert!(worker_manager_rx.try_recv().is_err());
+
+        // the first worker ran only the first job, the other ones go to the free worker
+        let result = vec![vec![ExecutionResult::default()]];
+        scheduler
+            .handle_worker_result(first, result, HashMap::new(), Instant::now())
+            .unwrap();
+        match worker_manager_rx.try_recv() {
+            Ok(WorkerManagerInMessage::WorkerJob { worker, jobs }) => {
+                assert_eq!(worker, second);
+                let stolen: Vec<_> = jobs.iter().map(|job| job.group.uuid).collect();
+                let released: Vec<_> = batch[1..].iter().map(|job| job.group.uuid).collect();
+                assert_eq!(stolen, released);
+            }
+            _ => panic!("The released jobs were not given to the free worker"),
+        }
+    }
+
+    #[test]
+    fn test_worker_files_forget_oldest() {
+        let key = |i: usize| FileStoreKey::from_content(&i.to_le_bytes());
+        let mut files = WorkerFiles::default();
+        files.extend((0..MAX_WORKER_FILES).map(key));
+        // already known files are not added twice
+        files.extend([key(0)]);
+        assert!(files.contains(&key(0)));
+        files.extend([key(MAX_WORKER_FILES)]);
+        assert!(!files.contains(&key(0)));
+        assert!(files.contains(&key(1)));
+        assert!(files.contains(&key(MAX_WORKER_FILES)));
+        assert_eq!(files.keys.len(), MAX_WORKER_FILES);
+        assert_eq!(files.order.len(), MAX_WORKER_FILES);
+    }
+
+    #[test]
+    fn test_worker_files_forgotten_on_disconnect() {
+        let dir = TempDir::new().unwrap();
+        let (mut scheduler, _worker_manager_rx) = scheduler(dir.path());
+        let worker = connect_worker(&mut scheduler, "worker", &[]);
+        scheduler
+            .worker_files
+            .entry(worker)
+            .or_default()
+            .extend([FileStoreKey::from_content(b"file")]);
+        scheduler.handle_worker_disconnected(worker).unwrap();
+        assert!(scheduler.worker_files.is_empty());
+    }
+}
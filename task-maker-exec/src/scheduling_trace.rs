@@ -0,0 +1,311 @@
+//! The scheduling trace of the evaluation of a DAG, recorded by the scheduler and exported in the
+//! Chrome tracing format, the JSON format read by `chrome://tracing` and by Perfetto.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use task_maker_dag::ExecutionGroupUuid;
+
+/// What an interval of the scheduling trace is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TraceSpanKind {
+    /// The execution group was ready, waiting in the queue for a free worker.
+    Queued,
+    /// The job was sent to the worker, which was receiving its input files. The end of this
+    /// interval is estimated from the duration of the executions.
+    Inputs,
+    /// The execution group was running on the worker.
+    Run,
+    /// The worker was sending the outputs of the job to the server.
+    Outputs,
+    /// The results of the execution group were taken from the cache, this interval has no
+    /// duration.
+    CacheHit,
+}
+
+impl TraceSpanKind {
+    /// The category of the events of this kind in the Chrome trace.
+    fn category(&self) -> &'static str {
+        match self {
+            TraceSpanKind::Queued => "queue",
+            TraceSpanKind::Inputs => "inputs",
+            TraceSpanKind::Run => "run",
+            TraceSpanKind::Outputs => "outputs",
+            TraceSpanKind::CacheHit => "cache",
+        }
+    }
+}
+
+/// An interval of the scheduling trace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceSpan {
+    /// What the interval is about.
+    pub kind: TraceSpanKind,
+    /// The description of the execution group.
+    pub name: String,
+    /// The name of the worker, if the interval is about a job sent to a worker.
+    pub worker: Option<String>,
+    /// The start of the interval, since the start of the evaluation.
+    pub start: Duration,
+    /// The duration of the interval.
+    pub duration: Duration,
+}
+
+/// The scheduling trace of the evaluation of a DAG on a server.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SchedulingTrace {
+    /// The intervals of the trace, in the order they were recorded.
+    pub spans: Vec<TraceSpan>,
+}
+
+impl SchedulingTrace {
+    /// Convert the traces of the servers that evaluated the DAG into a Chrome trace.
+    ///
+    /// Each server is a process of the trace. The queue of the server is its first thread, where
+    /// the waiting executions are asynchronous events and the cache hits are instant events. Each
+    /// worker is another thread, where the jobs are split in the transfer of the inputs, the run
+    /// and the transfer of the outputs.
+    pub fn to_chrome_trace(traces: &[SchedulingTrace]) -> Value {
+        let mut events = vec![];
+        let mut next_id = 0;
+        for (pid, trace) in traces.iter().enumerate() {
+            events.push(json!({
+                "name": "process_name",
+                "ph": "M",
+                "pid": pid,
+                "args": { "name": format!("Server {}", pid + 1) },
+            }));
+            events.push(json!({
+                "name": "thread_name",
+                "ph": "M",
+                "pid": pid,
+                "tid": 0,
+                "args": { "name": "Queue" },
+            }));
+            let mut workers: HashMap<&str, usize> = HashMap::new();
+            for span in &trace.spans {
+                let ts = span.start.as_micros() as u64;
+                let dur = span.duration.as_micros() as u64;
+                let cat = span.kind.category();
+                let worker = match &span.worker {
+                    Some(worker) => worker.as_str(),
+                    None => {
+                        if span.kind == TraceSpanKind::CacheHit {
+                            events.push(json!({
+                                "name": span.name, "cat": cat, "ph": "i", "s": "t",
+                                "ts": ts, "pid": pid, "tid": 0,
+                            }));
+                        } else {
+                            next_id += 1;
+                            for (ph, ts) in [("b", ts), ("e", ts + dur)] {
+                                events.push(json!({
+                                    "name": span.name, "cat": cat, "ph": ph, "id": next_id,
+                                    "ts": ts, "pid": pid, "tid": 0,
+                                }));
+                            }
+                        }
+                        continue;
+                    }
+                };
+                let num_workers = workers.len();
+                let tid = *workers.entry(worker).or_insert_with(|| {
+                    events.push(json!({
+                        "name": "thread_name",
+                        "ph": "M",
+                        "pid": pid,
+                        "tid": num_workers + 1,
+                        "args": { "name": worker },
+                    }));
+                    num_workers + 1
+                });
+                events.push(json!({
+                    "name": span.name, "cat": cat, "ph": "X",
+                    "ts": ts, "dur": dur, "pid": pid, "tid": tid,
+                }));
+            }
+        }
+        json!({ "traceEvents": events, "displayTimeUnit": "ms" })
+    }
+
+    /// Write the traces of the servers that evaluated the DAG to a file, in the Chrome tracing
+    /// format.
+    pub fn write_chrome_trace(path: &Path, traces: &[SchedulingTrace]) -> Result<(), Error> {
+        let trace = SchedulingTrace::to_chrome_trace(traces);
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create the trace file {}", path.display()))?;
+        serde_json::to_writer(std::io::BufWriter::new(file), &trace)
+            .context("Failed to write the trace")?;
+        Ok(())
+    }
+}
+
+/// A job sent to a worker, used for recording its intervals when it completes.
+#[derive(Debug)]
+struct DispatchedJob {
+    /// When the job was sent to the worker.
+    instant: Instant,
+    /// The name of the worker.
+    worker: String,
+}
+
+/// The recorder of the scheduling trace of a DAG, kept by the scheduler.
+#[derive(Debug)]
+pub(crate) struct TraceRecorder {
+    /// The start of the evaluation.
+    start: Instant,
+    /// When each of the ready groups has been added to the queue.
+    queued: HashMap<ExecutionGroupUuid, Instant>,
+    /// The groups that are being executed by a worker.
+    dispatched: HashMap<ExecutionGroupUuid, DispatchedJob>,
+    /// The trace recorded so far.
+    trace: SchedulingTrace,
+}
+
+impl TraceRecorder {
+    /// Start recording the trace of an evaluation.
+    pub fn new() -> TraceRecorder {
+        TraceRecorder {
+            start: Instant::now(),
+            queued: HashMap::new(),
+            dispatched: HashMap::new(),
+            trace: SchedulingTrace::default(),
+        }
+    }
+
+    /// The group is ready and has been added to the queue.
+    pub fn queued(&mut self, group: ExecutionGroupUuid) {
+        self.queued.insert(group, Instant::now());
+    }
+
+    /// The results of the group have been taken from the cache.
+    pub fn cache_hit(&mut self, group: ExecutionGroupUuid, name: &str) {
+        let now = Instant::now();
+        self.close_queued(group, name, now);
+        self.span(TraceSpanKind::CacheHit, name, None, now, now);
+    }
+
+    /// The group has been sent to a worker.
+    pub fn dispatched(&mut self, group: ExecutionGroupUuid, name: &str, worker: &str) {
+        let now = Instant::now();
+        self.close_queued(group, name, now);
+        let job = DispatchedJob {
+            instant: now,
+            worker: worker.to_string(),
+        };
+        self.dispatched.insert(group, job);
+    }
+
+    /// A worker completed a job, or a batch of jobs. For each job there is its group, its name and
+    /// how long it ran, in seconds. `done` is when the worker reported the results, before sending
+    /// the outputs to the server.
+    ///
+    /// The jobs of a batch are run one after the other, so the start of the first one is estimated
+    /// going back from `done` by the duration of all of them.
+    pub fn completed(&mut self, jobs: &[(ExecutionGroupUuid, &str, f64)], done: Instant) {
+        let now = Instant::now();
+        let dispatched: Vec<_> = jobs
+            .iter()
+            .filter_map(|(group, _, _)| self.dispatched.remove(group))
+            .collect();
+        let (first, (_, first_name, _)) = match (dispatched.first(), jobs.first()) {
+            (Some(first), Some(job)) => (first, job),
+            _ => return,
+        };
+        let total: f64 = jobs.iter().map(|(_, _, duration)| duration).sum();
+        let mut start = done
+            .checked_sub(Duration::from_secs_f64(total))
+            .unwrap_or(first.instant)
+            .max(first.instant);
+        let worker = Some(first.worker.as_str());
+        self.span(
+            TraceSpanKind::Inputs,
+            first_name,
+            worker,
+            first.instant,
+            start,
+        );
+        for (_, name, duration) in jobs {
+            let end = (start + Duration::from_secs_f64(*duration)).min(done);
+            self.span(TraceSpanKind::Run, name, worker, start, end);
+            start = end;
+        }
+        self.span(TraceSpanKind::Outputs, first_name, worker, done, now);
+    }
+
+    /// Stop recording and return the trace.
+    pub fn finish(self) -> SchedulingTrace {
+        self.trace
+    }
+
+    /// Record the interval the group waited in the queue, if it was in the queue.
+    fn close_queued(&mut self, group: ExecutionGroupUuid, name: &str, now: Instant) {
+        if let Some(queued) = self.queued.remove(&group) {
+            self.span(TraceSpanKind::Queued, name, None, queued, now);
+        }
+    }
+
+    /// Add an interval to the trace.
+    fn span(
+        &mut self,
+        kind: TraceSpanKind,
+        name: &str,
+        worker: Option<&str>,
+        from: Instant,
+        to: Instant,
+    ) {
+        self.trace.spans.push(TraceSpan {
+            kind,
+            name: name.to_string(),
+            worker: worker.map(String::from),
+            start: from.saturating_duration_since(self.start),
+            duration: to.saturating_duration_since(from),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_batch() {
+        let mut recorder = TraceRecorder::new();
+        let a = ExecutionGroupUuid::new_v4();
+        let b = ExecutionGroupUuid::new_v4();
+        recorder.queued(a);
+        recorder.queued(b);
+        recorder.dispatched(a, "a", "worker");
+        recorder.dispatched(b, "b", "worker");
+        recorder.completed(&[(a, "a", 0.0), (b, "b", 0.0)], Instant::now());
+        let trace = recorder.finish();
+        let kinds: Vec<_> = trace.spans.iter().map(|span| span.kind).collect();
+        use TraceSpanKind::*;
+        assert_eq!(kinds, vec![Queued, Queued, Inputs, Run, Run, Outputs]);
+        assert_eq!(trace.spans[4].name, "b");
+        assert!(trace.spans[0].worker.is_none());
+        assert_eq!(trace.spans[3].worker.as_deref(), Some("worker"));
+    }
+
+    #[test]
+    fn test_chrome_trace() {
+        let mut recorder = TraceRecorder::new();
+        let a = ExecutionGroupUuid::new_v4();
+        let b = ExecutionGroupUuid::new_v4();
+        recorder.queued(a);
+        recorder.cache_hit(a, "a");
+        recorder.dispatched(b, "b", "worker");
+        recorder.completed(&[(b, "b", 0.0)], Instant::now());
+        let trace = SchedulingTrace::to_chrome_trace(&[recorder.finish()]);
+        let events = trace["traceEvents"].as_array().unwrap();
+        let phases: Vec<_> = events.iter().map(|e| e["ph"].as_str().unwrap()).collect();
+        // process and queue names, queued a, cache hit a, worker name, inputs, run, outputs of b
+        assert_eq!(phases, vec!["M", "M", "b", "e", "i", "M", "X", "X", "X"]);
+        assert_eq!(events[5]["args"]["name"], "worker");
+        assert_eq!(events[7]["tid"], 1);
+    }
+}
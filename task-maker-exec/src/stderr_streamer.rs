@@ -0,0 +1,141 @@
+//! Streaming of the standard error of the running executions from the worker to the server.
+//!
+//! The streamer periodically reads what the executions wrote to their standard error since the last
+//! read, and sends only the last part of it to the server. This keeps the traffic bounded even when
+//! the processes write a lot, at the cost of losing some of the output: the stream is meant for
+//! watching the progress of the executions, not for collecting their full output.
+
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use ductile::ChannelSender;
+
+use crate::proto::WorkerClientMessage;
+
+/// Interval between two reads of the standard error of the executions.
+const STREAM_INTERVAL: Duration = Duration::from_millis(500);
+/// Maximum number of bytes sent to the server at each interval. When the executions write more than
+/// this, only the last bytes are sent.
+const MAX_STREAM_CHUNK: usize = 1024;
+
+/// A thread that streams to the server the standard error of some executions, until it's stopped.
+pub(crate) struct StderrStreamer {
+    /// Channel for stopping the streamer thread.
+    stop: crossbeam_channel::Sender<()>,
+    /// The streamer thread.
+    handle: JoinHandle<()>,
+}
+
+impl StderrStreamer {
+    /// Start streaming the content of the files at `paths`, where the executions write their
+    /// standard error. The files may not exist yet, or at all if the execution doesn't capture its
+    /// standard error.
+    pub fn start(
+        paths: Vec<PathBuf>,
+        sender: ChannelSender<WorkerClientMessage>,
+    ) -> Option<StderrStreamer> {
+        let (stop, stopped) = crossbeam_channel::bounded(1);
+        let handle = std::thread::Builder::new()
+            .name("Stderr streamer".into())
+            .spawn(move || {
+                let clock = crossbeam_channel::tick(STREAM_INTERVAL);
+                let mut offsets = HashMap::new();
+                loop {
+                    crossbeam_channel::select! {
+                        recv(clock) -> _ => {}
+                        recv(stopped) -> _ => break,
+                    }
+                    let chunk = read_new_data(&paths, &mut offsets, MAX_STREAM_CHUNK);
+                    if chunk.is_empty() {
+                        continue;
+                    }
+                    if sender.send(WorkerClientMessage::Stderr(chunk)).is_err() {
+                        warn!("Failed to send the stderr of the executions to the server");
+                        break;
+                    }
+                }
+            });
+        match handle {
+            Ok(handle) => Some(StderrStreamer { stop, handle }),
+            Err(e) => {
+                warn!("Failed to start the stderr streamer: {:?}", e);
+                None
+            }
+        }
+    }
+
+    /// Stop the streamer, waiting for its thread to exit.
+    pub fn stop(self) {
+        let _ = self.stop.send(());
+        if let Err(e) = self.handle.join() {
+            warn!("Stderr streamer panicked: {:?}", e);
+        }
+    }
+}
+
+/// Read the data appended to the files since the last call, keeping in `offsets` how much of each
+/// file has already been read. Only the last `limit` bytes of the new data are returned.
+fn read_new_data(paths: &[PathBuf], offsets: &mut HashMap<PathBuf, u64>, limit: usize) -> Vec<u8> {
+    let mut result = Vec::new();
+    for path in paths {
+        let mut file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            // the file is not there yet
+            Err(_) => continue,
+        };
+        let len = match file.metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(_) => continue,
+        };
+        let offset = offsets.entry(path.clone()).or_default();
+        if len <= *offset {
+            continue;
+        }
+        // skip the data that would be dropped anyway
+        let start = (*offset).max(len.saturating_sub(limit as u64));
+        let mut data = Vec::new();
+        let read = file
+            .seek(SeekFrom::Start(start))
+            .and_then(|_| file.take(len - start).read_to_end(&mut data));
+        if let Err(e) = read {
+            warn!("Failed to read {}: {:?}", path.display(), e);
+            continue;
+        }
+        *offset = len;
+        result.extend_from_slice(&data);
+    }
+    if result.len() > limit {
+        result.drain(..result.len() - limit);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_read_new_data() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("stderr");
+        let missing = tmpdir.path().join("missing");
+        let paths = vec![path.clone(), missing];
+        let mut offsets = HashMap::new();
+        assert!(read_new_data(&paths, &mut offsets, 10).is_empty());
+
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(b"12345").unwrap();
+        assert_eq!(read_new_data(&paths, &mut offsets, 10), b"12345");
+        assert!(read_new_data(&paths, &mut offsets, 10).is_empty());
+
+        file.write_all(b"abcdefghijklmno").unwrap();
+        assert_eq!(read_new_data(&paths, &mut offsets, 10), b"fghijklmno");
+        file.write_all(b"xyz").unwrap();
+        assert_eq!(read_new_data(&paths, &mut offsets, 10), b"xyz");
+    }
+}
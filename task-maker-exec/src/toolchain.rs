@@ -0,0 +1,46 @@
+//! Probe the versions of the toolchains (compilers and interpreters) of a worker.
+//!
+//! The workers run the probes of all the known toolchains at startup and report their versions to
+//! the server. The versions are part of the cache keys of the executions that use the toolchains,
+//! and they are reported in their results.
+
+use std::process::{Command, Stdio};
+
+use task_maker_dag::{Toolchain, ToolchainVersions};
+
+/// Run the probes of the toolchains, returning the versions of the ones that are available. The
+/// version is the first non-empty line printed by the probe, either on stdout or on stderr.
+pub fn probe_toolchains(toolchains: &[Toolchain]) -> ToolchainVersions {
+    let mut versions = ToolchainVersions::new();
+    for toolchain in toolchains {
+        match probe_toolchain(toolchain) {
+            Some(version) => {
+                debug!("Toolchain {}: {}", toolchain.name(), version);
+                versions.insert(toolchain.name(), version);
+            }
+            None => debug!("Toolchain {} is not available", toolchain.name()),
+        }
+    }
+    versions
+}
+
+/// Run the probe of a single toolchain, `None` if it fails.
+fn probe_toolchain(toolchain: &Toolchain) -> Option<String> {
+    let (program, args) = toolchain.probe.split_first()?;
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let first_line = |bytes: &[u8]| {
+        String::from_utf8_lossy(bytes)
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(String::from)
+    };
+    first_line(&output.stdout).or_else(|| first_line(&output.stderr))
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fs::Permissions;
 use std::io::Read;
 use std::os::unix::fs::PermissionsExt;
@@ -16,21 +16,63 @@ use uuid::Uuid;
 use task_maker_dag::*;
 use task_maker_store::*;
 
-use crate::executor::WorkerJob;
+#[cfg(feature = "chaos")]
+use crate::chaos::ChaosMonkey;
+use crate::executor::{WorkerJob, WorkerJobBatch};
 use crate::proto::*;
-use crate::sandbox::{Sandbox, SandboxResult};
+use crate::sandbox::{Sandbox, SandboxPool, SandboxResult};
 use crate::sandbox_runner::SandboxRunner;
+use crate::stderr_streamer::StderrStreamer;
 
-/// The information about the current job the worker is doing.
+/// The number of failed executions whose sandbox logs are kept by the worker, for the server asking
+/// them.
+const MAX_KEPT_SANDBOX_LOGS: usize = 64;
+/// The directories where the FIFOs are created when the sandbox directory does not support them,
+/// after the temporary directory of the system.
+const FIFO_FALLBACK_DIRS: &[&str] = &["/dev/shm"];
+/// The machine of the workers that run in the same process of the server.
+const LOCAL_MACHINE: &str = "(local)";
+
+/// The information about the current job the worker is doing, and the logs of the sandboxes of the
+/// last failed executions.
 struct WorkerCurrentJob {
-    /// Job currently waiting for, when there is a job running this should be `None`
-    current_job: Option<(Box<WorkerJob>, HashMap<FileUuid, FileStoreHandle>)>,
+    /// Job currently waiting for, when there is a job running this should be `None`. A single job
+    /// is stored as a batch with only one job.
+    current_job: Option<(Box<WorkerJobBatch>, HashMap<FileUuid, FileStoreHandle>)>,
+    /// Whether the current job has been sent as a batch, and its results should be reported with
+    /// `WorkerBatchDone`.
+    batched: bool,
+    /// Whether the server asked to stop the current job, the remaining jobs of the batch are not
+    /// run.
+    stopped: bool,
+    /// Whether the server asked to give back the jobs of the current batch that are not started
+    /// yet.
+    released: bool,
     /// The currently running sandbox.
     current_sandboxes: Option<Vec<Sandbox>>,
     /// The dependencies that are missing and required for the execution start.
     missing_deps: HashMap<FileStoreKey, Vec<FileUuid>>,
     /// Send to the sandbox_manager the list of files the server is missing.
-    server_asked_files: Option<Sender<Vec<FileUuid>>>,
+    server_asked_files: Option<Sender<(Vec<FileUuid>, FileEncoding)>>,
+    /// The logs of the sandboxes of the last executions that failed with an internal error, the
+    /// oldest first.
+    sandbox_logs: VecDeque<(ExecutionUuid, SandboxLogs)>,
+    /// The executions whose sandbox logs the server asked while a job was running. They are sent
+    /// after the results of the job, so that they don't get mixed with its files.
+    asked_sandbox_logs: Vec<ExecutionUuid>,
+}
+
+/// A batch of jobs taken from the current job of the worker, run by the sandbox batch manager.
+struct RunningBatch {
+    /// The jobs of the batch.
+    batch: WorkerJobBatch,
+    /// The handles of the files the jobs depend on.
+    handles: HashMap<FileUuid, FileStoreHandle>,
+    /// Whether the jobs have been sent as a batch, and their results should be reported with
+    /// `WorkerBatchDone`.
+    batched: bool,
+    /// Receives the list of files the server is missing, after it got the results.
+    server_asked_files: Receiver<(Vec<FileUuid>, FileEncoding)>,
 }
 
 /// The worker is the component that receives the work from the server and sends the results back.
@@ -51,10 +93,15 @@ pub struct Worker {
     current_job: Arc<Mutex<WorkerCurrentJob>>,
     /// Where to put the sandboxes.
     sandbox_path: PathBuf,
+    /// The sandbox directories that can be reused by the next executions.
+    sandbox_pool: Arc<SandboxPool>,
     /// The function that spawns an actual sandbox.
     sandbox_runner: Arc<dyn SandboxRunner>,
     /// The join handle of the currently running sandbox, if any.
     current_sandbox_thread: Option<JoinHandle<()>>,
+    /// Decides when this worker disconnects, if running in chaos mode.
+    #[cfg(feature = "chaos")]
+    chaos: Option<Arc<ChaosMonkey>>,
 }
 
 /// An handle of the connection to the worker.
@@ -67,6 +114,20 @@ pub struct WorkerConn {
     pub sender: ChannelSender<WorkerServerMessage>,
     /// The channel that receives messages from the server.
     pub receiver: ChannelReceiver<WorkerClientMessage>,
+    /// The encoding of the files exchanged with the worker.
+    pub file_encoding: FileEncoding,
+    /// How many times the machine of the worker is faster than the reference machine, if it has
+    /// been measured.
+    pub speed_factor: Option<f64>,
+    /// The versions of the toolchains of the worker.
+    pub toolchains: Arc<ToolchainVersions>,
+    /// The tags of the resources provided by the worker, `None` if it provides all of them.
+    pub resources: Option<Arc<ResourceTags>>,
+    /// The pool of workers this worker belongs to, `None` for the default one.
+    pub pool: Option<String>,
+    /// The machine this worker runs on. The workers on the same machine never run at the same time
+    /// more than one execution of each concurrency class.
+    pub machine: String,
 }
 
 /// An error generated by the worker.
@@ -82,22 +143,63 @@ impl WorkerCurrentJob {
     fn new() -> WorkerCurrentJob {
         WorkerCurrentJob {
             current_job: None,
+            batched: false,
+            stopped: false,
+            released: false,
             current_sandboxes: None,
             missing_deps: HashMap::new(),
             server_asked_files: None,
+            sandbox_logs: VecDeque::new(),
+            asked_sandbox_logs: Vec::new(),
         }
     }
+
+    /// Whether the job is the current one, or it's part of the current batch.
+    fn is_current(&self, job: ExecutionGroupUuid) -> bool {
+        self.current_job.as_ref().map_or(false, |(batch, _)| {
+            batch
+                .jobs
+                .iter()
+                .any(|worker_job| worker_job.group.uuid == job)
+        })
+    }
+
+    /// Keep the logs of the sandbox of a failed execution, forgetting the oldest ones.
+    fn keep_sandbox_logs(&mut self, execution: ExecutionUuid, logs: SandboxLogs) {
+        if self.sandbox_logs.len() == MAX_KEPT_SANDBOX_LOGS {
+            self.sandbox_logs.pop_front();
+        }
+        self.sandbox_logs.push_back((execution, logs));
+    }
+
+    /// Send to the server the logs of the sandbox of an execution, if they are still kept.
+    fn send_sandbox_logs(
+        &self,
+        sender: &ChannelSender<WorkerClientMessage>,
+        execution: ExecutionUuid,
+    ) -> Result<(), Error> {
+        let logs = self
+            .sandbox_logs
+            .iter()
+            .find(|(uuid, _)| *uuid == execution)
+            .map(|(_, logs)| logs.clone());
+        sender
+            .send(WorkerClientMessage::SandboxLogs(execution, logs))
+            .context("Failed to send SandboxLogs to the server")
+    }
 }
 
 impl Worker {
     /// Make a new worker attached to a [`FileStore`](../task_maker_store/struct.FileStore.html),
     /// will return a pair with the actual `Worker` and an handle with the channels to connect to
-    /// communicate with the worker.
+    /// communicate with the worker. `toolchains` are the versions of the toolchains of this
+    /// machine.
     pub fn new<S: Into<String>, P: Into<PathBuf>, R>(
         name: S,
         file_store: Arc<FileStore>,
         sandbox_path: P,
         sandbox_runner: R,
+        toolchains: Arc<ToolchainVersions>,
     ) -> Result<(Worker, WorkerConn), Error>
     where
         R: SandboxRunner + 'static,
@@ -122,6 +224,17 @@ impl Worker {
                 name,
                 sender: tx,
                 receiver: rx,
+                file_encoding: FileEncoding::Plain,
+                // the local workers run on the same machine of the client, their time limits are
+                // never scaled
+                speed_factor: None,
+                toolchains,
+                // the local workers can run all the executions of the client, whatever resources
+                // they require
+                resources: None,
+                // the local executor has a single pool
+                pool: None,
+                machine: LOCAL_MACHINE.into(),
             },
         ))
     }
@@ -146,18 +259,29 @@ impl Worker {
             receiver,
             file_store,
             current_job: Arc::new(Mutex::new(WorkerCurrentJob::new())),
+            sandbox_pool: Arc::new(SandboxPool::new(&sandbox_path)),
             sandbox_path,
             sandbox_runner,
             current_sandbox_thread: None,
+            #[cfg(feature = "chaos")]
+            chaos: None,
         })
     }
 
+    /// Disconnect this worker when `chaos` decides so.
+    #[cfg(feature = "chaos")]
+    pub(crate) fn with_chaos(mut self, chaos: Option<Arc<ChaosMonkey>>) -> Self {
+        self.chaos = chaos;
+        self
+    }
+
     /// Start the sandbox thread for the current job.
     fn start_job(&mut self) -> Result<(), Error> {
         self.current_sandbox_thread = Some(execute_job(
             self.current_job.clone(),
             &self.sender,
             &self.sandbox_path,
+            &self.sandbox_pool,
             self.sandbox_runner.clone(),
         )?);
         Ok(())
@@ -174,6 +298,52 @@ impl Worker {
         Ok(())
     }
 
+    /// Store the job received from the server and ask the files it needs, starting it if they are
+    /// all already available.
+    fn receive_job(&mut self, batch: WorkerJobBatch, batched: bool) -> Result<(), Error> {
+        assert!(self.current_job.lock().unwrap().current_job.is_none());
+        self.wait_sandbox()?;
+        let mut missing_deps: HashMap<FileStoreKey, Vec<FileUuid>> = HashMap::new();
+        let mut handles = HashMap::new();
+        for job in &batch.jobs {
+            for exec in &job.group.executions {
+                for input in exec.dependencies().iter() {
+                    let key = job
+                        .dep_keys
+                        .get(input)
+                        .ok_or(WorkerError::MissingDependencyKey { uuid: *input })?;
+                    match self.file_store.get(key) {
+                        None => {
+                            // ask the file only once
+                            if !missing_deps.contains_key(key) {
+                                self.sender
+                                    .send(WorkerClientMessage::AskFile(key.clone()))
+                                    .context("Failed to send AskFile to server")?;
+                            }
+                            missing_deps.entry(key.clone()).or_default().push(*input);
+                        }
+                        Some(handle) => {
+                            handles.insert(*input, handle);
+                        }
+                    }
+                }
+            }
+        }
+        let job_ready = missing_deps.is_empty();
+        {
+            let mut current_job = self.current_job.lock().unwrap();
+            current_job.missing_deps = missing_deps;
+            current_job.current_job = Some((Box::new(batch), handles));
+            current_job.batched = batched;
+            current_job.stopped = false;
+            current_job.released = false;
+        }
+        if job_ready {
+            self.start_job()?;
+        }
+        Ok(())
+    }
+
     /// The worker body, this function will block until the worker disconnects.
     #[allow(clippy::cognitive_complexity)]
     pub fn work(mut self) -> Result<(), Error> {
@@ -184,43 +354,24 @@ impl Worker {
 
         loop {
             match self.receiver.recv() {
+                #[cfg(feature = "chaos")]
+                Ok(WorkerServerMessage::Work(_) | WorkerServerMessage::WorkBatch(_))
+                    if self
+                        .chaos
+                        .as_ref()
+                        .map_or(false, |c| c.disconnects_worker()) =>
+                {
+                    warn!("Chaos: worker {} disconnects", self);
+                    break;
+                }
                 Ok(WorkerServerMessage::Work(job)) => {
                     trace!("Worker {} got job: {:?}", self, job);
-                    assert!(self.current_job.lock().unwrap().current_job.is_none());
-                    self.wait_sandbox()?;
-                    let mut missing_deps: HashMap<FileStoreKey, Vec<FileUuid>> = HashMap::new();
-                    let mut handles = HashMap::new();
-                    for exec in &job.group.executions {
-                        for input in exec.dependencies().iter() {
-                            let key = job
-                                .dep_keys
-                                .get(input)
-                                .ok_or(WorkerError::MissingDependencyKey { uuid: *input })?;
-                            match self.file_store.get(key) {
-                                None => {
-                                    // ask the file only once
-                                    if !missing_deps.contains_key(key) {
-                                        self.sender
-                                            .send(WorkerClientMessage::AskFile(key.clone()))
-                                            .context("Failed to send AskFile to server")?;
-                                    }
-                                    missing_deps.entry(key.clone()).or_default().push(*input);
-                                }
-                                Some(handle) => {
-                                    handles.insert(*input, handle);
-                                }
-                            }
-                        }
-                    }
-                    let job_ready = missing_deps.is_empty();
-                    {
-                        let mut current_job = self.current_job.lock().unwrap();
-                        current_job.missing_deps = missing_deps;
-                        current_job.current_job = Some((job, handles));
-                    }
-                    if job_ready {
-                        self.start_job()?;
-                    }
+                    let batch = WorkerJobBatch { jobs: vec![*job] };
+                    self.receive_job(batch, false)?;
+                }
+                Ok(WorkerServerMessage::WorkBatch(batch)) => {
+                    trace!("Worker {} got batch: {:?}", self, batch);
+                    self.receive_job(*batch, true)?;
                 }
                 Ok(WorkerServerMessage::ProvideFile(key)) => {
                     info!("Server sent file {:?}", key);
@@ -253,23 +404,36 @@ impl Worker {
                     break;
                 }
                 Ok(WorkerServerMessage::KillJob(job)) => {
-                    let current_job = self.current_job.lock().unwrap();
-                    if let Some((worker_job, _)) = current_job.current_job.as_ref() {
-                        // check that the job is the same
-                        if worker_job.group.uuid == job {
-                            if let Some(sandboxes) = current_job.current_sandboxes.as_ref() {
-                                // ask the sandbox to kill the process
-                                for sandbox in sandboxes {
-                                    sandbox.kill();
-                                }
+                    let mut current_job = self.current_job.lock().unwrap();
+                    if current_job.is_current(job) {
+                        current_job.stopped = true;
+                        if let Some(sandboxes) = current_job.current_sandboxes.as_ref() {
+                            // ask the sandbox to kill the process
+                            for sandbox in sandboxes {
+                                sandbox.kill();
                             }
                         }
                     }
                 }
-                Ok(WorkerServerMessage::AskFiles(files)) => {
+                Ok(WorkerServerMessage::ReleaseJobs(job)) => {
+                    let mut current_job = self.current_job.lock().unwrap();
+                    if current_job.is_current(job) {
+                        current_job.released = true;
+                    }
+                }
+                Ok(WorkerServerMessage::AskSandboxLogs(execution)) => {
+                    let mut current_job = self.current_job.lock().unwrap();
+                    // while a job is running the sandbox thread may be sending files
+                    if current_job.current_job.is_some() {
+                        current_job.asked_sandbox_logs.push(execution);
+                    } else {
+                        current_job.send_sandbox_logs(&self.sender, execution)?;
+                    }
+                }
+                Ok(WorkerServerMessage::AskFiles(files, encoding)) => {
                     let mut current_job = self.current_job.lock().unwrap();
                     if let Some(sender) = current_job.server_asked_files.take() {
-                        if let Err(e) = sender.send(files) {
+                        if let Err(e) = sender.send((files, encoding)) {
                             error!("Cannot send the list of files from the server to the worker manager: {:?}", e);
                         }
                     } else {
@@ -304,71 +468,48 @@ impl Worker {
     }
 }
 
-/// Spawn a new thread that will start the sandbox and will send the results back to the server.
+/// Spawn a new thread that will run the jobs of the current batch, one after the other, and will
+/// send the results back to the server.
 fn execute_job(
     current_job: Arc<Mutex<WorkerCurrentJob>>,
     sender: &ChannelSender<WorkerClientMessage>,
     sandbox_path: &Path,
+    sandbox_pool: &Arc<SandboxPool>,
     runner: Arc<dyn SandboxRunner>,
 ) -> Result<JoinHandle<()>, Error> {
-    let (job, sandboxes, fifo_dir, server_asked_files) = {
+    let running = {
         let mut current_job = current_job.lock().unwrap();
-        let job = current_job
+        let (batch, handles) = current_job
             .current_job
             .as_ref()
             .ok_or_else(|| anyhow!("Worker job is gone"))?;
-        let mut boxes = Vec::new();
-        let group = &job.0.group;
-        let fifo_dir = if group.fifo.is_empty() {
-            None
-        } else {
-            let fifo_dir = TempDir::new_in(sandbox_path).with_context(|| {
-                format!(
-                    "Failed to create temporary directory in {}",
-                    sandbox_path.display()
-                )
-            })?;
-            for fifo in &group.fifo {
-                let path = fifo_dir
-                    .path()
-                    .join(fifo.sandbox_path().file_name().unwrap());
-                nix::unistd::mkfifo(&path, nix::sys::stat::Mode::S_IRWXU)
-                    .with_context(|| format!("Failed to create FIFO at {}", path.display()))?;
-            }
-            Some(fifo_dir)
-        };
-        let keep_sandboxes = group.config().keep_sandboxes;
-        for exec in &group.executions {
-            let mut sandbox = Sandbox::new(
-                sandbox_path,
-                exec,
-                &job.1,
-                fifo_dir.as_ref().map(|d| d.path().to_owned()),
-            )?;
-            if keep_sandboxes {
-                sandbox.keep()?;
-            }
-            boxes.push(sandbox);
-        }
-        let job = job.0.clone();
-        current_job.current_sandboxes = Some(boxes.clone());
+        let (batch, handles) = (*batch.clone(), handles.clone());
         let (sender, receiver) = channel();
         current_job.server_asked_files = Some(sender);
-        (job, boxes, fifo_dir, receiver)
+        RunningBatch {
+            batch,
+            handles,
+            batched: current_job.batched,
+            server_asked_files: receiver,
+        }
     };
     let sender = sender.clone();
-    let description = job.group.description.clone();
+    let sandbox_path = sandbox_path.to_owned();
+    let sandbox_pool = sandbox_pool.clone();
+    let description = match running.batch.jobs.len() {
+        1 => running.batch.jobs[0].group.description.clone(),
+        n => format!("batch of {} jobs", n),
+    };
     let join_handle = std::thread::Builder::new()
         .name(format!("Sandbox group manager for {}", description))
         .spawn(move || {
-            sandbox_group_manager(
+            sandbox_batch_manager(
                 current_job,
-                *job,
+                running,
                 sender,
-                server_asked_files,
-                sandboxes,
+                &sandbox_path,
+                &sandbox_pool,
                 runner,
-                fifo_dir,
             )
             .with_context(|| format!("Sandbox group for {} failed", description))
             // FIXME: find a better way to propagate the error to the server
@@ -377,25 +518,261 @@ fn execute_job(
     Ok(join_handle)
 }
 
+/// Prepare the sandboxes of all the executions of a group, and the directory with its FIFOs. The
+/// sandboxes are stored as the current ones of the worker, so that they can be killed.
+fn prepare_group(
+    current_job: &Mutex<WorkerCurrentJob>,
+    job: &WorkerJob,
+    handles: &HashMap<FileUuid, FileStoreHandle>,
+    sandbox_path: &Path,
+    sandbox_pool: &Arc<SandboxPool>,
+) -> Result<(Vec<Sandbox>, Option<TempDir>), Error> {
+    let group = &job.group;
+    let fifo_dir = if group.fifo.is_empty() {
+        None
+    } else {
+        Some(create_fifo_dir(sandbox_path, &group.fifo)?)
+    };
+    let mut boxes = Vec::new();
+    for exec in &group.executions {
+        let mut sandbox = Sandbox::from_pool(
+            sandbox_pool,
+            exec,
+            handles,
+            fifo_dir.as_ref().map(|d| d.path().to_owned()),
+        )?;
+        if exec.config().keep_sandboxes {
+            sandbox.keep()?;
+        }
+        boxes.push(sandbox);
+    }
+    current_job.lock().unwrap().current_sandboxes = Some(boxes.clone());
+    Ok((boxes, fifo_dir))
+}
+
+/// Create a temporary directory with all the FIFOs of a group.
+///
+/// The directory is created inside `sandbox_path` if possible. Some filesystems (like the ones
+/// shared with the host by some container runtimes) do not support FIFOs, in that case the
+/// directory is created in one of the other candidate directories. Since the directory is
+/// mounted inside the sandboxes at the same path, the executions don't notice the difference.
+///
+/// There is no fallback without a filesystem supporting FIFOs: the sandboxes redirect the standard
+/// streams to paths, and the managers of the communication tasks receive the paths of the pipes as
+/// arguments, while a socket (like the ones of a socketpair) cannot be opened by path. When none of
+/// the candidates works the group fails, suggesting to point `TMPDIR` to a directory supporting
+/// them (e.g. a tmpfs).
+fn create_fifo_dir(sandbox_path: &Path, fifos: &[Fifo]) -> Result<TempDir, Error> {
+    let mut candidates = vec![sandbox_path.to_owned(), std::env::temp_dir()];
+    candidates.extend(FIFO_FALLBACK_DIRS.iter().map(PathBuf::from));
+    candidates.dedup();
+    let mut last_error = None;
+    for dir in &candidates {
+        match create_fifos_in(dir, fifos) {
+            Ok(fifo_dir) => return Ok(fifo_dir),
+            Err(e) => {
+                warn!("Cannot create the FIFOs in {}: {:?}", dir.display(), e);
+                last_error = Some(e);
+            }
+        }
+    }
+    let tried = candidates
+        .iter()
+        .map(|dir| dir.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(last_error
+        .unwrap_or_else(|| anyhow!("No directory for the FIFOs"))
+        .context(format!(
+            "None of the directories supports FIFOs ({}), set TMPDIR to one that does (e.g. a \
+            tmpfs) for running the tasks with communication",
+            tried
+        )))
+}
+
+/// Create a temporary directory inside `dir` with all the FIFOs of a group.
+fn create_fifos_in(dir: &Path, fifos: &[Fifo]) -> Result<TempDir, Error> {
+    let fifo_dir = TempDir::new_in(dir)
+        .with_context(|| format!("Failed to create temporary directory in {}", dir.display()))?;
+    for fifo in fifos {
+        let path = fifo_dir
+            .path()
+            .join(fifo.sandbox_path().file_name().unwrap());
+        nix::unistd::mkfifo(&path, nix::sys::stat::Mode::S_IRWXU)
+            .with_context(|| format!("Failed to create FIFO at {}", path.display()))?;
+    }
+    Ok(fifo_dir)
+}
+
+/// The sandbox batch manager runs the jobs of the batch one after the other, using the sandbox
+/// group manager. When all of them are done it sends all the results back to the server, and then
+/// the files the server asks for.
+///
+/// The sandboxes of all the jobs are kept until the files are sent, since the outputs are read
+/// from them.
+fn sandbox_batch_manager(
+    current_job: Arc<Mutex<WorkerCurrentJob>>,
+    running: RunningBatch,
+    sender: ChannelSender<WorkerClientMessage>,
+    sandbox_path: &Path,
+    sandbox_pool: &Arc<SandboxPool>,
+    runner: Arc<dyn SandboxRunner>,
+) -> Result<(), Error> {
+    let RunningBatch {
+        batch,
+        handles,
+        batched,
+        server_asked_files,
+    } = running;
+    let mut results = Vec::with_capacity(batch.jobs.len());
+    let mut outputs = HashMap::new();
+    let mut output_paths = HashMap::new();
+    let mut groups = Vec::with_capacity(batch.jobs.len());
+    for job in &batch.jobs {
+        // the jobs not started yet are given back to the server, which will send them to the
+        // workers that are free
+        if !results.is_empty() && current_job.lock().unwrap().released {
+            info!(
+                "Releasing {} jobs of the batch",
+                batch.jobs.len() - results.len()
+            );
+            break;
+        }
+        let num_executions = job.group.executions.len();
+        if current_job.lock().unwrap().stopped {
+            let error = "The job has been stopped".to_string();
+            results.push(vec![failed_execution_result(error); num_executions]);
+            continue;
+        }
+        match prepare_group(&current_job, job, &handles, sandbox_path, sandbox_pool) {
+            // the job may have been stopped while the sandboxes were prepared, before they could be
+            // killed
+            Ok((sandboxes, fifo_dir)) if current_job.lock().unwrap().stopped => {
+                let error = "The job has been stopped".to_string();
+                results.push(vec![failed_execution_result(error); num_executions]);
+                groups.push((sandboxes, fifo_dir));
+            }
+            Ok((sandboxes, fifo_dir)) => {
+                let streamer = if job.stream_stderr {
+                    let paths = sandboxes.iter().map(Sandbox::stderr_path).collect();
+                    StderrStreamer::start(paths, sender.clone())
+                } else {
+                    None
+                };
+                let group_results = sandbox_group_manager(
+                    job,
+                    sandboxes.clone(),
+                    runner.clone(),
+                    &mut outputs,
+                    &mut output_paths,
+                );
+                if let Some(streamer) = streamer {
+                    streamer.stop();
+                }
+                let group_results = group_results?;
+                let mut current_job = current_job.lock().unwrap();
+                for ((exec, result), sandbox) in job
+                    .group
+                    .executions
+                    .iter()
+                    .zip(group_results.iter())
+                    .zip(sandboxes.iter())
+                {
+                    if result.status.is_internal_error() {
+                        current_job.keep_sandbox_logs(exec.uuid, sandbox.logs());
+                    }
+                }
+                drop(current_job);
+                results.push(group_results);
+                groups.push((sandboxes, fifo_dir));
+            }
+            Err(e) => {
+                let error = format!("Failed to setup the sandbox: {:?}", e);
+                results.push(vec![failed_execution_result(error); num_executions]);
+            }
+        }
+    }
+    // tell the server the results and the list of produced files, with their sizes so that the
+    // server can check them before storing the files
+    let sized_outputs = outputs
+        .iter()
+        .map(|(uuid, key)| {
+            let size = std::fs::metadata(&output_paths[uuid]).map_or(0, |m| m.len());
+            (*uuid, (key.clone(), size))
+        })
+        .collect();
+    let message = if batched {
+        WorkerClientMessage::WorkerBatchDone(results, sized_outputs)
+    } else {
+        let result = results.pop().context("Empty job")?;
+        WorkerClientMessage::WorkerDone(result, sized_outputs)
+    };
+    sender.send(message).context("Failed to send WorkerDone")?;
+    // wait for the list of files to send
+    match server_asked_files.recv() {
+        Ok((missing_files, encoding)) => {
+            for uuid in missing_files {
+                if let Some(key) = outputs.get(&uuid) {
+                    sender
+                        .send(WorkerClientMessage::ProvideFile(uuid, key.clone()))
+                        .context("Failed to send ProvideFile")?;
+                    ChannelFileSender::send(&output_paths[&uuid], &sender, encoding)
+                        .context("Failed to send missing file")?;
+                } else {
+                    error!(
+                        "Server asked for file {}, which is not known to the worker",
+                        uuid
+                    );
+                }
+            }
+        }
+        Err(e) => {
+            // not receiving the list from the server means that the server is going down and does
+            // not bother of responding, letting the worker crash will crash the local executor.
+            // So just cleanup and exit without asking for more jobs.
+            warn!(
+                "List of missing files not received from the server: {:?}",
+                e
+            );
+            let mut job = current_job.lock().unwrap();
+            job.current_job = None;
+            job.current_sandboxes = None;
+            job.asked_sandbox_logs.clear();
+            return Ok(());
+        }
+    }
+    // this job is completed, reset the worker and ask for more work
+    let mut job = current_job.lock().unwrap();
+    job.current_job = None;
+    job.current_sandboxes = None;
+    for execution in std::mem::take(&mut job.asked_sandbox_logs) {
+        job.send_sandbox_logs(&sender, execution)?;
+    }
+    let _ = sender.send(WorkerClientMessage::GetWork);
+    for (_, fifo_dir) in groups {
+        // The sandbox may chmod -r the directory, revert it to allow deletion on drop
+        if let Some(fifo_dir) = fifo_dir {
+            let _ = std::fs::set_permissions(fifo_dir.path(), Permissions::from_mode(0o755));
+        }
+    }
+    Ok(())
+}
+
 /// The sandbox group manager spawns the threads of the sandbox of all the executions in the group.
 /// Then waits for their outcome and eventually stops the sandboxes if a process fails. When all the
-/// sandboxes complete, this manager collects their results and send them back to the server.
+/// sandboxes complete, this manager collects their results and the files they produced.
 ///
-/// Note that this function owns `fifo_dir`, the `TempDir` where the FIFOs are stored, it has not to
-/// be dropped before all the sandboxes end.
+/// Note that the caller owns the `TempDir` where the FIFOs are stored, it has not to be dropped
+/// before all the sandboxes end.
 fn sandbox_group_manager(
-    current_job: Arc<Mutex<WorkerCurrentJob>>,
-    job: WorkerJob,
-    sender: ChannelSender<WorkerClientMessage>,
-    server_asked_files_receiver: Receiver<Vec<FileUuid>>,
+    job: &WorkerJob,
     mut sandboxes: Vec<Sandbox>,
     runner: Arc<dyn SandboxRunner>,
-    fifo_dir: Option<TempDir>,
-) -> Result<(), Error> {
+    outputs: &mut HashMap<FileUuid, FileStoreKey>,
+    output_paths: &mut HashMap<FileUuid, PathBuf>,
+) -> Result<Vec<ExecutionResult>, Error> {
     assert_eq!(sandboxes.len(), job.group.executions.len());
     let mut results = vec![None; job.group.executions.len()];
-    let mut outputs = HashMap::new();
-    let mut output_paths = HashMap::new();
 
     // in case of simple executions there's no need to spawn the sandbox in a different thread and
     // then join from here
@@ -409,19 +786,13 @@ fn sandbox_group_manager(
         };
         let exec = &job.group.executions[0];
         let mut result = compute_execution_result(exec, result, &sandbox);
-        get_result_outputs(
-            exec,
-            &sandbox,
-            &mut outputs,
-            &mut output_paths,
-            &mut result.status,
-        );
+        get_result_outputs(exec, &sandbox, outputs, output_paths, &mut result.status);
 
         results[0] = Some(result);
     // this is the complex case: more than an execution (therefore more than a sandbox)
     // All the sandboxes will run in a separate thread and this thread will wait all of them. When
     // a sandbox is done, it signals to this thread the completion which simply computes the result.
-    // When all of them have finished this thread sends the result and exits.
+    // When all of them have finished this thread returns the results.
     } else {
         let mut missing = job.group.executions.len();
         let mut handles = Vec::new();
@@ -459,13 +830,7 @@ fn sandbox_group_manager(
                         }
                     }
 
-                    get_result_outputs(
-                        exec,
-                        sandbox,
-                        &mut outputs,
-                        &mut output_paths,
-                        &mut result.status,
-                    );
+                    get_result_outputs(exec, sandbox, outputs, output_paths, &mut result.status);
 
                     results[index] = Some(result);
                     missing -= 1;
@@ -480,55 +845,7 @@ fn sandbox_group_manager(
                 .context("Sandbox thread failed")?;
         }
     }
-    // tell the server the results and the list of produced files
-    sender
-        .send(WorkerClientMessage::WorkerDone(
-            results.into_iter().map(Option::unwrap).collect(),
-            outputs.clone(),
-        ))
-        .context("Failed to send WorkerDone")?;
-    // wait for the list of files to send
-    match server_asked_files_receiver.recv() {
-        Ok(missing_files) => {
-            for uuid in missing_files {
-                if let Some(key) = outputs.get(&uuid) {
-                    sender
-                        .send(WorkerClientMessage::ProvideFile(uuid, key.clone()))
-                        .context("Failed to send ProvideFile")?;
-                    ChannelFileSender::send(&output_paths[&uuid], &sender)
-                        .context("Failed to send missing file")?;
-                } else {
-                    error!(
-                        "Server asked for file {}, which is not known to the worker",
-                        uuid
-                    );
-                }
-            }
-        }
-        Err(e) => {
-            // not receiving the list from the server means that the server is going down and does
-            // not bother of responding, letting the worker crash will crash the local executor.
-            // So just cleanup and exit without asking for more jobs.
-            warn!(
-                "List of missing files not received from the server: {:?}",
-                e
-            );
-            let mut job = current_job.lock().unwrap();
-            job.current_job = None;
-            job.current_sandboxes = None;
-            return Ok(());
-        }
-    }
-    // this job is completed, reset the worker and ask for more work
-    let mut job = current_job.lock().unwrap();
-    job.current_job = None;
-    job.current_sandboxes = None;
-    let _ = sender.send(WorkerClientMessage::GetWork);
-    // The sandbox may chmod -r the directory, revert it to allow deletion on drop
-    if let Some(fifo_dir) = fifo_dir {
-        let _ = std::fs::set_permissions(fifo_dir.path(), Permissions::from_mode(0o755));
-    }
-    Ok(())
+    Ok(results.into_iter().map(Option::unwrap).collect())
 }
 
 /// Spawn the sandbox of an execution in a different thread and send to the group manager the
@@ -567,6 +884,7 @@ fn compute_execution_result(
             signal,
             resources,
             was_killed,
+            invocation,
         } => {
             let stdout = capture_stream(&sandbox.stdout_path(), execution.capture_stdout);
             let stderr = capture_stream(&sandbox.stderr_path(), execution.capture_stderr);
@@ -586,18 +904,20 @@ fn compute_execution_result(
                 resources,
                 stdout: stdout.ok().unwrap_or_default(),
                 was_killed,
-                was_cached: false,
                 stderr: stderr.ok().unwrap_or_default(),
+                sandbox: Some(invocation),
+                ..Default::default()
             }
         }
-        SandboxResult::Failed { error } => ExecutionResult {
-            status: ExecutionStatus::InternalError(error),
-            resources: ExecutionResourcesUsage::default(),
-            stdout: None,
-            was_killed: false,
-            was_cached: false,
-            stderr: None,
-        },
+        SandboxResult::Failed { error } => failed_execution_result(error),
+    }
+}
+
+/// The result of an execution that could not be run.
+fn failed_execution_result(error: String) -> ExecutionResult {
+    ExecutionResult {
+        status: ExecutionStatus::InternalError(error),
+        ..Default::default()
     }
 }
 
@@ -706,3 +1026,42 @@ fn check_sandbox_is_supported(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_fifo_dir() {
+        let tmpdir = TempDir::new().unwrap();
+        let mut group = ExecutionGroup::new("group");
+        group.new_fifo();
+        group.new_fifo();
+        let fifos = group.fifo;
+        let fifo_dir = create_fifo_dir(tmpdir.path(), &fifos).unwrap();
+        assert!(fifo_dir.path().starts_with(tmpdir.path()));
+        for fifo in &fifos {
+            let path = fifo_dir
+                .path()
+                .join(fifo.sandbox_path().file_name().unwrap());
+            assert!(path.exists());
+        }
+    }
+
+    #[test]
+    fn test_create_fifo_dir_fallback() {
+        let tmpdir = TempDir::new().unwrap();
+        // a regular file cannot contain the FIFOs
+        let sandbox_path = tmpdir.path().join("file");
+        std::fs::write(&sandbox_path, "").unwrap();
+        let mut group = ExecutionGroup::new("group");
+        group.new_fifo();
+        let fifos = group.fifo;
+        let fifo_dir = create_fifo_dir(&sandbox_path, &fifos).unwrap();
+        assert!(!fifo_dir.path().starts_with(&sandbox_path));
+        let path = fifo_dir
+            .path()
+            .join(fifos[0].sandbox_path().file_name().unwrap());
+        assert!(path.exists());
+    }
+}
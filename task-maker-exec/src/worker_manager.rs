@@ -1,19 +1,20 @@
 use std::collections::HashMap;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
 use std::thread;
+use std::time::Instant;
 
 use anyhow::{bail, Context, Error};
 use ductile::ChannelSender;
 
-use task_maker_dag::{ExecutionGroupUuid, WorkerUuid};
-use task_maker_store::FileStore;
+use task_maker_dag::{ExecutionGroupUuid, ExecutionResult, ExecutionUuid, FileUuid, WorkerUuid};
+use task_maker_store::{FileStore, FileStoreKey};
 
-use crate::executor::WorkerJob;
+use crate::executor::{TransferCounters, WorkerJob, WorkerJobBatch};
 use crate::proto::{
     ChannelFileIterator, ChannelFileSender, WorkerClientMessage, WorkerServerMessage,
 };
-use crate::scheduler::SchedulerInMessage;
+use crate::scheduler::{SchedulerInMessage, WorkerInfo};
 use crate::WorkerConn;
 
 /// Message coming from the Scheduler or the Executor for the WorkerManager
@@ -24,15 +25,29 @@ pub(crate) enum WorkerManagerInMessage {
     /// A worker has disconnected. This message is sent by the WorkerManager itself, from a
     /// different thread.
     WorkerDisconnected { worker: WorkerUuid },
-    /// The scheduler sent a new job for a worker. The WorkerManager will forward the job to the
-    /// actual worker.
-    WorkerJob { worker: WorkerUuid, job: WorkerJob },
+    /// The scheduler sent new jobs for a worker. The WorkerManager will forward the jobs to the
+    /// actual worker, in a batch if they are more than one.
+    WorkerJob {
+        worker: WorkerUuid,
+        jobs: Vec<WorkerJob>,
+    },
     /// The scheduler is asking a worker to stop doing a job, if the worker is still working on
     /// that.
     StopWorkerJob {
         worker: WorkerUuid,
         job: ExecutionGroupUuid,
     },
+    /// The scheduler is asking a worker to give back the jobs of its batch that are not started
+    /// yet, if the worker is still working on the batch with that job.
+    ReleaseWorkerJobs {
+        worker: WorkerUuid,
+        job: ExecutionGroupUuid,
+    },
+    /// The scheduler is asking a worker for the logs of the sandbox of an execution it ran.
+    AskSandboxLogs {
+        worker: WorkerUuid,
+        execution: ExecutionUuid,
+    },
     /// The WorkerManager is asked to exit and tell all the connected worker to exit too.
     Exit,
 }
@@ -49,6 +64,8 @@ pub(crate) struct WorkerManager {
     sender: Sender<WorkerManagerInMessage>,
     /// The receiver of the messages for the worker manager.
     receiver: Receiver<WorkerManagerInMessage>,
+    /// The counters of the files exchanged with the workers.
+    transfers: Arc<TransferCounters>,
 }
 
 impl WorkerManager {
@@ -60,12 +77,14 @@ impl WorkerManager {
         scheduler: Sender<SchedulerInMessage>,
         sender: Sender<WorkerManagerInMessage>,
         receiver: Receiver<WorkerManagerInMessage>,
+        transfers: Arc<TransferCounters>,
     ) -> WorkerManager {
         WorkerManager {
             file_store,
             scheduler,
             sender,
             receiver,
+            transfers,
         }
     }
 
@@ -86,15 +105,13 @@ impl WorkerManager {
                     let scheduler = self.scheduler.clone();
                     let file_store = self.file_store.clone();
                     let sender = self.sender.clone();
+                    let transfers = self.transfers.clone();
                     thread::Builder::new()
-                        .name(format!(
-                            "Manager of worker {} ({})",
-                            worker.name, worker.uuid
-                        ))
+                        .name(format!("Manager of {}", worker.name))
                         .spawn(move || {
-                            if let Err(e) =
-                                WorkerManager::worker_thread(worker, scheduler, sender, file_store)
-                            {
+                            if let Err(e) = WorkerManager::worker_thread(
+                                worker, scheduler, sender, file_store, transfers,
+                            ) {
                                 warn!("The manager of a worker failed: {:?}", e);
                             }
                         })
@@ -105,13 +122,18 @@ impl WorkerManager {
                         .remove(&worker)
                         .context("Unknown worker disconnected")?;
                 }
-                WorkerManagerInMessage::WorkerJob { worker, job } => {
-                    // if the worker is not present, it means it has just disconnected. The
-                    // scheduler should be already informed and should have resheduled the job.
+                WorkerManagerInMessage::WorkerJob { worker, mut jobs } => {
+                    // if the worker is not present, or it disconnects before receiving the jobs,
+                    // the scheduler is informed by its manager and it reschedules them.
                     if let Some(sender) = connected_workers.get(&worker) {
-                        sender
-                            .send(WorkerServerMessage::Work(Box::new(job)))
-                            .context("Failed to send Work to worker")?;
+                        let message = if jobs.len() == 1 {
+                            WorkerServerMessage::Work(Box::new(jobs.pop().unwrap()))
+                        } else {
+                            WorkerServerMessage::WorkBatch(Box::new(WorkerJobBatch { jobs }))
+                        };
+                        if let Err(e) = sender.send(message) {
+                            warn!("Failed to send Work to worker {}: {:?}", worker, e);
+                        }
                     }
                 }
                 WorkerManagerInMessage::Exit => {
@@ -120,9 +142,28 @@ impl WorkerManager {
                 }
                 WorkerManagerInMessage::StopWorkerJob { worker, job } => {
                     if let Some(sender) = connected_workers.get(&worker) {
-                        sender
-                            .send(WorkerServerMessage::KillJob(job))
-                            .context("Failed to send KillJob to worker")?;
+                        if let Err(e) = sender.send(WorkerServerMessage::KillJob(job)) {
+                            warn!("Failed to send KillJob to worker {}: {:?}", worker, e);
+                        }
+                    }
+                }
+                WorkerManagerInMessage::ReleaseWorkerJobs { worker, job } => {
+                    if let Some(sender) = connected_workers.get(&worker) {
+                        if let Err(e) = sender.send(WorkerServerMessage::ReleaseJobs(job)) {
+                            warn!("Failed to send ReleaseJobs to worker {}: {:?}", worker, e);
+                        }
+                    }
+                }
+                WorkerManagerInMessage::AskSandboxLogs { worker, execution } => {
+                    // if the worker is gone the scheduler already knows its logs won't arrive
+                    if let Some(sender) = connected_workers.get(&worker) {
+                        let message = WorkerServerMessage::AskSandboxLogs(execution);
+                        if let Err(e) = sender.send(message) {
+                            warn!(
+                                "Failed to send AskSandboxLogs to worker {}: {:?}",
+                                worker, e
+                            );
+                        }
                     }
                 }
             }
@@ -144,14 +185,57 @@ impl WorkerManager {
         scheduler: Sender<SchedulerInMessage>,
         worker_manager: Sender<WorkerManagerInMessage>,
         file_store: Arc<FileStore>,
+        transfers: Arc<TransferCounters>,
+    ) -> Result<(), Error> {
+        if let Err(e) =
+            WorkerManager::handle_worker_messages(&worker, &scheduler, &file_store, &transfers)
+        {
+            warn!("Connection with worker {} failed: {:?}", worker.uuid, e);
+        }
+        transfers.disconnected(worker.uuid);
+        // when the worker disconnects, tell the scheduler that the worker is no longer alive (thus
+        // rescheduling the job if needed).
+        if scheduler
+            .send(SchedulerInMessage::WorkerDisconnected { uuid: worker.uuid })
+            .is_err()
+        {
+            debug!("Cannot tell the scheduler that a worker left, maybe it's gone");
+        }
+        // send back to the WorkerManager a message, letting it know that the worker is no longer
+        // connected, thus removing it from the list.
+        if worker_manager
+            .send(WorkerManagerInMessage::WorkerDisconnected {
+                worker: worker.uuid,
+            })
+            .is_err()
+        {
+            debug!("Worker manager is gone");
+        }
+        Ok(())
+    }
+
+    /// Handle the messages from a worker until it disconnects or the scheduler is gone. An error
+    /// is returned if the connection with the worker breaks while exchanging the files.
+    fn handle_worker_messages(
+        worker: &WorkerConn,
+        scheduler: &Sender<SchedulerInMessage>,
+        file_store: &FileStore,
+        transfers: &TransferCounters,
     ) -> Result<(), Error> {
         while let Ok(message) = worker.receiver.recv() {
             match message {
                 WorkerClientMessage::GetWork => {
                     // the worker is asking for more work to do
                     let res = scheduler.send(SchedulerInMessage::WorkerConnected {
-                        uuid: worker.uuid,
-                        name: worker.name.clone(),
+                        worker: WorkerInfo {
+                            uuid: worker.uuid,
+                            name: worker.name.clone(),
+                            speed_factor: worker.speed_factor,
+                            toolchains: worker.toolchains.clone(),
+                            resources: worker.resources.clone(),
+                            pool: worker.pool.clone(),
+                            machine: worker.machine.clone(),
+                        },
                     });
                     if res.is_err() {
                         // the scheduler is gone
@@ -167,8 +251,14 @@ impl WorkerManager {
                         .sender
                         .send(WorkerServerMessage::ProvideFile(key))
                         .context("Failed to send ProvideFile to worker")?;
-                    ChannelFileSender::send(handle.path(), &worker.sender)
-                        .context("Failed to send file to worker")?;
+                    let start = Instant::now();
+                    let bytes = ChannelFileSender::send(
+                        handle.path(),
+                        &worker.sender,
+                        worker.file_encoding,
+                    )
+                    .context("Failed to send file to worker")?;
+                    transfers.sent(worker.uuid, bytes, start.elapsed());
                 }
                 WorkerClientMessage::ProvideFile(_, _) => {
                     // the worker should not provide files unless just after a WorkerDone message is
@@ -176,69 +266,144 @@ impl WorkerManager {
                     unreachable!("Unexpected ProvideFile from worker");
                 }
                 WorkerClientMessage::WorkerDone(result, outputs) => {
-                    // the worker completed its job and will send the produced files
-                    let mut output_handlers = HashMap::new();
-                    let mut missing_files = Vec::new();
-                    for (uuid, key) in &outputs {
-                        if let Some(handle) = file_store.get(key) {
-                            output_handlers.insert(*uuid, handle);
-                        } else {
-                            missing_files.push(*uuid);
-                        }
+                    if !WorkerManager::receive_results(
+                        worker,
+                        scheduler,
+                        file_store,
+                        transfers,
+                        vec![result],
+                        outputs,
+                    )? {
+                        break;
                     }
-                    let num_missing = missing_files.len();
-                    info!(
-                        "Asking worker {} for {} missing files",
-                        worker.uuid, num_missing
-                    );
-                    worker
-                        .sender
-                        .send(WorkerServerMessage::AskFiles(missing_files))
-                        .context("Failed to send AskFiles to worker")?;
-                    for _ in 0..num_missing {
-                        let message = worker
-                            .receiver
-                            .recv()
-                            .context("Failed to receive file from worker")?;
-                        if let WorkerClientMessage::ProvideFile(uuid, key) = message {
-                            let handle = file_store
-                                .store(&key, ChannelFileIterator::new(&worker.receiver))
-                                .context("Failed to store worker-provided file")?;
-                            output_handlers.insert(uuid, handle);
-                        } else {
-                            bail!("Unexpected message from worker: {:?}", message);
-                        }
+                }
+                WorkerClientMessage::SandboxLogs(execution, logs) => {
+                    let res = scheduler.send(SchedulerInMessage::SandboxLogs {
+                        worker: worker.uuid,
+                        execution,
+                        logs,
+                    });
+                    if res.is_err() {
+                        // the scheduler is gone
+                        break;
                     }
-                    let mex = SchedulerInMessage::WorkerResult {
+                }
+                WorkerClientMessage::Stderr(stderr) => {
+                    let res = scheduler.send(SchedulerInMessage::WorkerStderr {
                         worker: worker.uuid,
-                        result,
-                        outputs: output_handlers,
-                    };
-                    if let Err(e) = scheduler.send(mex) {
-                        warn!("Failed to send message to scheduler: {:?}", e);
+                        stderr,
+                    });
+                    if res.is_err() {
+                        // the scheduler is gone
+                        break;
+                    }
+                }
+                WorkerClientMessage::WorkerBatchDone(results, outputs) => {
+                    if !WorkerManager::receive_results(
+                        worker, scheduler, file_store, transfers, results, outputs,
+                    )? {
                         break;
                     }
                 }
             }
         }
-        // when the worker disconnects, tell the scheduler that the worker is no longer alive (thus
-        // rescheduling the job if needed).
-        if scheduler
-            .send(SchedulerInMessage::WorkerDisconnected { uuid: worker.uuid })
-            .is_err()
-        {
-            debug!("Cannot tell the scheduler that a worker left, maybe it's gone");
+        Ok(())
+    }
+
+    /// Receive from the worker the files produced by its last job (or batch of jobs) that are
+    /// missing in the local store, then send the results to the scheduler. Returns `false` if the
+    /// scheduler is gone.
+    ///
+    /// The missing files are not received if they don't fit in the limit on the output size of the
+    /// client, which is checked by the scheduler before they are stored.
+    fn receive_results(
+        worker: &WorkerConn,
+        scheduler: &Sender<SchedulerInMessage>,
+        file_store: &FileStore,
+        transfers: &TransferCounters,
+        result: Vec<Vec<ExecutionResult>>,
+        outputs: HashMap<FileUuid, (FileStoreKey, u64)>,
+    ) -> Result<bool, Error> {
+        let done = Instant::now();
+        let mut output_handlers = HashMap::new();
+        let mut missing_files = Vec::new();
+        let mut missing_size = 0;
+        for (uuid, (key, size)) in &outputs {
+            if let Some(handle) = file_store.get(key) {
+                output_handlers.insert(*uuid, handle);
+            } else {
+                missing_files.push(*uuid);
+                missing_size += size;
+            }
         }
-        // send back to the WorkerManager a message, letting it know that the worker is no longer
-        // connected, thus removing it from the list.
-        if worker_manager
-            .send(WorkerManagerInMessage::WorkerDisconnected {
-                worker: worker.uuid,
-            })
-            .is_err()
+        if !missing_files.is_empty()
+            && !WorkerManager::reserve_output_size(worker, scheduler, missing_size)
         {
-            debug!("Worker manager is gone");
+            warn!(
+                "The files of worker {} exceed the output size limit, not storing them",
+                worker.uuid
+            );
+            missing_files.clear();
         }
-        Ok(())
+        let num_missing = missing_files.len();
+        info!(
+            "Asking worker {} for {} missing files",
+            worker.uuid, num_missing
+        );
+        worker
+            .sender
+            .send(WorkerServerMessage::AskFiles(
+                missing_files,
+                worker.file_encoding,
+            ))
+            .context("Failed to send AskFiles to worker")?;
+        for _ in 0..num_missing {
+            let message = worker
+                .receiver
+                .recv()
+                .context("Failed to receive file from worker")?;
+            if let WorkerClientMessage::ProvideFile(uuid, key) = message {
+                let start = Instant::now();
+                let mut iterator = ChannelFileIterator::new(&worker.receiver);
+                let handle = file_store
+                    .store(&key, &mut iterator)
+                    .context("Failed to store worker-provided file")?;
+                transfers.received(worker.uuid, iterator.received_bytes(), start.elapsed());
+                output_handlers.insert(uuid, handle);
+            } else {
+                bail!("Unexpected message from worker: {:?}", message);
+            }
+        }
+        let mex = SchedulerInMessage::WorkerResult {
+            worker: worker.uuid,
+            result,
+            outputs: output_handlers,
+            done,
+        };
+        if let Err(e) = scheduler.send(mex) {
+            warn!("Failed to send message to scheduler: {:?}", e);
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Ask the scheduler whether the files produced by the current job of the worker, of `size`
+    /// bytes in total, can be stored. If the scheduler is gone they are stored anyway, the results
+    /// won't be used.
+    fn reserve_output_size(
+        worker: &WorkerConn,
+        scheduler: &Sender<SchedulerInMessage>,
+        size: u64,
+    ) -> bool {
+        let (reply, answer) = channel();
+        let message = SchedulerInMessage::ReserveOutputSize {
+            worker: worker.uuid,
+            size,
+            reply,
+        };
+        if scheduler.send(message).is_err() {
+            return true;
+        }
+        answer.recv().unwrap_or(true)
     }
 }
@@ -1,7 +1,9 @@
 use std::fmt::Write;
 use std::path::PathBuf;
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{bail, Result};
+
+use task_maker_diagnostics::{Diagnostic, DiagnosticCode};
 
 use crate::{ioi, terry, EvaluationConfig, TaskFormat};
 
@@ -48,20 +50,33 @@ pub fn find_task(
         };
     }
 
-    let mut message = "\n".to_string();
+    // a single failure caused by a diagnostic is reported as is, keeping its code and help
+    if fails.len() == 1 && fails[0].2.chain().any(|cause| cause.is::<Diagnostic>()) {
+        let (format, path, error) = fails.remove(0);
+        return Err(error.context(format!("Not a valid {} task at {}", format, path.display())));
+    }
+
+    let mut note = String::new();
     for (format, path, error) in fails {
-        let _ = writeln!(
-            message,
-            "    - Not a valid {} task at {}",
-            format,
-            path.display()
-        );
+        let _ = writeln!(note, "Not a valid {} task at {}", format, path.display());
         error.chain().for_each(|cause| {
-            let _ = write!(message, "      Caused by:\n        {}\n", cause);
+            let cause = match cause.downcast_ref::<Diagnostic>() {
+                Some(diagnostic) => match diagnostic.code() {
+                    Some(code) => format!("[{}] {}", code, diagnostic.message()),
+                    None => diagnostic.message().to_string(),
+                },
+                None => cause.to_string(),
+            };
+            let _ = writeln!(note, "  Caused by: {}", cause);
         });
     }
 
-    Err(anyhow!("{}", message)).context("Cannot find a valid task directory")
+    let mut diagnostic = Diagnostic::error("Cannot find a valid task directory")
+        .with_code(DiagnosticCode::TaskNotFound);
+    if !note.is_empty() {
+        diagnostic = diagnostic.with_note(note.trim_end());
+    }
+    Err(diagnostic.into())
 }
 
 /// Return the current working directory.
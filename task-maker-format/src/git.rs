@@ -0,0 +1,29 @@
+//! Information about the git repository containing a task or a contest.
+
+use std::path::Path;
+use std::process::Command;
+
+/// The commit of the git repository containing the directory, and whether the directory has
+/// uncommitted changes to the tracked files. If the directory is not in a git repository there is
+/// no commit.
+pub fn git_commit(dir: &Path) -> (Option<String>, bool) {
+    let commit = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+    if commit.is_none() {
+        return (None, false);
+    }
+    let dirty = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["status", "--porcelain", "--untracked-files=no", "--", "."])
+        .output()
+        .map(|output| !output.stdout.is_empty())
+        .unwrap_or(false);
+    (commit, dirty)
+}
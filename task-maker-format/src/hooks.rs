@@ -0,0 +1,210 @@
+//! Hooks for adding custom steps to the evaluation of the tasks.
+//!
+//! An [`EvaluationHook`] is called before and after the DAG of a task is built, and after the
+//! evaluation completes. It can add new executions to the DAG (e.g. for running a linter on the
+//! solutions, or for post-processing the outputs of the generators) and look at their results.
+//!
+//! The hooks can be registered in two ways:
+//! - statically, by the crates linked in the binary, with [`make_evaluation_hook!`];
+//! - at runtime, for example by a binary that loads its plugins dynamically, with
+//!   [`register_evaluation_hook`].
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Error};
+
+use crate::{EvaluationData, TaskFormat};
+
+/// Trait that describes a custom step of the evaluation.
+pub trait EvaluationHook: Send + Sync + std::fmt::Debug {
+    /// The name of the hook.
+    fn name(&self) -> &'static str;
+
+    /// This function will be called before the DAG of the task is built, the solutions to evaluate
+    /// are not known yet.
+    fn before_build(&self, _task: &TaskFormat, _eval: &mut EvaluationData) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// This function will be called after the DAG of the task is built. It can add new executions
+    /// to the DAG, also depending on the ones of the task.
+    fn after_build(&self, _task: &TaskFormat, _eval: &mut EvaluationData) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// This function will be called after the execution of the DAG completes.
+    fn after_evaluation(
+        &self,
+        _task: &TaskFormat,
+        _eval: &mut EvaluationData,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Struct for building new instances of `EvaluationHook`.
+///
+/// Like for the sanity checks, the builders are registered in the inventory instead of the hooks,
+/// since `Box`es are not available in a static context.
+pub struct EvaluationHookBuilder {
+    builder: fn() -> Box<dyn EvaluationHook>,
+}
+
+impl EvaluationHookBuilder {
+    /// A const function for initializing a new builder.
+    pub const fn new(builder: fn() -> Box<dyn EvaluationHook>) -> Self {
+        Self { builder }
+    }
+
+    /// Make an instance of this hook.
+    fn build(&self) -> Box<dyn EvaluationHook> {
+        (self.builder)()
+    }
+}
+
+inventory::collect!(&'static EvaluationHookBuilder);
+
+/// Register this struct, which must implement `Default`, as an evaluation hook.
+///
+/// ## Usage
+///
+/// ```ignore
+/// #[derive(Debug, Default)]
+/// struct HookName;
+/// impl EvaluationHook for HookName { /* ... */ }
+/// make_evaluation_hook!(HookName);
+/// ```
+#[macro_export]
+macro_rules! make_evaluation_hook {
+    ($name:tt) => {
+        $crate::paste::paste! {
+            #[allow(non_upper_case_globals)]
+            static [<__ $name _EVALUATION_HOOK>]: $crate::EvaluationHookBuilder =
+                $crate::EvaluationHookBuilder::new(|| Box::<$name>::default());
+            $crate::inventory::submit!(&[<__ $name _EVALUATION_HOOK>]);
+        }
+    };
+}
+
+lazy_static! {
+    /// The hooks registered at runtime.
+    static ref RUNTIME_HOOKS: Mutex<Vec<Arc<dyn EvaluationHook>>> = Mutex::new(vec![]);
+}
+
+/// Register an evaluation hook at runtime, it will be used by all the evaluations started after
+/// this call.
+pub fn register_evaluation_hook(hook: Box<dyn EvaluationHook>) {
+    RUNTIME_HOOKS.lock().unwrap().push(hook.into());
+}
+
+/// Build all the registered hooks: first the static ones, sorted by name, then the runtime ones in
+/// the order of registration.
+pub(crate) fn evaluation_hooks() -> Vec<Arc<dyn EvaluationHook>> {
+    let mut hooks: Vec<Arc<dyn EvaluationHook>> = inventory::iter::<&EvaluationHookBuilder>()
+        .map(|builder| builder.build().into())
+        .collect();
+    hooks.sort_by_key(|hook| hook.name());
+    hooks.extend(RUNTIME_HOOKS.lock().unwrap().iter().cloned());
+    hooks
+}
+
+/// A phase of the evaluation in which the hooks are called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HookPhase {
+    /// Before the DAG is built.
+    BeforeBuild,
+    /// After the DAG is built.
+    AfterBuild,
+    /// After the evaluation completes.
+    AfterEvaluation,
+}
+
+/// Call the hooks of the evaluation for the specified phase, stopping at the first one that fails.
+pub(crate) fn run_evaluation_hooks(
+    phase: HookPhase,
+    task: &TaskFormat,
+    eval: &mut EvaluationData,
+) -> Result<(), Error> {
+    // the hooks can change the evaluation data
+    for hook in eval.hooks.clone() {
+        let res = match phase {
+            HookPhase::BeforeBuild => hook.before_build(task, eval),
+            HookPhase::AfterBuild => hook.after_build(task, eval),
+            HookPhase::AfterEvaluation => hook.after_evaluation(task, eval),
+        };
+        res.with_context(|| format!("Evaluation hook {} failed", hook.name()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use anyhow::bail;
+
+    use crate::ioi::IOITask;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct CountingHook {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl EvaluationHook for CountingHook {
+        fn name(&self) -> &'static str {
+            "CountingHook"
+        }
+
+        fn after_build(&self, _task: &TaskFormat, eval: &mut EvaluationData) -> Result<(), Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if eval.task_root.ends_with("fail") {
+                bail!("failing on purpose");
+            }
+            Ok(())
+        }
+    }
+
+    /// A hook that does nothing, for testing the registration without affecting the evaluations
+    /// of the other tests.
+    #[derive(Debug, Default)]
+    struct NoopHook;
+
+    impl EvaluationHook for NoopHook {
+        fn name(&self) -> &'static str {
+            "NoopHook"
+        }
+    }
+
+    #[test]
+    fn test_evaluation_hooks() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let hook: Arc<dyn EvaluationHook> = Arc::new(CountingHook {
+            calls: calls.clone(),
+        });
+        let task: TaskFormat = IOITask::fake().into();
+
+        let (mut eval, _receiver) = EvaluationData::new("task");
+        eval.hooks = vec![hook.clone()];
+        run_evaluation_hooks(HookPhase::BeforeBuild, &task, &mut eval).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        run_evaluation_hooks(HookPhase::AfterBuild, &task, &mut eval).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let (mut eval, _receiver) = EvaluationData::new("fail");
+        eval.hooks = vec![hook];
+        let err = run_evaluation_hooks(HookPhase::AfterBuild, &task, &mut eval).unwrap_err();
+        assert!(format!("{:?}", err).contains("CountingHook"));
+    }
+
+    #[test]
+    fn test_register_evaluation_hook() {
+        let is_noop = |hook: &Arc<dyn EvaluationHook>| hook.name() == "NoopHook";
+        let (before, _receiver) = EvaluationData::new("task");
+        register_evaluation_hook(Box::new(NoopHook));
+        let (after, _receiver) = EvaluationData::new("task");
+        assert!(!before.hooks.iter().any(is_noop));
+        assert!(after.hooks.iter().any(is_noop));
+    }
+}
@@ -0,0 +1,256 @@
+//! Packaging of the attachments of a task.
+//!
+//! When enabled in `task.yaml` (with `attachment_packages: true`), a zip file is produced inside
+//! `att/` for each language that has a grader and a template. The package contains the template,
+//! the attached graders and headers of that language and all the files that are not source files
+//! (sample cases, ...). The zip files are built inside the DAG by the builtin `Zip` command.
+
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Error};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use task_maker_dag::{
+    BuiltinCommand, Execution, ExecutionCommand, ExecutionStatus, File, TrustLevel,
+};
+use task_maker_lang::LanguageManager;
+
+use crate::ioi::{IOITask, BOOKLET_PRIORITY};
+use crate::EvaluationData;
+
+/// A zip file with the attachments of the task for a single language.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentPackage {
+    /// Path of the zip file, relative to the task directory.
+    pub path: PathBuf,
+    /// Paths of the files inside the package, relative to the task directory.
+    pub files: Vec<PathBuf>,
+}
+
+impl AttachmentPackage {
+    /// Find the packages of the attachments of the task, one for each language with both a grader
+    /// and a template.
+    pub fn find(task: &IOITask) -> Result<Vec<AttachmentPackage>, Error> {
+        let att = task.path.join("att");
+        let Ok(dir) = att.read_dir() else {
+            return Ok(vec![]);
+        };
+        let mut files = vec![];
+        for entry in dir {
+            let path = entry.context("Cannot list the attachments")?.path();
+            // is_file follows the symlinks, the broken ones are skipped.
+            if path.is_file() && path.extension() != Some(OsStr::new("zip")) {
+                files.push(path);
+            }
+        }
+        files.sort();
+
+        let mut packages = vec![];
+        for grader in task.grader_map.all_paths() {
+            let Some(language) = LanguageManager::detect_language(grader) else {
+                continue;
+            };
+            let ext = grader
+                .extension()
+                .ok_or_else(|| anyhow!("Grader has no extension"))?
+                .to_string_lossy();
+            let template = att.join(format!("{}.{}", task.name, ext));
+            if !template.is_file() {
+                continue;
+            }
+            let path = PathBuf::from("att").join(format!("{}_{}.zip", task.name, ext));
+            if packages.iter().any(|p: &AttachmentPackage| p.path == path) {
+                continue;
+            }
+            let files = files
+                .iter()
+                .filter(|file| {
+                    if let Some(lang) = LanguageManager::detect_language(file) {
+                        return lang.name() == language.name();
+                    }
+                    let header_of = LanguageManager::detect_header_languages(file);
+                    header_of.is_empty() || header_of.iter().any(|l| l.name() == language.name())
+                })
+                .map(|file| task.path_of(file).to_path_buf())
+                .collect();
+            packages.push(AttachmentPackage { path, files });
+        }
+        Ok(packages)
+    }
+
+    /// Add the execution that builds the package to the DAG, writing the zip file inside the task
+    /// directory.
+    pub(crate) fn build(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
+        let zip_name = self
+            .path
+            .file_name()
+            .ok_or_else(|| anyhow!("Invalid package {}", self.path.display()))?
+            .to_string_lossy()
+            .to_string();
+        let mut exec = Execution::new(
+            format!("Packaging of {}", self.path.display()),
+            ExecutionCommand::builtin(BuiltinCommand::Zip),
+        );
+        exec.priority(BOOKLET_PRIORITY);
+        exec.trust(TrustLevel::Trusted);
+        let mut args = vec![zip_name.clone()];
+        for path in &self.files {
+            let name = path
+                .file_name()
+                .ok_or_else(|| anyhow!("Invalid attachment {}", path.display()))?
+                .to_string_lossy()
+                .to_string();
+            let file = File::new(format!("Attachment {}", path.display()));
+            exec.input(&file, &name, false);
+            eval.dag
+                .provide_file(file, task.path.join(path))
+                .with_context(|| format!("Failed to provide attachment {}", path.display()))?;
+            args.push(name);
+        }
+        exec.args(args);
+        let output = exec.output(&zip_name);
+        let package = self.path.clone();
+        eval.dag.on_execution_done(&exec.uuid, move |result| {
+            if result.status != ExecutionStatus::Success {
+                bail!("Failed to build {}: {:?}", package.display(), result);
+            }
+            Ok(())
+        });
+        eval.dag.add_execution(exec);
+        eval.dag
+            .write_file_to(&output, task.path.join(&self.path), false);
+        Ok(())
+    }
+}
+
+/// Write to `output` a zip file with the `files`, stored with their file name. The modification
+/// time of the files is fixed, so the same files produce the same zip.
+///
+/// This is the implementation of the builtin `Zip` command, run inside the sandbox.
+pub fn write_zip(output: &Path, files: &[PathBuf]) -> Result<(), Error> {
+    let out = std::fs::File::create(output)
+        .with_context(|| format!("Cannot create {}", output.display()))?;
+    let mut zip = ZipWriter::new(out);
+    // The default modification time is fixed, so the same files produce the same zip.
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for file in files {
+        let name = file
+            .file_name()
+            .ok_or_else(|| anyhow!("Invalid file {}", file.display()))?
+            .to_string_lossy();
+        let content =
+            std::fs::read(file).with_context(|| format!("Cannot read {}", file.display()))?;
+        zip.start_file(name, options)?;
+        zip.write_all(&content)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use crate::ioi::IOITask;
+
+    use super::*;
+
+    fn file_names(package: &AttachmentPackage) -> Vec<String> {
+        package
+            .files
+            .iter()
+            .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    fn make_task(dir: &Path) -> IOITask {
+        let mut task = IOITask::fake();
+        task.name = "task".into();
+        task.path = dir.to_path_buf();
+        std::fs::create_dir_all(dir.join("att")).unwrap();
+        for file in [
+            "grader.cpp",
+            "grader.py",
+            "task.cpp",
+            "task.py",
+            "task.h",
+            "input0.txt",
+        ] {
+            std::fs::write(dir.join("att").join(file), file).unwrap();
+        }
+        std::fs::create_dir_all(dir.join("sol")).unwrap();
+        std::fs::write(dir.join("sol/grader.cpp"), "").unwrap();
+        std::fs::write(dir.join("sol/grader.py"), "").unwrap();
+        task.grader_map = std::sync::Arc::new(task_maker_lang::GraderMap::new(vec![
+            dir.join("sol/grader.cpp"),
+            dir.join("sol/grader.py"),
+        ]));
+        task
+    }
+
+    #[test]
+    fn test_find_packages() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let task = make_task(tmpdir.path());
+        let packages = AttachmentPackage::find(&task).unwrap();
+        assert_eq!(packages.len(), 2);
+        let cpp = packages
+            .iter()
+            .find(|p| p.path == Path::new("att/task_cpp.zip"))
+            .unwrap();
+        assert_eq!(
+            file_names(cpp),
+            vec!["grader.cpp", "input0.txt", "task.cpp", "task.h"]
+        );
+        let py = packages
+            .iter()
+            .find(|p| p.path == Path::new("att/task_py.zip"))
+            .unwrap();
+        assert_eq!(file_names(py), vec!["grader.py", "input0.txt", "task.py"]);
+    }
+
+    #[test]
+    fn test_zip_is_deterministic() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let task = make_task(tmpdir.path());
+        let package = AttachmentPackage::find(&task).unwrap().remove(0);
+        let files: Vec<_> = package.files.iter().map(|f| task.path.join(f)).collect();
+        let first = tmpdir.path().join("first.zip");
+        let second = tmpdir.path().join("second.zip");
+        write_zip(&first, &files).unwrap();
+        write_zip(&second, &files).unwrap();
+        let zip = std::fs::read(&first).unwrap();
+        assert_eq!(zip, std::fs::read(&second).unwrap());
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(zip)).unwrap();
+        assert_eq!(archive.len(), package.files.len());
+        let mut content = String::new();
+        archive
+            .by_name("input0.txt")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "input0.txt");
+    }
+
+    #[test]
+    fn test_build_in_dag() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let task = make_task(tmpdir.path());
+        let package = AttachmentPackage::find(&task).unwrap().remove(0);
+        let (mut eval, _receiver) = EvaluationData::new(tmpdir.path());
+        package.build(&task, &mut eval).unwrap();
+
+        let groups = &eval.dag.data.execution_groups;
+        assert_eq!(groups.len(), 1);
+        let exec = &groups.values().next().unwrap().executions[0];
+        assert_eq!(exec.command, ExecutionCommand::builtin(BuiltinCommand::Zip));
+        let zip_name = package.path.file_name().unwrap();
+        assert_eq!(Path::new(&exec.args[0]), Path::new(zip_name));
+        assert_eq!(exec.inputs.len(), package.files.len());
+        assert!(exec.outputs.contains_key(Path::new(zip_name)));
+    }
+}
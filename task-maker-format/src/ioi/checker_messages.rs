@@ -0,0 +1,133 @@
+//! Mapping of the messages of the checker into the verdicts shown to the user.
+//!
+//! The checkers usually print a fixed set of messages (e.g. `Output is correct`), which may need to
+//! be localized for the contest. The mapping is read from the `checker_messages` field of
+//! `contest.yaml` (in the parent directory of the task) and of `task.yaml`, the entries of the task
+//! overriding the ones of the contest.
+//!
+//! The verdict replaces the message in the UIs, while the original message is kept in the
+//! `IOITestcaseScore` messages, next to the verdict.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use anyhow::{Context, Error};
+use serde::{Deserialize, Serialize};
+use typescript_definitions::TypeScriptify;
+
+/// The part of `contest.yaml` with the mapping of the messages of the checker.
+#[derive(Debug, Deserialize)]
+struct ContestCheckerMessages {
+    /// The verdict to show for each message of the checker.
+    #[serde(default)]
+    checker_messages: BTreeMap<String, String>,
+}
+
+/// The mapping from the messages of the checker to the verdicts shown to the user.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TypeScriptify)]
+pub struct CheckerMessages {
+    /// The verdict to show for each message of the checker.
+    pub messages: HashMap<String, String>,
+}
+
+impl CheckerMessages {
+    /// Make a new mapping from the messages of the checker to the verdicts.
+    pub fn new<I: IntoIterator<Item = (String, String)>>(messages: I) -> CheckerMessages {
+        CheckerMessages {
+            messages: messages.into_iter().collect(),
+        }
+    }
+
+    /// Build the mapping of the task in `task_dir`, merging the one of the contest with the one of
+    /// the task, specified in `task_messages`.
+    pub fn load<I: IntoIterator<Item = (String, String)>>(
+        task_dir: &Path,
+        task_messages: I,
+    ) -> Result<CheckerMessages, Error> {
+        let mut messages = HashMap::new();
+        if let Some(contest_dir) = task_dir.parent() {
+            let contest_yaml = contest_dir.join("contest.yaml");
+            if contest_yaml.exists() {
+                let content = std::fs::read_to_string(&contest_yaml)
+                    .with_context(|| format!("Failed to read {}", contest_yaml.display()))?;
+                let contest: ContestCheckerMessages = serde_yaml::from_str(&content)
+                    .with_context(|| format!("Failed to parse {}", contest_yaml.display()))?;
+                messages.extend(contest.checker_messages);
+            }
+        }
+        messages.extend(task_messages);
+        Ok(CheckerMessages { messages })
+    }
+
+    /// The verdict to show instead of the provided message of the checker, if the message is
+    /// mapped.
+    pub fn verdict(&self, message: &str) -> Option<String> {
+        self.messages.get(message.trim()).cloned()
+    }
+
+    /// Whether no message is mapped.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict() {
+        let messages = CheckerMessages::new(vec![(
+            "Output is correct".to_string(),
+            "Risposta corretta".to_string(),
+        )]);
+        assert_eq!(
+            messages.verdict("Output is correct"),
+            Some("Risposta corretta".to_string())
+        );
+        assert_eq!(
+            messages.verdict(" Output is correct\n"),
+            Some("Risposta corretta".to_string())
+        );
+        assert_eq!(messages.verdict("Output is incorrect"), None);
+    }
+
+    #[test]
+    fn test_load_without_contest() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let task_dir = tmpdir.path().join("task");
+        let messages = CheckerMessages::load(
+            &task_dir,
+            vec![("Wrong".to_string(), "Sbagliato".to_string())],
+        )
+        .unwrap();
+        assert_eq!(messages.verdict("Wrong"), Some("Sbagliato".to_string()));
+    }
+
+    #[test]
+    fn test_load_task_overrides_contest() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let task_dir = tmpdir.path().join("task");
+        std::fs::write(
+            tmpdir.path().join("contest.yaml"),
+            "tasks: [task]\nchecker_messages:\n  Correct: Corretto\n  Wrong: Errato\n",
+        )
+        .unwrap();
+        let messages = CheckerMessages::load(
+            &task_dir,
+            vec![("Wrong".to_string(), "Sbagliato".to_string())],
+        )
+        .unwrap();
+        assert_eq!(messages.verdict("Correct"), Some("Corretto".to_string()));
+        assert_eq!(messages.verdict("Wrong"), Some("Sbagliato".to_string()));
+    }
+
+    #[test]
+    fn test_load_contest_without_messages() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let task_dir = tmpdir.path().join("task");
+        std::fs::write(tmpdir.path().join("contest.yaml"), "tasks: [task]\n").unwrap();
+        let messages = CheckerMessages::load(&task_dir, vec![]).unwrap();
+        assert!(messages.is_empty());
+    }
+}
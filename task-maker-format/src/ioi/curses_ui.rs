@@ -14,8 +14,9 @@ use crate::ioi::{
     UIState,
 };
 use crate::ui::curses::{
-    compilation_status_text, draw_compilations, inner_block, render_block, render_server_status,
-    CursesDrawer, CursesUI as GenericCursesUI, FrameType, GREEN, ORANGE, RED, YELLOW,
+    compilation_status_text, draw_compilations, inner_block, live_stderr_height, render_block,
+    render_live_stderr, render_server_status, CursesDrawer, CursesUI as GenericCursesUI, FrameType,
+    GREEN, ORANGE, RED, YELLOW,
 };
 use crate::ui::UIExecutionStatus;
 use crate::ScoreStatus;
@@ -80,9 +81,10 @@ fn draw_frame(state: &UIState, f: &mut FrameType, loading: char, frame_index: us
         .map(|s| s.connected_workers.len())
         .unwrap_or(0) as u16
         + 2;
+    let stderr_len = live_stderr_height(state.executor_status.as_ref());
     let total_height = f.size().height;
     // fixed size section heights
-    let top_height = header_len + compilations_len + booklet_len + generations_len;
+    let top_height = header_len + compilations_len + booklet_len + generations_len + stderr_len;
     // if the sections don't just fit, reduce the size of the workers until they fit but
     // without shortening it more than 3 lines (aka box + 1 worker).
     if top_height + evaluations_len + workers_len > total_height {
@@ -100,6 +102,7 @@ fn draw_frame(state: &UIState, f: &mut FrameType, loading: char, frame_index: us
                 Constraint::Length(booklet_len),
                 Constraint::Length(generations_len),
                 Constraint::Min(0),
+                Constraint::Length(stderr_len),
                 Constraint::Length(workers_len),
             ]
             .as_ref(),
@@ -131,9 +134,10 @@ fn draw_frame(state: &UIState, f: &mut FrameType, loading: char, frame_index: us
         render_block(f, chunks[4], " Evaluations ");
         draw_evaluations(f, inner_block(chunks[4]), state, loading);
     }
+    render_live_stderr(f, chunks[5], state.executor_status.as_ref());
     render_server_status(
         f,
-        chunks[5],
+        chunks[6],
         state.executor_status.as_ref(),
         loading,
         frame_index,
@@ -0,0 +1,142 @@
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Error};
+use serde::Serialize;
+
+use task_maker_dag::{
+    Execution, ExecutionLimits, ExecutionResourcesUsage, ExecutionStatus, FileUuid,
+};
+
+use crate::ioi::{SubtaskId, TestcaseId};
+use crate::EvaluationData;
+
+/// Where the artifacts of the evaluation of a solution on a testcase are kept, when the
+/// `keep_artifacts` option of the evaluation is set.
+///
+/// The artifacts of each testcase are in `<solution>/subtask<N>/testcase<M>/`, where `<solution>`
+/// is the path of the solution relative to the task directory: the files used and produced by the
+/// evaluation, the standard error of the executions and a `.json` file with the result of each
+/// execution.
+#[derive(Debug, Clone)]
+pub(crate) struct Artifacts {
+    /// The directory of the artifacts of this testcase.
+    dir: PathBuf,
+}
+
+/// The result of an execution, as written in the artifacts.
+#[derive(Debug, Serialize)]
+struct ArtifactResult {
+    /// The description of the execution.
+    description: String,
+    /// The limits of the execution.
+    limits: ExecutionLimits,
+    /// The status of the execution.
+    status: ExecutionStatus,
+    /// Whether the execution has been killed by the sandbox.
+    was_killed: bool,
+    /// Whether the result comes from the cache.
+    was_cached: bool,
+    /// The resources used by the execution.
+    resources: ExecutionResourcesUsage,
+    /// The key in the file store of the full standard output, if it's kept.
+    stdout_key: Option<String>,
+    /// The key in the file store of the full standard error, if it's kept.
+    stderr_key: Option<String>,
+}
+
+impl Artifacts {
+    /// The artifacts of the evaluation of the solution at `solution` on a testcase, `None` if the
+    /// artifacts should not be kept.
+    pub(crate) fn new(
+        eval: &EvaluationData,
+        solution: &Path,
+        subtask_id: SubtaskId,
+        testcase_id: TestcaseId,
+    ) -> Option<Artifacts> {
+        let dir = eval.keep_artifacts.as_ref()?;
+        // the solutions with the same name in different directories must not share the artifacts,
+        // the solutions outside the task directory are kept by their full path
+        let name: PathBuf = solution
+            .strip_prefix(&eval.task_root)
+            .unwrap_or(solution)
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .collect();
+        Some(Artifacts {
+            dir: dir
+                .join(name)
+                .join(format!("subtask{}", subtask_id))
+                .join(format!("testcase{}", testcase_id)),
+        })
+    }
+
+    /// Keep a file of the evaluation, with the given name.
+    pub(crate) fn file<F: Into<FileUuid>>(&self, eval: &mut EvaluationData, file: F, name: &str) {
+        eval.dag.copy_file_to(file, self.dir.join(name));
+    }
+
+    /// Keep the standard error and the result of an execution, in `<name>.stderr` and
+    /// `<name>.json`. The execution is not added to the DAG.
+    pub(crate) fn execution(&self, eval: &mut EvaluationData, exec: &mut Execution, name: &str) {
+        if eval.dag.data.config.dry_run {
+            return;
+        }
+        self.file(eval, exec.stderr(), &format!("{}.stderr", name));
+        let path = self.dir.join(format!("{}.json", name));
+        let description = exec.description.clone();
+        let limits = exec.limits.clone();
+        eval.dag.on_execution_done(&exec.uuid, move |result| {
+            let result = ArtifactResult {
+                description,
+                limits,
+                status: result.status,
+                was_killed: result.was_killed,
+                was_cached: result.was_cached,
+                resources: result.resources,
+                stdout_key: result.stdout_key,
+                stderr_key: result.stderr_key,
+            };
+            write_result(&path, &result)
+                .with_context(|| format!("Failed to write artifact {}", path.display()))
+        });
+    }
+}
+
+/// Write the result of an execution to `path`, creating its directory.
+fn write_result(path: &Path, result: &ArtifactResult) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(result)?;
+    std::fs::write(path, json)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artifacts_dir() {
+        let (mut eval, _receiver) = EvaluationData::new("/task");
+        assert!(Artifacts::new(&eval, Path::new("/task/sol/sol.cpp"), 0, 0).is_none());
+        eval.keep_artifacts = Some("/artifacts".into());
+        let dir = |solution: &str| {
+            Artifacts::new(&eval, Path::new(solution), 1, 2)
+                .unwrap()
+                .dir
+        };
+        assert_eq!(
+            dir("/task/sol/sol.cpp"),
+            Path::new("/artifacts/sol/sol.cpp/subtask1/testcase2")
+        );
+        assert_eq!(
+            dir("/task/sol/old/sol.cpp"),
+            Path::new("/artifacts/sol/old/sol.cpp/subtask1/testcase2")
+        );
+        assert_eq!(
+            dir("/home/user/sol.cpp"),
+            Path::new("/artifacts/home/user/sol.cpp/subtask1/testcase2")
+        );
+    }
+}
@@ -1,14 +1,16 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use anyhow::{anyhow, Context, Error};
+use anyhow::{anyhow, bail, Context, Error};
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
-use task_maker_dag::{Execution, ExecutionCommand, ExecutionStatus, FileUuid, Priority};
-use task_maker_diagnostics::Diagnostic;
+use task_maker_dag::{
+    BuiltinCommand, Execution, ExecutionCommand, ExecutionStatus, FileUuid, Priority, TrustLevel,
+};
+use task_maker_diagnostics::{Diagnostic, DiagnosticCategory, DiagnosticCode};
 
-use crate::ioi::{SubtaskId, TestcaseId, EVALUATION_PRIORITY, STDERR_CONTENT_LENGTH};
+use crate::ioi::{Artifacts, SubtaskId, TestcaseId, EVALUATION_PRIORITY, STDERR_CONTENT_LENGTH};
 use crate::ui::UIMessage;
 use crate::{bind_exec_callbacks, UISender};
 use crate::{EvaluationData, SourceFile, Tag};
@@ -18,7 +20,7 @@ use crate::{EvaluationData, SourceFile, Tag};
 #[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
 pub enum Checker {
     /// Use a built-in white diff checker that scores 1.0 if the two output files are identical
-    /// except for white spaces. It internally uses `diff --ignore-all-spaces`
+    /// except for white spaces. It's implemented by task-maker, see the `white_diff` module.
     WhiteDiff,
     /// Use a custom checker based on an executable that can output a score (from 0.0 to 1.0) to
     /// stdout as well as a custom message on stderr.
@@ -28,6 +30,106 @@ pub enum Checker {
     Custom(Arc<SourceFile>),
 }
 
+/// What to do when a checker outputs a score outside the `[0, 1]` range (or `NaN`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CheckerScorePolicy {
+    /// Clamp the score inside the valid range (`NaN` becomes 0) and emit a warning.
+    #[default]
+    Clamp,
+    /// Do not assign a score to the testcase and emit an error.
+    Fail,
+}
+
+impl std::str::FromStr for CheckerScorePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<CheckerScorePolicy, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "clamp" => Ok(CheckerScorePolicy::Clamp),
+            "fail" => Ok(CheckerScorePolicy::Fail),
+            _ => Err(format!("Unknown checker score policy: {}", s)),
+        }
+    }
+}
+
+impl CheckerScorePolicy {
+    /// Parse and validate the score printed by a checker, emitting a diagnostic with the raw
+    /// output of the checker if it's not a valid score or if it's out of range. Returns the score
+    /// to use, or `None` if the testcase should not be scored.
+    ///
+    /// This is shared by all the checkers (including the managers of the communication tasks), so
+    /// that the policy is applied in the same way.
+    pub(crate) fn parse_score<S: UISender + ?Sized>(
+        &self,
+        raw_output: Vec<u8>,
+        testcase_id: Option<TestcaseId>,
+        description: String,
+        sender: &S,
+    ) -> Result<Option<f64>, Error> {
+        let output = String::from_utf8_lossy(&raw_output);
+        let score: f64 = match output.trim().parse() {
+            Ok(score) => score,
+            Err(e) => {
+                let message = if let Some(testcase_id) = testcase_id {
+                    format!(
+                        "Checker returned an invalid score ({:?}) for testcase {}",
+                        output, testcase_id
+                    )
+                } else {
+                    format!("Checker returned an invalid score ({:?})", output)
+                };
+                let diagnostic = Diagnostic::error(message)
+                    .with_code(DiagnosticCode::InvalidCheckerOutput)
+                    .with_note(description)
+                    .with_help(format!("The parse error is: {:?}", e))
+                    .with_help_attachment(raw_output);
+                sender.add_diagnostic(diagnostic)?;
+                return Ok(None);
+            }
+        };
+        if (0.0..=1.0).contains(&score) {
+            return Ok(Some(score));
+        }
+        let message = if let Some(testcase_id) = testcase_id {
+            format!(
+                "Checker returned an out-of-range score ({}) for testcase {}",
+                score, testcase_id
+            )
+        } else {
+            format!("Checker returned an out-of-range score ({})", score)
+        };
+        match self {
+            CheckerScorePolicy::Clamp => {
+                let clamped = if score.is_nan() {
+                    0.0
+                } else {
+                    score.clamp(0.0, 1.0)
+                };
+                let diagnostic = Diagnostic::warning(message)
+                    .with_code(DiagnosticCode::InvalidCheckerOutput)
+                    .with_category(DiagnosticCategory::Checker)
+                    .with_note(description)
+                    .with_help(format!(
+                        "The score has been changed to {}, the score must be between 0.0 and 1.0",
+                        clamped
+                    ))
+                    .with_help_attachment(raw_output);
+                sender.add_diagnostic(diagnostic)?;
+                Ok(Some(clamped))
+            }
+            CheckerScorePolicy::Fail => {
+                let diagnostic = Diagnostic::error(message)
+                    .with_code(DiagnosticCode::InvalidCheckerOutput)
+                    .with_note(description)
+                    .with_help("The score must be between 0.0 and 1.0")
+                    .with_help_attachment(raw_output);
+                sender.add_diagnostic(diagnostic)?;
+                Ok(None)
+            }
+        }
+    }
+}
+
 impl Checker {
     /// Build the execution of the checker for the specified files, the callback will be called when
     /// the result is ready. The execution does not send UI messages yet and it's not added to the
@@ -48,23 +150,20 @@ impl Checker {
     {
         match self {
             Checker::WhiteDiff => {
-                let mut exec = Execution::new(description, ExecutionCommand::system("diff"));
-                exec.args(vec![
-                    "--brief",
-                    "--speed-large-files",
-                    "--ignore-blank-lines",
-                    "--ignore-space-change",
-                    "correct",
-                    "test",
-                ])
-                .input(correct_output, "correct", false)
-                .input(test_output, "test", false)
-                .tag(Tag::Checking.into())
-                .priority(EVALUATION_PRIORITY - testcase_id.unwrap_or_default() as Priority);
+                let mut exec = Execution::new(
+                    description,
+                    ExecutionCommand::builtin(BuiltinCommand::WhiteDiff),
+                );
+                exec.args(vec!["correct", "test"])
+                    .input(correct_output, "correct", false)
+                    .input(test_output, "test", false)
+                    .tag(Tag::Checking.into())
+                    .priority(EVALUATION_PRIORITY - testcase_id.unwrap_or_default() as Priority);
+                exec.trust(TrustLevel::Trusted);
 
                 eval.dag.on_execution_done(&exec.uuid, move |result| {
                     match result.status {
-                        // diff exits with 0 if the files are equal
+                        // the white diff exits with 0 if the files are equal
                         ExecutionStatus::Success => callback(1.0, "Output is correct".into())
                             .context("Checker callback failed")?,
                         // return code 1 means the files are different
@@ -72,7 +171,7 @@ impl Checker {
                             callback(0.0, "Output is incorrect".into())
                                 .context("Checker callback failed")?
                         }
-                        _ => unreachable!("diff died badly? {:?}", result),
+                        _ => bail!("The white diff checker failed: {:?}", result),
                     };
                     Ok(())
                 });
@@ -93,8 +192,9 @@ impl Checker {
                     .capture_stdout(128)
                     .capture_stderr(STDERR_CONTENT_LENGTH)
                     .priority(EVALUATION_PRIORITY - testcase_id.unwrap_or_default() as Priority);
-                exec.limits_mut().allow_multiprocess();
+                exec.trust(TrustLevel::Trusted);
                 let sender = eval.sender.clone();
+                let score_policy = eval.checker_score_policy;
                 eval.dag.on_execution_done(&exec.uuid, move |res| {
                     let stdout = res
                         .stdout
@@ -114,33 +214,17 @@ impl Checker {
                             "Checker failed while computing a score for a testcase".into()
                         };
                         let diagnostic = Diagnostic::error(message)
+                            .with_code(DiagnosticCode::CheckerFailed)
                             .with_note(description)
                             .with_help(format!("The checker crashed with: {:?}", res.status))
                             .with_help_attachment(stderr);
                         sender.add_diagnostic(diagnostic)?;
                         return Ok(());
                     }
-                    let score = String::from_utf8_lossy(&stdout);
-                    let score: f64 = match score.trim().parse() {
-                        Ok(score) => score,
-                        Err(e) => {
-                            let message = if let Some(testcase_id) = testcase_id {
-                                format!(
-                                    "Checker returned an invalid score ({:?}) for testcase {}",
-                                    score, testcase_id
-                                )
-                            } else {
-                                format!("Checker returned an invalid score ({:?})", score)
-                            };
-                            let diagnostic = Diagnostic::error(message)
-                                .with_note(description)
-                                .with_help(format!("The parse error is: {:?}", e))
-                                .with_help_attachment(stdout);
-                            sender.add_diagnostic(diagnostic)?;
-                            return Ok(());
-                        }
-                    };
-                    callback(score, message)
+                    match score_policy.parse_score(stdout, testcase_id, description, &*sender)? {
+                        Some(score) => callback(score, message),
+                        None => Ok(()),
+                    }
                 });
                 Ok(exec)
             }
@@ -165,7 +249,7 @@ impl Checker {
         F: FnOnce(f64, String) -> Result<(), Error> + Send + Sync + 'static,
     {
         let solution = solution.into();
-        let exec = self.check(
+        let mut exec = self.check(
             eval,
             Some(testcase_id),
             format!(
@@ -179,6 +263,12 @@ impl Checker {
             test_output,
             callback,
         )?;
+        if let Some(artifacts) = Artifacts::new(eval, &solution, subtask_id, testcase_id) {
+            if let Some(stdout) = &exec.stdout {
+                artifacts.file(eval, stdout.uuid, "checker.stdout");
+            }
+            artifacts.execution(eval, &mut exec, "checker");
+        }
         bind_exec_callbacks!(
             eval,
             exec.uuid,
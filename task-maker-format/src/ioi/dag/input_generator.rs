@@ -5,10 +5,13 @@ use anyhow::{bail, Context, Error};
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
-use task_maker_dag::{Execution, File, FileUuid, Priority};
-use task_maker_diagnostics::Diagnostic;
+use task_maker_dag::{Execution, File, FileUuid, Priority, TrustLevel};
+use task_maker_diagnostics::{Diagnostic, DiagnosticCode};
 
-use crate::ioi::{SubtaskId, TestcaseId, GENERATION_PRIORITY, STDERR_CONTENT_LENGTH};
+use crate::ioi::{
+    apply_tool_time_limit, tool_time_limit_help, SubtaskId, TestcaseId, GENERATION_PRIORITY,
+    STDERR_CONTENT_LENGTH,
+};
 use crate::ui::UIMessage;
 use crate::{bind_exec_callbacks, UISender};
 use crate::{EvaluationData, SourceFile, Tag};
@@ -37,7 +40,10 @@ impl InputGenerator {
         match self {
             InputGenerator::StaticFile(path) => {
                 if !path.exists() {
-                    bail!("COPY from not existing file: {:?}", path);
+                    bail!(
+                        Diagnostic::error(format!("COPY from not existing file: {:?}", path))
+                            .with_code(DiagnosticCode::MissingFile)
+                    );
                 }
                 let file = File::new(format!(
                     "Static input file of testcase {}, subtask {} from {:?}",
@@ -56,7 +62,7 @@ impl InputGenerator {
                 let mut exec = source_file
                     .execute(eval, description, args.clone())
                     .context("Failed to execute generator source file")?;
-                exec.limits_mut().allow_multiprocess();
+                exec.trust(TrustLevel::Trusted);
                 exec.tag(Tag::Generation.into());
                 exec.priority(GENERATION_PRIORITY - testcase_id as Priority);
                 let stdout = exec.stdout();
@@ -66,12 +72,13 @@ impl InputGenerator {
     }
 
     /// Add the generation of the input file to the DAG and the callbacks to the UI, returning the
-    /// handle to the input file.
+    /// handle to the input file. The generator is limited to `time_limit` seconds, if set.
     pub(crate) fn generate_and_bind(
         &self,
         eval: &mut EvaluationData,
         subtask_id: SubtaskId,
         testcase_id: TestcaseId,
+        time_limit: Option<f64>,
     ) -> Result<FileUuid, Error> {
         let (input, gen) = self.generate(
             eval,
@@ -91,6 +98,7 @@ impl InputGenerator {
         );
         // If there is an execution, bind its callbacks and store the input file.
         if let Some(mut gen) = gen {
+            apply_tool_time_limit(&mut gen, time_limit);
             gen.capture_stderr(STDERR_CONTENT_LENGTH);
             bind_exec_callbacks!(eval, gen.uuid, |status| UIMessage::IOIGeneration {
                 subtask: subtask_id,
@@ -103,7 +111,13 @@ impl InputGenerator {
                 if !result.status.is_success() {
                     let mut diagnostic =
                         Diagnostic::error(format!("Failed to generate input {}", testcase_id))
+                            .with_code(DiagnosticCode::GenerationFailed)
                             .with_note(format!("Generator arguments are: {}", args));
+                    if let Some(help) =
+                        tool_time_limit_help(&result.status, "generation_time_limit")
+                    {
+                        diagnostic = diagnostic.with_help(help);
+                    }
                     if let Some(stderr) = result.stderr {
                         diagnostic = diagnostic.with_help_attachment(stderr);
                     }
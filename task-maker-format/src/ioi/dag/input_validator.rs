@@ -4,10 +4,13 @@ use anyhow::{Context, Error};
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
-use task_maker_dag::{Execution, FileUuid, Priority};
-use task_maker_diagnostics::Diagnostic;
+use task_maker_dag::{Execution, FileUuid, Priority, TrustLevel};
+use task_maker_diagnostics::{Diagnostic, DiagnosticCode};
 
-use crate::ioi::{SubtaskId, TestcaseId, GENERATION_PRIORITY, STDERR_CONTENT_LENGTH};
+use crate::ioi::{
+    apply_tool_time_limit, tool_time_limit_help, SubtaskId, TestcaseId, GENERATION_PRIORITY,
+    STDERR_CONTENT_LENGTH,
+};
 use crate::ui::UIMessage;
 use crate::{bind_exec_callbacks, UISender};
 use crate::{EvaluationData, SourceFile, Tag};
@@ -16,6 +19,9 @@ use crate::{EvaluationData, SourceFile, Tag};
 /// placed in the current working directory of the validation sandbox.
 pub const TM_VALIDATION_FILE_NAME: &str = "tm_validation_file";
 
+/// The variables that can be used in the arguments of an `InputValidator::CustomWithVariables`.
+pub const VALIDATOR_VARIABLES: &[&str] = &["INPUT", "ST_NUM", "ST_NAME", "TESTCASE"];
+
 /// An input file validator is responsible for checking that the input file follows the format and
 /// constraints defined by the task.
 #[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify, Default)]
@@ -26,6 +32,10 @@ pub enum InputValidator {
     /// Use a custom command to check if the input file is valid. The command should exit with
     /// non-zero return code if and only if the input is invalid.
     Custom(Arc<SourceFile>, Vec<String>),
+    /// Like `Custom`, but the arguments starting with `$` are variables, replaced with the values
+    /// of the testcase to validate: `$INPUT` is the path of the input file, `$ST_NUM` the id of the
+    /// subtask (starting from 0), `$ST_NAME` its name and `$TESTCASE` the id of the testcase.
+    CustomWithVariables(Arc<SourceFile>, Vec<String>),
 }
 
 impl InputValidator {
@@ -41,31 +51,44 @@ impl InputValidator {
         testcase_id: TestcaseId,
         input: FileUuid,
     ) -> Result<(Option<FileUuid>, Option<Execution>), Error> {
-        match self {
-            InputValidator::AssumeValid => Ok((None, None)),
-            InputValidator::Custom(source_file, args) => {
-                let mut exec = source_file
-                    .execute(eval, description, args.clone())
-                    .context("Failed to execute validator source file")?;
-                exec.input(input, TM_VALIDATION_FILE_NAME, false)
-                    .tag(Tag::Generation.into())
-                    .priority(GENERATION_PRIORITY - testcase_id as Priority)
-                    .env("TM_SUBTASK", subtask_id.to_string())
-                    .env("TM_TESTCASE", testcase_id.to_string());
-                if let Some(name) = subtask_name {
-                    exec.env("TM_SUBTASK_NAME", name);
-                }
-                exec.limits_mut().allow_multiprocess();
-                let stdout = exec.stdout();
-
-                Ok((Some(stdout.uuid), Some(exec)))
+        let (source_file, args) = match self {
+            InputValidator::AssumeValid => return Ok((None, None)),
+            InputValidator::Custom(source_file, args) => (source_file, args.clone()),
+            InputValidator::CustomWithVariables(source_file, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| match arg.as_str() {
+                        "$INPUT" => TM_VALIDATION_FILE_NAME.to_string(),
+                        "$ST_NUM" => subtask_id.to_string(),
+                        "$ST_NAME" => subtask_name.unwrap_or_default().to_string(),
+                        "$TESTCASE" => testcase_id.to_string(),
+                        _ => arg.clone(),
+                    })
+                    .collect();
+                (source_file, args)
             }
+        };
+        let mut exec = source_file
+            .execute(eval, description, args)
+            .context("Failed to execute validator source file")?;
+        exec.input(input, TM_VALIDATION_FILE_NAME, false)
+            .tag(Tag::Generation.into())
+            .priority(GENERATION_PRIORITY - testcase_id as Priority)
+            .env("TM_SUBTASK", subtask_id.to_string())
+            .env("TM_TESTCASE", testcase_id.to_string());
+        if let Some(name) = subtask_name {
+            exec.env("TM_SUBTASK_NAME", name);
         }
+        exec.trust(TrustLevel::Trusted);
+        let stdout = exec.stdout();
+
+        Ok((Some(stdout.uuid), Some(exec)))
     }
 
     /// Add the validation of the input file to the DAG and the callbacks to the UI, optionally
     /// returning a fake file that blocks the usage of the actual input until the validation
-    /// succeeds. If the validation is ignored, `None` is returned.
+    /// succeeds. If the validation is ignored, `None` is returned. The validator is limited to
+    /// `time_limit` seconds, if set.
     pub(crate) fn validate_and_bind(
         &self,
         eval: &mut EvaluationData,
@@ -73,6 +96,7 @@ impl InputValidator {
         subtask_name: Option<&str>,
         testcase_id: TestcaseId,
         input: FileUuid,
+        time_limit: Option<f64>,
     ) -> Result<Option<FileUuid>, Error> {
         let (handle, val) = self.validate(
             eval,
@@ -86,6 +110,7 @@ impl InputValidator {
             input,
         )?;
         if let Some(mut val) = val {
+            apply_tool_time_limit(&mut val, time_limit);
             val.capture_stderr(STDERR_CONTENT_LENGTH);
             bind_exec_callbacks!(eval, val.uuid, |status| UIMessage::IOIValidation {
                 subtask: subtask_id,
@@ -98,7 +123,13 @@ impl InputValidator {
                     let mut diagnostic = Diagnostic::error(format!(
                         "Failed to validate input {} for subtask {}",
                         testcase_id, subtask_id
-                    ));
+                    ))
+                    .with_code(DiagnosticCode::ValidationFailed);
+                    if let Some(help) =
+                        tool_time_limit_help(&result.status, "validation_time_limit")
+                    {
+                        diagnostic = diagnostic.with_help(help);
+                    }
                     if let Some(stderr) = result.stderr {
                         diagnostic = diagnostic.with_help_attachment(stderr);
                     }
@@ -1,12 +1,16 @@
 use serde::{Deserialize, Serialize};
+use typescript_definitions::TypeScriptify;
 
-pub use checker::Checker;
+pub(crate) use artifacts::Artifacts;
+pub use checker::{Checker, CheckerScorePolicy};
 pub use input_generator::InputGenerator;
-pub use input_validator::{InputValidator, TM_VALIDATION_FILE_NAME};
+pub use input_validator::{InputValidator, TM_VALIDATION_FILE_NAME, VALIDATOR_VARIABLES};
 pub use output_generator::OutputGenerator;
-use task_maker_dag::Priority;
-pub use task_type::{BatchTypeData, CommunicationTypeData, TaskType, UserIo};
+use task_maker_dag::{Execution, ExecutionStatus, Priority};
+pub(crate) use task_type::{execute_steps, TestcaseEvaluation};
+pub use task_type::{BatchTypeData, CommunicationTypeData, TaskType, TwoStepsTypeData, UserIo};
 
+mod artifacts;
 mod checker;
 mod input_generator;
 mod input_validator;
@@ -23,6 +27,13 @@ pub const BOOKLET_PRIORITY: Priority = 10_000;
 /// Maximum number of bytes of the captured standard error.
 pub const STDERR_CONTENT_LENGTH: usize = 10 * 1024;
 
+/// Default time limit, in seconds, of the generation of an input file.
+pub const DEFAULT_GENERATION_TIME_LIMIT: f64 = 60.0;
+/// Default time limit, in seconds, of the validation of an input file.
+pub const DEFAULT_VALIDATION_TIME_LIMIT: f64 = 30.0;
+/// Default time limit, in seconds, of the generation of an output file.
+pub const DEFAULT_OUTPUT_GENERATION_TIME_LIMIT: f64 = 60.0;
+
 /// The aggregator of testcase scores for computing the subtask score.
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -39,6 +50,32 @@ pub enum TestcaseScoreAggregator {
     Sum,
 }
 
+/// How the scores are rounded to the precision of the task.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreRounding {
+    /// Round to the nearest value, like CMS does.
+    #[default]
+    Nearest,
+    /// Round towards zero.
+    Down,
+    /// Round away from zero.
+    Up,
+}
+
+/// The time limits, in seconds, of the executions that prepare a testcase: the generator, the
+/// validator and the official solution. A limit of zero means no limit, and a limit that is not set
+/// falls back to the one of the task.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize, TypeScriptify)]
+pub struct ToolTimeLimits {
+    /// The time limit of the generation of the input file.
+    pub generation: Option<f64>,
+    /// The time limit of the validation of the input file.
+    pub validation: Option<f64>,
+    /// The time limit of the generation of the output file.
+    pub output_generation: Option<f64>,
+}
+
 /// Bind the input/output of an execution to the input and output file of a testcase. It correctly
 /// chooses if using stdin/stdout or using normal files by looking at the value set in the `Task`.
 ///
@@ -88,6 +125,76 @@ impl TestcaseScoreAggregator {
     }
 }
 
+impl ToolTimeLimits {
+    /// The default limits of the tasks.
+    pub fn task_default() -> ToolTimeLimits {
+        ToolTimeLimits {
+            generation: Some(DEFAULT_GENERATION_TIME_LIMIT),
+            validation: Some(DEFAULT_VALIDATION_TIME_LIMIT),
+            output_generation: Some(DEFAULT_OUTPUT_GENERATION_TIME_LIMIT),
+        }
+    }
+
+    /// The same limit for all the executions.
+    pub fn all(limit: f64) -> ToolTimeLimits {
+        ToolTimeLimits {
+            generation: Some(limit),
+            validation: Some(limit),
+            output_generation: Some(limit),
+        }
+    }
+
+    /// Fill the limits that are not set with the ones of `other`.
+    pub fn or(self, other: ToolTimeLimits) -> ToolTimeLimits {
+        ToolTimeLimits {
+            generation: self.generation.or(other.generation),
+            validation: self.validation.or(other.validation),
+            output_generation: self.output_generation.or(other.output_generation),
+        }
+    }
+}
+
+/// Limit the time of an execution preparing a testcase, if the limit is set and it's not zero. The
+/// wall time has some margin for the executions that wait for the disk.
+pub(crate) fn apply_tool_time_limit(exec: &mut Execution, limit: Option<f64>) {
+    if let Some(limit) = limit.filter(|limit| *limit > 0.0) {
+        exec.limits_mut()
+            .cpu_time(limit)
+            .wall_time(limit * 2.0 + 1.0);
+    }
+}
+
+/// A help message for the executions preparing a testcase that exceeded their time limit, telling
+/// where the limit can be changed.
+pub(crate) fn tool_time_limit_help(status: &ExecutionStatus, key: &str) -> Option<String> {
+    match status {
+        ExecutionStatus::TimeLimitExceeded | ExecutionStatus::WallTimeLimitExceeded => {
+            Some(format!(
+            "The time limit can be increased with {} in task.yaml, or for a single testcase with \
+             a \"# @timeout: SECONDS\" line before it in gen/GEN",
+            key
+        ))
+        }
+        _ => None,
+    }
+}
+
+impl ScoreRounding {
+    /// Round a score to `precision` decimal digits.
+    pub fn round(&self, score: f64, precision: usize) -> f64 {
+        let factor = 10f64.powi(precision as i32);
+        // drop the floating point noise first, otherwise 0.30000000000000004 would be rounded up
+        // to 0.4 and 0.29999999999999999 down to 0.2.
+        let scaled = (score * factor * 1e6).round() / 1e6;
+        let rounded = match self {
+            ScoreRounding::Nearest => scaled.round(),
+            ScoreRounding::Down => scaled.floor(),
+            ScoreRounding::Up => scaled.ceil(),
+        };
+        rounded / factor
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use itertools::Itertools;
@@ -95,7 +202,9 @@ mod tests {
     use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::Arc;
 
-    use task_maker_dag::{ExecutionResourcesUsage, ExecutionResult, ExecutionStatus, File};
+    use task_maker_dag::{
+        BuiltinCommand, ExecutionCommand, ExecutionResult, ExecutionStatus, File, ProvidedFile,
+    };
     use task_maker_lang::GraderMap;
 
     use crate::ioi::IOITask;
@@ -121,11 +230,19 @@ mod tests {
             testcases: Default::default(),
             input_validator_generator: Default::default(),
             testcase_score_aggregator: TestcaseScoreAggregator::Min,
-            score_precision: 0,
+            score_precision: None,
+            score_rounding: ScoreRounding::Nearest,
             grader_map: Arc::new(GraderMap::new(Vec::<PathBuf>::new())),
             booklets: vec![],
             difficulty: None,
             syllabus_level: None,
+            attachment_packages: false,
+            container_image: None,
+            forbidden_constructs: vec![],
+            required_resources: Default::default(),
+            quick_testcases: None,
+            official_solution_override: None,
+            checker_messages: Default::default(),
             sanity_checks: Default::default(),
         }
     }
@@ -158,6 +275,17 @@ mod tests {
         assert_abs_diff_eq!(1.0, sum);
     }
 
+    #[test]
+    fn test_score_rounding() {
+        assert_abs_diff_eq!(ScoreRounding::Nearest.round(0.1 + 0.2, 1), 0.3);
+        assert_abs_diff_eq!(ScoreRounding::Up.round(0.1 + 0.2, 1), 0.3);
+        assert_abs_diff_eq!(ScoreRounding::Down.round(99.99999999999, 0), 100.0);
+        assert_abs_diff_eq!(ScoreRounding::Nearest.round(33.335, 2), 33.34);
+        assert_abs_diff_eq!(ScoreRounding::Down.round(33.339, 2), 33.33);
+        assert_abs_diff_eq!(ScoreRounding::Up.round(33.331, 2), 33.34);
+        assert_abs_diff_eq!(ScoreRounding::Nearest.round(2.0 / 3.0 * 10.0, 0), 7.0);
+    }
+
     #[test]
     fn test_input_generator_static() {
         let tmpdir = tempfile::TempDir::new().unwrap();
@@ -165,7 +293,7 @@ mod tests {
         std::fs::write(&path, "x").unwrap();
         let generator = InputGenerator::StaticFile(path);
         let (mut eval, _) = EvaluationData::new(tmpdir.path());
-        let out = generator.generate_and_bind(&mut eval, 0, 0).unwrap();
+        let out = generator.generate_and_bind(&mut eval, 0, 0, None).unwrap();
         assert!(eval.dag.data.provided_files.contains_key(&out));
         assert!(eval
             .dag
@@ -182,7 +310,7 @@ mod tests {
         let path = tmpdir.path().join("input.txt");
         let generator = InputGenerator::StaticFile(path.clone());
         let (mut eval, _) = EvaluationData::new(tmpdir.path());
-        let gen = generator.generate_and_bind(&mut eval, 0, 0);
+        let gen = generator.generate_and_bind(&mut eval, 0, 0, None);
         assert!(gen.is_err());
         let err = gen.unwrap_err().to_string();
         assert!(err.contains("COPY"));
@@ -197,7 +325,7 @@ mod tests {
         let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
         let generator = InputGenerator::Custom(Arc::new(source), vec![]);
         let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
-        let out = generator.generate_and_bind(&mut eval, 0, 0).unwrap();
+        let out = generator.generate_and_bind(&mut eval, 0, 0, None).unwrap();
         assert_eq!(eval.dag.data.provided_files.len(), 1);
         assert_eq!(eval.dag.data.execution_groups.len(), 1);
         let group = eval.dag.data.execution_groups.values().next().unwrap();
@@ -212,13 +340,49 @@ mod tests {
             .is_some());
     }
 
+    #[test]
+    fn test_input_generator_time_limit() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("gen.py");
+        std::fs::write(&path, "x").unwrap();
+        let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
+        let generator = InputGenerator::Custom(Arc::new(source), vec![]);
+        let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+        generator
+            .generate_and_bind(&mut eval, 0, 0, Some(5.0))
+            .unwrap();
+        generator
+            .generate_and_bind(&mut eval, 0, 1, Some(0.0))
+            .unwrap();
+        let limits = eval
+            .dag
+            .data
+            .execution_groups
+            .values()
+            .map(|group| group.executions[0].limits.cpu_time)
+            .sorted_by(|a, b| a.partial_cmp(b).unwrap())
+            .collect_vec();
+        assert_eq!(limits, vec![None, Some(5.0)]);
+    }
+
+    #[test]
+    fn test_tool_time_limits_or() {
+        let limits = ToolTimeLimits {
+            generation: Some(10.0),
+            ..Default::default()
+        };
+        let limits = limits.or(ToolTimeLimits::task_default());
+        assert_eq!(limits.generation, Some(10.0));
+        assert_eq!(limits.validation, Some(DEFAULT_VALIDATION_TIME_LIMIT));
+    }
+
     #[test]
     fn test_input_validator_assume_valid() {
         let validator = InputValidator::AssumeValid;
         let file = File::new("input");
         let (mut eval, _recv) = EvaluationData::new("");
         let out = validator
-            .validate_and_bind(&mut eval, 0, None, 0, file.uuid)
+            .validate_and_bind(&mut eval, 0, None, 0, file.uuid, None)
             .unwrap();
         assert_eq!(eval.dag.data.provided_files.len(), 0);
         assert_eq!(eval.dag.data.execution_groups.len(), 0);
@@ -235,7 +399,7 @@ mod tests {
         let file = File::new("input");
         let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
         let out = validator
-            .validate_and_bind(&mut eval, 0, None, 0, file.uuid)
+            .validate_and_bind(&mut eval, 0, None, 0, file.uuid, None)
             .unwrap();
         assert_eq!(eval.dag.data.provided_files.len(), 1);
         assert_eq!(eval.dag.data.execution_groups.len(), 1);
@@ -259,7 +423,7 @@ mod tests {
         let file = File::new("input");
         let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
         let out = validator
-            .validate_and_bind(&mut eval, 0, Some("name"), 0, file.uuid)
+            .validate_and_bind(&mut eval, 0, Some("name"), 0, file.uuid, None)
             .unwrap();
         assert_eq!(eval.dag.data.provided_files.len(), 1);
         assert_eq!(eval.dag.data.execution_groups.len(), 1);
@@ -284,7 +448,7 @@ mod tests {
         let task = make_task(tmpdir.path());
         let (mut eval, _) = EvaluationData::new(tmpdir.path());
         let out = generator
-            .generate_and_bind(&task, &mut eval, 0, 0, file.uuid, None)
+            .generate_and_bind(&task, &mut eval, 0, 0, file.uuid, None, None)
             .unwrap()
             .unwrap();
         assert!(eval.dag.data.provided_files.contains_key(&out));
@@ -305,7 +469,7 @@ mod tests {
         let file = File::new("input");
         let task = make_task(tmpdir.path());
         let (mut eval, _) = EvaluationData::new(tmpdir.path());
-        let gen = generator.generate_and_bind(&task, &mut eval, 0, 0, file.uuid, None);
+        let gen = generator.generate_and_bind(&task, &mut eval, 0, 0, file.uuid, None, None);
         assert!(gen.is_err());
         let err = gen.unwrap_err().to_string();
         assert!(err.contains("Static output file not found"));
@@ -324,7 +488,7 @@ mod tests {
         let task = make_task(tmpdir.path());
         let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
         let out = generator
-            .generate_and_bind(&task, &mut eval, 0, 0, file.uuid, Some(val.uuid))
+            .generate_and_bind(&task, &mut eval, 0, 0, file.uuid, Some(val.uuid), None)
             .unwrap()
             .unwrap();
         assert_eq!(eval.dag.data.provided_files.len(), 1);
@@ -343,6 +507,95 @@ mod tests {
             .is_some());
     }
 
+    #[test]
+    fn test_output_generator_official_solution_override() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let official = tmpdir.path().join("solution.py");
+        std::fs::write(&official, "official").unwrap();
+        let other = tmpdir.path().join("other.py");
+        std::fs::write(&other, "other").unwrap();
+        // generate the output with a solution, returning where the output is written and the keys
+        // of the provided files, on which the cache key of the generation depends
+        let generate = |path: &PathBuf, overridden: bool| {
+            let source = SourceFile::new(path, "", "", None, None::<PathBuf>).unwrap();
+            let generator = OutputGenerator::Custom(Arc::new(source), vec![]);
+            let mut task = make_task(tmpdir.path());
+            if overridden {
+                task.official_solution_override = Some(path.clone());
+            }
+            let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+            let out = generator
+                .generate_and_bind(&task, &mut eval, 0, 0, File::new("input").uuid, None, None)
+                .unwrap()
+                .unwrap();
+            let write_to = eval.dag.file_callbacks().get(&out).and_then(|callbacks| {
+                callbacks
+                    .write_to
+                    .as_ref()
+                    .map(|write_to| write_to.dest.clone())
+            });
+            let keys = eval
+                .dag
+                .data
+                .provided_files
+                .values()
+                .map(|file| match file {
+                    ProvidedFile::LocalFile { key, .. } | ProvidedFile::Content { key, .. } => {
+                        key.clone()
+                    }
+                })
+                .collect_vec();
+            (write_to, keys)
+        };
+
+        let (write_to, official_keys) = generate(&official, false);
+        assert_eq!(write_to, Some(tmpdir.path().join("output/output0.txt")));
+        let (write_to, other_keys) = generate(&other, true);
+        assert_eq!(write_to, None);
+        assert_eq!(official_keys.len(), 1);
+        assert_eq!(other_keys.len(), 1);
+        assert_ne!(official_keys, other_keys);
+    }
+
+    #[test]
+    fn test_output_generator_two_steps() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("sol.py");
+        std::fs::write(&path, "x").unwrap();
+        let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
+        let generator = OutputGenerator::TwoSteps(Arc::new(source));
+        let file = File::new("input");
+        let val = File::new("validation");
+        let task = make_task(tmpdir.path());
+        let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+        let out = generator
+            .generate_and_bind(&task, &mut eval, 0, 0, file.uuid, Some(val.uuid), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(eval.dag.data.execution_groups.len(), 2);
+        let execs: Vec<_> = eval
+            .dag
+            .data
+            .execution_groups
+            .values()
+            .map(|group| &group.executions[0])
+            .collect();
+        let second = execs
+            .iter()
+            .find(|exec| exec.stdout.as_ref().unwrap().uuid == out)
+            .unwrap();
+        let first = execs.iter().find(|exec| exec.uuid != second.uuid).unwrap();
+        assert_eq!(first.args.last().unwrap(), "0");
+        assert_eq!(second.args.last().unwrap(), "1");
+        assert!(first.dependencies().contains(&file.uuid));
+        assert!(first.dependencies().contains(&val.uuid));
+        let first_output = first.stdout.as_ref().unwrap().uuid;
+        assert_eq!(second.stdin, Some(first_output));
+        assert!(!second.dependencies().contains(&file.uuid));
+        assert_eq!(first.tag, Some(Tag::Generation.into()));
+        assert_eq!(second.tag, Some(Tag::Generation.into()));
+    }
+
     #[test]
     fn test_checker_whitediff() {
         let checker = Checker::WhiteDiff;
@@ -359,16 +612,59 @@ mod tests {
         assert_eq!(eval.dag.data.execution_groups.len(), 1);
         let group = eval.dag.data.execution_groups.values().next().unwrap();
         assert_eq!(group.tag().as_ref().unwrap(), &Tag::Checking.into());
-        assert!(group.executions[0]
-            .args
-            .contains(&"--ignore-blank-lines".into()));
-        assert!(group.executions[0]
-            .args
-            .contains(&"--ignore-space-change".into()));
+        assert_eq!(
+            group.executions[0].command,
+            ExecutionCommand::builtin(BuiltinCommand::WhiteDiff)
+        );
+        assert_eq!(group.executions[0].args, vec!["correct", "test"]);
         assert!(group.executions[0].dependencies().contains(&output));
         assert!(group.executions[0].dependencies().contains(&test));
     }
 
+    #[test]
+    fn test_checker_keep_artifacts() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let checker = Checker::WhiteDiff;
+        let (mut eval, _recv) = EvaluationData::new("");
+        eval.keep_artifacts = Some(tmpdir.path().into());
+        let input = File::new("input").uuid;
+        let output = File::new("output").uuid;
+        let test = File::new("test").uuid;
+        checker
+            .check_and_bind(
+                &mut eval,
+                1,
+                2,
+                "sol/sol.cpp",
+                input,
+                output,
+                test,
+                |_, _| Ok(()),
+            )
+            .unwrap();
+        let dir = tmpdir.path().join("sol/sol.cpp/subtask1/testcase2");
+        let group = eval.dag.data.execution_groups.values().next().unwrap();
+        let stderr = group.executions[0].stderr.as_ref().unwrap().uuid;
+        assert_eq!(
+            eval.dag.file_callbacks()[&stderr].copy_to,
+            vec![dir.join("checker.stderr")]
+        );
+
+        let callbacks = eval.dag.execution_callbacks().drain().next().unwrap().1;
+        callbacks.on_done.into_iter().for_each(|cb| {
+            cb(ExecutionResult {
+                status: ExecutionStatus::Success,
+                was_cached: true,
+                ..Default::default()
+            })
+            .unwrap();
+        });
+        let meta = std::fs::read_to_string(dir.join("checker.json")).unwrap();
+        let meta: serde_json::Value = serde_json::from_str(&meta).unwrap();
+        assert_eq!(meta["status"], "Success");
+        assert_eq!(meta["was_cached"], true);
+    }
+
     #[test]
     fn test_checker_whitediff_correct() {
         let checker = Checker::WhiteDiff;
@@ -391,16 +687,7 @@ mod tests {
         callbacks.on_done.into_iter().for_each(|cb| {
             cb(ExecutionResult {
                 status: ExecutionStatus::Success,
-                was_killed: false,
-                was_cached: false,
-                resources: ExecutionResourcesUsage {
-                    cpu_time: 0.0,
-                    sys_time: 0.0,
-                    wall_time: 0.0,
-                    memory: 0,
-                },
-                stdout: None,
-                stderr: None,
+                ..Default::default()
             })
             .unwrap();
         });
@@ -429,16 +716,7 @@ mod tests {
         callbacks.on_done.into_iter().for_each(|cb| {
             cb(ExecutionResult {
                 status: ExecutionStatus::ReturnCode(1),
-                was_killed: false,
-                was_cached: false,
-                resources: ExecutionResourcesUsage {
-                    cpu_time: 0.0,
-                    sys_time: 0.0,
-                    wall_time: 0.0,
-                    memory: 0,
-                },
-                stdout: None,
-                stderr: None,
+                ..Default::default()
             })
             .unwrap();
         });
@@ -497,11 +775,9 @@ mod tests {
         let on_done = eval.dag.execution_callbacks().get_mut(&exec).unwrap();
         on_done.on_done.remove(0)(ExecutionResult {
             status: ExecutionStatus::Success,
-            was_killed: false,
-            was_cached: false,
-            resources: Default::default(),
             stdout: Some("1.0\n\n".into()),
             stderr: Some("Ok!\n\n".into()),
+            ..Default::default()
         })
         .unwrap();
 
@@ -535,11 +811,9 @@ mod tests {
         let on_done = eval.dag.execution_callbacks().get_mut(&exec).unwrap();
         on_done.on_done.remove(0)(ExecutionResult {
             status: ExecutionStatus::Success,
-            was_killed: false,
-            was_cached: false,
-            resources: Default::default(),
             stdout: Some("0.0\n\n".into()),
             stderr: Some("Ko!\n\n".into()),
+            ..Default::default()
         })
         .unwrap();
 
@@ -566,11 +840,9 @@ mod tests {
         let on_done = eval.dag.execution_callbacks().get_mut(&exec).unwrap();
         on_done.on_done.remove(0)(ExecutionResult {
             status: ExecutionStatus::Success,
-            was_killed: false,
-            was_cached: false,
-            resources: Default::default(),
             stdout: Some(":<\n\n".into()),
             stderr: Some("Ko!\n\n".into()),
+            ..Default::default()
         })
         .unwrap();
         drop(eval);
@@ -588,4 +860,70 @@ mod tests {
             .any(|m| m.contains("Checker returned an invalid score"));
         assert!(diagnostics);
     }
+
+    #[test]
+    fn test_checker_custom_out_of_range_score() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("check.py");
+        std::fs::write(&path, "x").unwrap();
+        let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
+        let checker = Checker::Custom(Arc::new(source));
+        let (mut eval, recv) = EvaluationData::new(tmpdir.path());
+        let input = File::new("input").uuid;
+        let output = File::new("output").uuid;
+        let test = File::new("test").uuid;
+        let cb_called = Arc::new(AtomicBool::new(false));
+        let cb_called2 = cb_called.clone();
+        let cb = move |score, _| {
+            assert_abs_diff_eq!(score, 1.0);
+            cb_called2.store(true, Ordering::Relaxed);
+            Ok(())
+        };
+        checker
+            .check_and_bind(&mut eval, 0, 0, "sol", input, output, test, cb)
+            .unwrap();
+        let group = eval.dag.data.execution_groups.values().next().unwrap();
+        let exec = group.executions[0].uuid;
+        let on_done = eval.dag.execution_callbacks().get_mut(&exec).unwrap();
+        on_done.on_done.remove(0)(ExecutionResult {
+            status: ExecutionStatus::Success,
+            stdout: Some("1.5\n".into()),
+            stderr: Some("Ok!\n".into()),
+            ..Default::default()
+        })
+        .unwrap();
+        drop(eval);
+
+        assert!(cb_called.load(Ordering::Relaxed));
+        let diagnostics = recv
+            .into_iter()
+            .flat_map(|m| match m {
+                UIMessage::Diagnostic { diagnostic } => Some(diagnostic),
+                _ => None,
+            })
+            .collect_vec();
+        assert!(diagnostics.iter().any(|d| d
+            .message()
+            .contains("Checker returned an out-of-range score")));
+    }
+
+    #[test]
+    fn test_checker_score_policy() {
+        let (sender, recv) = crate::ui::UIMessageSender::new();
+        let sender = std::sync::Mutex::new(sender);
+        let parse = |policy: CheckerScorePolicy, output: &str| {
+            policy
+                .parse_score(output.into(), Some(0), "checker".into(), &sender)
+                .unwrap()
+        };
+        assert_eq!(parse(CheckerScorePolicy::Fail, "0.5\n"), Some(0.5));
+        assert_eq!(parse(CheckerScorePolicy::Clamp, "1.5\n"), Some(1.0));
+        assert_eq!(parse(CheckerScorePolicy::Clamp, "NaN\n"), Some(0.0));
+        assert_eq!(parse(CheckerScorePolicy::Fail, "1.5\n"), None);
+        assert_eq!(parse(CheckerScorePolicy::Clamp, "foo\n"), None);
+        assert_eq!(parse(CheckerScorePolicy::Fail, "foo\n"), None);
+        drop(sender);
+        // a diagnostic for each invalid score
+        assert_eq!(recv.into_iter().count(), 5);
+    }
 }
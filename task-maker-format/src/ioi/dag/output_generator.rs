@@ -5,10 +5,13 @@ use anyhow::{bail, Context, Error};
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
-use task_maker_dag::{Execution, File, FileUuid, Priority};
-use task_maker_diagnostics::Diagnostic;
+use task_maker_dag::{Execution, File, FileUuid, Priority, TrustLevel};
+use task_maker_diagnostics::{Diagnostic, DiagnosticCode};
 
-use crate::ioi::{IOITask, SubtaskId, TestcaseId, GENERATION_PRIORITY, STDERR_CONTENT_LENGTH};
+use crate::ioi::{
+    apply_tool_time_limit, execute_steps, tool_time_limit_help, IOITask, SubtaskId, TestcaseId,
+    GENERATION_PRIORITY, STDERR_CONTENT_LENGTH,
+};
 use crate::ui::UIMessage;
 use crate::{bind_exec_callbacks, bind_exec_io, UISender};
 use crate::{EvaluationData, SourceFile, Tag};
@@ -24,12 +27,15 @@ pub enum OutputGenerator {
     /// Use a custom command to generate the output file. The task specification for input/output
     /// files are used.
     Custom(Arc<SourceFile>, Vec<String>),
+    /// Run the official solution of a TwoSteps task in both its steps, the output of the second
+    /// step is the output file.
+    TwoSteps(Arc<SourceFile>),
 }
 
 impl OutputGenerator {
-    /// Build the execution for the generation of the output file. Return the handle to the output
-    /// file and the `Execution` if any. The execution does not send UI messages yet and it's not
-    /// added to the DAG.
+    /// Build the executions for the generation of the output file. Return the handle to the output
+    /// file and the executions to run, in order. The executions do not send UI messages yet and
+    /// they are not added to the DAG.
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn generate(
         &self,
@@ -40,17 +46,20 @@ impl OutputGenerator {
         testcase_id: TestcaseId,
         input: FileUuid,
         validation_handle: Option<FileUuid>,
-    ) -> Result<(Option<FileUuid>, Option<Execution>), Error> {
+    ) -> Result<(Option<FileUuid>, Vec<Execution>), Error> {
         match self {
             OutputGenerator::NotAvailable => {
                 let file = File::new("Empty file");
                 let uuid = file.uuid;
                 eval.dag.provide_content(file, vec![]);
-                Ok((Some(uuid), None))
+                Ok((Some(uuid), vec![]))
             }
             OutputGenerator::StaticFile(path) => {
                 if !path.exists() {
-                    bail!("Static output file not found: {:?}", path);
+                    bail!(
+                        Diagnostic::error(format!("Static output file not found: {:?}", path))
+                            .with_code(DiagnosticCode::MissingFile)
+                    );
                 }
                 let file = File::new(format!(
                     "Static output file of testcase {}, subtask {} from {:?}",
@@ -63,7 +72,7 @@ impl OutputGenerator {
                         path.display()
                     )
                 })?;
-                Ok((Some(uuid), None))
+                Ok((Some(uuid), vec![]))
             }
             OutputGenerator::Custom(source_file, args) => {
                 let mut exec = source_file
@@ -71,14 +80,36 @@ impl OutputGenerator {
                     .context("Failed to execute output generator source file")?;
                 exec.tag(Tag::Generation.into());
                 exec.priority(GENERATION_PRIORITY - testcase_id as Priority);
+                exec.trust(TrustLevel::Trusted);
                 let output = bind_exec_io!(exec, task, input, validation_handle);
-                Ok((Some(output.uuid), Some(exec)))
+                Ok((Some(output.uuid), vec![exec]))
+            }
+            OutputGenerator::TwoSteps(source_file) => {
+                let (first, second, output) = execute_steps(
+                    task,
+                    eval,
+                    &description,
+                    source_file,
+                    input,
+                    validation_handle,
+                )
+                .context("Failed to execute output generator source file")?;
+                let mut execs = vec![first, second];
+                for exec in &mut execs {
+                    exec.tag(Tag::Generation.into());
+                    exec.priority(GENERATION_PRIORITY - testcase_id as Priority);
+                    exec.trust(TrustLevel::Trusted);
+                }
+                Ok((Some(output), execs))
             }
         }
     }
 
     /// Add the generation of the output file to the DAG and the callbacks to the UI, returning the
-    /// handle to the output file.
+    /// handle to the output file. The official solution is limited to `time_limit` seconds, if set.
+    ///
+    /// The output file is written to `output/`, unless it's generated by the solution passed with
+    /// `--official-solution`.
     pub(crate) fn generate_and_bind(
         &self,
         task: &IOITask,
@@ -87,8 +118,9 @@ impl OutputGenerator {
         testcase_id: TestcaseId,
         input: FileUuid,
         validation_handle: Option<FileUuid>,
+        time_limit: Option<f64>,
     ) -> Result<Option<FileUuid>, Error> {
-        let (output, sol) = self.generate(
+        let (output, sols) = self.generate(
             task,
             eval,
             format!(
@@ -100,7 +132,8 @@ impl OutputGenerator {
             input,
             validation_handle,
         )?;
-        if let Some(mut sol) = sol {
+        for mut sol in sols {
+            apply_tool_time_limit(&mut sol, time_limit);
             sol.capture_stderr(STDERR_CONTENT_LENGTH);
             bind_exec_callbacks!(eval, sol.uuid, |status| UIMessage::IOISolution {
                 subtask: subtask_id,
@@ -111,7 +144,13 @@ impl OutputGenerator {
             eval.dag.on_execution_done(&sol.uuid, move |result| {
                 if !result.status.is_success() {
                     let mut diagnostic =
-                        Diagnostic::error(format!("Failed to generate output {}", testcase_id));
+                        Diagnostic::error(format!("Failed to generate output {}", testcase_id))
+                            .with_code(DiagnosticCode::OutputGenerationFailed);
+                    if let Some(help) =
+                        tool_time_limit_help(&result.status, "output_generation_time_limit")
+                    {
+                        diagnostic = diagnostic.with_help(help);
+                    }
                     if let Some(stderr) = result.stderr {
                         diagnostic = diagnostic.with_help_attachment(stderr);
                     }
@@ -121,7 +160,9 @@ impl OutputGenerator {
             });
             eval.dag.add_execution(sol);
         }
-        if let Some(output) = output {
+        // the outputs of the solution passed with --official-solution would replace the ones of the
+        // task, which may even be static
+        if let (Some(output), None) = (output, &task.official_solution_override) {
             eval.dag.write_file_to(
                 output,
                 task.path
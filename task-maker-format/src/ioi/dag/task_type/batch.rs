@@ -1,17 +1,17 @@
-use std::sync::{Arc, Mutex};
-
 use anyhow::{anyhow, Context, Error};
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
 use task_maker_dag::{ExecutionStatus, FileUuid, Priority};
 
+use crate::ioi::seed_matrix::SeedRun;
 use crate::ioi::{
-    Checker, IOITask, OutputGenerator, ScoreManager, SubtaskId, TestcaseId, EVALUATION_PRIORITY,
+    Artifacts, Checker, OutputDeterminism, OutputGenerator, Profiler, Sanitizer, SeedMatrix,
+    TestcaseEvaluation, EVALUATION_PRIORITY, SEED_ENV_VAR,
 };
 use crate::ui::UIMessage;
 use crate::{bind_exec_callbacks, bind_exec_io};
-use crate::{EvaluationData, SourceFile, Tag};
+use crate::{EvaluationData, Tag};
 
 /// The internal data of a task of type `Batch`.
 #[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
@@ -24,34 +24,81 @@ pub struct BatchTypeData {
 }
 
 /// Evaluate a solution in a task of Batch type.
-#[allow(clippy::too_many_arguments)]
-pub fn evaluate(
-    task: &IOITask,
+pub(crate) fn evaluate(
+    eval: &mut EvaluationData,
+    testcase: TestcaseEvaluation,
+    data: &BatchTypeData,
+) -> Result<(), Error> {
+    let correct_output = testcase
+        .correct_output
+        .ok_or_else(|| anyhow!("Missing official solution"))?;
+    let runs = eval
+        .seed_matrix
+        .as_ref()
+        .map(|seed_matrix| seed_matrix.lock().unwrap().runs());
+    for run in 0..runs.unwrap_or(1) {
+        evaluate_run(eval, &testcase, correct_output, data, run)?;
+    }
+    Sanitizer::evaluate(
+        eval,
+        testcase.task,
+        testcase.subtask_id,
+        testcase.testcase_id,
+        testcase.source_file,
+        testcase.input,
+        testcase.validation_handle,
+    )?;
+    Profiler::evaluate(
+        eval,
+        testcase.task,
+        testcase.testcase_id,
+        testcase.source_file,
+        testcase.input,
+        testcase.validation_handle,
+    )?;
+    Ok(())
+}
+
+/// Evaluate a solution on a testcase once. When the solutions are evaluated with more seeds, this
+/// is the run with index `run`, and its score is aggregated with the ones of the other runs.
+fn evaluate_run(
     eval: &mut EvaluationData,
-    subtask_id: SubtaskId,
-    testcase_id: TestcaseId,
-    source_file: &SourceFile,
-    input: FileUuid,
-    validation_handle: Option<FileUuid>,
-    correct_output: Option<FileUuid>,
-    score_manager: Arc<Mutex<ScoreManager>>,
+    testcase: &TestcaseEvaluation,
+    correct_output: FileUuid,
     data: &BatchTypeData,
+    run: usize,
 ) -> Result<(), Error> {
-    let correct_output = correct_output.ok_or_else(|| anyhow!("Missing official solution"))?;
+    let &TestcaseEvaluation {
+        task,
+        subtask_id,
+        testcase_id,
+        source_file,
+        input,
+        validation_handle,
+        ..
+    } = testcase;
+    let score_manager = testcase.score_manager.clone();
+    let seed_matrix = eval.seed_matrix.clone();
+    let num_runs = seed_matrix
+        .as_ref()
+        .map(|seed_matrix| seed_matrix.lock().unwrap().runs());
+    let mut description = format!(
+        "Evaluation of {} on testcase {}, subtask {}",
+        source_file.name(),
+        testcase_id,
+        subtask_id
+    );
+    if num_runs.is_some() {
+        description += &format!(", seed {}", SeedMatrix::seed(run));
+    }
     let mut exec = source_file
-        .execute(
-            eval,
-            format!(
-                "Evaluation of {} on testcase {}, subtask {}",
-                source_file.name(),
-                testcase_id,
-                subtask_id
-            ),
-            Vec::<String>::new(),
-        )
+        .execute(eval, description, Vec::<String>::new())
         .context("Failed to execute solution source file")?;
     exec.tag(Tag::Evaluation.into());
     exec.priority(EVALUATION_PRIORITY - testcase_id as Priority);
+    if num_runs.is_some() {
+        exec.env(SEED_ENV_VAR, SeedMatrix::seed(run).to_string());
+    }
     let output = bind_exec_io!(exec, task, input, validation_handle);
     let path = source_file.path.clone();
     let limits = exec.limits_mut();
@@ -70,27 +117,84 @@ pub fn evaluate(
             testcase: testcase_id,
             solution,
             status,
-            part: 0,
-            num_parts: 1,
+            part: run,
+            num_parts: num_runs.unwrap_or(1),
         },
         path
     )?;
+    score_manager
+        .lock()
+        .unwrap()
+        .add_evaluation(testcase_id, exec.uuid);
+    let sender = eval.sender.clone();
+    let score_manager_skip = score_manager.clone();
+    eval.dag.on_execution_skip(&exec.uuid, move || {
+        score_manager_skip
+            .lock()
+            .unwrap()
+            .evaluation_skipped(subtask_id, testcase_id, sender)
+    });
     let sender = eval.sender.clone();
-    let score_manager_err = score_manager.clone();
+    let seed_run = SeedRun {
+        solution: source_file.path.clone(),
+        subtask: subtask_id,
+        testcase: testcase_id,
+        run,
+    };
+    let score_testcase = move |score: f64, message: String| match &seed_matrix {
+        Some(seed_matrix) => SeedMatrix::score(
+            seed_matrix,
+            seed_run,
+            score,
+            message,
+            &score_manager,
+            sender,
+        ),
+        None => {
+            score_manager
+                .lock()
+                .unwrap()
+                .score(subtask_id, testcase_id, score, message, sender)
+        }
+    };
+    let score_testcase_err = score_testcase.clone();
     eval.dag
         .on_execution_done(&exec.uuid, move |result| match result.status {
             ExecutionStatus::Success => Ok(()),
-            _ => score_manager_err.lock().unwrap().score(
-                subtask_id,
-                testcase_id,
-                0.0,
-                format!("{:?}", result.status),
-                sender,
-            ),
+            _ => score_testcase_err(0.0, format!("{:?}", result.status)),
         });
+    if let Some(artifacts) = Artifacts::new(eval, &source_file.path, subtask_id, testcase_id) {
+        let suffix = match num_runs {
+            Some(_) => format!(".seed{}", SeedMatrix::seed(run)),
+            None => String::new(),
+        };
+        if run == 0 {
+            artifacts.file(eval, input, "input.txt");
+            artifacts.file(eval, correct_output, "correct_output.txt");
+        }
+        artifacts.file(eval, output.uuid, &format!("output{}.txt", suffix));
+        artifacts.execution(eval, &mut exec, &format!("evaluation{}", suffix));
+    }
+    // the outputs of the other runs would be compared with the ones of the first run, and with
+    // the white diff the accepted outputs are already the expected ones
+    let output_determinism = eval
+        .output_determinism
+        .clone()
+        .filter(|_| run == 0 && !matches!(data.checker, Checker::WhiteDiff));
     eval.dag.add_execution(exec);
 
-    let sender = eval.sender.clone();
+    if let Some(output_determinism) = &output_determinism {
+        OutputDeterminism::bind(
+            output_determinism.clone(),
+            eval,
+            testcase_id,
+            source_file.path.clone(),
+            input,
+            correct_output,
+            output.uuid,
+        )?;
+    }
+    let solution_path = source_file.path.clone();
     data.checker.check_and_bind(
         eval,
         subtask_id,
@@ -100,10 +204,13 @@ pub fn evaluate(
         correct_output,
         output.uuid,
         move |score, message| {
-            score_manager
-                .lock()
-                .unwrap()
-                .score(subtask_id, testcase_id, score, message, sender)
+            if let Some(output_determinism) = output_determinism {
+                output_determinism
+                    .lock()
+                    .unwrap()
+                    .checked(testcase_id, solution_path, score);
+            }
+            score_testcase(score, message)
         },
     )?;
     Ok(())
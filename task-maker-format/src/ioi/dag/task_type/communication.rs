@@ -4,9 +4,12 @@ use anyhow::{anyhow, Context, Error};
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
-use task_maker_dag::{ExecutionGroup, FileUuid, Priority};
+use task_maker_dag::{ExecutionGroup, Priority, TrustLevel};
 
-use crate::ioi::{Checker, IOITask, ScoreManager, SubtaskId, TestcaseId, EVALUATION_PRIORITY};
+use crate::ioi::{
+    Artifacts, Checker, ScoreManager, SubtaskId, TestcaseEvaluation, TestcaseId,
+    EVALUATION_PRIORITY,
+};
 use crate::ui::{UIMessage, UIMessageSender};
 use crate::{bind_exec_callbacks, bind_exec_io};
 use crate::{EvaluationData, SourceFile, Tag};
@@ -63,19 +66,21 @@ struct ScoreSender {
 }
 
 /// Evaluate a solution in a task of Batch type.
-#[allow(clippy::too_many_arguments)]
-pub fn evaluate(
-    task: &IOITask,
+pub(crate) fn evaluate(
     eval: &mut EvaluationData,
-    subtask_id: SubtaskId,
-    testcase_id: TestcaseId,
-    source_file: &SourceFile,
-    input: FileUuid,
-    validation_handle: Option<FileUuid>,
-    _correct_output: Option<FileUuid>,
-    score_manager: Arc<Mutex<ScoreManager>>,
+    testcase: TestcaseEvaluation,
     data: &CommunicationTypeData,
 ) -> Result<(), Error> {
+    let TestcaseEvaluation {
+        task,
+        subtask_id,
+        testcase_id,
+        source_file,
+        input,
+        validation_handle,
+        score_manager,
+        ..
+    } = testcase;
     let mut group = ExecutionGroup::new(format!(
         "Evaluation of {} on testcase {}, subtask {}",
         source_file.name(),
@@ -103,6 +108,7 @@ pub fn evaluate(
     }
 
     let path = source_file.path.clone();
+    let artifacts = Artifacts::new(eval, &path, subtask_id, testcase_id);
     let num_processes = data.num_processes as usize;
     let score_sender = ScoreSender::new(
         subtask_id,
@@ -173,6 +179,14 @@ pub fn evaluate(
             }
             Ok(())
         });
+        if let Some(artifacts) = &artifacts {
+            let name = if num_processes > 1 {
+                format!("evaluation{}", process_index + 1)
+            } else {
+                "evaluation".to_string()
+            };
+            artifacts.execution(eval, &mut sol_exec, &name);
+        }
         group.add_execution(sol_exec);
     }
 
@@ -181,24 +195,22 @@ pub fn evaluate(
         args.push(&fifo_sol2man[process_index]);
         args.push(&fifo_man2sol[process_index]);
     }
+    let description = format!(
+        "Manager of {} on testcase {}, subtask {}",
+        source_file.name(),
+        testcase_id,
+        subtask_id
+    );
     let mut manager_exec = data
         .manager
-        .execute(
-            eval,
-            format!(
-                "Manager of {} on testcase {}, subtask {}",
-                source_file.name(),
-                testcase_id,
-                subtask_id
-            ),
-            args,
-        )
+        .execute(eval, &description, args)
         .context("Failed to execute manager source file")?;
     manager_exec
         .tag(Tag::Evaluation.into())
         .priority(EVALUATION_PRIORITY - testcase_id as Priority)
         .capture_stdout(128)
-        .capture_stderr(1024);
+        .capture_stderr(1024)
+        .trust(TrustLevel::Trusted);
     bind_exec_io!(manager_exec, task, input, validation_handle);
     let limits = manager_exec.limits_mut();
     if let Some(time_limit) = task.time_limit {
@@ -210,6 +222,11 @@ pub fn evaluate(
     if let Some(memory_limit) = task.memory_limit {
         limits.memory(memory_limit * 1024); // MiB -> KiB
     }
+    if let Some(artifacts) = &artifacts {
+        artifacts.file(eval, input, "input.txt");
+        artifacts.file(eval, manager_exec.stdout(), "manager.stdout");
+        artifacts.execution(eval, &mut manager_exec, "manager");
+    }
     bind_exec_callbacks!(
         eval,
         manager_exec.uuid,
@@ -221,6 +238,8 @@ pub fn evaluate(
         },
         path
     )?;
+    let sender = eval.sender.clone();
+    let score_policy = eval.checker_score_policy;
     eval.dag
         .on_execution_done(&manager_exec.uuid, move |result| {
             if !result.status.is_success() {
@@ -233,11 +252,14 @@ pub fn evaluate(
             let stderr = result
                 .stderr
                 .ok_or_else(|| anyhow!("Checker stderr not captured"))?;
-            let score = String::from_utf8_lossy(&stdout);
-            let score: f64 = score.trim().parse().context("Invalid score from checker")?;
             let message = String::from_utf8_lossy(&stderr).trim().to_string();
             let message = Checker::translate_checker_message(message);
-            score_sender.send(score, message)?;
+            let score =
+                score_policy.parse_score(stdout, Some(testcase_id), description, &*sender)?;
+            // with an invalid score the testcase is not scored, like with the other checkers
+            if let Some(score) = score {
+                score_sender.send(score, message)?;
+            }
             Ok(())
         });
     group.add_execution(manager_exec);
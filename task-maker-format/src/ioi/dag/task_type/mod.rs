@@ -7,12 +7,36 @@ use typescript_definitions::TypeScriptify;
 pub use batch::BatchTypeData;
 pub use communication::{CommunicationTypeData, UserIo};
 use task_maker_dag::FileUuid;
+pub(crate) use two_steps::execute_steps;
+pub use two_steps::TwoStepsTypeData;
 
 use crate::ioi::{Checker, IOITask, ScoreManager, SubtaskId, TestcaseId};
 use crate::{EvaluationData, SourceFile};
 
 mod batch;
 mod communication;
+mod two_steps;
+
+/// The solution and the testcase of an evaluation, with the files it uses and the `ScoreManager`
+/// that receives its score.
+pub(crate) struct TestcaseEvaluation<'a> {
+    /// The task the solution is evaluated in.
+    pub task: &'a IOITask,
+    /// The subtask of the testcase.
+    pub subtask_id: SubtaskId,
+    /// The testcase the solution is evaluated on.
+    pub testcase_id: TestcaseId,
+    /// The solution to evaluate.
+    pub source_file: &'a SourceFile,
+    /// The input file of the testcase.
+    pub input: FileUuid,
+    /// The handle of the validation of the input file, if it's validated.
+    pub validation_handle: Option<FileUuid>,
+    /// The official output file of the testcase, if any.
+    pub correct_output: Option<FileUuid>,
+    /// The `ScoreManager` of the solution.
+    pub score_manager: Arc<Mutex<ScoreManager>>,
+}
 
 /// The type of the task. This changes the behavior of the solutions.
 #[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
@@ -24,6 +48,10 @@ pub enum TaskType {
     /// The solution is executed in parallel with a manager and communicate using FIFO pipes. There
     /// are only input files since the manager computes the score of the solution.
     Communication(CommunicationTypeData),
+    /// The solution is executed twice per testcase: in the first step it reads the input file, in
+    /// the second step it reads the output file of the first step. The output of the second step is
+    /// checked like in a batch task.
+    TwoSteps(TwoStepsTypeData),
     /// Not an actual task.
     None,
 }
@@ -31,44 +59,15 @@ pub enum TaskType {
 impl TaskType {
     /// Evaluate a solution on a testcase, eventually adding to the `ScoreManager` the result of the
     /// evaluation. This will add both the execution as well as the checking to the DAG.
-    #[allow(clippy::too_many_arguments)]
     pub(crate) fn evaluate(
         &self,
-        task: &IOITask,
         eval: &mut EvaluationData,
-        subtask_id: SubtaskId,
-        testcase_id: TestcaseId,
-        source_file: &SourceFile,
-        input: FileUuid,
-        validation_handle: Option<FileUuid>,
-        correct_output: Option<FileUuid>,
-        score_manager: Arc<Mutex<ScoreManager>>,
+        testcase: TestcaseEvaluation,
     ) -> Result<(), Error> {
         match self {
-            TaskType::Batch(data) => batch::evaluate(
-                task,
-                eval,
-                subtask_id,
-                testcase_id,
-                source_file,
-                input,
-                validation_handle,
-                correct_output,
-                score_manager,
-                data,
-            ),
-            TaskType::Communication(data) => communication::evaluate(
-                task,
-                eval,
-                subtask_id,
-                testcase_id,
-                source_file,
-                input,
-                validation_handle,
-                correct_output,
-                score_manager,
-                data,
-            ),
+            TaskType::Batch(data) => batch::evaluate(eval, testcase, data),
+            TaskType::Communication(data) => communication::evaluate(eval, testcase, data),
+            TaskType::TwoSteps(data) => two_steps::evaluate(eval, testcase, data),
             TaskType::None => Ok(()),
         }
     }
@@ -78,7 +77,8 @@ impl TaskType {
     /// For example this will force the compilation of the checker in a batch task.
     pub(crate) fn prepare_dag(&self, eval: &mut EvaluationData) -> Result<(), Error> {
         match self {
-            TaskType::Batch(batch) => match &batch.checker {
+            TaskType::Batch(BatchTypeData { checker, .. })
+            | TaskType::TwoSteps(TwoStepsTypeData { checker }) => match checker {
                 Checker::Custom(checker) => {
                     checker.prepare(eval)?;
                 }
@@ -0,0 +1,157 @@
+use anyhow::{anyhow, Context, Error};
+use serde::{Deserialize, Serialize};
+use typescript_definitions::TypeScriptify;
+
+use task_maker_dag::{Execution, ExecutionStatus, FileUuid, Priority};
+
+use crate::ioi::{Artifacts, Checker, IOITask, TestcaseEvaluation, EVALUATION_PRIORITY};
+use crate::ui::UIMessage;
+use crate::{bind_exec_callbacks, bind_exec_io};
+use crate::{EvaluationData, SourceFile, Tag};
+
+/// The argument passed to the solution in the first step.
+const FIRST_STEP_ARG: &str = "0";
+/// The argument passed to the solution in the second step.
+const SECOND_STEP_ARG: &str = "1";
+
+/// The internal data of a task of type `TwoSteps`.
+#[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
+pub struct TwoStepsTypeData {
+    /// The checker to use for the output of the second step.
+    pub checker: Checker,
+}
+
+/// Build the executions of the two steps of `source_file`, returning them with the output of the
+/// second step.
+///
+/// In the first step the solution is run with `0` as argument, reading the input file and writing
+/// the output file as specified by the task. In the second step the solution is run with `1` as
+/// argument, and its input file is the output file of the first step. The executions are not added
+/// to the DAG.
+pub(crate) fn execute_steps(
+    task: &IOITask,
+    eval: &mut EvaluationData,
+    description: &str,
+    source_file: &SourceFile,
+    input: FileUuid,
+    validation_handle: Option<FileUuid>,
+) -> Result<(Execution, Execution, FileUuid), Error> {
+    let mut first = source_file
+        .execute(
+            eval,
+            format!("{} (first step)", description),
+            vec![FIRST_STEP_ARG],
+        )
+        .context("Failed to execute the first step of the solution")?;
+    let first_output = bind_exec_io!(first, task, input, validation_handle);
+    let mut second = source_file
+        .execute(
+            eval,
+            format!("{} (second step)", description),
+            vec![SECOND_STEP_ARG],
+        )
+        .context("Failed to execute the second step of the solution")?;
+    let output = bind_exec_io!(second, task, first_output.uuid, None::<FileUuid>);
+    Ok((first, second, output.uuid))
+}
+
+/// Evaluate a solution in a task of TwoSteps type.
+pub(crate) fn evaluate(
+    eval: &mut EvaluationData,
+    testcase: TestcaseEvaluation,
+    data: &TwoStepsTypeData,
+) -> Result<(), Error> {
+    let TestcaseEvaluation {
+        task,
+        subtask_id,
+        testcase_id,
+        source_file,
+        input,
+        validation_handle,
+        correct_output,
+        score_manager,
+    } = testcase;
+    let correct_output = correct_output.ok_or_else(|| anyhow!("Missing official solution"))?;
+    let description = format!(
+        "Evaluation of {} on testcase {}, subtask {}",
+        source_file.name(),
+        testcase_id,
+        subtask_id
+    );
+    let (first, second, output) = execute_steps(
+        task,
+        eval,
+        &description,
+        source_file,
+        input,
+        validation_handle,
+    )?;
+    let path = source_file.path.clone();
+    let artifacts = Artifacts::new(eval, &path, subtask_id, testcase_id);
+    if let Some(artifacts) = &artifacts {
+        artifacts.file(eval, input, "input.txt");
+        artifacts.file(eval, correct_output, "correct_output.txt");
+        artifacts.file(eval, output, "output.txt");
+    }
+    for (step, mut exec) in [first, second].into_iter().enumerate() {
+        exec.tag(Tag::Evaluation.into());
+        exec.priority(EVALUATION_PRIORITY - testcase_id as Priority);
+        let limits = exec.limits_mut();
+        if let Some(time_limit) = task.time_limit {
+            limits.cpu_time(time_limit);
+            limits.wall_time(time_limit * 1.5 + 1.0); // some margin
+        }
+        if let Some(memory_limit) = task.memory_limit {
+            limits.memory(memory_limit * 1024); // MiB -> KiB
+        }
+        bind_exec_callbacks!(
+            eval,
+            exec.uuid,
+            |status, solution| UIMessage::IOIEvaluation {
+                subtask: subtask_id,
+                testcase: testcase_id,
+                solution,
+                status,
+                part: step,
+                num_parts: 2,
+            },
+            path
+        )?;
+        // when the first step fails the second one is skipped, so at most one of them scores
+        let sender = eval.sender.clone();
+        let score_manager = score_manager.clone();
+        eval.dag
+            .on_execution_done(&exec.uuid, move |result| match result.status {
+                ExecutionStatus::Success => Ok(()),
+                _ => score_manager.lock().unwrap().score(
+                    subtask_id,
+                    testcase_id,
+                    0.0,
+                    format!("{:?}", result.status),
+                    sender,
+                ),
+            });
+        if let Some(artifacts) = &artifacts {
+            artifacts.execution(eval, &mut exec, &format!("evaluation{}", step + 1));
+        }
+        eval.dag.add_execution(exec);
+    }
+
+    let sender = eval.sender.clone();
+    data.checker.check_and_bind(
+        eval,
+        subtask_id,
+        testcase_id,
+        source_file.path.clone(),
+        input,
+        correct_output,
+        output,
+        move |score, message| {
+            score_manager
+                .lock()
+                .unwrap()
+                .score(subtask_id, testcase_id, score, message, sender)
+        },
+    )?;
+    Ok(())
+}
@@ -161,6 +161,11 @@ impl FinishUI {
                         cwrite!(self, YELLOW, "Solution failed: {:?}", sol.status);
                     }
                 }
+                if let Some(info) = state.task.testcases.get(tc_num) {
+                    if !info.tags.is_empty() {
+                        print!(" [{}]", info.tags.join(", "));
+                    }
+                }
                 println!();
                 if gen_failed {
                     let stderr = testcase.generation.as_ref().and_then(|g| g.stderr.as_ref());
@@ -209,11 +214,34 @@ impl FinishUI {
             .expect("Invalid file name")
             .to_string_lossy();
         cwrite!(self, BOLD, "{}", name);
+        if let Some(complexity) = state
+            .solutions
+            .get(path)
+            .and_then(|s| s.complexity.as_ref())
+        {
+            print!(" [{}]", complexity);
+        }
+        if let Some(original) = state
+            .solutions
+            .get(path)
+            .and_then(|s| s.duplicate_of.as_ref())
+        {
+            let original = original.file_name().unwrap_or_default();
+            print!(" (same as {})", original.to_string_lossy());
+        }
+        if let Some(metrics) = state.solutions.get(path).map(|s| &s.metrics) {
+            if metrics.size > 0 {
+                print!(" ({} bytes, {} lines)", metrics.size, metrics.lines);
+            }
+        }
         print!(": ");
 
         let score = eval.score;
         let normalized_score = score.map(|s| s / max_score);
         self.print_score_frac(normalized_score, score, max_score, &state.task);
+        if state.task.quick_testcases.is_some() {
+            cwrite!(self, YELLOW, " (partial, --quick)");
+        }
         println!();
 
         let results = eval
@@ -296,6 +324,14 @@ impl FinishUI {
                 print!(" [???]")
             }
         }
+        let metrics = testcase.custom_metrics();
+        if !metrics.is_empty() {
+            let metrics = metrics
+                .iter()
+                .map(|(name, value)| format!("{}: {}", name, value))
+                .join(" | ");
+            print!(" [{}]", metrics);
+        }
         print!(" {}", testcase.status.message());
         let mut was_killed = false;
         let mut was_cached = true;
@@ -321,6 +357,34 @@ impl FinishUI {
             self.print_right(format!("[{}]", name));
         }
         println!();
+        if !testcase.seed_runs.is_empty() {
+            print!("     Seeds:");
+            for run in testcase.seed_runs.iter().flatten() {
+                print!(" {} ", run.seed);
+                let color = self.score_color(run.score);
+                cwrite!(
+                    self,
+                    color,
+                    "[{:.prec$}]",
+                    run.score,
+                    prec = score_precision
+                );
+                if run.selected {
+                    print!("*");
+                }
+            }
+            println!();
+        }
+        if state.config.verbose_resources {
+            for result in testcase.results.iter().flatten() {
+                if let Some(details) = FinishUIUtils::resource_details(&result.resources) {
+                    println!("     {}", details);
+                }
+            }
+        }
+        for result in testcase.results.iter().flatten() {
+            FinishUIUtils::new(&mut self.stream).print_sandbox_logs(result);
+        }
     }
 
     /// The number of significant digits to use for printing a score.
@@ -331,11 +395,11 @@ impl FinishUI {
             .map(|st| st.max_score)
             .sum::<f64>()
             .log10() as usize;
-        task.score_precision + task_max_score_digits
+        task.score_precision.unwrap_or_default() + task_max_score_digits
     }
 
     fn print_summary(&mut self, state: &UIState) {
-        let score_precision = state.task.score_precision;
+        let score_precision = state.task.score_precision.unwrap_or_default();
         let column_width = score_precision + 4;
         cwriteln!(self, BLUE, "Summary");
         let max_len = FinishUIUtils::get_max_len(&state.evaluations);
@@ -428,6 +492,13 @@ impl FinishUI {
                 }
                 cwrite!(self, color, "]");
             }
+            if let Some(complexity) = state
+                .solutions
+                .get(path)
+                .and_then(|s| s.complexity.as_ref())
+            {
+                print!("  {}", complexity);
+            }
             println!();
         }
         println!();
@@ -441,6 +512,7 @@ impl FinishUI {
         max_score: f64,
         task: &IOITask,
     ) {
+        let score_precision = task.score_precision.unwrap_or_default();
         if let (Some(normalized_score), Some(score)) = (normalized_score, score) {
             let color = self.score_color(normalized_score);
             cwrite!(
@@ -449,16 +521,16 @@ impl FinishUI {
                 "{:.prec$} / {:.prec$}",
                 score,
                 max_score,
-                prec = task.score_precision
+                prec = score_precision
             );
-        } else if task.score_precision == 0 {
+        } else if score_precision == 0 {
             print!("X / {:.0}", max_score,);
         } else {
             print!(
                 "X.{:X<prec$} / {:.prec$}",
                 "",
                 max_score,
-                prec = task.score_precision
+                prec = score_precision
             );
         }
     }
@@ -10,8 +10,10 @@ use std::sync::Arc;
 
 use anyhow::{anyhow, bail, ensure, Context, Error};
 use pest::Parser;
+use serde::{Deserialize, Serialize};
+use typescript_definitions::TypeScriptify;
 
-use task_maker_diagnostics::CodeSpan;
+use task_maker_diagnostics::{CodeSpan, Diagnostic, DiagnosticCode};
 
 use crate::ioi::format::italian_yaml::TaskInputEntry;
 use crate::ioi::{
@@ -48,8 +50,8 @@ struct Manager {
 
 /// Operand of a constraint. It is either a constant integer value or a symbolic variable to
 /// substitute.
-#[derive(Debug, PartialEq, Eq)]
-enum ConstraintOperand {
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TypeScriptify)]
+pub enum ConstraintOperand {
     /// This operand is a constant integer value.
     Constant(i64),
     /// This operand is a symbolic variable. It is stored the variable name, without the dollar
@@ -58,8 +60,8 @@ enum ConstraintOperand {
 }
 
 /// The operator of a constraint.
-#[derive(Debug, PartialEq, Eq)]
-enum ConstraintOperator {
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TypeScriptify)]
+pub enum ConstraintOperator {
     /// Operator `<`.
     Less,
     /// Operator `<=`.
@@ -77,12 +79,12 @@ enum ConstraintOperator {
 /// Note that the number of operands is one more than the operators.
 /// All the operators must be _in the same direction_: in the same constraint there cannot be both
 /// a _less_ operator and a _greater_ one.
-#[derive(Default)]
-struct Constraint {
+#[derive(Default, Clone, PartialEq, Eq, Serialize, Deserialize, TypeScriptify)]
+pub struct Constraint {
     /// List of the operands of the constraint.
-    operands: Vec<ConstraintOperand>,
+    pub operands: Vec<ConstraintOperand>,
     /// List of the operators of the contraint.
-    operators: Vec<ConstraintOperator>,
+    pub operators: Vec<ConstraintOperator>,
 }
 
 /// Temporary structure with the metadata of the parsing of the `cases.gen` file. The internal data
@@ -109,6 +111,9 @@ where
     constraints: Vec<Constraint>,
     /// The list of additional constraints for the current subtask.
     subtask_constraints: Vec<Vec<Constraint>>,
+    /// The name of the validator of each subtask. The arguments are computed after parsing the
+    /// whole file, when all the constraints of the subtask are known.
+    subtask_validators: Vec<Option<String>>,
     /// The list of all the generators found, indexed by generator name.
     generators: HashMap<String, Manager>,
     /// The list of all the validators found, indexed by validator name.
@@ -160,6 +165,7 @@ where
             result: vec![],
             constraints: vec![],
             subtask_constraints: vec![],
+            subtask_validators: vec![],
             generators: Default::default(),
             validators: Default::default(),
             default_generator: None,
@@ -174,6 +180,7 @@ where
         for line in file.into_inner() {
             match line.as_rule() {
                 parser::Rule::line => {
+                    let span = line.as_span();
                     let line = line
                         .into_inner()
                         .next()
@@ -184,10 +191,14 @@ where
                                 .into_inner()
                                 .next()
                                 .ok_or_else(|| anyhow!("Corrupted parser"))?;
-                            cases.parse_command(command)?;
+                            cases
+                                .parse_command(command)
+                                .map_err(|e| cases.with_span(e, span))?;
                         }
                         parser::Rule::testcase => {
-                            cases.parse_testcase(line.as_str(), cases.current_generator.clone())?;
+                            cases
+                                .parse_testcase(line.as_str(), cases.current_generator.clone())
+                                .map_err(|e| cases.with_span(e, span))?;
                         }
                         parser::Rule::comment => {}
                         parser::Rule::empty => {}
@@ -198,12 +209,68 @@ where
                 _ => unreachable!(),
             }
         }
+        cases.resolve_validators()?;
         Ok(cases)
     }
 
+    /// Set the validator of each subtask, with the arguments computed from the variables of the
+    /// subtask.
+    fn resolve_validators(&mut self) -> Result<(), Error> {
+        let mut validators = HashMap::new();
+        for entry in &self.result {
+            if let TaskInputEntry::Subtask(subtask) = entry {
+                let name = self.subtask_validators[subtask.id as usize].as_deref();
+                let validator = self
+                    .get_validator(name, &self.get_validator_variables(subtask))
+                    .map_err(|e| match (e.downcast::<Diagnostic>(), &subtask.span) {
+                        (Ok(diagnostic), Some(span)) => {
+                            diagnostic.with_code_span(span.clone()).into()
+                        }
+                        (Ok(diagnostic), None) => diagnostic.into(),
+                        (Err(e), _) => e,
+                    })?;
+                validators.insert(subtask.id, validator);
+            }
+        }
+        for entry in self.result.iter_mut() {
+            if let TaskInputEntry::Subtask(subtask) = entry {
+                subtask.input_validator = validators.remove(&subtask.id).unwrap();
+            }
+        }
+        Ok(())
+    }
+
+    /// Attach to the error, if it's a `Diagnostic`, the span of the line of `cases.gen` that caused
+    /// it.
+    fn with_span(&self, error: Error, span: pest::Span) -> Error {
+        match error.downcast::<Diagnostic>() {
+            Ok(diagnostic) => match CodeSpan::from_str(
+                &self.file_path,
+                &self.file_content,
+                span.start(),
+                span.end() - span.start(),
+            ) {
+                Ok(code_span) => diagnostic.with_code_span(code_span).into(),
+                Err(_) => diagnostic.into(),
+            },
+            Err(error) => error,
+        }
+    }
+
     /// Return the list of `TaskInputEntry` from the `cases.gen` file.
     pub(crate) fn get_task_entries(&self) -> Vec<TaskInputEntry> {
-        self.result.clone()
+        let mut entries = self.result.clone();
+        for entry in entries.iter_mut() {
+            if let TaskInputEntry::Subtask(subtask) = entry {
+                subtask.constraints = self
+                    .constraints
+                    .iter()
+                    .chain(self.subtask_constraints[subtask.id as usize].iter())
+                    .cloned()
+                    .collect();
+            }
+        }
+        entries
     }
 
     /// Write an auto-generated version of the gen/GEN file inside the task directory.
@@ -275,6 +342,10 @@ where
                 self.parse_constraint(line)
                     .context("Failed to parse CONSTRAINT command")?;
             }
+            parser::Rule::CONSTRAINTS => {
+                self.parse_constraint(line)
+                    .context("Failed to parse CONSTRAINTS command")?;
+            }
             parser::Rule::SUBTASK => {
                 self.parse_subtask(line)
                     .context("Failed to parse SUBTASK command")?;
@@ -303,12 +374,16 @@ where
         current_generator: Option<String>,
     ) -> Result<(), Error> {
         if self.subtask_id == 0 {
-            bail!("Cannot add a testcase outside a subtask");
+            bail!(Diagnostic::error("Cannot add a testcase outside a subtask")
+                .with_code(DiagnosticCode::OutsideSubtask));
         }
         let current_generator = if let Some(gen) = current_generator {
             gen
         } else {
-            bail!("Cannot generate testcase: no default generator set");
+            bail!(
+                Diagnostic::error("Cannot generate testcase: no default generator set")
+                    .with_code(DiagnosticCode::NoGenerator)
+            );
         };
         let args = shell_words::split(line)
             .with_context(|| format!("Invalid command arguments for testcase '{}'", line))?;
@@ -356,12 +431,13 @@ where
         let path = line[1].as_str();
         let path = task_dir.join(path);
         if !path.exists() {
-            bail!(
+            bail!(Diagnostic::error(format!(
                 "Cannot add {} '{}': '{}' does not exists",
                 kind,
                 name,
                 path.display()
-            );
+            ))
+            .with_code(DiagnosticCode::MissingFile));
         }
         let source = SourceFile::new(
             &path,
@@ -375,7 +451,14 @@ where
             ),
         )
         .map(Arc::new)
-        .ok_or_else(|| anyhow!("Cannot use {} '{}': unknown language", kind, path.display()))?;
+        .ok_or_else(|| {
+            Diagnostic::error(format!(
+                "Cannot use {} '{}': unknown language",
+                kind,
+                path.display()
+            ))
+            .with_code(DiagnosticCode::UnknownLanguage)
+        })?;
         let args = shell_words::split(line[2].as_str())
             .with_context(|| format!("Invalid arguments of '{}'", name))?;
         if managers.contains_key(name) {
@@ -394,10 +477,16 @@ where
         if line.len() == 1 {
             let name = line[0].as_str();
             if self.subtask_id == 0 {
-                bail!("Cannot set the current generator to '{name}': outside a subtask",);
+                bail!(Diagnostic::error(format!(
+                    "Cannot set the current generator to '{name}': outside a subtask"
+                ))
+                .with_code(DiagnosticCode::OutsideSubtask));
             }
             if !self.generators.contains_key(name) {
-                bail!("Cannot set the current generator to '{name}': unknown generator",);
+                bail!(Diagnostic::error(format!(
+                    "Cannot set the current generator to '{name}': unknown generator"
+                ))
+                .with_code(DiagnosticCode::UndeclaredName));
             }
             self.current_generator = Some(name.to_string());
         } else {
@@ -417,15 +506,20 @@ where
         let line: Vec<_> = line.into_inner().collect();
         if line.len() == 1 {
             if self.subtask_id == 0 {
-                bail!("Cannot set the default validator outside a subtask");
+                bail!(
+                    Diagnostic::error("Cannot set the default validator outside a subtask")
+                        .with_code(DiagnosticCode::OutsideSubtask)
+                );
             }
-            let val = self
-                .get_validator(Some(line[0].as_str()), &self.get_auto_variables())
-                .context("Failed to get validator")?;
-            let Some(TaskInputEntry::Subtask(subtask)) = self.result.last_mut() else {
+            let name = line[0].as_str();
+            if !self.validators.contains_key(name) {
+                bail!(Diagnostic::error(format!("unknown validator '{}'", name))
+                    .with_code(DiagnosticCode::UndeclaredName));
+            }
+            let Some(TaskInputEntry::Subtask(_)) = self.result.last() else {
                 bail!("The validator must be set directly after a subtask");
             };
-            subtask.input_validator = val;
+            *self.subtask_validators.last_mut().unwrap() = Some(name.to_string());
         } else {
             CasesGen::<OutGen>::define_gen_val(
                 line,
@@ -439,8 +533,25 @@ where
         Ok(())
     }
 
-    /// Parse a `:CONSTRAINT` command.
+    /// Parse a `:CONSTRAINT` or a `:CONSTRAINTS` command, adding all the constraints in it.
     fn parse_constraint(&mut self, line: Pair) -> Result<(), Error> {
+        for constraint in line.into_inner() {
+            let constraint = self.parse_single_constraint(constraint)?;
+            // subtask_id = 0 means no subtask has been defined yet, so this constraint is global
+            if self.subtask_id == 0 {
+                self.constraints.push(constraint);
+            } else {
+                self.subtask_constraints
+                    .last_mut()
+                    .unwrap()
+                    .push(constraint);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse a single constraint of a `:CONSTRAINT` or `:CONSTRAINTS` command.
+    fn parse_single_constraint(&self, line: Pair) -> Result<Constraint, Error> {
         let line_str = line.as_str().to_string();
         let line: Vec<_> = line.into_inner().collect();
         let mut constraint = Constraint::default();
@@ -493,16 +604,7 @@ where
         if constraint.operands.len() < 2 {
             bail!("Malformed constraint: too few operands");
         }
-        // subtask_id = 0 means no subtask has been defined yet, so this constraint is global
-        if self.subtask_id == 0 {
-            self.constraints.push(constraint);
-        } else {
-            self.subtask_constraints
-                .last_mut()
-                .unwrap()
-                .push(constraint);
-        }
-        Ok(())
+        Ok(constraint)
     }
 
     /// Parse a `:SUBTASK` command.
@@ -511,6 +613,7 @@ where
         let line: Vec<_> = line.into_inner().collect();
         self.current_generator.clone_from(&self.default_generator);
         self.subtask_constraints.push(vec![]);
+        self.subtask_validators.push(self.default_validator.clone());
         let score = line[0].as_str();
         let score = f64::from_str(score).with_context(|| {
             format!(
@@ -548,10 +651,6 @@ where
                 )
                 .ok(),
                 is_default: false,
-                input_validator: self.get_validator(
-                    self.default_validator.as_deref(),
-                    &self.get_auto_variables(),
-                )?,
                 ..Default::default()
             },
         ));
@@ -576,7 +675,10 @@ where
     /// Parse a `:COPY` command.
     fn parse_copy(&mut self, line: Pair) -> Result<(), Error> {
         if self.subtask_id == 0 {
-            bail!("Cannot add a COPY testcase outside a subtask");
+            bail!(
+                Diagnostic::error("Cannot add a COPY testcase outside a subtask")
+                    .with_code(DiagnosticCode::OutsideSubtask)
+            );
         }
         let path = line
             .into_inner()
@@ -585,10 +687,11 @@ where
             .as_str();
         let path = self.task_dir.join(path);
         if !path.exists() {
-            bail!(
+            bail!(Diagnostic::error(format!(
                 "Cannot copy testcase from '{}': file not found",
                 path.display()
-            );
+            ))
+            .with_code(DiagnosticCode::MissingFile));
         }
         self.result.push(TaskInputEntry::Testcase(TestcaseInfo::new(
             self.testcase_id,
@@ -608,7 +711,8 @@ where
         match validator {
             Some(val) => {
                 let Some(validator) = self.validators.get(val) else {
-                    bail!("unknown validator '{}'", val);
+                    bail!(Diagnostic::error(format!("unknown validator '{}'", val))
+                        .with_code(DiagnosticCode::UndeclaredName));
                 };
                 let args = if validator.args.is_empty() {
                     vec![variables["INPUT"].clone(), variables["ST_NUM"].clone()]
@@ -619,7 +723,11 @@ where
                         // the `variables` map.
                         let arg = arg.strip_prefix('$').unwrap_or(arg);
                         let Some(value) = variables.get(arg) else {
-                            bail!("Unknown variable in validator arguments: ${}", arg);
+                            bail!(Diagnostic::error(format!(
+                                "Unknown variable in validator arguments: ${}",
+                                arg
+                            ))
+                            .with_code(DiagnosticCode::UndeclaredName));
                         };
                         args.push(value.clone());
                     }
@@ -634,13 +742,17 @@ where
     /// Parse a `:RUN` command.
     fn parse_run(&mut self, line: Pair) -> Result<(), Error> {
         if self.subtask_id == 0 {
-            bail!("Cannot add a testcase outside a subtask");
+            bail!(Diagnostic::error("Cannot add a testcase outside a subtask")
+                .with_code(DiagnosticCode::OutsideSubtask));
         }
         let line: Vec<_> = line.into_inner().collect();
         let name = line[0].as_str();
         let args = line[1].as_str();
         if !self.generators.contains_key(name) {
-            bail!("Generator '{}' not declared", name);
+            bail!(
+                Diagnostic::error(format!("Generator '{}' not declared", name))
+                    .with_code(DiagnosticCode::UndeclaredName)
+            );
         }
         self.parse_testcase(args, Some(name.into()))?;
         Ok(())
@@ -656,6 +768,31 @@ where
         vars
     }
 
+    /// Compute the variables accessible in the arguments of the validator of a subtask: the
+    /// automatic ones and the bounds of the variables derived from the constraints of the subtask,
+    /// `$MIN_var` and `$MAX_var`.
+    fn get_validator_variables(&self, subtask: &SubtaskInfo) -> HashMap<String, String> {
+        let mut vars = HashMap::new();
+        vars.insert("INPUT".to_string(), TM_VALIDATION_FILE_NAME.to_string());
+        vars.insert("ST_NUM".to_string(), subtask.id.to_string());
+        if let Some(name) = &subtask.name {
+            vars.insert("ST_NAME".to_string(), name.clone());
+        }
+        let constraints = self
+            .constraints
+            .iter()
+            .chain(self.subtask_constraints[subtask.id as usize].iter());
+        for (var, (min, max)) in variable_bounds(constraints) {
+            if let Some(min) = min {
+                vars.insert(format!("MIN_{}", var), min.to_string());
+            }
+            if let Some(max) = max {
+                vars.insert(format!("MAX_{}", var), max.to_string());
+            }
+        }
+        vars
+    }
+
     /// Obtain the automatic variables for the current testcase.
     fn get_auto_variables(&self) -> HashMap<String, String> {
         let mut vars = HashMap::new();
@@ -668,6 +805,45 @@ where
     }
 }
 
+/// The bounds of the variables implied by the constraints, comparing them with the constants next
+/// to them: `1 <= $N < 100` gives 1 as minimum and 99 as maximum of `$N`. When more constraints
+/// bound the same variable the tightest bounds are used. The bounds implied by the other variables
+/// (like `$N < $M`) are not derived.
+fn variable_bounds<'a, I>(constraints: I) -> HashMap<String, (Option<i64>, Option<i64>)>
+where
+    I: IntoIterator<Item = &'a Constraint>,
+{
+    let mut bounds: HashMap<String, (Option<i64>, Option<i64>)> = HashMap::new();
+    for constraint in constraints {
+        let pairs = constraint.operands.iter().zip(&constraint.operands[1..]);
+        for ((lhs, rhs), operator) in pairs.zip(&constraint.operators) {
+            // normalize to `$var operator constant`
+            let (var, operator, value) = match (lhs, rhs) {
+                (ConstraintOperand::Variable(var), ConstraintOperand::Constant(value)) => {
+                    (var, operator.clone(), *value)
+                }
+                (ConstraintOperand::Constant(value), ConstraintOperand::Variable(var)) => {
+                    (var, operator.flipped(), *value)
+                }
+                _ => continue,
+            };
+            let (min, max) = match operator {
+                ConstraintOperator::Less => (None, Some(value.saturating_sub(1))),
+                ConstraintOperator::LessEqual => (None, Some(value)),
+                ConstraintOperator::Greater => (Some(value.saturating_add(1)), None),
+                ConstraintOperator::GreaterEqual => (Some(value), None),
+                ConstraintOperator::Equal => (Some(value), Some(value)),
+            };
+            let entry = bounds.entry(var.clone()).or_default();
+            entry.0 = entry.0.max(min);
+            if let Some(max) = max {
+                entry.1 = Some(entry.1.map_or(max, |current| current.min(max)));
+            }
+        }
+    }
+    bounds
+}
+
 /// Check if the gen/GEN file is deletable, i.e. it exists and it is autogenerated.
 pub(crate) fn is_gen_gen_deletable(path: &Path) -> Result<bool, Error> {
     if !path.exists() {
@@ -697,6 +873,17 @@ impl ConstraintOperator {
             ConstraintOperator::GreaterEqual => lhs >= rhs,
         }
     }
+
+    /// The operator with the operands swapped: `a < b` is the same as `b > a`.
+    fn flipped(&self) -> ConstraintOperator {
+        match self {
+            ConstraintOperator::Less => ConstraintOperator::Greater,
+            ConstraintOperator::LessEqual => ConstraintOperator::GreaterEqual,
+            ConstraintOperator::Greater => ConstraintOperator::Less,
+            ConstraintOperator::GreaterEqual => ConstraintOperator::LessEqual,
+            ConstraintOperator::Equal => ConstraintOperator::Equal,
+        }
+    }
 }
 
 impl FromStr for ConstraintOperator {
@@ -774,7 +961,7 @@ impl Constraint {
     }
 }
 
-impl Debug for Constraint {
+impl Display for Constraint {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
         let mut constraint = self.operands[0].to_string();
         for (op, var) in self.operators.iter().zip(self.operands[1..].iter()) {
@@ -784,16 +971,23 @@ impl Debug for Constraint {
     }
 }
 
+impl Debug for Constraint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        Display::fmt(self, f)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::Path;
 
     use anyhow::Error;
     use speculoos::{assert_that, AssertionFailure, Spec};
+    use task_maker_diagnostics::{Diagnostic, DiagnosticCode};
     use tempfile::TempDir;
 
     use crate::ioi::format::italian_yaml::cases_gen::{
-        CasesGen, ConstraintOperand, ConstraintOperator,
+        variable_bounds, CasesGen, ConstraintOperand, ConstraintOperator,
     };
     use crate::ioi::format::italian_yaml::TaskInputEntry;
     use crate::ioi::{
@@ -1218,6 +1412,62 @@ mod tests {
         assert_that(&gen.unwrap_err()).has_error("Invalid integer constant");
     }
 
+    #[test]
+    fn test_subtask_constraints_in_entries() {
+        let gen = TestHelper::new()
+            .cases_gen(":CONSTRAINT 1 <= $N\n:SUBTASK 10\n:SUBTASK 90\n:CONSTRAINT $N <= 100")
+            .unwrap();
+        let constraints: Vec<_> = gen
+            .get_task_entries()
+            .into_iter()
+            .filter_map(|entry| match entry {
+                TaskInputEntry::Subtask(subtask) => Some(subtask.constraints),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            constraints[1][1].operands[1],
+            ConstraintOperand::Constant(100)
+        );
+        let constraints: Vec<Vec<_>> = constraints
+            .iter()
+            .map(|constraints| constraints.iter().map(ToString::to_string).collect())
+            .collect();
+        assert_eq!(
+            constraints,
+            vec![vec!["1 <= $N"], vec!["1 <= $N", "$N <= 100"]]
+        );
+    }
+
+    #[test]
+    fn test_add_constraints() {
+        let gen = TestHelper::new()
+            .cases_gen(":CONSTRAINTS 1 <= $N <= 100, $M < $N, $K = 3")
+            .unwrap();
+        let constraints: Vec<_> = gen
+            .constraints
+            .iter()
+            .map(|constr| format!("{:?}", constr))
+            .collect();
+        assert_eq!(constraints, vec!["1 <= $N <= 100", "$M < $N", "$K = 3"]);
+    }
+
+    #[test]
+    fn test_add_constraints_in_subtask() {
+        let gen = TestHelper::new()
+            .cases_gen(":CONSTRAINT 1 <= $N\n:SUBTASK 10\n:CONSTRAINTS $N <= 10, $M <= $N")
+            .unwrap();
+        assert_eq!(gen.constraints.len(), 1);
+        assert_eq!(gen.subtask_constraints[0].len(), 2);
+    }
+
+    #[test]
+    fn test_add_constraints_malformed() {
+        let gen = TestHelper::new().cases_gen(":CONSTRAINTS 1 <= $N, $K < $N > $M");
+        assert!(gen.is_err());
+        assert_that(&gen.unwrap_err()).has_error("inequality direction must be the same");
+    }
+
     /**********************
      * : SUBTASK
      *********************/
@@ -1340,6 +1590,63 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_subtask_validator_args_from_constraints() {
+        let gen = TestHelper::new()
+            .add_file("gen/generator.py")
+            .add_file("gen/val.py")
+            .cases_gen(
+                ":GEN default gen/generator.py N M\n\
+                :VAL default gen/val.py $INPUT $MIN_N $MAX_N $MAX_M\n\
+                :CONSTRAINT 1 <= $N <= 1000\n\
+                :CONSTRAINT $M < 100\n\
+                :SUBTASK 10\n\
+                :CONSTRAINT $N <= 10\n\
+                1 2\n\
+                :SUBTASK 90\n\
+                3 4",
+            )
+            .unwrap();
+        let args: Vec<_> = gen
+            .result
+            .iter()
+            .filter_map(|entry| match entry {
+                TaskInputEntry::Subtask(subtask) => match &subtask.input_validator {
+                    InputValidator::Custom(_, args) => Some(args.clone()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            args,
+            vec![
+                vec![TM_VALIDATION_FILE_NAME, "1", "10", "99"],
+                vec![TM_VALIDATION_FILE_NAME, "1", "1000", "99"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subtask_validator_args_unknown_bound() {
+        let gen = TestHelper::new()
+            .add_file("gen/val.py")
+            .cases_gen(":VAL default gen/val.py $MAX_N\n:CONSTRAINT $N > 3\n:SUBTASK 10");
+        assert!(gen.is_err());
+        assert_that(&gen.unwrap_err()).has_error("Unknown variable in validator arguments: $MAX_N");
+    }
+
+    #[test]
+    fn test_variable_bounds() {
+        let gen = TestHelper::new()
+            .cases_gen(":CONSTRAINTS 5 > $N >= 2, $K = 3, $N > $K, 0 <= $M, 4 <= $N")
+            .unwrap();
+        let bounds = variable_bounds(&gen.constraints);
+        assert_eq!(bounds["N"], (Some(4), Some(4)));
+        assert_eq!(bounds["K"], (Some(3), Some(3)));
+        assert_eq!(bounds["M"], (Some(0), None));
+    }
+
     /**********************
      * : COPY
      *********************/
@@ -1452,6 +1759,15 @@ mod tests {
         assert_that(&gen.unwrap_err()).has_error("Generator 'foo' not declared");
     }
 
+    #[test]
+    fn test_add_run_missing_gen_diagnostic() {
+        let gen = TestHelper::new().cases_gen(":SUBTASK 42\n:RUN foo 42 42");
+        let error = gen.unwrap_err();
+        let diagnostic = error.downcast_ref::<Diagnostic>().unwrap();
+        assert_eq!(diagnostic.code(), Some(DiagnosticCode::UndeclaredName));
+        assert!(diagnostic.to_string().contains(":RUN foo 42 42"));
+    }
+
     #[test]
     fn test_add_run_no_subtask() {
         let gen = TestHelper::new()
@@ -5,13 +5,13 @@ use std::sync::Arc;
 use anyhow::{anyhow, bail, ensure, Context, Error};
 use pest::Parser;
 
-use task_maker_diagnostics::CodeSpan;
+use task_maker_diagnostics::{CodeSpan, Diagnostic, DiagnosticCode};
 
 use crate::ioi::format::italian_yaml::TaskInputEntry;
-use crate::ioi::italian_yaml::cleanup_subtask_name;
+use crate::ioi::italian_yaml::{cleanup_subtask_name, multiple_candidates};
 use crate::ioi::{
     InputGenerator, InputValidator, OutputGenerator, SubtaskId, SubtaskInfo, TestcaseId,
-    TestcaseInfo,
+    TestcaseInfo, ToolTimeLimits,
 };
 use crate::{find_source_file, WriteBinTo};
 
@@ -25,6 +25,9 @@ mod parser {
 }
 
 /// Parse the `gen/GEN` file extracting the subtasks and the testcases.
+///
+/// The generator is required only if some testcases are generated by a command: a `gen/GEN` with
+/// only `#COPY` lines can be used for splitting the static input files in subtasks.
 pub(crate) fn parse_gen_gen<P: AsRef<Path>, V, O>(
     path: P,
     get_validator: V,
@@ -49,6 +52,10 @@ where
     let mut subtask_id: SubtaskId = 0;
     let mut entries = vec![];
     let mut st_name_to_id = HashMap::new();
+    // the tags of the next testcase
+    let mut pending_tags: Vec<String> = vec![];
+    // the time limit of the tools of the next testcase
+    let mut pending_timeout: Option<f64> = None;
 
     let mut default_subtask = Some(SubtaskInfo {
         id: 0,
@@ -73,11 +80,9 @@ where
     );
     if generators.len() > 1 {
         let paths = generators.iter().map(|s| s.name()).collect::<Vec<_>>();
-        bail!("Multiple generators found: {:?}", paths);
-    } else if generators.is_empty() {
-        bail!("No generator found");
+        bail!(multiple_candidates("generators", &paths));
     }
-    let generator = generators.pop().map(Arc::new).unwrap();
+    let generator = generators.pop().map(Arc::new);
     debug!("Detected input generator: {:?}", generator);
 
     for line in file.into_inner() {
@@ -145,9 +150,14 @@ where
                             bail!("#STDEP: must immediately follow a #ST: in gen/GEN");
                         };
                         for dependency in line.into_inner() {
-                            let dep_id = *st_name_to_id
-                                .get(dependency.as_str())
-                                .ok_or_else(|| anyhow!("Unknown subtask name: {}", dependency))?;
+                            let dep_id =
+                                *st_name_to_id.get(dependency.as_str()).ok_or_else(|| {
+                                    Diagnostic::error(format!(
+                                        "Unknown subtask name: {}",
+                                        dependency
+                                    ))
+                                    .with_code(DiagnosticCode::UndeclaredName)
+                                })?;
                             subtask.dependencies.push(dep_id);
                         }
                     }
@@ -161,11 +171,16 @@ where
                             .next()
                             .ok_or_else(|| anyhow!("Corrupted parser"))?
                             .as_str();
-                        entries.push(TaskInputEntry::Testcase(TestcaseInfo::new(
+                        let mut testcase = TestcaseInfo::new(
                             testcase_count,
                             InputGenerator::StaticFile(task_dir.join(what)),
                             get_output_gen(testcase_count),
-                        )));
+                        );
+                        testcase.tags = std::mem::take(&mut pending_tags);
+                        if let Some(timeout) = pending_timeout.take() {
+                            testcase.time_limits = ToolTimeLimits::all(timeout);
+                        }
+                        entries.push(TaskInputEntry::Testcase(testcase));
                         testcase_count += 1;
                     }
                     parser::Rule::command => {
@@ -173,19 +188,70 @@ where
                             entries.push(TaskInputEntry::Subtask(default));
                             subtask_id += 1;
                         }
+                        let Some(generator) = generator.clone() else {
+                            let mut diagnostic = Diagnostic::error(
+                                "No generator found, but a testcase is generated by a command",
+                            )
+                            .with_code(DiagnosticCode::NoGenerator)
+                            .with_help(
+                                "Add the generator at gen/generator.*, or use #COPY: lines for \
+                                the input files",
+                            );
+                            if let Ok(code_span) = CodeSpan::from_str(
+                                path,
+                                &content,
+                                span.start(),
+                                span.end() - span.start(),
+                            ) {
+                                diagnostic = diagnostic.with_code_span(code_span);
+                            }
+                            bail!(diagnostic);
+                        };
                         let cmd: Vec<String> =
                             line.into_inner().map(|x| x.as_str().to_owned()).collect();
                         let output_generator = get_output_gen(testcase_count);
                         if let OutputGenerator::StaticFile(_) = output_generator {
-                            bail!("Generator detected but no solution found. Cannot generate output files.");
+                            bail!(Diagnostic::error(
+                                "Generator detected but no solution found. Cannot generate output files."
+                            )
+                            .with_code(DiagnosticCode::MissingOfficialSolution));
                         }
-                        entries.push(TaskInputEntry::Testcase(TestcaseInfo::new(
+                        let mut testcase = TestcaseInfo::new(
                             testcase_count,
-                            InputGenerator::Custom(generator.clone(), cmd),
+                            InputGenerator::Custom(generator, cmd),
                             output_generator,
-                        )));
+                        );
+                        testcase.tags = std::mem::take(&mut pending_tags);
+                        if let Some(timeout) = pending_timeout.take() {
+                            testcase.time_limits = ToolTimeLimits::all(timeout);
+                        }
+                        entries.push(TaskInputEntry::Testcase(testcase));
                         testcase_count += 1;
                     }
+                    parser::Rule::tags => {
+                        for tag in line.into_inner() {
+                            let tag = tag.as_str().to_string();
+                            if !pending_tags.contains(&tag) {
+                                pending_tags.push(tag);
+                            }
+                        }
+                    }
+                    parser::Rule::timeout => {
+                        let seconds = line
+                            .into_inner()
+                            .next()
+                            .ok_or_else(|| anyhow!("Corrupted parser"))?
+                            .as_str();
+                        ensure!(
+                            pending_timeout.is_none(),
+                            "Cannot set the # @timeout: of a testcase twice in gen/GEN"
+                        );
+                        let timeout: f64 = seconds
+                            .parse()
+                            .with_context(|| format!("Invalid timeout in gen/GEN: {}", seconds))?;
+                        ensure!(timeout >= 0.0, "Invalid timeout in gen/GEN: {}", seconds);
+                        pending_timeout = Some(timeout);
+                    }
                     parser::Rule::comment => {}
                     parser::Rule::empty => {}
                     _ => unreachable!(),
@@ -195,6 +261,14 @@ where
             _ => unreachable!(),
         }
     }
+    ensure!(
+        pending_tags.is_empty(),
+        "The last # @tags: line of gen/GEN is not followed by a testcase"
+    );
+    ensure!(
+        pending_timeout.is_none(),
+        "The last # @timeout: line of gen/GEN is not followed by a testcase"
+    );
     Ok(entries)
 }
 
@@ -212,7 +286,9 @@ mod tests {
 
     use crate::ioi::format::italian_yaml::gen_gen::parse_gen_gen;
     use crate::ioi::format::italian_yaml::TaskInputEntry;
-    use crate::ioi::{InputGenerator, InputValidator, OutputGenerator, SubtaskId, TestcaseId};
+    use crate::ioi::{
+        InputGenerator, InputValidator, OutputGenerator, SubtaskId, TestcaseId, ToolTimeLimits,
+    };
     use crate::SourceFile;
 
     fn make_task<S: AsRef<str>>(gen_gen: S) -> TempDir {
@@ -561,4 +637,112 @@ mod tests {
         let res = get_parsed_gen_gen("#ST:1\n#STNAME: tree-line");
         assert!(res.is_ok());
     }
+
+    #[test]
+    fn test_parser_tags() {
+        let entries =
+            get_parsed_gen_gen("# @tags: tiny, line-graph\n1234\n#@tags:tree # a tree\n5678\n9\n")
+                .unwrap();
+        if let [Subtask(_), Testcase(tc0), Testcase(tc1), Testcase(tc2)] = entries.as_slice() {
+            assert_eq!(tc0.tags, vec!["tiny", "line-graph"]);
+            assert_eq!(tc1.tags, vec!["tree"]);
+            assert!(tc2.tags.is_empty());
+        } else {
+            panic!("Wrong entries returned: {:?}", entries);
+        }
+    }
+
+    #[test]
+    fn test_parser_tags_copy() {
+        let task = make_task("# @tags: tiny\n# @tags: tiny, manual\n#COPY: random/file\n");
+        let entries = get_entries(task.path()).unwrap();
+        if let [Subtask(_), Testcase(testcase)] = entries.as_slice() {
+            assert_eq!(testcase.tags, vec!["tiny", "manual"]);
+        } else {
+            panic!("Wrong entries returned: {:?}", entries);
+        }
+    }
+
+    #[test]
+    fn test_parser_tags_invalid() {
+        assert!(get_parsed_gen_gen("# @tags: a b\n1234\n").is_err());
+        assert!(get_parsed_gen_gen("1234\n# @tags: tree\n").is_err());
+    }
+
+    #[test]
+    fn test_parser_tags_comment() {
+        let entries = get_parsed_gen_gen("# @tagsless comment\n1234\n").unwrap();
+        if let [Subtask(_), Testcase(testcase)] = entries.as_slice() {
+            assert!(testcase.tags.is_empty());
+        } else {
+            panic!("Wrong entries returned: {:?}", entries);
+        }
+    }
+
+    #[test]
+    fn test_parser_timeout() {
+        let entries = get_parsed_gen_gen(
+            "# @timeout: 2.5
+1234
+#COPY: file
+",
+        )
+        .unwrap();
+        if let [Subtask(_), Testcase(tc0), Testcase(tc1)] = entries.as_slice() {
+            assert_eq!(tc0.time_limits, ToolTimeLimits::all(2.5));
+            assert_eq!(tc1.time_limits, ToolTimeLimits::default());
+        } else {
+            panic!("Wrong entries returned: {:?}", entries);
+        }
+    }
+
+    #[test]
+    fn test_parser_timeout_invalid() {
+        assert!(get_parsed_gen_gen(
+            "# @timeout: 5s
+1234
+"
+        )
+        .is_err());
+        assert!(get_parsed_gen_gen(
+            "# @timeout: 1
+# @timeout: 2
+1234
+"
+        )
+        .is_err());
+        assert!(get_parsed_gen_gen(
+            "1234
+# @timeout: 5
+"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parser_without_generator() {
+        let task = make_task("#ST: 100\n#COPY: random/file\n#COPY: other/file\n");
+        fs::remove_file(task.path().join("gen").join("generator.py")).unwrap();
+        let entries = get_entries(task.path()).unwrap();
+        if let [Subtask(_), Testcase(testcase0), Testcase(testcase1)] = entries.as_slice() {
+            assert!(matches!(
+                testcase0.input_generator,
+                InputGenerator::StaticFile(_)
+            ));
+            assert!(matches!(
+                testcase1.input_generator,
+                InputGenerator::StaticFile(_)
+            ));
+        } else {
+            panic!("Wrong entries returned: {:?}", entries);
+        }
+    }
+
+    #[test]
+    fn test_parser_without_generator_with_command() {
+        let task = make_task("#COPY: random/file\n1234\n");
+        fs::remove_file(task.path().join("gen").join("generator.py")).unwrap();
+        let err = get_entries(task.path()).unwrap_err();
+        assert!(err.to_string().contains("No generator found"), "{:?}", err);
+    }
 }
@@ -161,6 +161,13 @@
 //! Example: `:CONSTRAINT 0 <= $N < $M <= 1000000` will check that the variables `$N` and `$M` are
 //! between 0 and 1000000 and `$N` is smaller than `$M`.
 //!
+//! ### `: CONSTRAINTS constraint (, constraint)*`
+//! This command adds many constraints at once, separated by commas. Each of them follows the same
+//! rules of `: CONSTRAINT`.
+//!
+//! Example: `:CONSTRAINTS 1 <= $N <= 1000, 1 <= $M <= $N` is the same as two `: CONSTRAINT`
+//! commands.
+//!
 //! ### `: SUBTASK score [name]`
 //! This command marks the start of a new subtask, just like how `#ST` in `gen/GEN` did. The score
 //! can be a simple floating point number (either an integer or an integer.integer). The name
@@ -202,6 +209,11 @@
 //! - `$ST_NUM`: the 0-based index of the subtask
 //! - `$ST_NAME`: the name of the subtask
 //! - `$INPUT` _(only for validators)_: the name of the file to validate
+//! - `$MIN_var` and `$MAX_var` _(only for validators)_: the bounds of the variable `$var` derived
+//!   from the constraints of the subtask that compare it with a constant. For example with
+//!   `: CONSTRAINT 1 <= $N < 1000` the validator can receive `$MIN_N` (1) and `$MAX_N` (999), so
+//!   the bounds are written only once. When more constraints bound the same variable the tightest
+//!   bounds are used.
 //!
 //! ## Full example of `cases.gen`
 //! ```text
@@ -255,7 +267,7 @@
 //! be checked because it was scoped only to the second subtask.
 //! The subtask also does not have a name, the default one (`subtask2`) will be used.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::BufWriter;
 use std::path::{Path, PathBuf};
@@ -268,16 +280,19 @@ use unic::normal::StrNormalForm;
 use unic::ucd::category::GeneralCategory;
 
 pub(crate) use cases_gen::{is_gen_gen_deletable, TM_ALLOW_DELETE_COOKIE};
+pub use cases_gen::{Constraint, ConstraintOperand, ConstraintOperator};
+use task_maker_diagnostics::{Diagnostic, DiagnosticCode};
 use task_maker_lang::GraderMap;
 
 use crate::ioi::sanity_checks::get_sanity_checks;
 use crate::ioi::{
-    make_task_booklets, Checker, IOITask, InputValidator, OutputGenerator, SubtaskId, SubtaskInfo,
-    TaskType, TestcaseId, TestcaseInfo, TestcaseScoreAggregator,
+    make_task_booklets, Checker, CheckerMessages, IOITask, InputValidator, OutputGenerator,
+    ScoreRounding, SubtaskId, SubtaskInfo, TaskType, TestcaseId, TestcaseInfo,
+    TestcaseScoreAggregator, ToolTimeLimits,
 };
-use crate::ioi::{BatchTypeData, CommunicationTypeData, UserIo};
-use crate::ioi::{InputValidatorGenerator, TM_VALIDATION_FILE_NAME};
-use crate::{find_source_file, list_files, EvaluationConfig, WriteBinTo};
+use crate::ioi::{BatchTypeData, CommunicationTypeData, TwoStepsTypeData, UserIo};
+use crate::ioi::{InputValidatorGenerator, TM_VALIDATION_FILE_NAME, VALIDATOR_VARIABLES};
+use crate::{find_source_file, list_files, EvaluationConfig, SourceFile, WriteBinTo};
 
 mod cases_gen;
 mod gen_gen;
@@ -345,9 +360,14 @@ struct TaskYAML {
     /// The parameters of the score type.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub score_type_parameters: Option<Vec<(f64, String)>>,
-    /// The number of decimal digits when displaying the scores.
-    #[serde(default)]
-    pub score_precision: usize,
+    /// The number of decimal digits of the scores. When set, the score of the task is rounded to
+    /// this number of digits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_precision: Option<usize>,
+    /// How the scores are rounded to `score_precision` digits: nearest, down or up. Defaults to
+    /// nearest.
+    #[serde(default, skip_serializing)]
+    pub score_rounding: ScoreRounding,
     /// The primary language used when importing the task.
     pub primary_language: Option<String>,
 
@@ -357,6 +377,15 @@ struct TaskYAML {
     /// The memory limit in MiB of the execution of the solution, if not set it's unlimited.
     #[serde(alias = "memlimit")]
     pub memory_limit: Option<u64>,
+    /// The time limit in seconds of the generation of each input file. Zero means unlimited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation_time_limit: Option<f64>,
+    /// The time limit in seconds of the validation of each input file. Zero means unlimited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation_time_limit: Option<f64>,
+    /// The time limit in seconds of the generation of each output file. Zero means unlimited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_generation_time_limit: Option<f64>,
 
     /// Whether this is an output only task. Defaults to false.
     #[serde(default)]
@@ -381,6 +410,36 @@ struct TaskYAML {
     /// Can be either "std_io" for using stdin/stdout, or "fifo_io" for using pipes given in argv.
     /// Defaults to "fifo_io".
     pub user_io: Option<String>,
+    /// Whether the solutions are run in two steps, with `0` and `1` as argument, the second step
+    /// reading the output of the first one. Defaults to false.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub two_steps: bool,
+
+    /// Whether to package the attachments in a zip file per language. Defaults to false.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub attachment_packages: bool,
+    /// The OCI container image in which the executions of the task are run, for the tasks that need
+    /// a toolchain not installed on the workers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_image: Option<String>,
+    /// The strings that the solutions cannot contain, like `#include <thread>` or `system(`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forbidden_constructs: Vec<String>,
+    /// The tags of the resources (e.g. `gpu`) that the workers must provide for running the
+    /// executions of the task.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_resources: Vec<String>,
+
+    /// The arguments passed to the validator of gen/GEN or input/, instead of the path of the input
+    /// file and the subtask number starting from 1. The arguments starting with `$` are variables:
+    /// `$INPUT`, `$ST_NUM` (starting from 0), `$ST_NAME` and `$TESTCASE`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validator_args: Option<Vec<String>>,
+
+    /// The verdicts to show instead of the messages of the checker, overriding the ones of
+    /// `contest.yaml`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checker_messages: BTreeMap<String, String>,
 
     /// Compatibility with cms, unused.
     pub score_mode: Option<String>,
@@ -402,9 +461,12 @@ struct TaskYAMLOrig {
     pub title: String,
     /// The score type to use for this task.
     pub score_type: Option<TestcaseScoreAggregator>,
-    /// The number of decimal digits when displaying the scores.
+    /// The number of decimal digits of the scores.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_precision: Option<usize>,
+    /// How the scores are rounded to `score_precision` digits.
     #[serde(default)]
-    pub score_precision: usize,
+    pub score_rounding: ScoreRounding,
     /// The primary language used when importing the task.
     pub primary_language: Option<String>,
 
@@ -412,6 +474,12 @@ struct TaskYAMLOrig {
     pub time_limit: f64,
     /// The memory limit in MiB of the execution of the solution.
     pub memory_limit: u64,
+    /// The time limit in seconds of the generation of each input file.
+    pub generation_time_limit: Option<f64>,
+    /// The time limit in seconds of the validation of each input file.
+    pub validation_time_limit: Option<f64>,
+    /// The time limit in seconds of the generation of each output file.
+    pub output_generation_time_limit: Option<f64>,
 
     /// Whether this is an output only task. Defaults to false.
     #[serde(default)]
@@ -436,6 +504,27 @@ struct TaskYAMLOrig {
     /// Can be either "std_io" for using stdin/stdout, or "fifo_io" for using pipes given in argv.
     /// Defaults to "fifo_io".
     pub user_io: Option<String>,
+    /// Whether the solutions are run in two steps, the second reading the output of the first one.
+    #[serde(default)]
+    pub two_steps: bool,
+
+    /// Whether to package the attachments in a zip file per language. Defaults to false.
+    #[serde(default)]
+    pub attachment_packages: bool,
+    /// The OCI container image in which the executions of the task are run.
+    #[serde(default)]
+    pub container_image: Option<String>,
+    /// The strings that the solutions cannot contain.
+    #[serde(default)]
+    pub forbidden_constructs: Vec<String>,
+    /// The tags of the resources that the workers must provide for running the executions.
+    #[serde(default)]
+    pub required_resources: Vec<String>,
+
+    /// The verdicts to show instead of the messages of the checker, overriding the ones of
+    /// `contest.yaml`.
+    #[serde(default)]
+    pub checker_messages: BTreeMap<String, String>,
 }
 
 impl TaskYAMLOrig {
@@ -446,9 +535,13 @@ impl TaskYAMLOrig {
             score_type: self.score_type,
             score_type_parameters: None,
             score_precision: self.score_precision,
+            score_rounding: self.score_rounding,
             primary_language: Some(self.primary_language.unwrap_or_else(|| "en".into())),
             time_limit: Some(self.time_limit),
             memory_limit: Some(self.memory_limit),
+            generation_time_limit: self.generation_time_limit,
+            validation_time_limit: self.validation_time_limit,
+            output_generation_time_limit: self.output_generation_time_limit,
             output_only: self.output_only,
             infile: self.infile,
             outfile: self.outfile,
@@ -456,6 +549,13 @@ impl TaskYAMLOrig {
             syllabuslevel: self.syllabuslevel,
             num_processes: self.num_processes,
             user_io: self.user_io,
+            two_steps: self.two_steps,
+            attachment_packages: self.attachment_packages,
+            container_image: self.container_image,
+            forbidden_constructs: self.forbidden_constructs,
+            required_resources: self.required_resources,
+            validator_args: None,
+            checker_messages: self.checker_messages,
             score_mode: Some("max_subtask".into()),
             token_mode: Some("disabled".into()),
             public_testcases: Some("all".into()),
@@ -508,16 +608,19 @@ pub fn parse_task<P: AsRef<Path>>(
         let file = File::open(&path)
             .with_context(|| format!("Cannot open task.yaml.orig from {}", path.display()))?;
         let yaml_orig: TaskYAMLOrig =
-            serde_yaml::from_reader(file).context("Failed to deserialize task.yaml.orig")?;
+            serde_yaml::from_reader(file).map_err(|e| invalid_yaml("task.yaml.orig", e))?;
         yaml = yaml_orig.into_task_yaml(task_dir);
     } else if task_dir.join("task.yaml").exists() {
         task_yaml_overwrite = false;
         let path = task_dir.join(task_dir.join("task.yaml"));
         let file = File::open(&path)
             .with_context(|| format!("Cannot open task.yaml from {}", path.display()))?;
-        yaml = serde_yaml::from_reader(file).context("Failed to deserialize task.yaml")?;
+        yaml = serde_yaml::from_reader(file).map_err(|e| invalid_yaml("task.yaml", e))?;
     } else {
-        bail!("No task.yaml found in {}", task_dir.display());
+        bail!(
+            Diagnostic::error(format!("No task.yaml found in {}", task_dir.display()))
+                .with_code(DiagnosticCode::TaskNotFound)
+        );
     }
     debug!("The yaml is {:#?}", yaml);
 
@@ -535,22 +638,65 @@ pub fn parse_task<P: AsRef<Path>>(
     debug!("The graders are: {:#?}", grader_map);
 
     let task_type = if let Some(comm) = parse_communication_task_data(task_dir, &yaml)? {
+        if yaml.two_steps {
+            bail!(Diagnostic::error(
+                "A task with a communication manager cannot have two_steps in task.yaml"
+            )
+            .with_code(DiagnosticCode::InvalidTaskYaml));
+        }
         comm
+    } else if yaml.two_steps {
+        TaskType::TwoSteps(TwoStepsTypeData {
+            checker: detect_checker(task_dir)?,
+        })
     } else {
-        parse_batch_task_data(task_dir, grader_map.clone())?
+        parse_batch_task_data(
+            task_dir,
+            grader_map.clone(),
+            eval_config.official_solution.as_deref(),
+        )?
     };
 
     let gen_gen = task_dir.join("gen").join("GEN");
     let cases_gen = task_dir.join("gen").join("cases.gen");
-    let output_generator: Box<dyn Fn(TestcaseId) -> OutputGenerator> =
-        if let TaskType::Batch(_) = &task_type {
-            Box::new(
-                detect_output_generator(task_dir.to_path_buf(), grader_map.clone())
-                    .context("Failed to detect output generator")?,
+    let output_generator: Box<dyn Fn(TestcaseId) -> OutputGenerator> = match &task_type {
+        TaskType::Batch(_) => detect_output_generator(
+            task_dir.to_path_buf(),
+            grader_map.clone(),
+            eval_config.official_solution.as_deref(),
+            eval_config.trust_outputs,
+        )
+        .context("Failed to detect output generator")?,
+        TaskType::TwoSteps(_) => {
+            let output_generator = detect_output_generator(
+                task_dir.to_path_buf(),
+                grader_map.clone(),
+                eval_config.official_solution.as_deref(),
+                eval_config.trust_outputs,
             )
-        } else {
+            .context("Failed to detect output generator")?;
+            // the official solution has to be run in two steps as well
+            Box::new(move |tc| match output_generator(tc) {
+                OutputGenerator::Custom(solution, _) => OutputGenerator::TwoSteps(solution),
+                output_generator => output_generator,
+            })
+        }
+        _ => {
+            if eval_config.official_solution.is_some() {
+                bail!(Diagnostic::error(
+                    "--official-solution is supported only by Batch and TwoSteps tasks"
+                )
+                .with_code(DiagnosticCode::UnsupportedOption));
+            }
+            if eval_config.trust_outputs {
+                bail!(Diagnostic::error(
+                    "--trust-outputs is supported only by Batch and TwoSteps tasks"
+                )
+                .with_code(DiagnosticCode::UnsupportedOption));
+            }
             Box::new(|_| OutputGenerator::NotAvailable)
-        };
+        }
+    };
 
     let inputs = if cases_gen.exists() {
         debug!("Parsing testcases from gen/cases.gen");
@@ -563,19 +709,28 @@ pub fn parse_task<P: AsRef<Path>>(
         debug!("Parsing testcases from gen/GEN");
         gen_gen::parse_gen_gen(
             &gen_gen,
-            detect_validator(task_dir.into()).context("Failed to detect validator")?,
+            detect_validator(task_dir.into(), yaml.validator_args.clone())
+                .context("Failed to detect validator")?,
             output_generator,
         )?
     } else {
         debug!("Using testcases inside input/");
         static_inputs::static_inputs(
             task_dir,
-            detect_validator(task_dir.into()).context("Failed to detect validator")?,
+            detect_validator(task_dir.into(), yaml.validator_args.clone())
+                .context("Failed to detect validator")?,
             output_generator,
         )
         .collect()
     };
 
+    let time_limits = ToolTimeLimits {
+        generation: yaml.generation_time_limit,
+        validation: yaml.validation_time_limit,
+        output_generation: yaml.output_generation_time_limit,
+    }
+    .or(ToolTimeLimits::task_default());
+
     let mut subtasks = HashMap::new();
     let mut testcases = HashMap::new();
     let mut last_subtask: Option<SubtaskInfo> = None;
@@ -587,7 +742,8 @@ pub fn parse_task<P: AsRef<Path>>(
                 }
                 last_subtask = Some(subtask);
             }
-            TaskInputEntry::Testcase(testcase) => {
+            TaskInputEntry::Testcase(mut testcase) => {
+                testcase.time_limits = testcase.time_limits.or(time_limits);
                 let st = last_subtask.as_mut().context("Testcase before Subtask")?;
                 st.testcases.push(testcase.id);
                 st.testcases_owned.push(testcase.id);
@@ -654,6 +810,8 @@ pub fn parse_task<P: AsRef<Path>>(
         bail!("Use task.yaml.orig to use subtask dependencies");
     }
 
+    let checker_messages = CheckerMessages::load(task_dir, yaml.checker_messages)
+        .context("Failed to load the checker messages")?;
     let mut task = IOITask {
         path: task_dir.into(),
         task_type,
@@ -665,12 +823,20 @@ pub fn parse_task<P: AsRef<Path>>(
         outfile,
         testcase_score_aggregator,
         score_precision: yaml.score_precision,
+        score_rounding: yaml.score_rounding,
         subtasks,
         testcases,
         grader_map,
         booklets: Vec::new(),
         difficulty: yaml.difficulty,
         syllabus_level: yaml.syllabuslevel,
+        attachment_packages: yaml.attachment_packages,
+        container_image: yaml.container_image,
+        forbidden_constructs: yaml.forbidden_constructs,
+        required_resources: yaml.required_resources.into_iter().collect(),
+        quick_testcases: None,
+        official_solution_override: eval_config.official_solution.clone(),
+        checker_messages,
         sanity_checks: Arc::new(get_sanity_checks(
             &eval_config
                 .disabled_sanity_checks
@@ -679,7 +845,8 @@ pub fn parse_task<P: AsRef<Path>>(
                 .collect::<Vec<_>>(),
         )),
         input_validator_generator: InputValidatorGenerator::new(
-            detect_validator(task_dir.to_path_buf()).context("Failed to detect validator")?,
+            detect_validator(task_dir.to_path_buf(), yaml.validator_args)
+                .context("Failed to detect validator")?,
         ),
     };
     // split the creation of the task because make_booklets need an instance of Task
@@ -693,9 +860,28 @@ pub fn parse_task<P: AsRef<Path>>(
 /// Search for a valid input validator inside the task directory. Will return a function that, given
 /// a subtask id, returns an `InputValidator` using that validator. If no validator is found,
 /// `InputValidator::AssumeValid` is used.
+///
+/// If `validator_args` is provided, the validator is called with those arguments instead of the
+/// default ones.
 fn detect_validator(
     task_dir: PathBuf,
+    validator_args: Option<Vec<String>>,
 ) -> Result<impl Fn(Option<SubtaskId>) -> InputValidator, Error> {
+    for arg in validator_args.iter().flatten() {
+        if let Some(var) = arg.strip_prefix('$') {
+            if !VALIDATOR_VARIABLES.contains(&var) {
+                bail!(Diagnostic::error(format!(
+                    "Unknown variable in validator_args of task.yaml: {}",
+                    arg
+                ))
+                .with_help(format!(
+                    "The valid variables are: ${}",
+                    VALIDATOR_VARIABLES.join(", $")
+                ))
+                .with_code(DiagnosticCode::UndeclaredName));
+            }
+        }
+    }
     let mut validators = find_source_file(
         &task_dir,
         vec![
@@ -711,12 +897,15 @@ fn detect_validator(
     );
     if validators.len() > 1 {
         let paths = validators.iter().map(|s| s.name()).collect::<Vec<_>>();
-        bail!("Multiple validators found: {:?}", paths);
+        bail!(multiple_candidates("validators", &paths));
     }
     let validator = validators.pop().map(Arc::new);
     debug!("Detected input validator: {:?}", validator);
     Ok(move |st: Option<SubtaskId>| -> InputValidator {
         if let Some(validator) = validator.as_ref() {
+            if let Some(args) = &validator_args {
+                return InputValidator::CustomWithVariables(validator.clone(), args.clone());
+            }
             InputValidator::Custom(
                 validator.clone(),
                 // for legacy support reasons the subtask is passed 1-based
@@ -734,10 +923,70 @@ fn detect_validator(
 /// Search for a valid output generator (aka official solution) inside the task directory. Will
 /// return a function that, given a testcase id, returns an `OutputGenerator` using that generator.
 /// If no generator is found, `OutputGenerator::StaticFile` is used instead.
+///
+/// If `override_solution` is provided, that solution is used instead of the one in the task
+/// directory. With `trust_outputs` the output files are always the static ones, even if there is
+/// an official solution.
 fn detect_output_generator(
     task_dir: PathBuf,
     grader_map: Arc<GraderMap>,
-) -> Result<impl Fn(TestcaseId) -> OutputGenerator, Error> {
+    override_solution: Option<&Path>,
+    trust_outputs: bool,
+) -> Result<Box<dyn Fn(TestcaseId) -> OutputGenerator>, Error> {
+    let output_directory = task_dir.join("output");
+    if trust_outputs && override_solution.is_some() {
+        bail!(
+            Diagnostic::error("--trust-outputs and --official-solution cannot be used together")
+                .with_code(DiagnosticCode::UnsupportedOption)
+                .with_help("The trusted output files are not generated by any solution")
+        );
+    }
+    if trust_outputs {
+        if !output_directory.is_dir() {
+            bail!(Diagnostic::error(
+                "The output files cannot be trusted since there is no output/ directory"
+            )
+            .with_code(DiagnosticCode::MissingFile)
+            .with_help(
+                "The output files should be at output/output0.txt, output/output1.txt, ..."
+            ));
+        }
+        debug!("Using the output files inside output/");
+        return Ok(Box::new(move |tc: TestcaseId| {
+            OutputGenerator::StaticFile(output_directory.join(format!("output{}.txt", tc)))
+        }));
+    }
+    if let Some(path) = override_solution {
+        if !path.exists() {
+            bail!(Diagnostic::error(format!(
+                "Cannot find the official solution at {}",
+                path.display()
+            ))
+            .with_code(DiagnosticCode::MissingOfficialSolution)
+            .with_help("Check the path passed to --official-solution"));
+        }
+        let name = path.strip_prefix(&task_dir).unwrap_or(path);
+        let solution = SourceFile::new(
+            path,
+            &task_dir,
+            format!("Official solution at {}", name.display()),
+            Some(grader_map),
+            // bin/official_solution is the executable of the official solution of the task
+            None::<PathBuf>,
+        )
+        .ok_or_else(|| {
+            Diagnostic::error(format!(
+                "Unknown language of the solution {}",
+                path.display()
+            ))
+            .with_code(DiagnosticCode::UnknownLanguage)
+        })?;
+        let solution = Arc::new(solution);
+        debug!("Overridden output generator: {:?}", solution);
+        return Ok(Box::new(move |_: TestcaseId| {
+            OutputGenerator::Custom(solution.clone(), vec![])
+        }));
+    }
     let mut official_solutions = find_source_file(
         &task_dir,
         vec![
@@ -756,22 +1005,21 @@ fn detect_output_generator(
             .iter()
             .map(|s| s.name())
             .collect::<Vec<_>>();
-        bail!("Multiple official solutions found: {:?}", paths);
+        bail!(multiple_candidates("official solutions", &paths));
     }
     let official_solution = official_solutions.pop().map(Arc::new);
     debug!("Detected output generator: {:?}", official_solution);
-    let output_directory = task_dir.join("output");
-    Ok(move |tc: TestcaseId| -> OutputGenerator {
+    Ok(Box::new(move |tc: TestcaseId| -> OutputGenerator {
         if let Some(solution) = official_solution.as_ref() {
             OutputGenerator::Custom(solution.clone(), vec![])
         } else {
             OutputGenerator::StaticFile(output_directory.join(format!("output{}.txt", tc)))
         }
-    })
+    }))
 }
 
-/// Parse the task components relative to the batch task type.
-fn parse_batch_task_data(task_dir: &Path, grader_map: Arc<GraderMap>) -> Result<TaskType, Error> {
+/// Detect the checker of the task, the default one if there is no custom checker.
+fn detect_checker(task_dir: &Path) -> Result<Checker, Error> {
     let mut checkers = find_source_file(
         task_dir,
         vec!["check/checker.*", "cor/correttore.*"],
@@ -782,9 +1030,9 @@ fn parse_batch_task_data(task_dir: &Path, grader_map: Arc<GraderMap>) -> Result<
     );
     if checkers.len() > 1 {
         let paths = checkers.iter().map(|s| s.name()).collect::<Vec<_>>();
-        bail!("Multiple checkers found: {:?}", paths)
+        bail!(multiple_candidates("checkers", &paths));
     }
-    let checker = checkers
+    Ok(checkers
         .pop()
         .map(|mut c| {
             // Always copy the custom checker.
@@ -795,10 +1043,19 @@ fn parse_batch_task_data(task_dir: &Path, grader_map: Arc<GraderMap>) -> Result<
 
             Checker::Custom(Arc::new(c))
         })
-        .unwrap_or(Checker::WhiteDiff);
+        .unwrap_or(Checker::WhiteDiff))
+}
 
-    let official_solution = detect_output_generator(task_dir.to_path_buf(), grader_map)
-        .context("Failed to detect output generator")?;
+/// Parse the task components relative to the batch task type.
+fn parse_batch_task_data(
+    task_dir: &Path,
+    grader_map: Arc<GraderMap>,
+    override_solution: Option<&Path>,
+) -> Result<TaskType, Error> {
+    let checker = detect_checker(task_dir)?;
+    let official_solution =
+        detect_output_generator(task_dir.to_path_buf(), grader_map, override_solution, false)
+            .context("Failed to detect output generator")?;
     let official_solution = match official_solution(0) {
         gen @ OutputGenerator::Custom(_, _) => Some(gen),
         _ => None,
@@ -824,7 +1081,7 @@ fn parse_communication_task_data(
     );
     if managers.len() > 1 {
         let paths = managers.iter().map(|s| s.name()).collect::<Vec<_>>();
-        bail!("Multiple managers found: {:?}", paths);
+        bail!(multiple_candidates("managers", &paths));
     }
     let mut manager = if let Some(manager) = managers.pop() {
         manager
@@ -862,6 +1119,19 @@ fn default_outfile() -> String {
     "output.txt".into()
 }
 
+/// The diagnostic of a task.yaml (or task.yaml.orig) that cannot be deserialized.
+fn invalid_yaml(name: &str, error: serde_yaml::Error) -> Diagnostic {
+    Diagnostic::error(format!("Failed to deserialize {}", name))
+        .with_code(DiagnosticCode::InvalidTaskYaml)
+        .with_note(error.to_string())
+}
+
+/// The diagnostic of more files found for the same role, e.g. more checkers.
+fn multiple_candidates(kind: &str, paths: &[String]) -> Diagnostic {
+    Diagnostic::error(format!("Multiple {} found: {:?}", kind, paths))
+        .with_code(DiagnosticCode::MultipleCandidates)
+}
+
 /// Normalize and validate the content of the subtask name.
 fn cleanup_subtask_name(id: &str) -> Result<String, Error> {
     let id = id.trim();
@@ -15,26 +15,34 @@
 //! a `Checker`, a program that computes the score of the testcase given the input file, the output
 //! file and the _correct_ output file (the one produced by the jury).
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 use unic::normal::StrNormalForm;
 use wildmatch::WildMatch;
 
+pub use attachments::*;
+pub use checker_messages::CheckerMessages;
 use curses_ui::CursesUI;
 pub use dag::*;
 pub use format::italian_yaml;
+pub use output_determinism::OutputDeterminism;
+pub use profiler::{Profiler, ProfilerTool};
+pub use sanitizer::Sanitizer;
+pub use seed_matrix::{SeedAggregation, SeedMatrix, SEED_ENV_VAR};
+pub use solution_dedup::SolutionDedup;
 pub use statement::*;
 pub use task_info::*;
-use task_maker_dag::{ExecutionDAGConfig, FileUuid};
-use task_maker_diagnostics::CodeSpan;
+use task_maker_dag::{ExecutionDAGConfig, ExecutionSkipper, ExecutionUuid, FileUuid, ResourceTags};
+use task_maker_diagnostics::{CodeSpan, Diagnostic, DiagnosticCategory, DiagnosticCode};
 use task_maker_lang::GraderMap;
+pub use time_limit::*;
 pub use ui_state::*;
 
 use crate::ioi::format::italian_yaml::TM_ALLOW_DELETE_COOKIE;
@@ -44,13 +52,21 @@ use crate::solution::SolutionInfo;
 use crate::ui::*;
 use crate::{EvaluationConfig, EvaluationData, TaskInfo, UISender};
 
+mod attachments;
+mod checker_messages;
 mod curses_ui;
 mod dag;
 pub(crate) mod finish_ui;
 mod format;
+mod output_determinism;
+mod profiler;
+mod sanitizer;
 pub mod sanity_checks;
+mod seed_matrix;
+mod solution_dedup;
 mod statement;
 pub(crate) mod task_info;
+pub(crate) mod time_limit;
 pub(crate) mod ui_state;
 
 /// In IOI tasks the subtask numbers are non-negative 0-based integers.
@@ -74,6 +90,31 @@ pub struct ScoreManager {
     subtask_testcases: HashMap<SubtaskId, Vec<TestcaseId>>,
     /// The aggregator to use for computing the subtask scores.
     aggregator: TestcaseScoreAggregator,
+    /// The number of decimal digits the score of the task is rounded to, if any.
+    precision: Option<usize>,
+    /// How the score of the task is rounded to `precision` digits.
+    rounding: ScoreRounding,
+    /// When set, the evaluations of the subtasks that already failed because of a dependency are
+    /// skipped.
+    dependent_subtasks: Option<DependentSubtasks>,
+    /// The verdicts to send instead of the messages of the checker.
+    checker_messages: CheckerMessages,
+}
+
+/// The state for skipping the testcases that cannot change the score of the solution anymore, since
+/// all the subtasks that include them have already failed a testcase of one of their dependencies.
+#[derive(Debug, Clone)]
+struct DependentSubtasks {
+    /// The handle for skipping the evaluations.
+    skipper: ExecutionSkipper,
+    /// The testcases owned by each subtask, the others come from its dependencies.
+    owned_testcases: HashMap<SubtaskId, HashSet<TestcaseId>>,
+    /// The executions of the solution on each testcase.
+    evaluations: HashMap<TestcaseId, Vec<ExecutionUuid>>,
+    /// The subtasks in which a testcase of a dependency scored zero.
+    failed_subtasks: HashSet<SubtaskId>,
+    /// The testcases whose evaluations have been skipped.
+    skipped_testcases: HashSet<TestcaseId>,
 }
 
 /// A simple struct that generates input validators for a given subtask.
@@ -133,9 +174,13 @@ pub struct IOITask {
     /// The aggregator to use to compute the score of the subtask based on the score of the
     /// testcases.
     pub testcase_score_aggregator: TestcaseScoreAggregator,
-    /// The number of decimal digits when displaying the scores.
+    /// The number of decimal digits of the scores, if set the score of the task is rounded to it.
+    /// When not set the scores are shown without decimal digits, but they are not rounded.
+    #[serde(default)]
+    pub score_precision: Option<usize>,
+    /// How the score of the task is rounded to `score_precision` digits.
     #[serde(default)]
-    pub score_precision: usize,
+    pub score_rounding: ScoreRounding,
     /// The graders registered for this task.
     pub grader_map: Arc<GraderMap>,
     /// The booklets to compile for this task.
@@ -145,6 +190,31 @@ pub struct IOITask {
     /// An integer that defines the level inside a _syllabus_ (for example for the Olympiads in
     /// Teams). Used only in booklet compilations.
     pub syllabus_level: Option<u8>,
+    /// Whether to package the attachments in a zip file per language.
+    #[serde(default)]
+    pub attachment_packages: bool,
+    /// The OCI container image in which the executions of the task are run, if any.
+    #[serde(default)]
+    pub container_image: Option<String>,
+    /// The strings that the solutions cannot contain, checked by a sanity check.
+    #[serde(default)]
+    pub forbidden_constructs: Vec<String>,
+    /// The tags of the resources that the workers must provide for running the executions of the
+    /// task.
+    #[serde(default)]
+    pub required_resources: ResourceTags,
+    /// The only testcases on which the solutions are evaluated, when they are evaluated with
+    /// `--quick`. The scores of the solutions are then partial.
+    #[serde(default)]
+    pub quick_testcases: Option<HashSet<TestcaseId>>,
+    /// The solution that generates the output files instead of the official one of the task, set
+    /// with `--official-solution`. Its output files are not written to `output/`, which keeps the
+    /// ones of the task.
+    #[serde(default)]
+    pub official_solution_override: Option<PathBuf>,
+    /// The verdicts to show instead of the messages of the checker.
+    #[serde(default)]
+    pub checker_messages: CheckerMessages,
     /// The sanity checks attached to this task. Wrapped in Arc since `SanityChecks` is not Clone.
     /// It's also not `Serialize` nor `Deserialize`, all the sanity checks will be lost on
     /// serialization.
@@ -178,6 +248,9 @@ pub struct SubtaskInfo {
     pub is_default: bool,
     /// The list of the dependencies of this subtask.
     pub dependencies: Vec<SubtaskId>,
+    /// The constraints on the variables of the testcases of this subtask, including the global
+    /// ones. Only the tasks using `cases.gen` have them.
+    pub constraints: Vec<italian_yaml::Constraint>,
 }
 
 /// A testcase of a IOI task.
@@ -196,6 +269,13 @@ pub struct TestcaseInfo {
     pub input_file: Option<FileUuid>,
     /// The generated official output file UUID. This is set only after the DAG is built.
     pub official_output_file: Option<FileUuid>,
+    /// The tags of this testcase (e.g. `tree`), from the `# @tags:` lines of `gen/GEN`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The time limits of the generation, the validation and the output generation of this
+    /// testcase, from the `# @timeout:` lines of `gen/GEN` or from `task.yaml`.
+    #[serde(default)]
+    pub time_limits: ToolTimeLimits,
 }
 
 impl IOITask {
@@ -223,11 +303,19 @@ impl IOITask {
             testcases: Default::default(),
             input_validator_generator: Default::default(),
             testcase_score_aggregator: TestcaseScoreAggregator::Min,
-            score_precision: 0,
+            score_precision: None,
+            score_rounding: ScoreRounding::Nearest,
             grader_map: Arc::new(GraderMap::new::<&Path>(vec![])),
             booklets: vec![],
             difficulty: None,
             syllabus_level: None,
+            attachment_packages: false,
+            container_image: None,
+            forbidden_constructs: vec![],
+            required_resources: ResourceTags::new(),
+            quick_testcases: None,
+            official_solution_override: None,
+            checker_messages: Default::default(),
             sanity_checks: Arc::new(Default::default()),
         }
     }
@@ -266,36 +354,136 @@ impl IOITask {
         eval: &mut EvaluationData,
         config: &EvaluationConfig,
     ) -> Result<(), Error> {
+        if !config.subtask_filter.is_empty()
+            || !config.testcase_filter.is_empty()
+            || !config.tag_filter.is_empty()
+        {
+            self.retain_testcases(
+                &config.subtask_filter,
+                &config.testcase_filter,
+                &config.tag_filter,
+            );
+            if self.testcases.is_empty() {
+                bail!(Diagnostic::error(
+                    "No testcase matches the subtask, testcase and tag filters"
+                )
+                .with_code(DiagnosticCode::NoTestcaseSelected));
+            }
+            // the task is not complete anymore, the sanity checks and the booklets would be wrong
+            self.sanity_checks = Arc::new(Default::default());
+            self.booklets.clear();
+        }
+        if config.quick {
+            self.quick_testcases = Some(self.representative_testcases());
+        }
         eval.sender.send(UIMessage::IOITask {
             task: Box::new(self.clone()),
         })?;
+        if let Some(testcases) = &self.quick_testcases {
+            eval.sender.add_diagnostic(
+                Diagnostic::info(format!(
+                    "The solutions are evaluated only on {} of the {} testcases, their scores are \
+                    partial",
+                    testcases.len(),
+                    self.testcases.len()
+                ))
+                .with_category(DiagnosticCategory::Scoring)
+                .with_help("Evaluate the task without --quick for the full scores"),
+            )?;
+        }
+        if let Some(solution) = &config.official_solution {
+            eval.sender.add_diagnostic(
+                Diagnostic::warning(format!(
+                    "The output files are generated by {} instead of the official solution of the \
+                    task",
+                    solution.display()
+                ))
+                .with_category(DiagnosticCategory::Generation),
+            )?;
+        }
+        eval.checker_score_policy = config.checker_score_policy;
+        eval.dag
+            .config_mut()
+            .container_image(self.container_image.clone());
+        self.check_batch_only_options(config)?;
+        if config.check_output_determinism {
+            eval.output_determinism = Some(Default::default());
+        }
+        if config.sanitize {
+            eval.sanitizer = Some(Default::default());
+        }
+        if let Some(tool) = config.profile {
+            eval.profiler = Some(Profiler::new(
+                tool,
+                self,
+                config.profile_all_testcases,
+                eval,
+            )?);
+        }
+        if let Some(runs) = config.seed_runs {
+            eval.seed_matrix = Some(Arc::new(Mutex::new(SeedMatrix::new(
+                runs,
+                config.seed_aggregation,
+            ))));
+        }
+        if config.skip_dependent_subtasks {
+            if !matches!(self.testcase_score_aggregator, TestcaseScoreAggregator::Min) {
+                eval.sender.add_diagnostic(
+                    Diagnostic::warning(
+                        "--skip-dependent-subtasks has no effect: the subtask scores are not the \
+                        minimum of the testcase scores",
+                    )
+                    .with_category(DiagnosticCategory::Scoring),
+                )?;
+            }
+        }
         eval.solutions = config.find_solutions(
             &self.path,
             vec!["sol/*"],
             Some(self.grader_map.clone()),
             eval,
         );
+        let duplicates = config.dedup_solutions.find_duplicates(&eval.solutions);
+        for solution in eval.solutions.iter() {
+            let path = &solution.source_file.path;
+            if let Some(original) = duplicates.get(path) {
+                eval.sender.add_diagnostic(
+                    Diagnostic::info(format!(
+                        "Solution {} has the same source as {}, it's evaluated only once",
+                        solution.source_file.name(),
+                        original.file_name().unwrap_or_default().to_string_lossy()
+                    ))
+                    .with_category(DiagnosticCategory::Solutions)
+                    .with_help("Use --dedup-solutions off for evaluating all the solutions"),
+                )?;
+            }
+        }
 
         let solutions: Vec<_> = eval
             .solutions
             .clone()
             .into_iter()
+            .filter(|source| !duplicates.contains_key(&source.source_file.path))
             .map(|source| {
                 let path = source.source_file.path.clone();
-                Ok((
-                    source,
-                    Arc::new(Mutex::new(ScoreManager::new(
-                        self,
-                        path,
-                        eval.sender.clone(),
-                    )?)),
-                ))
+                let mut score_manager = ScoreManager::new(self, path, eval.sender.clone())?;
+                if config.skip_dependent_subtasks {
+                    score_manager.skip_dependent_subtasks(self, eval.dag.skipper());
+                }
+                if let Some(testcases) = &self.quick_testcases {
+                    score_manager.score_only(testcases);
+                }
+                Ok((source, Arc::new(Mutex::new(score_manager))))
             })
             .collect::<Result<_, Error>>()?;
 
-        let solution_info = solutions
+        let solution_info = eval
+            .solutions
             .iter()
-            .map(|(solution, _)| SolutionInfo::from(solution))
+            .map(|solution| SolutionInfo {
+                duplicate_of: duplicates.get(&solution.source_file.path).cloned(),
+                ..SolutionInfo::from(solution)
+            })
             .collect_vec();
         eval.sender.send(UIMessage::Solutions {
             solutions: solution_info,
@@ -321,9 +509,29 @@ impl IOITask {
                     .testcases
                     .get(&testcase_id)
                     .expect("Testcase not found in the task");
+                if !self.is_evaluated(testcase.id) {
+                    // the testcase is not used by --quick, don't even generate it
+                    eval.sender.send(UIMessage::IOIGeneration {
+                        subtask: subtask.id,
+                        testcase: testcase.id,
+                        status: UIExecutionStatus::Skipped,
+                    })?;
+                    for (solution, _) in solutions.iter() {
+                        eval.sender.send(UIMessage::IOIEvaluation {
+                            subtask: subtask.id,
+                            testcase: testcase.id,
+                            solution: solution.source_file.path.clone(),
+                            status: UIExecutionStatus::Skipped,
+                            part: 0,
+                            num_parts: 1,
+                        })?;
+                    }
+                    continue;
+                }
+                let limits = &testcase.time_limits;
                 let input = testcase
                     .input_generator
-                    .generate_and_bind(eval, subtask.id, testcase.id)
+                    .generate_and_bind(eval, subtask.id, testcase.id, limits.generation)
                     .context("Failed to bind input generator")?;
                 let val_handle = subtask
                     .input_validator
@@ -333,11 +541,20 @@ impl IOITask {
                         subtask.name.as_deref(),
                         testcase.id,
                         input,
+                        limits.validation,
                     )
                     .context("Failed to bind validator")?;
                 let output = testcase
                     .output_generator
-                    .generate_and_bind(self, eval, subtask.id, testcase.id, input, val_handle)
+                    .generate_and_bind(
+                        self,
+                        eval,
+                        subtask.id,
+                        testcase.id,
+                        input,
+                        val_handle,
+                        limits.output_generation,
+                    )
                     .context("Failed to bind output generator")?;
                 // Store the generated input and output files for setting them into the task
                 // outside the loop.
@@ -353,15 +570,17 @@ impl IOITask {
 
                     self.task_type
                         .evaluate(
-                            self,
                             eval,
-                            subtask.id,
-                            testcase.id,
-                            &solution.source_file,
-                            input,
-                            val_handle,
-                            output,
-                            score_manager.clone(),
+                            TestcaseEvaluation {
+                                task: self,
+                                subtask_id: subtask.id,
+                                testcase_id: testcase.id,
+                                source_file: &solution.source_file,
+                                input,
+                                validation_handle: val_handle,
+                                correct_output: output,
+                                score_manager: score_manager.clone(),
+                            },
                         )
                         .context("Failed to bind evaluation")?;
                 }
@@ -382,6 +601,10 @@ impl IOITask {
                         .testcases
                         .get(&testcase_id)
                         .expect("Testcase not found in the task");
+                    let Some(input) = testcase.input_file else {
+                        // not generated because of --quick
+                        continue;
+                    };
 
                     let _val_handle = subtask
                         .input_validator
@@ -390,7 +613,8 @@ impl IOITask {
                             subtask.id,
                             subtask.name.as_deref(),
                             testcase.id,
-                            testcase.input_file.unwrap(),
+                            input,
+                            testcase.time_limits.validation,
                         )
                         .context("Failed to bind validator")?;
                 }
@@ -401,15 +625,128 @@ impl IOITask {
                 .build(eval)
                 .context("Failed to bind booklet compilation")?;
         }
+        if self.attachment_packages {
+            for package in AttachmentPackage::find(self)? {
+                package
+                    .build(self, eval)
+                    .context("Failed to bind attachment packaging")?;
+            }
+        }
         self.sanity_checks
             .pre_hook(self, eval)
             .context("Sanity check pre-hooks failed")?;
+        if !self.required_resources.is_empty() {
+            for group in eval.dag.data.execution_groups.values_mut() {
+                for exec in group.executions.iter_mut() {
+                    exec.required_resources
+                        .extend(self.required_resources.iter().cloned());
+                }
+            }
+        }
         Ok(())
     }
 
+    /// The testcases on which the solutions are evaluated with `--quick`: the first testcase of
+    /// each subtask and the largest testcase of the task.
+    ///
+    /// The size of the generated inputs is not known before the evaluation, so the largest
+    /// testcase is the one with the largest input file only when all the inputs are static files;
+    /// otherwise it's the last testcase of the task, since the testcases are usually sorted by
+    /// increasing size.
+    pub fn representative_testcases(&self) -> HashSet<TestcaseId> {
+        let mut testcases: HashSet<_> = self
+            .subtasks
+            .values()
+            .filter_map(|subtask| subtask.testcases.iter().min().copied())
+            .collect();
+        let input_sizes: Option<Vec<_>> = self
+            .testcases
+            .values()
+            .map(|testcase| match &testcase.input_generator {
+                InputGenerator::StaticFile(path) => {
+                    Some((path.metadata().ok()?.len(), testcase.id))
+                }
+                _ => None,
+            })
+            .collect();
+        let largest = match input_sizes {
+            Some(sizes) => sizes.into_iter().max().map(|(_, id)| id),
+            None => self.testcases.keys().max().copied(),
+        };
+        testcases.extend(largest);
+        testcases
+    }
+
+    /// Whether the solutions are evaluated on the testcase, i.e. it's not skipped by `--quick`.
+    pub fn is_evaluated(&self, testcase: TestcaseId) -> bool {
+        match &self.quick_testcases {
+            Some(testcases) => testcases.contains(&testcase),
+            None => true,
+        }
+    }
+
+    /// Keep only the testcases selected by the filters, removing the subtasks left without
+    /// testcases. An empty filter selects everything, the tag filter selects the testcases with at
+    /// least one of its tags.
+    ///
+    /// The testcases owned by a removed subtask but used by a remaining one become owned by the
+    /// first remaining subtask using them, so that they are still generated.
+    pub fn retain_testcases(
+        &mut self,
+        subtask_filter: &[SubtaskId],
+        testcase_filter: &[TestcaseId],
+        tag_filter: &[String],
+    ) {
+        let testcases = &self.testcases;
+        let is_selected = |subtask: SubtaskId, testcase: TestcaseId| {
+            (subtask_filter.is_empty() || subtask_filter.contains(&subtask))
+                && (testcase_filter.is_empty() || testcase_filter.contains(&testcase))
+                && (tag_filter.is_empty()
+                    || testcases.get(&testcase).map_or(false, |tc| {
+                        tc.tags.iter().any(|tag| tag_filter.contains(tag))
+                    }))
+        };
+        for subtask in self.subtasks.values_mut() {
+            let id = subtask.id;
+            subtask.testcases.retain(|&tc| is_selected(id, tc));
+            subtask.testcases_owned.retain(|&tc| is_selected(id, tc));
+        }
+        self.subtasks
+            .retain(|_, subtask| !subtask.testcases.is_empty());
+
+        let remaining: HashSet<SubtaskId> = self.subtasks.keys().copied().collect();
+        let used: HashSet<TestcaseId> = self
+            .subtasks
+            .values()
+            .flat_map(|subtask| subtask.testcases.iter().copied())
+            .collect();
+        self.testcases.retain(|id, _| used.contains(id));
+
+        let mut owned: HashSet<TestcaseId> = self
+            .subtasks
+            .values()
+            .flat_map(|subtask| subtask.testcases_owned.iter().copied())
+            .collect();
+        for subtask_id in remaining.iter().sorted() {
+            let subtask = self.subtasks.get_mut(subtask_id).unwrap();
+            subtask.dependencies.retain(|dep| remaining.contains(dep));
+            for &testcase in &subtask.testcases {
+                if owned.insert(testcase) {
+                    subtask.testcases_owned.push(testcase);
+                }
+            }
+        }
+    }
+
     /// Hook called after the execution completed, useful for sending messages to the UI about the
     /// results of the sanity checks with data available only after the evaluation.
     pub fn sanity_check_post_hook(&self, eval: &mut EvaluationData) -> Result<(), Error> {
+        if let Some(output_determinism) = &eval.output_determinism {
+            output_determinism
+                .lock()
+                .unwrap()
+                .report(&self.path, eval)?;
+        }
         self.sanity_checks.post_hook(self, eval)
     }
 
@@ -464,8 +801,10 @@ impl IOITask {
             })?;
         }
         // remove the compiled checkers
-        if let TaskType::Batch(data) = &self.task_type {
-            if let Checker::Custom(_) = data.checker {
+        if let TaskType::Batch(BatchTypeData { checker, .. })
+        | TaskType::TwoSteps(TwoStepsTypeData { checker }) = &self.task_type
+        {
+            if let Checker::Custom(_) = checker {
                 for checker in &["check/checker", "cor/correttore"] {
                     let path = self.path.join(checker);
                     if path.exists() {
@@ -503,6 +842,31 @@ impl IOITask {
         ))
     }
 
+    /// Fail if the configuration enables an option that is supported only by Batch tasks and this
+    /// task is not of that type.
+    fn check_batch_only_options(&self, config: &EvaluationConfig) -> Result<(), Error> {
+        if matches!(self.task_type, TaskType::Batch(_)) {
+            return Ok(());
+        }
+        let options = [
+            (
+                "--check-output-determinism",
+                config.check_output_determinism,
+            ),
+            ("--sanitize", config.sanitize),
+            ("--profile", config.profile.is_some()),
+            ("--seed-runs", config.seed_runs.is_some()),
+            ("--skip-dependent-subtasks", config.skip_dependent_subtasks),
+        ];
+        if let Some((option, _)) = options.iter().find(|(_, enabled)| *enabled) {
+            bail!(
+                Diagnostic::error(format!("{} is supported only by Batch tasks", option))
+                    .with_code(DiagnosticCode::UnsupportedOption)
+            );
+        }
+        Ok(())
+    }
+
     /// Find the list of all the subtasks that match the given pattern.
     fn find_subtasks_by_pattern_name(&self, pattern: impl AsRef<str>) -> Vec<&SubtaskInfo> {
         // Normalize the pattern; the subtask names are already normalized.
@@ -542,6 +906,8 @@ impl TestcaseInfo {
             output_generator,
             input_file: None,
             official_output_file: None,
+            tags: vec![],
+            time_limits: Default::default(),
         }
     }
 }
@@ -568,6 +934,10 @@ impl ScoreManager {
                 .map(|(st_num, st)| (*st_num, st.testcases.clone()))
                 .collect(),
             aggregator: task.testcase_score_aggregator,
+            precision: task.score_precision,
+            rounding: task.score_rounding,
+            dependent_subtasks: None,
+            checker_messages: task.checker_messages.clone(),
         };
 
         for (st_num, st) in &task.subtasks {
@@ -578,6 +948,73 @@ impl ScoreManager {
         Ok(ret)
     }
 
+    /// Skip the evaluations of the testcases that cannot change the score anymore: when the
+    /// solution scores zero on a testcase, the subtasks that include it from one of their
+    /// dependencies are worth zero, and the testcases included only by such subtasks are skipped.
+    ///
+    /// The evaluations have to be registered with `add_evaluation`. Since a single testcase worth
+    /// zero makes the subtask worth zero only if the subtask score is the minimum of its testcases,
+    /// nothing is skipped with the other aggregators.
+    pub fn skip_dependent_subtasks(&mut self, task: &IOITask, skipper: ExecutionSkipper) {
+        self.dependent_subtasks = Some(DependentSubtasks {
+            skipper,
+            owned_testcases: task
+                .subtasks
+                .iter()
+                .map(|(id, st)| (*id, st.testcases_owned.iter().copied().collect()))
+                .collect(),
+            evaluations: HashMap::new(),
+            failed_subtasks: HashSet::new(),
+            skipped_testcases: HashSet::new(),
+        });
+    }
+
+    /// Score the subtasks only on their testcases among `testcases`, the ones evaluated by
+    /// `--quick`, extrapolating the verdict of the subtask from them. The other testcases are never
+    /// scored.
+    pub fn score_only(&mut self, testcases: &HashSet<TestcaseId>) {
+        for subtask_testcases in self.subtask_testcases.values_mut() {
+            subtask_testcases.retain(|testcase| testcases.contains(testcase));
+        }
+    }
+
+    /// Register an execution of the solution on a testcase, to be skipped if the testcase cannot
+    /// change the score anymore.
+    pub fn add_evaluation(&mut self, testcase_id: TestcaseId, execution: ExecutionUuid) {
+        if let Some(dependent_subtasks) = &mut self.dependent_subtasks {
+            dependent_subtasks
+                .evaluations
+                .entry(testcase_id)
+                .or_default()
+                .push(execution);
+        }
+    }
+
+    /// Score zero a testcase whose evaluation has been skipped by `skip_dependent_subtasks`, so
+    /// that the score of the solution is computed anyway. The evaluations skipped for other
+    /// reasons (e.g. a failed generation) are not scored.
+    pub fn evaluation_skipped(
+        &mut self,
+        subtask_id: SubtaskId,
+        testcase_id: TestcaseId,
+        sender: Arc<Mutex<UIMessageSender>>,
+    ) -> Result<(), Error> {
+        let skipped = match &self.dependent_subtasks {
+            Some(dependent_subtasks) => dependent_subtasks.skipped_testcases.contains(&testcase_id),
+            None => false,
+        };
+        if !skipped || self.testcase_scores[&testcase_id].is_some() {
+            return Ok(());
+        }
+        self.score(
+            subtask_id,
+            testcase_id,
+            0.0,
+            "Skipped since a dependency of the subtask failed".into(),
+            sender,
+        )
+    }
+
     /// Store the score of the testcase and eventually compute the score of the subtask and of the
     /// task.
     pub fn score(
@@ -589,13 +1026,16 @@ impl ScoreManager {
         sender: Arc<Mutex<UIMessageSender>>,
     ) -> Result<(), Error> {
         self.testcase_scores.insert(testcase_id, Some(score));
+        let verdict = self.checker_messages.verdict(&message);
         sender.send(UIMessage::IOITestcaseScore {
             subtask: subtask_id,
             testcase: testcase_id,
             solution: self.solution.clone(),
             score,
             message,
+            verdict,
         })?;
+        self.skip_failed_testcases(testcase_id, score);
 
         for (subtask_id, subtask) in self
             .subtask_scores
@@ -610,6 +1050,42 @@ impl ScoreManager {
         Ok(())
     }
 
+    /// After the score of a testcase, skip the testcases whose subtasks have all failed because of
+    /// a dependency.
+    fn skip_failed_testcases(&mut self, testcase_id: TestcaseId, score: f64) {
+        let Some(dependent_subtasks) = &mut self.dependent_subtasks else {
+            return;
+        };
+        if score > 0.0 || !matches!(self.aggregator, TestcaseScoreAggregator::Min) {
+            return;
+        }
+        for (subtask_id, testcases) in &self.subtask_testcases {
+            if testcases.contains(&testcase_id)
+                && !dependent_subtasks.owned_testcases[subtask_id].contains(&testcase_id)
+            {
+                dependent_subtasks.failed_subtasks.insert(*subtask_id);
+            }
+        }
+        for (testcase_id, evaluations) in &dependent_subtasks.evaluations {
+            if self.testcase_scores[testcase_id].is_some()
+                || dependent_subtasks.skipped_testcases.contains(testcase_id)
+            {
+                continue;
+            }
+            let useless = self
+                .subtask_testcases
+                .iter()
+                .filter(|(_, testcases)| testcases.contains(testcase_id))
+                .all(|(subtask_id, _)| dependent_subtasks.failed_subtasks.contains(subtask_id));
+            if useless {
+                dependent_subtasks.skipped_testcases.insert(*testcase_id);
+                for execution in evaluations {
+                    dependent_subtasks.skipper.skip(*execution);
+                }
+            }
+        }
+    }
+
     fn score_subtask(
         &mut self,
         subtask_id: SubtaskId,
@@ -633,11 +1109,14 @@ impl ScoreManager {
                 normalized_score,
             })?;
             if self.subtask_scores.values().all(Option::is_some) {
-                let task_score: f64 = self
+                let mut task_score: f64 = self
                     .subtask_scores
                     .values()
                     .map(|score| score.unwrap())
                     .sum();
+                if let Some(precision) = self.precision {
+                    task_score = self.rounding.round(task_score, precision);
+                }
                 sender.send(UIMessage::IOITaskScore {
                     solution: self.solution.clone(),
                     score: task_score,
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Error;
+use itertools::Itertools;
+
+use task_maker_dag::FileUuid;
+use task_maker_diagnostics::{Diagnostic, DiagnosticCategory, DiagnosticCode};
+
+use crate::ioi::{Checker, TestcaseId};
+use crate::EvaluationData;
+
+/// Compare the output files of the accepted solutions, warning when different outputs are
+/// accepted on the same testcase. In tasks where the expected output is unique this usually means
+/// that the checker is too permissive, or that the statement is underspecified.
+///
+/// The output of each solution is compared with the expected output of the testcase by an
+/// execution of the white diff checker, so the differences in the whitespaces are ignored.
+#[derive(Debug, Default)]
+pub struct OutputDeterminism {
+    /// The outputs of the solutions, for each testcase.
+    outputs: HashMap<TestcaseId, HashMap<PathBuf, SolutionOutput>>,
+}
+
+/// What is known about the output of a solution on a testcase.
+#[derive(Debug, Default)]
+struct SolutionOutput {
+    /// Whether the output file is equal to the expected one, if they have been compared.
+    same_as_expected: Option<bool>,
+    /// The score given by the checker, if it has been run.
+    score: Option<f64>,
+}
+
+impl OutputDeterminism {
+    /// Add to the DAG the comparison of the output file produced by `solution` on a testcase with
+    /// the expected output of the testcase.
+    pub(crate) fn bind(
+        state: Arc<Mutex<OutputDeterminism>>,
+        eval: &mut EvaluationData,
+        testcase: TestcaseId,
+        solution: PathBuf,
+        input: FileUuid,
+        correct_output: FileUuid,
+        test_output: FileUuid,
+    ) -> Result<(), Error> {
+        let description = format!(
+            "Comparing the output of {:?} of testcase {} with the expected one",
+            solution.file_name().unwrap_or_default(),
+            testcase
+        );
+        let exec = Checker::WhiteDiff.check(
+            eval,
+            Some(testcase),
+            description,
+            input,
+            correct_output,
+            test_output,
+            move |score, _| {
+                state
+                    .lock()
+                    .unwrap()
+                    .entry(testcase, solution)
+                    .same_as_expected = Some(score == 1.0);
+                Ok(())
+            },
+        )?;
+        eval.dag.add_execution(exec);
+        Ok(())
+    }
+
+    /// Store the score given by the checker to the output of `solution` on a testcase.
+    pub(crate) fn checked(&mut self, testcase: TestcaseId, solution: PathBuf, score: f64) {
+        self.entry(testcase, solution).score = Some(score);
+    }
+
+    /// Emit a warning for each testcase where some accepted solutions produced an output different
+    /// from the expected one.
+    pub(crate) fn report(&self, task_path: &Path, eval: &EvaluationData) -> Result<(), Error> {
+        for (testcase, outputs) in self.outputs.iter().sorted_by_key(|(tc, _)| **tc) {
+            let different = outputs
+                .iter()
+                .filter(|(_, output)| matches!(output.score, Some(score) if score >= 1.0))
+                .filter(|(_, output)| output.same_as_expected == Some(false))
+                .map(|(solution, _)| {
+                    let name = solution.strip_prefix(task_path).unwrap_or(solution);
+                    name.display().to_string()
+                })
+                .sorted()
+                .join(", ");
+            if different.is_empty() {
+                continue;
+            }
+            eval.add_diagnostic(
+                Diagnostic::warning(format!(
+                    "The accepted solutions produced different outputs on testcase {}",
+                    testcase
+                ))
+                .with_code(DiagnosticCode::NonDeterministicOutput)
+                .with_category(DiagnosticCategory::Solutions)
+                .with_note(format!(
+                    "The outputs of these solutions are different from the expected one: {}",
+                    different
+                ))
+                .with_help(
+                    "If the expected output is unique the checker may be too permissive, or the \
+                    statement may be underspecified",
+                ),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn entry(&mut self, testcase: TestcaseId, solution: PathBuf) -> &mut SolutionOutput {
+        self.outputs
+            .entry(testcase)
+            .or_default()
+            .entry(solution)
+            .or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::UIMessage;
+
+    use super::*;
+
+    fn report(state: &OutputDeterminism) -> Vec<String> {
+        let (eval, receiver) = EvaluationData::new("/task");
+        state.report(Path::new("/task"), &eval).unwrap();
+        receiver
+            .try_iter()
+            .map(|message| match message {
+                UIMessage::Diagnostic { diagnostic } => diagnostic.to_string(),
+                _ => panic!("Unexpected message: {:?}", message),
+            })
+            .collect()
+    }
+
+    fn output(state: &mut OutputDeterminism, tc: TestcaseId, sol: &str, same: bool, score: f64) {
+        let output = state.entry(tc, Path::new("/task/sol").join(sol));
+        output.same_as_expected = Some(same);
+        output.score = Some(score);
+    }
+
+    #[test]
+    fn test_report_different_accepted_outputs() {
+        let mut state = OutputDeterminism::default();
+        output(&mut state, 0, "sol.cpp", true, 1.0);
+        output(&mut state, 0, "other.cpp", false, 1.0);
+        output(&mut state, 0, "another.py", false, 1.0);
+        let warnings = report(&state);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("on testcase 0"));
+        assert!(warnings[0].contains("sol/another.py, sol/other.cpp"));
+        assert!(!warnings[0].contains("sol/sol.cpp"));
+    }
+
+    #[test]
+    fn test_report_ignores_not_accepted() {
+        let mut state = OutputDeterminism::default();
+        output(&mut state, 0, "sol.cpp", true, 1.0);
+        output(&mut state, 0, "wrong.cpp", false, 0.0);
+        output(&mut state, 1, "sol.cpp", true, 1.0);
+        // the solution failed, so its output has not been checked
+        state
+            .entry(1, "/task/sol/crash.cpp".into())
+            .same_as_expected = Some(false);
+        assert!(report(&state).is_empty());
+    }
+}
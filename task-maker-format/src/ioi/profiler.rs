@@ -0,0 +1,498 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Error};
+use itertools::Itertools;
+
+use task_maker_dag::{ExecutionCommand, FileUuid, Priority};
+use task_maker_diagnostics::{Diagnostic, DiagnosticCategory};
+
+use crate::ioi::{IOITask, TestcaseId, STDERR_CONTENT_LENGTH};
+use crate::ui::format_size;
+use crate::{bind_exec_io, EvaluationData, SourceFile, Tag};
+
+/// How much slower than the normal ones the profiled executions are allowed to be. Valgrind is
+/// very slow.
+const PROFILER_TIME_FACTOR: f64 = 50.0;
+/// Maximum number of bytes of the output of massif that are parsed.
+const MASSIF_CONTENT_LENGTH: usize = 64 * 1024 * 1024;
+/// Maximum number of bytes of the output of `time -v` that are parsed.
+const TIME_CONTENT_LENGTH: usize = 64 * 1024;
+/// Number of allocation sites listed in the report of massif.
+const TOP_ALLOCATION_SITES: usize = 5;
+
+/// The tool used for measuring the memory usage of the solutions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilerTool {
+    /// Valgrind's massif, which records where the heap memory is allocated.
+    Massif,
+    /// `/usr/bin/time -v`, which measures only the peak resident memory.
+    Time,
+}
+
+impl FromStr for ProfilerTool {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "massif" => Ok(ProfilerTool::Massif),
+            "time" => Ok(ProfilerTool::Time),
+            _ => Err(format!("Unknown profiler: {}", s)),
+        }
+    }
+}
+
+/// Run the solutions also under a memory profiler on the last testcase, or on each evaluated
+/// testcase, reporting their peak memory usage. These executions don't affect the scores of the
+/// solutions.
+///
+/// With massif the output of the profiler is written in `bin/profiles/`, together with the heap of
+/// the peak in the folded stack format used by the flame graph tools:
+/// `bin/profiles/sol/name.cpp.testcase3.massif` and `bin/profiles/sol/name.cpp.testcase3.folded`.
+#[derive(Debug)]
+pub struct Profiler {
+    /// The tool used for profiling.
+    tool: ProfilerTool,
+    /// The only testcase on which the solutions are profiled, `None` for all of them.
+    testcase: Option<TestcaseId>,
+}
+
+impl Profiler {
+    /// Make a new profiler that uses `tool`, falling back to `time` if valgrind is not installed.
+    /// Unless `all_testcases` is set, the solutions are profiled only on the last testcase of the
+    /// task, which is usually the largest one.
+    pub(crate) fn new(
+        tool: ProfilerTool,
+        task: &IOITask,
+        all_testcases: bool,
+        eval: &mut EvaluationData,
+    ) -> Result<Profiler, Error> {
+        let testcase = if all_testcases {
+            None
+        } else {
+            task.testcases.keys().max().copied()
+        };
+        if tool == ProfilerTool::Massif && which::which("valgrind").is_err() {
+            let message =
+                "valgrind is not installed, the memory is measured with /usr/bin/time -v instead";
+            eval.sender.add_diagnostic(
+                Diagnostic::warning(message).with_category(DiagnosticCategory::Solutions),
+            )?;
+            return Ok(Profiler {
+                tool: ProfilerTool::Time,
+                testcase,
+            });
+        }
+        Ok(Profiler { tool, testcase })
+    }
+
+    /// Add to the DAG the profiled execution of the solution on a testcase.
+    pub(crate) fn evaluate(
+        eval: &mut EvaluationData,
+        task: &IOITask,
+        testcase_id: TestcaseId,
+        source_file: &SourceFile,
+        input: FileUuid,
+        validation_handle: Option<FileUuid>,
+    ) -> Result<(), Error> {
+        let Some(profiler) = eval.profiler.as_ref() else {
+            return Ok(());
+        };
+        if matches!(profiler.testcase, Some(testcase) if testcase != testcase_id) {
+            return Ok(());
+        }
+        let tool = profiler.tool;
+        let mut exec = source_file
+            .execute(
+                eval,
+                format!(
+                    "Profiling of {} on testcase {}",
+                    source_file.name(),
+                    testcase_id
+                ),
+                Vec::<String>::new(),
+            )
+            .context("Failed to execute the profiled solution")?;
+        exec.tag(Tag::Evaluation.into());
+        // the profiled executions are run after the others
+        exec.priority(-(testcase_id as Priority));
+        exec.capture_stderr(STDERR_CONTENT_LENGTH);
+        bind_exec_io!(exec, task, input, validation_handle);
+        let report = match tool {
+            ProfilerTool::Massif => {
+                exec.wrap_command(
+                    ExecutionCommand::system("valgrind"),
+                    ["--tool=massif", "--massif-out-file=massif.out", "--"],
+                );
+                exec.output("massif.out")
+            }
+            ProfilerTool::Time => {
+                exec.wrap_command(
+                    ExecutionCommand::system("/usr/bin/time"),
+                    ["-v", "-o", "time.txt"],
+                );
+                exec.output("time.txt")
+            }
+        };
+        let limits = exec.limits_mut();
+        if let Some(time_limit) = task.time_limit {
+            limits.cpu_time(time_limit * PROFILER_TIME_FACTOR);
+            limits.wall_time(time_limit * PROFILER_TIME_FACTOR * 1.5 + 1.0);
+        }
+        // the profilers spawn processes, use much more memory and write their report in the sandbox
+        limits
+            .allow_multiprocess()
+            .read_only(false)
+            .mount_tmpfs(true)
+            .mount_proc(true);
+
+        let name = source_file.relative_path().to_owned();
+        let sender = eval.sender.clone();
+        let name_clone = name.clone();
+        eval.dag.on_execution_done(&exec.uuid, move |result| {
+            if !result.status.is_success() {
+                sender.add_diagnostic(
+                    Diagnostic::warning(format!(
+                        "The profiled execution of {} on testcase {} failed: {:?}",
+                        name_clone.display(),
+                        testcase_id,
+                        result.status
+                    ))
+                    .with_category(DiagnosticCategory::Solutions)
+                    .with_help_attachment(result.stderr.unwrap_or_default()),
+                )?;
+            }
+            Ok(())
+        });
+
+        let sender = eval.sender.clone();
+        match tool {
+            ProfilerTool::Massif => {
+                let massif_path = profile_path(task, &name, testcase_id, "massif");
+                let folded_path = profile_path(task, &name, testcase_id, "folded");
+                let dry_run = eval.dag.data.config.dry_run;
+                eval.dag
+                    .write_file_to_allow_fail(&report, &massif_path, false);
+                eval.dag
+                    .get_file_content(&report, MASSIF_CONTENT_LENGTH, move |content| {
+                        let content = String::from_utf8_lossy(&content);
+                        let Some(peak) = MassifPeak::parse(&content) else {
+                            return Ok(());
+                        };
+                        if !dry_run {
+                            if let Some(tree) = &peak.tree {
+                                if let Some(parent) = folded_path.parent() {
+                                    std::fs::create_dir_all(parent).with_context(|| {
+                                        format!("Failed to create {}", parent.display())
+                                    })?;
+                                }
+                                std::fs::write(&folded_path, folded_stacks(tree)).with_context(
+                                    || format!("Failed to write {}", folded_path.display()),
+                                )?;
+                            }
+                        }
+                        let mut diagnostic = Diagnostic::info(format!(
+                            "Peak heap usage of {} on testcase {}: {} (+{} of allocator overhead)",
+                            name.display(),
+                            testcase_id,
+                            format_size(peak.heap_bytes),
+                            format_size(peak.extra_bytes)
+                        ))
+                        .with_help(format!("The full profile is in {}", massif_path.display()));
+                        if let Some(tree) = &peak.tree {
+                            diagnostic = diagnostic
+                                .with_note(top_allocation_sites(tree))
+                                .with_help_attachment(peak.tree_text.into_bytes());
+                        }
+                        sender.add_diagnostic(diagnostic)?;
+                        Ok(())
+                    });
+            }
+            ProfilerTool::Time => {
+                eval.dag
+                    .get_file_content(&report, TIME_CONTENT_LENGTH, move |content| {
+                        let content = String::from_utf8_lossy(&content);
+                        if let Some(kib) = parse_max_rss(&content) {
+                            sender.add_diagnostic(Diagnostic::info(format!(
+                                "Peak memory usage of {} on testcase {}: {}",
+                                name.display(),
+                                testcase_id,
+                                format_size(kib * 1024)
+                            )))?;
+                        }
+                        Ok(())
+                    });
+            }
+        }
+        eval.dag.add_execution(exec);
+        Ok(())
+    }
+}
+
+/// The path of a file with the profile of a solution on a testcase, inside `bin/profiles/` at the
+/// same relative path of the solution.
+fn profile_path(
+    task: &IOITask,
+    solution: &Path,
+    testcase_id: TestcaseId,
+    extension: &str,
+) -> PathBuf {
+    let name = solution.file_name().unwrap_or_default();
+    // the solutions outside the task are placed directly in the folder of the profiles
+    let solution = if solution.is_absolute() {
+        Path::new(name)
+    } else {
+        solution
+    };
+    let name = name.to_string_lossy();
+    task.path
+        .join("bin")
+        .join("profiles")
+        .join(solution)
+        .with_file_name(format!("{}.testcase{}.{}", name, testcase_id, extension))
+}
+
+/// A node of the heap tree of a massif snapshot: the memory allocated from a code location.
+#[derive(Debug, Clone, PartialEq)]
+struct HeapNode {
+    /// The number of bytes allocated from here, including the children.
+    bytes: u64,
+    /// The function and the position in the source code, without the address.
+    label: String,
+    /// The functions called from here that allocated the memory.
+    children: Vec<HeapNode>,
+}
+
+/// The snapshot of the peak memory usage in the output of massif.
+#[derive(Debug, Clone, PartialEq)]
+struct MassifPeak {
+    /// The bytes allocated in the heap.
+    heap_bytes: u64,
+    /// The bytes of overhead of the allocator.
+    extra_bytes: u64,
+    /// The heap tree of the snapshot, if it's detailed.
+    tree: Option<HeapNode>,
+    /// The heap tree as written by massif.
+    tree_text: String,
+}
+
+impl MassifPeak {
+    /// Find the peak snapshot in the output of massif: the one marked as the peak or, if none is
+    /// marked, the one with the most allocated bytes.
+    fn parse(content: &str) -> Option<MassifPeak> {
+        let mut snapshots = vec![];
+        let mut lines = content.lines().peekable();
+        while let Some(line) = lines.next() {
+            if line.starts_with("snapshot=") {
+                snapshots.push((
+                    false,
+                    MassifPeak {
+                        heap_bytes: 0,
+                        extra_bytes: 0,
+                        tree: None,
+                        tree_text: String::new(),
+                    },
+                ));
+                continue;
+            }
+            let Some((is_peak, snapshot)) = snapshots.last_mut() else {
+                continue;
+            };
+            if let Some(bytes) = line.strip_prefix("mem_heap_B=") {
+                snapshot.heap_bytes = bytes.trim().parse().ok()?;
+            } else if let Some(bytes) = line.strip_prefix("mem_heap_extra_B=") {
+                snapshot.extra_bytes = bytes.trim().parse().ok()?;
+            } else if let Some(kind) = line.strip_prefix("heap_tree=") {
+                *is_peak = kind.trim() == "peak";
+                while let Some(line) = lines.next_if(|line| line.trim_start().starts_with('n')) {
+                    snapshot.tree_text += line;
+                    snapshot.tree_text += "\n";
+                }
+                snapshot.tree = parse_heap_tree(&mut snapshot.tree_text.lines());
+            }
+        }
+        if let Some(index) = snapshots.iter().position(|(is_peak, _)| *is_peak) {
+            return Some(snapshots.swap_remove(index).1);
+        }
+        snapshots
+            .into_iter()
+            .map(|(_, snapshot)| snapshot)
+            .max_by_key(|snapshot| snapshot.heap_bytes)
+    }
+}
+
+/// Parse a node of a heap tree of massif, with all its children. Each node is a line like
+/// `n2: 4000 0x10916B: foo() (sol.cpp:5)`, followed by its 2 children.
+fn parse_heap_tree<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Option<HeapNode> {
+    let line = lines.next()?.trim_start();
+    let (num_children, rest) = line.strip_prefix('n')?.split_once(": ")?;
+    let (bytes, label) = rest.split_once(' ').unwrap_or((rest, ""));
+    let num_children: usize = num_children.parse().ok()?;
+    let children = (0..num_children)
+        .map(|_| parse_heap_tree(lines))
+        .collect::<Option<Vec<_>>>()?;
+    // remove the address of the instruction, which changes at every compilation
+    let label = match label.split_once(": ") {
+        Some((address, rest)) if address.starts_with("0x") => rest,
+        _ => label,
+    };
+    Some(HeapNode {
+        bytes: bytes.parse().ok()?,
+        label: label.to_string(),
+        children,
+    })
+}
+
+/// Convert a heap tree to the folded stack format of the flame graph tools: a line for each stack
+/// with the frames separated by `;`, from the outermost, followed by the number of bytes
+/// allocated by the innermost frame. The root of the tree (the allocation functions) is omitted.
+fn folded_stacks(root: &HeapNode) -> String {
+    fn visit<'a>(node: &'a HeapNode, stack: &mut Vec<&'a str>, result: &mut String) {
+        stack.push(&node.label);
+        let children_bytes: u64 = node.children.iter().map(|child| child.bytes).sum();
+        let own_bytes = node.bytes.saturating_sub(children_bytes);
+        if own_bytes > 0 {
+            let frames = stack.iter().rev().map(|frame| frame.replace(';', ","));
+            *result += &format!("{} {}\n", frames.format(";"), own_bytes);
+        }
+        for child in &node.children {
+            visit(child, stack, result);
+        }
+        stack.pop();
+    }
+
+    let mut result = String::new();
+    for child in &root.children {
+        visit(child, &mut vec![], &mut result);
+    }
+    result
+}
+
+/// List the code locations that allocated the most memory directly.
+fn top_allocation_sites(root: &HeapNode) -> String {
+    root.children
+        .iter()
+        .sorted_by_key(|node| std::cmp::Reverse(node.bytes))
+        .take(TOP_ALLOCATION_SITES)
+        .map(|node| format!("{:>10} {}", format_size(node.bytes), node.label))
+        .join("\n")
+}
+
+/// Find the peak resident memory, in KiB, in the output of `time -v`.
+fn parse_max_rss(content: &str) -> Option<u64> {
+    content.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("Maximum resident set size (kbytes):")?
+            .trim()
+            .parse()
+            .ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASSIF_OUT: &str = "desc: --massif-out-file=massif.out
+cmd: ./sol
+time_unit: i
+#-----------
+snapshot=0
+#-----------
+time=0
+mem_heap_B=0
+mem_heap_extra_B=0
+mem_stacks_B=0
+heap_tree=empty
+#-----------
+snapshot=1
+#-----------
+time=1234
+mem_heap_B=4000
+mem_heap_extra_B=16
+mem_stacks_B=0
+heap_tree=peak
+n2: 4000 (heap allocation functions) malloc/new/new[], --alloc-fns, etc.
+ n1: 3000 0x10916B: foo() (sol.cpp:5)
+  n0: 3000 0x1091A0: main (sol.cpp:12)
+ n0: 1000 0x1091B5: main (sol.cpp:13)
+#-----------
+snapshot=2
+#-----------
+time=2000
+mem_heap_B=1000
+mem_heap_extra_B=8
+mem_stacks_B=0
+heap_tree=empty
+";
+
+    #[test]
+    fn test_massif_peak() {
+        let peak = MassifPeak::parse(MASSIF_OUT).unwrap();
+        assert_eq!(peak.heap_bytes, 4000);
+        assert_eq!(peak.extra_bytes, 16);
+        assert_eq!(peak.tree_text.lines().count(), 4);
+        let tree = peak.tree.unwrap();
+        assert_eq!(tree.bytes, 4000);
+        assert_eq!(tree.children.len(), 2);
+        assert_eq!(tree.children[0].label, "foo() (sol.cpp:5)");
+        assert_eq!(tree.children[0].children[0].label, "main (sol.cpp:12)");
+        assert_eq!(tree.children[1].bytes, 1000);
+    }
+
+    #[test]
+    fn test_massif_peak_not_marked() {
+        let content = MASSIF_OUT.replace("heap_tree=peak", "heap_tree=detailed");
+        let peak = MassifPeak::parse(&content).unwrap();
+        assert_eq!(peak.heap_bytes, 4000);
+        assert!(peak.tree.is_some());
+    }
+
+    #[test]
+    fn test_massif_peak_invalid() {
+        assert_eq!(MassifPeak::parse(""), None);
+        assert_eq!(MassifPeak::parse("snapshot=0\nmem_heap_B=lots\n"), None);
+    }
+
+    #[test]
+    fn test_folded_stacks() {
+        let tree = MassifPeak::parse(MASSIF_OUT).unwrap().tree.unwrap();
+        assert_eq!(
+            folded_stacks(&tree),
+            "main (sol.cpp:12);foo() (sol.cpp:5) 3000\nmain (sol.cpp:13) 1000\n"
+        );
+    }
+
+    #[test]
+    fn test_top_allocation_sites() {
+        let tree = MassifPeak::parse(MASSIF_OUT).unwrap().tree.unwrap();
+        let sites = top_allocation_sites(&tree);
+        let lines: Vec<_> = sites.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("2.9KiB foo() (sol.cpp:5)"));
+        assert!(lines[1].ends_with("1000B main (sol.cpp:13)"));
+    }
+
+    #[test]
+    fn test_parse_max_rss() {
+        let content = "\tCommand being timed: \"./sol\"\n\
+            \tUser time (seconds): 0.01\n\
+            \tMaximum resident set size (kbytes): 3456\n\
+            \tExit status: 0\n";
+        assert_eq!(parse_max_rss(content), Some(3456));
+        assert_eq!(
+            parse_max_rss("Command exited with non-zero status 1\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_profile_path() {
+        let mut task = IOITask::fake();
+        task.path = PathBuf::from("/task");
+        assert_eq!(
+            profile_path(&task, Path::new("sol/sol.cpp"), 3, "folded"),
+            Path::new("/task/bin/profiles/sol/sol.cpp.testcase3.folded")
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Error};
+
+use task_maker_dag::{FileUuid, Priority};
+use task_maker_diagnostics::{Diagnostic, DiagnosticCategory, DiagnosticCode};
+
+use crate::ioi::{IOITask, SubtaskId, TestcaseId, STDERR_CONTENT_LENGTH};
+use crate::{bind_exec_io, EvaluationData, SourceFile, Tag, UISender};
+
+/// How much slower than the normal ones the sanitized executions are allowed to be.
+const SANITIZER_TIME_FACTOR: f64 = 3.0;
+
+/// Run the C/C++ solutions compiled with the address and undefined behavior sanitizers on the first
+/// testcase of each subtask, warning about the errors found by the sanitizers. These executions
+/// don't affect the scores of the solutions.
+///
+/// The sanitized executables reserve a lot of virtual memory, so they are run without the memory
+/// limit, and since they are slower their time limit is increased.
+#[derive(Debug, Default)]
+pub struct Sanitizer {
+    /// The sanitized version of each solution, `None` if its language doesn't support the
+    /// sanitizers.
+    sources: HashMap<PathBuf, Option<Arc<task_maker_lang::SourceFile>>>,
+}
+
+impl Sanitizer {
+    /// Add to the DAG the sanitized execution of the solution on a testcase, if it's the first one
+    /// of the subtask and the language of the solution supports the sanitizers.
+    pub(crate) fn evaluate(
+        eval: &mut EvaluationData,
+        task: &IOITask,
+        subtask_id: SubtaskId,
+        testcase_id: TestcaseId,
+        source_file: &SourceFile,
+        input: FileUuid,
+        validation_handle: Option<FileUuid>,
+    ) -> Result<(), Error> {
+        if task.subtasks[&subtask_id].testcases.iter().min() != Some(&testcase_id) {
+            return Ok(());
+        }
+        let Some(sanitizer) = eval.sanitizer.as_mut() else {
+            return Ok(());
+        };
+        let sanitized = sanitizer
+            .sources
+            .entry(source_file.path.clone())
+            .or_insert_with(|| sanitized_source_file(source_file))
+            .clone();
+        let Some(sanitized) = sanitized else {
+            return Ok(());
+        };
+
+        let name = source_file.relative_path().to_owned();
+        let (comp, mut exec) = sanitized
+            .execute(
+                &mut eval.dag,
+                format!(
+                    "Sanitized evaluation of {} on testcase {}",
+                    source_file.name(),
+                    testcase_id
+                ),
+                vec![],
+            )
+            .context("Failed to execute the sanitized solution")?;
+        if let Some(comp) = comp {
+            let sender = eval.sender.clone();
+            let name = name.clone();
+            eval.dag.on_execution_done(&comp, move |result| {
+                if !result.status.is_success() {
+                    sender.add_diagnostic(
+                        Diagnostic::warning(format!(
+                            "Failed to compile {} with the sanitizers",
+                            name.display()
+                        ))
+                        .with_category(DiagnosticCategory::Solutions),
+                    )?;
+                }
+                Ok(())
+            });
+        }
+        exec.tag(Tag::Evaluation.into());
+        // the sanitized executions are run after the others
+        exec.priority(-(testcase_id as Priority));
+        exec.capture_stderr(STDERR_CONTENT_LENGTH);
+        // the leak sanitizer doesn't work inside the sandbox
+        exec.env("ASAN_OPTIONS", "detect_leaks=0");
+        bind_exec_io!(exec, task, input, validation_handle);
+        let limits = exec.limits_mut();
+        if let Some(time_limit) = task.time_limit {
+            limits.cpu_time(time_limit * SANITIZER_TIME_FACTOR);
+            limits.wall_time(time_limit * SANITIZER_TIME_FACTOR * 1.5 + 1.0);
+        }
+        limits.mount_proc(true);
+
+        let sender = eval.sender.clone();
+        eval.dag.on_execution_done(&exec.uuid, move |result| {
+            let stderr = result.stderr.unwrap_or_default();
+            if let Some(error) = find_sanitizer_error(&String::from_utf8_lossy(&stderr)) {
+                sender.add_diagnostic(
+                    Diagnostic::warning(format!(
+                        "The sanitizers found an error in {} on testcase {}",
+                        name.display(),
+                        testcase_id
+                    ))
+                    .with_code(DiagnosticCode::SanitizerError)
+                    .with_category(DiagnosticCategory::Solutions)
+                    .with_note(error)
+                    .with_help_attachment(stderr),
+                )?;
+            }
+            Ok(())
+        });
+        eval.dag.add_execution(exec);
+        Ok(())
+    }
+}
+
+/// Make the sanitized version of a source file, if its language supports the sanitizers.
+fn sanitized_source_file(source_file: &SourceFile) -> Option<Arc<task_maker_lang::SourceFile>> {
+    if !source_file.language().supports_sanitizers() {
+        return None;
+    }
+    let mut sanitized = task_maker_lang::SourceFile::new(
+        &source_file.path,
+        &source_file.base_path,
+        source_file.grader_map.clone(),
+        None::<PathBuf>,
+    )?;
+    sanitized.sanitize();
+    Some(Arc::new(sanitized))
+}
+
+/// Find the first error reported by the sanitizers in the standard error of a program.
+fn find_sanitizer_error(stderr: &str) -> Option<String> {
+    stderr
+        .lines()
+        .find(|line| {
+            line.contains("ERROR: AddressSanitizer")
+                || line.contains("ERROR: UndefinedBehaviorSanitizer")
+                || line.contains("runtime error:")
+        })
+        .map(|line| line.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_sanitizer_error_asan() {
+        let stderr = "=================================================================\n\
+            ==42==ERROR: AddressSanitizer: heap-buffer-overflow on address 0x602000000014\n\
+            READ of size 4 at 0x602000000014 thread T0\n";
+        assert_eq!(
+            find_sanitizer_error(stderr).unwrap(),
+            "==42==ERROR: AddressSanitizer: heap-buffer-overflow on address 0x602000000014"
+        );
+    }
+
+    #[test]
+    fn test_find_sanitizer_error_ubsan() {
+        let stderr = "sol.cpp:5:7: runtime error: signed integer overflow: \
+            2147483647 + 1 cannot be represented in type 'int'\n";
+        assert!(find_sanitizer_error(stderr)
+            .unwrap()
+            .starts_with("sol.cpp:5:7: runtime error: signed integer overflow"));
+    }
+
+    #[test]
+    fn test_find_sanitizer_error_none() {
+        assert_eq!(find_sanitizer_error(""), None);
+        assert_eq!(find_sanitizer_error("debug: error in the input\n"), None);
+    }
+}
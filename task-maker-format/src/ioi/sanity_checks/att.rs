@@ -8,8 +8,8 @@ use itertools::Itertools;
 use regex::Regex;
 
 use task_maker_dag::File;
-use task_maker_diagnostics::Diagnostic;
-use task_maker_lang::GraderMap;
+use task_maker_diagnostics::{Diagnostic, DiagnosticCategory};
+use task_maker_lang::{GraderMap, LanguageManager};
 
 use crate::ioi::sanity_checks::check_missing_graders;
 use crate::ioi::{IOITask, InputGenerator, TaskType, TestcaseId};
@@ -67,6 +67,7 @@ impl SanityCheck for AttTemplates {
                 let grader_name = task.path_of(grader);
                 eval.add_diagnostic(
                     Diagnostic::warning(format!("Missing template at {}", att_name))
+                        .with_category(DiagnosticCategory::Attachments)
                         .with_note(format!("Because of {}", grader_name.display())),
                 )?;
             }
@@ -137,7 +138,7 @@ impl SanityCheck for AttSampleFiles {
                 eval.add_diagnostic(Diagnostic::warning(format!(
                     "Sample case {} is not a symlink",
                     task.path_of(&sample).display()
-                )).with_help("Move this file in the statement folder and symlink it here. This way the sample file can be included in the compiled statement."))?;
+                )).with_category(DiagnosticCategory::Attachments).with_help("Move this file in the statement folder and symlink it here. This way the sample file can be included in the compiled statement."))?;
             }
             if let Ok(path) = sample.canonicalize() {
                 let file_name = path.file_name().unwrap_or_default().to_string_lossy();
@@ -173,7 +174,10 @@ impl SanityCheck for AttSampleFiles {
             }
         }
         if no_sample {
-            eval.add_diagnostic(Diagnostic::warning("No sample file in att/"))?;
+            eval.add_diagnostic(
+                Diagnostic::warning("No sample file in att/")
+                    .with_category(DiagnosticCategory::Attachments),
+            )?;
         }
         Ok(())
     }
@@ -258,7 +262,7 @@ impl SanityCheck for AttSampleFilesValid {
                     .context("Failed to provide sample output file")?;
 
                 // generate the output file
-                let (correct_output, sol) = solution
+                let (correct_output, sols) = solution
                     .generate(
                         task,
                         eval,
@@ -274,7 +278,7 @@ impl SanityCheck for AttSampleFilesValid {
                     .context("Failed to generate correct sample output file")?;
                 let correct_output =
                     correct_output.ok_or_else(|| anyhow!("Missing official solution"))?;
-                if let Some(mut sol) = sol {
+                for mut sol in sols {
                     sol.capture_stderr(1024);
                     let sender = eval.sender.clone();
                     eval.dag.on_execution_done(&sol.uuid, move |res| {
@@ -309,12 +313,15 @@ impl SanityCheck for AttSampleFilesValid {
                         output_uuid,
                         move |score, message| {
                             if abs_diff_ne!(score, 1.0) {
-                                sender.add_diagnostic(Diagnostic::warning(format!(
-                                    "Sample output file {} scores {}: {}",
-                                    output_name.display(),
-                                    score,
-                                    message
-                                )))?;
+                                sender.add_diagnostic(
+                                    Diagnostic::warning(format!(
+                                        "Sample output file {} scores {}: {}",
+                                        output_name.display(),
+                                        score,
+                                        message
+                                    ))
+                                    .with_category(DiagnosticCategory::Attachments),
+                                )?;
                             }
                             Ok(())
                         },
@@ -580,3 +587,61 @@ impl SanityCheck for AttTemplatesShouldCompile {
         Ok(())
     }
 }
+
+/// Check that the templates in att compile with the official graders in sol, so they implement
+/// the functions the graders expect. The templates in sol that are not symlinks are already
+/// compiled with the graders when evaluated as solutions, and the packages of the attachments
+/// contain the same templates, so they are covered as well.
+///
+/// Only the compiled languages are checked, the others would need to run the template.
+#[derive(Debug, Default)]
+pub struct AttTemplatesMatchGraders;
+make_sanity_check!(AttTemplatesMatchGraders);
+
+impl SanityCheck for AttTemplatesMatchGraders {
+    type Task = IOITask;
+
+    fn name(&self) -> &'static str {
+        "AttTemplatesMatchGraders"
+    }
+
+    fn category(&self) -> SanityCheckCategory {
+        SanityCheckCategory::Attachments
+    }
+
+    fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
+        for grader in task.grader_map.all_paths() {
+            let ext = grader
+                .extension()
+                .ok_or_else(|| anyhow!("Grader has no extension"))?
+                .to_string_lossy();
+            let att_name = format!("att/{}.{}", task.name, ext);
+            let template = task.path.join(&att_name);
+            if !template.is_file() {
+                continue;
+            }
+            let Some(language) = LanguageManager::detect_language(&template) else {
+                continue;
+            };
+            if !language.need_compilation() {
+                continue;
+            }
+            let source_file = SourceFile::new(
+                template,
+                &task.path,
+                format!(
+                    "Template {} ({}) compiled with grader {}",
+                    att_name,
+                    language.name(),
+                    task.path_of(grader).display()
+                ),
+                Some(task.grader_map.clone()),
+                None::<String>,
+            );
+            if let Some(source_file) = source_file {
+                source_file.prepare(eval)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,13 +1,19 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::sync::{Arc, Mutex};
 
-use crate::ioi::IOITask;
+use crate::ioi::{IOITask, SubtaskId, TestcaseId};
 use crate::sanity_checks::{make_sanity_check, SanityCheck, SanityCheckCategory};
 use crate::EvaluationData;
-use anyhow::Error;
+use anyhow::{Context, Error};
 use itertools::Itertools;
+use serde::Serialize;
 use task_maker_dag::FileUuid;
-use task_maker_diagnostics::Diagnostic;
+use task_maker_diagnostics::{Diagnostic, DiagnosticCategory};
+
+/// Maximum number of characters of the first line of a file to keep in the inputs index.
+const PREVIEW_LENGTH: usize = 80;
+/// A file is considered an outlier if it's this many times larger than the median of the others.
+const OUTLIER_SIZE_FACTOR: u64 = 100;
 
 /// Check that the input and output files end with `\n`.
 #[derive(Debug, Default)]
@@ -80,6 +86,7 @@ impl CheckEndWithNewLine {
             );
             eval.add_diagnostic(
                 Diagnostic::warning(message)
+                    .with_category(DiagnosticCategory::Generation)
                     .with_note("It's bad practice to have files that do not end with new-line"),
             )?;
         }
@@ -129,3 +136,238 @@ impl SanityCheck for IOEndWithNewLine {
         Ok(())
     }
 }
+
+/// Write `bin/inputs-index.json` with some statistics about the input and output files, and warn
+/// about the empty files and the files way larger than the others.
+#[derive(Debug, Default)]
+pub struct InputsIndex {
+    /// The statistics of the input files, indexed by testcase.
+    inputs: Arc<Mutex<BTreeMap<TestcaseId, FileStats>>>,
+    /// The statistics of the official output files, indexed by testcase.
+    outputs: Arc<Mutex<BTreeMap<TestcaseId, FileStats>>>,
+}
+make_sanity_check!(InputsIndex);
+
+/// Some statistics about the content of a file.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FileStats {
+    /// The size of the file, in bytes.
+    pub size: u64,
+    /// The number of lines of the file.
+    pub lines: u64,
+    /// The first characters of the first line of the file.
+    pub preview: String,
+    /// Whether the first line has been read completely.
+    #[serde(skip)]
+    preview_done: bool,
+    /// Whether the last byte of the file was a new line.
+    #[serde(skip)]
+    ends_with_new_line: bool,
+}
+
+impl FileStats {
+    /// Process the next chunk of the file. An empty chunk marks the end of the file.
+    pub fn add_chunk(&mut self, chunk: &[u8]) {
+        if chunk.is_empty() {
+            if self.size > 0 && !self.ends_with_new_line {
+                self.lines += 1;
+            }
+            return;
+        }
+        self.size += chunk.len() as u64;
+        self.lines += chunk.iter().filter(|&&c| c == b'\n').count() as u64;
+        self.ends_with_new_line = chunk.last() == Some(&b'\n');
+        if !self.preview_done {
+            let line = chunk.split(|&c| c == b'\n').next().unwrap_or_default();
+            self.preview_done = line.len() < chunk.len();
+            self.preview += &String::from_utf8_lossy(line);
+            if self.preview.chars().count() >= PREVIEW_LENGTH {
+                self.preview = self.preview.chars().take(PREVIEW_LENGTH).collect();
+                self.preview_done = true;
+            }
+        }
+    }
+
+    /// Compute the statistics of `file` while it is produced, storing them in `stats`.
+    fn bind(
+        eval: &mut EvaluationData,
+        file: FileUuid,
+        testcase_id: TestcaseId,
+        stats: Arc<Mutex<BTreeMap<TestcaseId, FileStats>>>,
+    ) {
+        let mut file_stats = FileStats::default();
+        eval.dag.get_file_content_chunked(file, move |chunk| {
+            file_stats.add_chunk(chunk);
+            if chunk.is_empty() {
+                stats
+                    .lock()
+                    .unwrap()
+                    .insert(testcase_id, file_stats.clone());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// An entry of `bin/inputs-index.json`.
+#[derive(Debug, Serialize)]
+struct InputsIndexEntry<'a> {
+    /// The id of the subtask that owns the testcase.
+    subtask: Option<SubtaskId>,
+    /// The id of the testcase.
+    testcase: TestcaseId,
+    /// The statistics of the input file.
+    input: Option<&'a FileStats>,
+    /// The statistics of the official output file.
+    output: Option<&'a FileStats>,
+}
+
+/// Find the files that are empty or way larger than the median of the other files.
+fn find_outliers(stats: &BTreeMap<TestcaseId, FileStats>) -> (Vec<TestcaseId>, Vec<TestcaseId>) {
+    let empty = stats
+        .iter()
+        .filter(|(_, s)| s.size == 0)
+        .map(|(id, _)| *id)
+        .collect_vec();
+    let sizes = stats.values().map(|s| s.size).sorted().collect_vec();
+    let median = sizes.get(sizes.len() / 2).copied().unwrap_or(0);
+    let large = if sizes.len() < 2 || median == 0 {
+        vec![]
+    } else {
+        stats
+            .iter()
+            .filter(|(_, s)| s.size > median * OUTLIER_SIZE_FACTOR)
+            .map(|(id, _)| *id)
+            .collect_vec()
+    };
+    (empty, large)
+}
+
+impl SanityCheck for InputsIndex {
+    type Task = IOITask;
+
+    fn name(&self) -> &'static str {
+        "InputsIndex"
+    }
+
+    fn category(&self) -> SanityCheckCategory {
+        SanityCheckCategory::Io
+    }
+
+    fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
+        for (&testcase_id, testcase) in &task.testcases {
+            if let Some(input_file) = testcase.input_file {
+                FileStats::bind(eval, input_file, testcase_id, self.inputs.clone());
+            }
+            if let Some(output_file) = testcase.official_output_file {
+                FileStats::bind(eval, output_file, testcase_id, self.outputs.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn post_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
+        let inputs = self.inputs.lock().unwrap();
+        let outputs = self.outputs.lock().unwrap();
+
+        if !eval.dag.data.config.dry_run && !task.testcases.is_empty() {
+            let index = task
+                .testcases
+                .keys()
+                .sorted()
+                .map(|&testcase| InputsIndexEntry {
+                    subtask: task
+                        .subtasks
+                        .values()
+                        .find(|st| st.testcases_owned.contains(&testcase))
+                        .map(|st| st.id),
+                    testcase,
+                    input: inputs.get(&testcase),
+                    output: outputs.get(&testcase),
+                })
+                .collect_vec();
+            let path = task.path.join("bin").join("inputs-index.json");
+            std::fs::create_dir_all(task.path.join("bin"))
+                .context("Failed to create bin/ directory")?;
+            let content =
+                serde_json::to_string_pretty(&index).context("Failed to serialize the index")?;
+            std::fs::write(&path, content)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+
+        for (stats, kind, name) in [
+            (&*inputs, "input", "input"),
+            (&*outputs, "official output", "output"),
+        ] {
+            let (empty, large) = find_outliers(stats);
+            if !empty.is_empty() {
+                eval.add_diagnostic(
+                    Diagnostic::warning(format!(
+                        "These {} files are empty: {}",
+                        kind,
+                        empty
+                            .iter()
+                            .map(|id| format!("{}/{}{}.txt", name, name, id))
+                            .join(", ")
+                    ))
+                    .with_category(DiagnosticCategory::Generation),
+                )?;
+            }
+            if !large.is_empty() {
+                eval.add_diagnostic(
+                    Diagnostic::warning(format!(
+                        "These {} files are much larger than the others: {}",
+                        kind,
+                        large
+                            .iter()
+                            .map(|id| format!(
+                                "{}/{}{}.txt ({} bytes)",
+                                name, name, id, stats[id].size
+                            ))
+                            .join(", ")
+                    ))
+                    .with_category(DiagnosticCategory::Generation)
+                    .with_note(format!(
+                        "They are more than {} times larger than the median",
+                        OUTLIER_SIZE_FACTOR
+                    ))
+                    .with_help("See bin/inputs-index.json for the size of all the files"),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_stats() {
+        let mut stats = FileStats::default();
+        stats.add_chunk(b"1 2");
+        stats.add_chunk(b" 3\n4\n5");
+        stats.add_chunk(b"");
+        assert_eq!(stats.size, 9);
+        assert_eq!(stats.lines, 3);
+        assert_eq!(stats.preview, "1 2 3");
+    }
+
+    #[test]
+    fn test_find_outliers() {
+        let mut stats = BTreeMap::new();
+        for (id, size) in [(0, 10), (1, 0), (2, 12), (3, 5000), (4, 11)] {
+            stats.insert(
+                id,
+                FileStats {
+                    size,
+                    ..Default::default()
+                },
+            );
+        }
+        let (empty, large) = find_outliers(&stats);
+        assert_eq!(empty, vec![1]);
+        assert_eq!(large, vec![3]);
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Error};
-use task_maker_diagnostics::Diagnostic;
+use task_maker_diagnostics::{CodeSpan, Diagnostic, DiagnosticCategory};
 
 use crate::ioi::sanity_checks::check_missing_graders;
 use crate::ioi::IOITask;
@@ -53,11 +53,14 @@ impl SanityCheck for SolTemplateSymlink {
             let att_template = format!("att/{}.{}", task.name, ext);
 
             if !template.is_symlink() {
-                eval.add_diagnostic(Diagnostic::warning(format!(
-                    "Template {} is not a symlink. It should point to {}",
-                    task.path_of(&template).display(),
-                    att_template
-                )))?;
+                eval.add_diagnostic(
+                    Diagnostic::warning(format!(
+                        "Template {} is not a symlink. It should point to {}",
+                        task.path_of(&template).display(),
+                        att_template
+                    ))
+                    .with_category(DiagnosticCategory::Solutions),
+                )?;
             }
         }
         Ok(())
@@ -111,9 +114,61 @@ impl SanityCheck for SolutionsWithNoChecks {
                     "The following solutions are missing the subtask checks: {}",
                     solutions.join(", ")
                 ))
+                .with_category(DiagnosticCategory::Solutions)
                 .with_help("Try running task-maker-tools add-solution-checks"),
             )?;
         }
         Ok(())
     }
 }
+
+/// Check that the solutions don't contain the forbidden constructs of the task.
+#[derive(Debug, Default)]
+pub struct SolutionsForbiddenConstructs;
+make_sanity_check!(SolutionsForbiddenConstructs);
+
+impl SanityCheck for SolutionsForbiddenConstructs {
+    type Task = IOITask;
+
+    fn name(&self) -> &'static str {
+        "SolutionsForbiddenConstructs"
+    }
+
+    fn category(&self) -> SanityCheckCategory {
+        SanityCheckCategory::Solutions
+    }
+
+    fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
+        if task.forbidden_constructs.is_empty() {
+            return Ok(());
+        }
+        for solution in eval.solutions.clone() {
+            let path = &solution.source_file.path;
+            // the solutions that are not a single file are not checked
+            let Ok(content) = std::fs::read_to_string(path) else {
+                continue;
+            };
+            let relative_path = solution.source_file.relative_path();
+            for construct in &task.forbidden_constructs {
+                let Some(offset) = content.find(construct.as_str()) else {
+                    continue;
+                };
+                let occurrences = content.matches(construct.as_str()).count();
+                let mut diagnostic = Diagnostic::warning(format!(
+                    "{} uses the forbidden construct '{}'",
+                    relative_path.display(),
+                    construct
+                ))
+                .with_category(DiagnosticCategory::Solutions);
+                if let Ok(span) = CodeSpan::from_str(path, &content, offset, construct.len()) {
+                    diagnostic = diagnostic.with_code_span(span);
+                }
+                if occurrences > 1 {
+                    diagnostic = diagnostic.with_note(format!("It's used {} times", occurrences));
+                }
+                eval.add_diagnostic(diagnostic)?;
+            }
+        }
+        Ok(())
+    }
+}
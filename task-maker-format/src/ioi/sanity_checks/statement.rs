@@ -6,7 +6,7 @@ use std::process::Command;
 use anyhow::{Context, Error};
 use itertools::Itertools;
 use regex::Regex;
-use task_maker_diagnostics::{CodeSpan, Diagnostic};
+use task_maker_diagnostics::{CodeSpan, Diagnostic, DiagnosticCategory};
 
 use crate::ioi::{IOITask, SubtaskId};
 use crate::sanity_checks::{make_sanity_check, SanityCheck, SanityCheckCategory};
@@ -234,6 +234,7 @@ impl SanityCheck for StatementCompiled {
                 "The official statement at {} is not the one compiled by task-maker",
                 task.path_of(&path).display()
             ))
+            .with_category(DiagnosticCategory::Statement)
             .with_help(format!(
                 "Maybe it should be a symlink to one of the compiled PDF ({})",
                 booklet_dest
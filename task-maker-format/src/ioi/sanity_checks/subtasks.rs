@@ -5,7 +5,7 @@ use anyhow::Error;
 use blake3::{Hash, Hasher};
 use itertools::Itertools;
 use task_maker_dag::FileUuid;
-use task_maker_diagnostics::Diagnostic;
+use task_maker_diagnostics::{Diagnostic, DiagnosticCategory};
 
 use crate::ioi::SubtaskId;
 use crate::sanity_checks::{make_sanity_check, SanityCheck, SanityCheckCategory};
@@ -48,7 +48,8 @@ impl SanityCheck for MissingSubtaskNames {
                 "These subtasks are missing a name: {}",
                 missing_name.iter().map(|(name, _)| name).join(", ")
             );
-            let mut diagnostic = Diagnostic::warning(message);
+            let mut diagnostic =
+                Diagnostic::warning(message).with_category(DiagnosticCategory::Task);
             if missing_name.iter().any(|(_, span)| span.is_some()) {
                 diagnostic = diagnostic
                     .with_help("Add '#STNAME: name' in gen/GEN after each subtask definition:");
@@ -220,7 +221,8 @@ impl SanityCheck for AllOutputsEqual {
                     .unwrap_or_default();
                 let message = format!("All outputs for subtask {id}{name} are identical");
 
-                let mut diag = Diagnostic::warning(message);
+                let mut diag =
+                    Diagnostic::warning(message).with_category(DiagnosticCategory::Generation);
                 if let Ok(contents) = std::str::from_utf8(first.first_chunk.as_ref().unwrap()) {
                     let contents = contents.chars().take(20).join("");
                     diag = diag.with_note(format!("They all start with: {contents}"));
@@ -262,7 +264,7 @@ impl SanityCheck for DeprecatedDescriptionInSubtask {
                     if let Some(span) = subtask.span.clone() {
                         diagnostic = diagnostic.with_code_span(span);
                     }
-                    eval.add_diagnostic(diagnostic)?;
+                    eval.add_deprecation("subtask-description", diagnostic)?;
                 }
                 _ => {}
             }
@@ -5,7 +5,7 @@ use std::{
 
 use anyhow::Error;
 use regex::Regex;
-use task_maker_diagnostics::{CodeSpan, Diagnostic};
+use task_maker_diagnostics::{CodeSpan, Diagnostic, DiagnosticCategory};
 
 use crate::ioi::IOITask;
 use crate::sanity_checks::{make_sanity_check, SanityCheck, SanityCheckCategory};
@@ -69,6 +69,7 @@ impl SanityCheck for BrokenSymlinks {
                 if let Ok(content) = file.read_link() {
                     eval.add_diagnostic(
                         Diagnostic::warning(format!("{} is a broken symlink", path.display()))
+                            .with_category(DiagnosticCategory::Task)
                             .with_note(format!("It points to {}", content.display())),
                     )?;
                 }
@@ -111,6 +112,7 @@ impl SanityCheck for NoBitsStdCpp {
                         diagnostic
                             .unwrap_or_else(|| {
                                 Diagnostic::warning(r#"Usage of "bits/stdc++.h" is discouraged"#)
+                                    .with_category(DiagnosticCategory::Task)
                                     .with_note("This won't compile under Clang")
                             })
                             .with_code_span(CodeSpan::from_str(
@@ -224,6 +226,7 @@ impl SanityCheck for SubtaskDependencies {
                     Diagnostic::warning(format!(
                         "Subtasks {st_names:?} are solved by the same set of solutions",
                     ))
+                    .with_category(DiagnosticCategory::Scoring)
                     .with_note("Add a solution that solves only one of them"),
                 )?;
             }
@@ -259,9 +262,11 @@ impl SanityCheck for SubtaskDependencies {
                 })
                 .collect::<Vec<_>>();
             eval.add_diagnostic(
-                Diagnostic::warning("Subtasks are not in order of difficulty").with_note(format!(
-                    "Based on the current solutions the following pairs of subtasks seems to be ordered incorrectly {to_swap_names:?}"
-                )),
+                Diagnostic::warning("Subtasks are not in order of difficulty")
+                    .with_category(DiagnosticCategory::Scoring)
+                    .with_note(format!(
+                        "Based on the current solutions the following pairs of subtasks seems to be ordered incorrectly {to_swap_names:?}"
+                    )),
             )?;
         }
 
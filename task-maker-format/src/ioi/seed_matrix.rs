@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Error;
+
+use crate::ioi::{ScoreManager, SubtaskId, TestcaseId};
+use crate::ui::{UIMessage, UIMessageSender};
+use crate::UISender;
+
+/// The environment variable with the seed of the run, for the randomized solutions.
+pub const SEED_ENV_VAR: &str = "TASK_MAKER_SEED";
+
+/// How the scores of the runs of a solution on a testcase are aggregated into the score of the
+/// testcase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SeedAggregation {
+    /// The testcase gets the verdict of the run with the lowest score.
+    #[default]
+    Worst,
+    /// The testcase gets the verdict of the run with the highest score.
+    Best,
+    /// The testcase gets the verdict of the run with the median score.
+    Median,
+}
+
+impl FromStr for SeedAggregation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "worst" => Ok(SeedAggregation::Worst),
+            "best" => Ok(SeedAggregation::Best),
+            "median" => Ok(SeedAggregation::Median),
+            _ => Err(format!("Unknown seed aggregation: {}", s)),
+        }
+    }
+}
+
+impl SeedAggregation {
+    /// The index of the run whose verdict is given to the testcase. The first of the runs with the
+    /// selected score is chosen.
+    fn select(&self, scores: &[f64]) -> usize {
+        let mut runs: Vec<usize> = (0..scores.len()).collect();
+        runs.sort_by(|a, b| scores[*a].total_cmp(&scores[*b]).then(a.cmp(b)));
+        let index = match self {
+            SeedAggregation::Worst => 0,
+            SeedAggregation::Best => {
+                let best = scores[runs[runs.len() - 1]];
+                runs.iter().position(|run| scores[*run] == best).unwrap()
+            }
+            SeedAggregation::Median => (runs.len() - 1) / 2,
+        };
+        runs[index]
+    }
+}
+
+/// Evaluate the solutions more times on each testcase, each time with a different seed in the
+/// `TASK_MAKER_SEED` environment variable. The score of the testcase is the one of the run selected
+/// by the `SeedAggregation`, and the results of all the runs are sent to the UI.
+///
+/// The seed of the run `i` is `i + 1`, so a run can be reproduced by setting the variable by hand.
+#[derive(Debug)]
+pub struct SeedMatrix {
+    /// The number of runs of each solution on each testcase.
+    runs: usize,
+    /// How the verdict of the testcase is selected.
+    aggregation: SeedAggregation,
+    /// The score and the message of each run, for each solution and testcase.
+    scores: HashMap<(PathBuf, TestcaseId), Vec<Option<(f64, String)>>>,
+}
+
+/// A run of a solution on a testcase.
+#[derive(Debug, Clone)]
+pub(crate) struct SeedRun {
+    /// The path of the solution.
+    pub solution: PathBuf,
+    /// The subtask of the testcase.
+    pub subtask: SubtaskId,
+    /// The testcase.
+    pub testcase: TestcaseId,
+    /// The index of the run.
+    pub run: usize,
+}
+
+impl SeedMatrix {
+    /// Make a new `SeedMatrix` with the specified number of runs per testcase.
+    pub(crate) fn new(runs: usize, aggregation: SeedAggregation) -> SeedMatrix {
+        SeedMatrix {
+            runs,
+            aggregation,
+            scores: HashMap::new(),
+        }
+    }
+
+    /// The number of runs of each solution on each testcase.
+    pub(crate) fn runs(&self) -> usize {
+        self.runs
+    }
+
+    /// The seed of a run.
+    pub(crate) fn seed(run: usize) -> u64 {
+        run as u64 + 1
+    }
+
+    /// Store the score of a run. When all the runs of the solution on the testcase are scored, their
+    /// results are sent to the UI and the score of the selected run is given to the testcase.
+    pub(crate) fn score(
+        state: &Mutex<SeedMatrix>,
+        run: SeedRun,
+        score: f64,
+        message: String,
+        score_manager: &Mutex<ScoreManager>,
+        sender: Arc<Mutex<UIMessageSender>>,
+    ) -> Result<(), Error> {
+        let (results, selected) = {
+            let mut state = state.lock().unwrap();
+            let runs = state.runs;
+            let aggregation = state.aggregation;
+            let scores = state
+                .scores
+                .entry((run.solution.clone(), run.testcase))
+                .or_insert_with(|| vec![None; runs]);
+            scores[run.run] = Some((score, message));
+            if scores.iter().any(Option::is_none) {
+                return Ok(());
+            }
+            let results: Vec<_> = scores.iter().flatten().cloned().collect();
+            let selected =
+                aggregation.select(&results.iter().map(|(score, _)| *score).collect::<Vec<_>>());
+            (results, selected)
+        };
+        for (index, (score, message)) in results.iter().enumerate() {
+            sender.send(UIMessage::IOISeedRun {
+                subtask: run.subtask,
+                testcase: run.testcase,
+                solution: run.solution.clone(),
+                run: index,
+                num_runs: results.len(),
+                seed: SeedMatrix::seed(index),
+                score: *score,
+                message: message.clone(),
+                selected: index == selected,
+            })?;
+        }
+        let (score, message) = results[selected].clone();
+        score_manager
+            .lock()
+            .unwrap()
+            .score(run.subtask, run.testcase, score, message, sender)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        let scores = [1.0, 0.0, 0.5, 0.0, 1.0];
+        assert_eq!(SeedAggregation::Worst.select(&scores), 1);
+        assert_eq!(SeedAggregation::Best.select(&scores), 0);
+        assert_eq!(SeedAggregation::Median.select(&scores), 2);
+        assert_eq!(SeedAggregation::Median.select(&[1.0, 0.0]), 1);
+        assert_eq!(SeedAggregation::Worst.select(&[0.5]), 0);
+    }
+}
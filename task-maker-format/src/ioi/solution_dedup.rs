@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::solution::Solution;
+
+/// The languages whose comments start with `//` or are enclosed in `/* */`.
+const C_LIKE_EXTENSIONS: &[&str] = &[
+    "c", "cc", "cpp", "cxx", "c++", "h", "hpp", "java", "js", "ts", "go", "cs", "kt", "swift", "rs",
+];
+/// The languages whose comments start with `#`.
+const HASH_COMMENT_EXTENSIONS: &[&str] = &["py", "sh", "rb", "pl"];
+
+/// How the solutions with the same source are detected, for evaluating them only once. The
+/// results of the evaluated solution are shown also for its duplicates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SolutionDedup {
+    /// All the solutions are evaluated, even if they are the same.
+    Off,
+    /// The solutions with exactly the same source file are evaluated once.
+    #[default]
+    Exact,
+    /// The solutions whose sources differ only in the comments, in the empty lines and in the
+    /// trailing whitespace are evaluated once.
+    Normalized,
+}
+
+impl FromStr for SolutionDedup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(SolutionDedup::Off),
+            "exact" => Ok(SolutionDedup::Exact),
+            "normalized" => Ok(SolutionDedup::Normalized),
+            _ => Err(format!("Unknown solution deduplication: {}", s)),
+        }
+    }
+}
+
+impl SolutionDedup {
+    /// Find the solutions that are duplicates of another one, mapping each of them to the first
+    /// solution with the same source. Only the solutions in the same language can be duplicates,
+    /// and the solutions that cannot be read (e.g. the directories) are never duplicates.
+    pub(crate) fn find_duplicates(&self, solutions: &[Solution]) -> HashMap<PathBuf, PathBuf> {
+        let mut duplicates = HashMap::new();
+        if *self == SolutionDedup::Off {
+            return duplicates;
+        }
+        let mut originals: HashMap<(String, blake3::Hash), &Path> = HashMap::new();
+        for solution in solutions {
+            let path = &solution.source_file.path;
+            let Some(key) = self.source_key(path) else {
+                continue;
+            };
+            match originals.get(&key) {
+                Some(original) => {
+                    duplicates.insert(path.clone(), original.to_path_buf());
+                }
+                None => {
+                    originals.insert(key, path);
+                }
+            }
+        }
+        duplicates
+    }
+
+    /// The key that is the same for the source files that are considered duplicates.
+    fn source_key(&self, path: &Path) -> Option<(String, blake3::Hash)> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        let content = std::fs::read(path).ok()?;
+        let hash = match self {
+            SolutionDedup::Off => return None,
+            SolutionDedup::Exact => blake3::hash(&content),
+            SolutionDedup::Normalized => {
+                let content = String::from_utf8_lossy(&content);
+                blake3::hash(normalize_source(&content, &extension).as_bytes())
+            }
+        };
+        Some((extension, hash))
+    }
+}
+
+/// Remove from a source file the comments, the empty lines and the trailing whitespace. The
+/// indentation is kept since it's meaningful in some languages, and the string literals are not
+/// changed.
+fn normalize_source(content: &str, extension: &str) -> String {
+    let c_like = C_LIKE_EXTENSIONS.contains(&extension);
+    let hash_comments = HASH_COMMENT_EXTENSIONS.contains(&extension);
+    // in Rust `'` starts also the lifetimes, not only the character literals
+    let char_literals = c_like && extension != "rs";
+
+    let mut result = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut quote = None;
+    while let Some(c) = chars.next() {
+        if let Some(q) = quote {
+            result.push(c);
+            if c == '\\' {
+                if let Some(escaped) = chars.next() {
+                    result.push(escaped);
+                }
+            } else if c == q || c == '\n' {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                quote = Some(c);
+                result.push(c);
+            }
+            '\'' if char_literals || hash_comments => {
+                quote = Some(c);
+                result.push(c);
+            }
+            '/' if c_like && chars.peek() == Some(&'/') => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            '/' if c_like && chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    // keep the line breaks of the comment for not joining the lines around it
+                    if c == '\n' {
+                        result.push(c);
+                    }
+                    prev = c;
+                }
+            }
+            '#' if hash_comments => while chars.next_if(|&c| c != '\n').is_some() {},
+            _ => result.push(c),
+        }
+    }
+    result
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_source_c_like() {
+        let source = [
+            "// @check-accepted: *",
+            "#include <cstdio>",
+            "",
+            "int main() { /* multi",
+            "  line */",
+            "    printf(\"http://x\"); // hello  ",
+            "    return '/'; ",
+            "}",
+        ]
+        .join("\n");
+        assert_eq!(
+            normalize_source(&source, "cpp"),
+            "#include <cstdio>\nint main() {\n    printf(\"http://x\");\n    return '/';\n}"
+        );
+    }
+
+    #[test]
+    fn test_normalize_source_python() {
+        let source = "#!/usr/bin/env python3\n# comment\nif True:\n    print('#1')  # no\n";
+        assert_eq!(normalize_source(source, "py"), "if True:\n    print('#1')");
+    }
+
+    #[test]
+    fn test_normalize_source_different() {
+        assert_ne!(
+            normalize_source("int x = 1;", "cpp"),
+            normalize_source("int x = 2;", "cpp")
+        );
+        assert_ne!(
+            normalize_source("if a:\n  x\ny", "py"),
+            normalize_source("if a:\n  x\n  y", "py")
+        );
+    }
+
+    #[test]
+    fn test_parse_solution_dedup() {
+        assert_eq!(SolutionDedup::from_str("off"), Ok(SolutionDedup::Off));
+        assert_eq!(SolutionDedup::from_str("exact"), Ok(SolutionDedup::Exact));
+        assert_eq!(
+            SolutionDedup::from_str("normalized"),
+            Ok(SolutionDedup::Normalized)
+        );
+        assert!(SolutionDedup::from_str("foo").is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{anyhow, Context, Error};
 use askama::Template;
@@ -10,12 +11,15 @@ use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
 use task_maker_dag::{Execution, ExecutionCommand, File};
-use task_maker_diagnostics::Diagnostic;
+use task_maker_diagnostics::{Diagnostic, DiagnosticCategory};
 
+use crate::ioi::statement::latex_cache::{LatexCache, LATEX_CACHE_INPUT, LATEX_CACHE_OUTPUT};
 use crate::ioi::statement::statement::Statement;
 use crate::ioi::BOOKLET_PRIORITY;
 use crate::ui::UIMessageSender;
-use crate::{bind_exec_callbacks, ui::UIMessage, EvaluationData, Tag, UISender, DATA_DIR};
+use crate::{
+    bind_exec_callbacks, git_commit, ui::UIMessage, EvaluationData, Tag, UISender, DATA_DIR,
+};
 
 /// Configuration of a `Booklet`, including the setting from the contest configuration.
 #[derive(Debug, Clone, Serialize, Deserialize, Default, TypeScriptify)]
@@ -40,6 +44,9 @@ pub struct BookletConfig {
     pub logo: Option<String>,
     /// The path to the intro page.
     pub intro_page: Option<PathBuf>,
+    /// The stamp to put in the footer of the pages, for tracing the printed booklet back to its
+    /// sources.
+    pub build_stamp: Option<String>,
 }
 
 /// Template to use to render the `booklet.tex` file.
@@ -58,6 +65,7 @@ pub struct BookletTemplate {
     packages: String,
     tasks: String,
     intro_page: String,
+    build_stamp: String,
 }
 
 /// A `Booklet` is a pdf file containing the statements of some tasks. It is compiled from a series
@@ -70,6 +78,8 @@ pub struct Booklet {
     pub statements: Vec<Statement>,
     /// Where to copy the booklet.
     pub dest: PathBuf,
+    /// Where to keep the auxiliary files of latexmk between the compilations, if anywhere.
+    pub latex_cache_dir: Option<PathBuf>,
 }
 
 /// Part of the schema of `contest.yaml`, used for extracting the configuration of the booklet.
@@ -87,6 +97,10 @@ pub struct ContestYAML {
     pub show_summary: Option<String>,
     /// Some(relative_path) for a front page for the booklet.
     pub booklet_intro_path: Option<PathBuf>,
+    /// Whether to put in the footer of the booklet the git commit of the contest, the build date
+    /// and the version of task-maker.
+    #[serde(default)]
+    pub booklet_build_stamp: bool,
     /// The list of the tasks in the contest (in the correct order).
     pub tasks: Vec<String>,
 }
@@ -98,6 +112,7 @@ impl Booklet {
             config,
             dest: dest.into(),
             statements: Vec::new(),
+            latex_cache_dir: None,
         }
     }
 
@@ -114,36 +129,72 @@ impl Booklet {
             .ok_or_else(|| anyhow!("Invalid destination file {}", self.dest.display()))?
             .to_string_lossy()
             .to_string();
-        let mut exec = Execution::new(
-            "Compilation of the booklet",
-            ExecutionCommand::system("latexmk"),
-        );
-        exec.args(vec![
+        let tex = self.make_tex();
+        let statement_texs = self.statements.iter().map(|s| s.tex()).collect_vec();
+        let latex_cache = self.latex_cache_dir.as_ref().and_then(|cache_dir| {
+            let mut sources = blake3::Hasher::new();
+            sources.update(tex.as_bytes());
+            for statement_tex in &statement_texs {
+                sources.update(statement_tex.as_bytes());
+            }
+            let cache = LatexCache::new(cache_dir, &self.dest);
+            match cache.prepare(&sources.finalize()) {
+                Ok(input) => Some((cache, input)),
+                Err(e) => {
+                    warn!("Not using the LaTeX cache for {}: {:?}", booklet_name, e);
+                    None
+                }
+            }
+        });
+        let latexmk_args = [
             "-shell-escape",
             "-f",
             "-interaction=nonstopmode",
             "-pdf",
             "booklet.tex",
-        ]);
+        ];
+        let mut exec = if latex_cache.is_some() {
+            // extract the auxiliary files before latexmk and archive them after it, keeping its
+            // exit code
+            let script = format!(
+                "if [ -f {input} ]; then tar -xf {input}; fi; latexmk {args}; status=$?; \
+                tar -cf {output} $(ls booklet.* | grep -v -e '\\.tex$' -e '\\.pdf$'); \
+                exit $status",
+                input = LATEX_CACHE_INPUT,
+                output = LATEX_CACHE_OUTPUT,
+                args = latexmk_args.join(" ")
+            );
+            let mut exec =
+                Execution::new("Compilation of the booklet", ExecutionCommand::system("sh"));
+            exec.args(vec!["-c".to_string(), script]);
+            exec
+        } else {
+            let mut exec = Execution::new(
+                "Compilation of the booklet",
+                ExecutionCommand::system("latexmk"),
+            );
+            exec.args(latexmk_args);
+            exec
+        };
         exec.limits_mut()
             .read_only(false)
             .allow_multiprocess()
             .add_extra_readable_dir("/etc")
             .mount_tmpfs(true);
         exec.tag(Tag::Booklet.into());
+        exec.concurrency_class("latex");
         exec.priority(BOOKLET_PRIORITY);
         let output = exec.output("booklet.pdf");
 
         let source = File::new("Source of the booklet");
-        let tex = self.make_tex();
         exec.input(&source, "booklet.tex", false);
         eval.dag.provide_content(source, tex.into_bytes());
 
-        for statement in self.statements.iter() {
+        for (statement, statement_tex) in self.statements.iter().zip(statement_texs) {
             let name = &statement.config().name;
             let tex = File::new(format!("Source of statement of {}", name));
             exec.input(&tex, Path::new(&name).join("statement.tex"), false);
-            eval.dag.provide_content(tex, statement.tex().into_bytes());
+            eval.dag.provide_content(tex, statement_tex.into_bytes());
             let base_dir = PathBuf::from(&name);
             let deps = statement
                 .build_deps(eval, &booklet_name, &self.config)
@@ -195,6 +246,18 @@ impl Booklet {
             }
             Ok(())
         });
+        if let Some((cache, input)) = latex_cache {
+            if let Some(input) = input {
+                let file = File::new("Auxiliary files of latexmk");
+                eval.dag
+                    .provide_file(file.clone(), input)
+                    .context("Failed to provide the auxiliary files of latexmk")?;
+                exec.input(file, LATEX_CACHE_INPUT, false);
+            }
+            let output = exec.output(LATEX_CACHE_OUTPUT);
+            eval.dag
+                .write_file_to_allow_fail(output, cache.output_path(), false);
+        }
         eval.dag.add_execution(exec);
         // latexmk may fail but still produce a good-enough pdf file
         eval.dag.write_file_to_allow_fail(output, &self.dest, false);
@@ -238,6 +301,7 @@ impl Booklet {
                 .map(std::fs::read_to_string)
                 .unwrap_or_else(|| Ok(String::new()))
                 .unwrap_or_default(),
+            build_stamp: self.config.build_stamp.clone().unwrap_or_default(),
         }
         .to_string()
     }
@@ -283,6 +347,7 @@ impl Booklet {
                     "Found Latex errors while compiling the booklet {}",
                     booklet_name.as_ref().display()
                 ))
+                .with_category(DiagnosticCategory::Statement)
                 .with_note(note),
             )?;
         }
@@ -297,8 +362,14 @@ impl BookletConfig {
         contest_dir: P,
         booklet_solutions: bool,
     ) -> Result<BookletConfig, Error> {
-        if let Some(contest_yaml) = Self::contest_yaml(contest_dir) {
+        let contest_dir = contest_dir.into();
+        if let Some(contest_yaml) = Self::contest_yaml(&contest_dir) {
             let contest_yaml = contest_yaml?;
+            let build_stamp = if contest_yaml.booklet_build_stamp {
+                Some(build_stamp(&contest_dir))
+            } else {
+                None
+            };
             Ok(BookletConfig {
                 language: language.into(),
                 show_solutions: booklet_solutions,
@@ -310,6 +381,7 @@ impl BookletConfig {
                 date: contest_yaml.date,
                 logo: contest_yaml.logo,
                 intro_page: contest_yaml.booklet_intro_path,
+                build_stamp,
             })
         } else {
             Ok(BookletConfig {
@@ -323,6 +395,7 @@ impl BookletConfig {
                 date: None,
                 logo: None,
                 intro_page: None,
+                build_stamp: None,
             })
         }
     }
@@ -350,6 +423,51 @@ impl BookletConfig {
     }
 }
 
+/// Number of characters of the commit hash shown in the build stamp, like `git rev-parse --short`.
+const SHORT_COMMIT_LEN: usize = 7;
+
+/// Make the build stamp of a booklet of the contest in `contest_dir`, with the git commit of the
+/// contest (if it's a git repository), the current date and the version of task-maker.
+fn build_stamp(contest_dir: &Path) -> String {
+    let mut stamp = String::new();
+    if let (Some(commit), dirty) = git_commit(contest_dir) {
+        stamp += &format!("commit {}", &commit[..commit.len().min(SHORT_COMMIT_LEN)]);
+        if dirty {
+            stamp += " (modified)";
+        }
+        stamp += ", ";
+    }
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86400)
+        .unwrap_or_default();
+    let (year, month, day) = civil_from_days(days as i64);
+    stamp += &format!(
+        "built on {:04}-{:02}-{:02} with task-maker-rust {}",
+        year,
+        month,
+        day,
+        env!("CARGO_PKG_VERSION")
+    );
+    stamp
+}
+
+/// Convert a number of days since 1970-01-01 to a (year, month, day) date of the proleptic
+/// Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::ioi::StatementConfig;
@@ -392,4 +510,67 @@ mod tests {
         assert!(!outputs.contains(&stderr_path));
         assert!(!outputs.contains(&stdout_path));
     }
+
+    fn make_cached_booklet(task_root: &Path, text: &str) -> Booklet {
+        std::fs::write(task_root.join("text.tex"), text).unwrap();
+        let statement =
+            Statement::new(task_root.join("text.tex"), StatementConfig::default()).unwrap();
+        let mut booklet = Booklet::new(BookletConfig::default(), task_root.join("dest.pdf"));
+        booklet.latex_cache_dir = Some(task_root.join("latex"));
+        booklet.add_statement(statement);
+        booklet
+    }
+
+    #[test]
+    fn test_latex_cache() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let task_root = tmpdir.path();
+        let booklet = make_cached_booklet(task_root, "loltex");
+        let cache_output = LatexCache::new(&task_root.join("latex"), &booklet.dest).output_path();
+
+        let (mut eval, _recv) = EvaluationData::new(task_root);
+        booklet.build(&mut eval).unwrap();
+        let outputs: Vec<_> = eval
+            .dag
+            .file_callbacks()
+            .values()
+            .filter_map(|f| f.write_to.as_ref())
+            .map(|f| f.dest.clone())
+            .collect();
+        assert!(outputs.contains(&cache_output));
+        let group = eval.dag.data.execution_groups.values().next().unwrap();
+        let exec = &group.executions[0];
+        assert_eq!(exec.command, ExecutionCommand::system("sh"));
+        assert!(!exec.inputs.contains_key(Path::new(LATEX_CACHE_INPUT)));
+
+        // the statement changed, the auxiliary files of the last compilation are used
+        std::fs::write(&cache_output, "aux").unwrap();
+        let booklet = make_cached_booklet(task_root, "loltex2");
+        let (mut eval, _recv) = EvaluationData::new(task_root);
+        booklet.build(&mut eval).unwrap();
+        let group = eval.dag.data.execution_groups.values().next().unwrap();
+        assert!(group.executions[0]
+            .inputs
+            .contains_key(Path::new(LATEX_CACHE_INPUT)));
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(59), (1970, 3, 1));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(20742), (2026, 10, 16));
+    }
+
+    #[test]
+    fn test_build_stamp_in_tex() {
+        let config = BookletConfig {
+            build_stamp: Some("commit abc1234".into()),
+            ..Default::default()
+        };
+        let booklet = Booklet::new(config, "dest.pdf");
+        assert!(booklet
+            .make_tex()
+            .contains(r"\setBuildStamp{commit abc1234}"));
+    }
 }
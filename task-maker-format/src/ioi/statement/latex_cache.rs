@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+
+/// The name of the archive with the auxiliary files of latexmk inside the sandbox.
+pub const LATEX_CACHE_INPUT: &str = "latex-cache.tar";
+/// The name of the archive with the new auxiliary files of latexmk inside the sandbox.
+pub const LATEX_CACHE_OUTPUT: &str = "latex-cache.out.tar";
+
+/// The cache of the auxiliary files of latexmk (`.aux`, `.toc`, `.fdb_latexmk`, ...) of a booklet,
+/// kept between the compilations for not starting every time from scratch.
+///
+/// The archive given to the compilation stays the same while the sources of the statements don't
+/// change, otherwise the compilation would never hit the cache of the executions. When the sources
+/// change, the archive produced by the last compilation is given to the next one.
+#[derive(Debug, Clone)]
+pub struct LatexCache {
+    /// The directory with the cache of this booklet.
+    dir: PathBuf,
+}
+
+impl LatexCache {
+    /// The cache of the booklet compiled to `dest`, inside `cache_dir`.
+    pub fn new(cache_dir: &Path, dest: &Path) -> LatexCache {
+        let key = blake3::hash(dest.to_string_lossy().as_bytes());
+        LatexCache {
+            dir: cache_dir.join(key.to_hex().as_str()),
+        }
+    }
+
+    /// Select the archive to give to the compilation of the booklet with the sources hashed to
+    /// `sources`, if any.
+    pub fn prepare(&self, sources: &blake3::Hash) -> Result<Option<PathBuf>, Error> {
+        std::fs::create_dir_all(&self.dir).with_context(|| {
+            format!(
+                "Failed to create the LaTeX cache directory {}",
+                self.dir.display()
+            )
+        })?;
+        let input = self.dir.join("input.tar");
+        let output = self.output_path();
+        let key_path = self.dir.join("sources");
+        let key = sources.to_hex().to_string();
+        if std::fs::read_to_string(&key_path).ok().as_deref() != Some(key.as_str()) {
+            // the sources changed, start from the auxiliary files of the last compilation
+            if output.exists() {
+                std::fs::copy(&output, &input)
+                    .context("Failed to copy the auxiliary files of latexmk")?;
+            } else if input.exists() {
+                std::fs::remove_file(&input)
+                    .context("Failed to remove the auxiliary files of latexmk")?;
+            }
+            std::fs::write(&key_path, key).context("Failed to write the LaTeX cache key")?;
+        }
+        Ok(Some(input).filter(|input| input.exists()))
+    }
+
+    /// Where to store the archive produced by the compilation.
+    pub fn output_path(&self) -> PathBuf {
+        self.dir.join("output.tar")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latex_cache_rotation() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let cache = LatexCache::new(tmpdir.path(), Path::new("booklet.pdf"));
+        let first = blake3::hash(b"first");
+        let second = blake3::hash(b"second");
+
+        assert_eq!(cache.prepare(&first).unwrap(), None);
+        std::fs::write(cache.output_path(), "aux1").unwrap();
+        // same sources, same input as before
+        assert_eq!(cache.prepare(&first).unwrap(), None);
+
+        let input = cache.prepare(&second).unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(&input).unwrap(), "aux1");
+        std::fs::write(cache.output_path(), "aux2").unwrap();
+        let input = cache.prepare(&second).unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(input).unwrap(), "aux1");
+
+        let input = cache.prepare(&first).unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(input).unwrap(), "aux2");
+    }
+
+    #[test]
+    fn test_latex_cache_per_booklet() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let cache1 = LatexCache::new(tmpdir.path(), Path::new("english.pdf"));
+        let cache2 = LatexCache::new(tmpdir.path(), Path::new("italian.pdf"));
+        assert_ne!(cache1.output_path(), cache2.output_path());
+    }
+}
@@ -12,6 +12,7 @@ use crate::{list_files, EvaluationConfig};
 
 mod asy;
 mod booklet;
+mod latex_cache;
 #[allow(clippy::module_inception)]
 mod statement;
 
@@ -36,6 +37,7 @@ pub fn make_task_booklets(
         )
         .context("Failed to build booklet")?;
         let mut booklet = Booklet::new(booklet_config, dest);
+        booklet.latex_cache_dir = eval_config.latex_cache_dir.clone();
         booklet.add_statement(statement);
         booklets.push(booklet);
     }
@@ -84,6 +86,7 @@ pub fn make_contest_booklets(
                 .context("Failed to build booklet contest configuration")?;
         let dest = contest_dir.join(format!("{}.pdf", language));
         let mut booklet = Booklet::new(booklet_config, dest);
+        booklet.latex_cache_dir = eval_config.latex_cache_dir.clone();
 
         for (task, path) in tasks {
             let config = StatementConfig::from_task(task);
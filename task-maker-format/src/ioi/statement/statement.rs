@@ -2,7 +2,8 @@ use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Error};
 use askama::Template;
-use regex::Regex;
+use itertools::Itertools;
+use regex::{NoExpand, Regex};
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
@@ -15,6 +16,8 @@ use crate::EvaluationData;
 lazy_static! {
     /// This regex will match all the `\usepackage` inside a latex file.
     static ref USE_PACKAGE_REGEX: Regex = Regex::new(r"\\usepackage.+").expect("Invalid regex");
+    /// This regex will match the `\subtasktable` macro inside a latex file.
+    static ref SUBTASK_TABLE_REGEX: Regex = Regex::new(r"\\subtasktable\b").expect("Invalid regex");
 }
 
 /// The configuration of a `Statement`.
@@ -36,6 +39,19 @@ pub struct StatementConfig {
     pub difficulty: Option<u8>,
     /// The level of the syllabus of the task.
     pub syllabus_level: Option<u8>,
+    /// The subtasks of the task, sorted by id, used for expanding `\subtasktable`.
+    pub subtasks: Vec<StatementSubtask>,
+}
+
+/// A subtask of the task, as shown in the subtask table of the statement.
+#[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
+pub struct StatementSubtask {
+    /// The name of the subtask, if any.
+    pub name: Option<String>,
+    /// The maximum score of the subtask.
+    pub max_score: f64,
+    /// The constraints of the subtask, including the global ones, as written in `cases.gen`.
+    pub constraints: Vec<String>,
 }
 
 /// A statement is a `.tex` file with all the other assets included in its directory.
@@ -157,12 +173,58 @@ impl Statement {
                 .map(|x| x.to_string())
                 .unwrap_or_default(),
             content: USE_PACKAGE_REGEX
-                .replace_all(&self.content, r"% $0")
+                .replace_all(&self.subtask_table(), r"% $0")
                 .to_string(),
         };
         template.to_string()
     }
 
+    /// Return the content of the statement with `\subtasktable` replaced by the table of the
+    /// subtasks of the task. The table is a `SubtaskTable` environment of `cms-contest.cls`, with a
+    /// row for each subtask.
+    ///
+    /// The constraints shared by all the subtasks are omitted from the rows, they are usually
+    /// written in the statement anyway. A subtask without other constraints is described by its
+    /// name.
+    fn subtask_table(&self) -> String {
+        if !SUBTASK_TABLE_REGEX.is_match(&self.content) {
+            return self.content.clone();
+        }
+        let subtasks = &self.config.subtasks;
+        let common = |constraint: &String| {
+            subtasks.len() > 1
+                && subtasks
+                    .iter()
+                    .all(|st| st.constraints.contains(constraint))
+        };
+        let mut table = String::from("\\begin{SubtaskTable}\n");
+        for (index, subtask) in subtasks.iter().enumerate() {
+            let mut description = subtask
+                .constraints
+                .iter()
+                .filter(|constraint| !common(constraint))
+                .map(|constraint| format!("${}$", constraint_to_latex(constraint)))
+                .join(", ");
+            if description.is_empty() {
+                description = subtask
+                    .name
+                    .as_deref()
+                    .map(escape_latex)
+                    .unwrap_or_default();
+            }
+            table += &format!(
+                "\\SubtaskTableRow{{{}}}{{{}}}{{{}}}\n",
+                index + 1,
+                subtask.max_score,
+                description
+            );
+        }
+        table += "\\end{SubtaskTable}";
+        SUBTASK_TABLE_REGEX
+            .replace_all(&self.content, NoExpand(&table))
+            .to_string()
+    }
+
     /// Return a list of all the `\usepackage` used by the statement.
     pub fn packages(&self) -> Vec<String> {
         let mut packages = Vec::new();
@@ -263,8 +325,48 @@ impl StatementConfig {
             memory_limit: task.memory_limit,
             difficulty: task.difficulty,
             syllabus_level: task.syllabus_level,
+            subtasks: task
+                .subtasks
+                .values()
+                .sorted_by_key(|st| st.id)
+                .map(|st| StatementSubtask {
+                    name: st.name.clone(),
+                    max_score: st.max_score,
+                    constraints: st.constraints.iter().map(ToString::to_string).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Convert a constraint of `cases.gen` (e.g. `1 <= $N`) into LaTeX math (e.g. `1 \le N`).
+fn constraint_to_latex(constraint: &str) -> String {
+    constraint
+        .split_whitespace()
+        .map(|token| match token {
+            "<=" => "\\le".to_string(),
+            ">=" => "\\ge".to_string(),
+            _ => token.trim_start_matches('$').replace('_', "\\_"),
+        })
+        .join(" ")
+}
+
+/// Escape the characters that have a special meaning in LaTeX.
+fn escape_latex(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped += "\\textbackslash{}",
+            '~' => escaped += "\\textasciitilde{}",
+            '^' => escaped += "\\textasciicircum{}",
+            '{' | '}' | '$' | '&' | '#' | '_' | '%' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
         }
     }
+    escaped
 }
 
 #[cfg(test)]
@@ -273,7 +375,7 @@ mod tests {
 
     use tempfile::TempDir;
 
-    use crate::ioi::{Statement, StatementConfig};
+    use crate::ioi::{Statement, StatementConfig, StatementSubtask};
     use crate::EvaluationData;
 
     #[test]
@@ -307,6 +409,35 @@ mod tests {
         assert!(Statement::is_valid_pdf_dependency(Path::new("/do/not/exists")).is_err());
     }
 
+    #[test]
+    fn test_subtask_table() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("test.tex");
+        std::fs::write(&path, "Scoring\n\\subtasktable\n\\subtasktablefoo").unwrap();
+        let subtask = |name: &str, max_score, constraints: &[&str]| StatementSubtask {
+            name: Some(name.into()),
+            max_score,
+            constraints: constraints.iter().map(|c| c.to_string()).collect(),
+        };
+        let config = StatementConfig {
+            subtasks: vec![
+                subtask("samples", 0.0, &["1 <= $N"]),
+                subtask("n_small", 30.0, &["1 <= $N", "$N <= 10"]),
+                subtask("full", 70.0, &["1 <= $N", "$N <= $MAX_N"]),
+            ],
+            ..Default::default()
+        };
+        let statement = Statement::new(&path, config).unwrap();
+        assert_eq!(
+            statement.subtask_table(),
+            "Scoring\n\\begin{SubtaskTable}\n\
+             \\SubtaskTableRow{1}{0}{samples}\n\
+             \\SubtaskTableRow{2}{30}{$N \\le 10$}\n\
+             \\SubtaskTableRow{3}{70}{$N \\le MAX\\_N$}\n\
+             \\end{SubtaskTable}\n\\subtasktablefoo"
+        );
+    }
+
     #[test]
     fn test_process_possible_dependency() {
         let tmpdir = TempDir::new().unwrap();
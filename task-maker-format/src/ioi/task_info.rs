@@ -1,11 +1,13 @@
 use std::path::PathBuf;
 
-use anyhow::Error;
+use anyhow::{Context, Error};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
-use crate::ioi::IOITask;
+use crate::ioi::italian_yaml::Constraint;
+use crate::ioi::{IOITask, ScoreRounding, SubtaskId, TestcaseId};
+use crate::{hash_file, TASK_INFO_VERSION};
 
 /// Task information structure.
 #[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
@@ -22,8 +24,12 @@ pub struct IOITaskInfo {
     limits: TaskInfoLimits,
     /// Statements of the task.
     statements: Vec<TaskInfoStatement>,
+    /// Languages of the statements of the task, sorted and without duplicates.
+    statement_languages: Vec<String>,
     /// Attachments of the task.
     attachments: Vec<TaskInfoAttachment>,
+    /// Testcases of the task, sorted by id.
+    testcases: Vec<TaskInfoTestcase>,
 }
 
 /// Limits of the task.
@@ -44,15 +50,34 @@ pub struct TaskInfoAttachment {
     content_type: String,
     /// Path of this attachment relative to task directory.
     path: PathBuf,
+    /// The BLAKE3 hash of the content of this attachment, in hex.
+    hash: String,
 }
 
 /// Info of the subtasks.
 #[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
 pub struct TaskInfoSubtask {
+    /// The id of this subtask.
+    id: SubtaskId,
+    /// The name of this subtask, if any.
+    name: Option<String>,
     /// Maximum score for this subtask.
     max_score: f64,
     /// Number of testcases for this subtask.
     testcases: u64,
+    /// The ids of the testcases of this subtask.
+    testcase_ids: Vec<TestcaseId>,
+    /// The constraints that the testcases of this subtask satisfy.
+    constraints: Vec<Constraint>,
+}
+
+/// Info of the testcases.
+#[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
+pub struct TaskInfoTestcase {
+    /// The id of this testcase.
+    id: TestcaseId,
+    /// The tags of this testcase.
+    tags: Vec<String>,
 }
 
 /// Scoring for the task.
@@ -60,6 +85,10 @@ pub struct TaskInfoSubtask {
 pub struct TaskInfoScoring {
     /// Maximum score for the task.
     max_score: f64,
+    /// Number of decimal digits of the scores.
+    precision: Option<usize>,
+    /// How the score of the task is rounded to `precision` digits, if set.
+    rounding: ScoreRounding,
     /// Subtasks of this task.
     subtasks: Vec<TaskInfoSubtask>,
 }
@@ -79,7 +108,7 @@ impl IOITaskInfo {
     /// Generate the task information from the provided `Task`.
     pub fn new(task: &IOITask) -> Result<IOITaskInfo, Error> {
         Ok(IOITaskInfo {
-            version: 1,
+            version: TASK_INFO_VERSION,
             name: task.name.clone(),
             title: task.title.clone(),
             scoring: TaskInfoScoring {
@@ -87,13 +116,19 @@ impl IOITaskInfo {
                     .subtasks
                     .iter()
                     .fold(0.0, |sum, (_, subtask)| sum + subtask.max_score),
+                precision: task.score_precision,
+                rounding: task.score_rounding,
                 subtasks: task
                     .subtasks
                     .iter()
                     .sorted_by_key(|(&id, _)| id)
                     .map(|(_, subtask)| TaskInfoSubtask {
+                        id: subtask.id,
+                        name: subtask.name.clone(),
                         max_score: subtask.max_score,
                         testcases: subtask.testcases.len() as u64,
+                        testcase_ids: subtask.testcases.clone(),
+                        constraints: subtask.constraints.clone(),
                     })
                     .collect(),
             },
@@ -112,26 +147,48 @@ impl IOITaskInfo {
                     path: task.path_of(&booklet.dest).into(),
                 })
                 .collect(),
-            attachments: task
-                .path
-                .join("att")
-                .read_dir()
-                .map(|dir| {
-                    dir.filter(|entry| entry.as_ref().unwrap().file_type().unwrap().is_file())
-                        .map(|entry| {
-                            let entry = entry.unwrap();
-                            let path = entry.path();
-                            TaskInfoAttachment {
-                                name: entry.file_name().to_str().unwrap().into(),
-                                content_type: mime_guess::from_path(path)
-                                    .first()
-                                    .map_or("UNKNOWN".into(), |t| t.to_string()),
-                                path: task.path_of(&entry.path()).into(),
-                            }
-                        })
-                        .collect()
+            statement_languages: task
+                .booklets
+                .iter()
+                .map(|booklet| booklet.config.language.clone())
+                .sorted()
+                .dedup()
+                .collect(),
+            attachments: attachments(task)?,
+            testcases: task
+                .testcases
+                .values()
+                .sorted_by_key(|testcase| testcase.id)
+                .map(|testcase| TaskInfoTestcase {
+                    id: testcase.id,
+                    tags: testcase.tags.clone(),
                 })
-                .unwrap_or_default(),
+                .collect(),
         })
     }
 }
+
+/// Find the attachments of the task, inside the `att/` directory, sorted by name.
+fn attachments(task: &IOITask) -> Result<Vec<TaskInfoAttachment>, Error> {
+    let Ok(dir) = task.path.join("att").read_dir() else {
+        return Ok(vec![]);
+    };
+    let mut attachments = vec![];
+    for entry in dir {
+        let entry = entry.context("Cannot list the attachments")?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let path = entry.path();
+        attachments.push(TaskInfoAttachment {
+            name: entry.file_name().to_string_lossy().into(),
+            content_type: mime_guess::from_path(&path)
+                .first()
+                .map_or("UNKNOWN".into(), |t| t.to_string()),
+            path: task.path_of(&path).into(),
+            hash: hash_file(&path)?,
+        });
+    }
+    attachments.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(attachments)
+}
@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use itertools::Itertools;
+
+use task_maker_diagnostics::Diagnostic;
+
+use crate::ioi::ui_state::TestcaseEvaluationStatus;
+use crate::ioi::{TestcaseId, UIState};
+
+/// How the suggested time limit should be rounded to a multiple of the rounding step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeLimitRounding {
+    /// Round to the smallest multiple of the step not smaller than the time.
+    Up,
+    /// Round to the closest multiple of the step.
+    Nearest,
+}
+
+impl std::str::FromStr for TimeLimitRounding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TimeLimitRounding, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "up" => Ok(TimeLimitRounding::Up),
+            "nearest" => Ok(TimeLimitRounding::Nearest),
+            _ => Err(format!("Unknown rounding: {}", s)),
+        }
+    }
+}
+
+/// The policy used for computing a time limit from the time used by the reference solutions.
+#[derive(Debug, Clone)]
+pub struct TimeLimitPolicy {
+    /// The time used by the slowest reference solution is multiplied by this factor.
+    pub multiplier: f64,
+    /// The time limit is rounded to a multiple of this number of seconds.
+    pub step: f64,
+    /// How to round the time limit.
+    pub rounding: TimeLimitRounding,
+}
+
+impl Default for TimeLimitPolicy {
+    fn default() -> Self {
+        TimeLimitPolicy {
+            multiplier: 2.0,
+            step: 0.1,
+            rounding: TimeLimitRounding::Up,
+        }
+    }
+}
+
+impl TimeLimitPolicy {
+    /// Compute the time limit to use for a task whose slowest reference solution takes `time`
+    /// seconds. The result is never smaller than a single step.
+    pub fn apply(&self, time: f64) -> f64 {
+        let time = time * self.multiplier;
+        if self.step <= 0.0 {
+            return time;
+        }
+        let steps = time / self.step;
+        // Avoid floating point errors pushing an exact multiple of the step to the next one.
+        let steps = (steps * 1e6).round() / 1e6;
+        let steps = match self.rounding {
+            TimeLimitRounding::Up => steps.ceil(),
+            TimeLimitRounding::Nearest => steps.round(),
+        };
+        let limit = steps.max(1.0) * self.step;
+        (limit * 1e6).round() / 1e6
+    }
+}
+
+/// A time limit suggested from the evaluation of the reference solutions.
+#[derive(Debug, Clone)]
+pub struct TimeLimitSuggestion {
+    /// The suggested time limit, in seconds.
+    pub time_limit: f64,
+    /// The solutions that have been used as reference, i.e. the ones accepted on all the
+    /// testcases.
+    pub reference_solutions: Vec<PathBuf>,
+    /// For each testcase, the maximum cpu time used by a reference solution.
+    pub testcase_times: HashMap<TestcaseId, f64>,
+    /// The maximum cpu time used by a reference solution.
+    pub max_cpu_time: f64,
+    /// The testcase where `max_cpu_time` was used.
+    pub slowest_testcase: TestcaseId,
+    /// The reference solution that used `max_cpu_time`.
+    pub slowest_solution: PathBuf,
+    /// The time used by the solutions grouped by their intended complexity, sorted by time. Only
+    /// the solutions with a `@complexity` annotation are included.
+    pub complexity_classes: Vec<ComplexityClass>,
+}
+
+/// The time used by the solutions with the same intended complexity.
+#[derive(Debug, Clone)]
+pub struct ComplexityClass {
+    /// The intended complexity, as written in the `@complexity` annotation of the solutions.
+    pub complexity: String,
+    /// The solutions with this complexity.
+    pub solutions: Vec<PathBuf>,
+    /// The maximum cpu time used by a solution of this class on a testcase.
+    pub max_cpu_time: f64,
+    /// Whether a solution of this class exceeded the time limit, in which case `max_cpu_time` is
+    /// only a lower bound.
+    pub time_limit_exceeded: bool,
+}
+
+impl UIState {
+    /// Suggest a time limit for the task using the time used by the reference solutions, i.e. the
+    /// solutions accepted on all the testcases.
+    ///
+    /// Returns `None` if there are no reference solutions. This function should be called only
+    /// after all the executions have completed.
+    pub fn suggest_time_limit(&self, policy: &TimeLimitPolicy) -> Option<TimeLimitSuggestion> {
+        let reference_solutions = self
+            .evaluations
+            .iter()
+            .filter(|(_, eval)| {
+                !eval.testcases.is_empty()
+                    && eval
+                        .testcases
+                        .values()
+                        .all(|tc| tc.status.is_success() && !tc.results.is_empty())
+            })
+            .map(|(path, _)| path.clone())
+            .sorted()
+            .collect_vec();
+
+        let mut testcase_times: HashMap<TestcaseId, f64> = HashMap::new();
+        let mut slowest: Option<(f64, TestcaseId, &PathBuf)> = None;
+        for path in &reference_solutions {
+            for (&tc_num, testcase) in &self.evaluations[path].testcases {
+                let time = testcase
+                    .results
+                    .iter()
+                    .flatten()
+                    .map(|res| res.resources.cpu_time)
+                    .fold(0.0, f64::max);
+                let entry = testcase_times.entry(tc_num).or_default();
+                *entry = entry.max(time);
+                if !matches!(slowest, Some((max, _, _)) if max >= time) {
+                    slowest = Some((time, tc_num, path));
+                }
+            }
+        }
+        let (max_cpu_time, slowest_testcase, slowest_solution) = slowest?;
+        Some(TimeLimitSuggestion {
+            time_limit: policy.apply(max_cpu_time),
+            slowest_solution: slowest_solution.clone(),
+            reference_solutions,
+            testcase_times,
+            max_cpu_time,
+            slowest_testcase,
+            complexity_classes: self.complexity_classes(),
+        })
+    }
+
+    /// Group the evaluated solutions by their intended complexity, computing the time used by each
+    /// group.
+    fn complexity_classes(&self) -> Vec<ComplexityClass> {
+        let mut classes: HashMap<&str, ComplexityClass> = HashMap::new();
+        for (path, eval) in self.evaluations.iter().sorted_by_key(|(path, _)| *path) {
+            let Some(complexity) = self.solutions.get(path).and_then(|s| s.complexity.as_ref())
+            else {
+                continue;
+            };
+            let class = classes
+                .entry(complexity.as_str())
+                .or_insert_with(|| ComplexityClass {
+                    complexity: complexity.clone(),
+                    solutions: vec![],
+                    max_cpu_time: 0.0,
+                    time_limit_exceeded: false,
+                });
+            class.solutions.push(path.clone());
+            for testcase in eval.testcases.values() {
+                for result in testcase.results.iter().flatten() {
+                    class.max_cpu_time = class.max_cpu_time.max(result.resources.cpu_time);
+                }
+                class.time_limit_exceeded |= matches!(
+                    testcase.status,
+                    TestcaseEvaluationStatus::TimeLimitExceeded
+                        | TestcaseEvaluationStatus::WallTimeLimitExceeded
+                );
+            }
+        }
+        classes
+            .into_values()
+            .sorted_by(|a, b| {
+                (a.time_limit_exceeded, a.max_cpu_time)
+                    .partial_cmp(&(b.time_limit_exceeded, b.max_cpu_time))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .collect()
+    }
+}
+
+impl TimeLimitSuggestion {
+    /// Describe the suggestion with a diagnostic, comparing it with the current time limit of the
+    /// task, so that it's shown by every UI.
+    pub fn diagnostic(&self, state: &UIState) -> Diagnostic {
+        let current = state
+            .task
+            .time_limit
+            .map(|t| format!("{}s", t))
+            .unwrap_or_else(|| "unlimited".to_string());
+        let mut note = vec![
+            format!(
+                "Reference solutions: {}",
+                self.reference_solutions
+                    .iter()
+                    .map(|path| state
+                        .solutions
+                        .get(path)
+                        .map(|sol| sol.name.clone())
+                        .unwrap_or_else(|| path.to_string_lossy().into()))
+                    .join(", ")
+            ),
+            format!(
+                "Slowest execution: {:.3}s on testcase {} by {}",
+                self.max_cpu_time,
+                self.slowest_testcase,
+                self.slowest_solution
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+            ),
+        ];
+        if !self.complexity_classes.is_empty() {
+            note.push("Complexity classes:".into());
+        }
+        let width = self
+            .complexity_classes
+            .iter()
+            .map(|class| class.complexity.len())
+            .max()
+            .unwrap_or_default();
+        for class in &self.complexity_classes {
+            let time = format!(
+                "{}{:.3}s",
+                if class.time_limit_exceeded { ">" } else { "" },
+                class.max_cpu_time
+            );
+            let mut line = format!(
+                "  {:width$}  {:>8}  {}",
+                class.complexity,
+                time,
+                class
+                    .solutions
+                    .iter()
+                    .map(|path| path.file_name().unwrap_or_default().to_string_lossy())
+                    .join(", "),
+                width = width
+            );
+            if class.time_limit_exceeded || class.max_cpu_time > self.time_limit {
+                line += " (over the suggested time limit)";
+            }
+            note.push(line);
+        }
+        Diagnostic::info(format!(
+            "Suggested time limit: {}s (currently {})",
+            self.time_limit, current
+        ))
+        .with_note(note.join("\n"))
+    }
+}
+
+/// Write the time limit into the `task.yaml` of the task, keeping the rest of the file untouched.
+///
+/// If the task has a `task.yaml.orig` that file is patched instead, since `task.yaml` is generated
+/// from it. Returns the path of the patched file.
+pub fn patch_task_yaml_time_limit(task_dir: &Path, time_limit: f64) -> Result<PathBuf, Error> {
+    let path = if task_dir.join("task.yaml.orig").exists() {
+        task_dir.join("task.yaml.orig")
+    } else {
+        task_dir.join("task.yaml")
+    };
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let new_line = format!("time_limit: {}", time_limit);
+    let mut found = false;
+    let mut lines = content
+        .lines()
+        .map(|line| {
+            if line.starts_with("time_limit:") {
+                found = true;
+                new_line.as_str()
+            } else {
+                line
+            }
+        })
+        .collect_vec();
+    if !found {
+        lines.push(&new_line);
+    }
+    let new_content = lines.join("\n") + "\n";
+    std::fs::write(&path, new_content)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_apply() {
+        let policy = TimeLimitPolicy::default();
+        assert_eq!(policy.apply(0.5), 1.0);
+        assert_eq!(policy.apply(0.51), 1.1);
+        assert_eq!(policy.apply(0.0), 0.1);
+        let policy = TimeLimitPolicy {
+            multiplier: 1.5,
+            step: 0.5,
+            rounding: TimeLimitRounding::Nearest,
+        };
+        assert_eq!(policy.apply(1.1), 1.5);
+        assert_eq!(policy.apply(1.2), 2.0);
+    }
+
+    #[test]
+    fn test_patch_task_yaml_time_limit() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("task.yaml");
+        std::fs::write(&path, "name: task\ntime_limit: 1.0\nmemory_limit: 256\n").unwrap();
+        patch_task_yaml_time_limit(tmpdir.path(), 1.5).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "name: task\ntime_limit: 1.5\nmemory_limit: 256\n");
+
+        std::fs::write(&path, "name: task\n").unwrap();
+        patch_task_yaml_time_limit(tmpdir.path(), 2.0).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "name: task\ntime_limit: 2\n");
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::time::SystemTime;
 
@@ -7,8 +7,10 @@ use task_maker_diagnostics::DiagnosticContext;
 use task_maker_exec::ExecutorStatus;
 
 use crate::solution::{SolutionCheck, SolutionInfo, TestcaseEvaluationResult};
-use crate::ui::{CompilationStatus, UIExecutionStatus, UIMessage, UIStateT};
-use crate::{ioi::*, ScoreStatus};
+use crate::ui::{
+    CompilationStatus, ExitCondition, ExitPolicy, UIExecutionStatus, UIMessage, UIStateT,
+};
+use crate::{ioi::*, ScoreStatus, TaskFormat};
 
 /// Status of the generation of a testcase input and output.
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -112,6 +114,22 @@ pub struct SolutionTestcaseEvaluationState {
     pub results: Vec<Option<ExecutionResult>>,
     /// The result of the checker.
     pub checker: Option<ExecutionResult>,
+    /// The results of the runs with the different seeds, when the solutions are evaluated with
+    /// more seeds. The execution of each run is in `results`, at the same index.
+    pub seed_runs: Vec<Option<SeedRunState>>,
+}
+
+/// The result of a run of a solution on a testcase with a seed.
+#[derive(Debug, Clone)]
+pub struct SeedRunState {
+    /// The seed of the run.
+    pub seed: u64,
+    /// The score of the run.
+    pub score: f64,
+    /// The message associated with the score of the run.
+    pub message: String,
+    /// Whether the verdict of this run is the one given to the testcase.
+    pub selected: bool,
 }
 
 impl SolutionTestcaseEvaluationState {
@@ -153,6 +171,18 @@ impl SolutionTestcaseEvaluationState {
 
         false
     }
+
+    /// The custom metrics of the testcase, summed over all the executions of the solution and the
+    /// checker, sorted by name.
+    pub fn custom_metrics(&self) -> BTreeMap<&str, f64> {
+        let mut metrics = BTreeMap::new();
+        for result in self.results.iter().flatten().chain(self.checker.iter()) {
+            for (name, value) in &result.custom_metrics {
+                *metrics.entry(name.as_str()).or_default() += value;
+            }
+        }
+        metrics
+    }
 }
 
 /// State of the evaluation of a subtask.
@@ -204,6 +234,7 @@ impl SolutionEvaluationState {
                             status: TestcaseEvaluationStatus::Pending,
                             results: Vec::new(),
                             checker: None,
+                            seed_runs: Vec::new(),
                         },
                     )
                 })
@@ -239,6 +270,8 @@ pub struct UIState {
     pub max_score: f64,
     /// The set of solutions that will be evaluated.
     pub solutions: HashMap<PathBuf, SolutionInfo>,
+    /// The duplicates of each solution, which get the same results of the solution.
+    pub duplicates: HashMap<PathBuf, Vec<PathBuf>>,
     /// The status of the compilations.
     pub compilations: HashMap<PathBuf, CompilationStatus>,
     /// The state of the generation of the testcases.
@@ -265,6 +298,22 @@ impl TestcaseEvaluationStatus {
         )
     }
 
+    /// The status of the testcase after the execution of the solution ended with `status`.
+    pub fn from_execution(status: &ExecutionStatus) -> TestcaseEvaluationStatus {
+        match status {
+            ExecutionStatus::Success => TestcaseEvaluationStatus::Solved,
+            ExecutionStatus::ReturnCode(_) => TestcaseEvaluationStatus::RuntimeError,
+            ExecutionStatus::Signal(_, _) => TestcaseEvaluationStatus::RuntimeError,
+            ExecutionStatus::TimeLimitExceeded => TestcaseEvaluationStatus::TimeLimitExceeded,
+            ExecutionStatus::SysTimeLimitExceeded => TestcaseEvaluationStatus::TimeLimitExceeded,
+            ExecutionStatus::WallTimeLimitExceeded => {
+                TestcaseEvaluationStatus::WallTimeLimitExceeded
+            }
+            ExecutionStatus::MemoryLimitExceeded => TestcaseEvaluationStatus::MemoryLimitExceeded,
+            ExecutionStatus::InternalError(_) => TestcaseEvaluationStatus::Failed,
+        }
+    }
+
     /// Whether the testcase evaluation has completed successfully.
     pub fn is_success(&self) -> bool {
         matches!(self, TestcaseEvaluationStatus::Accepted(_))
@@ -361,6 +410,7 @@ impl UIState {
             max_score: task.subtasks.values().map(|s| s.max_score).sum(),
             task: task.clone(),
             solutions: HashMap::new(),
+            duplicates: HashMap::new(),
             compilations: HashMap::new(),
             generations,
             evaluations: HashMap::new(),
@@ -386,12 +436,18 @@ impl UIState {
                     let Some(solution_result) = solution_result else {
                         continue;
                     };
-                    let testcase_results: Vec<Option<TestcaseEvaluationResult>> = self
-                        .task
-                        .subtasks[&subtask.id]
+                    let testcases = self.task.subtasks[&subtask.id]
                         .testcases
                         .iter()
-                        .map(|testcase_id| (&solution_result.testcases[testcase_id].status).into())
+                        .map(|testcase_id| &solution_result.testcases[testcase_id]);
+                    // The testcases skipped because the subtask already failed a testcase of one of
+                    // its dependencies are scored anyway, and they don't change the outcome of the
+                    // check.
+                    let testcase_results: Vec<Option<TestcaseEvaluationResult>> = testcases
+                        .filter(|tc| {
+                            tc.status != TestcaseEvaluationStatus::Skipped || tc.score.is_none()
+                        })
+                        .map(|tc| (&tc.status).into())
                         .collect();
                     if testcase_results.iter().any(Option::is_none) {
                         continue;
@@ -412,15 +468,85 @@ impl UIState {
         }
         result
     }
+
+    /// The reasons for failing the evaluation according to the exit policy, empty if the
+    /// evaluation should succeed.
+    ///
+    /// This function should be called only after all the executions have completed.
+    pub fn exit_failures(&self, policy: &ExitPolicy) -> Vec<String> {
+        let mut failures = policy.common_failures(&self.compilations, &self.diagnostics);
+        if policy.contains(ExitCondition::OfficialSolution) {
+            for (subtask_id, subtask) in self.generations.iter().sorted_by_key(|(id, _)| **id) {
+                for (testcase_id, testcase) in
+                    subtask.testcases.iter().sorted_by_key(|(id, _)| **id)
+                {
+                    let Some(result) = &testcase.solution else {
+                        continue;
+                    };
+                    if result.status != ExecutionStatus::Success {
+                        failures.push(format!(
+                            "[{}] The official solution failed on testcase {} of subtask {}: {:?}",
+                            ExitCondition::OfficialSolution,
+                            testcase_id,
+                            subtask_id,
+                            result.status
+                        ));
+                    }
+                }
+            }
+        }
+        if policy.contains(ExitCondition::Scores) {
+            let outcomes = self
+                .run_solution_checks()
+                .into_iter()
+                .filter(|outcome| !outcome.success)
+                .sorted_by(|a, b| (&a.solution, a.subtask_id).cmp(&(&b.solution, b.subtask_id)));
+            for outcome in outcomes {
+                let subtask = &self.task.subtasks[&outcome.subtask_id];
+                let name = match self.solutions.get(&outcome.solution) {
+                    Some(solution) => solution.name.clone(),
+                    None => outcome.solution.to_string_lossy().to_string(),
+                };
+                failures.push(format!(
+                    "[{}] {} doesn't match @check-{} on subtask {}",
+                    ExitCondition::Scores,
+                    name,
+                    outcome.check.result.as_str(),
+                    subtask.name.as_deref().unwrap_or("?"),
+                ));
+            }
+        }
+        failures
+    }
 }
 
 impl UIStateT for UIState {
     /// Apply a `UIMessage` to this state.
     fn apply(&mut self, message: UIMessage) {
+        // the duplicated solutions are not evaluated, they get the same results of the original
+        let mirrored = match message.ioi_solution().and_then(|s| self.duplicates.get(s)) {
+            Some(duplicates) => duplicates
+                .iter()
+                .filter_map(|duplicate| message.with_ioi_solution(duplicate))
+                .collect_vec(),
+            None => vec![],
+        };
+        for message in mirrored {
+            self.apply(message);
+        }
         match message {
             UIMessage::StopUI => {}
             UIMessage::ServerStatus { status } => self.executor_status = Some(status),
             UIMessage::Solutions { solutions } => {
+                self.duplicates.clear();
+                for info in &solutions {
+                    if let Some(original) = &info.duplicate_of {
+                        self.duplicates
+                            .entry(original.clone())
+                            .or_default()
+                            .push(info.path.clone());
+                    }
+                }
                 self.solutions = solutions
                     .into_iter()
                     .map(|info| (info.path.clone(), info))
@@ -552,37 +678,14 @@ impl UIStateT for UIState {
                         testcase.status = TestcaseEvaluationStatus::Solving
                     }
                     UIExecutionStatus::Done { result } => {
-                        match result.status {
-                            ExecutionStatus::Success => {
-                                testcase.status = TestcaseEvaluationStatus::Solved
-                            }
-                            ExecutionStatus::ReturnCode(_) => {
-                                testcase.status = TestcaseEvaluationStatus::RuntimeError
-                            }
-                            ExecutionStatus::Signal(_, _) => {
-                                testcase.status = TestcaseEvaluationStatus::RuntimeError
-                            }
-                            ExecutionStatus::TimeLimitExceeded => {
-                                testcase.status = TestcaseEvaluationStatus::TimeLimitExceeded
-                            }
-                            ExecutionStatus::SysTimeLimitExceeded => {
-                                testcase.status = TestcaseEvaluationStatus::TimeLimitExceeded
-                            }
-                            ExecutionStatus::WallTimeLimitExceeded => {
-                                testcase.status = TestcaseEvaluationStatus::WallTimeLimitExceeded
-                            }
-                            ExecutionStatus::MemoryLimitExceeded => {
-                                testcase.status = TestcaseEvaluationStatus::MemoryLimitExceeded
-                            }
-                            ExecutionStatus::InternalError(_) => {
-                                testcase.status = TestcaseEvaluationStatus::Failed
-                            }
-                        }
+                        testcase.status = TestcaseEvaluationStatus::from_execution(&result.status);
                         testcase.results[part] = Some(result);
                     }
-                    UIExecutionStatus::Skipped => {
+                    // a part can be skipped because of the failure of a previous one
+                    UIExecutionStatus::Skipped if !testcase.status.has_completed() => {
                         testcase.status = TestcaseEvaluationStatus::Skipped
                     }
+                    UIExecutionStatus::Skipped => {}
                 }
             }
             UIMessage::IOIChecker {
@@ -612,8 +715,10 @@ impl UIStateT for UIState {
                 solution,
                 score,
                 message,
+                verdict,
                 ..
             } => {
+                let message = verdict.unwrap_or(message);
                 let task = &self.task;
                 let eval = self
                     .evaluations
@@ -621,6 +726,19 @@ impl UIStateT for UIState {
                     .or_insert_with(|| SolutionEvaluationState::new(task));
                 let testcase = eval.testcases.get_mut(&testcase).expect("Missing testcase");
                 testcase.score = Some(score);
+                let selected_run = testcase
+                    .seed_runs
+                    .iter()
+                    .position(|run| run.as_ref().is_some_and(|run| run.selected));
+                if let Some(run) = selected_run {
+                    // the status is left by the last completed run, not by the selected one
+                    testcase.status = match testcase.results.get(run) {
+                        Some(Some(result)) if !result.status.is_success() => {
+                            TestcaseEvaluationStatus::from_execution(&result.status)
+                        }
+                        _ => TestcaseEvaluationStatus::Solved,
+                    };
+                }
                 if !testcase.status.has_completed() {
                     testcase.status = match ScoreStatus::from_score(score, 1.0) {
                         ScoreStatus::WrongAnswer => TestcaseEvaluationStatus::WrongAnswer(message),
@@ -629,6 +747,33 @@ impl UIStateT for UIState {
                     };
                 }
             }
+            UIMessage::IOISeedRun {
+                testcase,
+                solution,
+                run,
+                num_runs,
+                seed,
+                score,
+                message,
+                selected,
+                ..
+            } => {
+                let task = &self.task;
+                let eval = self
+                    .evaluations
+                    .entry(solution)
+                    .or_insert_with(|| SolutionEvaluationState::new(task));
+                let testcase = eval.testcases.get_mut(&testcase).expect("Missing testcase");
+                if testcase.seed_runs.len() != num_runs {
+                    testcase.seed_runs = vec![None; num_runs];
+                }
+                testcase.seed_runs[run] = Some(SeedRunState {
+                    seed,
+                    score,
+                    message,
+                    selected,
+                });
+            }
             UIMessage::IOISubtaskScore {
                 subtask,
                 solution,
@@ -687,7 +832,7 @@ impl UIStateT for UIState {
                     .expect("Statement dependency step is gone")
                     .status = status;
             }
-            UIMessage::Diagnostic { diagnostic } => {
+            UIMessage::Diagnostic { diagnostic } | UIMessage::Deprecation { diagnostic, .. } => {
                 self.diagnostics.add_diagnostic(diagnostic);
             }
             UIMessage::TerryTask { .. }
@@ -702,4 +847,11 @@ impl UIStateT for UIState {
     fn finish(&mut self) {
         finish_ui::FinishUI::print(self);
     }
+
+    fn restart(&self, task: &TaskFormat, config: ExecutionDAGConfig) -> Option<Self> {
+        match task {
+            TaskFormat::IOI(task) => Some(UIState::new(task, config)),
+            _ => None,
+        }
+    }
 }
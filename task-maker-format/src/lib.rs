@@ -20,23 +20,25 @@ extern crate log;
 #[macro_use]
 extern crate pest_derive;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
-use anyhow::Error;
+use anyhow::{Context, Error};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
 pub use detect_format::find_task;
+pub use git::git_commit;
+pub use hooks::{register_evaluation_hook, EvaluationHook, EvaluationHookBuilder};
 pub use sanity_checks::get_sanity_check_list;
 pub use sanity_checks::SanityCheckCategory;
 pub use source_file::SourceFile;
 pub use tag::{Tag, VALID_TAGS};
 pub use task_format::*;
 use task_maker_dag::ExecutionDAG;
-use task_maker_diagnostics::Diagnostic;
+use task_maker_diagnostics::{Diagnostic, DiagnosticCategory};
 use task_maker_lang::{GraderMap, LanguageManager};
 
 use crate::ioi::task_info::IOITaskInfo;
@@ -46,7 +48,15 @@ use crate::terry::{Seed, TerryTask};
 use crate::ui::UI;
 pub use testcase_score_status::ScoreStatus;
 
+// used by `make_evaluation_hook!`
+#[doc(hidden)]
+pub use inventory;
+#[doc(hidden)]
+pub use paste;
+
 mod detect_format;
+mod git;
+mod hooks;
 pub mod ioi;
 mod sanity_checks;
 mod solution;
@@ -56,6 +66,7 @@ mod task_format;
 pub mod terry;
 mod testcase_score_status;
 pub mod ui;
+pub mod white_diff;
 
 lazy_static! {
     /// Directory where the data files are stored. It is taken from the `TM_DATA_DIR` environment
@@ -72,6 +83,10 @@ lazy_static! {
     };
 }
 
+/// Version of the structure of [`TaskInfo`]. It is bumped every time a field is added, changed or
+/// removed.
+pub const TASK_INFO_VERSION: u64 = 3;
+
 /// Information about a parsed task, returned with the `--task-info` option.
 #[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
 pub enum TaskInfo {
@@ -100,6 +115,43 @@ pub struct EvaluationConfig {
     pub seed: Option<Seed>,
     /// Do not write any file inside the task directory.
     pub dry_run: bool,
+    /// What to do when a checker outputs a score outside the valid range.
+    pub checker_score_policy: ioi::CheckerScorePolicy,
+    /// Evaluate only the testcases of these subtasks. If left empty all the subtasks are evaluated.
+    pub subtask_filter: Vec<u32>,
+    /// Evaluate only these testcases. If left empty all the testcases are evaluated.
+    pub testcase_filter: Vec<u32>,
+    /// Evaluate only the testcases with at least one of these tags. If left empty all the testcases
+    /// are evaluated.
+    pub tag_filter: Vec<String>,
+    /// Generate the output files with this solution instead of the official one of the task.
+    pub official_solution: Option<PathBuf>,
+    /// Use the output files in `output/` as the correct outputs, without generating them with the
+    /// official solution.
+    pub trust_outputs: bool,
+    /// Warn when the accepted solutions produce different outputs on the same testcase.
+    pub check_output_determinism: bool,
+    /// Run the C/C++ solutions also compiled with the sanitizers, warning about their findings.
+    pub sanitize: bool,
+    /// Run the solutions also under this memory profiler, reporting their peak memory usage.
+    pub profile: Option<ioi::ProfilerTool>,
+    /// Profile the solutions on all the evaluated testcases, instead of only on the last one.
+    pub profile_all_testcases: bool,
+    /// Skip the evaluation of the subtasks that already failed because one of their dependencies
+    /// failed.
+    pub skip_dependent_subtasks: bool,
+    /// Evaluate the solutions only on a representative subset of the testcases, with partial
+    /// scores.
+    pub quick: bool,
+    /// Evaluate the solutions this many times on each testcase, each time with a different seed.
+    pub seed_runs: Option<usize>,
+    /// How the scores of the runs with different seeds are aggregated.
+    pub seed_aggregation: ioi::SeedAggregation,
+    /// Where to keep the auxiliary files of LaTeX between the compilations of the booklets, if
+    /// anywhere.
+    pub latex_cache_dir: Option<PathBuf>,
+    /// How the solutions with the same source are detected, for evaluating them only once.
+    pub dedup_solutions: ioi::SolutionDedup,
 }
 
 /// The data for an evaluation, including the DAG and the UI channel.
@@ -112,6 +164,26 @@ pub struct EvaluationData {
     pub solutions: Vec<Solution>,
     /// The sender of the UI.
     pub sender: Arc<Mutex<ui::UIMessageSender>>,
+    /// What to do when a checker outputs a score outside the valid range.
+    pub checker_score_policy: ioi::CheckerScorePolicy,
+    /// When set, the outputs of the accepted solutions are compared after the evaluation.
+    pub output_determinism: Option<Arc<Mutex<ioi::OutputDeterminism>>>,
+    /// When set, the solutions are also run compiled with the sanitizers.
+    pub sanitizer: Option<ioi::Sanitizer>,
+    /// When set, the solutions are also run under a memory profiler.
+    pub profiler: Option<ioi::Profiler>,
+    /// When set, the solutions are evaluated more times on each testcase, with different seeds.
+    pub seed_matrix: Option<Arc<Mutex<ioi::SeedMatrix>>>,
+    /// Directory where to keep the files and the results of the evaluation of each solution on
+    /// each testcase, if any. The files are written by the client, so this is not part of the
+    /// configuration of the DAG.
+    pub keep_artifacts: Option<PathBuf>,
+    /// The executables already compiled in this evaluation, shared by all the `SourceFile`s of the
+    /// same source, so that each of them is compiled only once.
+    pub(crate) executables: HashMap<source_file::ExecutableKey, task_maker_dag::File>,
+    /// The evaluation hooks called by all the phases of this evaluation, built when the evaluation
+    /// starts.
+    pub(crate) hooks: Vec<Arc<dyn EvaluationHook>>,
 }
 
 impl EvaluationData {
@@ -124,6 +196,14 @@ impl EvaluationData {
                 dag: ExecutionDAG::new(),
                 solutions: Default::default(),
                 sender: Arc::new(Mutex::new(sender)),
+                checker_score_policy: Default::default(),
+                output_determinism: None,
+                sanitizer: None,
+                profiler: None,
+                seed_matrix: None,
+                keep_artifacts: None,
+                executables: HashMap::new(),
+                hooks: hooks::evaluation_hooks(),
             },
             receiver,
         )
@@ -133,6 +213,11 @@ impl EvaluationData {
     pub fn add_diagnostic(&self, diagnostic: Diagnostic) -> Result<(), Error> {
         self.sender.add_diagnostic(diagnostic)
     }
+
+    /// Notify the UI that a deprecated feature is being used.
+    pub fn add_deprecation(&self, feature: &str, diagnostic: Diagnostic) -> Result<(), Error> {
+        self.sender.add_deprecation(feature, diagnostic)
+    }
 }
 
 /// What can send [`UIMessage`](ui/enum.UIMessage.html)s.
@@ -144,6 +229,14 @@ pub trait UISender {
     fn add_diagnostic(&self, diagnostic: Diagnostic) -> Result<(), Error> {
         self.send(ui::UIMessage::Diagnostic { diagnostic })
     }
+
+    /// Send a deprecation warning to the UI.
+    fn add_deprecation(&self, feature: &str, diagnostic: Diagnostic) -> Result<(), Error> {
+        self.send(ui::UIMessage::Deprecation {
+            feature: feature.into(),
+            diagnostic: diagnostic.with_category(DiagnosticCategory::Deprecation),
+        })
+    }
 }
 
 /// Implement `.send(message)` for `Mutex<UIMessageSender>` in order to do
@@ -220,6 +313,16 @@ impl EvaluationConfig {
     }
 }
 
+/// Compute the hex BLAKE3 hash of the content of a file.
+pub(crate) fn hash_file(path: &Path) -> Result<String, Error> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let mut hasher = blake3::Hasher::new();
+    std::io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Cannot read {}", path.display()))?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
 /// List all the files inside `cwd` that matches a list of glob patterns. The results are in the
 /// same order of the patterns.
 pub(crate) fn list_files<P: AsRef<Path>, S: AsRef<str>>(cwd: P, patterns: Vec<S>) -> Vec<PathBuf> {
@@ -3,7 +3,7 @@
 use std::sync::Mutex;
 
 use anyhow::Error;
-use task_maker_diagnostics::Diagnostic;
+use task_maker_diagnostics::{Diagnostic, DiagnosticCategory};
 
 use crate::EvaluationData;
 
@@ -48,6 +48,18 @@ impl SanityCheckCategory {
             SanityCheckCategory::Task => "Task",
         }
     }
+
+    /// The category of the diagnostics emitted by the sanity checks of this category.
+    pub fn diagnostic_category(&self) -> DiagnosticCategory {
+        match self {
+            SanityCheckCategory::Attachments => DiagnosticCategory::Attachments,
+            SanityCheckCategory::Checker => DiagnosticCategory::Checker,
+            SanityCheckCategory::Io => DiagnosticCategory::Generation,
+            SanityCheckCategory::Solutions => DiagnosticCategory::Solutions,
+            SanityCheckCategory::Statement => DiagnosticCategory::Statement,
+            SanityCheckCategory::Task => DiagnosticCategory::Task,
+        }
+    }
 }
 
 /// Trait that describes the behavior of a sanity check.
@@ -147,11 +159,10 @@ impl<Task> SanityChecks<Task> {
         let mut state = self.state.lock().unwrap();
         for check in state.sanity_checks.iter_mut() {
             if let Err(e) = check.pre_hook(task, eval) {
-                eval.add_diagnostic(Diagnostic::warning(format!(
-                    "Sanity check {} failed: {}",
-                    check.name(),
-                    e
-                )))?;
+                eval.add_diagnostic(
+                    Diagnostic::warning(format!("Sanity check {} failed: {}", check.name(), e))
+                        .with_category(check.category().diagnostic_category()),
+                )?;
             }
         }
         Ok(())
@@ -163,11 +174,10 @@ impl<Task> SanityChecks<Task> {
         let mut state = self.state.lock().unwrap();
         for check in state.sanity_checks.iter_mut() {
             if let Err(e) = check.post_hook(task, eval) {
-                eval.add_diagnostic(Diagnostic::warning(format!(
-                    "Sanity check {} failed: {}",
-                    check.name(),
-                    e
-                )))?;
+                eval.add_diagnostic(
+                    Diagnostic::warning(format!("Sanity check {} failed: {}", check.name(), e))
+                        .with_category(check.category().diagnostic_category()),
+                )?;
             }
         }
         Ok(())
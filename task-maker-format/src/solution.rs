@@ -10,7 +10,7 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use task_maker_diagnostics::{CodeSpan, Diagnostic};
 
-use task_maker_lang::GraderMap;
+use task_maker_lang::{find_build_manifest, GraderMap};
 
 use crate::{EvaluationData, SourceFile};
 
@@ -21,6 +21,10 @@ pub struct Solution {
     pub source_file: Arc<SourceFile>,
     /// The set of checks to perform on the solution.
     pub checks: Vec<SolutionCheck>,
+    /// The intended complexity of the solution, from its `@complexity` annotation.
+    pub complexity: Option<String>,
+    /// The static metrics of the source file of the solution.
+    pub metrics: SourceMetrics,
 }
 
 impl Solution {
@@ -47,8 +51,24 @@ impl Solution {
         Some(Self {
             source_file: Arc::new(source_file),
             checks: SolutionCheck::extract_check_list(path, eval).ok()?,
+            complexity: Solution::extract_complexity(path).ok()?,
+            metrics: SourceMetrics::new(path),
         })
     }
+
+    /// Extract the intended complexity of a solution from its `@complexity` annotation, for example
+    /// `// @complexity: O(N log N)`. It's free text, used only for grouping the solutions in the
+    /// reports. If there are more annotations the first one is used.
+    pub fn extract_complexity<P: AsRef<Path>>(path: P) -> Result<Option<String>, Error> {
+        let (_, content) = read_annotations(path.as_ref())?;
+        let complexity = find_annotations(&content, "@complexity:")
+            .map(|(_, line, start)| {
+                let value = &line[start + "@complexity:".len()..];
+                value.trim().trim_end_matches("*/").trim().to_string()
+            })
+            .find(|value| !value.is_empty());
+        Ok(complexity)
+    }
 }
 
 /// Some information about a solution.
@@ -62,6 +82,14 @@ pub struct SolutionInfo {
     pub language_name: String,
     /// The list of checks specified inside the source file.
     pub checks: Vec<SolutionCheck>,
+    /// The intended complexity specified inside the source file.
+    pub complexity: Option<String>,
+    /// The solution with the same source this solution is a duplicate of, if any. The duplicates
+    /// are not evaluated, they get the results of the original solution.
+    pub duplicate_of: Option<PathBuf>,
+    /// The static metrics of the source file.
+    #[serde(default)]
+    pub metrics: SourceMetrics,
 }
 
 impl From<&Solution> for SolutionInfo {
@@ -71,6 +99,35 @@ impl From<&Solution> for SolutionInfo {
             name: solution.source_file.name(),
             language_name: solution.source_file.language().name().into(),
             checks: solution.checks.clone(),
+            complexity: solution.complexity.clone(),
+            duplicate_of: None,
+            metrics: solution.metrics,
+        }
+    }
+}
+
+/// The static metrics of the source file of a solution, computed without compiling it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMetrics {
+    /// The size of the source file, in bytes.
+    pub size: u64,
+    /// The number of lines of the source file that are not empty.
+    pub lines: usize,
+}
+
+impl SourceMetrics {
+    /// Compute the metrics of the source file at `path`. The solutions that are not a single file
+    /// (e.g. the directories with a build manifest) or that cannot be read get empty metrics.
+    pub fn new(path: &Path) -> SourceMetrics {
+        let Ok(content) = std::fs::read(path) else {
+            return SourceMetrics::default();
+        };
+        SourceMetrics {
+            size: content.len() as u64,
+            lines: String::from_utf8_lossy(&content)
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .count(),
         }
     }
 }
@@ -287,13 +344,13 @@ impl SolutionCheckResult {
 }
 
 impl SolutionCheck {
-    /// Try to extract the list of [`SolutionCheck`] from a file.
+    /// Try to extract the list of [`SolutionCheck`] from a file. The checks of a build directory
+    /// are in its build manifest.
     pub fn extract_check_list<P: AsRef<Path>>(
         path: P,
         eval: &mut EvaluationData,
     ) -> Result<Vec<Self>, Error> {
         lazy_static! {
-            static ref FIND_CHECKS: Regex = Regex::new(r".*(@check-.*)").expect("Invalid regex");
             static ref EXTRACT_CHECKS: Regex = Regex::new(
                 r"(?x)
             @check-     # signal the start of a check
@@ -310,23 +367,13 @@ impl SolutionCheck {
             .expect("Invalid regex");
         }
 
-        let path = path.as_ref();
-        let mut file = File::open(path)?;
-        let mut content = String::new();
-        file.read_to_string(&mut content)?;
+        let (source, content) = read_annotations(path.as_ref())?;
+        let path = source.strip_prefix(&eval.task_root).unwrap_or(&source);
 
         let mut checks = vec![];
-        let mut file_offset = 0;
-        for line in content.split('\n') {
-            file_offset += line.len() + 1; // Includes the \n.
-            let found = match FIND_CHECKS.captures(line).and_then(|c| c.get(1)) {
-                None => continue,
-                Some(found) => found,
-            };
+        for (line_offset, line, start) in find_annotations(&content, "@check-") {
             let captures = EXTRACT_CHECKS.captures_iter(line).next();
-            let path = path.strip_prefix(&eval.task_root).unwrap_or(path);
-            // file_offset includes the current line length.
-            let offset = file_offset - 1 - line.len() + found.start();
+            let offset = line_offset + start;
 
             if let Some(captures) = captures {
                 let capture = captures.get(0).unwrap();
@@ -340,7 +387,7 @@ impl SolutionCheck {
                     checks.push(Self::new(result, pattern, code_span.clone()));
                 }
             } else {
-                let len = found.end() - found.start();
+                let len = line.len() - start;
                 let mut diagnostic = Diagnostic::error(format!(
                     "In '{}' the check '{}' is not valid",
                     path.display(),
@@ -356,6 +403,34 @@ impl SolutionCheck {
     }
 }
 
+/// Read the file with the annotations of a solution, like the `@check` rules. It's the solution
+/// itself, or its build manifest if the solution is a build directory. Returns the path of the
+/// file and its content.
+fn read_annotations(path: &Path) -> Result<(PathBuf, String), Error> {
+    let path = find_build_manifest(path).unwrap_or_else(|| path.to_path_buf());
+    let mut file = File::open(&path)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok((path, content))
+}
+
+/// Find the lines of `content` with an annotation starting with `marker`. For each of them the
+/// offset of the line in `content`, the line and the position of the last `marker` in the line are
+/// returned.
+fn find_annotations<'a>(
+    content: &'a str,
+    marker: &'a str,
+) -> impl Iterator<Item = (usize, &'a str, usize)> + 'a {
+    content
+        .split('\n')
+        .scan(0, |offset, line| {
+            let line_offset = *offset;
+            *offset += line.len() + 1; // Includes the \n.
+            Some((line_offset, line))
+        })
+        .filter_map(move |(offset, line)| line.rfind(marker).map(|start| (offset, line, start)))
+}
+
 /// Split the patterns by whitespace.
 fn split_patterns(patterns: &str) -> Vec<&str> {
     let mut result = vec![];
@@ -372,7 +447,7 @@ mod tests {
     use crate::EvaluationData;
     use anyhow::Error;
 
-    use crate::solution::{SolutionCheck, SolutionCheckResult};
+    use crate::solution::{Solution, SolutionCheck, SolutionCheckResult, SourceMetrics};
 
     fn get_checks(source: &str) -> Result<Vec<SolutionCheck>, Error> {
         let tmpdir = tempfile::TempDir::new().unwrap();
@@ -474,4 +549,30 @@ mod tests {
         assert_eq!(checks[1].subtask_name_pattern, "st2");
         assert_eq!(checks[1].code_span.as_str(), "@check-accepted: \tst1 \t\u{000B}\u{000C}\u{00A0}\u{1680}\u{2000}\u{2001}\u{2002}\u{2003}\u{2004}\u{2005}\u{2006}\u{200A} st2\t  \t   ");
     }
+
+    #[test]
+    fn test_extract_complexity() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("source.txt");
+        std::fs::write(
+            &path,
+            "/* @complexity: */\n/* @complexity:  O(N log N) */\n",
+        )
+        .unwrap();
+        let complexity = Solution::extract_complexity(&path).unwrap();
+        assert_eq!(complexity.as_deref(), Some("O(N log N)"));
+        std::fs::write(&path, "# @check-accepted: st1\n").unwrap();
+        assert_eq!(Solution::extract_complexity(&path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_source_metrics() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("source.txt");
+        std::fs::write(&path, "int main() {\n\n  \n  return 0;\n}\n").unwrap();
+        let metrics = SourceMetrics::new(&path);
+        assert_eq!(metrics, SourceMetrics { size: 31, lines: 3 });
+        let metrics = SourceMetrics::new(tmpdir.path());
+        assert_eq!(metrics, SourceMetrics::default());
+    }
 }
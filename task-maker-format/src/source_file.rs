@@ -1,12 +1,12 @@
 use std::ops::{Deref, DerefMut};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::{Context, Error};
 use serde::{Deserialize, Serialize};
 
 use task_maker_dag::*;
-use task_maker_diagnostics::Diagnostic;
+use task_maker_diagnostics::{Diagnostic, DiagnosticCode};
 use task_maker_lang::GraderMap;
 
 use crate::ui::*;
@@ -23,6 +23,20 @@ pub struct SourceFile {
     description: String,
 }
 
+/// What identifies the executable compiled from a source file: two source files with the same key
+/// produce the same executable, even if they are written to different paths.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ExecutableKey {
+    /// Path to the source file.
+    path: PathBuf,
+    /// Paths to the graders compiled together with the source file, sorted.
+    graders: Vec<PathBuf>,
+    /// Whether the executable is statically linked.
+    link_static: bool,
+    /// Whether the executable is compiled with the sanitizers.
+    sanitize: bool,
+}
+
 impl SourceFile {
     /// Make a new `SourceFile`. See
     /// [`task_maker_lang::SourceFile`](../task_maker_lang/struct.SourceFile.html) for the details.
@@ -53,6 +67,7 @@ impl SourceFile {
         description: S,
         args: I,
     ) -> Result<Execution, Error> {
+        self.share_executable(eval);
         let (comp, exec) = self.base.execute(
             &mut eval.dag,
             description,
@@ -65,6 +80,7 @@ impl SourceFile {
     /// Prepare an execution of the source file, eventually adding the compilation to the DAG.
     /// The compilation messages are sent to the UI.
     pub fn prepare(&self, eval: &mut EvaluationData) -> Result<Option<ExecutionUuid>, Error> {
+        self.share_executable(eval);
         let comp = self.base.prepare(&mut eval.dag)?;
         self.bind_compilation_exe(eval, comp)?;
         Ok(comp)
@@ -72,11 +88,45 @@ impl SourceFile {
 
     /// Prepare the source file if needed and return the executable file.
     pub fn executable(&self, eval: &mut EvaluationData) -> Result<FileUuid, Error> {
+        self.share_executable(eval);
         let (exe, comp) = self.base.executable(&mut eval.dag)?;
         self.bind_compilation_exe(eval, comp)?;
         Ok(exe)
     }
 
+    /// The key of the executable of this source file.
+    fn executable_key(&self) -> ExecutableKey {
+        let mut graders: Vec<PathBuf> = self
+            .grader_map
+            .iter()
+            .flat_map(|map| map.all_paths().map(Path::to_path_buf))
+            .collect();
+        graders.sort();
+        ExecutableKey {
+            path: self.path.clone(),
+            graders,
+            link_static: self.link_static,
+            sanitize: self.sanitize,
+        }
+    }
+
+    /// If the same source file has already been compiled in this evaluation, reuse its executable
+    /// instead of compiling it again. The executable is still copied where this source file would
+    /// have written it.
+    fn share_executable(&self, eval: &mut EvaluationData) {
+        if self.executable.lock().unwrap().is_some() {
+            return;
+        }
+        if let Some(exe) = eval.executables.get(&self.executable_key()) {
+            if eval.dag.config_mut().copy_exe || self.copy_exe {
+                if let Some(write_bin_to) = &self.write_bin_to {
+                    eval.dag.copy_file_to(exe, write_bin_to);
+                }
+            }
+            *self.executable.lock().unwrap() = Some(exe.clone());
+        }
+    }
+
     /// Bind the callbacks for the compilation callbacks.
     fn bind_compilation_exe(
         &self,
@@ -85,6 +135,9 @@ impl SourceFile {
     ) -> Result<(), Error> {
         // if there is the compilation, send to the UI the messages
         if let Some(comp_uuid) = comp {
+            if let Some(exe) = self.executable.lock().unwrap().as_ref() {
+                eval.executables.insert(self.executable_key(), exe.clone());
+            }
             let path = &self.path;
             bind_exec_callbacks!(
                 eval,
@@ -104,6 +157,7 @@ impl SourceFile {
                 if !result.status.is_success() {
                     let mut diagnostic =
                         Diagnostic::error(format!("Failed to compile {}", path.display()))
+                            .with_code(DiagnosticCode::CompilationFailed)
                             .with_note(description);
                     if let Some(stderr) = result.stderr {
                         diagnostic = diagnostic.with_help_attachment(stderr);
@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 
 use task_maker_dag::ExecutionDAGConfig;
 
+use crate::hooks::{run_evaluation_hooks, HookPhase};
 use crate::{ui, EvaluationConfig, EvaluationData, IOITask, TaskInfo, TerryTask, UI};
 
 /// The format of the task.
@@ -39,16 +40,19 @@ impl TaskFormat {
         }
     }
 
-    /// Add the executions required for evaluating this task to the execution DAG.
+    /// Add the executions required for evaluating this task to the execution DAG, including the
+    /// ones of the registered [`EvaluationHook`](crate::EvaluationHook)s.
     pub fn build_dag(
         &mut self,
         eval: &mut EvaluationData,
         config: &EvaluationConfig,
     ) -> Result<(), Error> {
+        run_evaluation_hooks(HookPhase::BeforeBuild, self, eval)?;
         match self {
-            TaskFormat::IOI(task) => task.build_dag(eval, config),
-            TaskFormat::Terry(task) => task.build_dag(eval, config),
+            TaskFormat::IOI(task) => task.build_dag(eval, config)?,
+            TaskFormat::Terry(task) => task.build_dag(eval, config)?,
         }
+        run_evaluation_hooks(HookPhase::AfterBuild, self, eval)
     }
 
     /// Hook called after the execution completed, useful for sending messages to the UI about the
@@ -60,6 +64,12 @@ impl TaskFormat {
         }
     }
 
+    /// Hook called after the execution completed, calling the registered
+    /// [`EvaluationHook`](crate::EvaluationHook)s.
+    pub fn evaluation_post_hook(&self, eval: &mut EvaluationData) -> Result<(), Error> {
+        run_evaluation_hooks(HookPhase::AfterEvaluation, self, eval)
+    }
+
     /// Clean the task folder removing the files that can be generated automatically.
     pub fn clean(&self) -> Result<(), Error> {
         match self {
@@ -5,13 +5,15 @@ use tui::text::{Span, Spans};
 use tui::widgets::{Block, Borders, Paragraph};
 
 use crate::terry::finish_ui::FinishUI;
-use crate::terry::ui_state::{SolutionState, SolutionStatus, UIState};
-use crate::terry::{CaseStatus, SolutionOutcome};
+use crate::terry::ui_state::{CaseOutcome, SolutionState, SolutionStatus, UIState};
+use crate::terry::{SolutionAlert, SolutionOutcome};
 use crate::ui::curses::{
-    compilation_status_text, draw_compilations, inner_block, render_block, render_server_status,
-    CursesDrawer, CursesUI as GenericCursesUI, FrameType, GREEN, RED, YELLOW,
+    compilation_status_text, draw_compilations, inner_block, live_stderr_height, render_block,
+    render_live_stderr, render_server_status, CursesDrawer, CursesUI as GenericCursesUI, FrameType,
+    GREEN, RED, YELLOW,
 };
 use crate::ui::FinishUIUtils;
+use crate::ScoreStatus;
 
 /// An animated UI for Terry tasks, dynamically refreshing using curses as a backend.
 pub(crate) type CursesUI = GenericCursesUI<UIState, Drawer, FinishUI>;
@@ -55,9 +57,10 @@ fn draw_frame(state: &UIState, f: &mut FrameType, loading: char, frame_index: us
         .map(|s| s.connected_workers.len())
         .unwrap_or(0) as u16
         + 2;
+    let stderr_len = live_stderr_height(state.executor_status.as_ref());
     let total_height = f.size().height;
     // fixed size section heights
-    let top_height = header_len + compilations_len;
+    let top_height = header_len + compilations_len + stderr_len;
     // if the sections don't just fit, reduce the size of the workers until they fit but
     // without shortening it more than 3 lines (aka box + 1 worker).
     if top_height + evaluations_len + workers_len > total_height {
@@ -73,6 +76,7 @@ fn draw_frame(state: &UIState, f: &mut FrameType, loading: char, frame_index: us
                 Constraint::Length(header_len),
                 Constraint::Length(compilations_len),
                 Constraint::Min(0),
+                Constraint::Length(stderr_len),
                 Constraint::Length(workers_len),
             ]
             .as_ref(),
@@ -94,9 +98,10 @@ fn draw_frame(state: &UIState, f: &mut FrameType, loading: char, frame_index: us
     }
     render_block(f, chunks[2], " Evaluations ");
     draw_evaluations(f, inner_block(chunks[2]), state, loading);
+    render_live_stderr(f, chunks[3], state.executor_status.as_ref());
     render_server_status(
         f,
-        chunks[3],
+        chunks[4],
         state.executor_status.as_ref(),
         loading,
         frame_index,
@@ -131,7 +136,7 @@ fn draw_evaluations(frame: &mut FrameType, rect: Rect, state: &UIState, loading:
                 loading,
             ));
             spans.push(Span::raw("  "));
-            spans.append(&mut evaluation_line(solution_state));
+            spans.append(&mut evaluation_line(solution_state, loading));
             spans.into()
         })
         .collect();
@@ -140,44 +145,53 @@ fn draw_evaluations(frame: &mut FrameType, rect: Rect, state: &UIState, loading:
 }
 
 /// Return the line with the status of the evaluation of a solution.
-fn evaluation_line<'a>(state: &SolutionState) -> Vec<Span<'a>> {
+fn evaluation_line<'a>(state: &SolutionState, loading: char) -> Vec<Span<'a>> {
     match &state.status {
         SolutionStatus::Pending => vec![],
-        SolutionStatus::Generating => vec![Span::raw("Generating")],
-        SolutionStatus::Generated => vec![Span::raw("Generated")],
-        SolutionStatus::Validating => vec![Span::raw("Validating")],
-        SolutionStatus::Validated => vec![Span::raw("Validated")],
-        SolutionStatus::Solving => vec![Span::raw("Solving")],
-        SolutionStatus::Solved => vec![Span::raw("Solved")],
-        SolutionStatus::Checking => vec![Span::raw("Checking")],
+        SolutionStatus::Generating => evaluation_progress(0, false, loading),
+        SolutionStatus::Generated => evaluation_progress(0, true, loading),
+        SolutionStatus::Validating => evaluation_progress(1, false, loading),
+        SolutionStatus::Validated => evaluation_progress(1, true, loading),
+        SolutionStatus::Solving => evaluation_progress(2, false, loading),
+        SolutionStatus::Solved => evaluation_progress(2, true, loading),
+        SolutionStatus::Checking => evaluation_progress(3, false, loading),
         SolutionStatus::Done => evaluation_outcome(state.outcome.as_ref()),
-        SolutionStatus::Failed(e) => vec![Span::raw(format!("Failed: {}", e))],
+        SolutionStatus::Failed(e) => vec![Span::styled(format!("Failed: {}", e), *RED)],
         SolutionStatus::Skipped => vec![Span::raw("Skipped")],
     }
 }
 
+/// Return the progress of the steps of the evaluation of a solution: the steps before `step` are
+/// done, `step` is running unless it's `done`, the following ones are still pending.
+fn evaluation_progress<'a>(step: usize, done: bool, loading: char) -> Vec<Span<'a>> {
+    ["Generation", "Validation", "Evaluation", "Checker"]
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            if index < step || (index == step && done) {
+                Span::styled(format!("{} ", name), *GREEN)
+            } else if index == step {
+                Span::styled(format!("{} {} ", name, loading), *YELLOW)
+            } else {
+                Span::raw(format!("{} ", name))
+            }
+        })
+        .collect()
+}
+
 /// Return the line with the outcome of the evaluation of a solution.
 fn evaluation_outcome<'a>(outcome: Option<&Result<SolutionOutcome, String>>) -> Vec<Span<'a>> {
     match outcome {
         Some(Ok(outcome)) => {
-            let mut res = Vec::new();
-            for (val, feed) in outcome
+            let mut res = evaluation_grid(outcome);
+            for alert in outcome
                 .validation
-                .cases
+                .alerts
                 .iter()
-                .zip(outcome.feedback.cases.iter())
+                .chain(outcome.feedback.alerts.iter())
             {
-                match val.status {
-                    CaseStatus::Missing => res.push(Span::styled("m ", *YELLOW)),
-                    CaseStatus::Parsed => {
-                        if feed.correct {
-                            res.push(Span::styled("c ", *GREEN))
-                        } else {
-                            res.push(Span::styled("w ", *RED))
-                        }
-                    }
-                    CaseStatus::Invalid => res.push(Span::styled("i ", *RED)),
-                }
+                res.push(Span::raw(" "));
+                res.push(evaluation_alert(alert));
             }
             res
         }
@@ -186,6 +200,37 @@ fn evaluation_outcome<'a>(outcome: Option<&Result<SolutionOutcome, String>>) ->
     }
 }
 
+/// Return the grid with the outcome of the test cases of a solution, like `[AAWX]`, where each
+/// letter is the outcome of a test case and the brackets are colored by the score.
+fn evaluation_grid<'a>(outcome: &SolutionOutcome) -> Vec<Span<'a>> {
+    let par_style = match ScoreStatus::from_score(outcome.score, 1.0) {
+        ScoreStatus::Accepted => *GREEN,
+        ScoreStatus::WrongAnswer => *RED,
+        ScoreStatus::PartialScore => *YELLOW,
+    };
+    let mut res = vec![Span::styled("[", par_style)];
+    for case in CaseOutcome::from_outcome(outcome) {
+        let letter = case.letter().to_string();
+        res.push(match case {
+            CaseOutcome::Correct => Span::styled(letter, *GREEN),
+            CaseOutcome::Wrong | CaseOutcome::Invalid => Span::styled(letter, *RED),
+            CaseOutcome::Missing => Span::raw(letter),
+        });
+    }
+    res.push(Span::styled("]", par_style));
+    res
+}
+
+/// Return an alert sent by the checker, colored by its severity.
+fn evaluation_alert<'a>(alert: &SolutionAlert) -> Span<'a> {
+    let text = format!("[{}] {}", alert.severity, alert.message);
+    match alert.severity.as_str() {
+        "warning" => Span::styled(text, *YELLOW),
+        "danger" | "error" => Span::styled(text, *RED),
+        _ => Span::raw(text),
+    }
+}
+
 /// Return the score column of a solution.
 fn evaluation_score<'a>(max_score: f64, state: &SolutionState, loading: char) -> Span<'a> {
     match state.status {
@@ -214,3 +259,37 @@ fn evaluation_score<'a>(max_score: f64, state: &SolutionState, loading: char) ->
         SolutionStatus::Failed(_) | SolutionStatus::Skipped => Span::raw(" X "),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::terry::parse_outcome;
+
+    use super::*;
+
+    #[test]
+    fn test_evaluation_grid() {
+        let outcome = br#"{
+            "score": 0.5,
+            "validation": {
+                "cases": [{"status": "parsed"}, {"status": "parsed"}, {"status": "missing"}],
+                "alerts": [{"severity": "warning", "message": "Invalid line"}]
+            },
+            "feedback": {
+                "cases": [{"correct": true}, {"correct": false}, {"correct": false}],
+                "alerts": []
+            }
+        }"#;
+        let outcome = parse_outcome(outcome).unwrap();
+        let grid = evaluation_grid(&outcome);
+        let text: String = grid.iter().map(|span| span.content.as_ref()).collect();
+        assert_eq!(text, "[AWX]");
+        assert_eq!(grid[0].style, *YELLOW);
+        assert_eq!(grid[1].style, *GREEN);
+        assert_eq!(grid[2].style, *RED);
+        assert_eq!(grid[4].style, *YELLOW);
+
+        let line = evaluation_outcome(Some(&Ok(outcome)));
+        let text: String = line.iter().map(|span| span.content.as_ref()).collect();
+        assert_eq!(text, "[AWX] [warning] Invalid line");
+    }
+}
@@ -3,9 +3,9 @@ use std::sync::Arc;
 use anyhow::{anyhow, Context, Error};
 use serde::{Deserialize, Serialize};
 
-use task_maker_dag::{Execution, ExecutionLimits, FileUuid};
+use task_maker_dag::{Execution, ExecutionLimits, FileUuid, TrustLevel};
 
-use crate::terry::{Seed, SolutionOutcome};
+use crate::terry::{parse_outcome, Seed, SolutionOutcome};
 use crate::ui::UIMessage;
 use crate::{bind_exec_callbacks, EvaluationData, SourceFile, Tag};
 
@@ -60,7 +60,7 @@ impl InputGenerator {
             self.source
                 .execute(eval, description, vec![seed.to_string(), "0".to_string()])?;
         include_official_solution(eval, &mut exec, official_solution)?;
-        exec.limits_mut().allow_multiprocess();
+        exec.trust(TrustLevel::Trusted);
         exec.tag(Tag::Generation.into());
         let input_file = exec.stdout();
         Ok((input_file.uuid, exec))
@@ -127,7 +127,7 @@ impl InputValidator {
     ) -> Result<(FileUuid, Execution), Error> {
         let mut exec = self.source.execute(eval, description, Vec::<&str>::new())?;
         include_official_solution(eval, &mut exec, official_solution)?;
-        exec.limits_mut().allow_multiprocess();
+        exec.trust(TrustLevel::Trusted);
         exec.stdin(input).tag(Tag::Generation.into());
         let stdout = exec.stdout();
         Ok((stdout.uuid, exec))
@@ -240,6 +240,7 @@ impl Checker {
             .execute(eval, &description, vec!["input.txt", "output.txt"])?;
         include_official_solution(eval, &mut exec, official_solution)?;
         *exec.limits_mut() = ExecutionLimits::unrestricted();
+        exec.trust(TrustLevel::Trusted);
         exec.input(input, "input.txt", false)
             .input(output, "output.txt", false)
             .capture_stdout(OUTCOME_SIZE_LIMIT);
@@ -247,7 +248,7 @@ impl Checker {
             let stdout = res
                 .stdout
                 .ok_or_else(|| anyhow!("Checker stdout not captured"))?;
-            callback(serde_json::from_slice(&stdout).map_err(|e| e.into()))
+            callback(parse_outcome(&stdout))
         });
         Ok(exec)
     }
@@ -1,14 +1,14 @@
 use std::path::Path;
 
 use itertools::Itertools;
-use termcolor::{ColorChoice, StandardStream};
+use termcolor::{ColorChoice, ColorSpec, StandardStream};
 
 use task_maker_dag::{ExecutionResult, ExecutionStatus};
 
-use crate::terry::ui_state::{SolutionState, SolutionStatus, UIState};
-use crate::terry::CaseStatus;
+use crate::terry::ui_state::{CaseOutcome, SolutionState, SolutionStatus, UIState};
+use crate::terry::{CaseStatus, SolutionAlert};
 use crate::ui::{FinishUI as FinishUITrait, FinishUIUtils, BLUE, BOLD, GREEN, RED, YELLOW};
-use crate::{cwrite, cwriteln};
+use crate::{cwrite, cwriteln, ScoreStatus};
 
 /// UI that prints to `stdout` the ending result of the evaluation of a IOI task.
 pub struct FinishUI {
@@ -117,9 +117,11 @@ impl FinishUI {
         }
     }
 
-    /// Print the standard error in the provided, if present and not empty.
+    /// Print the standard error in the provided, if present and not empty, and the logs of the
+    /// sandbox if it failed.
     fn print_stderr(&mut self, result: &Option<ExecutionResult>) {
         if let Some(res) = result {
+            FinishUIUtils::new(&mut self.stream).print_sandbox_logs(res);
             if let Some(content) = &res.stderr {
                 let content = String::from_utf8_lossy(content);
                 let content = content.trim();
@@ -164,6 +166,24 @@ impl FinishUI {
             }
             println!();
         }
+        for alert in outcome
+            .validation
+            .alerts
+            .iter()
+            .chain(outcome.feedback.alerts.iter())
+        {
+            self.print_alert(alert);
+        }
+    }
+
+    /// Print an alert sent by the checker, colored by its severity.
+    fn print_alert(&mut self, alert: &SolutionAlert) {
+        match alert.severity.as_str() {
+            "warning" => cwrite!(self, YELLOW, "{:>7}", alert.severity),
+            "danger" | "error" => cwrite!(self, RED, "{:>7}", alert.severity),
+            _ => cwrite!(self, BOLD, "{:>7}", alert.severity),
+        }
+        println!(": {}", alert.message);
     }
 
     /// Print the summary of the solution results.
@@ -186,24 +206,24 @@ impl FinishUI {
                     } else {
                         cwrite!(self, YELLOW, "{:>3}", score.floor());
                     }
-                    for (val, feed) in outcome
-                        .validation
-                        .cases
-                        .iter()
-                        .zip(outcome.feedback.cases.iter())
-                    {
-                        match val.status {
-                            CaseStatus::Missing => print!(" m"),
-                            CaseStatus::Parsed => {
-                                if feed.correct {
-                                    cwrite!(self, GREEN, " c");
-                                } else {
-                                    cwrite!(self, RED, " w");
-                                }
+                    print!("  ");
+                    let color: &ColorSpec = match ScoreStatus::from_score(outcome.score, 1.0) {
+                        ScoreStatus::Accepted => &GREEN,
+                        ScoreStatus::WrongAnswer => &RED,
+                        ScoreStatus::PartialScore => &YELLOW,
+                    };
+                    cwrite!(self, color, "[");
+                    for case in CaseOutcome::from_outcome(outcome) {
+                        let letter = case.letter();
+                        match case {
+                            CaseOutcome::Correct => cwrite!(self, GREEN, "{}", letter),
+                            CaseOutcome::Wrong | CaseOutcome::Invalid => {
+                                cwrite!(self, RED, "{}", letter)
                             }
-                            CaseStatus::Invalid => cwrite!(self, RED, " i"),
+                            CaseOutcome::Missing => cwrite!(self, BOLD, "{}", letter),
                         }
                     }
+                    cwrite!(self, color, "]");
                 }
                 Some(Err(e)) => {
                     print!("    {}", e);
@@ -6,6 +6,7 @@ use anyhow::{anyhow, bail, Error};
 use serde::{Deserialize, Serialize};
 
 use crate::terry::dag::{Checker, InputGenerator, InputValidator};
+use crate::terry::public_files::find_public_files;
 use crate::terry::sanity_checks::get_sanity_checks;
 use crate::terry::TerryTask;
 use crate::{find_source_file, EvaluationConfig, SourceFile, WriteBinTo};
@@ -25,6 +26,9 @@ struct TaskYAML {
     pub description: String,
     /// The maximum score for this task.
     pub max_score: f64,
+    /// Whether to copy the files inside `public/` in the statement directory. Defaults to false.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub copy_public_files: bool,
 }
 
 /// Given a path to a task in the Terry format, try to parse the task inside of it.
@@ -49,6 +53,8 @@ pub fn parse_task<P: AsRef<Path>>(
         name: yaml.name,
         description: yaml.description,
         max_score: yaml.max_score,
+        public_files: find_public_files(task_dir)?,
+        copy_public_files: yaml.copy_public_files,
         generator,
         validator,
         checker,
@@ -3,20 +3,21 @@ use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use anyhow::Error;
+use anyhow::{Context, Error};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
+pub use outcome::{parse_outcome, validate_outcome};
 pub use task_info::*;
-use task_maker_dag::ExecutionDAGConfig;
+use task_maker_dag::{ExecutionDAGConfig, File};
+pub use ui_state::*;
 
 use crate::sanity_checks::SanityChecks;
 use crate::solution::SolutionInfo;
 use crate::terry::curses_ui::CursesUI;
 use crate::terry::dag::{Checker, InputGenerator, InputValidator, Solution};
 use crate::terry::format::parse_task;
-use crate::terry::ui_state::UIState;
 use crate::ui::{JsonUI, PrintUI, RawUI, SilentUI, UIMessage, UIType, UI};
 use crate::{list_files, EvaluationConfig, EvaluationData, SourceFile, TaskInfo, UISender};
 
@@ -24,6 +25,8 @@ mod curses_ui;
 mod dag;
 pub(crate) mod finish_ui;
 mod format;
+mod outcome;
+mod public_files;
 pub(crate) mod sanity_checks;
 pub(crate) mod task_info;
 pub(crate) mod ui_state;
@@ -42,6 +45,13 @@ pub struct TerryTask {
     pub description: String,
     /// The maximum score for this task.
     pub max_score: f64,
+    /// The files inside `public/`, published together with the statement. The paths are relative
+    /// to `public/`.
+    #[serde(default)]
+    pub public_files: Vec<PathBuf>,
+    /// Whether to copy the public files inside `statement/`, where terry serves them.
+    #[serde(default)]
+    pub copy_public_files: bool,
 
     /// The generator of input files of this task.
     #[serde(skip_serializing)]
@@ -219,10 +229,68 @@ impl TerryTask {
                 },
             )?;
         }
+        if self.copy_public_files {
+            self.copy_public_files(eval)
+                .context("Failed to bind the copy of the public files")?;
+        }
         self.sanity_checks.pre_hook(self, eval)?;
         Ok(())
     }
 
+    /// Add the executions required for grading an output file that was not produced by the
+    /// solutions of the task, like the one submitted by a contestant. The input file is generated
+    /// again with `seed`, validated and then checked against `output`.
+    ///
+    /// The callback is called with the outcome of the checker, it's not called if the generation,
+    /// the validation or the checking fail.
+    pub fn grade_output<F>(
+        &self,
+        eval: &mut EvaluationData,
+        name: &str,
+        seed: Seed,
+        output: &Path,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: FnOnce(Result<SolutionOutcome, Error>) -> Result<(), Error> + 'static,
+    {
+        let (input, gen) = self.generator.generate(
+            eval,
+            format!("Generation of input file for {} with seed {}", name, seed),
+            seed,
+            self.official_solution.clone(),
+        )?;
+        eval.dag.add_execution(gen);
+        let validation = if let Some(validator) = self.validator.as_ref() {
+            let (validation, val) = validator.validate(
+                eval,
+                format!("Validation of input file for {}", name),
+                input,
+                self.official_solution.clone(),
+            )?;
+            eval.dag.add_execution(val);
+            Some(validation)
+        } else {
+            None
+        };
+        let output_file = File::new(format!("Output file of {}", name));
+        let output_uuid = output_file.uuid;
+        eval.dag.provide_file(output_file, output)?;
+        let mut check = self.checker.check(
+            eval,
+            format!("Checking output of {}", name),
+            input,
+            output_uuid,
+            self.official_solution.clone(),
+            callback,
+        )?;
+        if let Some(validation) = validation {
+            check.input(validation, "wait_for_validation", false);
+        }
+        eval.dag.add_execution(check);
+        Ok(())
+    }
+
     /// Hook called after the execution completed, useful for sending messages to the UI about the
     /// results of the sanity checks with data available only after the evaluation.
     pub fn sanity_check_post_hook(&self, eval: &mut EvaluationData) -> Result<(), Error> {
@@ -253,6 +321,9 @@ impl TerryTask {
             info!("Removing {}", bin_path.display());
             std::fs::remove_dir_all(bin_path)?;
         }
+        if self.copy_public_files {
+            self.remove_public_copies()?;
+        }
         Ok(())
     }
 
@@ -0,0 +1,290 @@
+//! The validation of the outcome printed by the checker of a Terry task.
+//!
+//! The outcome is checked against the schema of [`SolutionOutcome`] before being parsed, so that a
+//! checker that doesn't follow the protocol is reported with the exact fields that are wrong,
+//! instead of with the first error of the deserializer.
+
+use anyhow::{anyhow, Context, Error};
+use serde_json::{Map, Value};
+
+use crate::terry::SolutionOutcome;
+
+/// The maximum number of errors reported for an invalid outcome.
+const MAX_REPORTED_ERRORS: usize = 5;
+
+/// The valid values of the status of a case of the validation.
+const CASE_STATUSES: &[&str] = &["missing", "parsed", "invalid"];
+
+/// Parse the outcome printed by the checker, failing with the list of the problems found if it
+/// doesn't follow the protocol.
+pub fn parse_outcome(stdout: &[u8]) -> Result<SolutionOutcome, Error> {
+    if stdout.iter().all(u8::is_ascii_whitespace) {
+        return Err(anyhow!("the checker printed nothing"));
+    }
+    let value: Value = serde_json::from_slice(stdout)
+        .map_err(|e| anyhow!("the checker didn't print a valid JSON: {}", e))?;
+    let errors = validate_outcome(&value);
+    if !errors.is_empty() {
+        let mut message = errors
+            .iter()
+            .take(MAX_REPORTED_ERRORS)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("; ");
+        if errors.len() > MAX_REPORTED_ERRORS {
+            message += &format!(" (and {} more)", errors.len() - MAX_REPORTED_ERRORS);
+        }
+        return Err(anyhow!(message));
+    }
+    serde_json::from_value(value).context("the outcome does not match the protocol")
+}
+
+/// Check the outcome printed by the checker against the schema of [`SolutionOutcome`], returning
+/// the list of the problems found, each one with the path of the field it's about.
+pub fn validate_outcome(outcome: &Value) -> Vec<String> {
+    let mut errors = vec![];
+    let Some(outcome) = outcome.as_object() else {
+        errors.push(format!(
+            "the outcome must be an object, found {}",
+            kind(outcome)
+        ));
+        return errors;
+    };
+    if let Some(score) = field(&mut errors, outcome, "", "score") {
+        match score.as_f64() {
+            Some(score) if (0.0..=1.0).contains(&score) => {}
+            Some(score) => errors.push(format!(
+                "`score` must be between 0.0 and 1.0, found {}",
+                score
+            )),
+            None => errors.push(format!("`score` must be a number, found {}", kind(score))),
+        }
+    }
+
+    let mut num_cases = [None, None];
+    for (index, section) in ["validation", "feedback"].into_iter().enumerate() {
+        let Some(value) = field(&mut errors, outcome, "", section) else {
+            continue;
+        };
+        let Some(value) = object(&mut errors, value, section) else {
+            continue;
+        };
+        if let Some(cases) = field(&mut errors, value, section, "cases") {
+            let path = format!("{}.cases", section);
+            if let Some(cases) = array(&mut errors, cases, &path) {
+                num_cases[index] = Some(cases.len());
+                for (i, case) in cases.iter().enumerate() {
+                    let path = format!("{}[{}]", path, i);
+                    if let Some(case) = object(&mut errors, case, &path) {
+                        validate_case(&mut errors, case, &path, section == "validation");
+                    }
+                }
+            }
+        }
+        if let Some(alerts) = field(&mut errors, value, section, "alerts") {
+            let path = format!("{}.alerts", section);
+            if let Some(alerts) = array(&mut errors, alerts, &path) {
+                for (i, alert) in alerts.iter().enumerate() {
+                    let path = format!("{}[{}]", path, i);
+                    if let Some(alert) = object(&mut errors, alert, &path) {
+                        string(&mut errors, alert, &path, "severity");
+                        string(&mut errors, alert, &path, "message");
+                    }
+                }
+            }
+        }
+    }
+    if let [Some(validation), Some(feedback)] = num_cases {
+        if validation != feedback {
+            errors.push(format!(
+                "`validation.cases` and `feedback.cases` have different lengths: {} and {}",
+                validation, feedback
+            ));
+        }
+    }
+    errors
+}
+
+/// Check a case of the validation (with its `status`) or of the feedback (with `correct`).
+fn validate_case(
+    errors: &mut Vec<String>,
+    case: &Map<String, Value>,
+    path: &str,
+    validation: bool,
+) {
+    if validation {
+        if let Some(status) = field(errors, case, path, "status") {
+            match status.as_str() {
+                Some(status) if CASE_STATUSES.contains(&status) => {}
+                _ => errors.push(format!(
+                    "`{}.status` must be missing, parsed or invalid, found {}",
+                    path, status
+                )),
+            }
+        }
+    } else if let Some(correct) = field(errors, case, path, "correct") {
+        if !correct.is_boolean() {
+            errors.push(format!(
+                "`{}.correct` must be a boolean, found {}",
+                path,
+                kind(correct)
+            ));
+        }
+    }
+    if let Some(message) = case.get("message") {
+        if !message.is_null() && !message.is_string() {
+            errors.push(format!(
+                "`{}.message` must be a string or null, found {}",
+                path,
+                kind(message)
+            ));
+        }
+    }
+}
+
+/// Get a required field of an object, reporting it if it's missing.
+fn field<'a>(
+    errors: &mut Vec<String>,
+    object: &'a Map<String, Value>,
+    path: &str,
+    name: &str,
+) -> Option<&'a Value> {
+    let value = object.get(name);
+    if value.is_none() {
+        match path {
+            "" => errors.push(format!("missing field `{}`", name)),
+            _ => errors.push(format!("missing field `{}.{}`", path, name)),
+        }
+    }
+    value
+}
+
+/// Check that a required field of an object is a string.
+fn string(errors: &mut Vec<String>, object: &Map<String, Value>, path: &str, name: &str) {
+    if let Some(value) = field(errors, object, path, name) {
+        if !value.is_string() {
+            errors.push(format!(
+                "`{}.{}` must be a string, found {}",
+                path,
+                name,
+                kind(value)
+            ));
+        }
+    }
+}
+
+/// Check that a value is an object.
+fn object<'a>(
+    errors: &mut Vec<String>,
+    value: &'a Value,
+    path: &str,
+) -> Option<&'a Map<String, Value>> {
+    let object = value.as_object();
+    if object.is_none() {
+        errors.push(format!(
+            "`{}` must be an object, found {}",
+            path,
+            kind(value)
+        ));
+    }
+    object
+}
+
+/// Check that a value is an array.
+fn array<'a>(errors: &mut Vec<String>, value: &'a Value, path: &str) -> Option<&'a Vec<Value>> {
+    let array = value.as_array();
+    if array.is_none() {
+        errors.push(format!(
+            "`{}` must be an array, found {}",
+            path,
+            kind(value)
+        ));
+    }
+    array
+}
+
+/// The kind of a JSON value, for the error messages.
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn errors(outcome: Value) -> Vec<String> {
+        validate_outcome(&outcome)
+    }
+
+    #[test]
+    fn test_valid_outcome() {
+        let outcome = br#"{
+            "score": 0.5,
+            "validation": {
+                "cases": [{"status": "parsed", "message": null}, {"status": "missing"}],
+                "alerts": [{"severity": "warning", "message": "Invalid line"}]
+            },
+            "feedback": {
+                "cases": [{"correct": true, "message": "Ok"}, {"correct": false}],
+                "alerts": []
+            }
+        }"#;
+        let outcome = parse_outcome(outcome).unwrap();
+        assert_eq!(outcome.score, 0.5);
+        assert_eq!(outcome.feedback.cases.len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_outcome() {
+        assert_eq!(
+            errors(json!({"score": 2, "feedback": {"cases": [], "alerts": []}})),
+            vec![
+                "`score` must be between 0.0 and 1.0, found 2",
+                "missing field `validation`"
+            ]
+        );
+        assert_eq!(
+            errors(json!({
+                "score": "1",
+                "validation": {"cases": [{"status": "ok"}], "alerts": [{"message": 1}]},
+                "feedback": {"cases": [{"correct": 1}, {"correct": true}]}
+            })),
+            vec![
+                "`score` must be a number, found a string",
+                "`validation.cases[0].status` must be missing, parsed or invalid, found \"ok\"",
+                "missing field `validation.alerts[0].severity`",
+                "`validation.alerts[0].message` must be a string, found a number",
+                "`feedback.cases[0].correct` must be a boolean, found a number",
+                "missing field `feedback.alerts`",
+                "`validation.cases` and `feedback.cases` have different lengths: 1 and 2",
+            ]
+        );
+        assert_eq!(
+            errors(json!([])),
+            vec!["the outcome must be an object, found an array"]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_outcome() {
+        let err = parse_outcome(b"").unwrap_err();
+        assert_eq!(err.to_string(), "the checker printed nothing");
+        let err = parse_outcome(b"0.5").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the outcome must be an object, found a number"
+        );
+        let err = parse_outcome(b"{\"score\": ").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("the checker didn't print a valid JSON"));
+    }
+}
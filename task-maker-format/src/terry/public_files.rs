@@ -0,0 +1,181 @@
+//! The public files of a Terry task.
+//!
+//! The files inside `public/` are published together with the statement, like the sample inputs
+//! or the images. terry serves only the files inside `statement/`, and the statement links them
+//! with `{{baseURI}}name`. When enabled in `task.yaml` (with `copy_public_files: true`), the public
+//! files are copied inside `statement/` during the evaluation, and they are removed by `--clean`.
+
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Error};
+use itertools::Itertools;
+
+use task_maker_dag::File;
+
+use crate::terry::TerryTask;
+use crate::EvaluationData;
+
+/// The prefix of the links of the statement that terry replaces with the URL of its files.
+const BASE_URI: &str = "{{baseURI}}";
+
+/// Find the files inside the `public/` directory of the task, recursively. The paths are relative
+/// to `public/` and sorted.
+pub(crate) fn find_public_files(task_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let public = task_dir.join("public");
+    let mut files = vec![];
+    if public.is_dir() {
+        list_directory(&public, Path::new(""), &mut files)?;
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Add to `files` the files inside `dir`, placing them under `prefix`.
+fn list_directory(dir: &Path, prefix: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in dir
+        .read_dir()
+        .with_context(|| format!("Cannot list {}", dir.display()))?
+    {
+        let entry = entry.with_context(|| format!("Cannot list {}", dir.display()))?;
+        let path = entry.path();
+        let name = prefix.join(entry.file_name());
+        // is_dir and is_file follow the symlinks, the broken ones are skipped.
+        if path.is_dir() {
+            list_directory(&path, &name, files)?;
+        } else if path.is_file() {
+            files.push(name);
+        }
+    }
+    Ok(())
+}
+
+/// Find the files of the statement directory that are linked by the statement, with the markdown
+/// links and images or with the `src` and `href` attributes of the HTML tags. The links to other
+/// sites and outside the statement directory are ignored. The paths are relative to `statement/`,
+/// sorted and without duplicates.
+pub(crate) fn statement_references(statement: &str) -> Vec<PathBuf> {
+    let mut targets = vec![];
+    for (index, _) in statement.match_indices("](") {
+        let rest = &statement[index + 2..];
+        let end = rest
+            .find(|c: char| c == ')' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        targets.push(rest[..end].trim_start_matches('<').trim_end_matches('>'));
+    }
+    for attribute in ["src=\"", "href=\""] {
+        for (index, _) in statement.match_indices(attribute) {
+            let rest = &statement[index + attribute.len()..];
+            targets.push(&rest[..rest.find('"').unwrap_or(rest.len())]);
+        }
+    }
+    targets
+        .into_iter()
+        .filter_map(resolve_reference)
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+/// The path, relative to `statement/`, of the file linked by `target`, if it's a file of the
+/// statement directory.
+fn resolve_reference(target: &str) -> Option<PathBuf> {
+    let target = target.split(|c: char| c == '#' || c == '?').next()?;
+    let target = match target.strip_prefix(BASE_URI) {
+        Some(target) => target,
+        // the URLs, the absolute paths and the links like mailto:
+        None if target.contains(':') || target.starts_with('/') => return None,
+        None => target,
+    };
+    let components = Path::new(target)
+        .components()
+        .filter(|component| component != &Component::CurDir)
+        .collect_vec();
+    let normal = components
+        .iter()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if components.is_empty() || !normal {
+        return None;
+    }
+    Some(components.into_iter().collect())
+}
+
+impl TerryTask {
+    /// Add to the DAG the copy of the public files inside the statement directory.
+    pub(crate) fn copy_public_files(&self, eval: &mut EvaluationData) -> Result<(), Error> {
+        for path in &self.public_files {
+            let file = File::new(format!("Public file {}", path.display()));
+            eval.dag
+                .write_file_to(&file, self.path.join("statement").join(path), false);
+            eval.dag
+                .provide_file(file, self.path.join("public").join(path))?;
+        }
+        Ok(())
+    }
+
+    /// Remove the copies of the public files inside the statement directory, and the directories
+    /// left empty.
+    pub(crate) fn remove_public_copies(&self) -> Result<(), Error> {
+        let statement = self.path.join("statement");
+        for path in &self.public_files {
+            let copy = statement.join(path);
+            if !copy.exists() {
+                continue;
+            }
+            info!("Removing {}", copy.display());
+            std::fs::remove_file(&copy)
+                .with_context(|| format!("Failed to remove {}", copy.display()))?;
+            let mut dir = copy.parent();
+            while let Some(parent) = dir.filter(|dir| *dir != statement) {
+                // fails if the directory is not empty
+                if std::fs::remove_dir(parent).is_err() {
+                    break;
+                }
+                dir = parent.parent();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_references() {
+        let statement = [
+            "# Task",
+            "Download the [input]({{baseURI}}input.txt) and the ![image](images/graph.png \"A\").",
+            "See [the rules](https://example.com/rules), [above](#task) and [me](mailto:a@b.c).",
+            "<img src=\"./images/graph.png\"> <a href=\"{{baseURI}}sample.zip?dl=1\">sample</a>",
+            "[outside](../managers/checker.py) [absolute](/etc/passwd) [empty]()",
+        ]
+        .join("\n");
+        assert_eq!(
+            statement_references(&statement),
+            vec![
+                PathBuf::from("images/graph.png"),
+                PathBuf::from("input.txt"),
+                PathBuf::from("sample.zip"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_public_files() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        assert!(find_public_files(tmpdir.path()).unwrap().is_empty());
+
+        let public = tmpdir.path().join("public");
+        std::fs::create_dir_all(public.join("images")).unwrap();
+        std::fs::write(public.join("input.txt"), "1 2").unwrap();
+        std::fs::write(public.join("images/graph.png"), "").unwrap();
+        assert_eq!(
+            find_public_files(tmpdir.path()).unwrap(),
+            vec![
+                PathBuf::from("images/graph.png"),
+                PathBuf::from("input.txt"),
+            ]
+        );
+    }
+}
@@ -3,10 +3,10 @@ use anyhow::{bail, Context, Error};
 use task_maker_dag::File;
 
 use crate::sanity_checks::{make_sanity_check, SanityCheck, SanityCheckCategory};
-use crate::terry::TerryTask;
+use crate::terry::{CaseStatus, TerryTask};
 use crate::{list_files, EvaluationData, UISender, DATA_DIR};
 use std::path::Path;
-use task_maker_diagnostics::Diagnostic;
+use task_maker_diagnostics::{Diagnostic, DiagnosticCode};
 
 /// Fuzz the checker with some nasty output files making sure it doesn't crash.
 ///
@@ -111,3 +111,88 @@ impl SanityCheck for FuzzChecker {
         Ok(())
     }
 }
+
+/// Check that the checker follows the protocol on an empty output file.
+///
+/// The outcome must be a valid JSON with all the required fields, and since the output is empty it
+/// should have no score, with all the cases missing and none of them correct.
+#[derive(Debug, Default)]
+pub struct CheckerProtocol;
+make_sanity_check!(CheckerProtocol);
+
+impl SanityCheck for CheckerProtocol {
+    type Task = TerryTask;
+
+    fn name(&self) -> &'static str {
+        "CheckerProtocol"
+    }
+
+    fn category(&self) -> SanityCheckCategory {
+        SanityCheckCategory::Checker
+    }
+
+    fn pre_hook(&self, task: &TerryTask, eval: &mut EvaluationData) -> Result<(), Error> {
+        // keep the seed fixed for the cache, it's the same of FuzzChecker
+        let seed = 42;
+        let (input, gen) = task.generator.generate(
+            eval,
+            "Generation of input for CheckerProtocol".into(),
+            seed,
+            task.official_solution.clone(),
+        )?;
+        eval.dag.add_execution(gen);
+        let output_file = File::new("Empty output for CheckerProtocol");
+        let output_uuid = output_file.uuid;
+        eval.dag.provide_content(output_file, vec![]);
+        let sender = eval.sender.clone();
+        let check = task.checker.check(
+            eval,
+            "Checking empty output for CheckerProtocol".into(),
+            input,
+            output_uuid,
+            task.official_solution.clone(),
+            move |outcome| {
+                let outcome = match outcome {
+                    Ok(outcome) => outcome,
+                    Err(e) => {
+                        return sender.add_diagnostic(
+                            Diagnostic::error(
+                                "The outcome of the checker on an empty output is not valid",
+                            )
+                            .with_code(DiagnosticCode::InvalidTerryOutcome)
+                            .with_note(e.to_string()),
+                        );
+                    }
+                };
+                if outcome.score != 0.0 {
+                    sender.add_diagnostic(Diagnostic::warning(format!(
+                        "The checker gives {} of the score to an empty output",
+                        outcome.score
+                    )))?;
+                }
+                let cases = &outcome.validation.cases;
+                if !cases
+                    .iter()
+                    .all(|case| matches!(case.status, CaseStatus::Missing))
+                {
+                    sender.add_diagnostic(
+                        Diagnostic::warning(
+                            "The checker doesn't mark all the cases of an empty output as missing",
+                        )
+                        .with_note("The empty output doesn't contain any case"),
+                    )?;
+                }
+                let correct = outcome.feedback.cases.iter().filter(|c| c.correct).count();
+                if correct > 0 {
+                    sender.add_diagnostic(Diagnostic::warning(format!(
+                        "The checker marks {} cases of an empty output as correct",
+                        correct
+                    )))?;
+                }
+                Ok(())
+            },
+        )?;
+        eval.dag.add_execution(check);
+        Ok(())
+    }
+}
@@ -1,7 +1,8 @@
 use anyhow::Error;
-use task_maker_diagnostics::Diagnostic;
+use task_maker_diagnostics::{Diagnostic, DiagnosticCategory};
 
 use crate::sanity_checks::{make_sanity_check, SanityCheck, SanityCheckCategory};
+use crate::terry::public_files::statement_references;
 use crate::terry::TerryTask;
 use crate::EvaluationData;
 
@@ -28,3 +29,76 @@ impl SanityCheck for StatementPresent {
         Ok(())
     }
 }
+
+/// Check that the files linked by the statement exist, and that the copies of the public files in
+/// the statement directory are up to date.
+#[derive(Debug, Default)]
+pub struct StatementPublicFiles;
+make_sanity_check!(StatementPublicFiles);
+
+impl SanityCheck for StatementPublicFiles {
+    type Task = TerryTask;
+
+    fn name(&self) -> &'static str {
+        "StatementPublicFiles"
+    }
+
+    fn category(&self) -> SanityCheckCategory {
+        SanityCheckCategory::Statement
+    }
+
+    fn pre_hook(&self, task: &TerryTask, eval: &mut EvaluationData) -> Result<(), Error> {
+        let statement_dir = task.path.join("statement");
+        // the missing statement is reported by StatementPresent
+        let Ok(statement) = std::fs::read_to_string(statement_dir.join("statement.md")) else {
+            return Ok(());
+        };
+        for reference in statement_references(&statement) {
+            let public = task.public_files.contains(&reference);
+            if (task.copy_public_files && public) || statement_dir.join(&reference).is_file() {
+                continue;
+            }
+            if public {
+                eval.add_diagnostic(
+                    Diagnostic::error(format!(
+                        "The statement links public/{}, but it's not in the statement directory",
+                        reference.display()
+                    ))
+                    .with_category(DiagnosticCategory::Statement)
+                    .with_help("Set copy_public_files: true in task.yaml for copying it there"),
+                )?;
+            } else {
+                eval.add_diagnostic(
+                    Diagnostic::error(format!(
+                        "The statement links {}, which does not exist",
+                        reference.display()
+                    ))
+                    .with_category(DiagnosticCategory::Statement)
+                    .with_help("Put it inside public/"),
+                )?;
+            }
+        }
+        if task.copy_public_files {
+            return Ok(());
+        }
+        for file in &task.public_files {
+            let copy = statement_dir.join(file);
+            if !copy.is_file() {
+                continue;
+            }
+            let public = task.path.join("public").join(file);
+            if std::fs::read(&copy).ok() != std::fs::read(&public).ok() {
+                eval.add_diagnostic(
+                    Diagnostic::warning(format!(
+                        "statement/{} is different from public/{}",
+                        file.display(),
+                        file.display()
+                    ))
+                    .with_category(DiagnosticCategory::Statement)
+                    .with_help("Set copy_public_files: true in task.yaml for keeping it updated"),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
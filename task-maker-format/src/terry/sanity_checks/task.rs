@@ -1,5 +1,5 @@
 use anyhow::Error;
-use task_maker_diagnostics::Diagnostic;
+use task_maker_diagnostics::{Diagnostic, DiagnosticCategory};
 
 use crate::sanity_checks::{make_sanity_check, SanityCheck, SanityCheckCategory};
 use crate::terry::TerryTask;
@@ -23,7 +23,10 @@ impl SanityCheck for ValidatorPresent {
 
     fn pre_hook(&self, task: &TerryTask, eval: &mut EvaluationData) -> Result<(), Error> {
         if task.validator.is_none() {
-            eval.add_diagnostic(Diagnostic::warning("Validator not present"))?;
+            eval.add_diagnostic(
+                Diagnostic::warning("Validator not present")
+                    .with_category(DiagnosticCategory::Generation),
+            )?;
         }
         Ok(())
     }
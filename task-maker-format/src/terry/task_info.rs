@@ -1,8 +1,11 @@
+use std::path::{Path, PathBuf};
+
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
 use crate::terry::TerryTask;
+use crate::{hash_file, TASK_INFO_VERSION};
 
 /// Task information structure.
 #[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
@@ -15,16 +18,46 @@ pub struct TerryTaskInfo {
     pub description: String,
     /// The maximum score for this task.
     pub max_score: f64,
+    /// The files published together with the statement, sorted by name.
+    pub public_files: Vec<TaskInfoPublicFile>,
+}
+
+/// A file published together with the statement of the task.
+#[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
+pub struct TaskInfoPublicFile {
+    /// Name of this file, relative to the `public/` directory.
+    pub name: PathBuf,
+    /// MIME type of this file.
+    pub content_type: String,
+    /// Path of this file relative to task directory.
+    pub path: PathBuf,
+    /// The BLAKE3 hash of the content of this file, in hex.
+    pub hash: String,
 }
 
 impl TerryTaskInfo {
     /// Generate the task information from the provided `Task`.
     pub fn new(task: &TerryTask) -> Result<TerryTaskInfo, Error> {
         Ok(TerryTaskInfo {
-            version: 1,
+            version: TASK_INFO_VERSION,
             name: task.name.clone(),
             description: task.description.clone(),
             max_score: task.max_score,
+            public_files: task
+                .public_files
+                .iter()
+                .map(|name| {
+                    let path = Path::new("public").join(name);
+                    Ok(TaskInfoPublicFile {
+                        name: name.clone(),
+                        content_type: mime_guess::from_path(name)
+                            .first()
+                            .map_or("UNKNOWN".into(), |t| t.to_string()),
+                        hash: hash_file(&task.path.join(&path))?,
+                        path,
+                    })
+                })
+                .collect::<Result<_, Error>>()?,
         })
     }
 }
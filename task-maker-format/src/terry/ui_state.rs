@@ -2,13 +2,14 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::time::SystemTime;
 
-use task_maker_dag::{ExecutionResult, ExecutionStatus};
+use task_maker_dag::{ExecutionDAGConfig, ExecutionResult, ExecutionStatus};
 use task_maker_diagnostics::DiagnosticContext;
 use task_maker_exec::ExecutorStatus;
 
 use crate::terry::finish_ui;
-use crate::terry::{Seed, SolutionOutcome, TerryTask};
-use crate::ui::{CompilationStatus, FinishUI, UIExecutionStatus, UIMessage, UIStateT};
+use crate::terry::{CaseStatus, Seed, SolutionOutcome, TerryTask};
+use crate::ui::{CompilationStatus, ExitPolicy, FinishUI, UIExecutionStatus, UIMessage, UIStateT};
+use crate::TaskFormat;
 
 /// The state of a Terry task, all the information for the UI are stored here.
 #[derive(Debug, Clone)]
@@ -72,6 +73,47 @@ pub enum SolutionStatus {
     Skipped,
 }
 
+/// The outcome of a test case of a solution, a cell of the per-testcase grid shown by the UIs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseOutcome {
+    /// The test case is valid and correct.
+    Correct,
+    /// The test case is valid but wrong.
+    Wrong,
+    /// The test case is not present in the output file.
+    Missing,
+    /// The test case is present but its format is invalid.
+    Invalid,
+}
+
+impl CaseOutcome {
+    /// The outcomes of all the test cases of a solution, in order.
+    pub fn from_outcome(outcome: &SolutionOutcome) -> Vec<CaseOutcome> {
+        outcome
+            .validation
+            .cases
+            .iter()
+            .zip(outcome.feedback.cases.iter())
+            .map(|(val, feed)| match val.status {
+                CaseStatus::Missing => CaseOutcome::Missing,
+                CaseStatus::Parsed if feed.correct => CaseOutcome::Correct,
+                CaseStatus::Parsed => CaseOutcome::Wrong,
+                CaseStatus::Invalid => CaseOutcome::Invalid,
+            })
+            .collect()
+    }
+
+    /// The letter of the cell in the grid, following the letters of the grid of the IOI tasks.
+    pub fn letter(&self) -> char {
+        match self {
+            CaseOutcome::Correct => 'A',
+            CaseOutcome::Wrong => 'W',
+            CaseOutcome::Missing => 'X',
+            CaseOutcome::Invalid => 'I',
+        }
+    }
+}
+
 impl SolutionState {
     fn new() -> Self {
         Self {
@@ -97,6 +139,12 @@ impl UIState {
             diagnostics: Default::default(),
         }
     }
+
+    /// The reasons for failing the evaluation according to the exit policy, empty if the
+    /// evaluation should succeed. Terry tasks have no official solution and no expected scores.
+    pub fn exit_failures(&self, policy: &ExitPolicy) -> Vec<String> {
+        policy.common_failures(&self.compilations, &self.diagnostics)
+    }
 }
 
 impl UIStateT for UIState {
@@ -201,7 +249,7 @@ impl UIStateT for UIState {
                     .expect("Outcome of an unknown solution");
                 sol.outcome = Some(outcome);
             }
-            UIMessage::Diagnostic { diagnostic } => {
+            UIMessage::Diagnostic { diagnostic } | UIMessage::Deprecation { diagnostic, .. } => {
                 self.diagnostics.add_diagnostic(diagnostic);
             }
             UIMessage::IOITask { .. }
@@ -211,6 +259,7 @@ impl UIStateT for UIState {
             | UIMessage::IOIEvaluation { .. }
             | UIMessage::IOIChecker { .. }
             | UIMessage::IOITestcaseScore { .. }
+            | UIMessage::IOISeedRun { .. }
             | UIMessage::IOISubtaskScore { .. }
             | UIMessage::IOITaskScore { .. }
             | UIMessage::IOIBooklet { .. }
@@ -221,4 +270,56 @@ impl UIStateT for UIState {
     fn finish(&mut self) {
         finish_ui::FinishUI::print(self)
     }
+
+    fn restart(&self, task: &TaskFormat, _config: ExecutionDAGConfig) -> Option<Self> {
+        match task {
+            TaskFormat::Terry(task) => Some(UIState::new(task)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terry::parse_outcome;
+
+    use super::*;
+
+    #[test]
+    fn test_case_outcomes() {
+        let outcome = br#"{
+            "score": 0.25,
+            "validation": {
+                "cases": [
+                    {"status": "parsed"},
+                    {"status": "parsed"},
+                    {"status": "missing"},
+                    {"status": "invalid"}
+                ],
+                "alerts": []
+            },
+            "feedback": {
+                "cases": [
+                    {"correct": true},
+                    {"correct": false},
+                    {"correct": false},
+                    {"correct": false}
+                ],
+                "alerts": []
+            }
+        }"#;
+        let outcome = parse_outcome(outcome).unwrap();
+        let cases = CaseOutcome::from_outcome(&outcome);
+        assert_eq!(
+            cases,
+            vec![
+                CaseOutcome::Correct,
+                CaseOutcome::Wrong,
+                CaseOutcome::Missing,
+                CaseOutcome::Invalid
+            ]
+        );
+        let grid: String = cases.iter().map(CaseOutcome::letter).collect();
+        assert_eq!(grid, "AWXI");
+    }
 }
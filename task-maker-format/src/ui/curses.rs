@@ -8,7 +8,7 @@ use std::sync::{Arc, RwLock};
 use std::thread::JoinHandle;
 use std::time::SystemTime;
 
-use anyhow::Error;
+use anyhow::{Context, Error};
 use itertools::Itertools;
 use nix::sys::signal::{self, Signal};
 use nix::unistd::Pid;
@@ -20,17 +20,23 @@ use tui::backend::TermionBackend;
 use tui::layout::Rect;
 use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
-use tui::widgets::{Block, Borders, Paragraph};
+use tui::widgets::{Block, Borders, Clear, Paragraph};
 use tui::{Frame, Terminal};
 
-use task_maker_exec::{ExecutorStatus, ExecutorWorkerStatus};
+use task_maker_dag::ExecutionDAGConfig;
+use task_maker_exec::{ExecutorStatus, ExecutorWorkerStatus, TransferStats};
 
-use crate::ui::{CompilationStatus, FinishUI, UIMessage, UIStateT, UI};
+use crate::ui::{format_size, CompilationStatus, FinishUI, UIMessage, UIStateT, UI};
+use crate::TaskFormat;
 
 /// The framerate of the UI.
 pub(crate) const FPS: u64 = 30;
 /// After how many seconds rotate the list of workers if they don't fit on the screen.
 pub(crate) const ROTATION_DELAY: u64 = 1;
+/// Maximum number of lines of the standard error shown for each worker in the live stderr block.
+const LIVE_STDERR_LINES: usize = 3;
+/// Maximum height of the live stderr block, including its borders.
+const MAX_LIVE_STDERR_HEIGHT: u16 = 14;
 
 /// The type of the terminal with its backend.
 pub type FrameType<'a> =
@@ -90,6 +96,8 @@ where
     ui_thread: Option<JoinHandle<()>>,
     /// The state of the task for the UI.
     state: Arc<RwLock<State>>,
+    /// The lines of text shown over the bottom of the UI, if any.
+    notice: Arc<RwLock<Vec<String>>>,
     /// When it becomes true the UI will stop.
     stop: Arc<AtomicBool>,
 
@@ -112,26 +120,23 @@ where
 {
     /// Make a new generic `CursesUI`.
     pub fn new(state: State) -> Result<CursesUI<State, Drawer, Finish>, Error> {
-        let state = Arc::new(RwLock::new(state));
-        let stop = Arc::new(AtomicBool::new(false));
         let mut ui = CursesUI {
             ui_thread: None,
-            state: state.clone(),
-            stop: stop.clone(),
+            state: Arc::new(RwLock::new(state)),
+            notice: Default::default(),
+            stop: Arc::new(AtomicBool::new(false)),
             drawer: Default::default(),
             finish_ui: Default::default(),
         };
-        let handle = ui.start(state, stop)?;
-        ui.ui_thread = Some(handle);
+        ui.ui_thread = Some(ui.start()?);
         Ok(ui)
     }
 
     /// Start the drawing thread of the UI, returning the `JoinHandle` of it.
-    fn start(
-        &mut self,
-        state: Arc<RwLock<State>>,
-        stop: Arc<AtomicBool>,
-    ) -> Result<JoinHandle<()>, Error> {
+    fn start(&self) -> Result<JoinHandle<()>, Error> {
+        let state = self.state.clone();
+        let notice = self.notice.clone();
+        let stop = self.stop.clone();
         let stdout = io::stdout().into_raw_mode()?;
         let stdout = MouseTerminal::from(stdout);
         let stdout = AlternateScreen::from(stdout);
@@ -158,6 +163,8 @@ where
                         .draw(|f| {
                             let state = state.read().expect("UI state lock is poisoned");
                             Drawer::draw(&state, f, loading, loading_index);
+                            let notice = notice.read().expect("UI notice lock is poisoned");
+                            draw_notice(f, &notice);
                         })
                         .expect("Failed to draw to the screen");
                     // reduce the framerate to at most `FPS`
@@ -192,6 +199,37 @@ where
         let state = self.state.read().expect("State lock is poisoned");
         Finish::print(&state);
     }
+
+    fn restart(&mut self, task: &TaskFormat, config: ExecutionDAGConfig) -> Result<(), Error> {
+        let state = self
+            .state
+            .read()
+            .expect("UI state lock is poisoned")
+            .restart(task, config)
+            .context("This UI cannot show the new task")?;
+        *self.state.write().expect("UI state lock is poisoned") = state;
+        self.notice
+            .write()
+            .expect("UI notice lock is poisoned")
+            .clear();
+        // the drawing thread exits on ^C, which only stops the evaluation
+        if self
+            .ui_thread
+            .as_ref()
+            .map_or(true, |thread| thread.is_finished())
+        {
+            if let Some(thread) = self.ui_thread.take() {
+                thread.join().expect("UI thread failed");
+            }
+            self.stop.store(false, Ordering::Relaxed);
+            self.ui_thread = Some(self.start()?);
+        }
+        Ok(())
+    }
+
+    fn set_notice(&mut self, notice: Vec<String>) {
+        *self.notice.write().expect("UI notice lock is poisoned") = notice;
+    }
 }
 
 impl<State, Drawer, Finish> Drop for CursesUI<State, Drawer, Finish>
@@ -210,6 +248,20 @@ where
     }
 }
 
+/// Draw the notice over the bottom of the frame, using at most half of its height.
+fn draw_notice(frame: &mut FrameType, notice: &[String]) {
+    let size = frame.size();
+    let height = std::cmp::min(notice.len() as u16 + 2, size.height / 2);
+    if notice.is_empty() || height < 3 {
+        return;
+    }
+    let rect = Rect::new(size.x, size.y + size.height - height, size.width, height);
+    let text: Vec<Spans> = notice.iter().map(|line| line.clone().into()).collect();
+    frame.render_widget(Clear, rect);
+    render_block(frame, rect, " Watch ");
+    frame.render_widget(Paragraph::new(text), inner_block(rect));
+}
+
 /// Get the rect of the inner rect of a block with the borders.
 pub fn inner_block(rect: Rect) -> Rect {
     if rect.width < 2 || rect.height < 2 {
@@ -314,15 +366,35 @@ fn draw_server_status_summary(
     } else {
         return;
     };
-    let paragraph = Paragraph::new(Spans(vec![
+    let mut spans = vec![
         Span::styled(" Ready ", Style::default().add_modifier(Modifier::BOLD)),
         Span::raw(format!("{} ─", status.ready_execs)),
         Span::styled(" Waiting ", Style::default().add_modifier(Modifier::BOLD)),
         Span::raw(format!("{} ", status.waiting_execs)),
-    ]));
+    ];
+    push_transfers_summary(&mut spans, " Transfers ", &status.transfers);
+    push_transfers_summary(&mut spans, " Client ", &status.client_transfers);
+    let paragraph = Paragraph::new(Spans(spans));
     frame.render_widget(paragraph, rect);
 }
 
+/// Add to the summary of the server status the amount of data transferred, if any.
+fn push_transfers_summary(spans: &mut Vec<Span>, title: &'static str, transfers: &TransferStats) {
+    if let Some(bandwidth) = transfers.bandwidth() {
+        spans.push(Span::raw("─"));
+        spans.push(Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(format!(
+            "↑{} ↓{} ({}/s) ",
+            format_size(transfers.sent_bytes),
+            format_size(transfers.received_bytes),
+            format_size(bandwidth as u64)
+        )));
+    }
+}
+
 /// Draw the content of the server status box, splitting the workers in 2 groups if they don't fit,
 /// and rotating them if they still don't fit.
 fn draw_server_status(
@@ -420,6 +492,75 @@ fn draw_workers_chunk(
     frame.render_widget(paragraph, rect);
 }
 
+/// The height of the block with the standard error streamed by the workers, 0 if nothing is being
+/// streamed.
+pub fn live_stderr_height(status: Option<&ExecutorStatus<SystemTime>>) -> u16 {
+    let lines = live_stderr_lines(status).len() as u16;
+    if lines == 0 {
+        0
+    } else {
+        (lines + 2).min(MAX_LIVE_STDERR_HEIGHT)
+    }
+}
+
+/// Draw the block with the last lines of the standard error streamed by the workers.
+pub fn render_live_stderr(
+    frame: &mut FrameType,
+    rect: Rect,
+    status: Option<&ExecutorStatus<SystemTime>>,
+) {
+    let lines = live_stderr_lines(status);
+    if lines.is_empty() {
+        return;
+    }
+    render_block(frame, rect, " Live stderr ");
+    let paragraph = Paragraph::new(lines);
+    frame.render_widget(paragraph, inner_block(rect));
+}
+
+/// The lines of the live stderr block: the name of each worker with some stderr, followed by its
+/// last lines.
+fn live_stderr_lines(status: Option<&ExecutorStatus<SystemTime>>) -> Vec<Spans<'static>> {
+    let status = if let Some(status) = status {
+        status
+    } else {
+        return vec![];
+    };
+    let mut lines = vec![];
+    for worker in status
+        .connected_workers
+        .iter()
+        .sorted_by_key(|worker| &worker.name)
+    {
+        let job = match &worker.current_job {
+            Some(job) => job,
+            None => continue,
+        };
+        let stderr = match &job.stderr {
+            Some(stderr) => stderr,
+            None => continue,
+        };
+        // the progress bars usually rewrite the same line using \r
+        let last_lines = stderr
+            .split(['\n', '\r'])
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .collect_vec();
+        if last_lines.is_empty() {
+            continue;
+        }
+        lines.push(Spans(vec![
+            Span::styled(worker.name.clone(), *BOLD),
+            Span::raw(format!(" - {}", job.job)),
+        ]));
+        let skip = last_lines.len().saturating_sub(LIVE_STDERR_LINES);
+        for line in last_lines.into_iter().skip(skip) {
+            lines.push(Span::raw(format!("  {}", line)).into());
+        }
+    }
+    lines
+}
+
 /// Send to the current process `SIGINT`, letting it exit gracefully.
 fn send_ctrl_c() {
     let pid = std::process::id();
@@ -0,0 +1,169 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Error};
+use itertools::Itertools;
+
+use task_maker_diagnostics::{DiagnosticContext, DiagnosticLevel};
+
+use crate::ui::CompilationStatus;
+
+/// A condition of the final state of the evaluation that makes task-maker exit with an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
+pub enum ExitCondition {
+    /// The compilation of a source file failed.
+    Compilation,
+    /// The official solution failed on a testcase.
+    OfficialSolution,
+    /// A sanity check emitted an error.
+    Sanity,
+    /// The score of a solution doesn't match the expectations written in its source file.
+    Scores,
+}
+
+impl ExitCondition {
+    /// All the conditions.
+    pub const ALL: &'static [ExitCondition] = &[
+        ExitCondition::Compilation,
+        ExitCondition::OfficialSolution,
+        ExitCondition::Sanity,
+        ExitCondition::Scores,
+    ];
+
+    /// The name of the condition, as used on the command line.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExitCondition::Compilation => "compilation",
+            ExitCondition::OfficialSolution => "official-solution",
+            ExitCondition::Sanity => "sanity",
+            ExitCondition::Scores => "scores",
+        }
+    }
+}
+
+impl Display for ExitCondition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ExitCondition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        match ExitCondition::ALL.iter().find(|c| c.as_str() == s) {
+            Some(condition) => Ok(*condition),
+            None => bail!("Unknown exit condition: {}", s),
+        }
+    }
+}
+
+/// The conditions of the final state of the evaluation that make task-maker exit with an error.
+///
+/// It's parsed from a comma separated list of conditions, where `all` selects all of them, e.g.
+/// `all` or `compilation,scores`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExitPolicy {
+    /// The selected conditions.
+    conditions: BTreeSet<ExitCondition>,
+}
+
+impl ExitPolicy {
+    /// Whether the policy fails the evaluation on this condition.
+    pub fn contains(&self, condition: ExitCondition) -> bool {
+        self.conditions.contains(&condition)
+    }
+
+    /// The reasons for failing the evaluation that are common to all the task formats: the failed
+    /// compilations and the errors of the sanity checks.
+    pub(crate) fn common_failures(
+        &self,
+        compilations: &HashMap<PathBuf, CompilationStatus>,
+        diagnostics: &DiagnosticContext,
+    ) -> Vec<String> {
+        let mut failures = vec![];
+        if self.contains(ExitCondition::Compilation) {
+            for (path, status) in compilations.iter().sorted_by_key(|(path, _)| *path) {
+                if let CompilationStatus::Failed { .. } = status {
+                    let name = path.file_name().unwrap_or(path.as_os_str());
+                    failures.push(format!(
+                        "[{}] The compilation of {} failed",
+                        ExitCondition::Compilation,
+                        name.to_string_lossy()
+                    ));
+                }
+            }
+        }
+        if self.contains(ExitCondition::Sanity) {
+            for diagnostic in diagnostics.diagnostics() {
+                if diagnostic.level() == DiagnosticLevel::Error {
+                    failures.push(format!(
+                        "[{}] {}",
+                        ExitCondition::Sanity,
+                        diagnostic.message()
+                    ));
+                }
+            }
+        }
+        failures
+    }
+}
+
+impl FromStr for ExitPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut policy = ExitPolicy::default();
+        for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            if item.eq_ignore_ascii_case("all") {
+                policy.conditions.extend(ExitCondition::ALL);
+            } else {
+                policy.conditions.insert(item.parse()?);
+            }
+        }
+        if policy.conditions.is_empty() {
+            bail!("No exit condition selected");
+        }
+        Ok(policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use task_maker_diagnostics::Diagnostic;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_exit_policy() {
+        let policy = ExitPolicy::from_str("all").unwrap();
+        assert!(ExitCondition::ALL.iter().all(|c| policy.contains(*c)));
+        let policy = ExitPolicy::from_str("Compilation, official-solution").unwrap();
+        assert!(policy.contains(ExitCondition::Compilation));
+        assert!(policy.contains(ExitCondition::OfficialSolution));
+        assert!(!policy.contains(ExitCondition::Sanity));
+        assert!(!policy.contains(ExitCondition::Scores));
+        assert!(ExitPolicy::from_str("scores,foo").is_err());
+        assert!(ExitPolicy::from_str(",").is_err());
+    }
+
+    #[test]
+    fn test_common_failures() {
+        let mut compilations = HashMap::new();
+        compilations.insert(PathBuf::from("sol/ok.cpp"), CompilationStatus::Skipped);
+        let mut diagnostics = DiagnosticContext::new();
+        diagnostics.add_diagnostic(Diagnostic::warning("just a warning"));
+        diagnostics.add_diagnostic(Diagnostic::error("Missing statement"));
+
+        let policy = ExitPolicy::from_str("compilation").unwrap();
+        assert!(policy
+            .common_failures(&compilations, &diagnostics)
+            .is_empty());
+        let policy = ExitPolicy::from_str("sanity").unwrap();
+        let failures = policy.common_failures(&compilations, &diagnostics);
+        assert_eq!(failures, vec!["[sanity] Missing statement".to_string()]);
+    }
+}
@@ -1,21 +1,149 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Error};
+use typescript_definitions::TypeScriptify;
+
 use crate::ui::*;
 
+/// Version of the schema of the messages emitted by the `JsonUI`, in the `MAJOR.MINOR` form.
+///
+/// The major version is bumped when a message is changed in a backward-incompatible way (i.e. a
+/// field is removed or its type changes), the minor version is bumped when new messages or new
+/// fields are added.
+pub const JSON_SCHEMA_VERSION: &str = "2.2";
+
+/// The first message emitted by the `JsonUI`, containing the versions of the producer.
+#[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
+pub enum JsonUIHeader {
+    /// The header of a run of the `JsonUI`.
+    Header {
+        /// The version of task-maker that produced the messages.
+        version: String,
+        /// The version of the schema of the messages, see [`JSON_SCHEMA_VERSION`].
+        schema_version: String,
+    },
+}
+
+impl JsonUIHeader {
+    /// Make the header for the current version of task-maker.
+    pub fn current() -> JsonUIHeader {
+        JsonUIHeader::Header {
+            version: env!("CARGO_PKG_VERSION").into(),
+            schema_version: JSON_SCHEMA_VERSION.into(),
+        }
+    }
+}
+
+/// The last message emitted when more tasks are evaluated in a batch with the `JsonUI`, after the
+/// messages of all the tasks.
+#[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
+pub enum JsonUIBatchSummary {
+    /// The summary of the scores of all the tasks of the batch.
+    BatchSummary {
+        /// The outcome of each task, in the order they were evaluated.
+        tasks: Vec<BatchTaskOutcome>,
+    },
+}
+
+/// The outcome of the evaluation of a task of a batch.
+#[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
+pub struct BatchTaskOutcome {
+    /// The directory of the task.
+    pub task: PathBuf,
+    /// The scores of the solutions, by path relative to the task directory. `None` if the solution
+    /// was not scored.
+    pub scores: BTreeMap<PathBuf, Option<f64>>,
+    /// The error that prevented the evaluation of the task, if any.
+    pub error: Option<String>,
+}
+
+/// Check that the requested version of the schema is compatible with the one emitted by the
+/// `JsonUI`.
+///
+/// The request is compatible if it has the same major version and a minor version that is not
+/// newer than the current one. The minor version can be omitted.
+pub fn check_json_schema_version(requested: &str) -> Result<(), Error> {
+    let parse = |version: &str| -> Result<(u32, u32), Error> {
+        let mut parts = version.trim().splitn(2, '.');
+        let major = parts.next().unwrap_or_default();
+        let major = major
+            .parse()
+            .with_context(|| format!("Invalid major version: {}", major))?;
+        let minor = match parts.next() {
+            Some(minor) => minor
+                .parse()
+                .with_context(|| format!("Invalid minor version: {}", minor))?,
+            None => 0,
+        };
+        Ok((major, minor))
+    };
+    let (req_major, req_minor) = parse(requested)
+        .with_context(|| format!("Invalid schema version requested: {}", requested))?;
+    let (major, minor) = parse(JSON_SCHEMA_VERSION).expect("Invalid JSON_SCHEMA_VERSION");
+    if req_major != major || req_minor > minor {
+        bail!(
+            "The requested JSON schema version ({}) is not compatible with the one of this version of task-maker ({})",
+            requested,
+            JSON_SCHEMA_VERSION
+        );
+    }
+    Ok(())
+}
+
 /// This UI will print to stdout the UI messages as json.
+///
+/// The first line is always a [`JsonUIHeader`], followed by one [`UIMessage`] per line.
 #[derive(Default)]
-pub struct JsonUI;
+pub struct JsonUI {
+    /// Whether the header has already been printed.
+    header_printed: bool,
+}
 
 impl JsonUI {
     /// Make a new `JsonUI`.
     pub fn new() -> JsonUI {
-        JsonUI {}
+        JsonUI {
+            header_printed: false,
+        }
+    }
+
+    /// Print the header, if it hasn't been printed yet.
+    fn print_header(&mut self) {
+        if self.header_printed {
+            return;
+        }
+        self.header_printed = true;
+        let header =
+            serde_json::to_string(&JsonUIHeader::current()).expect("Failed to serialize header");
+        println!("{}", header);
     }
 }
 
 impl UI for JsonUI {
     fn on_message(&mut self, message: UIMessage) {
+        self.print_header();
         let message = serde_json::to_string(&message).expect("Failed to serialize message");
         println!("{}", message);
     }
 
-    fn finish(&mut self) {}
+    fn finish(&mut self) {
+        self.print_header();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_json_schema_version() {
+        assert!(check_json_schema_version(JSON_SCHEMA_VERSION).is_ok());
+        assert!(check_json_schema_version("2").is_ok());
+        assert!(check_json_schema_version("2.0").is_ok());
+        assert!(check_json_schema_version("2.999").is_err());
+        assert!(check_json_schema_version("1.0").is_err());
+        assert!(check_json_schema_version("3.0").is_err());
+        assert!(check_json_schema_version("lol").is_err());
+    }
 }
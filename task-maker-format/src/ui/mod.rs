@@ -1,10 +1,10 @@
 //! The UI functionality for the task formats.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::PathBuf;
 use std::sync::mpsc::{channel, Receiver, Sender};
 
-use anyhow::Error;
+use anyhow::{bail, Error};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 pub use termcolor::WriteColor;
@@ -12,19 +12,27 @@ use termcolor::{Color, ColorChoice, ColorSpec, StandardStream};
 use typescript_definitions::TypeScriptify;
 
 pub use curses::{
-    inner_block, render_block, render_server_status, CursesDrawer, CursesUI, FrameType,
+    inner_block, live_stderr_height, render_block, render_live_stderr, render_server_status,
+    CursesDrawer, CursesUI, FrameType,
+};
+pub use exit_policy::{ExitCondition, ExitPolicy};
+pub use json::{
+    check_json_schema_version, BatchTaskOutcome, JsonUI, JsonUIBatchSummary, JsonUIHeader,
+    JSON_SCHEMA_VERSION,
 };
-pub use json::JsonUI;
 pub use print::PrintUI;
 pub use raw::RawUI;
 pub use silent::SilentUI;
-use task_maker_dag::{ExecutionResourcesUsage, ExecutionResult, ExecutionStatus, WorkerUuid};
+use task_maker_dag::{
+    ExecutionDAGConfig, ExecutionResourcesUsage, ExecutionResult, ExecutionStatus, WorkerUuid,
+};
 use task_maker_diagnostics::DiagnosticContext;
 pub use ui_message::UIMessage;
 
-use crate::{cwrite, cwriteln};
+use crate::{cwrite, cwriteln, TaskFormat};
 
 pub mod curses;
+mod exit_policy;
 mod json;
 mod print;
 mod raw;
@@ -112,6 +120,7 @@ lazy_static! {
 }
 
 /// The status of an execution.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TypeScriptify)]
 pub enum UIExecutionStatus {
     /// The `Execution` is known to the DAG and when all its dependencies are ready it will
@@ -192,6 +201,22 @@ impl CompilationStatus {
             UIExecutionStatus::Skipped => *self = CompilationStatus::Skipped,
         }
     }
+
+    /// The number of warnings printed by the compiler to its standard error, recognized by the
+    /// `warning:` of the messages of gcc, clang, rustc and javac. The summaries like `2 warnings
+    /// emitted` are not counted.
+    pub fn compiler_warnings(&self) -> usize {
+        let stderr = match self {
+            CompilationStatus::Done { stderr, .. } | CompilationStatus::Failed { stderr, .. } => {
+                stderr.as_deref().unwrap_or_default()
+            }
+            _ => return 0,
+        };
+        stderr
+            .lines()
+            .filter(|line| line.contains("warning:") && !line.contains(" emitted"))
+            .count()
+    }
 }
 
 /// The state of a task, all the information for the UI are stored here.
@@ -203,6 +228,15 @@ pub trait UIStateT {
 
     /// Print the final results using a finish UI.
     fn finish(&mut self);
+
+    /// The initial state of a new evaluation of `task`, for the UIs shown across more evaluations.
+    /// `None` if this state cannot describe the task.
+    fn restart(&self, _task: &TaskFormat, _config: ExecutionDAGConfig) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
 }
 
 /// UI that prints to `stdout` the ending result of the evaluation of a task.
@@ -243,6 +277,12 @@ impl<'a> FinishUIUtils<'a> {
                 CompilationStatus::Done { result, .. } => {
                     cwrite!(self, GREEN, " OK  ");
                     FinishUIUtils::print_time_memory(&result.resources);
+                    FinishUIUtils::print_toolchain(result);
+                    match status.compiler_warnings() {
+                        0 => {}
+                        1 => cwrite!(self, YELLOW, " | 1 warning"),
+                        n => cwrite!(self, YELLOW, " | {} warnings", n),
+                    }
                 }
                 CompilationStatus::Failed {
                     result,
@@ -251,6 +291,7 @@ impl<'a> FinishUIUtils<'a> {
                 } => {
                     cwrite!(self, RED, "FAIL ");
                     FinishUIUtils::print_time_memory(&result.resources);
+                    FinishUIUtils::print_toolchain(result);
                     if let Some(stdout) = stdout {
                         if !stdout.trim().is_empty() {
                             println!();
@@ -272,6 +313,41 @@ impl<'a> FinishUIUtils<'a> {
             }
             println!();
         }
+
+        // the compilations may have been run by workers with different toolchains
+        let mut versions: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+        for status in compilations.values() {
+            let result = match status {
+                CompilationStatus::Done { result, .. }
+                | CompilationStatus::Failed { result, .. } => result,
+                _ => continue,
+            };
+            if let Some(toolchain) = &result.toolchain {
+                if let Some(version) = &toolchain.version {
+                    versions
+                        .entry(toolchain.name())
+                        .or_default()
+                        .insert(version);
+                }
+            }
+        }
+        for (toolchain, versions) in versions {
+            if versions.len() > 1 {
+                cwrite!(self, YELLOW, "Warning: ");
+                println!(
+                    "the compilations used different versions of {}: {}",
+                    toolchain,
+                    versions.iter().join(", ")
+                );
+            }
+        }
+    }
+
+    /// Print the version of the toolchain used by an execution, if known.
+    pub fn print_toolchain(result: &ExecutionResult) {
+        if let Some(version) = result.toolchain.as_ref().and_then(|t| t.version.as_ref()) {
+            print!(" | {}", version);
+        }
     }
 
     /// Print the time and memory usage of an execution.
@@ -283,6 +359,47 @@ impl<'a> FinishUIUtils<'a> {
         );
     }
 
+    /// Describe the additional resource metrics of an execution, if any of them was collected.
+    pub fn resource_details(resources: &ExecutionResourcesUsage) -> Option<String> {
+        let mut details = vec![];
+        if let (Some(voluntary), Some(involuntary)) = (
+            resources.voluntary_context_switches,
+            resources.involuntary_context_switches,
+        ) {
+            details.push(format!("ctx switches {}/{}", voluntary, involuntary));
+        }
+        if let (Some(minor), Some(major)) =
+            (resources.minor_page_faults, resources.major_page_faults)
+        {
+            details.push(format!("page faults {}/{}", minor, major));
+        }
+        if let Some(peak) = resources.memory_samples.iter().map(|s| s.memory).max() {
+            details.push(format!(
+                "peak rss {:.1}MiB over {} samples",
+                peak as f64 / 1024.0,
+                resources.memory_samples.len()
+            ));
+        }
+        if details.is_empty() {
+            None
+        } else {
+            Some(details.join(" | "))
+        }
+    }
+
+    /// Print the logs of the sandbox of a failed execution, if they have been fetched from the
+    /// worker.
+    pub fn print_sandbox_logs(&mut self, result: &ExecutionResult) {
+        let logs = match &result.sandbox_logs {
+            Some(logs) => logs,
+            None => return,
+        };
+        for (name, content) in &logs.files {
+            cwriteln!(self, BOLD, "Sandbox log {}:", name);
+            println!("{}", content.trim());
+        }
+    }
+
     /// Print a message for the non-successful variants of the provided status.
     pub fn print_fail_execution_status(status: &ExecutionStatus) {
         match status {
@@ -342,6 +459,14 @@ pub trait UI: Send {
     fn on_message(&mut self, message: UIMessage);
     /// Make the UI print the ending results.
     fn finish(&mut self);
+    /// Start showing a new evaluation of `task`, discarding the state of the previous one, instead
+    /// of building a new UI (e.g. with `--watch`). Only the curses UI supports it.
+    fn restart(&mut self, _task: &TaskFormat, _config: ExecutionDAGConfig) -> Result<(), Error> {
+        bail!("This UI cannot show more than one evaluation")
+    }
+    /// Show some lines of text together with the state of the evaluation, until the next
+    /// evaluation starts. The UIs that don't support it ignore them.
+    fn set_notice(&mut self, _notice: Vec<String>) {}
 }
 
 /// The type of the UI to use, it enumerates all the known UI interfaces.
@@ -389,6 +514,17 @@ impl Default for StdoutPrinter {
     }
 }
 
+/// Format a size in bytes in a human readable form.
+pub(crate) fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{}B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1}KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1}MiB", bytes as f64 / 1024.0 / 1024.0)
+    }
+}
+
 /// Write to `$self.stream`, in the color specified as second parameter. The arguments that follow
 /// will be passed to `write!`.
 ///
@@ -447,3 +583,37 @@ macro_rules! cwriteln {
         $self.stream.reset().unwrap();
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(1000), "1000B");
+        assert_eq!(format_size(3000), "2.9KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0MiB");
+    }
+
+    #[test]
+    fn test_compiler_warnings() {
+        let stderr = [
+            "sol.cpp: In function 'int main()':",
+            "sol.cpp:5:9: warning: unused variable 'x' [-Wunused-variable]",
+            "    5 |     int x;",
+            "warning: unused variable: `y`",
+            "warning: 1 warning emitted",
+        ]
+        .join("\n");
+        let status = CompilationStatus::Done {
+            result: ExecutionResult {
+                status: ExecutionStatus::Success,
+                ..Default::default()
+            },
+            stdout: None,
+            stderr: Some(stderr),
+        };
+        assert_eq!(status.compiler_warnings(), 2);
+        assert_eq!(CompilationStatus::Running.compiler_warnings(), 0);
+    }
+}
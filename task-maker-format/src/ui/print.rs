@@ -219,11 +219,42 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
                 solution,
                 score,
                 message,
+                verdict,
             } => {
                 print!("[TESTCAS] ");
                 self.write_message(format!(
                     "Solution {:?} scored {} on testcase {} of subtask {}: {}",
-                    solution, score, testcase, subtask, message
+                    solution,
+                    score,
+                    testcase,
+                    subtask,
+                    verdict.unwrap_or(message)
+                ));
+            }
+            UIMessage::IOISeedRun {
+                subtask,
+                testcase,
+                solution,
+                run,
+                num_runs,
+                seed,
+                score,
+                message,
+                selected,
+            } => {
+                print!("[SEEDRUN] ");
+                self.write_message(format!(
+                    "Solution {:?} scored {} on testcase {} of subtask {} in run {} of {} (seed \
+                    {}){}: {}",
+                    solution,
+                    score,
+                    testcase,
+                    subtask,
+                    run + 1,
+                    num_runs,
+                    seed,
+                    if selected { " [selected]" } else { "" },
+                    message
                 ));
             }
             UIMessage::IOISubtaskScore {
@@ -266,6 +297,13 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
                 println!("[DIAGNOSTIC]");
                 println!("{}", diagnostic);
             }
+            UIMessage::Deprecation {
+                feature,
+                diagnostic,
+            } => {
+                println!("[DEPRECATION] {}", feature);
+                println!("{}", diagnostic);
+            }
             UIMessage::TerryTask { .. } => {}
             UIMessage::TerryGeneration {
                 solution,
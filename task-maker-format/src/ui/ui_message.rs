@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 use serde::{Deserialize, Serialize};
@@ -114,8 +114,35 @@ pub enum UIMessage {
         solution: PathBuf,
         /// The score of the testcase.
         score: f64,
-        /// The message associated with the score.
+        /// The message associated with the score, as produced by the checker.
         message: String,
+        /// The verdict to show instead of the message, if the task maps the message of the checker
+        /// to one.
+        #[serde(default)]
+        verdict: Option<String>,
+    },
+
+    /// The results of the runs of a solution on a testcase, when the solutions are evaluated with
+    /// more seeds. They are sent when all the runs are scored, before the score of the testcase.
+    IOISeedRun {
+        /// The id of the subtask.
+        subtask: SubtaskId,
+        /// The id of the testcase.
+        testcase: TestcaseId,
+        /// The path of the solution.
+        solution: PathBuf,
+        /// The index of the run.
+        run: usize,
+        /// The number of runs of the solution on the testcase.
+        num_runs: usize,
+        /// The seed of the run.
+        seed: u64,
+        /// The score of the run.
+        score: f64,
+        /// The message associated with the score of the run.
+        message: String,
+        /// Whether this is the run whose verdict is given to the testcase.
+        selected: bool,
     },
 
     /// The score of a subtask is ready.
@@ -214,4 +241,47 @@ pub enum UIMessage {
         /// The diagnostic message.
         diagnostic: Diagnostic,
     },
+
+    /// An option or a task configuration that is going to be removed is being used.
+    Deprecation {
+        /// A stable identifier of the deprecated feature, for the tools that consume the UI
+        /// messages.
+        feature: String,
+        /// The diagnostic message describing the deprecation and how to migrate.
+        diagnostic: Diagnostic,
+    },
+}
+
+impl UIMessage {
+    /// The path of the solution of a IOI task this message is about, if any. For the compilations
+    /// it's the path of the compiled file.
+    pub fn ioi_solution(&self) -> Option<&Path> {
+        match self {
+            UIMessage::Compilation { file, .. } => Some(file),
+            UIMessage::IOIEvaluation { solution, .. }
+            | UIMessage::IOIChecker { solution, .. }
+            | UIMessage::IOITestcaseScore { solution, .. }
+            | UIMessage::IOISeedRun { solution, .. }
+            | UIMessage::IOISubtaskScore { solution, .. }
+            | UIMessage::IOITaskScore { solution, .. } => Some(solution),
+            _ => None,
+        }
+    }
+
+    /// The same message, but about another solution of a IOI task. `None` if this message is not
+    /// about a solution.
+    pub fn with_ioi_solution(&self, path: &Path) -> Option<UIMessage> {
+        let mut message = self.clone();
+        match &mut message {
+            UIMessage::Compilation { file, .. } => *file = path.into(),
+            UIMessage::IOIEvaluation { solution, .. }
+            | UIMessage::IOIChecker { solution, .. }
+            | UIMessage::IOITestcaseScore { solution, .. }
+            | UIMessage::IOISeedRun { solution, .. }
+            | UIMessage::IOISubtaskScore { solution, .. }
+            | UIMessage::IOITaskScore { solution, .. } => *solution = path.into(),
+            _ => return None,
+        }
+        Some(message)
+    }
 }
@@ -0,0 +1,193 @@
+//! The comparison of the output files done by the default checker.
+//!
+//! Two files are considered equal when they contain the same lines, ignoring the blank lines, and
+//! each pair of lines contains the same sequence of tokens, where a token is a maximal sequence of
+//! bytes that are not ASCII whitespaces (space, tab, line feed, form feed and carriage return). So
+//! the amount and the kind of the whitespaces between the tokens of a line don't matter, including
+//! the leading and trailing ones, but the tokens cannot be moved to another line. These are the
+//! semantics of `diff --ignore-blank-lines --ignore-space-change`, which was used before, except
+//! for the leading whitespaces. The files are compared as bytes, without decoding them, so the
+//! result doesn't depend on the locale.
+
+use std::io::{BufRead, BufReader, Read};
+
+/// The size of the buffer used for reading each file.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// An element of a file, as read by `Tokens`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Item {
+    /// A token, whose content is in the buffer passed to `Tokens::next`.
+    Token,
+    /// The end of a line with at least a token.
+    LineEnd,
+    /// The end of the file.
+    End,
+}
+
+/// What happened while reading a chunk of a file.
+enum Step {
+    /// More of the file has to be read.
+    Continue,
+    /// A token has been read completely.
+    Token,
+    /// A line feed has been read.
+    LineFeed,
+    /// The file has ended.
+    End,
+}
+
+/// Reads the tokens and the ends of the non-blank lines of a file, one at a time.
+struct Tokens<R> {
+    reader: R,
+    /// Whether the current line has at least a token.
+    line_has_tokens: bool,
+}
+
+impl<R: BufRead> Tokens<R> {
+    /// Read the next element of the file, storing the content of the token into `token`.
+    fn next(&mut self, token: &mut Vec<u8>) -> std::io::Result<Item> {
+        token.clear();
+        loop {
+            let (consumed, step) = {
+                let buf = self.reader.fill_buf()?;
+                if buf.is_empty() {
+                    (0, Step::End)
+                } else if token.is_empty() {
+                    // skip the whitespaces before the token, stopping at the end of the line
+                    match buf
+                        .iter()
+                        .position(|b| *b == b'\n' || !b.is_ascii_whitespace())
+                    {
+                        None => (buf.len(), Step::Continue),
+                        Some(start) if buf[start] == b'\n' => (start + 1, Step::LineFeed),
+                        Some(start) => {
+                            let (len, step) = read_token(token, &buf[start..]);
+                            (start + len, step)
+                        }
+                    }
+                } else {
+                    read_token(token, buf)
+                }
+            };
+            self.reader.consume(consumed);
+            match step {
+                Step::Continue => {}
+                Step::Token => {
+                    self.line_has_tokens = true;
+                    return Ok(Item::Token);
+                }
+                Step::LineFeed => {
+                    if std::mem::take(&mut self.line_has_tokens) {
+                        return Ok(Item::LineEnd);
+                    }
+                }
+                Step::End => {
+                    if !token.is_empty() {
+                        self.line_has_tokens = true;
+                        return Ok(Item::Token);
+                    }
+                    // the last line may not end with a line feed
+                    if std::mem::take(&mut self.line_has_tokens) {
+                        return Ok(Item::LineEnd);
+                    }
+                    return Ok(Item::End);
+                }
+            }
+        }
+    }
+}
+
+/// Add to `token` the bytes of `buf` up to the first whitespace, returning how many bytes were
+/// added and whether the token is complete.
+fn read_token(token: &mut Vec<u8>, buf: &[u8]) -> (usize, Step) {
+    match buf.iter().position(u8::is_ascii_whitespace) {
+        Some(len) => {
+            token.extend_from_slice(&buf[..len]);
+            (len, Step::Token)
+        }
+        None => {
+            token.extend_from_slice(buf);
+            (buf.len(), Step::Continue)
+        }
+    }
+}
+
+/// Check whether the two files contain the same non-blank lines of tokens. The files are read
+/// once, in chunks, so they can be larger than the memory.
+pub fn white_diff<A: Read, B: Read>(a: A, b: B) -> std::io::Result<bool> {
+    let mut a = Tokens {
+        reader: BufReader::with_capacity(BUFFER_SIZE, a),
+        line_has_tokens: false,
+    };
+    let mut b = Tokens {
+        reader: BufReader::with_capacity(BUFFER_SIZE, b),
+        line_has_tokens: false,
+    };
+    let mut token_a = Vec::new();
+    let mut token_b = Vec::new();
+    loop {
+        let item_a = a.next(&mut token_a)?;
+        let item_b = b.next(&mut token_b)?;
+        if item_a != item_b || token_a != token_b {
+            return Ok(false);
+        }
+        if item_a == Item::End {
+            return Ok(true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn equal(a: &str, b: &str) -> bool {
+        white_diff(a.as_bytes(), b.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_white_diff_equal() {
+        assert!(equal("", ""));
+        assert!(equal("", " \n\n"));
+        assert!(equal("1 2\n3\n", "1 2\n3\n"));
+        assert!(equal("1 2\n3\n", "  1\t2  \r\n\n3"));
+        assert!(equal("1 2\n\n\n3\n", "\n1 2\n  \n3\n\n"));
+        assert!(equal("1 2 3", "1 2 3\n"));
+    }
+
+    #[test]
+    fn test_white_diff_different() {
+        assert!(!equal("1 2 3", "1 23"));
+        assert!(!equal("1 2 3", "1 2"));
+        assert!(!equal("", "0"));
+        assert!(!equal("abc", "ABC"));
+    }
+
+    #[test]
+    fn test_white_diff_lines() {
+        assert!(!equal("1 2 3", "1\n2\n3\n"));
+        assert!(!equal("1 2\n3\n", "1\n2 3\n"));
+        assert!(!equal("1\n2\n", "1 2\n"));
+    }
+
+    #[test]
+    fn test_white_diff_tokens_across_buffers() {
+        let token = "x".repeat(BUFFER_SIZE * 2 + 7);
+        let a = format!("{} {}\n", token, token);
+        let b = format!("\n\n{}\t{}", token, token);
+        assert!(equal(&a, &b));
+        let c = format!("{}{}", token, token);
+        assert!(!equal(&a, &c));
+        let d = format!("{}\n{}", token, token);
+        assert!(!equal(&a, &d));
+    }
+
+    #[test]
+    fn test_white_diff_blank_lines_across_buffers() {
+        let blank = " ".repeat(BUFFER_SIZE + 3);
+        let a = format!("1\n{}\n{}\n2\n", blank, blank);
+        assert!(equal(&a, "1\n2"));
+        assert!(!equal(&a, "1 2"));
+    }
+}
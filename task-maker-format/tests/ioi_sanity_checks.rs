@@ -2,7 +2,7 @@ use std::process::Command;
 use std::sync::Arc;
 use task_maker_format::ioi::{Booklet, BookletConfig, IOITask, Statement, StatementConfig};
 use task_maker_format::ui::UIMessage;
-use task_maker_format::EvaluationData;
+use task_maker_format::{EvaluationData, Solution};
 use task_maker_lang::GraderMap;
 
 mod utils;
@@ -83,6 +83,37 @@ fn test_sanity_checks_att_templates() {
     has_warning(&warnings, "Missing template at att/task.cpp");
 }
 
+#[test]
+fn test_sanity_checks_att_templates_match_graders() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let mut task = utils::new_task_with_context(tmpdir.path());
+    std::fs::create_dir(tmpdir.path().join("sol")).unwrap();
+    std::fs::create_dir(tmpdir.path().join("att")).unwrap();
+    std::fs::write(tmpdir.path().join("sol/grader.cpp"), "x").unwrap();
+    std::fs::write(tmpdir.path().join("sol/grader.py"), "x").unwrap();
+    std::os::unix::fs::symlink("../sol/grader.cpp", tmpdir.path().join("att/grader.cpp")).unwrap();
+    std::fs::write(tmpdir.path().join("att/task.cpp"), "x").unwrap();
+    std::fs::write(tmpdir.path().join("att/task.py"), "x").unwrap();
+    task.grader_map = Arc::new(GraderMap::new(vec![
+        tmpdir.path().join("sol/grader.cpp"),
+        tmpdir.path().join("sol/grader.py"),
+    ]));
+
+    let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+    task.sanity_checks.pre_hook(&task, &mut eval).unwrap();
+    let compilations: Vec<_> = eval
+        .dag
+        .data
+        .execution_groups
+        .values()
+        .flat_map(|group| group.executions.iter())
+        .filter(|exec| exec.description.starts_with("Compilation of task."))
+        .map(|exec| exec.description.clone())
+        .collect();
+    // the python template is not compiled, the cpp one is compiled only with the sol grader
+    assert_eq!(compilations, vec!["Compilation of task.cpp"]);
+}
+
 #[test]
 fn test_sanity_checks_att_sample_files_nothing() {
     let tmpdir = tempfile::TempDir::new().unwrap();
@@ -413,3 +444,30 @@ fn test_sanity_checks_statement_git_known() {
     let warnings = get_post_warnings(&task);
     does_not_have_warning(&warnings, "git");
 }
+
+#[test]
+fn test_sanity_checks_forbidden_constructs() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let mut task = utils::new_task_with_context(tmpdir.path());
+    task.forbidden_constructs = vec!["system(".into(), "#include <thread>".into()];
+    std::fs::create_dir(tmpdir.path().join("sol")).unwrap();
+    let source = "#include <cstdlib>\nint main() { system(\"ls\"); system(\"ls\"); }\n";
+    std::fs::write(tmpdir.path().join("sol/sol.cpp"), source).unwrap();
+
+    let (mut eval, recv) = EvaluationData::new(tmpdir.path());
+    let path = tmpdir.path().join("sol/sol.cpp");
+    let solution = Solution::new(&path, tmpdir.path(), None, &mut eval).unwrap();
+    eval.solutions.push(solution);
+    task.sanity_checks.pre_hook(&task, &mut eval).unwrap();
+    let mut warnings = vec![];
+    while let Ok(mex) = recv.try_recv() {
+        if let UIMessage::Diagnostic { diagnostic } = mex {
+            warnings.push(diagnostic.to_string())
+        }
+    }
+    has_warning(
+        &warnings,
+        "sol/sol.cpp uses the forbidden construct 'system('",
+    );
+    does_not_have_warning(&warnings, "#include <thread>");
+}
@@ -1,8 +1,9 @@
 use std::path::PathBuf;
 use std::sync::Arc;
+use task_maker_dag::ExecutionTag;
 use task_maker_format::ioi::{
-    Booklet, BookletConfig, InputGenerator, InputValidator, OutputGenerator, Statement,
-    StatementConfig,
+    BatchTypeData, Booklet, BookletConfig, Checker, InputGenerator, InputValidator,
+    OutputGenerator, Statement, StatementConfig, SubtaskInfo, TaskType,
 };
 use task_maker_format::{EvaluationConfig, EvaluationData, SourceFile};
 
@@ -18,6 +19,78 @@ fn test_ioi_task_execute_copy() {
     assert_eq!(eval.dag.data.provided_files.len(), 6);
 }
 
+#[test]
+fn test_ioi_task_execute_filter_testcases() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let mut task = utils::new_task_with_context(tmpdir.path());
+    let (mut eval, _receiver) = EvaluationData::new(tmpdir.path());
+    let config = EvaluationConfig {
+        subtask_filter: vec![1],
+        testcase_filter: vec![2],
+        ..Default::default()
+    };
+    task.build_dag(&mut eval, &config).unwrap();
+    assert_eq!(task.subtasks.len(), 1);
+    assert_eq!(task.subtasks[&1].testcases, vec![2]);
+    assert_eq!(task.testcases.len(), 1);
+    assert_eq!(eval.dag.data.provided_files.len(), 2);
+
+    let mut task = utils::new_task_with_context(tmpdir.path());
+    let (mut eval, _receiver) = EvaluationData::new(tmpdir.path());
+    let config = EvaluationConfig {
+        subtask_filter: vec![0],
+        testcase_filter: vec![2],
+        ..Default::default()
+    };
+    assert!(task.build_dag(&mut eval, &config).is_err());
+}
+
+#[test]
+fn test_ioi_task_execute_filter_tags() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let mut task = utils::new_task_with_context(tmpdir.path());
+    task.testcases.get_mut(&0).unwrap().tags = vec!["tiny".into()];
+    task.testcases.get_mut(&2).unwrap().tags = vec!["tree".into(), "tiny".into()];
+    let (mut eval, _receiver) = EvaluationData::new(tmpdir.path());
+    let config = EvaluationConfig {
+        tag_filter: vec!["tree".into()],
+        ..Default::default()
+    };
+    task.build_dag(&mut eval, &config).unwrap();
+    assert_eq!(task.subtasks.len(), 1);
+    assert_eq!(task.subtasks[&1].testcases, vec![2]);
+    assert_eq!(task.testcases.len(), 1);
+
+    let mut task = utils::new_task_with_context(tmpdir.path());
+    let (mut eval, _receiver) = EvaluationData::new(tmpdir.path());
+    let config = EvaluationConfig {
+        tag_filter: vec!["line".into()],
+        ..Default::default()
+    };
+    assert!(task.build_dag(&mut eval, &config).is_err());
+}
+
+#[test]
+fn test_ioi_task_retain_shared_testcases() {
+    let mut task = utils::new_task();
+    // subtask 2 uses the testcases of subtask 1
+    task.subtasks.insert(
+        2,
+        SubtaskInfo {
+            id: 2,
+            max_score: 0.0,
+            testcases: vec![1, 2],
+            dependencies: vec![1],
+            ..Default::default()
+        },
+    );
+    task.retain_testcases(&[2], &[], &[]);
+    assert_eq!(task.subtasks.len(), 1);
+    assert_eq!(task.subtasks[&2].testcases_owned, vec![1, 2]);
+    assert!(task.subtasks[&2].dependencies.is_empty());
+    assert_eq!(task.testcases.len(), 2);
+}
+
 #[test]
 fn test_ioi_task_execute_gen() {
     let tmpdir = tempfile::TempDir::new().unwrap();
@@ -87,6 +160,57 @@ fn test_ioi_task_execute_eval() {
     assert_eq!(eval.dag.data.execution_groups.len(), 3 + 3); // eval + checker
 }
 
+#[test]
+fn test_ioi_task_execute_compile_once() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let mut task = utils::new_task_with_context(tmpdir.path());
+
+    std::fs::create_dir(tmpdir.path().join("sol")).unwrap();
+    std::fs::create_dir(tmpdir.path().join("check")).unwrap();
+    std::fs::write(tmpdir.path().join("sol/solution.cpp"), "foo").unwrap();
+    std::fs::write(tmpdir.path().join("sol/other.cpp"), "foo").unwrap();
+    std::fs::write(tmpdir.path().join("check/checker.cpp"), "foo").unwrap();
+
+    // the official solution is also evaluated as a solution
+    let official = SourceFile::new(
+        tmpdir.path().join("sol/solution.cpp"),
+        tmpdir.path(),
+        "",
+        Some(task.grader_map.clone()),
+        Some(tmpdir.path().join("bin/official_solution")),
+    )
+    .unwrap();
+    let official = Arc::new(official);
+    for testcase in task.testcases.values_mut() {
+        testcase.output_generator = OutputGenerator::Custom(official.clone(), vec![]);
+    }
+    let checker = SourceFile::new(
+        tmpdir.path().join("check/checker.cpp"),
+        tmpdir.path(),
+        "",
+        None,
+        Some(tmpdir.path().join("check/checker")),
+    )
+    .unwrap();
+    task.task_type = TaskType::Batch(BatchTypeData {
+        output_generator: None,
+        checker: Checker::Custom(Arc::new(checker)),
+    });
+
+    let (mut eval, _receiver) = EvaluationData::new(tmpdir.path());
+    task.build_dag(&mut eval, &EvaluationConfig::default())
+        .unwrap();
+    let compilations = eval
+        .dag
+        .data
+        .execution_groups
+        .values()
+        .flat_map(|group| &group.executions)
+        .filter(|exec| exec.tag == Some(ExecutionTag::from("compilation")))
+        .count();
+    assert_eq!(compilations, 3); // solution.cpp + other.cpp + checker.cpp
+}
+
 #[test]
 fn test_ioi_task_execute_booklet() {
     let tmpdir = tempfile::TempDir::new().unwrap();
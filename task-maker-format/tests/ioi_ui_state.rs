@@ -4,7 +4,8 @@ use task_maker_dag::ExecutionStatus;
 use task_maker_exec::ExecutorStatus;
 use task_maker_format::ioi::{TestcaseEvaluationStatus, TestcaseGenerationStatus, UIState};
 use task_maker_format::ui::UIStateT;
-use task_maker_format::ui::{CompilationStatus, UIExecutionStatus, UIMessage};
+use task_maker_format::ui::{CompilationStatus, ExitPolicy, UIExecutionStatus, UIMessage};
+use task_maker_format::SolutionInfo;
 
 mod utils;
 
@@ -16,6 +17,8 @@ fn test_ui_state_server_status() {
         connected_workers: vec![],
         ready_execs: 1,
         waiting_execs: 123,
+        transfers: Default::default(),
+        client_transfers: Default::default(),
     };
     assert_eq!(ui.executor_status, None);
     ui.apply(UIMessage::ServerStatus {
@@ -408,6 +411,35 @@ fn test_ui_state_evaluation_return_code() {
     );
 }
 
+#[test]
+fn test_ui_state_evaluation_part_skipped_after_failure() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    let file = PathBuf::from("file");
+    let mut result = utils::bad_result();
+    result.status = ExecutionStatus::ReturnCode(1);
+    ui.apply(UIMessage::IOIEvaluation {
+        subtask: 0,
+        testcase: 0,
+        solution: file.clone(),
+        status: UIExecutionStatus::Done { result },
+        part: 0,
+        num_parts: 2,
+    });
+    ui.apply(UIMessage::IOIEvaluation {
+        subtask: 0,
+        testcase: 0,
+        solution: file.clone(),
+        status: UIExecutionStatus::Skipped,
+        part: 1,
+        num_parts: 2,
+    });
+    assert_eq!(
+        ui.evaluations[&file].testcases[&0].status,
+        TestcaseEvaluationStatus::RuntimeError
+    );
+}
+
 #[test]
 fn test_ui_state_evaluation_signal() {
     let task = utils::new_task();
@@ -608,6 +640,7 @@ fn test_ui_state_testcase_score_wrong_answer() {
         solution: file.clone(),
         score: 0.0,
         message: "nope".to_string(),
+        verdict: None,
     });
     assert_eq!(ui.evaluations[&file].testcases[&0].score, Some(0.0));
     assert_eq!(
@@ -616,6 +649,25 @@ fn test_ui_state_testcase_score_wrong_answer() {
     );
 }
 
+#[test]
+fn test_ui_state_testcase_score_verdict() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    let file = PathBuf::from("file");
+    ui.apply(UIMessage::IOITestcaseScore {
+        subtask: 0,
+        testcase: 0,
+        solution: file.clone(),
+        score: 0.0,
+        message: "Output is incorrect".to_string(),
+        verdict: Some("Risposta errata".to_string()),
+    });
+    assert_eq!(
+        ui.evaluations[&file].testcases[&0].status,
+        TestcaseEvaluationStatus::WrongAnswer("Risposta errata".into())
+    );
+}
+
 #[test]
 fn test_ui_state_testcase_score_partial() {
     let task = utils::new_task();
@@ -627,6 +679,7 @@ fn test_ui_state_testcase_score_partial() {
         solution: file.clone(),
         score: 0.5,
         message: "almost".to_string(),
+        verdict: None,
     });
     assert_eq!(ui.evaluations[&file].testcases[&0].score, Some(0.5));
     assert_eq!(
@@ -646,6 +699,7 @@ fn test_ui_state_testcase_score_accepted() {
         solution: file.clone(),
         score: 1.0,
         message: "yep".to_string(),
+        verdict: None,
     });
     assert_eq!(ui.evaluations[&file].testcases[&0].score, Some(1.0));
     assert_eq!(
@@ -654,6 +708,52 @@ fn test_ui_state_testcase_score_accepted() {
     );
 }
 
+#[test]
+fn test_ui_state_seed_runs() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    let file = PathBuf::from("file");
+    let mut result = utils::bad_result();
+    result.status = ExecutionStatus::TimeLimitExceeded;
+    let results = [result, utils::good_result()];
+    for (run, result) in results.into_iter().enumerate() {
+        ui.apply(UIMessage::IOIEvaluation {
+            subtask: 0,
+            testcase: 0,
+            solution: file.clone(),
+            status: UIExecutionStatus::Done { result },
+            part: run,
+            num_parts: 2,
+        });
+    }
+    for (run, score) in [0.0, 1.0].into_iter().enumerate() {
+        ui.apply(UIMessage::IOISeedRun {
+            subtask: 0,
+            testcase: 0,
+            solution: file.clone(),
+            run,
+            num_runs: 2,
+            seed: run as u64 + 1,
+            score,
+            message: "msg".to_string(),
+            selected: run == 0,
+        });
+    }
+    ui.apply(UIMessage::IOITestcaseScore {
+        subtask: 0,
+        testcase: 0,
+        solution: file.clone(),
+        score: 0.0,
+        message: "msg".to_string(),
+        verdict: None,
+    });
+    let testcase = &ui.evaluations[&file].testcases[&0];
+    assert_eq!(testcase.seed_runs.len(), 2);
+    assert_eq!(testcase.seed_runs[1].as_ref().unwrap().seed, 2);
+    // the verdict is the one of the selected run, not of the last one
+    assert_eq!(testcase.status, TestcaseEvaluationStatus::TimeLimitExceeded);
+}
+
 #[test]
 fn test_ui_state_subtask_score() {
     let task = utils::new_task();
@@ -716,3 +816,74 @@ fn test_ui_state_booklet_dep() {
         UIExecutionStatus::Pending
     );
 }
+
+#[test]
+fn test_ui_state_exit_failures() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    ui.apply(UIMessage::Compilation {
+        file: PathBuf::from("sol/wrong.cpp"),
+        status: UIExecutionStatus::Done {
+            result: utils::bad_result(),
+        },
+    });
+    ui.apply(UIMessage::IOISolution {
+        subtask: 0,
+        testcase: 0,
+        status: UIExecutionStatus::Done {
+            result: utils::bad_result(),
+        },
+    });
+    let policy: ExitPolicy = "scores,sanity".parse().unwrap();
+    assert!(ui.exit_failures(&policy).is_empty());
+    let policy: ExitPolicy = "compilation".parse().unwrap();
+    assert_eq!(
+        ui.exit_failures(&policy),
+        vec!["[compilation] The compilation of wrong.cpp failed".to_string()]
+    );
+    let policy: ExitPolicy = "all".parse().unwrap();
+    let failures = ui.exit_failures(&policy);
+    assert_eq!(failures.len(), 2);
+    assert!(failures[1].starts_with("[official-solution] The official solution failed"));
+}
+
+#[test]
+fn test_ui_state_duplicated_solution() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    let original = PathBuf::from("sol/a.cpp");
+    let duplicate = PathBuf::from("sol/b.cpp");
+    let info = |path: &PathBuf, duplicate_of: Option<PathBuf>| SolutionInfo {
+        path: path.clone(),
+        name: path.file_name().unwrap().to_string_lossy().to_string(),
+        language_name: "C++".into(),
+        checks: vec![],
+        complexity: None,
+        duplicate_of,
+        metrics: Default::default(),
+    };
+    ui.apply(UIMessage::Solutions {
+        solutions: vec![
+            info(&original, None),
+            info(&duplicate, Some(original.clone())),
+        ],
+    });
+    ui.apply(UIMessage::Compilation {
+        file: original.clone(),
+        status: UIExecutionStatus::Skipped,
+    });
+    ui.apply(UIMessage::IOITestcaseScore {
+        subtask: 0,
+        testcase: 0,
+        solution: original.clone(),
+        score: 1.0,
+        message: "Output is correct".to_string(),
+        verdict: None,
+    });
+    assert_eq!(ui.compilations[&duplicate], CompilationStatus::Skipped);
+    assert_eq!(ui.evaluations[&duplicate].testcases[&0].score, Some(1.0));
+    assert_eq!(
+        ui.evaluations[&duplicate].testcases[&0].status,
+        ui.evaluations[&original].testcases[&0].status
+    );
+}
@@ -1,8 +1,10 @@
 #[macro_use]
 extern crate approx;
 
+use std::collections::HashSet;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use task_maker_dag::{ExecutionDAG, ExecutionUuid};
 use task_maker_format::ioi::*;
 use task_maker_format::ui::{UIMessage, UIMessageSender};
 
@@ -27,12 +29,14 @@ fn test_score_manager() {
                 solution,
                 score,
                 message,
+                verdict,
             } => {
                 assert_eq!(subtask, 0);
                 assert_eq!(testcase, 0);
                 assert_eq!(solution, PathBuf::from("sol"));
                 assert_abs_diff_eq!(score, 1.0);
                 assert_eq!(message, "foo");
+                assert_eq!(verdict, None);
             }
             _ => panic!("Expecting UIMessage::IOITestcaseScore but was {:?}", mex),
         }
@@ -70,12 +74,14 @@ fn test_score_manager() {
                 solution,
                 score,
                 message,
+                verdict,
             } => {
                 assert_eq!(subtask, 1);
                 assert_eq!(testcase, 1);
                 assert_eq!(solution, PathBuf::from("sol"));
                 assert_abs_diff_eq!(score, 1.0);
                 assert_eq!(message, "foo");
+                assert_eq!(verdict, None);
             }
             _ => panic!("Expecting UIMessage::IOITestcaseScore but was {:?}", mex),
         }
@@ -95,12 +101,14 @@ fn test_score_manager() {
                 solution,
                 score,
                 message,
+                verdict,
             } => {
                 assert_eq!(subtask, 1);
                 assert_eq!(testcase, 2);
                 assert_eq!(solution, PathBuf::from("sol"));
                 assert_abs_diff_eq!(score, 0.0);
                 assert_eq!(message, "foo");
+                assert_eq!(verdict, None);
             }
             _ => panic!("Expecting UIMessage::IOITestcaseScore but was {:?}", mex),
         }
@@ -139,6 +147,42 @@ fn test_score_manager() {
     assert!(receiver.try_recv().is_err());
 }
 
+#[test]
+fn test_score_manager_checker_messages() {
+    let mut task = utils::new_task();
+    task.checker_messages = CheckerMessages::new(vec![(
+        "Output is correct".to_string(),
+        "Risposta corretta".to_string(),
+    )]);
+    let (sender, receiver) = UIMessageSender::new();
+    let sender = Arc::new(Mutex::new(sender));
+    let mut manager = ScoreManager::new(&task, "sol".into(), sender.clone()).unwrap();
+
+    manager
+        .score(1, 1, 1.0, "Output is correct".into(), sender.clone())
+        .unwrap();
+    match receiver.try_recv() {
+        Ok(UIMessage::IOITestcaseScore {
+            message, verdict, ..
+        }) => {
+            assert_eq!(message, "Output is correct");
+            assert_eq!(verdict.as_deref(), Some("Risposta corretta"));
+        }
+        mex => panic!("Expecting UIMessage::IOITestcaseScore but was {:?}", mex),
+    }
+
+    manager.score(1, 2, 0.0, "foo".into(), sender).unwrap();
+    match receiver.try_recv() {
+        Ok(UIMessage::IOITestcaseScore {
+            message, verdict, ..
+        }) => {
+            assert_eq!(message, "foo");
+            assert_eq!(verdict, None);
+        }
+        mex => panic!("Expecting UIMessage::IOITestcaseScore but was {:?}", mex),
+    }
+}
+
 #[test]
 fn test_score_manager_empty_subtask() {
     let mut task = utils::new_task();
@@ -179,12 +223,14 @@ fn test_score_manager_empty_subtask() {
                 solution,
                 score,
                 message,
+                verdict,
             } => {
                 assert_eq!(subtask, 0);
                 assert_eq!(testcase, 0);
                 assert_eq!(solution, PathBuf::from("sol"));
                 assert_abs_diff_eq!(score, 1.0);
                 assert_eq!(message, "foo");
+                assert_eq!(verdict, None);
             }
             _ => panic!("Expecting UIMessage::IOITestcaseScore but was {:?}", mex),
         }
@@ -222,3 +268,137 @@ fn test_score_manager_empty_subtask() {
     }
     assert!(receiver.try_recv().is_err());
 }
+
+#[test]
+fn test_score_manager_skip_dependent_subtasks() {
+    let mut task = utils::new_task();
+    // the second subtask depends on the first one
+    let subtask = task.subtasks.get_mut(&1).unwrap();
+    subtask.testcases = vec![0, 1, 2];
+    subtask.dependencies = vec![0];
+
+    let (sender, receiver) = UIMessageSender::new();
+    let sender = Arc::new(Mutex::new(sender));
+    let dag = ExecutionDAG::new();
+    let mut manager = ScoreManager::new(&task, "sol".into(), sender.clone()).unwrap();
+    manager.skip_dependent_subtasks(&task, dag.skipper());
+    let evaluations: Vec<_> = (0..3).map(|_| ExecutionUuid::new_v4()).collect();
+    for (testcase, uuid) in evaluations.iter().enumerate() {
+        manager.add_evaluation(testcase as TestcaseId, *uuid);
+    }
+
+    manager
+        .score(1, 1, 1.0, "foo".into(), sender.clone())
+        .unwrap();
+    assert!(dag.skipper().take().is_empty());
+    manager
+        .score(0, 0, 0.0, "foo".into(), sender.clone())
+        .unwrap();
+    // testcase 1 is already scored, only testcase 2 can be skipped
+    assert_eq!(dag.skipper().take(), vec![evaluations[2]]);
+
+    // the testcases that were not asked to be skipped are not scored again
+    manager.evaluation_skipped(1, 1, sender.clone()).unwrap();
+    manager.evaluation_skipped(1, 2, sender).unwrap();
+    let messages: Vec<_> = receiver.try_iter().collect();
+    let testcase_scores = |id| {
+        messages
+            .iter()
+            .filter(|mex| {
+                matches!(mex, UIMessage::IOITestcaseScore { testcase, .. } if *testcase == id)
+            })
+            .count()
+    };
+    assert_eq!(testcase_scores(1), 1);
+    assert_eq!(testcase_scores(2), 1);
+    match messages.last() {
+        Some(UIMessage::IOITaskScore { score, .. }) => assert_abs_diff_eq!(*score, 0.0),
+        mex => panic!("Expecting UIMessage::IOITaskScore but was {:?}", mex),
+    }
+}
+
+#[test]
+fn test_score_manager_quick() {
+    let mut task = utils::new_task();
+    task.testcases.insert(
+        3,
+        TestcaseInfo::new(
+            3,
+            InputGenerator::StaticFile("x".into()),
+            OutputGenerator::StaticFile("x".into()),
+        ),
+    );
+    let subtask = task.subtasks.get_mut(&1).unwrap();
+    subtask.testcases = vec![1, 2, 3];
+    subtask.testcases_owned = vec![1, 2, 3];
+    let testcases = task.representative_testcases();
+    assert_eq!(testcases, HashSet::from([0, 1, 3]));
+
+    let (sender, receiver) = UIMessageSender::new();
+    let sender = Arc::new(Mutex::new(sender));
+    let mut manager = ScoreManager::new(&task, "sol".into(), sender.clone()).unwrap();
+    manager.score_only(&testcases);
+    manager
+        .score(0, 0, 1.0, "foo".into(), sender.clone())
+        .unwrap();
+    manager
+        .score(1, 1, 1.0, "foo".into(), sender.clone())
+        .unwrap();
+    manager.score(1, 3, 0.5, "foo".into(), sender).unwrap();
+    // testcase 2 is not evaluated, the second subtask is scored on the other ones
+    match receiver.try_iter().last() {
+        Some(UIMessage::IOITaskScore { score, .. }) => assert_abs_diff_eq!(score, 55.0),
+        mex => panic!("Expecting UIMessage::IOITaskScore but was {:?}", mex),
+    }
+}
+
+#[test]
+fn test_representative_testcases_largest_input() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let mut task = utils::new_task();
+    for (testcase, size) in [(0, 10), (1, 1000), (2, 100)] {
+        let path = tmpdir.path().join(format!("input{}.txt", testcase));
+        std::fs::write(&path, "x".repeat(size)).unwrap();
+        task.testcases.get_mut(&testcase).unwrap().input_generator =
+            InputGenerator::StaticFile(path);
+    }
+    // all the inputs are static: the largest one is evaluated instead of the last one
+    assert_eq!(task.representative_testcases(), HashSet::from([0, 1]));
+}
+
+#[test]
+fn test_score_manager_precision() {
+    let mut task = utils::new_task();
+    task.score_precision = Some(1);
+    let (sender, receiver) = UIMessageSender::new();
+    let sender = Arc::new(Mutex::new(sender));
+    let mut manager = ScoreManager::new(&task, "sol".into(), sender.clone()).unwrap();
+
+    manager
+        .score(0, 0, 1.0 / 3.0, "foo".into(), sender.clone())
+        .unwrap();
+    manager
+        .score(1, 1, 1.0, "foo".into(), sender.clone())
+        .unwrap();
+    manager.score(1, 2, 1.0, "foo".into(), sender).unwrap();
+    let messages: Vec<_> = receiver.try_iter().collect();
+    // the scores of the subtasks are not rounded, only the one of the task is
+    let subtask_score = messages
+        .iter()
+        .find_map(|mex| match mex {
+            UIMessage::IOISubtaskScore {
+                subtask: 0, score, ..
+            } => Some(*score),
+            _ => None,
+        })
+        .expect("Expecting UIMessage::IOISubtaskScore");
+    assert_abs_diff_eq!(subtask_score, 10.0 / 3.0);
+    let task_score = messages
+        .iter()
+        .find_map(|mex| match mex {
+            UIMessage::IOITaskScore { score, .. } => Some(*score),
+            _ => None,
+        })
+        .expect("Expecting UIMessage::IOITaskScore");
+    assert_abs_diff_eq!(task_score, 93.3);
+}
@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use task_maker_dag::{ExecutionResourcesUsage, ExecutionResult, ExecutionStatus};
+use task_maker_dag::{ExecutionResult, ExecutionStatus};
 use task_maker_format::ioi::sanity_checks::get_sanity_checks;
 use task_maker_format::ioi::*;
 use task_maker_lang::GraderMap;
@@ -34,11 +34,19 @@ pub fn new_task_with_context(path: &Path) -> IOITask {
         testcases: HashMap::new(),
         input_validator_generator: Default::default(),
         testcase_score_aggregator: TestcaseScoreAggregator::Min,
-        score_precision: 0,
+        score_precision: None,
+        score_rounding: ScoreRounding::Nearest,
         grader_map: Arc::new(GraderMap::new(Vec::<PathBuf>::new())),
         booklets: vec![],
         difficulty: None,
         syllabus_level: None,
+        attachment_packages: false,
+        container_image: None,
+        forbidden_constructs: vec![],
+        required_resources: Default::default(),
+        quick_testcases: None,
+        official_solution_override: None,
+        checker_messages: Default::default(),
         sanity_checks: Arc::new(get_sanity_checks(&[])),
     };
     task.testcases.entry(0).or_insert(TestcaseInfo::new(
@@ -78,31 +86,13 @@ pub fn new_task_with_context(path: &Path) -> IOITask {
 pub fn good_result() -> ExecutionResult {
     ExecutionResult {
         status: ExecutionStatus::Success,
-        was_killed: false,
-        was_cached: false,
-        resources: ExecutionResourcesUsage {
-            cpu_time: 0.0,
-            sys_time: 0.0,
-            wall_time: 0.0,
-            memory: 0,
-        },
-        stdout: None,
-        stderr: None,
+        ..Default::default()
     }
 }
 
 pub fn bad_result() -> ExecutionResult {
     ExecutionResult {
         status: ExecutionStatus::ReturnCode(123),
-        was_killed: false,
-        was_cached: false,
-        resources: ExecutionResourcesUsage {
-            cpu_time: 0.0,
-            sys_time: 0.0,
-            wall_time: 0.0,
-            memory: 0,
-        },
-        stdout: None,
-        stderr: None,
+        ..Default::default()
     }
 }
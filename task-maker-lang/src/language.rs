@@ -2,7 +2,7 @@ use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Error};
 
-use task_maker_dag::{Execution, ExecutionCommand, ExecutionDAG, ExecutionLimits, File};
+use task_maker_dag::{Execution, ExecutionCommand, ExecutionDAG, ExecutionLimits, File, Toolchain};
 
 use crate::{Dependency, GraderMap};
 
@@ -22,6 +22,12 @@ pub trait Language: std::fmt::Debug + Send + Sync {
     /// extension is inside this list.
     fn extensions(&self) -> Vec<&'static str>;
 
+    /// List of extensions of the header files of this language. These files are not programs on
+    /// their own, but they are used only by the programs in this language.
+    fn header_extensions(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
     /// Whether this language needs a compilation step. Returning `true` here triggers many changes
     /// in the behaviour of the execution. Of course the compilation step will be added, because of
     /// that there is the need to know how to compile the source file, forcing the implementation of
@@ -35,6 +41,18 @@ pub trait Language: std::fmt::Debug + Send + Sync {
     /// If the language does not support inline comments, return `None`.
     fn inline_comment_prefix(&self) -> Option<&'static str>;
 
+    /// The system toolchains (compilers and interpreters) used for compiling and running the
+    /// programs in this language. The workers probe their versions when they start.
+    fn toolchains(&self) -> Vec<Toolchain> {
+        vec![]
+    }
+
+    /// Whether the programs in this language can be compiled with the sanitizers, see
+    /// `CompilationSettings::sanitize`.
+    fn supports_sanitizers(&self) -> bool {
+        false
+    }
+
     /// Return the `CompiledLanguageBuilder` for compiling a source file with this language.
     ///
     /// This method must return `Some` if and only if `need_compilation` returns `true`.
@@ -101,6 +119,9 @@ pub struct CompilationSettings {
     pub copy_exe: bool,
     /// Whether to try to link statically the binary.
     pub list_static: bool,
+    /// Whether to instrument the binary with the address and undefined behavior sanitizers. Used
+    /// only by the languages that support them.
+    pub sanitize: bool,
 }
 
 /// This trait describes the API of a "compiled language builder", a component that builds the DAG
@@ -232,6 +253,7 @@ impl<'l, 'c> CompiledLanguageBuilder for SimpleCompiledLanguageBuilder<'l, 'c> {
         let name = self.source_path.file_name().unwrap().to_string_lossy();
         let mut comp = Execution::new(format!("Compilation of {}", name), self.compiler.clone());
         comp.args.clone_from(&self.args);
+        comp.toolchain = Toolchain::of_command(&self.compiler);
 
         // compilation dependencies
         for dep in self.dependencies.drain(..) {
@@ -0,0 +1,330 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Error};
+use serde::Deserialize;
+
+use task_maker_dag::{Execution, ExecutionCommand, ExecutionDAG, File, Toolchain};
+
+use crate::language::{CompilationSettings, CompiledLanguageBuilder, Language};
+use crate::GraderMap;
+
+/// The name of the manifest describing how to build a directory.
+const BUILD_YAML: &str = "build.yaml";
+/// The name of the Makefile used for building a directory without a `build.yaml`.
+const MAKEFILE: &str = "Makefile";
+/// The default name of the executable produced by the build.
+const DEFAULT_EXECUTABLE: &str = "solution";
+/// The directory of the sandbox of the build where the graders of the task are put.
+const GRADERS_DIR: &str = "graders";
+
+/// The content of a `build.yaml`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BuildYAML {
+    /// The shell command that builds the executable.
+    build: String,
+    /// The path of the executable produced by the build, relative to the directory.
+    #[serde(default = "default_executable")]
+    executable: String,
+}
+
+fn default_executable() -> String {
+    DEFAULT_EXECUTABLE.into()
+}
+
+/// If `path` is a directory that can be built, return the path to the manifest that describes how
+/// to build it: its `build.yaml` or, if missing, its `Makefile`.
+pub fn find_build_manifest(path: &Path) -> Option<PathBuf> {
+    if !path.is_dir() {
+        return None;
+    }
+    [BUILD_YAML, MAKEFILE]
+        .iter()
+        .map(|name| path.join(name))
+        .find(|manifest| manifest.is_file())
+}
+
+/// A directory with many source files, compiled by the command of its `build.yaml` or by `make`.
+///
+/// All the files of the directory are put in the sandbox of the compilation, so changing any of
+/// them invalidates the cache. The graders of the task are put in the `graders/` directory of the
+/// sandbox, and the build command chooses which one to link. Only the executable is available at
+/// runtime. The symbolic links inside the directory are not supported.
+#[derive(Debug)]
+pub struct LanguageBuildDir;
+
+impl LanguageBuildDir {
+    /// Make a new LanguageBuildDir.
+    pub fn new() -> LanguageBuildDir {
+        LanguageBuildDir {}
+    }
+}
+
+impl Language for LanguageBuildDir {
+    fn name(&self) -> &'static str {
+        "Build directory"
+    }
+
+    fn extensions(&self) -> Vec<&'static str> {
+        // the directories are detected by their manifest, not by their extension
+        vec![]
+    }
+
+    fn need_compilation(&self) -> bool {
+        true
+    }
+
+    fn inline_comment_prefix(&self) -> Option<&'static str> {
+        // both build.yaml and Makefile use # for the comments
+        Some("#")
+    }
+
+    fn toolchains(&self) -> Vec<Toolchain> {
+        Toolchain::of_command(&ExecutionCommand::system("make"))
+            .into_iter()
+            .collect()
+    }
+
+    fn compilation_builder(
+        &self,
+        source: &Path,
+        settings: CompilationSettings,
+    ) -> Option<Box<dyn CompiledLanguageBuilder + '_>> {
+        Some(Box::new(BuildDirBuilder {
+            path: source.to_owned(),
+            settings,
+            graders: vec![],
+        }))
+    }
+}
+
+/// The `CompiledLanguageBuilder` of a build directory.
+struct BuildDirBuilder {
+    /// The path to the directory to build.
+    path: PathBuf,
+    /// The settings for this compilation.
+    settings: CompilationSettings,
+    /// The graders of the task, put in the sandbox of the compilation.
+    graders: Vec<PathBuf>,
+}
+
+impl CompiledLanguageBuilder for BuildDirBuilder {
+    fn use_grader(&mut self, grader_map: &GraderMap) {
+        // the language of the directory is not known, all the graders are available to the build
+        self.graders = grader_map.all_paths().map(Path::to_owned).collect();
+        self.graders.sort();
+    }
+
+    fn finalize(&mut self, dag: &mut ExecutionDAG) -> Result<(Execution, File), Error> {
+        let manifest = find_build_manifest(&self.path)
+            .with_context(|| format!("{} has no build manifest", self.path.display()))?;
+        let (command, args, executable) = if manifest.ends_with(BUILD_YAML) {
+            let file = std::fs::File::open(&manifest)
+                .with_context(|| format!("Failed to open {}", manifest.display()))?;
+            let build: BuildYAML = serde_yaml::from_reader(file)
+                .with_context(|| format!("Invalid build manifest {}", manifest.display()))?;
+            let args = vec!["-c".to_string(), build.build];
+            (ExecutionCommand::system("sh"), args, build.executable)
+        } else {
+            (
+                ExecutionCommand::system("make"),
+                vec![],
+                default_executable(),
+            )
+        };
+
+        let name = self.path.file_name().unwrap().to_string_lossy();
+        let mut comp = Execution::new(format!("Compilation of {}", name), command);
+        comp.args = args;
+        comp.toolchain = Toolchain::of_command(&comp.command);
+
+        for (local_path, sandbox_path, executable) in list_dir(&self.path)? {
+            let file = File::new(format!("Source file {:?} of {:?}", sandbox_path, self.path));
+            comp.input(&file, &sandbox_path, executable);
+            dag.provide_file(file, &local_path)
+                .context("Failed to provide source file")?;
+        }
+        for grader in &self.graders {
+            let sandbox_path = Path::new(GRADERS_DIR).join(grader.file_name().unwrap());
+            if comp.inputs.contains_key(&sandbox_path) {
+                bail!(
+                    "{} contains {}, which is reserved for the graders",
+                    self.path.display(),
+                    sandbox_path.display()
+                );
+            }
+            let file = File::new(format!("Grader {:?} of {:?}", sandbox_path, self.path));
+            comp.input(&file, &sandbox_path, false);
+            dag.provide_file(file, grader)
+                .context("Failed to provide grader")?;
+        }
+
+        let exec = comp.output(&executable);
+        if self.settings.copy_exe {
+            if let Some(write_to) = &self.settings.write_to {
+                dag.write_file_to(&exec, write_to, true);
+            }
+        }
+        Ok((comp, exec))
+    }
+}
+
+/// Whether the file is executable. Only unix has the executable permission.
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+/// Whether the file is executable. Only unix has the executable permission.
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// List recursively all the files of a directory, sorted. Each item contains the local path, the
+/// path relative to the directory and whether the file is executable. The symbolic links are
+/// rejected, since they may point outside of the directory.
+fn list_dir(dir: &Path) -> Result<Vec<(PathBuf, PathBuf, bool)>, Error> {
+    let mut result = vec![];
+    let mut pending = vec![dir.to_owned()];
+    while let Some(current) = pending.pop() {
+        let entries = std::fs::read_dir(&current)
+            .with_context(|| format!("Failed to list {}", current.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            let metadata = std::fs::symlink_metadata(&path)
+                .with_context(|| format!("Failed to get metadata of {}", path.display()))?;
+            if metadata.file_type().is_symlink() {
+                bail!(
+                    "{} is a symbolic link, which is not supported in the directory of a solution",
+                    path.display()
+                );
+            }
+            if metadata.is_dir() {
+                pending.push(path);
+            } else {
+                let relative = path.strip_prefix(dir).unwrap().to_owned();
+                let executable = is_executable(&metadata);
+                result.push((path, relative, executable));
+            }
+        }
+    }
+    result.sort();
+    if result.is_empty() {
+        bail!("{} is empty", dir.display());
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(dir: &Path) -> Result<(Execution, File), Error> {
+        compile_with_graders(dir, &GraderMap::new(Vec::<PathBuf>::new()))
+    }
+
+    fn compile_with_graders(dir: &Path, graders: &GraderMap) -> Result<(Execution, File), Error> {
+        let lang = LanguageBuildDir::new();
+        let settings = CompilationSettings::default();
+        let mut builder = lang.compilation_builder(dir, settings).unwrap();
+        builder.use_grader(graders);
+        builder.finalize(&mut ExecutionDAG::new())
+    }
+
+    #[test]
+    fn test_find_build_manifest() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let dir = tmpdir.path();
+        assert_eq!(find_build_manifest(dir), None);
+        std::fs::write(dir.join(MAKEFILE), "").unwrap();
+        assert_eq!(find_build_manifest(dir), Some(dir.join(MAKEFILE)));
+        std::fs::write(dir.join(BUILD_YAML), "").unwrap();
+        assert_eq!(find_build_manifest(dir), Some(dir.join(BUILD_YAML)));
+        assert_eq!(find_build_manifest(&dir.join(BUILD_YAML)), None);
+    }
+
+    #[test]
+    fn test_build_yaml() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let dir = tmpdir.path();
+        std::fs::write(
+            dir.join(BUILD_YAML),
+            "build: g++ *.cpp -o sol\nexecutable: sol\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("main.cpp"), "").unwrap();
+        std::fs::create_dir(dir.join("lib")).unwrap();
+        std::fs::write(dir.join("lib/lib.h"), "").unwrap();
+
+        let (comp, exe) = compile(dir).unwrap();
+        assert_eq!(comp.command, ExecutionCommand::system("sh"));
+        assert_eq!(comp.args, vec!["-c", "g++ *.cpp -o sol"]);
+        assert_eq!(comp.inputs.len(), 3);
+        for input in [BUILD_YAML, "main.cpp", "lib/lib.h"] {
+            assert!(
+                comp.inputs.contains_key(Path::new(input)),
+                "{} missing",
+                input
+            );
+        }
+        assert_eq!(comp.outputs[Path::new("sol")], exe);
+    }
+
+    #[test]
+    fn test_makefile() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let dir = tmpdir.path();
+        std::fs::write(dir.join(MAKEFILE), "solution: main.c\n").unwrap();
+        std::fs::write(dir.join("main.c"), "").unwrap();
+
+        let (comp, exe) = compile(dir).unwrap();
+        assert_eq!(comp.command, ExecutionCommand::system("make"));
+        assert!(comp.args.is_empty());
+        assert_eq!(comp.inputs.len(), 2);
+        assert_eq!(comp.outputs[Path::new(DEFAULT_EXECUTABLE)], exe);
+    }
+
+    #[test]
+    fn test_graders() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let dir = tmpdir.path().join("sol");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join(MAKEFILE), "").unwrap();
+        std::fs::write(tmpdir.path().join("grader.cpp"), "").unwrap();
+        std::fs::write(tmpdir.path().join("grader.py"), "").unwrap();
+        let graders = GraderMap::new(vec![
+            tmpdir.path().join("grader.cpp"),
+            tmpdir.path().join("grader.py"),
+        ]);
+
+        let (comp, _) = compile_with_graders(&dir, &graders).unwrap();
+        assert_eq!(comp.inputs.len(), 3);
+        assert!(comp.inputs.contains_key(Path::new("graders/grader.cpp")));
+        assert!(comp.inputs.contains_key(Path::new("graders/grader.py")));
+
+        std::fs::create_dir(dir.join(GRADERS_DIR)).unwrap();
+        std::fs::write(dir.join("graders/grader.cpp"), "").unwrap();
+        assert!(compile_with_graders(&dir, &graders).is_err());
+    }
+
+    #[test]
+    fn test_symlink() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let dir = tmpdir.path().join("sol");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join(MAKEFILE), "").unwrap();
+        std::fs::write(tmpdir.path().join("secret"), "").unwrap();
+        std::os::unix::fs::symlink(tmpdir.path().join("secret"), dir.join("link")).unwrap();
+        assert!(compile(&dir).is_err());
+    }
+
+    #[test]
+    fn test_invalid_build_yaml() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let dir = tmpdir.path();
+        std::fs::write(dir.join(BUILD_YAML), "executable: sol\n").unwrap();
+        assert!(compile(dir).is_err());
+    }
+}
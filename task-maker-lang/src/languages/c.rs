@@ -1,6 +1,6 @@
 use std::path::Path;
 
-use task_maker_dag::ExecutionCommand;
+use task_maker_dag::{ExecutionCommand, Toolchain};
 
 use crate::language::{
     CompilationSettings, CompiledLanguageBuilder, SimpleCompiledLanguageBuilder,
@@ -60,6 +60,10 @@ impl Language for LanguageC {
         vec!["c"]
     }
 
+    fn header_extensions(&self) -> Vec<&'static str> {
+        vec!["h"]
+    }
+
     fn need_compilation(&self) -> bool {
         true
     }
@@ -68,6 +72,16 @@ impl Language for LanguageC {
         Some("//")
     }
 
+    fn toolchains(&self) -> Vec<Toolchain> {
+        Toolchain::of_command(&self.config.compiler)
+            .into_iter()
+            .collect()
+    }
+
+    fn supports_sanitizers(&self) -> bool {
+        true
+    }
+
     fn compilation_builder(
         &self,
         source: &Path,
@@ -93,7 +107,12 @@ impl Language for LanguageC {
         for arg in &self.config.extra_flags {
             metadata.add_arg(arg);
         }
-        if metadata.settings.list_static {
+        if metadata.settings.sanitize {
+            // the sanitizers cannot be linked statically
+            metadata
+                .add_arg("-fsanitize=address,undefined")
+                .add_arg("-fno-omit-frame-pointer");
+        } else if metadata.settings.list_static {
             metadata.add_arg("-static");
         }
 
@@ -62,6 +62,10 @@ impl Language for LanguageCpp {
         vec!["cpp", "cc", "c++"]
     }
 
+    fn header_extensions(&self) -> Vec<&'static str> {
+        vec!["h", "hpp", "hh", "hxx"]
+    }
+
     fn need_compilation(&self) -> bool {
         true
     }
@@ -70,6 +74,16 @@ impl Language for LanguageCpp {
         Some("//")
     }
 
+    fn toolchains(&self) -> Vec<Toolchain> {
+        Toolchain::of_command(&self.config.compiler)
+            .into_iter()
+            .collect()
+    }
+
+    fn supports_sanitizers(&self) -> bool {
+        true
+    }
+
     fn compilation_builder(
         &self,
         source: &Path,
@@ -94,7 +108,12 @@ impl Language for LanguageCpp {
         for arg in &self.config.extra_flags {
             metadata.add_arg(arg);
         }
-        if metadata.settings.list_static {
+        if metadata.settings.sanitize {
+            // the sanitizers cannot be linked statically
+            metadata
+                .add_arg("-fsanitize=address,undefined")
+                .add_arg("-fno-omit-frame-pointer");
+        } else if metadata.settings.list_static {
             metadata.add_arg("-static");
         }
 
@@ -223,6 +242,30 @@ mod tests {
         assert_that(&args).contains("-static".to_string());
     }
 
+    #[test]
+    fn test_compilation_args_sanitize() {
+        let tmp = setup();
+
+        let lang = LanguageCpp::new(LanguageCppConfiguration {
+            compiler: ExecutionCommand::System("g++".into()),
+            std_version: "c++14".to_string(),
+            extra_flags: vec![],
+        });
+        let settings = CompilationSettings {
+            list_static: true,
+            sanitize: true,
+            ..Default::default()
+        };
+        let mut builder = lang
+            .compilation_builder(&tmp.path().join("foo.cpp"), settings)
+            .unwrap();
+        let (comp, _exec) = builder.finalize(&mut ExecutionDAG::new()).unwrap();
+
+        let args = comp.args;
+        assert_that(&args).contains("-fsanitize=address,undefined".to_string());
+        assert_that(&args).does_not_contain("-static".to_string());
+    }
+
     #[test]
     fn test_extract_imports() {
         let tmpdir = setup();
@@ -34,6 +34,16 @@ impl Language for LanguageCSharp {
         Some("//")
     }
 
+    fn toolchains(&self) -> Vec<Toolchain> {
+        [
+            ExecutionCommand::system("mcs"),
+            ExecutionCommand::system("mono"),
+        ]
+        .iter()
+        .filter_map(Toolchain::of_command)
+        .collect()
+    }
+
     fn compilation_builder(
         &self,
         source: &Path,
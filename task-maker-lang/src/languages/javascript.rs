@@ -32,6 +32,12 @@ impl Language for LanguageJS {
         Some("//")
     }
 
+    fn toolchains(&self) -> Vec<Toolchain> {
+        Toolchain::of_command(&ExecutionCommand::system("node"))
+            .into_iter()
+            .collect()
+    }
+
     fn runtime_command(&self, _path: &Path, _write_to: Option<&Path>) -> ExecutionCommand {
         ExecutionCommand::system("node")
     }
@@ -5,6 +5,7 @@ use task_maker_dag::File;
 
 use crate::Dependency;
 
+pub(crate) mod build_dir;
 pub(crate) mod c;
 pub(crate) mod cpp;
 pub(crate) mod csharp;
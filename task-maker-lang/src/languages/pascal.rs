@@ -36,6 +36,12 @@ impl Language for LanguagePascal {
         Some("//")
     }
 
+    fn toolchains(&self) -> Vec<Toolchain> {
+        Toolchain::of_command(&ExecutionCommand::system("fpc"))
+            .into_iter()
+            .collect()
+    }
+
     fn compilation_builder(
         &self,
         source: &Path,
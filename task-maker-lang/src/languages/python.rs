@@ -2,7 +2,7 @@ use std::path::{Path, PathBuf};
 
 use regex::Regex;
 
-use task_maker_dag::ExecutionCommand;
+use task_maker_dag::{ExecutionCommand, Toolchain};
 
 use crate::language::Language;
 use crate::languages::find_dependencies;
@@ -54,6 +54,18 @@ impl Language for LanguagePython {
         Some("#")
     }
 
+    fn toolchains(&self) -> Vec<Toolchain> {
+        // with autodetect the script is run with the interpreter of its shebang
+        let interpreter = match self.version {
+            LanguagePythonVersion::Autodetect => return vec![],
+            LanguagePythonVersion::Python2 => "python2",
+            LanguagePythonVersion::Python3 => "python3",
+        };
+        Toolchain::of_command(&ExecutionCommand::system(interpreter))
+            .into_iter()
+            .collect()
+    }
+
     fn runtime_command(&self, path: &Path, write_to: Option<&Path>) -> ExecutionCommand {
         match self.version {
             LanguagePythonVersion::Autodetect => {
@@ -1,5 +1,5 @@
 use std::path::{Path, PathBuf};
-use task_maker_dag::ExecutionCommand;
+use task_maker_dag::{ExecutionCommand, Toolchain};
 
 use crate::language::{
     CompilationSettings, CompiledLanguageBuilder, Language, SimpleCompiledLanguageBuilder,
@@ -62,6 +62,12 @@ impl Language for LanguageRust {
         Some("//")
     }
 
+    fn toolchains(&self) -> Vec<Toolchain> {
+        Toolchain::of_command(&ExecutionCommand::system("rustc"))
+            .into_iter()
+            .collect()
+    }
+
     fn compilation_builder(
         &self,
         source: &Path,
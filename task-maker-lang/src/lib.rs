@@ -36,8 +36,9 @@ use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
 pub use grader_map::GraderMap;
+pub use languages::build_dir::find_build_manifest;
 pub use source_file::SourceFile;
-use task_maker_dag::File;
+use task_maker_dag::{File, Toolchain};
 
 use crate::language::Language;
 
@@ -86,11 +87,13 @@ impl LanguageManager {
                 Arc::new(rust::LanguageRust::new()),
                 Arc::new(csharp::LanguageCSharp::new()),
                 Arc::new(javascript::LanguageJS::new()),
+                Arc::new(build_dir::LanguageBuildDir::new()),
             ],
         }
     }
 
-    /// Given a path to a file guess the language that the source file probably is.
+    /// Given a path to a file guess the language that the source file probably is. A directory with
+    /// a `build.yaml` or a `Makefile` is a single source file, built by its manifest.
     ///
     /// ```
     /// use task_maker_lang::LanguageManager;
@@ -104,6 +107,9 @@ impl LanguageManager {
     /// ```
     pub fn detect_language<P: AsRef<Path>>(path: P) -> Option<Arc<dyn Language>> {
         let manager = &LANGUAGE_MANAGER_SINGL;
+        if find_build_manifest(path.as_ref()).is_some() {
+            return LanguageManager::from_name(languages::build_dir::LanguageBuildDir.name());
+        }
         let ext = path
             .as_ref()
             .extension()
@@ -120,6 +126,43 @@ impl LanguageManager {
         None
     }
 
+    /// Given a path to a file find the languages that use it as a header file.
+    ///
+    /// ```
+    /// use task_maker_lang::LanguageManager;
+    ///
+    /// let langs = LanguageManager::detect_header_languages("task.h");
+    /// assert!(langs.iter().any(|l| l.name().contains("C++")));
+    /// assert!(LanguageManager::detect_header_languages("task.py").is_empty());
+    /// ```
+    pub fn detect_header_languages<P: AsRef<Path>>(path: P) -> Vec<Arc<dyn Language>> {
+        let manager = &LANGUAGE_MANAGER_SINGL;
+        let ext = path
+            .as_ref()
+            .extension()
+            .map(|s| s.to_string_lossy())
+            .unwrap_or_else(|| "".into())
+            .to_lowercase();
+        manager
+            .known_languages
+            .iter()
+            .filter(|lang| lang.header_extensions().contains(&ext.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// The toolchains used by all the known languages, without duplicates.
+    pub fn toolchains() -> Vec<Toolchain> {
+        let manager = &LANGUAGE_MANAGER_SINGL;
+        let mut toolchains: Vec<Toolchain> = vec![];
+        for toolchain in manager.known_languages.iter().flat_map(|l| l.toolchains()) {
+            if !toolchains.contains(&toolchain) {
+                toolchains.push(toolchain);
+            }
+        }
+        toolchains
+    }
+
     /// Search between the known languages the one with the specified name and return it if found.
     pub(crate) fn from_name<S: AsRef<str>>(name: S) -> Option<Arc<dyn Language>> {
         let manager = &LANGUAGE_MANAGER_SINGL;
@@ -6,7 +6,7 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use typescript_definitions::TypeScriptify;
 
 use task_maker_dag::{
-    Execution, ExecutionDAG, ExecutionTag, ExecutionUuid, File, FileUuid, Priority,
+    Execution, ExecutionDAG, ExecutionTag, ExecutionUuid, File, FileUuid, Priority, Toolchain,
 };
 
 use crate::language::{CompilationSettings, Language};
@@ -41,6 +41,8 @@ pub struct SourceFile {
     pub write_bin_to: Option<PathBuf>,
     /// Whether this source file should be statically linked.
     pub link_static: bool,
+    /// Whether this source file should be compiled with the sanitizers.
+    pub sanitize: bool,
 }
 
 impl SourceFile {
@@ -70,6 +72,7 @@ impl SourceFile {
             write_bin_to: write_bin_to.map(|p| p.into()),
             copy_exe: false,
             link_static: false,
+            sanitize: false,
         })
     }
 
@@ -149,6 +152,8 @@ impl SourceFile {
             description.as_ref(),
             self.language.runtime_command(&self.path, write_to),
         );
+        // the interpreter, if the program is run by a system one
+        exec.toolchain = Toolchain::of_command(&exec.command);
         for arg in &args {
             let path = self.base_path.join(arg);
             if path.exists() {
@@ -200,6 +205,12 @@ impl SourceFile {
         }
     }
 
+    /// Compile the source file with the address and undefined behavior sanitizers. It has no effect
+    /// if the language does not support them.
+    pub fn sanitize(&mut self) {
+        self.sanitize = true;
+    }
+
     /// Prepare the source file if needed and return the executable file. If the compilation step
     /// was not executed yet the handle to the compilation execution is also returned.
     pub fn executable(
@@ -261,6 +272,7 @@ impl SourceFile {
         let settings = CompilationSettings {
             write_to: write_to.map(Into::into),
             list_static: self.link_static,
+            sanitize: self.sanitize,
             copy_exe: dag.config_mut().copy_exe || self.copy_exe,
         };
         if let Some(mut metadata) = self.language.compilation_builder(&self.path, settings) {
@@ -269,6 +281,7 @@ impl SourceFile {
             }
             let (mut comp, exec) = metadata.finalize(dag)?;
             comp.tag(ExecutionTag::from("compilation"))
+                .artifact(self.language.name())
                 .priority(COMPILATION_PRIORITY)
                 .capture_stdout(COMPILATION_CONTENT_LENGTH)
                 .capture_stderr(COMPILATION_CONTENT_LENGTH);
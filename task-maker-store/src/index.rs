@@ -3,6 +3,7 @@ use std::collections::hash_map::Entry;
 use std::collections::{BinaryHeap, HashMap};
 use std::fs::{create_dir_all, remove_dir, File};
 use std::io::{BufReader, BufWriter, Read, Write};
+use std::os::unix::fs::MetadataExt;
 use std::path::Path;
 use std::time::SystemTime;
 
@@ -20,6 +21,10 @@ use crate::{FileStore, FileStoreKey, LockedFiles};
 /// version is a prefix of the magic of the new version.
 const MAGIC: &[u8] = formatcp!("task-maker-store v{}\n", env!("CARGO_PKG_VERSION")).as_bytes();
 
+/// The identity of the index file on disk (modification time, inode and size), used for knowing
+/// whether it has been changed by another process.
+type IndexFingerprint = (SystemTime, u64, u64);
+
 /// An entry of a file inside the file store.
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 struct FileStoreIndexItem {
@@ -47,6 +52,9 @@ pub(crate) struct FileStoreIndex {
     total_size: u64,
     /// The list of all the files known in the index.
     known_files: HashMap<FileStoreKey, FileStoreIndexItem>,
+    /// The fingerprint of the index file when it was last loaded or stored.
+    #[serde(skip)]
+    fingerprint: Option<IndexFingerprint>,
 }
 
 impl FileStoreIndex {
@@ -58,6 +66,7 @@ impl FileStoreIndex {
             return Ok(FileStoreIndex {
                 total_size: 0,
                 known_files: HashMap::new(),
+                fingerprint: None,
             });
         }
 
@@ -78,14 +87,49 @@ impl FileStoreIndex {
             return Ok(FileStoreIndex {
                 total_size: 0,
                 known_files: HashMap::new(),
+                fingerprint: None,
             });
         }
 
-        bincode::deserialize_from(reader).context("Failed to deserialize index file")
+        let mut index: FileStoreIndex =
+            bincode::deserialize_from(reader).context("Failed to deserialize index file")?;
+        index.fingerprint = fingerprint(path);
+        Ok(index)
+    }
+
+    /// Load the changes made to the index file by the other processes since it was last loaded or
+    /// stored, merging them into this index.
+    pub(crate) fn sync<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let current = fingerprint(path);
+        if current.is_none() || current == self.fingerprint {
+            return Ok(());
+        }
+        debug!("Index at {:?} changed, reloading it", path);
+        let other = FileStoreIndex::load(path)?;
+        self.merge(other);
+        Ok(())
+    }
+
+    /// Add to this index the files known by the other one, keeping the most recent access time.
+    fn merge(&mut self, other: FileStoreIndex) {
+        for (key, item) in other.known_files {
+            match self.known_files.entry(key) {
+                Entry::Occupied(mut entry) => {
+                    let entry = entry.get_mut();
+                    entry.last_access = entry.last_access.max(item.last_access);
+                }
+                Entry::Vacant(entry) => {
+                    self.total_size += item.size;
+                    entry.insert(item);
+                }
+            }
+        }
+        self.fingerprint = other.fingerprint;
     }
 
     /// Store a dump of this index to the path provided.
-    pub(crate) fn store<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+    pub(crate) fn store<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
         let path = path.as_ref();
         debug!("Saving index file at {}", path.display());
 
@@ -104,6 +148,7 @@ impl FileStoreIndex {
         bincode::serialize_into(writer, &self).context("Failed to write index")?;
         std::fs::rename(&tmp, path)
             .with_context(|| format!("Failed to move {} -> {}", tmp.display(), path.display()))?;
+        self.fingerprint = fingerprint(path);
         Ok(())
     }
 
@@ -140,7 +185,9 @@ impl FileStoreIndex {
     }
 
     /// Perform a flushing operation, cleaning some space on the disk by removing the Least Recently
-    /// Used files. This function won't remove the files currently locked.
+    /// Used files. This function won't remove the files currently locked or pinned.
+    ///
+    /// The subscribers of the file store are notified of the removed files.
     pub(crate) fn flush(
         &mut self,
         file_store: &FileStore,
@@ -166,8 +213,8 @@ impl FileStoreIndex {
                 // locking)
                 None => break,
             };
-            // cannot remove a file used by some other process
-            if locked_files.ref_counts.contains_key(&key) {
+            // cannot remove a file used by some other process, or pinned
+            if locked_files.ref_counts.contains_key(&key) || locked_files.pinned.contains(&key) {
                 surviving.push((key, entry));
             } else {
                 self.total_size -= entry.size;
@@ -178,6 +225,7 @@ impl FileStoreIndex {
                 if let Err(e) = FileStore::remove_file(&path) {
                     warn!("Cannot flush file {:?}: {}", path, e.to_string());
                 }
+                file_store.notify_eviction(&key);
                 let base_path = file_store.base_path.canonicalize().with_context(|| {
                     format!(
                         "Invalid file store base path: {}",
@@ -212,6 +260,12 @@ impl FileStoreIndex {
     }
 }
 
+/// The fingerprint of the index file, if it exists.
+fn fingerprint(path: &Path) -> Option<IndexFingerprint> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.ino(), metadata.len()))
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs::File;
@@ -338,6 +392,56 @@ mod tests {
         assert!(!store.key_to_path(&key3).exists());
     }
 
+    #[test]
+    fn test_flush_pinned() {
+        let cwd = get_cwd();
+        let key1 = {
+            let store = FileStore::new(cwd.path(), 200, 100).unwrap();
+            let key1 = add_file_to_store(&store, 90).key.clone();
+            store.pin(&key1).unwrap();
+            key1
+        };
+        // the pin persists after the store is opened again
+        let store = FileStore::new(cwd.path(), 200, 100).unwrap();
+        assert!(store.is_pinned(&key1));
+        let key2 = add_file_to_store(&store, 95).key.clone();
+        let key3 = add_file_to_store(&store, 50).key.clone();
+
+        let mut index = store.index.lock().unwrap();
+        let locked = store.locked_files.lock().unwrap();
+        index.flush(&store, &locked, 100).unwrap();
+
+        assert_eq!(index.total_size, 90);
+        assert!(store.key_to_path(&key1).exists());
+        assert!(!store.key_to_path(&key2).exists());
+        assert!(!store.key_to_path(&key3).exists());
+    }
+
+    #[test]
+    fn test_flush_notify_eviction() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 200, 100).unwrap();
+        let evictions = store.subscribe_evictions();
+        let handle = add_file_to_store(&store, 90);
+        let key2 = add_file_to_store(&store, 95).key.clone();
+        assert!(evictions.try_recv().is_err());
+
+        let mut index = store.index.lock().unwrap();
+        let locked = store.locked_files.lock().unwrap();
+        index.flush(&store, &locked, 100).unwrap();
+
+        let evicted: Vec<_> = evictions.try_iter().collect();
+        assert_eq!(evicted, vec![key2]);
+        assert!(handle.path.exists());
+        // a dropped receiver is forgotten
+        drop(evictions);
+        drop(handle);
+        drop(locked);
+        drop(index);
+        add_file_to_store(&store, 150);
+        assert!(store.eviction_subscribers.lock().unwrap().is_empty());
+    }
+
     #[test]
     fn test_flush_touch() {
         let cwd = get_cwd();
@@ -4,7 +4,15 @@
 //! is granted via their hash. The size of the store folder is limited to a specific amount and the
 //! least-recently-used files are removed automatically.
 //!
-//! The access to the store directory via this crate is exclusive even between processes.
+//! The store directory can be shared by many processes at the same time: each of them holds a
+//! shared lock on the store, while the updates of the index are serialized by a short exclusive
+//! lock. The least-recently-used files are removed only when there are no other processes using the
+//! store, since the files they are using are not known. A postponed flush is retried periodically,
+//! so that it happens as soon as the other processes leave, even if the store is not used anymore.
+//!
+//! Some files can be pinned, preventing them from being removed even if they are not used, and the
+//! files removed to free space can be received from a channel, for example for invalidating what
+//! refers to them.
 //!
 //! # Example
 //!
@@ -21,7 +29,7 @@
 //! # let store_dir = tmp.path().join("store");
 //! # let path = tmp.path().join("file.txt");
 //! # fs::write(&path, "hello world")?;
-//! // make a new store based on a directory, this will lock if the store is already in use
+//! // make a new store based on a directory, this will wait if the store is being flushed
 //! let store = FileStore::new(store_dir, 1000, 1000)?;
 //! // compute the key of a file and make an iterator over its content
 //! let key = FileStoreKey::from_file(&path)?;
@@ -41,16 +49,21 @@
 extern crate log;
 
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Formatter;
-use std::io::Write;
-use std::os::unix::prelude::PermissionsExt;
+use std::io::{Read, Write};
+use std::os::unix::prelude::{AsRawFd, PermissionsExt};
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
 
-use anyhow::{bail, Context, Error};
+use anyhow::{anyhow, bail, Context, Error};
 use blake3::{hash, Hash, Hasher};
 use fslock::LockFile;
+use nix::errno::Errno;
+use nix::fcntl::{flock, FlockArg};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::index::FileStoreIndex;
@@ -61,31 +74,49 @@ mod read_file_iterator;
 
 /// Whether to check the file integrity on the store before getting it.
 const INTEGRITY_CHECKS_ENABLED: bool = false;
-/// The name of the lock of the file store.
+/// The name of the lock of the file store, shared by all the processes using the store and taken
+/// exclusively for flushing it.
 const STORE_LOCK_FILE: &str = "exclusive.lock";
+/// The name of the lock taken while updating the index of the file store.
+const STORE_INDEX_LOCK_FILE: &str = "index.lock";
 /// The name of the index of the file store.
 const STORE_INDEX_FILE: &str = "index.bin";
+/// The name of the file with the keys of the pinned files of the file store.
+const STORE_PINNED_FILE: &str = "pinned.bin";
+/// Size of the chunks read by `FileStore::store_from_reader`.
+const STORE_READ_BUFFER_SIZE: usize = 64 * 1024;
+/// How often a flush postponed because the store was used by other processes is retried.
+#[cfg(not(test))]
+const FLUSH_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+/// How often a flush postponed because the store was used by other processes is retried.
+#[cfg(test)]
+const FLUSH_RETRY_INTERVAL: Duration = Duration::from_millis(50);
 
 /// Container with the ref counts of all the handles still alive.
 #[derive(Debug)]
 struct LockedFiles {
     /// Map from a `FileStoreKey` to the number of handles alive.
     ref_counts: HashMap<FileStoreKey, usize>,
+    /// The files pinned with `FileStore::pin`, they are never removed by the flush.
+    pinned: HashSet<FileStoreKey>,
 }
 
 /// A file store will manage all the files in the store directory.
 ///
 /// This will manage a file storage directory with the ability of:
 /// * remove files not needed anymore that takes too much space.
-/// * locking so no other instances of `FileStorage` can access the storage while
-///   this is still running, even in other processes.
+/// * sharing the storage with other instances of `FileStore`, even in other processes, updating
+///   the index without losing the changes of the others.
 /// * do not remove files useful for the current computations.
+/// * do not remove the pinned files.
+/// * notify the removal of the files to the subscribed receivers.
 #[derive(Debug)]
 pub struct FileStore {
     /// Base directory of the `FileStore`.
     base_path: PathBuf,
-    /// Lock to the `FileStore` directory.
-    _lock: LockFile,
+    /// Lock to the `FileStore` directory, shared while the store is in use and exclusive while
+    /// flushing.
+    lock: std::fs::File,
     /// The files locked because there are some handles still alive.
     locked_files: Arc<Mutex<LockedFiles>>,
     /// The index with the files known to the store. This is used when flushing the old files.
@@ -94,6 +125,11 @@ pub struct FileStore {
     max_store_size: u64,
     /// Target size of the file store after the flush.
     min_store_size: u64,
+    /// The channels where the keys of the files removed by the flush are sent.
+    eviction_subscribers: Arc<Mutex<Vec<Sender<FileStoreKey>>>>,
+    /// The thread retrying the postponed flushes, with the channel that stops it when dropped.
+    /// This is `None` for the copy of the store used by the thread itself.
+    flush_retry: Option<(Sender<()>, JoinHandle<()>)>,
 }
 
 /// Handle of a file in the `FileStore`, this must be computable given the content of the file, i.e.
@@ -104,6 +140,13 @@ pub struct FileStoreKey {
     hash: Hash,
 }
 
+/// The content of a file doesn't match the key it's stored with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentMismatchError {
+    /// The key the file was stored with.
+    pub key: FileStoreKey,
+}
+
 /// An handle to a specific file inside the store, until this handle is dropped the `FileStore` will
 /// not flush away the file for clearing space. You can clone the handle extending the life of the
 /// file.
@@ -118,9 +161,9 @@ pub struct FileStoreHandle {
 }
 
 impl FileStore {
-    /// Make a new `FileStore` in the specified base directory, will lock if another instance of a
-    /// `FileStore` is locking the directory. The locking is implemented via platform-specific file
-    /// locking. Having two instances of the file store running concurrently is not safe.
+    /// Make a new `FileStore` in the specified base directory. Many instances of `FileStore` can
+    /// use the same directory at the same time, even in different processes; this will wait only if
+    /// another instance is flushing the directory. The locking is implemented via `flock`.
     ///
     /// ```
     /// use task_maker_store::FileStore;
@@ -131,10 +174,10 @@ impl FileStore {
     /// # fn main() -> Result<(), Error> {
     /// # let dir = TempDir::new()?;
     /// # let store_dir = dir.path();
-    /// // make a new store based on a directory, this will lock if the store is already in use
-    /// // somewhere
+    /// // make a new store based on a directory, this will wait if the store is being flushed
     /// let store = FileStore::new(store_dir, 1000, 1000)?;
-    /// // let store2 = FileStore::new(store_dir) // this will lock!!
+    /// // the directory can be shared with other stores
+    /// let store2 = FileStore::new(store_dir, 1000, 1000)?;
     /// # Ok(())
     /// # }
     /// ```
@@ -152,35 +195,45 @@ impl FileStore {
             )
         })?;
         let lock_path = base_path.join(STORE_LOCK_FILE);
-        let mut lock = LockFile::open(&lock_path)
+        let lock = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
             .with_context(|| format!("Failed to create lock file at {}", lock_path.display()))?;
 
-        if !lock
-            .try_lock()
-            .context("Failed to obtain exclusive lock on storage")?
-        {
+        if flock(lock.as_raw_fd(), FlockArg::LockSharedNonblock).is_err() {
             warn!("Store locked... waiting");
-            lock.lock()
-                .context("Failed to obtain exclusive lock on storage")?;
+            flock(lock.as_raw_fd(), FlockArg::LockShared)
+                .context("Failed to obtain shared lock on storage")?;
         }
 
         let index = FileStoreIndex::load(base_path.join(STORE_INDEX_FILE))
             .context("Failed to load storage index")?;
-        Ok(FileStore {
+        let mut locked_files = LockedFiles::new();
+        locked_files.pinned = FileStore::load_pinned(&base_path.join(STORE_PINNED_FILE))
+            .context("Failed to load the pinned files")?;
+        let mut store = FileStore {
             base_path,
-            _lock: lock,
-            locked_files: Arc::new(Mutex::new(LockedFiles::new())),
+            lock,
+            locked_files: Arc::new(Mutex::new(locked_files)),
             index: Arc::new(Mutex::new(index)),
             max_store_size,
             min_store_size,
-        })
+            eviction_subscribers: Arc::new(Mutex::new(Vec::new())),
+            flush_retry: None,
+        };
+        store.flush_retry = Some(store.spawn_flush_retry()?);
+        Ok(store)
     }
 
     /// Given an iterator of `Vec<u8>` consume all of it writing the content to the disk if the file
     /// is not already present on disk. The file is stored inside the base directory and `chmod -w`.
     ///
     /// If the file is already present it is not overwritten but the iterator is consumed
-    /// nevertheless.
+    /// nevertheless. Otherwise the content is checked against the key, failing with a
+    /// [`ContentMismatchError`] if it doesn't match (e.g. because it has been corrupted while it
+    /// was transferred).
     ///
     /// Will return an handle to that file, keeping the file alive.
     ///
@@ -229,13 +282,23 @@ impl FileStore {
             let tmpfile_path = tmpdir.path().join("file");
             let mut tmpfile =
                 std::fs::File::create(&tmpfile_path).context("Failed to create temporary file")?;
+            let mut hasher = Hasher::new();
             if !content
                 .into_iter()
-                .map(|data| tmpfile.write_all(&data))
+                .map(|data| {
+                    hasher.update(&data);
+                    tmpfile.write_all(&data)
+                })
                 .all(|r| r.is_ok())
             {
                 bail!("Failed to store file");
             }
+            if hasher.finalize() != key.hash {
+                return Err(ContentMismatchError { key: key.clone() }.into());
+            }
+            // the file is complete and readonly before it appears in the store, so that the other
+            // processes (and a crash) never see it partially written
+            FileStore::mark_readonly(&tmpfile_path).context("Failed to mark file as readonly")?;
             std::fs::rename(&tmpfile_path, &path).with_context(|| {
                 format!(
                     "Failed to rename {} -> {}",
@@ -243,22 +306,44 @@ impl FileStore {
                     path.display()
                 )
             })?;
-            FileStore::mark_readonly(&path).context("Failed to mark file as readonly")?;
-            {
-                let mut index = self.index.lock().unwrap();
-                index
-                    .add(key.clone(), path)
-                    .context("Failed to add file to index")?;
-                self.maybe_flush(&mut index)?;
-                // FIXME: maybe this can be done less frequently
+            // FIXME: maybe this can be done less frequently
+            self.update_index(|index| {
                 index
-                    .store(self.base_path.join(STORE_INDEX_FILE))
-                    .context("Failed to store the index to file")?;
-            }
+                    .add(key.clone(), &path)
+                    .context("Failed to add file to index")
+            })?;
         }
         Ok(handle)
     }
 
+    /// Like [`FileStore::store`], but the content is read from `reader` until its end. Unlike a
+    /// failing iterator, an error while reading is reported as such, and not as a
+    /// [`ContentMismatchError`].
+    ///
+    /// The reader is consumed even if the file is already present, so it can be a part of a
+    /// larger stream, e.g. a `Read::take` of an archive with more files.
+    pub fn store_from_reader<R: Read>(
+        &self,
+        key: &FileStoreKey,
+        mut reader: R,
+    ) -> Result<FileStoreHandle, Error> {
+        let mut error = None;
+        let mut buffer = vec![0; STORE_READ_BUFFER_SIZE];
+        let chunks = std::iter::from_fn(|| match reader.read(&mut buffer) {
+            Ok(0) => None,
+            Ok(n) => Some(buffer[..n].to_vec()),
+            Err(e) => {
+                error = Some(e);
+                None
+            }
+        });
+        let handle = self.store(key, chunks);
+        if let Some(e) = error {
+            return Err(e).context("Failed to read the content of the file");
+        }
+        handle
+    }
+
     /// Returns an handle to the file with that key or `None` if it's not in the
     /// [`FileStore`](struct.FileStore.html).
     ///
@@ -309,6 +394,97 @@ impl FileStore {
         Some(FileStoreHandle::new(self, key))
     }
 
+    /// Pin a file, preventing the flush from removing it even when there are no handles to it. The
+    /// file doesn't need to be in the store already, and the pin persists until
+    /// [`FileStore::unpin`] is called, even between different instances of the store.
+    ///
+    /// ```
+    /// use task_maker_store::{FileStore, FileStoreKey};
+    ///
+    /// # use anyhow::Error;
+    /// # use tempfile::TempDir;
+    /// # fn main() -> Result<(), Error> {
+    /// # let tmp = TempDir::new().unwrap();
+    /// let store = FileStore::new(tmp.path(), 1000, 1000)?;
+    /// let key = FileStoreKey::from_content(b"hello world");
+    /// store.pin(&key)?;
+    /// assert!(store.is_pinned(&key));
+    /// store.unpin(&key)?;
+    /// assert!(!store.is_pinned(&key));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pin(&self, key: &FileStoreKey) -> Result<(), Error> {
+        let _lock = self.lock_index()?;
+        let mut locked_files = self.locked_files.lock().unwrap();
+        // the pins of the other processes may have changed
+        locked_files.pinned = FileStore::load_pinned(&self.base_path.join(STORE_PINNED_FILE))?;
+        if locked_files.pinned.insert(key.clone()) {
+            self.store_pinned(&locked_files.pinned)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the pin of a file, allowing the flush to remove it again. Unpinning a file that is
+    /// not pinned does nothing.
+    pub fn unpin(&self, key: &FileStoreKey) -> Result<(), Error> {
+        let _lock = self.lock_index()?;
+        let mut locked_files = self.locked_files.lock().unwrap();
+        locked_files.pinned = FileStore::load_pinned(&self.base_path.join(STORE_PINNED_FILE))?;
+        if locked_files.pinned.remove(key) {
+            self.store_pinned(&locked_files.pinned)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the file is pinned.
+    pub fn is_pinned(&self, key: &FileStoreKey) -> bool {
+        self.locked_files.lock().unwrap().pinned.contains(key)
+    }
+
+    /// Subscribe to the removal of the files: the key of each file removed from the store to free
+    /// space is sent to the returned channel. Dropping the receiver ends the subscription.
+    ///
+    /// This allows the users of the store, like the cache, to forget what refers to the removed
+    /// files as soon as they are removed.
+    pub fn subscribe_evictions(&self) -> Receiver<FileStoreKey> {
+        let (sender, receiver) = channel();
+        self.eviction_subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Notify the subscribers that a file has been removed from the store, forgetting the ones
+    /// that are not listening anymore.
+    fn notify_eviction(&self, key: &FileStoreKey) {
+        let mut subscribers = self.eviction_subscribers.lock().unwrap();
+        subscribers.retain(|sender| sender.send(key.clone()).is_ok());
+    }
+
+    /// Load the keys of the pinned files, the file may not exist if no file has ever been pinned.
+    fn load_pinned(path: &Path) -> Result<HashSet<FileStoreKey>, Error> {
+        if !path.exists() {
+            return Ok(HashSet::new());
+        }
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let keys: Vec<FileStoreKey> = bincode::deserialize_from(std::io::BufReader::new(file))
+            .with_context(|| format!("Failed to deserialize {}", path.display()))?;
+        Ok(keys.into_iter().collect())
+    }
+
+    /// Store the keys of the pinned files.
+    fn store_pinned(&self, pinned: &HashSet<FileStoreKey>) -> Result<(), Error> {
+        let path = self.base_path.join(STORE_PINNED_FILE);
+        let tmp = path.with_extension("tmp");
+        let keys: Vec<_> = pinned.iter().collect();
+        let content = bincode::serialize(&keys).context("Failed to serialize the pinned files")?;
+        std::fs::write(&tmp, content)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path)
+            .with_context(|| format!("Failed to move {} -> {}", tmp.display(), path.display()))?;
+        Ok(())
+    }
+
     /// Path of the file to disk.
     fn key_to_path(&self, key: &FileStoreKey) -> PathBuf {
         self.base_path.join(key.suffix())
@@ -359,41 +535,135 @@ impl FileStore {
         }
     }
 
+    /// Take the lock of the index, released when the returned value is dropped.
+    fn lock_index(&self) -> Result<LockFile, Error> {
+        let lock_path = self.base_path.join(STORE_INDEX_LOCK_FILE);
+        let mut lock = LockFile::open(&lock_path)
+            .with_context(|| format!("Failed to create lock file at {}", lock_path.display()))?;
+        lock.lock()
+            .context("Failed to obtain the lock of the index")?;
+        Ok(lock)
+    }
+
+    /// Update the index while holding its lock: the changes made by the other processes are loaded
+    /// before calling `update`, then the store is flushed if needed and the index is written back.
+    fn update_index<F>(&self, update: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut FileStoreIndex) -> Result<(), Error>,
+    {
+        let _lock = self.lock_index()?;
+        let mut index = self
+            .index
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock the index due to poison"))?;
+        let path = self.base_path.join(STORE_INDEX_FILE);
+        index
+            .sync(&path)
+            .context("Failed to load the changes to the index")?;
+        update(&mut index)?;
+        self.maybe_flush(&mut index)?;
+        index
+            .store(&path)
+            .context("Failed to store the index to file")
+    }
+
     /// Check if the file store needs flushing, and do so if needed.
+    ///
+    /// The flush is postponed if other processes are using the store, since the files they are
+    /// using may be removed. The thread spawned by `spawn_flush_retry` will try again later.
     fn maybe_flush(&self, index: &mut FileStoreIndex) -> Result<(), Error> {
-        if index.need_flush(self.max_store_size) {
-            let locked = self.locked_files.lock().unwrap();
-            index
-                .flush(self, &locked, self.min_store_size)
-                .context("Failed to flush index")?;
+        if !index.need_flush(self.max_store_size) {
+            return Ok(());
         }
-        Ok(())
+        if !self.try_lock_exclusive()? {
+            debug!("The store is used by other processes, not flushing it");
+            return Ok(());
+        }
+        let flushed = self.flush(index);
+        flock(self.lock.as_raw_fd(), FlockArg::LockShared)
+            .context("Failed to obtain shared lock on storage")?;
+        flushed
     }
-}
 
-impl Drop for FileStore {
-    fn drop(&mut self) {
-        match self.index.lock() {
-            Ok(mut index) => {
-                let locked = match self.locked_files.lock() {
-                    Ok(l) => l,
-                    Err(_) => {
-                        warn!("Cannot lock locked_files due to poison");
-                        return;
+    /// Flush the store, while holding the exclusive lock on it.
+    fn flush(&self, index: &mut FileStoreIndex) -> Result<(), Error> {
+        let mut locked = self
+            .locked_files
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock locked_files due to poison"))?;
+        locked.pinned = FileStore::load_pinned(&self.base_path.join(STORE_PINNED_FILE))?;
+        index
+            .flush(self, &locked, self.min_store_size)
+            .context("Failed to flush index")
+    }
+
+    /// Try to make the lock on the store exclusive, returning whether it succeeded because there
+    /// are no other processes using the store. If it fails the lock stays shared.
+    fn try_lock_exclusive(&self) -> Result<bool, Error> {
+        match flock(self.lock.as_raw_fd(), FlockArg::LockExclusiveNonblock) {
+            Ok(()) => Ok(true),
+            Err(Errno::EWOULDBLOCK) => {
+                // converting the lock may have released it
+                flock(self.lock.as_raw_fd(), FlockArg::LockShared)
+                    .context("Failed to obtain shared lock on storage")?;
+                Ok(false)
+            }
+            Err(e) => Err(e).context("Failed to obtain exclusive lock on storage"),
+        }
+    }
+
+    /// Spawn the thread that periodically retries the flushes postponed because the store was used
+    /// by other processes. Without it, a store that is not used anymore would never flush after
+    /// the other processes leave, since they cannot flush while this store holds its lock.
+    fn spawn_flush_retry(&self) -> Result<(Sender<()>, JoinHandle<()>), Error> {
+        // the cloned file shares the lock with this store
+        let lock = self
+            .lock
+            .try_clone()
+            .context("Failed to clone the lock of the store")?;
+        let store = FileStore {
+            base_path: self.base_path.clone(),
+            lock,
+            locked_files: self.locked_files.clone(),
+            index: self.index.clone(),
+            max_store_size: self.max_store_size,
+            min_store_size: self.min_store_size,
+            eviction_subscribers: self.eviction_subscribers.clone(),
+            flush_retry: None,
+        };
+        let (stop, stopped) = channel();
+        let handle = std::thread::Builder::new()
+            .name("File store flush retry".into())
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) =
+                    stopped.recv_timeout(FLUSH_RETRY_INTERVAL)
+                {
+                    let pending = store.index.lock().unwrap().need_flush(store.max_store_size);
+                    if !pending {
+                        continue;
                     }
-                };
-                if index.need_flush(self.max_store_size) {
-                    if let Err(e) = index.flush(self, &locked, self.min_store_size) {
-                        warn!("Cannot flush the index: {}", e.to_string());
+                    if let Err(e) = store.update_index(|_| Ok(())) {
+                        warn!("Failed to retry the flush of the store: {:?}", e);
                     }
                 }
-                if let Err(e) = index.store(self.base_path.join(STORE_INDEX_FILE)) {
-                    warn!("Cannot store the index: {}", e.to_string());
-                }
-            }
-            Err(_) => {
-                warn!("Cannot store the index due to poisoned lock");
-            }
+            })
+            .context("Failed to spawn the flush retry thread")?;
+        Ok((stop, handle))
+    }
+}
+
+impl Drop for FileStore {
+    fn drop(&mut self) {
+        // the copy used by the flush retry thread leaves the index to the store it comes from
+        let Some((stop, flush_retry)) = self.flush_retry.take() else {
+            return;
+        };
+        drop(stop);
+        if flush_retry.join().is_err() {
+            warn!("The flush retry thread panicked");
+        }
+        if let Err(e) = self.update_index(|_| Ok(())) {
+            warn!("Cannot store the index: {:?}", e);
         }
     }
 }
@@ -444,6 +714,18 @@ impl std::fmt::Display for FileStoreKey {
     }
 }
 
+impl std::fmt::Display for ContentMismatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The content of the file does not match its key {}",
+            self.key
+        )
+    }
+}
+
+impl std::error::Error for ContentMismatchError {}
+
 impl std::fmt::Debug for FileStoreKey {
     fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
         write!(f, "{}", self.hash.to_hex())
@@ -553,6 +835,7 @@ impl LockedFiles {
     fn new() -> LockedFiles {
         LockedFiles {
             ref_counts: HashMap::new(),
+            pinned: HashSet::new(),
         }
     }
 }
@@ -610,20 +893,68 @@ mod tests {
 
     #[test]
     fn test_new_filestore_concurrent() {
-        use std::time::*;
-
         let cwd = get_cwd();
-        let store_dir = cwd.path().to_owned();
+        let store1 = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let store2 = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let handle1 = add_file_to_store(&cwd.path().join("file1.txt"), "file1", &store1);
+        let handle2 = add_file_to_store(&cwd.path().join("file2.txt"), "file2", &store2);
+        // the files stored by an instance are visible to the other ones
+        assert!(store2.get(&handle1.key).is_some());
+        assert!(store1.get(&handle2.key).is_some());
+        drop(store1);
+        drop(store2);
+        // and none of them is lost from the index
         let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
-        let thr = std::thread::spawn(move || {
-            let start = Instant::now();
-            let _store = FileStore::new(store_dir, 1000, 1000).unwrap();
-            let end = Instant::now();
-            assert!(end - start >= Duration::from_millis(300));
-        });
-        std::thread::sleep(Duration::from_millis(500));
-        drop(store);
-        thr.join().unwrap();
+        assert_eq!(store.index.lock().unwrap().total_size, 10);
+    }
+
+    #[test]
+    fn test_flush_shared_store() {
+        let cwd = get_cwd();
+        let store1 = FileStore::new(cwd.path(), 10, 0).unwrap();
+        let store2 = FileStore::new(cwd.path(), 10, 0).unwrap();
+        let key1 = add_file_to_store(&cwd.path().join("file1.txt"), "file1", &store1)
+            .key
+            .clone();
+        let key2 = add_file_to_store(&cwd.path().join("file2.txt"), "file2", &store1)
+            .key
+            .clone();
+        // store2 may be using the files, they are not removed
+        assert!(store1.key_to_path(&key1).exists());
+        assert!(store1.key_to_path(&key2).exists());
+        drop(store2);
+        drop(store1);
+        // the last instance flushes the store
+        assert!(!cwd.path().join(key1.suffix()).exists());
+        assert!(!cwd.path().join(key2.suffix()).exists());
+    }
+
+    #[test]
+    fn test_flush_contended_store() {
+        let cwd = get_cwd();
+        let store1 = FileStore::new(cwd.path(), 10, 0).unwrap();
+        let store2 = FileStore::new(cwd.path(), 10, 0).unwrap();
+        let key1 = add_file_to_store(&cwd.path().join("file1.txt"), "file1", &store1)
+            .key
+            .clone();
+        let key2 = add_file_to_store(&cwd.path().join("file2.txt"), "file2", &store1)
+            .key
+            .clone();
+        // the retries fail while store2 may be using the files
+        std::thread::sleep(FLUSH_RETRY_INTERVAL * 4);
+        assert!(store1.key_to_path(&key1).exists());
+        assert!(store1.key_to_path(&key2).exists());
+        // store2 cannot flush when it leaves, since store1 is still using the store
+        drop(store2);
+        // but store1 flushes by itself, without being used anymore
+        let deadline = std::time::Instant::now() + FLUSH_RETRY_INTERVAL * 100;
+        while store1.key_to_path(&key1).exists() || store1.key_to_path(&key2).exists() {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "The store is not flushed"
+            );
+            std::thread::sleep(FLUSH_RETRY_INTERVAL / 2);
+        }
     }
 
     #[test]
@@ -647,6 +978,39 @@ mod tests {
             .readonly());
     }
 
+    #[test]
+    fn test_store_content_mismatch() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let key = FileStoreKey::from_content(b"test");
+        let err = store
+            .store(&key, std::iter::once(b"tset".to_vec()))
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ContentMismatchError>(),
+            Some(&ContentMismatchError { key: key.clone() })
+        );
+        assert!(store.get(&key).is_none());
+    }
+
+    #[test]
+    fn test_store_from_reader() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let key = FileStoreKey::from_content(b"test");
+        let mut stream = &b"testrest"[..];
+        let handle = store
+            .store_from_reader(&key, (&mut stream).take(4))
+            .unwrap();
+        assert_eq!(read_to_string(handle.path()).unwrap(), "test");
+        // the file is already there, but the reader is consumed anyway
+        let mut stream = &b"testrest"[..];
+        store
+            .store_from_reader(&key, (&mut stream).take(4))
+            .unwrap();
+        assert_eq!(stream, b"rest");
+    }
+
     #[test]
     fn test_get() {
         let cwd = get_cwd();
@@ -0,0 +1,35 @@
+mod common;
+use common::TestInterface;
+
+use task_maker_format::ioi::TestcaseEvaluationStatus::*;
+
+fn build_dir(test: TestInterface) {
+    test.success()
+        .time_limit(1.0)
+        .memory_limit(64)
+        .max_score(100.0)
+        .subtask_scores(vec![100.0])
+        .must_compile("soluzione.cpp")
+        .must_compile("multi")
+        .must_compile("make")
+        .must_not_compile("broken")
+        .solution_score("soluzione.cpp", vec![100.0])
+        .solution_score("multi", vec![100.0])
+        .solution_score("make", vec![100.0])
+        .solution_statuses("multi", vec![Accepted("Output is correct".into())])
+        .solution_statuses("make", vec![Accepted("Output is correct".into())]);
+}
+
+#[test]
+fn build_dir_local() {
+    better_panic::install();
+
+    build_dir(TestInterface::run_local("build_dir"));
+}
+
+#[test]
+fn build_dir_remote() {
+    better_panic::install();
+
+    build_dir(TestInterface::run_remote("build_dir"));
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "chaos")]
+
+mod common;
+use common::TestInterface;
+
+use std::time::Duration;
+
+use task_maker_exec::ChaosConfig;
+use task_maker_format::ioi::TestcaseEvaluationStatus::*;
+
+#[test]
+fn chaos_recoverable_failures() {
+    better_panic::install();
+
+    // delays, disconnections and corrupted uploads must not change the outcome of the evaluation
+    let chaos = ChaosConfig {
+        seed: 42,
+        delay_probability: 0.3,
+        max_delay: Duration::from_millis(50),
+        disconnect_probability: 0.2,
+        corrupt_probability: 0.1,
+        ..Default::default()
+    };
+    TestInterface::run_local_with_chaos("with_stdio", chaos)
+        .success()
+        .max_score(100.0)
+        .subtask_scores(vec![100.0])
+        .must_compile("soluzione.cpp")
+        .must_compile("wa.cpp")
+        .must_compile("wrong_file.cpp")
+        .not_compiled("noop.py")
+        .solution_score("soluzione.cpp", vec![100.0])
+        .solution_score("noop.py", vec![0.0])
+        .solution_score("wa.cpp", vec![50.0])
+        .solution_score("wrong_file.cpp", vec![0.0])
+        .solution_statuses("soluzione.cpp", vec![Accepted("Output is correct".into())])
+        .solution_statuses(
+            "wa.cpp",
+            vec![
+                Accepted("Output is correct".into()),
+                Accepted("Output is correct".into()),
+                WrongAnswer("Output is incorrect".into()),
+                WrongAnswer("Output is incorrect".into()),
+            ],
+        );
+}
+
+#[test]
+fn chaos_killed_sandboxes() {
+    better_panic::install();
+
+    // the killed executions fail, but the evaluation must complete anyway
+    let chaos = ChaosConfig {
+        seed: 42,
+        kill_probability: 0.2,
+        ..Default::default()
+    };
+    TestInterface::run_local_with_chaos("with_stdio", chaos).success();
+}
@@ -1,6 +1,5 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use anyhow::Error;
@@ -9,15 +8,16 @@ use itertools::Itertools;
 use tempfile::TempDir;
 
 use task_maker_dag::ExecutionStatus;
+#[cfg(feature = "chaos")]
+use task_maker_exec::ChaosConfig;
 use task_maker_format::ioi::{
     IOITask, SubtaskId, TestcaseEvaluationStatus, TestcaseGenerationStatus, UIState,
 };
 use task_maker_format::ui::CompilationStatus;
-use task_maker_format::ui::UIStateT;
-use task_maker_format::EvaluationConfig;
+use task_maker_format::{EvaluationConfig, TaskFormat};
 use task_maker_rust::tools::server::{main_server, ServerOpt};
 use task_maker_rust::tools::worker::{main_worker, WorkerOpt};
-use task_maker_rust::{run_evaluation, Evaluation, Opt};
+use task_maker_rust::{EvaluationBuilder, EvaluationState};
 
 use approx::abs_diff_eq;
 
@@ -36,47 +36,41 @@ pub struct TestInterfaceSuccessful {
 
 impl TestInterface {
     pub fn run_local<P: Into<PathBuf>>(path: P) -> Self {
-        let _ = env_logger::Builder::from_default_env()
-            .format_timestamp_nanos()
-            .is_test(true)
-            .try_init();
-        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .join("tests")
-            .join("tasks")
-            .join(path.into());
-        let tempdir = TempDir::new().expect("Cannot crete tempdir");
-        TestInterface {
-            state: TestInterface::run_task_maker(path, false, tempdir.path(), &[]),
-            _tempdir: tempdir,
-        }
+        TestInterface::run_local_with_config(path, EvaluationConfig::default())
+    }
+
+    /// Evaluate the task locally with the specified configuration.
+    pub fn run_local_with_config<P: Into<PathBuf>>(path: P, config: EvaluationConfig) -> Self {
+        TestInterface::run_local_with_builder(path, config, |builder| builder)
+    }
+
+    /// Evaluate the task locally, injecting the failures of `chaos` in the executor.
+    #[cfg(feature = "chaos")]
+    pub fn run_local_with_chaos<P: Into<PathBuf>>(path: P, chaos: ChaosConfig) -> Self {
+        TestInterface::run_local_with_builder(path, EvaluationConfig::default(), |builder| {
+            builder.num_cores(4).chaos(chaos)
+        })
     }
 
     /// Evaluate the task using a "remote" setup (spawning a local server and local workers).
     pub fn run_remote<P: Into<PathBuf>>(path: P) -> Self {
-        let _ = env_logger::Builder::from_default_env()
-            .format_timestamp_nanos()
-            .is_test(true)
-            .try_init();
+        TestInterface::init_logger();
         let tempdir = TempDir::new().expect("Cannot crete tempdir");
         let client_path = tempdir.path().join("client.sock");
         let worker_path = tempdir.path().join("worker.sock");
         TestInterface::spawn_server(&client_path, &worker_path);
         TestInterface::wait_file(&client_path);
 
-        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .join("tests")
-            .join("tasks")
-            .join(path.into());
+        let state = TestInterface::evaluation_builder(
+            TestInterface::task_path(path),
+            EvaluationConfig::default(),
+            false,
+            tempdir.path(),
+        )
+        .map(|builder| builder.evaluate_on(format!("unix://{}", client_path.display())))
+        .and_then(TestInterface::evaluate);
         TestInterface {
-            state: TestInterface::run_task_maker(
-                path,
-                false,
-                tempdir.path(),
-                &[
-                    "--evaluate-on",
-                    &format!("unix://{}", client_path.display()),
-                ],
-            ),
+            state,
             _tempdir: tempdir,
         }
     }
@@ -108,60 +102,68 @@ impl TestInterface {
         }
     }
 
-    /// Run task-maker blocking this thread by calling the entry point of the local execution, i.e.
-    /// not sending `--server` nor `--worker`. This approach is used to keep a single process
-    /// running and keep tracing the coverage.
-    fn run_task_maker(
+    /// Evaluate the task locally, blocking this thread, with the builder of the evaluation
+    /// customized by `customize`. The task is evaluated from code, i.e. not spawning `--server` nor
+    /// `--worker`, for keeping a single process running and tracing the coverage.
+    fn run_local_with_builder<P, F>(path: P, config: EvaluationConfig, customize: F) -> Self
+    where
+        P: Into<PathBuf>,
+        F: FnOnce(EvaluationBuilder) -> EvaluationBuilder,
+    {
+        TestInterface::init_logger();
+        let tempdir = TempDir::new().expect("Cannot crete tempdir");
+        let state = TestInterface::evaluation_builder(
+            TestInterface::task_path(path),
+            config,
+            false,
+            tempdir.path(),
+        )
+        .map(customize)
+        .and_then(TestInterface::evaluate);
+        TestInterface {
+            state,
+            _tempdir: tempdir,
+        }
+    }
+
+    /// Initialize the logger of the tests, if not already initialized.
+    fn init_logger() {
+        let _ = env_logger::Builder::from_default_env()
+            .format_timestamp_nanos()
+            .is_test(true)
+            .try_init();
+    }
+
+    /// The directory of the test task with that name.
+    fn task_path<P: Into<PathBuf>>(path: P) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("tasks")
+            .join(path.into())
+    }
+
+    /// Prepare the evaluation of the task, locally by default.
+    fn evaluation_builder(
         task_dir: PathBuf,
+        config: EvaluationConfig,
         cache: bool,
         store_dir: &Path,
-        extra_args: &[&str],
-    ) -> Result<UIState, Error> {
-        let mut args: Vec<&str> = vec!["task-maker"];
-        let path = task_dir.to_string_lossy().into_owned();
-        let path = format!("--task-dir={}", path);
-        args.push(&path);
-        args.push("--ui=silent");
-        if !cache {
-            args.push("--no-cache");
-        }
-        args.push("-vv");
-        let store_dir = format!("--store-dir={}", store_dir.to_string_lossy());
-        args.push(&store_dir);
-        for arg in extra_args {
-            args.push(arg);
-        }
+    ) -> Result<EvaluationBuilder, Error> {
         std::env::set_var(
             "TASK_MAKER_TOOLS_PATH",
             env!("CARGO_BIN_EXE_task-maker-tools"),
         );
-        let opt = Opt::parse_from(&args);
-
-        let task = IOITask::new(
-            &task_dir,
-            &EvaluationConfig {
-                solution_filter: vec![],
-                booklet_solutions: false,
-                no_statement: false,
-                solution_paths: vec![],
-                disabled_sanity_checks: vec![],
-                seed: None,
-                dry_run: false,
-            },
-        )
-        .unwrap();
-        let state = Arc::new(Mutex::new(UIState::new(&task, Default::default())));
-
-        let state2 = state.clone();
-        let res = run_evaluation(opt, move |_, mex| state2.lock().unwrap().apply(mex));
-        match res? {
-            Evaluation::Done => {
-                let state = state.lock().unwrap();
-                Ok(state.clone())
-            }
-            Evaluation::Clean => {
-                panic!("Unexpected task cleaning");
-            }
+        let task = IOITask::new(&task_dir, &config)?;
+        Ok(EvaluationBuilder::new(TaskFormat::IOI(task), config)
+            .store_dir(store_dir)
+            .cache(cache))
+    }
+
+    /// Run the evaluation blocking this thread, returning its final state.
+    fn evaluate(builder: EvaluationBuilder) -> Result<UIState, Error> {
+        match builder.run()? {
+            EvaluationState::IOI(state) => Ok(state),
+            EvaluationState::Terry(_) => panic!("Unexpected Terry task"),
         }
     }
 
@@ -1,5 +1,6 @@
 mod common;
 use common::TestInterface;
+use task_maker_format::EvaluationConfig;
 
 fn classic(test: TestInterface) {
     test.success()
@@ -31,6 +32,16 @@ fn deps_local() {
     classic(TestInterface::run_local("deps"));
 }
 
+#[test]
+fn deps_local_skipping_dependent_subtasks() {
+    better_panic::install();
+    let config = EvaluationConfig {
+        skip_dependent_subtasks: true,
+        ..Default::default()
+    };
+    classic(TestInterface::run_local_with_config("deps", config));
+}
+
 #[test]
 fn deps_remote() {
     better_panic::install();
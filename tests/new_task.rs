@@ -0,0 +1,92 @@
+use task_maker_format::ioi::TestcaseEvaluationStatus::*;
+use task_maker_format::terry::TerryTask;
+use task_maker_format::{EvaluationConfig, TaskFormat};
+use task_maker_rust::tools::new_task::{main_new_task, NewTaskFormat, NewTaskOpt, NewTaskType};
+use task_maker_rust::EvaluationBuilder;
+
+mod common;
+use common::TestInterface;
+
+fn new_task(task_type: NewTaskType) {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    main_new_task(NewTaskOpt {
+        name: "sum".into(),
+        format: NewTaskFormat::Ioi,
+        task_type,
+        title: Some("Sum of integers".into()),
+        parent_dir: tmpdir.path().into(),
+    })
+    .unwrap();
+
+    let correct = vec![Accepted("Output is correct".into()); 5];
+    TestInterface::run_local(tmpdir.path().join("sum"))
+        .success()
+        .max_score(100.0)
+        .subtask_scores(vec![100.0])
+        .must_compile("solution.cpp")
+        .not_compiled("solution_python.py")
+        .solution_score("solution.cpp", vec![100.0])
+        .solution_score("solution_python.py", vec![100.0])
+        .solution_statuses("solution.cpp", correct.clone())
+        .solution_statuses("solution_python.py", correct);
+}
+
+#[test]
+fn new_task_batch() {
+    better_panic::install();
+    new_task(NewTaskType::Batch);
+}
+
+#[test]
+fn new_task_communication() {
+    better_panic::install();
+    new_task(NewTaskType::Communication);
+}
+
+#[test]
+fn new_task_output_only() {
+    better_panic::install();
+    new_task(NewTaskType::OutputOnly);
+}
+
+#[test]
+fn new_task_terry() {
+    better_panic::install();
+    std::env::set_var(
+        "TASK_MAKER_TOOLS_PATH",
+        env!("CARGO_BIN_EXE_task-maker-tools"),
+    );
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    main_new_task(NewTaskOpt {
+        name: "sum".into(),
+        format: NewTaskFormat::Terry,
+        task_type: NewTaskType::Batch,
+        title: None,
+        parent_dir: tmpdir.path().into(),
+    })
+    .unwrap();
+
+    let task_dir = tmpdir.path().join("sum");
+    let config = EvaluationConfig::default();
+    let task = TerryTask::new(&task_dir, &config).unwrap();
+    let state = EvaluationBuilder::new(TaskFormat::Terry(task), config)
+        .store_dir(tmpdir.path().join("store"))
+        .run()
+        .unwrap();
+    let scores = state.solution_scores();
+    assert_eq!(scores[&task_dir.join("solutions/solution.py")], Some(100.0));
+}
+
+#[test]
+fn new_task_existing_dir() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let opt = NewTaskOpt {
+        name: "sum".into(),
+        format: NewTaskFormat::Ioi,
+        task_type: NewTaskType::Batch,
+        title: None,
+        parent_dir: tmpdir.path().into(),
+    };
+    main_new_task(opt.clone()).unwrap();
+    assert!(main_new_task(opt).is_err());
+}
@@ -0,0 +1,36 @@
+mod common;
+use common::TestInterface;
+use task_maker_format::EvaluationConfig;
+
+#[test]
+fn sanitize_local() {
+    better_panic::install();
+
+    let config = EvaluationConfig {
+        sanitize: true,
+        ..Default::default()
+    };
+    TestInterface::run_local_with_config("sanitize", config)
+        .success()
+        .max_score(100.0)
+        .must_compile("soluzione.cpp")
+        .must_compile("overflow.cpp")
+        .not_compiled("echo.py")
+        // the sanitized executions don't affect the scores
+        .solution_score("soluzione.cpp", vec![100.0])
+        .solution_score("overflow.cpp", vec![100.0])
+        .solution_score("echo.py", vec![100.0])
+        .has_diagnostic("The sanitizers found an error in sol/overflow.cpp on testcase 0")
+        .not_has_diagnostic("The sanitizers found an error in sol/soluzione.cpp")
+        .not_has_diagnostic("Failed to compile");
+}
+
+#[test]
+fn sanitize_disabled() {
+    better_panic::install();
+
+    TestInterface::run_local("sanitize")
+        .success()
+        .solution_score("overflow.cpp", vec![100.0])
+        .not_has_diagnostic("The sanitizers found an error");
+}
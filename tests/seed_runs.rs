@@ -0,0 +1,43 @@
+mod common;
+use common::TestInterface;
+use task_maker_format::ioi::SeedAggregation;
+use task_maker_format::EvaluationConfig;
+
+/// The configuration running the solutions with 3 seeds on each testcase.
+fn three_seeds(aggregation: SeedAggregation) -> EvaluationConfig {
+    EvaluationConfig {
+        seed_runs: Some(3),
+        seed_aggregation: aggregation,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn seed_runs_worst() {
+    better_panic::install();
+
+    TestInterface::run_local_with_config("seed_runs", three_seeds(SeedAggregation::Worst))
+        .success()
+        .max_score(100.0)
+        .solution_score("soluzione.py", vec![100.0])
+        // the second seed fails every testcase
+        .solution_score("random.py", vec![0.0]);
+}
+
+#[test]
+fn seed_runs_best() {
+    better_panic::install();
+
+    TestInterface::run_local_with_config("seed_runs", three_seeds(SeedAggregation::Best))
+        .success()
+        .solution_score("random.py", vec![100.0]);
+}
+
+#[test]
+fn seed_runs_disabled() {
+    better_panic::install();
+
+    TestInterface::run_local("seed_runs")
+        .success()
+        .solution_score("random.py", vec![100.0]);
+}
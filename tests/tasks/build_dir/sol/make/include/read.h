@@ -0,0 +1,7 @@
+#include <stdio.h>
+
+static int read_int(void) {
+  int N;
+  if (scanf("%d", &N) != 1) return -1;
+  return N;
+}
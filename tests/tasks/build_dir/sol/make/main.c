@@ -0,0 +1,6 @@
+#include "read.h"
+
+int main(void) {
+  printf("%d\n", read_int());
+  return 0;
+}
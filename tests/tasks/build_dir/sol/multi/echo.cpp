@@ -0,0 +1,3 @@
+#include "echo.h"
+
+int echo(int N) { return N; }
@@ -0,0 +1,9 @@
+#include <iostream>
+
+#include "echo.h"
+
+int main() {
+  int N;
+  std::cin >> N;
+  std::cout << echo(N) << std::endl;
+}
@@ -0,0 +1,12 @@
+#include <iostream>
+#include <vector>
+
+int main() {
+  int N;
+  std::cin >> N;
+  std::vector<int> v(N, N);
+  // reads past the end of the vector, the output is still correct
+  volatile int past_the_end = v[N];
+  (void)past_the_end;
+  std::cout << v[N - 1] << std::endl;
+}
@@ -0,0 +1,6 @@
+#!/usr/bin/env python3
+import os
+
+# wrong only with the second seed
+N = int(input())
+print(N + 1 if os.environ.get("TASK_MAKER_SEED") == "2" else N)
@@ -0,0 +1,5 @@
+#include <cstdlib>
+
+int encode(int N) { abort(); }
+
+int decode(int X) { return (X - 1) / 2; }
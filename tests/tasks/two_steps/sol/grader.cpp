@@ -0,0 +1,16 @@
+#include <cstring>
+#include <iostream>
+
+int encode(int N);
+int decode(int X);
+
+int main(int argc, char** argv) {
+  if (argc != 2) return 1;
+  int value;
+  std::cin >> value;
+  if (strcmp(argv[1], "0") == 0) {
+    std::cout << encode(value) << std::endl;
+  } else {
+    std::cout << decode(value) << std::endl;
+  }
+}
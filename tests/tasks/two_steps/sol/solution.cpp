@@ -0,0 +1,3 @@
+int encode(int N) { return 2 * N + 1; }
+
+int decode(int X) { return (X - 1) / 2; }
@@ -0,0 +1,3 @@
+int encode(int N) { return N; }
+
+int decode(int X) { return (X - 1) / 2; }
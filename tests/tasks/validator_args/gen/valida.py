@@ -0,0 +1,9 @@
+#!/usr/bin/env python3
+
+import sys
+
+flag, testcase, path, subtask, name = sys.argv[1:]
+assert flag == "--testcase"
+value = int(open(path).read())
+expected = {"0": (0, 1, "small"), "1": (0, 2, "small"), "2": (1, 1000, "large")}
+assert expected[testcase] == (int(subtask), value, name), sys.argv
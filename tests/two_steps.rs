@@ -0,0 +1,35 @@
+mod common;
+use common::TestInterface;
+
+use task_maker_format::ioi::TestcaseEvaluationStatus::*;
+
+fn two_steps(test: TestInterface) {
+    test.success()
+        .time_limit(1.0)
+        .memory_limit(64)
+        .max_score(100.0)
+        .subtask_scores(vec![100.0])
+        .must_compile("solution.cpp")
+        .must_compile("wrong.cpp")
+        .must_compile("crash_first.cpp")
+        .solution_score("solution.cpp", vec![100.0])
+        .solution_score("wrong.cpp", vec![0.0])
+        .solution_score("crash_first.cpp", vec![0.0])
+        .solution_statuses("solution.cpp", vec![Accepted("Output is correct".into())])
+        .solution_statuses("wrong.cpp", vec![WrongAnswer("Output is incorrect".into())])
+        .solution_statuses("crash_first.cpp", vec![RuntimeError]);
+}
+
+#[test]
+fn two_steps_local() {
+    better_panic::install();
+
+    two_steps(TestInterface::run_local("two_steps"));
+}
+
+#[test]
+fn two_steps_remote() {
+    better_panic::install();
+
+    two_steps(TestInterface::run_remote("two_steps"));
+}
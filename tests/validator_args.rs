@@ -0,0 +1,26 @@
+mod common;
+use common::TestInterface;
+
+fn validator_args(test: TestInterface) {
+    test.success()
+        .time_limit(1.0)
+        .memory_limit(64)
+        .max_score(100.0)
+        .subtask_scores(vec![40.0, 60.0])
+        .validation_fails(vec![None, None, None])
+        .solution_score("soluzione.cpp", vec![40.0, 60.0]);
+}
+
+#[test]
+fn validator_args_local() {
+    better_panic::install();
+
+    validator_args(TestInterface::run_local("validator_args"));
+}
+
+#[test]
+fn validator_args_remote() {
+    better_panic::install();
+
+    validator_args(TestInterface::run_remote("validator_args"));
+}
@@ -2,6 +2,7 @@ mod common;
 use common::TestInterface;
 
 use task_maker_format::ioi::TestcaseEvaluationStatus::*;
+use task_maker_format::EvaluationConfig;
 
 fn without_gen(test: TestInterface) {
     test.success()
@@ -44,3 +45,14 @@ fn without_gen_remote() {
 
     without_gen(TestInterface::run_remote("without_gen"));
 }
+
+#[test]
+fn without_gen_trusting_outputs() {
+    better_panic::install();
+
+    let config = EvaluationConfig {
+        trust_outputs: true,
+        ..Default::default()
+    };
+    without_gen(TestInterface::run_local_with_config("without_gen", config));
+}